@@ -0,0 +1,22 @@
+use std::env;
+use std::process::exit;
+extern crate blastoise;
+
+
+// blastoise-check dir, or blastoise-check table_meta_dir table_file_dir
+fn main() {
+    let args : Vec<String> = env::args().skip(1).collect();
+    let (table_meta_dir, table_file_dir) = match args.len() {
+        1 => (args[0].clone(), args[0].clone()),
+        2 => (args[0].clone(), args[1].clone()),
+        _ => {
+            println!("usage: blastoise-check (dir | table_meta_dir table_file_dir)");
+            exit(2);
+        }
+    };
+    let report = blastoise::check_data_dir(&table_meta_dir, &table_file_dir);
+    println!("{}", report);
+    if !report.is_ok() {
+        exit(1);
+    }
+}
@@ -1,70 +1,178 @@
-import socket
-import cStringIO
-import sys
-import cmd
-
-from query import handle_query, print_tuple_desc, SqlError
-
-
-class Console(cmd.Cmd):
-    prompt = 'Blastoise > '
-
-    def __init__(self, host, port):
-        cmd.Cmd.__init__(self)  # Cmd is not new style
-        self.socket = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
-        self.socket.connect((host, port))
-        self.tmp = ''
-
-    def default(self, line):
-        self.tmp += line
-        if line[-1] != ';':
-            return
-        req = self.tmp[:-1] + '\n'
-        print 'processing %s' % req
-        self.tmp = ''
-        self.socket.sendall(req)
-        data = self.get_remote_data()
-        try:
-            (tuple_desc, tuple_list) = handle_query(data)
-        except SqlError as e:
-            print e.message
-            return
-        print_tuple_desc(tuple_desc)
-        for t in tuple_list:
-            print t
-
-    def do_show(self, line):
-        if line.strip() != 'tables':
-            print 'only support show tables'
-        else:
-            self.socket.sendall('show tables\n')
-            data = self.get_remote_data()
-            print data
-
-    def do_EOF(self, line):
-        self.socket.close()
-        return True
-
-    def get_remote_data(self):
-        buff = cStringIO.StringIO()
-        while True:
-            data = self.socket.recv(1000)
-            buff.write(data)
-            if '\r\n' in data:
-                break
-        data = buff.getvalue()
-        buff.close()
-        return data
-
-def help():
-    print 'usage: python blastc host port'
-    sys.exit()
-
-
-if __name__ == '__main__':
-    if len(sys.argv) != 3:
-        help()
-    host = sys.argv[1]
-    port = int(sys.argv[2])
-    Console(host, port).cmdloop()
-
+import socket
+import ssl
+import hashlib
+import sys
+import cmd
+import csv
+import json
+import re
+import struct
+
+from query import handle_query, print_columns, repr_summary, SqlError, ResponseReader
+
+# the local file of \copy is sent in frames of at most this, below max_copy_frame_bytes of the server
+COPY_FRAME_BYTES = 65536
+
+
+class Console(cmd.Cmd):
+    prompt = 'Blastoise > '
+
+    def __init__(self, address, user=None, password=None, output_format='table', ca=None, fingerprint=None):
+        # address is (host, port), or the path of the unix socket
+        # tls is used with either the ca file or the sha256 fingerprint of the server certificate
+        cmd.Cmd.__init__(self)  # Cmd is not new style
+        self.output_format = output_format
+        if isinstance(address, tuple):
+            self.socket = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        else:
+            self.socket = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self.socket.connect(address)
+        if ca is not None:
+            self.socket = ssl.wrap_socket(self.socket, cert_reqs=ssl.CERT_REQUIRED, ca_certs=ca)
+        elif fingerprint is not None:
+            self.socket = ssl.wrap_socket(self.socket, cert_reqs=ssl.CERT_NONE)
+            cert = self.socket.getpeercert(binary_form=True)
+            if hashlib.sha256(cert).hexdigest() != fingerprint.replace(':', '').lower():
+                self.socket.close()
+                raise SqlError('unexpected certificate fingerprint')
+        self.reader = ResponseReader(self.socket)
+        self.tmp = ''
+        self.names = None  # the namespace of the server for the completion
+        if user is not None:
+            self.socket.sendall('auth %s %s\n' % (user, password or ''))
+            handle_query(self.reader)  # raise SqlError on failure
+
+    def default(self, line):
+        if not self.tmp and line.startswith('\\copy'):
+            return self.copy_in(line)
+        self.tmp += line
+        if line[-1] != ';':
+            return
+        req = self.tmp[:-1] + '\n'
+        if self.output_format == 'table':
+            print 'processing %s' % req
+        self.tmp = ''
+        self.socket.sendall(req)
+        try:
+            (columns, tuple_list, row_count) = handle_query(self.reader)
+        except SqlError as e:
+            print e.message
+            return
+        if not columns:
+            if self.output_format == 'table':
+                print repr_summary(columns, row_count)
+            return
+        if self.output_format == 'table':
+            print_columns(columns)
+            for t in tuple_list:
+                print t
+            print repr_summary(columns, row_count)
+        elif self.output_format == 'json':
+            names = [c['name'] for c in columns]
+            print json.dumps([dict(zip(names, t)) for t in tuple_list])
+        else:
+            # quoted the same way as copy to
+            delimiter = ',' if self.output_format == 'csv' else '\t'
+            writer = csv.writer(sys.stdout, delimiter=delimiter, lineterminator='\n')
+            writer.writerow([c['name'] for c in columns])
+            writer.writerows(tuple_list)
+        for w in self.reader.warnings:
+            sys.stderr.write('warning: %s\n' % w)
+
+    def copy_in(self, line):
+        # `\copy table from 'file' [with ...]` streams the local file as `copy table from stdin`
+        match = re.match(r"\\copy\s+(\w+)\s+from\s+'([^']*)'(.*)$", line.strip().rstrip(';'))
+        if match is None:
+            print "usage: \\copy table from 'file' [with options]"
+            return
+        table, path, options = match.groups()
+        try:
+            data = open(path, 'rb')
+        except IOError as e:
+            print "can't open %s: %s" % (path, e.strerror)
+            return
+        self.socket.sendall('copy %s from stdin%s\n' % (table, options))
+        with data:
+            while True:
+                # an empty frame ends the data
+                chunk = data.read(COPY_FRAME_BYTES)
+                self.socket.sendall(struct.pack('<I', len(chunk)) + chunk)
+                if not chunk:
+                    break
+        try:
+            (columns, tuple_list, row_count) = handle_query(self.reader)
+        except SqlError as e:
+            print e.message
+            return
+        print repr_summary(columns, row_count)
+
+    def do_show(self, line):
+        if line.strip() not in ('tables', 'status'):
+            print 'only support show tables and show status'
+        else:
+            self.socket.sendall('show %s\n' % line.strip())
+            print self.reader.read_line()
+
+    def namespace(self):
+        # taken again only after a ddl, checking the version is one short line
+        self.socket.sendall('show namespace version\n')
+        version = int(self.reader.read_line())
+        if self.names is None or self.names['version'] != version:
+            self.socket.sendall('show namespace\n')
+            self.names = json.loads(self.reader.read_line())
+        return self.names
+
+    def complete_sql(self, text):
+        names = self.namespace()
+        words = set(names['keywords'])
+        for table in names['tables']:
+            words.add(table['name'])
+            words.update(table['columns'])
+        return sorted(w for w in words if w.startswith(text))
+
+    def completedefault(self, text, line, begidx, endidx):
+        return self.complete_sql(text)
+
+    def completenames(self, text, *ignored):
+        # the first word of a statement
+        return cmd.Cmd.completenames(self, text, *ignored) + self.complete_sql(text)
+
+    def do_EOF(self, line):
+        self.socket.close()
+        return True
+
+def help():
+    print 'usage: python blastc (host port | --socket path) [--user user --password password] [--format table|csv|tsv|json]'
+    print '       [--ca ca_file | --fingerprint sha256]  (tls with the server certificate verified by either)'
+    sys.exit()
+
+
+if __name__ == '__main__':
+    args = sys.argv[1:]
+    opts = {}
+    address_len = 2  # host and port
+    if args[:1] == ['--socket']:
+        if len(args) < 2:
+            help()
+        address_len = 0
+        opts['socket'] = args[1]
+        args = args[2:]
+    while len(args) > address_len:
+        if len(args) < address_len + 2 or args[-2] not in ('--user', '--password', '--format', '--ca', '--fingerprint'):
+            help()
+        opts[args[-2][2:]] = args[-1]
+        args = args[:-2]
+    if len(args) != address_len:
+        help()
+    address = opts['socket'] if 'socket' in opts else (args[0], int(args[1]))
+    output_format = opts.get('format', 'table')
+    if output_format not in ('table', 'csv', 'tsv', 'json'):
+        help()
+    if 'socket' in opts and ('ca' in opts or 'fingerprint' in opts):
+        help()
+    try:
+        Console(address, opts.get('user'), opts.get('password'), output_format,
+                opts.get('ca'), opts.get('fingerprint')).cmdloop()
+    except SqlError as e:
+        print e.message
+
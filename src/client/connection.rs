@@ -0,0 +1,594 @@
+use std::io::{self, Read, Write, BufReader};
+use std::net::{TcpStream, SocketAddr, ToSocketAddrs, Shutdown};
+use std::time::Duration;
+use std::rc::Rc;
+use std::mem::transmute;
+use std::fmt;
+use std::error::Error;
+use rustc_serialize::json;
+#[cfg(feature = "tls")]
+use openssl::ssl::{SslConnector, SslMethod, SslStream, SslVerifyMode};
+#[cfg(feature = "tls")]
+use openssl::hash::MessageDigest;
+use ::store::table::AttrType;
+use ::store::tuple::TupleValue;
+use ::server::handler::{ColumnInfo, Summary, StatementKind};
+#[cfg(feature = "tls")]
+use ::utils::sha256::to_hex;
+
+
+const ROW_BATCH_TAG : u8 = 3;
+const END_TAG : u8 = 0;
+const ERROR_TAG : u8 = 2;
+const WARNING_TAG : u8 = 4;
+// the data of copy from stdin is sent in frames of at most this, below max_copy_frame_bytes of the server
+const COPY_FRAME_BYTES : usize = 65536;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    // the error result of the server, see src/server/result_error.rs for the codes
+    Server{ code : u32, message : String },
+    Protocol(String),  // a response not following doc/README.md
+    ConnectionBroken,  // failed in an earlier call, reconnect() before using it again
+    Tls(String),  // the handshake failed or the certificate is not trusted
+    ColumnNotExist(String),
+    TypeMismatch{ column : String, value : TupleValue },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ClientError::Io(ref err) => write!(f, "io error: {}", err),
+            &ClientError::Server{code, ref message} => write!(f, "server error {}: {}", code, message),
+            &ClientError::Protocol(ref msg) => write!(f, "protocol error: {}", msg),
+            &ClientError::ConnectionBroken => write!(f, "connection broken"),
+            &ClientError::Tls(ref msg) => write!(f, "tls error: {}", msg),
+            &ClientError::ColumnNotExist(ref name) => write!(f, "column {} not exist", name),
+            &ClientError::TypeMismatch{ref column, ref value} =>
+                write!(f, "unexpected value {:?} of column {}", value, column),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn description(&self) -> &str {
+        match self {
+            &ClientError::Io(ref err) => err.description(),
+            &ClientError::Server{ref message, ..} => message,
+            &ClientError::Protocol(ref msg) => msg,
+            &ClientError::ConnectionBroken => "connection broken",
+            &ClientError::Tls(ref msg) => msg,
+            &ClientError::ColumnNotExist(..) => "column not exist",
+            &ClientError::TypeMismatch{..} => "type mismatch",
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err : io::Error) -> ClientError {
+        ClientError::Io(err)
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub user : String,
+    pub password : String,
+}
+
+// How the certificate of a tls server is trusted.
+// A self-signed certificate can be trusted with its own file as the ca file,
+// or with its fingerprint, as printed by `openssl x509 -noout -fingerprint -sha256`.
+// The host name in the certificate is not checked.
+#[derive(Debug, Clone)]
+pub enum TlsVerify {
+    CaFile(String),
+    Fingerprint(String),  // the sha256 of the certificate in hex, the colons are ignored
+}
+
+#[derive(Debug)]
+enum ClientStream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(SslStream<TcpStream>),
+}
+
+impl ClientStream {
+    fn get_tcp(&self) -> &TcpStream {
+        match self {
+            &ClientStream::Plain(ref stream) => stream,
+            #[cfg(feature = "tls")]
+            &ClientStream::Tls(ref stream) => stream.get_ref(),
+        }
+    }
+}
+
+impl Read for ClientStream {
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+        match self {
+            &mut ClientStream::Plain(ref mut stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            &mut ClientStream::Tls(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for ClientStream {
+    fn write(&mut self, buf : &[u8]) -> io::Result<usize> {
+        match self {
+            &mut ClientStream::Plain(ref mut stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            &mut ClientStream::Tls(ref mut stream) => stream.write(buf),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            &mut ClientStream::Plain(ref mut stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            &mut ClientStream::Tls(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+fn start_tls(stream : TcpStream, verify : &TlsVerify) -> ClientResult<ClientStream> {
+    let mut builder = try!(SslConnector::builder(SslMethod::tls())
+        .map_err(|e| ClientError::Tls(e.to_string())));
+    match verify {
+        &TlsVerify::CaFile(ref path) => {
+            builder.set_verify(SslVerifyMode::PEER);
+            try!(builder.set_ca_file(path)
+                .map_err(|e| ClientError::Tls(format!("invalid ca file {}: {}", path, e))));
+        }
+        // checked after the handshake
+        &TlsVerify::Fingerprint(..) => builder.set_verify(SslVerifyMode::NONE),
+    }
+    let mut config = try!(builder.build().configure().map_err(|e| ClientError::Tls(e.to_string())));
+    config.set_verify_hostname(false);
+    config.set_use_server_name_indication(false);
+    let stream = try!(config.connect("", stream).map_err(|e| ClientError::Tls(e.to_string())));
+    if let &TlsVerify::Fingerprint(ref expected) = verify {
+        let fingerprint = stream.ssl().peer_certificate()
+            .and_then(|cert| cert.digest(MessageDigest::sha256()).ok())
+            .map(|digest| to_hex(&digest));
+        let expected = expected.replace(":", "").to_lowercase();
+        if fingerprint != Some(expected) {
+            let msg = format!("unexpected certificate fingerprint {:?}", fingerprint);
+            return Err(ClientError::Tls(msg));
+        }
+    }
+    Ok(ClientStream::Tls(stream))
+}
+
+#[cfg(not(feature = "tls"))]
+fn start_tls(_stream : TcpStream, _verify : &TlsVerify) -> ClientResult<ClientStream> {
+    Err(ClientError::Tls("the client is built without the tls feature".to_string()))
+}
+
+// A connection using the native protocol, one statement at a time.
+// After an io or protocol error the position in the stream is unknown,
+// so the connection is closed and every call fails until reconnect().
+#[derive(Debug)]
+pub struct Client {
+    addr : SocketAddr,
+    credentials : Option<Credentials>,
+    timeout : Option<Duration>,
+    tls : Option<TlsVerify>,
+    reader : Option<BufReader<ClientStream>>,  // None when broken
+}
+
+pub fn connect<A : ToSocketAddrs>(addr : A, credentials : Option<Credentials>) -> ClientResult<Client> {
+    open_client(addr, credentials, None)
+}
+
+// to the tls_port of the server, or its port when plaintext_enabled is false
+pub fn connect_tls<A : ToSocketAddrs>(addr : A, credentials : Option<Credentials>, verify : TlsVerify)
+        -> ClientResult<Client> {
+    open_client(addr, credentials, Some(verify))
+}
+
+fn open_client<A : ToSocketAddrs>(addr : A, credentials : Option<Credentials>, tls : Option<TlsVerify>)
+        -> ClientResult<Client> {
+    let addr = match try!(addr.to_socket_addrs()).next() {
+        Some(addr) => addr,
+        None => return Err(ClientError::Protocol("no address to connect".to_string())),
+    };
+    let mut client = Client{
+        addr : addr, credentials : credentials, timeout : None, tls : tls, reader : None,
+    };
+    try!(client.reconnect());
+    Ok(client)
+}
+
+impl Client {
+    pub fn reconnect(&mut self) -> ClientResult<()> {
+        self.close();
+        let stream = try!(TcpStream::connect(&self.addr));
+        try!(stream.set_read_timeout(self.timeout));
+        // the tls handshake comes before the auth statement
+        let stream = match self.tls {
+            Some(ref verify) => try!(start_tls(stream, verify)),
+            None => ClientStream::Plain(stream),
+        };
+        self.reader = Some(BufReader::new(stream));
+        if let Some(Credentials{ref user, ref password}) = self.credentials.clone() {
+            let done = match self.execute(&format!("auth {} {}", user, password)) {
+                Ok(Response::Done) => Ok(true),
+                Ok(..) => Ok(false),
+                Err(err) => Err(err),
+            };
+            match done {
+                Ok(true) => (),
+                // the server closes the connection after a failed auth
+                Ok(false) => return Err(self.fail("unexpected result of auth")),
+                Err(err) => {
+                    self.close();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // a slow server fails the call with a timeout instead of blocking it
+    pub fn set_timeout(&mut self, timeout : Option<Duration>) -> ClientResult<()> {
+        self.timeout = timeout;
+        if let Some(ref reader) = self.reader {
+            try!(reader.get_ref().get_tcp().set_read_timeout(timeout));
+        }
+        Ok(())
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.reader.is_none()
+    }
+
+    pub fn close(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.get_ref().get_tcp().shutdown(Shutdown::Both).ok();
+        }
+    }
+
+    // The rows of a select are read lazily by the RowStream,
+    // the client can't be used until the stream is dropped.
+    pub fn execute(&mut self, sql : &str) -> ClientResult<Response> {
+        try!(self.send_line(sql));
+        self.read_response()
+    }
+
+    // Streams the data of `copy t from stdin ...` in frames, the rows inserted are returned
+    // after all of it is sent. A failed line fails the copy with its line number in the message.
+    pub fn copy_in<R : Read>(&mut self, sql : &str, data : &mut R) -> ClientResult<Summary> {
+        try!(self.send_line(sql));
+        let mut buf = vec![0u8; COPY_FRAME_BYTES];
+        loop {
+            let n = match data.read(&mut buf) {
+                Ok(n) => n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    // the server is still waiting for the end of the data
+                    self.close();
+                    return Err(ClientError::Io(err));
+                }
+            };
+            // the empty frame ends the data
+            try!(self.send_frame(&buf[..n]));
+            if n == 0 {
+                break;
+            }
+        }
+        let summary = match try!(self.read_response()) {
+            Response::Affected(summary) => Some(summary),
+            _ => None,
+        };
+        match summary {
+            Some(summary) => Ok(summary),
+            None => Err(self.fail("unexpected result of copy")),
+        }
+    }
+
+    fn send_line(&mut self, sql : &str) -> ClientResult<()> {
+        // statements are sent one per line
+        let line = sql.replace("\r\n", " ").replace('\n', " ");
+        let sent = match self.reader {
+            Some(ref mut reader) => reader.get_mut().write_all(format!("{}\n", line).as_bytes()),
+            None => return Err(ClientError::ConnectionBroken),
+        };
+        self.check(sent)
+    }
+
+    fn send_frame(&mut self, data : &[u8]) -> ClientResult<()> {
+        let len = data.len() as u32;
+        let len_bytes = [len as u8, (len >> 8) as u8, (len >> 16) as u8, (len >> 24) as u8];
+        let sent = match self.reader {
+            Some(ref mut reader) => {
+                let stream = reader.get_mut();
+                stream.write_all(&len_bytes).and_then(|_| stream.write_all(data))
+            }
+            None => return Err(ClientError::ConnectionBroken),
+        };
+        self.check(sent)
+    }
+
+    fn read_response(&mut self) -> ClientResult<Response> {
+        let json_len = try!(self.read_u32()) as usize;
+        if json_len == 0 {
+            let head = try!(self.read_bytes(2));
+            if head == b"\r\n" {
+                return Ok(Response::Done);
+            }
+            let rest = try!(self.read_bytes(2));
+            let code = read_u32(&[head[0], head[1], rest[0], rest[1]]);
+            let message = try!(self.read_line());
+            return Err(ClientError::Server{ code : code, message : message });
+        }
+        let header = try!(self.read_bytes(json_len));
+        let columns : Option<Vec<ColumnInfo>> = String::from_utf8(header).ok()
+            .and_then(|s| json::decode(&s).ok());
+        let columns = match columns {
+            Some(columns) => columns,
+            None => return Err(self.fail("invalid columns")),
+        };
+        if columns.is_empty() {
+            return Ok(Response::Affected(try!(self.read_summary())));
+        }
+        Ok(Response::Rows(RowStream::new(self, columns)))
+    }
+
+    fn check<T>(&mut self, result : io::Result<T>) -> ClientResult<T> {
+        if result.is_err() {
+            self.close();
+        }
+        Ok(try!(result))
+    }
+
+    fn fail(&mut self, msg : &str) -> ClientError {
+        self.close();
+        ClientError::Protocol(msg.to_string())
+    }
+
+    fn read_bytes(&mut self, len : usize) -> ClientResult<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        let result = match self.reader {
+            Some(ref mut reader) => reader.read_exact(&mut buf),
+            None => return Err(ClientError::ConnectionBroken),
+        };
+        try!(self.check(result));
+        Ok(buf)
+    }
+
+    fn read_u32(&mut self) -> ClientResult<u32> {
+        let bytes = try!(self.read_bytes(4));
+        Ok(read_u32(&bytes))
+    }
+
+    fn read_line(&mut self) -> ClientResult<String> {
+        let mut line = Vec::new();
+        while !line.ends_with(b"\r\n") {
+            line.extend(try!(self.read_bytes(1)));
+        }
+        line.truncate(line.len() - 2);
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+
+    fn read_summary(&mut self) -> ClientResult<Summary> {
+        let bytes = try!(self.read_bytes(7));
+        let kind = match StatementKind::from_code(bytes[0]) {
+            Some(kind) => kind,
+            None => return Err(self.fail("invalid statement kind")),
+        };
+        if &bytes[5..] != b"\r\n" {
+            return Err(self.fail("summary not ended with \\r\\n"));
+        }
+        Ok(Summary::new(kind, read_u32(&bytes[1..5]) as usize))
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[derive(Debug)]
+pub enum Response<'a> {
+    Done,  // the statements without a result, such as create and drop
+    Affected(Summary),  // insert, update, delete, copy to and copy from stdin
+    Rows(RowStream<'a>),
+}
+
+// a column by its index or its name
+pub trait ColumnIndex : fmt::Display {
+    fn index(&self, columns : &[ColumnInfo]) -> Option<usize>;
+}
+
+impl ColumnIndex for usize {
+    fn index(&self, columns : &[ColumnInfo]) -> Option<usize> {
+        if *self < columns.len() { Some(*self) } else { None }
+    }
+}
+
+impl<'a> ColumnIndex for &'a str {
+    fn index(&self, columns : &[ColumnInfo]) -> Option<usize> {
+        columns.iter().position(|c| c.name == *self)
+    }
+}
+
+// Null is sent as zero bytes in the native protocol, so it's read as 0 or an empty string.
+#[derive(Debug, Clone)]
+pub struct Row {
+    columns : Rc<Vec<ColumnInfo>>,
+    values : Vec<TupleValue>,
+}
+
+impl Row {
+    pub fn get<I : ColumnIndex>(&self, column : I) -> ClientResult<&TupleValue> {
+        self.get_with_name(column).map(|(_, value)| value)
+    }
+    pub fn get_int<I : ColumnIndex>(&self, column : I) -> ClientResult<i32> {
+        match try!(self.get_with_name(column)) {
+            (_, &TupleValue::Int(n)) => Ok(n),
+            (name, value) => Err(ClientError::TypeMismatch{ column : name, value : value.clone() }),
+        }
+    }
+    pub fn get_float<I : ColumnIndex>(&self, column : I) -> ClientResult<f32> {
+        match try!(self.get_with_name(column)) {
+            (_, &TupleValue::Float(f)) => Ok(f),
+            (name, value) => Err(ClientError::TypeMismatch{ column : name, value : value.clone() }),
+        }
+    }
+    pub fn get_string<I : ColumnIndex>(&self, column : I) -> ClientResult<String> {
+        match try!(self.get_with_name(column)) {
+            (_, &TupleValue::Char(ref s)) => Ok(s.clone()),
+            (name, value) => Err(ClientError::TypeMismatch{ column : name, value : value.clone() }),
+        }
+    }
+    pub fn values(&self) -> &[TupleValue] {
+        &self.values
+    }
+    fn get_with_name<I : ColumnIndex>(&self, column : I) -> ClientResult<(String, &TupleValue)> {
+        match column.index(&self.columns) {
+            Some(i) => Ok((self.columns[i].name.clone(), &self.values[i])),
+            None => Err(ClientError::ColumnNotExist(column.to_string())),
+        }
+    }
+}
+
+// Reads the row batches off the socket as they are iterated.
+// Dropping it before the end reads and discards the rest of the result.
+#[derive(Debug)]
+pub struct RowStream<'a> {
+    client : &'a mut Client,
+    columns : Rc<Vec<ColumnInfo>>,
+    tuple_len : usize,
+    batch_left : usize,  // rows of the current batch not read yet
+    warnings : Vec<String>,
+    summary : Option<Summary>,  // set at the end of the rows
+    finished : bool,
+}
+
+impl<'a> RowStream<'a> {
+    fn new(client : &'a mut Client, columns : Vec<ColumnInfo>) -> RowStream<'a> {
+        let tuple_len = columns.iter().map(|c| attr_len(&c.attr_type)).sum();
+        RowStream{
+            client : client,
+            columns : Rc::new(columns),
+            tuple_len : tuple_len,
+            batch_left : 0,
+            warnings : Vec::new(),
+            summary : None,
+            finished : false,
+        }
+    }
+
+    pub fn get_columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    // the warnings received so far, all of them once the rows are read
+    pub fn get_warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    // the number of rows returned, only after the last row
+    pub fn get_summary(&self) -> Option<Summary> {
+        self.summary
+    }
+
+    // None after the last row, an error also ends the rows
+    pub fn next_row(&mut self) -> ClientResult<Option<Row>> {
+        let result = self.read_row();
+        if result.is_err() {
+            self.finished = true;
+            self.batch_left = 0;
+        }
+        result
+    }
+
+    fn read_row(&mut self) -> ClientResult<Option<Row>> {
+        while self.batch_left == 0 {
+            if self.finished {
+                return Ok(None);
+            }
+            try!(self.read_frame());
+        }
+        let data = try!(self.client.read_bytes(self.tuple_len));
+        self.batch_left -= 1;
+        Ok(Some(Row{ columns : self.columns.clone(), values : decode_row(&self.columns, &data) }))
+    }
+
+    fn read_frame(&mut self) -> ClientResult<()> {
+        let tag = try!(self.client.read_bytes(1))[0];
+        match tag {
+            ROW_BATCH_TAG => self.batch_left = try!(self.client.read_u32()) as usize,
+            WARNING_TAG => {
+                let len = try!(self.client.read_u32()) as usize;
+                let msg = try!(self.client.read_bytes(len));
+                self.warnings.push(String::from_utf8_lossy(&msg).into_owned());
+            }
+            ERROR_TAG => {
+                // failed in the middle of the rows
+                let code = try!(self.client.read_u32());
+                let message = try!(self.client.read_line());
+                return Err(ClientError::Server{ code : code, message : message });
+            }
+            END_TAG => {
+                self.finished = true;
+                self.summary = Some(try!(self.client.read_summary()));
+            }
+            _ => return Err(self.client.fail("unexpected tag")),
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for RowStream<'a> {
+    type Item = ClientResult<Row>;
+    fn next(&mut self) -> Option<ClientResult<Row>> {
+        match self.next_row() {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<'a> Drop for RowStream<'a> {
+    fn drop(&mut self) {
+        // the next result starts after the end of this one
+        while let Some(Ok(..)) = self.next() {}
+    }
+}
+
+fn read_u32(bytes : &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+}
+
+fn attr_len(attr : &AttrType) -> usize {
+    match attr {
+        &AttrType::Int | &AttrType::Float => 4,
+        &AttrType::Char{len} => len,
+    }
+}
+
+fn decode_row(columns : &[ColumnInfo], data : &[u8]) -> Vec<TupleValue> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    for column in columns.iter() {
+        let len = attr_len(&column.attr_type);
+        let bytes = &data[offset..offset + len];
+        values.push(match column.attr_type {
+            AttrType::Int => TupleValue::Int(read_u32(bytes) as i32),
+            AttrType::Float => TupleValue::Float(unsafe{ transmute(read_u32(bytes)) }),
+            AttrType::Char{..} => {
+                let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+                TupleValue::Char(String::from_utf8_lossy(&bytes[..end]).into_owned())
+            }
+        });
+        offset += len;
+    }
+    values
+}
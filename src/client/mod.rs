@@ -0,0 +1,6 @@
+#[allow(dead_code)]
+pub mod connection;
+
+pub use self::connection::{connect, connect_tls, Client, Credentials, TlsVerify};
+pub use self::connection::{Response, RowStream, Row, ColumnIndex};
+pub use self::connection::{ClientError, ClientResult};
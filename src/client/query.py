@@ -1,72 +1,163 @@
-import struct
-import json
-
-
-class SqlError(Exception):
-    pass
-
-
-def handle_query(response):
-    assert len(response) > 4
-    json_len = struct.unpack('<I', response[:4])[0]
-    if json_len == 0:
-        raise SqlError(response[4:])
-    json_str = struct.unpack('%ds' % json_len, response[4:4+json_len])[0]
-    tuple_desc = json.loads(json_str)
-    tuple_len = sum(map(attr_len, tuple_desc))
-    payload_start = 4 + json_len
-    tuple_sum = int(len(response) - payload_start) / int(tuple_len)
-    payload_end = tuple_len * tuple_sum + payload_start
-    assert len(response) == payload_end + 2
-    assert response[payload_end:] == '\r\n'
-    
-    gaps = [0] + map(get_gap, tuple_desc)[:-1]
-    offset_list = map(lambda i: sum(gaps[:i]), range(1, len(gaps)+1))
-    
-    # payload
-    result = []
-    for i in range(payload_start, payload_end, tuple_len):
-        index_list = map(lambda o: o + i, offset_list)
-        value_list = map(lambda (a, j): get_value(a, response, j), zip(tuple_desc, index_list))
-        result.append(tuple(value_list))
-
-    return (tuple_desc, result)
-
-
-def attr_len(attr):
-    if attr['type'] in ("Int", "Float"):
-        return 4
-    else:
-        assert attr['type'] == 'Char'
-        return int(attr['len'])
-
-
-def get_value(attr_type, data, index):
-    if attr_type['type'] == 'Int':
-        return struct.unpack('<I', data[index:index+4])[0]
-    elif attr_type['type'] == 'Float':
-        return struct.unpack('<f', data[index:index+4])[0]
-    elif attr_type['type'] == 'Char':
-        str_len = int(attr_type['len'])
-        return struct.unpack('%ds' % str_len, data[index:index+str_len])[0].rstrip('\0')
-    raise Exception('invalid type %s' % attr_type['type'])
-
-
-def get_gap(attr_type):
-    if attr_type['type'] in ('Int', 'Float'):
-        return 4
-    elif attr_type['type'] == 'Char':
-        return int(attr_type['len'])
-    raise Exception('invalid type %s' % attr_type['type'])
-
-
-def print_tuple_desc(tuple_desc):
-    print ', '.join(map(repr_attr_type, tuple_desc))
-
-
-def repr_attr_type(attr_type):
-    if attr_type['type'] in ('Int', 'Float'):
-        return attr_type['type']
-    elif attr_type['type'] == 'Char':
-        return 'Char(%s)' % attr_type['len']
-    raise Exception('invalid type')
+import struct
+import json
+
+
+class SqlError(Exception):
+    def __init__(self, message, code=None):
+        Exception.__init__(self, message)
+        self.code = code
+
+
+ROW_BATCH_TAG = '\x03'
+END_TAG = '\x00'
+ERROR_TAG = '\x02'
+WARNING_TAG = '\x04'
+
+
+class ResponseReader(object):
+    def __init__(self, sock):
+        self.sock = sock
+        self.buff = ''
+        self.warnings = []  # of the last query
+
+    def recv(self):
+        data = self.sock.recv(1000)
+        if not data:
+            raise SqlError('connection closed by server')
+        self.buff += data
+
+    def read(self, n):
+        while len(self.buff) < n:
+            self.recv()
+        data, self.buff = self.buff[:n], self.buff[n:]
+        return data
+
+    def peek(self, n):
+        while len(self.buff) < n:
+            self.recv()
+        return self.buff[:n]
+
+    def read_error(self):
+        code = struct.unpack('<I', self.read(4))[0]
+        return SqlError(self.read_line(), code)
+
+    def read_summary(self):
+        # the statement kind (1 byte) and the number of rows
+        kind = ord(self.read(1))
+        row_count = struct.unpack('<I', self.read(4))[0]
+        assert self.read(2) == '\r\n'
+        return (kind, row_count)
+
+    def read_line(self):
+        while '\r\n' not in self.buff:
+            self.recv()
+        i = self.buff.index('\r\n')
+        line, self.buff = self.buff[:i], self.buff[i+2:]
+        return line
+
+
+def handle_query(reader):
+    reader.warnings = []
+    json_len = struct.unpack('<I', reader.read(4))[0]
+    if json_len == 0:
+        if reader.peek(2) == '\r\n':
+            reader.read(2)
+            raise SqlError('')  # other non-query
+        raise reader.read_error()
+    json_str = reader.read(json_len)
+    columns = json.loads(json_str)
+    if not columns:
+        # insert, update, delete and copy to only return the number of rows
+        (_, row_count) = reader.read_summary()
+        return (columns, [], row_count)
+    tuple_desc = [c['attr_type'] for c in columns]
+    tuple_len = sum(map(attr_len, tuple_desc))
+
+    gaps = [0] + map(get_gap, tuple_desc)[:-1]
+    offset_list = map(lambda i: sum(gaps[:i]), range(1, len(gaps)+1))
+
+    # payload, the tuples come in batches beginning with ROW_BATCH_TAG and the count
+    result = []
+    while True:
+        tag = reader.read(1)
+        if tag == END_TAG:
+            break
+        elif tag == ERROR_TAG:
+            raise reader.read_error()
+        elif tag == WARNING_TAG:
+            msg_len = struct.unpack('<I', reader.read(4))[0]
+            reader.warnings.append(reader.read(msg_len))
+            continue
+        assert tag == ROW_BATCH_TAG
+        row_count = struct.unpack('<I', reader.read(4))[0]
+        for _ in range(row_count):
+            data = reader.read(tuple_len)
+            value_list = map(lambda (a, j): get_value(a, data, j), zip(tuple_desc, offset_list))
+            result.append(tuple(value_list))
+    (_, row_count) = reader.read_summary()
+
+    return (columns, result, row_count)
+
+
+def attr_len(attr):
+    if attr['type'] in ("Int", "Float"):
+        return 4
+    else:
+        assert attr['type'] == 'Char'
+        return int(attr['len'])
+
+
+def get_value(attr_type, data, index):
+    if attr_type['type'] == 'Int':
+        return struct.unpack('<I', data[index:index+4])[0]
+    elif attr_type['type'] == 'Float':
+        return shortest_float(struct.unpack('<f', data[index:index+4])[0])
+    elif attr_type['type'] == 'Char':
+        str_len = int(attr_type['len'])
+        return struct.unpack('%ds' % str_len, data[index:index+str_len])[0].rstrip('\0')
+    raise Exception('invalid type %s' % attr_type['type'])
+
+
+def shortest_float(f):
+    # the f32 widened to a double prints as 12345.77734375, the server prints 12345.777
+    if f != f or f in (float('inf'), float('-inf')):
+        return f
+    for precision in range(1, 10):
+        short = float('%.*g' % (precision, f))
+        try:
+            if struct.unpack('<f', struct.pack('<f', short))[0] == f:
+                return short
+        except OverflowError:  # rounded over the largest f32
+            pass
+    return f
+
+
+def get_gap(attr_type):
+    if attr_type['type'] in ('Int', 'Float'):
+        return 4
+    elif attr_type['type'] == 'Char':
+        return int(attr_type['len'])
+    raise Exception('invalid type %s' % attr_type['type'])
+
+
+def repr_summary(columns, row_count):
+    rows = '1 row' if row_count == 1 else '%d rows' % row_count
+    if columns:
+        return '%s in set' % rows
+    return 'Query OK, %s affected' % rows
+
+
+def print_columns(columns):
+    print ', '.join(map(repr_column, columns))
+
+
+def repr_column(column):
+    return '%s %s' % (column['name'], repr_attr_type(column['attr_type']))
+
+
+def repr_attr_type(attr_type):
+    if attr_type['type'] in ('Int', 'Float'):
+        return attr_type['type']
+    elif attr_type['type'] == 'Char':
+        return 'Char(%s)' % attr_type['len']
+    raise Exception('invalid type')
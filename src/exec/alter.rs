@@ -0,0 +1,83 @@
+use std::boxed::Box;
+use std::option::Option;
+use ::parser::AlterStatement;
+use ::parser::alter::AlterAction;
+use ::store::table::{Table, TableManagerRef};
+use ::store::tuple::{TupleData, TupleValue};
+use ::store::rewrite::{RowTransform, ColumnSource};
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType};
+use super::create_drop::gen_attr;
+
+
+#[derive(Debug)]
+pub struct AlterTable {
+    stmt : AlterStatement,
+    finished : bool,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+}
+
+impl AlterTable {
+    pub fn new(stmt : AlterStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(AlterTable{
+            stmt : stmt,
+            finished : false,
+            table_manager : table_manager.clone(),
+            error : None,
+        })
+    }
+}
+
+pub fn gen_alter_layout(table : &Table, action : &AlterAction) -> (Table, RowTransform) {
+    // all kinds of alter are done by rewriting the table
+    let mut new_table = table.clone();
+    new_table.stats = None;  // of the old columns
+    let mut sources : Vec<ColumnSource> =
+        (0..table.attr_list.len()).map(|i| ColumnSource::Old(i)).collect();
+    let find_attr = |name : &String| table.attr_list.iter().position(|a| &a.name == name).unwrap();
+    match action {
+        &AlterAction::AddAttr(ref decl) => {
+            new_table.attr_list.push(gen_attr(decl));
+            sources.push(ColumnSource::Value(TupleValue::Null));
+        }
+        &AlterAction::DropAttr(ref name) => {
+            let i = find_attr(name);
+            new_table.attr_list.remove(i);
+            sources.remove(i);
+        }
+        &AlterAction::ModifyAttr(ref decl) => {
+            new_table.attr_list[find_attr(&decl.name)] = gen_attr(decl);
+        }
+    }
+    (new_table, RowTransform{ sources : sources })
+}
+
+impl ExecIter for AlterTable {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let table = self.table_manager.borrow().get_table(&self.stmt.table).unwrap();
+        let (new_table, transform) = gen_alter_layout(&table.borrow(), &self.stmt.action);
+        let result = self.table_manager.borrow_mut().rewrite_table(new_table, &transform);
+        if let Err(error_msg) = result {
+            self.error = Some(ExecError{
+                error_type : ExecErrorType::AlterRewriteFailed,
+                error_msg : error_msg,
+            });
+        }
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+    // the tuples of a page are copied before they are inserted into the new file
+    fn get_min_pages(&self) -> usize { 1 }
+}
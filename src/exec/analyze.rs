@@ -0,0 +1,49 @@
+use std::boxed::Box;
+use std::option::Option;
+use ::parser::AnalyzeStatement;
+use ::store::table::TableManagerRef;
+use ::store::tuple::TupleData;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, gen_store_error};
+
+
+#[derive(Debug)]
+pub struct Analyze {
+    stmt : AnalyzeStatement,
+    finished : bool,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+}
+
+impl Analyze {
+    pub fn new(stmt : AnalyzeStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Analyze{
+            stmt : stmt,
+            finished : false,
+            table_manager : table_manager.clone(),
+            error : None,
+        })
+    }
+}
+
+impl ExecIter for Analyze {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        if let Err(err) = self.table_manager.borrow_mut().analyze(&self.stmt.table) {
+            self.error = Some(gen_store_error(err));
+        }
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+    fn get_min_pages(&self) -> usize { 1 }
+}
@@ -0,0 +1,96 @@
+use std::boxed::Box;
+use std::option::Option;
+use ::parser::{BackupStatement, RestoreStatement};
+use ::store::table::TableManagerRef;
+use ::store::tuple::TupleData;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType};
+
+
+#[derive(Debug)]
+pub struct Backup {
+    stmt : BackupStatement,
+    finished : bool,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+}
+
+impl Backup {
+    pub fn new(stmt : BackupStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Backup{
+            stmt : stmt,
+            finished : false,
+            table_manager : table_manager.clone(),
+            error : None,
+        })
+    }
+}
+
+impl ExecIter for Backup {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        if let Err(error_msg) = self.table_manager.borrow_mut().backup(&self.stmt.path) {
+            self.error = Some(ExecError{
+                error_type : ExecErrorType::BackupFailed,
+                error_msg : error_msg,
+            });
+        }
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+}
+
+
+#[derive(Debug)]
+pub struct Restore {
+    stmt : RestoreStatement,
+    finished : bool,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+}
+
+impl Restore {
+    pub fn new(stmt : RestoreStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Restore{
+            stmt : stmt,
+            finished : false,
+            table_manager : table_manager.clone(),
+            error : None,
+        })
+    }
+}
+
+impl ExecIter for Restore {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let result = self.table_manager.borrow_mut().restore(&self.stmt.path, self.stmt.force);
+        if let Err(error_msg) = result {
+            self.error = Some(ExecError{
+                error_type : ExecErrorType::RestoreFailed,
+                error_msg : error_msg,
+            });
+        }
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+}
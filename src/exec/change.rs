@@ -1,226 +1,370 @@
-use std::boxed::Box;
-use std::option::Option;
-use std::collections::HashMap;
-use std::ptr::write;
-use ::utils::pointer::write_string;
-use ::store::table::{AttrType, TableManagerRef};
-use ::store::tuple::{TupleData, TupleValue, TupleDesc};
-use ::parser::{
-    InsertStatement,
-};
-use super::iter::{ExecIter, ExecIterRef};
-use super::error::{ExecError, ExecErrorType};
-
-
-#[derive(Debug)]
-pub struct Insert {
-    stmt : InsertStatement,
-    table_manager : TableManagerRef,
-    finished : bool,
-}
-
-impl Insert {
-    pub fn new(stmt : InsertStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-        Box::new(Insert{
-            finished : false,
-            stmt : stmt,
-            table_manager : table_manager.clone(),
-        })
-    }
-}
-
-impl ExecIter for Insert {
-    fn open(&mut self) {
-        assert!(!self.finished);
-    }
-    fn close(&mut self) { self.finished = true; }
-    fn explain(&self) -> String {
-        format!("{}", self.stmt)
-    }
-    fn get_next(&mut self) -> Option<TupleData> {
-        if self.finished {
-            return None;
-        }
-        self.table_manager.borrow_mut().insert(&self.stmt.table, &self.stmt.value_list);
-        self.close();
-        None
-    }
-    fn get_error(&self) -> Option<ExecError> { None }
-}
-
-
-#[derive(Debug)]
-pub struct Delete {
-    table : String,
-    data_source : ExecIterRef,
-    table_manager : TableManagerRef,
-    finished : bool,
-}
-
-impl Delete {
-    pub fn new(table : &String, data_source : ExecIterRef, table_manager : &TableManagerRef) -> ExecIterRef {
-        Box::new(Delete{
-            table : table.clone(),
-            data_source : data_source,
-            table_manager : table_manager.clone(),
-            finished : false,
-        })
-    }
-}
-
-impl ExecIter for Delete {
-    fn open(&mut self) {
-        assert!(!self.finished);
-        self.data_source.open();
-    }
-    fn close(&mut self) {
-        self.data_source.close();
-        self.finished = true;
-    }
-    fn explain(&self) -> String {
-        format!("delete tuple from source: {:?}", self.data_source)
-    }
-    fn get_next(&mut self) -> Option<TupleData> {
-        if self.finished {
-            return None;
-        }
-        let tuple_data = match self.data_source.get_next() {
-            Some(tuple_data) => tuple_data,
-            None => {
-                self.close();
-                return None;
-            }
-        };
-        self.table_manager.borrow_mut().file_manager.delete(&self.table, tuple_data[0]);
-        Some(tuple_data)  // only to indicate not finished, the data inside is only for tests
-    }
-    fn get_error(&self) -> Option<ExecError> { None }
-}
-
-
-#[derive(Debug)]
-pub struct Update {
-    table : String,
-    data_source : ExecIterRef,
-    table_manager : TableManagerRef,
-    finished : bool,
-    set_values : HashMap<usize, TupleValue>,
-    tuple_desc : TupleDesc,
-}
-
-impl Update {
-    pub fn new(
-            table : &String,
-            tuple_desc : TupleDesc,
-            set_values : HashMap<usize, TupleValue>,
-            data_source : ExecIterRef,
-            table_manager : &TableManagerRef) -> ExecIterRef {
-        Box::new(Update{
-            table : table.clone(),
-            tuple_desc : tuple_desc,
-            data_source : data_source,
-            table_manager : table_manager.clone(),
-            finished : false,
-            set_values : set_values,
-        })
-    }
-}
-
-impl ExecIter for Update {
-    fn open(&mut self) {
-        assert!(!self.finished);
-        self.data_source.open();
-    }
-    fn close(&mut self) {
-        self.data_source.close();
-        self.finished = true;
-    }
-    fn explain(&self) -> String {
-        format!("update tuple from source: {:?}, set {:?}", self.data_source, self.set_values)
-    }
-    fn get_next(&mut self) -> Option<TupleData> {
-        if self.finished {
-            return None;
-        }
-        let tuple_data = match self.data_source.get_next() {
-            Some(tuple_data) => tuple_data,
-            None => {
-                self.close();
-                return None;
-            }
-        };
-        for (i, v) in self.set_values.iter() {
-            let p = tuple_data[*i];
-            unsafe {
-                match v {
-                    &TupleValue::Int(num) => write::<i32>(p as *mut i32, num),
-                    &TupleValue::Float(num) => write::<f32>(p as *mut f32, num),
-                    &TupleValue::Char(ref s) => {
-                        let len = extract!(self.tuple_desc.attr_desc[*i], AttrType::Char{len}, len);
-                        write_string(p, s, len);
-                    }
-                }
-            }
-        }
-        Some(tuple_data)
-    }
-    fn get_error(&self) -> Option<ExecError> { None }
-}
-
-#[derive(Debug)]
-pub struct CheckAndInsert {
-    filter_plan : ExecIterRef,
-    insert_plan : ExecIterRef,
-    error : Option<ExecError>,
-    finished : bool,
-}
-
-impl CheckAndInsert {
-    pub fn new(filter_plan : ExecIterRef, insert_plan : ExecIterRef) -> ExecIterRef {
-        Box::new(CheckAndInsert{
-            filter_plan : filter_plan,
-            insert_plan : insert_plan,
-            error : None,
-            finished : false,
-        })
-    }
-}
-
-impl ExecIter for CheckAndInsert {
-    fn open(&mut self) {
-        assert!(!self.finished);
-        self.filter_plan.open();
-        self.insert_plan.open();
-    }
-    fn close(&mut self) {
-        if self.finished {
-            return;
-        }
-        self.finished = true;
-        self.filter_plan.close();
-        self.insert_plan.close();
-    }
-    fn explain(&self) -> String {
-        format!("check if primary key exist {:?}, then insert {:?}",
-            self.filter_plan, self.insert_plan)
-    }
-    fn get_next(&mut self) -> Option<TupleData> {
-        if self.finished {
-            return None;
-        }
-        match self.filter_plan.get_next() {
-            Some(..) => {
-                self.error = Some(ExecError{
-                    error_type : ExecErrorType::PrimaryKeyExist,
-                    error_msg : format!("primary key already exist"),
-                });
-                self.close();
-            }
-            None => {
-                self.insert_plan.get_next();
-            }
-        };
-        None
-    }
-    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
-}
+use std::boxed::Box;
+use std::option::Option;
+use std::collections::HashMap;
+use std::cmp::max;
+use std::ptr::write_bytes;
+use ::utils::pointer::{write_string, write_i32_le, write_f32_le};
+use ::store::table::{AttrType, TableManagerRef};
+use ::store::tuple::{TupleData, TupleValue, TupleDesc};
+use ::store::undo::{UndoLog, UndoOp};
+use ::parser::{
+    InsertStatement,
+};
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType, gen_store_error};
+use super::generated::GeneratedColumns;
+
+
+#[derive(Debug)]
+pub struct Insert {
+    stmt : InsertStatement,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+    finished : bool,
+    affected_num : usize,
+}
+
+impl Insert {
+    pub fn new(stmt : InsertStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Insert{
+            finished : false,
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+            error : None,
+            affected_num : 0,
+        })
+    }
+}
+
+impl ExecIter for Insert {
+    fn open(&mut self) {
+        assert!(!self.finished);
+    }
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        let result = self.table_manager.borrow_mut().insert(&self.stmt.table, &self.stmt.value_list);
+        match result {
+            Ok(..) => self.affected_num = 1,
+            Err(err) => self.error = Some(gen_store_error(err)),
+        }
+        self.close();
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { self.affected_num }
+    fn get_min_pages(&self) -> usize { 1 }
+}
+
+
+#[derive(Debug)]
+pub struct Delete {
+    table : String,
+    data_source : ExecIterRef,
+    table_manager : TableManagerRef,
+    undo_log : UndoLog,  // all or none of the tuples are deleted
+    error : Option<ExecError>,
+    finished : bool,
+    affected_num : usize,
+}
+
+impl Delete {
+    pub fn new(table : &String, data_source : ExecIterRef, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Delete{
+            table : table.clone(),
+            data_source : data_source,
+            table_manager : table_manager.clone(),
+            undo_log : UndoLog::new(table),
+            error : None,
+            finished : false,
+            affected_num : 0,
+        })
+    }
+    fn finish(&mut self, error : Option<ExecError>) {
+        self.close();
+        if error.is_some() {
+            // the changes which can't be undone are logged
+            self.undo_log.rollback(&mut self.table_manager.borrow_mut().file_manager).ok();
+            self.affected_num = 0;
+        }
+        self.undo_log.clear();
+        self.error = error;
+    }
+}
+
+impl ExecIter for Delete {
+    fn open(&mut self) {
+        assert!(!self.finished);
+        self.data_source.open();
+    }
+    fn close(&mut self) {
+        self.data_source.close();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("delete tuple from source: {:?}", self.data_source)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        let tuple_data = match self.data_source.get_next() {
+            Some(tuple_data) => tuple_data,
+            None => {
+                let error = self.data_source.get_error();
+                self.finish(error);
+                return None;
+            }
+        };
+        let position = self.data_source.get_position().unwrap();
+        // a page of the table evicted by the scan may have failed to be written
+        let writable = self.table_manager.borrow().file_manager.check_writable(&self.table);
+        if let Err(err) = writable {
+            self.finish(Some(gen_store_error(err)));
+            return None;
+        }
+        let result = self.table_manager.borrow_mut().file_manager.get_slot_bytes(&self.table, position);
+        let before = match result {
+            Ok(before) => before,
+            Err(err) => {
+                self.finish(Some(gen_store_error(err)));
+                return None;
+            }
+        };
+        let free_pages = self.table_manager.borrow_mut().file_manager.get_free_pages(&self.table);
+        let result = self.table_manager.borrow_mut().file_manager.delete(&self.table, position);
+        if let Err(err) = result {
+            self.finish(Some(gen_store_error(err)));
+            return None;
+        }
+        self.undo_log.push_delete(position, before, free_pages);
+        self.affected_num += 1;
+        Some(tuple_data)  // only to indicate not finished, the data inside is only for tests
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { self.affected_num }
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> { vec![&mut self.data_source] }
+    fn describe(&self) -> String {
+        format!("delete: {}", self.table)
+    }
+    // the tuples are deleted in the page pinned by the source
+    fn get_min_pages(&self) -> usize { self.data_source.get_min_pages() }
+}
+
+
+#[derive(Debug)]
+pub struct Update {
+    table : String,
+    data_source : ExecIterRef,
+    table_manager : TableManagerRef,
+    error : Option<ExecError>,
+    finished : bool,
+    set_values : HashMap<usize, TupleValue>,
+    tuple_desc : TupleDesc,
+    generated : Option<GeneratedColumns>,  // recomputed after the set values are written
+    undo_log : UndoLog,  // all or none of the tuples are updated
+    affected_num : usize,
+}
+
+impl Update {
+    pub fn new(
+            table : &String,
+            tuple_desc : TupleDesc,
+            set_values : HashMap<usize, TupleValue>,
+            data_source : ExecIterRef,
+            table_manager : &TableManagerRef) -> ExecIterRef {
+        Update::with_generated(table, tuple_desc, set_values, None, data_source, table_manager)
+    }
+    pub fn with_generated(
+            table : &String,
+            tuple_desc : TupleDesc,
+            set_values : HashMap<usize, TupleValue>,
+            generated : Option<GeneratedColumns>,
+            data_source : ExecIterRef,
+            table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Update{
+            table : table.clone(),
+            tuple_desc : tuple_desc,
+            data_source : data_source,
+            table_manager : table_manager.clone(),
+            error : None,
+            finished : false,
+            set_values : set_values,
+            generated : generated,
+            undo_log : UndoLog::new(table),
+            affected_num : 0,
+        })
+    }
+    fn finish(&mut self, error : Option<ExecError>) {
+        self.close();
+        if error.is_some() {
+            // the changes which can't be undone are logged
+            self.undo_log.rollback(&mut self.table_manager.borrow_mut().file_manager).ok();
+            self.affected_num = 0;
+        }
+        self.undo_log.clear();
+        self.error = error;
+    }
+}
+
+impl ExecIter for Update {
+    fn open(&mut self) {
+        assert!(!self.finished);
+        self.data_source.open();
+    }
+    fn close(&mut self) {
+        self.data_source.close();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("update tuple from source: {:?}, set {:?}", self.data_source, self.set_values)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        let tuple_data = match self.data_source.get_next() {
+            Some(tuple_data) => tuple_data,
+            None => {
+                let error = self.data_source.get_error();
+                self.finish(error);
+                return None;
+            }
+        };
+        let position = self.data_source.get_position().unwrap();
+        let writable = self.table_manager.borrow().file_manager.check_writable(&self.table);
+        if let Err(err) = writable {
+            self.finish(Some(gen_store_error(err)));
+            return None;
+        }
+        let result = self.table_manager.borrow_mut().file_manager.get_slot_bytes(&self.table, position);
+        let before = match result {
+            Ok(before) => before,
+            Err(err) => {
+                self.finish(Some(gen_store_error(err)));
+                return None;
+            }
+        };
+        self.undo_log.push(UndoOp::Update, position, before);
+        for (i, v) in self.set_values.iter() {
+            let p = tuple_data[*i];
+            unsafe {
+                match v {
+                    &TupleValue::Int(num) => write_i32_le(p, num),
+                    &TupleValue::Float(num) => write_f32_le(p, num),
+                    &TupleValue::Char(ref s) => {
+                        let len = extract!(self.tuple_desc.attr_desc[*i], AttrType::Char{len}, len);
+                        write_string(p, s, len);
+                    }
+                    &TupleValue::Null => {
+                        let len = match self.tuple_desc.attr_desc[*i] {
+                            AttrType::Int | AttrType::Float => 4,
+                            AttrType::Char{len} => len,
+                        };
+                        write_bytes(p as *mut u8, 0, len);
+                    }
+                }
+            }
+        }
+        if let Some(ref generated) = self.generated {
+            generated.fill_tuple(&tuple_data);
+        }
+        self.table_manager.borrow_mut().file_manager.mark_dirty_by_position(&self.table, position);
+        self.affected_num += 1;
+        Some(tuple_data)
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { self.affected_num }
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> { vec![&mut self.data_source] }
+    fn describe(&self) -> String {
+        format!("update: {}", self.table)
+    }
+    // the tuples are updated in the page pinned by the source
+    fn get_min_pages(&self) -> usize { self.data_source.get_min_pages() }
+}
+
+#[derive(Debug)]
+pub struct CheckAndInsert {
+    filter_plan : ExecIterRef,
+    insert_plan : ExecIterRef,
+    error : Option<ExecError>,
+    finished : bool,
+}
+
+impl CheckAndInsert {
+    pub fn new(filter_plan : ExecIterRef, insert_plan : ExecIterRef) -> ExecIterRef {
+        Box::new(CheckAndInsert{
+            filter_plan : filter_plan,
+            insert_plan : insert_plan,
+            error : None,
+            finished : false,
+        })
+    }
+}
+
+impl ExecIter for CheckAndInsert {
+    fn open(&mut self) {
+        assert!(!self.finished);
+        self.filter_plan.open();
+        self.insert_plan.open();
+    }
+    fn close(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.filter_plan.close();
+        self.insert_plan.close();
+    }
+    fn explain(&self) -> String {
+        format!("check if primary key exist {:?}, then insert {:?}",
+            self.filter_plan, self.insert_plan)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        match self.filter_plan.get_next() {
+            Some(..) => {
+                self.error = Some(ExecError{
+                    error_type : ExecErrorType::PrimaryKeyExist,
+                    error_msg : format!("primary key already exist"),
+                });
+                self.close();
+            }
+            None => {
+                if let Some(err) = self.filter_plan.get_error() {
+                    // the primary key is not checked
+                    self.error = Some(err);
+                    self.close();
+                    return None;
+                }
+                self.insert_plan.get_next();
+                // such as the table being read-only
+                self.error = self.insert_plan.get_error();
+            }
+        };
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { self.insert_plan.get_affected_num() }
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> {
+        vec![&mut self.filter_plan, &mut self.insert_plan]
+    }
+    fn describe(&self) -> String {
+        "check primary key and insert".to_string()
+    }
+    // the check is closed before the insert
+    fn get_min_pages(&self) -> usize {
+        max(self.filter_plan.get_min_pages(), self.insert_plan.get_min_pages())
+    }
+}
@@ -0,0 +1,493 @@
+use std::boxed::Box;
+use std::option::Option;
+use std::fs::{File, OpenOptions, canonicalize, remove_file};
+use std::io::{BufRead, BufReader, BufWriter, Write, ErrorKind};
+use std::env::current_dir;
+use std::path::{Path, PathBuf};
+use ::parser::CopyFromStatement;
+use ::parser::copy::CopyOptions;
+use ::parser::common::{ValueExpr, ValueList, Literal};
+use ::parser::sem_check::check_assign;
+use ::store::table::{Attr, AttrType, TableManagerRef, CopyInput};
+use ::store::file::StoreErrorType;
+use ::store::tuple::{TupleData, gen_tuple_value};
+use ::utils::csv::{CsvReader, CsvRecord, CsvField, write_record_fields, value_to_field};
+use ::utils::float::parse_float;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType, gen_store_error};
+use super::gen_plan::gen_check_primary_key_exist_plan;
+use super::generated::GeneratedColumns;
+
+
+#[derive(Debug)]
+pub struct CopyFrom {
+    stmt : CopyFromStatement,
+    table_manager : TableManagerRef,
+    finished : bool,
+    inserted : usize,
+    rejected : Vec<String>,
+    error : Option<ExecError>,
+}
+
+impl CopyFrom {
+    pub fn new(stmt : CopyFromStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(CopyFrom{
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+            finished : false,
+            inserted : 0,
+            rejected : Vec::new(),
+            error : None,
+        })
+    }
+
+    fn set_error(&mut self, error_type : ExecErrorType, error_msg : String) {
+        self.error = Some(ExecError{
+            error_type : error_type,
+            error_msg : error_msg,
+        });
+    }
+
+    fn gen_rejected_msg(&self) -> String {
+        let mut msg = format!("{} row(s) inserted, {} line(s) rejected",
+            self.inserted, self.rejected.len());
+        for err in self.rejected.iter() {
+            msg.push('\n');
+            msg.push_str(err);
+        }
+        msg
+    }
+
+    // return false when the threshold is exceeded and copy should be aborted
+    fn reject(&mut self, err_msg : String) -> bool {
+        self.rejected.push(err_msg);
+        if self.rejected.len() > self.stmt.options.max_errors {
+            let msg = self.gen_rejected_msg();
+            self.set_error(ExecErrorType::CopyTooManyErrors, msg);
+            return false;
+        }
+        true
+    }
+
+    fn copy_file(&mut self, path : &String) {
+        let full_path = match resolve_path(path) {
+            Ok(full_path) => full_path,
+            Err(err_msg) => return self.set_error(ExecErrorType::CopyOpenFileFailed, err_msg),
+        };
+        let file = match File::open(&full_path) {
+            Ok(file) => file,
+            Err(err) => return self.set_error(ExecErrorType::CopyOpenFileFailed,
+                format!("can't open {:?}: {}", path, err)),
+        };
+        let delimiter = self.stmt.options.delimiter.chars().next().unwrap();
+        self.copy_records(CsvReader::new(BufReader::new(file), delimiter));
+    }
+
+    fn copy_stdin(&mut self) {
+        let taken = self.table_manager.borrow_mut().take_copy_input();
+        let input = match taken {
+            Some(CopyInput(input)) => input,
+            None => return self.set_error(ExecErrorType::CopyStdinFailed,
+                "copy from stdin is only supported by the native protocol".to_string()),
+        };
+        let delimiter = self.stmt.options.delimiter.chars().next().unwrap();
+        self.copy_records(CsvReader::new(BufReader::new(input), delimiter));
+    }
+
+    fn copy_records<R : BufRead>(&mut self, mut reader : CsvReader<R>) {
+        let table = self.table_manager.borrow().get_table(&self.stmt.table).unwrap();
+        let attr_list = table.borrow().attr_list.clone();
+        let pk_index = table.borrow().get_primary_key_index();
+        let generated = GeneratedColumns::new(&table.borrow());
+
+        let column_map = if self.stmt.options.header {
+            let header = match reader.read_record() {
+                Ok(Some(header)) => header,
+                Ok(None) => return,  // empty file
+                Err(err) => return self.set_error(ExecErrorType::CopyInvalidHeader,
+                    format!("line {}: {}", err.line, err.error_msg)),
+            };
+            match gen_column_map(&header, &attr_list) {
+                Ok(column_map) => column_map,
+                Err(err_msg) => return self.set_error(ExecErrorType::CopyInvalidHeader, err_msg),
+            }
+        } else {
+            (0..attr_list.len()).map(|i| Some(i)).collect()
+        };
+        let column_sum = column_map.iter().filter(|c| c.is_some()).count();
+
+        let guard = self.table_manager.borrow().get_query_guard();
+        loop {
+            if let Err(err_msg) = guard.check() {
+                return self.set_error(ExecErrorType::QueryCancelled, err_msg);
+            }
+            let record = match reader.read_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(ref err) if err.read_failed && self.stmt.is_stdin() => {
+                    // the client is gone or broke the protocol, not a line to reject
+                    return self.set_error(ExecErrorType::CopyStdinFailed,
+                        format!("line {}: {}", err.line, err.error_msg));
+                }
+                Err(err) => {
+                    // the rest of the file can't be read reliably
+                    self.reject(format!("line {}: {}", err.line, err.error_msg));
+                    break;
+                }
+            };
+            if is_blank_record(&record) {
+                continue;
+            }
+            let mut value_list = match gen_value_list(&record, &column_map, column_sum,
+                    &attr_list, self.stmt.options.import_null()) {
+                Ok(value_list) => value_list,
+                Err(err_msg) => {
+                    if !self.reject(err_msg) { return; }
+                    continue;
+                }
+            };
+            // the generated attributes in the file are ignored
+            generated.fill_values(&mut value_list);
+            let pk = extract!(value_list[pk_index].literal(), &Literal::Int(pk), pk);
+            let mut check = gen_check_primary_key_exist_plan(pk, &self.stmt.table, &self.table_manager);
+            check.open();
+            let exist = check.get_next().is_some();
+            check.close();
+            if let Some(err) = check.get_error() {
+                self.error = Some(err);
+                return;
+            }
+            if exist {
+                if !self.reject(format!("line {}: primary key {} already exist", record.line, pk)) {
+                    return;
+                }
+                continue;
+            }
+            let result = self.table_manager.borrow_mut().insert(&self.stmt.table, &value_list);
+            match result {
+                Ok(..) => (),
+                Err(ref err) if err.error_type == StoreErrorType::InvalidValuesNum => {
+                    if !self.reject(format!("line {}: {}", record.line, err.error_msg)) { return; }
+                    continue;
+                }
+                // not a problem of the line, the lines inserted before are kept
+                Err(err) => {
+                    self.error = Some(gen_store_error(err));
+                    return;
+                }
+            }
+            self.inserted += 1;
+        }
+        if !self.rejected.is_empty() && self.error.is_none() {
+            let msg = self.gen_rejected_msg();
+            self.set_error(ExecErrorType::CopyLinesRejected, msg);
+        }
+    }
+}
+
+impl ExecIter for CopyFrom {
+    fn open(&mut self) {
+        assert!(!self.finished);
+    }
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        match self.stmt.path.clone() {
+            Some(path) => self.copy_file(&path),
+            None => self.copy_stdin(),
+        }
+        self.close();
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { self.inserted }
+    // a line is checked by a scan, which is closed before it's inserted
+    fn get_min_pages(&self) -> usize { 1 }
+}
+
+#[derive(Debug)]
+pub struct CopyTo {
+    path : String,
+    options : CopyOptions,
+    attr_desc : Vec<AttrType>,
+    header : Vec<String>,
+    data_source : ExecIterRef,
+    writer : Option<BufWriter<File>>,
+    output_path : Option<PathBuf>,  // resolved when the file is opened
+    finished : bool,
+    error : Option<ExecError>,
+}
+
+impl CopyTo {
+    pub fn new(
+            path : String,
+            options : CopyOptions,
+            attr_desc : Vec<AttrType>,
+            header : Vec<String>,
+            data_source : ExecIterRef) -> ExecIterRef {
+        Box::new(CopyTo{
+            path : path,
+            options : options,
+            attr_desc : attr_desc,
+            header : header,
+            data_source : data_source,
+            writer : None,
+            output_path : None,
+            finished : false,
+            error : None,
+        })
+    }
+
+    fn set_error(&mut self, error_type : ExecErrorType, error_msg : String) {
+        self.error = Some(ExecError{
+            error_type : error_type,
+            error_msg : error_msg,
+        });
+        self.close();
+    }
+
+    fn open_file(&mut self) -> Result<BufWriter<File>, (ExecErrorType, String)> {
+        let path = match resolve_output_path(&self.path) {
+            Ok(path) => path,
+            Err(err_msg) => return Err((ExecErrorType::CopyOpenFileFailed, err_msg)),
+        };
+        let mut open_options = OpenOptions::new();
+        open_options.write(true);
+        if self.options.overwrite {
+            open_options.create(true).truncate(true);
+        } else {
+            open_options.create_new(true);
+        }
+        match open_options.open(&path) {
+            Ok(file) => {
+                self.output_path = Some(path);
+                Ok(BufWriter::new(file))
+            }
+            Err(err) => {
+                if err.kind() == ErrorKind::AlreadyExists {
+                    Err((ExecErrorType::CopyFileExist, format!(
+                        "{:?} already exist, use `with overwrite` to replace it", self.path)))
+                } else {
+                    Err((ExecErrorType::CopyOpenFileFailed,
+                        format!("can't open {:?}: {}", self.path, err)))
+                }
+            }
+        }
+    }
+
+    // the rows written before the query is cancelled or killed are not a whole result
+    fn remove_cancelled_file(&mut self) {
+        let cancelled = match self.error {
+            Some(ExecError{ error_type : ExecErrorType::QueryCancelled, .. }) => true,
+            _ => false,
+        };
+        if let (true, Some(path)) = (cancelled, self.output_path.take()) {
+            remove_file(&path).ok();
+        }
+    }
+
+    fn write_fields(&mut self, fields : &Vec<CsvField>) -> bool {
+        let delimiter = self.options.delimiter.chars().next().unwrap();
+        let result = write_record_fields(self.writer.as_mut().unwrap(), fields, delimiter);
+        if let Err(err) = result {
+            let error_msg = format!("can't write {:?}: {}", self.path, err);
+            self.set_error(ExecErrorType::CopyWriteFileFailed, error_msg);
+            return false;
+        }
+        true
+    }
+}
+
+impl ExecIter for CopyTo {
+    fn open(&mut self) {
+        assert!(!self.finished);
+        match self.open_file() {
+            Ok(writer) => self.writer = Some(writer),
+            Err((error_type, error_msg)) => return self.set_error(error_type, error_msg),
+        }
+        self.data_source.open();
+        if self.options.header {
+            let header = self.header.iter().map(|h| CsvField{ value : h.clone(), quoted : false }).collect();
+            self.write_fields(&header);
+        }
+    }
+    fn close(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.data_source.close();
+        if let Some(mut writer) = self.writer.take() {
+            if let Err(err) = writer.flush() {
+                self.error = Some(ExecError{
+                    error_type : ExecErrorType::CopyWriteFileFailed,
+                    error_msg : format!("can't write {:?}: {}", self.path, err),
+                });
+            }
+        }
+    }
+    fn explain(&self) -> String {
+        format!("copy tuple from source: {:?} to {:?} {}", self.data_source, self.path, self.options)
+    }
+    fn get_next(&mut self) -> Option<TupleData> {
+        if self.finished {
+            return None;
+        }
+        let tuple_data = match self.data_source.get_next() {
+            Some(tuple_data) => tuple_data,
+            None => {
+                if let Some(err) = self.data_source.get_error() {
+                    self.error = Some(err);
+                }
+                self.close();
+                self.remove_cancelled_file();
+                return None;
+            }
+        };
+        let fields = gen_tuple_value(&self.attr_desc, tuple_data.clone()).iter()
+            .map(|v| value_to_field(v, self.options.export_null())).collect();
+        if !self.write_fields(&fields) {
+            return None;
+        }
+        Some(tuple_data)  // one tuple written
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> { vec![&mut self.data_source] }
+    fn describe(&self) -> String {
+        format!("copy to {:?}", self.path)
+    }
+    fn get_min_pages(&self) -> usize { self.data_source.get_min_pages() }
+}
+
+fn resolve_output_path(path : &String) -> Result<PathBuf, String> {
+    // the file may not exist yet, so check the directory containing it
+    let base = try!(get_base_dir());
+    let full_path = base.join(path);
+    let dir = match full_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => base.clone(),
+    };
+    let dir = match canonicalize(&dir) {
+        Ok(dir) => dir,
+        Err(err) => return Err(format!("can't open directory of {:?}: {}", path, err)),
+    };
+    let file_name = match Path::new(path).file_name() {
+        Some(file_name) => file_name.to_os_string(),
+        None => return Err(format!("invalid file name {:?}", path)),
+    };
+    let full_path = dir.join(file_name);
+    // an existing target can be a symlink
+    let real_path = canonicalize(&full_path).unwrap_or(full_path.clone());
+    if !dir.starts_with(&base) || !real_path.starts_with(&base) {
+        return Err(format!("{:?} is outside the working directory", path));
+    }
+    Ok(full_path)
+}
+
+fn get_base_dir() -> Result<PathBuf, String> {
+    match current_dir().and_then(|dir| canonicalize(dir)) {
+        Ok(base) => Ok(base),
+        Err(err) => Err(format!("can't get working directory: {}", err)),
+    }
+}
+
+fn resolve_path(path : &String) -> Result<PathBuf, String> {
+    // sem check rejects absolute path and `..`, this also catches symlinks pointing outside
+    let base = try!(get_base_dir());
+    let full_path = match canonicalize(base.join(path)) {
+        Ok(full_path) => full_path,
+        Err(err) => return Err(format!("can't open {:?}: {}", path, err)),
+    };
+    if !full_path.starts_with(&base) {
+        return Err(format!("{:?} is outside the working directory", path));
+    }
+    Ok(full_path)
+}
+
+fn is_blank_record(record : &CsvRecord) -> bool {
+    record.fields.len() == 1 && record.fields[0].value.is_empty() && !record.fields[0].quoted
+}
+
+// map every attribute to its column in the file, None for attributes missing in header
+fn gen_column_map(header : &CsvRecord, attr_list : &Vec<Attr>) -> Result<Vec<Option<usize>>, String> {
+    let mut column_map : Vec<Option<usize>> = vec![None; attr_list.len()];
+    for (column, field) in header.fields.iter().enumerate() {
+        let name = field.value.trim();
+        let index = match attr_list.iter().position(|a| a.name == name) {
+            Some(index) => index,
+            None => return Err(format!("line {}, column {}: unknown attribute `{}`",
+                header.line, column + 1, name)),
+        };
+        if column_map[index].is_some() {
+            return Err(format!("line {}, column {}: duplicate attribute `{}`",
+                header.line, column + 1, name));
+        }
+        column_map[index] = Some(column);
+    }
+    for (attr, column) in attr_list.iter().zip(column_map.iter()) {
+        if column.is_none() && !attr.nullable && attr.generated.is_none() {
+            return Err(format!("line {}: attribute `{}` is not nullable but missing in header",
+                header.line, attr.name));
+        }
+    }
+    Ok(column_map)
+}
+
+fn gen_value_list(
+        record : &CsvRecord,
+        column_map : &Vec<Option<usize>>,
+        column_sum : usize,
+        attr_list : &Vec<Attr>,
+        null_as : Option<&str>) -> Result<ValueList, String> {
+    if record.fields.len() != column_sum {
+        return Err(format!("line {}: expected {} fields, found {}",
+            record.line, column_sum, record.fields.len()));
+    }
+    let mut value_list = ValueList::new();
+    for (attr, column) in attr_list.iter().zip(column_map.iter()) {
+        let value = match column {
+            &Some(column) => {
+                match field_to_value(&record.fields[column], attr, null_as) {
+                    Ok(value) => value,
+                    Err(reason) => return Err(format!("line {}, column {}: {}",
+                        record.line, column + 1, reason)),
+                }
+            }
+            &None => ValueExpr::from_literal(Literal::Null),
+        };
+        value_list.push(value);
+    }
+    Ok(value_list)
+}
+
+fn field_to_value(field : &CsvField, attr : &Attr, null_as : Option<&str>) -> Result<ValueExpr, String> {
+    let mut value = if !field.quoted && null_as == Some(&field.value[..]) {
+        ValueExpr::from_literal(Literal::Null)
+    } else {
+        match attr.attr_type {
+            AttrType::Int => match field.value.trim().parse::<i32>() {
+                Ok(n) => ValueExpr::from_literal(Literal::Int(n)),
+                Err(..) => return Err(format!("invalid int value {:?} for attribute {}",
+                    field.value, attr.name)),
+            },
+            AttrType::Float => match parse_float(&field.value) {
+                Some(n) => ValueExpr::from_literal(Literal::Float(n)),
+                None => return Err(format!("invalid float value {:?} for attribute {}",
+                    field.value, attr.name)),
+            },
+            AttrType::Char{..} => ValueExpr::from_literal(Literal::Str(field.value.clone())),
+        }
+    };
+    match check_assign(&mut value, attr) {
+        Ok(()) => Ok(value),
+        Err(errs) => Err(errs[0].error_msg.clone()),
+    }
+}
@@ -1,10 +1,46 @@
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub enum ExecErrorType {
-    PrimaryKeyExist,
-}
-
-#[derive(Debug, Clone)]
-pub struct ExecError {
-    pub error_type : ExecErrorType,
-    pub error_msg : String,
-}
+use ::store::file::{StoreError, StoreErrorType};
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExecErrorType {
+    PrimaryKeyExist,
+    DeleteTupleNotFound,
+    AlterRewriteFailed,
+    CopyOpenFileFailed,
+    CopyInvalidHeader,
+    CopyTooManyErrors,
+    CopyLinesRejected,
+    CopyFileExist,
+    CopyWriteFileFailed,
+    BackupFailed,
+    RestoreFailed,
+    QueryCancelled,
+    TableInUse,  // by a conflicting statement, until table_lock_timeout_ms
+    StatementTimeout,  // in a blocking wait of the storage, such as for a table lock
+    PagePoolExhausted,  // all the pages in the pool are pinned
+    InvalidInsertValuesNum,  // of the rows not checked by sem_check
+    FlushFailed,
+    ChecksumMismatch,  // a page read from the file is corrupted
+    CopyStdinFailed,  // the data of copy from stdin is missing or broken
+    TableReadOnly,  // after a write of its file failed, until a flush succeeds
+    WriteFailed,  // no page in the pool can be written to make room, like on a full disk
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecError {
+    pub error_type : ExecErrorType,
+    pub error_msg : String,
+}
+
+pub fn gen_store_error(err : StoreError) -> ExecError {
+    ExecError{
+        error_type : match err.error_type {
+            StoreErrorType::InvalidValuesNum => ExecErrorType::InvalidInsertValuesNum,
+            StoreErrorType::Corrupted => ExecErrorType::ChecksumMismatch,
+            StoreErrorType::EvictFailed => ExecErrorType::WriteFailed,
+            StoreErrorType::ReadOnly => ExecErrorType::TableReadOnly,
+            StoreErrorType::TupleNotFound => ExecErrorType::DeleteTupleNotFound,
+        },
+        error_msg : err.error_msg,
+    }
+}
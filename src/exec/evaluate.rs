@@ -1,149 +1,185 @@
-use std::ptr::read;
-use std::collections::HashMap;
-use ::parser::condition::{
-    ConditionExpr,
-    ArithExpr,
-    ArithOp,
-    CmpOperantExpr,
-    CmpOp,
-    CondRef,
-    LogicOp,
-};
-use ::parser::common::{ValueExpr, ValueType};
-use ::parser::attribute::AttributeExpr;
-use ::store::buffer::DataPtr;
-use ::store::table::AttrType;
-use ::utils::pointer::read_string;
-
-
-pub type PtrMap = HashMap<(String, String), (DataPtr, AttrType)>;
-
-
-pub fn eval_cond(condition : &ConditionExpr, ptr_map : &PtrMap) -> bool {
-    match condition {
-        &ConditionExpr::NotExpr{ ref operant } => !eval_cond(operant, ptr_map),
-        &ConditionExpr::CmpExpr{ ref lhs, ref rhs, op } =>
-            eval_cmp_operant(lhs, rhs, op, ptr_map),
-        &ConditionExpr::LogicExpr{ ref lhs, ref rhs, op } =>
-            eval_logic_op(lhs, rhs, op, ptr_map),
-    }
-}
-
-pub fn eval_logic_op(lhs : &CondRef, rhs : &CondRef, op : LogicOp, ptr_map : &PtrMap) -> bool {
-    let lresult = eval_cond(&**lhs, ptr_map);
-    let rresult = eval_cond(&**rhs, ptr_map);
-    match op {
-        LogicOp::Or => lresult || rresult,
-        LogicOp::And => lresult && rresult,
-    }
-}
-
-pub fn eval_cmp_operant(
-        lhs : &CmpOperantExpr,
-        rhs : &CmpOperantExpr,
-        op : CmpOp,
-        ptr_map : &PtrMap) -> bool {
-    match (lhs, rhs) {
-        (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Value(ref r)) => {
-            let lvalue = eval_str(l);
-            let rvalue = eval_str(r);
-            eval_str_cmp(&lvalue, &rvalue, op)
-        }
-        (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Arith(ref r)) => {
-            let lvalue = eval_str(l);
-            let rvalue = eval_str_attr(r, ptr_map);
-            eval_str_cmp(&lvalue, &rvalue, op)
-        }
-        (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Value(ref r)) => {
-            let lvalue = eval_str_attr(l, ptr_map);
-            let rvalue = eval_str(r);
-            eval_str_cmp(&lvalue, &rvalue, op)
-        }
-        (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Arith(ref r)) => {
-            let lvalue = eval_arith(l, ptr_map);
-            let rvalue = eval_arith(r, ptr_map);
-            match op {
-                CmpOp::LT => lvalue < rvalue,
-                CmpOp::GT => lvalue > rvalue,
-                CmpOp::LE => lvalue <= rvalue,
-                CmpOp::GE => lvalue >= rvalue,
-                CmpOp::EQ => lvalue == rvalue,
-                CmpOp::NE => lvalue != rvalue,
-                CmpOp::Is => unimplemented!(),
-                CmpOp::IsNot => unimplemented!(),
-            }
-        }
-    }
-}
-
-pub fn eval_str_cmp(lvalue : &String, rvalue : &String, op : CmpOp) -> bool {
-    match op {
-        CmpOp::LT | CmpOp::GT| CmpOp::LE| CmpOp::GE =>
-            panic!("invalid operationo for string"),
-        CmpOp::EQ => lvalue == rvalue,
-        CmpOp::NE => lvalue != rvalue,
-        CmpOp::Is => unimplemented!(),
-        CmpOp::IsNot => unimplemented!(),
-    }
-}
-
-pub fn eval_str_attr(expr : &ArithExpr, ptr_map : &PtrMap) -> String {
-    match expr {
-        &ArithExpr::Attr( ref attr_expr ) => {
-            let (table, attr) = match attr_expr {
-                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
-                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
-            };
-            assert!(table.is_some());
-            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
-            let len = extract!(t, AttrType::Char{len}, len);
-            unsafe{ read_string(p, len) }
-        }
-        _ => panic!("expected attribute, found {:?}", expr),
-    }
-}
-
-pub fn eval_arith(expr : &ArithExpr, ptr_map : &PtrMap) -> f32 {
-    match expr {
-        &ArithExpr::BinaryExpr{ ref lhs, ref rhs, op } => {
-            let l = eval_arith(lhs, ptr_map);
-            let r = eval_arith(rhs, ptr_map);
-            match op {
-                ArithOp::Add => l + r,
-                ArithOp::Sub => l - r,
-                ArithOp::Mul => l * r,
-                ArithOp::Div => l / r,
-                ArithOp::Mod => l % r,
-            }
-        }
-        &ArithExpr::MinusExpr{ ref operant } => - eval_arith(operant, ptr_map),
-        &ArithExpr::Value(ref v) => eval_num(v),
-        &ArithExpr::Attr( ref attr_expr ) => {
-            let (table, attr) = match attr_expr {
-                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
-                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
-            };
-            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
-            match t {
-                AttrType::Int => unsafe{ read::<i32>(p as *const i32) as f32 },
-                AttrType::Float => unsafe{ read::<f32>(p as *const f32) },
-                _ => panic!("invalid type {:?}", t),
-            }
-        }
-    }
-}
-
-pub fn eval_num(expr : &ValueExpr) -> f32 {
-    match expr.value_type {
-        ValueType::Integer => expr.value.parse::<i32>().unwrap() as f32,
-        ValueType::Float => expr.value.parse::<f32>().unwrap(),
-        t => panic!("invalid type {:?}", t),
-    }
-}
-
-pub fn eval_str(expr : &ValueExpr) -> String {
-    match expr.value_type {
-        ValueType::String => expr.value.clone(),
-        t => panic!("invalid type {:?}", t),
-    }
-}
+use std::collections::HashMap;
+use ::parser::condition::{
+    ConditionExpr,
+    ArithExpr,
+    ArithOp,
+    CmpOperantExpr,
+    CmpOp,
+    CondRef,
+    LogicOp,
+};
+use ::parser::common::{ValueExpr, Literal};
+use ::parser::attribute::AttributeExpr;
+use ::store::buffer::DataPtr;
+use ::store::table::AttrType;
+use ::utils::pointer::{read_string, canonical_str, read_i32_le, read_f32_le};
+
+
+pub type PtrMap = HashMap<(String, String), (DataPtr, AttrType)>;
+
+
+pub fn eval_cond(condition : &ConditionExpr, ptr_map : &PtrMap) -> bool {
+    match condition {
+        &ConditionExpr::NotExpr{ ref operant } => !eval_cond(operant, ptr_map),
+        &ConditionExpr::CmpExpr{ ref lhs, ref rhs, op } =>
+            eval_cmp_operant(lhs, rhs, op, ptr_map),
+        &ConditionExpr::LogicExpr{ ref lhs, ref rhs, op } =>
+            eval_logic_op(lhs, rhs, op, ptr_map),
+    }
+}
+
+pub fn eval_logic_op(lhs : &CondRef, rhs : &CondRef, op : LogicOp, ptr_map : &PtrMap) -> bool {
+    let lresult = eval_cond(&**lhs, ptr_map);
+    let rresult = eval_cond(&**rhs, ptr_map);
+    match op {
+        LogicOp::Or => lresult || rresult,
+        LogicOp::And => lresult && rresult,
+    }
+}
+
+pub fn eval_cmp_operant(
+        lhs : &CmpOperantExpr,
+        rhs : &CmpOperantExpr,
+        op : CmpOp,
+        ptr_map : &PtrMap) -> bool {
+    match (lhs, rhs) {
+        (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Value(ref r)) => {
+            let lvalue = eval_str(l);
+            let rvalue = eval_str(r);
+            eval_str_cmp(&lvalue, &rvalue, op)
+        }
+        (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Arith(ref r)) => {
+            let lvalue = eval_str(l);
+            let rvalue = eval_str_attr(r, ptr_map);
+            eval_str_cmp(&lvalue, &rvalue, op)
+        }
+        (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Value(ref r)) => {
+            let lvalue = eval_str_attr(l, ptr_map);
+            let rvalue = eval_str(r);
+            eval_str_cmp(&lvalue, &rvalue, op)
+        }
+        (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Arith(ref r)) => {
+            let lvalue = eval_arith(l, ptr_map);
+            let rvalue = eval_arith(r, ptr_map);
+            match op {
+                CmpOp::LT => lvalue < rvalue,
+                CmpOp::GT => lvalue > rvalue,
+                CmpOp::LE => lvalue <= rvalue,
+                CmpOp::GE => lvalue >= rvalue,
+                CmpOp::EQ => lvalue == rvalue,
+                CmpOp::NE => lvalue != rvalue,
+                CmpOp::Is => unimplemented!(),
+                CmpOp::IsNot => unimplemented!(),
+            }
+        }
+    }
+}
+
+// The value of a condition decided without any row, None if it depends on the attributes.
+// Only the comparisons the evaluator would not reject at runtime are folded.
+pub fn fold_cond(condition : &ConditionExpr) -> Option<bool> {
+    match condition {
+        &ConditionExpr::NotExpr{ ref operant } => fold_cond(operant).map(|result| !result),
+        &ConditionExpr::LogicExpr{ ref lhs, ref rhs, op } => {
+            match (op, fold_cond(lhs), fold_cond(rhs)) {
+                (LogicOp::And, Some(false), _) | (LogicOp::And, _, Some(false)) => Some(false),
+                (LogicOp::Or, Some(true), _) | (LogicOp::Or, _, Some(true)) => Some(true),
+                (_, Some(l), Some(_)) => Some(l),  // both true for and, both false for or
+                _ => None,
+            }
+        }
+        &ConditionExpr::CmpExpr{ ref lhs, ref rhs, op } => {
+            let foldable = match (lhs, rhs, op) {
+                (_, _, CmpOp::Is) | (_, _, CmpOp::IsNot) => false,
+                (&CmpOperantExpr::Value(..), &CmpOperantExpr::Value(..), CmpOp::EQ)
+                | (&CmpOperantExpr::Value(..), &CmpOperantExpr::Value(..), CmpOp::NE) => true,
+                (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Arith(ref r), _) =>
+                    !has_attr(l) && !has_attr(r),
+                _ => false,
+            };
+            if foldable { Some(eval_cmp_operant(lhs, rhs, op, &PtrMap::new())) } else { None }
+        }
+    }
+}
+
+fn has_attr(expr : &ArithExpr) -> bool {
+    match expr {
+        &ArithExpr::BinaryExpr{ ref lhs, ref rhs, .. } => has_attr(lhs) || has_attr(rhs),
+        &ArithExpr::MinusExpr{ ref operant } => has_attr(operant),
+        &ArithExpr::Value(..) => false,
+        &ArithExpr::Attr(..) => true,
+    }
+}
+
+pub fn eval_str_cmp(lvalue : &String, rvalue : &String, op : CmpOp) -> bool {
+    match op {
+        CmpOp::LT | CmpOp::GT| CmpOp::LE| CmpOp::GE =>
+            panic!("invalid operationo for string"),
+        // a literal is compared as it would be stored
+        CmpOp::EQ => canonical_str(lvalue) == canonical_str(rvalue),
+        CmpOp::NE => canonical_str(lvalue) != canonical_str(rvalue),
+        CmpOp::Is => unimplemented!(),
+        CmpOp::IsNot => unimplemented!(),
+    }
+}
+
+pub fn eval_str_attr(expr : &ArithExpr, ptr_map : &PtrMap) -> String {
+    match expr {
+        &ArithExpr::Attr( ref attr_expr ) => {
+            let (table, attr) = match attr_expr {
+                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
+                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
+            };
+            assert!(table.is_some());
+            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
+            let len = extract!(t, AttrType::Char{len}, len);
+            unsafe{ read_string(p, len) }
+        }
+        _ => panic!("expected attribute, found {:?}", expr),
+    }
+}
+
+pub fn eval_arith(expr : &ArithExpr, ptr_map : &PtrMap) -> f32 {
+    match expr {
+        &ArithExpr::BinaryExpr{ ref lhs, ref rhs, op } => {
+            let l = eval_arith(lhs, ptr_map);
+            let r = eval_arith(rhs, ptr_map);
+            match op {
+                ArithOp::Add => l + r,
+                ArithOp::Sub => l - r,
+                ArithOp::Mul => l * r,
+                ArithOp::Div => l / r,
+                ArithOp::Mod => l % r,
+            }
+        }
+        &ArithExpr::MinusExpr{ ref operant } => - eval_arith(operant, ptr_map),
+        &ArithExpr::Value(ref v) => eval_num(v),
+        &ArithExpr::Attr( ref attr_expr ) => {
+            let (table, attr) = match attr_expr {
+                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
+                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
+            };
+            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
+            match t {
+                AttrType::Int => unsafe{ read_i32_le(p) as f32 },
+                AttrType::Float => unsafe{ read_f32_le(p) },
+                _ => panic!("invalid type {:?}", t),
+            }
+        }
+    }
+}
+
+pub fn eval_num(expr : &ValueExpr) -> f32 {
+    match expr.literal() {
+        &Literal::Int(n) => n as f32,
+        &Literal::Float(f) => f,
+        l => panic!("invalid literal {:?}", l),
+    }
+}
+
+pub fn eval_str(expr : &ValueExpr) -> String {
+    match expr.literal() {
+        &Literal::Str(ref s) => s.clone(),
+        l => panic!("invalid literal {:?}", l),
+    }
+}
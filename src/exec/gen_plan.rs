@@ -1,224 +1,462 @@
-use std::vec::Vec;
-use std::boxed::Box;
-use std::collections::HashMap;
-use ::parser::common::{Statement, ValueExpr, ValueType};
-use ::parser::select::{Relation, SelectExpr};
-use ::parser::attribute::AttributeExpr;
-use ::parser::condition::gen_check_primary_key_condition_expr;
-use ::parser::{
-    SelectStatement,
-    InsertStatement,
-    UpdateStatement,
-    DeleteStatement,
-    CreateStatement,
-    DropStatement,
-};
-use ::store::table::{TableSet, TableManagerRef, TableRef};
-use ::store::tuple::TupleValue; 
-use super::iter::ExecIterRef;
-use super::create_drop::{CreateTable, DropTable};
-use super::change::{Insert, CheckAndInsert, Update, Delete};
-use super::query::{FileScan, Filter, Projection};
-
-
-pub fn gen_plan(stmt : Statement, table_manager : &TableManagerRef)
-        -> ExecIterRef {
-    match stmt {
-        Statement::Create(create) => gen_create_plan(create, table_manager),
-        Statement::Drop(drop) => gen_drop_plan(drop, table_manager),
-        Statement::Insert(insert) => gen_insert_plan(insert, table_manager),
-        Statement::Update(update) => gen_update_plan(update, table_manager),
-        Statement::Delete(delete) => gen_delete_plan(delete, table_manager),
-        Statement::Select(select) => gen_select_plan(select, table_manager),
-    }
-}
-
-pub fn gen_create_plan(stmt : CreateStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    CreateTable::new(stmt, table_manager)
-}
-
-pub fn gen_drop_plan(stmt : DropStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    DropTable::new(stmt, table_manager)
-}
-
-pub fn gen_select_plan(stmt : SelectStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    // join and sub query not supported now
-    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
-    let table = table_manager.borrow().get_table(&table_name).unwrap();
-    let mut query = FileScan::new(&table_name, table_manager);
-    let (attr_index, proj_attr_list) = gen_select_proj_info(&stmt, &table);
-    let need_proj = is_match!(stmt.select_expr, SelectExpr::AttrList(..));
-    if let Some(cond) = stmt.where_condition {
-        query = Filter::new(Box::new(cond),
-            table.borrow().gen_index_map(),
-            table.borrow().gen_tuple_desc(), query);
-    }
-    if need_proj {
-        query = Projection::new(attr_index, proj_attr_list, query);
-    }
-    query
-}
-
-pub fn gen_select_proj_info(
-        stmt : &SelectStatement, table : &TableRef) -> (Vec<usize>, Vec<(String, String)>) {
-    let table = table.borrow();
-    let mut proj_attr_index = Vec::new();
-    let mut proj_attr_list = Vec::new();
-    let mut table_and_attr_list = match stmt.select_expr {
-        SelectExpr::AttrList(ref l) => {
-            let mut table_and_attr_list = Vec::new();
-            for attr in l {
-                let table_and_attr = extract!(attr, &AttributeExpr::TableAttr{ref table, ref attr},
-                    (table.clone().unwrap(), attr.clone()));
-                table_and_attr_list.push(table_and_attr);
-            }
-            table_and_attr_list
-        }
-        SelectExpr::AllAttribute => {
-            table.get_attr_name_list().iter().map(|a| (table.name.clone(), a.clone())).collect()
-        }
-    };
-    let index_map = table.gen_index_map();
-    for table_and_attr in table_and_attr_list.drain(..) {
-        proj_attr_index.push(index_map.get(&table_and_attr).unwrap().clone());
-        proj_attr_list.push(table_and_attr);
-    }
-    (proj_attr_index, proj_attr_list)
-}
-
-pub fn gen_proj_info(
-        stmt : &Statement, table_manager : &TableManagerRef) -> (Vec<usize>, Vec<(String, String)>) {
-    let mut proj_attr_index = Vec::new();
-    let mut proj_attr_list = Vec::new();
-    let table = get_stmt_table(stmt, table_manager);
-    if let &Statement::Select(ref select) = stmt {
-        return gen_select_proj_info(select, &table);
-    } else {
-        let table = table.borrow();
-        let table_name = table.name.clone();
-        for (i, attr) in table.attr_list.iter().enumerate() {
-            proj_attr_list.push((table_name.clone(), attr.name.clone()));
-            proj_attr_index.push(i);
-        }
-    }
-    (proj_attr_index, proj_attr_list)
-}
-
-pub fn get_stmt_table(stmt : &Statement, table_manager : &TableManagerRef) -> TableRef {
-    match stmt {
-        &Statement::Create(..) | &Statement::Drop(..) => panic!("invalid state"),
-        &Statement::Insert(ref insert) => table_manager.borrow().get_table(&insert.table).unwrap(),
-        &Statement::Update(ref update) => table_manager.borrow().get_table(&update.table).unwrap(),
-        &Statement::Delete(ref delete) => table_manager.borrow().get_table(&delete.table).unwrap(),
-        &Statement::Select(ref select) => {
-            let table_name = extract!(
-                select.relation_list[0], Relation::TableName(ref name), name);
-            table_manager.borrow().get_table(&table_name).unwrap()
-        }
-    }
-}
-
-pub fn gen_delete_plan(stmt : DeleteStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
-    let mut data_source = FileScan::new(&stmt.table, table_manager);
-    if let Some(cond) = stmt.where_condition {
-        data_source = Filter::new(Box::new(cond),
-            table.borrow().gen_index_map(),
-            table.borrow().gen_tuple_desc(), data_source);
-    }
-    Delete::new(&stmt.table, data_source, table_manager)
-}
-
-pub fn gen_insert_plan(stmt : InsertStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
-    let pk_index = table.borrow().get_primary_key_index();
-    let pk = stmt.value_list[pk_index].value.parse::<i32>().unwrap();
-    let check = gen_check_primary_key_exist_plan(pk, &stmt.table, table_manager);
-    CheckAndInsert::new(check, Insert::new(stmt, table_manager))
-}
-
-pub fn gen_update_plan(stmt : UpdateStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
-    let mut data_source = FileScan::new(&stmt.table, table_manager);
-    if let Some(cond) = stmt.where_condition {
-        data_source = Filter::new(Box::new(cond),
-            table.borrow().gen_index_map(),
-            table.borrow().gen_tuple_desc(), data_source);
-    }
-    let mut set_values = HashMap::new();
-    let index_map = table.borrow().gen_index_map();
-    for assign in stmt.set_list.iter() {
-        let attr = &assign.attr;
-        let value = &assign.value;
-        let index = index_map.get(&(stmt.table.clone(), attr.clone())).unwrap();
-        let tuple_value = value_expr_to_tuple_value(value);
-        set_values.insert(*index, tuple_value);
-    }
-    let tuple_desc = table.borrow().gen_tuple_desc();
-    Update::new(&stmt.table, tuple_desc, set_values, data_source, table_manager)
-}
-
-pub fn value_expr_to_tuple_value(expr : &ValueExpr) -> TupleValue {
-    match expr.value_type {
-        ValueType::Integer => TupleValue::Int(expr.value.parse::<i32>().unwrap()),
-        ValueType::Float => TupleValue::Float(expr.value.parse::<f32>().unwrap()),
-        ValueType::String => TupleValue::Char(expr.value.clone()),
-        ValueType::Null => unimplemented!(),
-    }
-}
-
-pub fn gen_check_primary_key_exist_plan(
-        pk : i32,
-        table_name : &String,
-        table_manager : &TableManagerRef) -> ExecIterRef {
-    let table = table_manager.borrow().get_table(table_name).unwrap();
-    let pk_attr = table.borrow().get_primary_key_attr();
-    let cond = gen_check_primary_key_condition_expr(table_name, &pk_attr.name, pk);
-    let scan = FileScan::new(table_name, table_manager);
-    let filter = Filter::new(Box::new(cond),
-        table.borrow().gen_index_map(),
-        table.borrow().gen_tuple_desc(), scan);
-    filter
-}
-
-pub fn gen_table_set(stmt : &Statement, table_manager : &TableManagerRef) -> TableSet {
-    let mut table_list = Vec::new();
-    match stmt {
-        &Statement::Select(ref select) => {
-            let mut tables = gen_select_table_set_helper(select);
-            for name in tables.drain(..) {
-                table_list.push(name);
-            }
-        }
-        &Statement::Delete(ref delete) =>
-            { table_list.push(delete.table.clone()); }
-        &Statement::Update(ref update) =>
-            { table_list.push(update.table.clone()); }
-        &Statement::Insert(ref insert) =>
-            { table_list.push(insert.table.clone()); }
-        &Statement::Create(ref create) => {
-            if let Some(..) = table_manager.borrow().get_table(&create.table) {
-                table_list.push(create.table.clone());
-            }
-        }
-        &Statement::Drop(ref drop) => {
-            if let Some(..) = table_manager.borrow().get_table(&drop.table) {
-                table_list.push(drop.table.clone());
-            }
-        }
-
-    }
-    table_manager.borrow().gen_table_set(&table_list)
-}
-
-fn gen_select_table_set_helper(stmt : &SelectStatement) -> Vec<String> {
-    let mut result = Vec::new();
-    for rel in &stmt.relation_list {
-        match rel {
-            &Relation::TableName(ref name) => result.push(name.clone()),
-            &Relation::Select(ref sub_select) =>
-                result.extend_from_slice(&gen_select_table_set_helper(sub_select))
-        }
-    }
-    result
-}
+use std::vec::Vec;
+use std::boxed::Box;
+use std::collections::HashMap;
+use ::parser::common::{Statement, ValueExpr, Literal, ToSql};
+use ::parser::select::{Relation, SelectExpr};
+use ::parser::attribute::AttributeExpr;
+use ::parser::condition::{ConditionExpr, CmpOperantExpr, ArithExpr, CmpOp, LogicOp};
+use ::parser::condition::gen_check_primary_key_condition_expr;
+use ::parser::{
+    SelectStatement,
+    InsertStatement,
+    UpdateStatement,
+    DeleteStatement,
+    CreateStatement,
+    DropStatement,
+    AlterStatement,
+    CopyFromStatement,
+    CopyToStatement,
+    BackupStatement,
+    RestoreStatement,
+    AnalyzeStatement,
+};
+use ::store::table::{Table, TableSet, TableManagerRef, TableRef};
+use ::store::tuple::TupleValue; 
+use ::utils::array::projection;
+use super::iter::ExecIterRef;
+use super::error::{ExecError, ExecErrorType};
+use super::create_drop::{CreateTable, DropTable};
+use super::alter::AlterTable;
+use super::change::{Insert, CheckAndInsert, Update, Delete};
+use super::copy::{CopyFrom, CopyTo};
+use super::backup::{Backup, Restore};
+use super::analyze::Analyze;
+use super::query::{FileScan, Filter, Projection, EmptySource};
+use super::evaluate::fold_cond;
+use super::generated::GeneratedColumns;
+
+
+pub fn gen_plan(stmt : Statement, table_manager : &TableManagerRef)
+        -> ExecIterRef {
+    let plan = match stmt {
+        Statement::Create(create) => gen_create_plan(create, table_manager),
+        Statement::Drop(drop) => gen_drop_plan(drop, table_manager),
+        Statement::Alter(alter) => gen_alter_plan(alter, table_manager),
+        Statement::Insert(insert) => gen_insert_plan(insert, table_manager),
+        Statement::Update(update) => gen_update_plan(update, table_manager),
+        Statement::Delete(delete) => gen_delete_plan(delete, table_manager),
+        Statement::Select(select) => gen_select_plan(select, table_manager),
+        Statement::CopyFrom(copy) => gen_copy_from_plan(copy, table_manager),
+        Statement::CopyTo(copy) => gen_copy_to_plan(copy, table_manager),
+        Statement::Backup(backup) => gen_backup_plan(backup, table_manager),
+        Statement::Restore(restore) => gen_restore_plan(restore, table_manager),
+        Statement::Analyze(analyze) => gen_analyze_plan(analyze, table_manager),
+    };
+    log_debug!("plan: {}", plan.explain());
+    plan
+}
+
+// Fails before the plan is opened when the pool can't hold the pages it needs at the same time,
+// rather than in the middle of it with a part of the changes done.
+pub fn check_plan_pages(plan : &ExecIterRef, table_manager : &TableManagerRef) -> Result<(), ExecError> {
+    let needed = plan.get_min_pages();
+    let table_manager = table_manager.borrow();
+    // the pages pinned by the plans outside the statement, such as of an embedding program
+    let unpinned = table_manager.file_manager.get_unpinned_num();
+    if needed <= unpinned {
+        return Ok(());
+    }
+    Err(ExecError{
+        error_type : ExecErrorType::PagePoolExhausted,
+        error_msg : format!("query needs at least {} buffer pages, pool has {} of {} unpinned",
+            needed, unpinned, table_manager.file_manager.page_pool.get_capacity()),
+    })
+}
+
+pub fn gen_create_plan(stmt : CreateStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    CreateTable::new(stmt, table_manager)
+}
+
+pub fn gen_drop_plan(stmt : DropStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    DropTable::new(stmt, table_manager)
+}
+
+pub fn gen_alter_plan(stmt : AlterStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    AlterTable::new(stmt, table_manager)
+}
+
+pub fn gen_copy_from_plan(stmt : CopyFromStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    CopyFrom::new(stmt, table_manager)
+}
+
+pub fn gen_copy_to_plan(stmt : CopyToStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    let table_name = extract!(&stmt.select.relation_list[0], &Relation::TableName(ref name), name.clone());
+    let table = table_manager.borrow().get_table(&table_name).unwrap();
+    let (attr_index, proj_attr_list) = gen_select_proj_info(&stmt.select, &table);
+    let attr_desc = projection(&attr_index, table.borrow().gen_tuple_desc().attr_desc);
+    let header = gen_column_names(&proj_attr_list);
+    let CopyToStatement{ select, path, options } = stmt;
+    // the source yields the projected tuples, just like a select statement
+    let data_source = gen_select_plan(select, table_manager);
+    CopyTo::new(path, options, attr_desc, header, data_source)
+}
+
+pub fn gen_backup_plan(stmt : BackupStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    Backup::new(stmt, table_manager)
+}
+
+pub fn gen_restore_plan(stmt : RestoreStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    Restore::new(stmt, table_manager)
+}
+
+pub fn gen_analyze_plan(stmt : AnalyzeStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    Analyze::new(stmt, table_manager)
+}
+
+pub fn gen_select_plan(stmt : SelectStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    // join and sub query not supported now
+    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
+    let table = table_manager.borrow().get_table(&table_name).unwrap();
+    let (attr_index, proj_attr_list) = gen_select_proj_info(&stmt, &table);
+    // all the attributes are already in the order of the table
+    let need_proj = is_match!(stmt.select_expr, SelectExpr::AttrList(..));
+    let mut query = match stmt.where_condition {
+        // the projection is kept for the explain, the columns of the result come from the statement
+        Some(ref cond) if fold_cond(cond) == Some(false) =>
+            EmptySource::new(format!("{} is always false", cond.to_sql())),
+        Some(cond) => {
+            let estimated_row_num = estimate_row_num(&cond, &table.borrow());
+            Filter::with_estimate(Box::new(cond),
+                table.borrow().gen_index_map(),
+                table.borrow().gen_tuple_desc(), FileScan::new(&table_name, table_manager), estimated_row_num)
+        }
+        None => FileScan::new(&table_name, table_manager),
+    };
+    if need_proj {
+        query = Projection::new(attr_index, proj_attr_list, query);
+    }
+    query
+}
+
+// the guesses for a comparison which the stats can't tell
+const DEFAULT_EQ_SELECTIVITY : f64 = 0.1;
+const DEFAULT_RANGE_SELECTIVITY : f64 = 1.0 / 3.0;
+
+// the estimated number of the rows passing cond, None if the table is not analyzed
+pub fn estimate_row_num(cond : &ConditionExpr, table : &Table) -> Option<usize> {
+    table.stats.as_ref().map(|stats|
+        (stats.row_num as f64 * estimate_selectivity(cond, table)).round() as usize)
+}
+
+// the estimated fraction of the rows passing cond, the operants of and and or are assumed independent
+pub fn estimate_selectivity(cond : &ConditionExpr, table : &Table) -> f64 {
+    match cond {
+        &ConditionExpr::LogicExpr{ref lhs, ref rhs, op} => {
+            let (l, r) = (estimate_selectivity(lhs, table), estimate_selectivity(rhs, table));
+            match op {
+                LogicOp::And => l * r,
+                LogicOp::Or => l + r - l * r,
+            }
+        }
+        &ConditionExpr::NotExpr{ref operant} => 1.0 - estimate_selectivity(operant, table),
+        &ConditionExpr::CmpExpr{ref lhs, ref rhs, op} => {
+            match (get_cmp_attr(lhs), get_cmp_attr(rhs)) {
+                (Some(attr), None) => estimate_cmp_selectivity(table, attr, op, rhs),
+                (None, Some(attr)) => estimate_cmp_selectivity(table, attr, swap_cmp_op(op), lhs),
+                _ => default_cmp_selectivity(op),
+            }
+        }
+    }
+}
+
+// a comparison of the column attr with the literal other, by the histogram for a range
+fn estimate_cmp_selectivity(table : &Table, attr : &String, op : CmpOp, other : &CmpOperantExpr) -> f64 {
+    let column = table.stats.as_ref().and_then(|stats| stats.columns.iter().find(|c| &c.name == attr));
+    let column = match column {
+        Some(column) if is_cmp_literal(other) => column,
+        _ => return default_cmp_selectivity(op),
+    };
+    let number = get_cmp_number(other);
+    match op {
+        CmpOp::EQ => column.estimate_fraction_equal(number),
+        CmpOp::NE => 1.0 - column.estimate_fraction_equal(number),
+        CmpOp::LT | CmpOp::LE | CmpOp::GT | CmpOp::GE => {
+            // `> v` is the rest of `<= v`, and `>= v` of `< v`
+            let or_equal = op == CmpOp::LE || op == CmpOp::GT;
+            match number.and_then(|v| column.estimate_fraction_below(v, or_equal)) {
+                Some(f) if op == CmpOp::LT || op == CmpOp::LE => f,
+                Some(f) => 1.0 - f,
+                None => default_cmp_selectivity(op),
+            }
+        }
+        CmpOp::Is | CmpOp::IsNot => default_cmp_selectivity(op),
+    }
+}
+
+fn default_cmp_selectivity(op : CmpOp) -> f64 {
+    match op {
+        CmpOp::EQ | CmpOp::Is => DEFAULT_EQ_SELECTIVITY,
+        CmpOp::NE | CmpOp::IsNot => 1.0 - DEFAULT_EQ_SELECTIVITY,
+        CmpOp::LT | CmpOp::LE | CmpOp::GT | CmpOp::GE => DEFAULT_RANGE_SELECTIVITY,
+    }
+}
+
+// the same comparison with the operants swapped, `v < a` is `a > v`
+fn swap_cmp_op(op : CmpOp) -> CmpOp {
+    match op {
+        CmpOp::LT => CmpOp::GT,
+        CmpOp::GT => CmpOp::LT,
+        CmpOp::LE => CmpOp::GE,
+        CmpOp::GE => CmpOp::LE,
+        other => other,
+    }
+}
+
+fn get_cmp_attr(operant : &CmpOperantExpr) -> Option<&String> {
+    match operant {
+        &CmpOperantExpr::Arith(ArithExpr::Attr(AttributeExpr::TableAttr{ref attr, ..})) => Some(attr),
+        _ => None,
+    }
+}
+
+fn is_cmp_literal(operant : &CmpOperantExpr) -> bool {
+    match operant {
+        &CmpOperantExpr::Value(ref value) => is_match!(value.literal, Some(Literal::Str(..))),
+        &CmpOperantExpr::Arith(ref arith) => get_arith_number(arith).is_some(),
+    }
+}
+
+fn get_cmp_number(operant : &CmpOperantExpr) -> Option<f64> {
+    match operant {
+        &CmpOperantExpr::Arith(ref arith) => get_arith_number(arith),
+        &CmpOperantExpr::Value(..) => None,
+    }
+}
+
+fn get_arith_number(arith : &ArithExpr) -> Option<f64> {
+    match arith {
+        &ArithExpr::Value(ref value) => match value.literal {
+            Some(Literal::Int(n)) => Some(n as f64),
+            Some(Literal::Float(f)) => Some(f as f64),
+            _ => None,
+        },
+        &ArithExpr::MinusExpr{ref operant} => get_arith_number(operant).map(|n| -n),
+        _ => None,
+    }
+}
+
+pub fn gen_select_proj_info(
+        stmt : &SelectStatement, table : &TableRef) -> (Vec<usize>, Vec<(String, String)>) {
+    let table = table.borrow();
+    let mut proj_attr_index = Vec::new();
+    let mut proj_attr_list = Vec::new();
+    let mut table_and_attr_list = expand_select_expr(&stmt.select_expr, &table);
+    let index_map = table.gen_index_map();
+    for table_and_attr in table_and_attr_list.drain(..) {
+        proj_attr_index.push(index_map.get(&table_and_attr).unwrap().clone());
+        proj_attr_list.push(table_and_attr);
+    }
+    (proj_attr_index, proj_attr_list)
+}
+
+// the (table, attr) of the result columns, `*` and `table.*` are all the attributes in the declaration order
+pub fn expand_select_expr(select_expr : &SelectExpr, table : &Table) -> Vec<(String, String)> {
+    match select_expr {
+        &SelectExpr::AttrList(ref l) => {
+            let mut table_and_attr_list = Vec::new();
+            for attr in l {
+                let table_and_attr = extract!(attr, &AttributeExpr::TableAttr{ref table, ref attr},
+                    (table.clone().unwrap(), attr.clone()));
+                table_and_attr_list.push(table_and_attr);
+            }
+            table_and_attr_list
+        }
+        &SelectExpr::AllAttribute | &SelectExpr::TableAllAttribute(..) => {
+            // join not supported now, so table.* is the only table
+            table.get_attr_name_list().iter().map(|a| (table.name.clone(), a.clone())).collect()
+        }
+    }
+}
+
+// the names of the result columns, qualified by the table only when the columns come from more than one
+pub fn gen_column_names(proj_attr_list : &Vec<(String, String)>) -> Vec<String> {
+    let qualified = proj_attr_list.iter().any(|&(ref table, _)| *table != proj_attr_list[0].0);
+    proj_attr_list.iter().map(|&(ref table, ref attr)| {
+        if qualified { format!("{}.{}", table, attr) } else { attr.clone() }
+    }).collect()
+}
+
+pub fn gen_proj_info(
+        stmt : &Statement, table_manager : &TableManagerRef) -> (Vec<usize>, Vec<(String, String)>) {
+    let mut proj_attr_index = Vec::new();
+    let mut proj_attr_list = Vec::new();
+    let table = get_stmt_table(stmt, table_manager);
+    if let &Statement::Select(ref select) = stmt {
+        return gen_select_proj_info(select, &table);
+    } else {
+        let table = table.borrow();
+        let table_name = table.name.clone();
+        for (i, attr) in table.attr_list.iter().enumerate() {
+            proj_attr_list.push((table_name.clone(), attr.name.clone()));
+            proj_attr_index.push(i);
+        }
+    }
+    (proj_attr_index, proj_attr_list)
+}
+
+pub fn get_stmt_table(stmt : &Statement, table_manager : &TableManagerRef) -> TableRef {
+    match stmt {
+        &Statement::Create(..) | &Statement::Drop(..) | &Statement::Alter(..) | &Statement::Analyze(..)
+            | &Statement::Backup(..) | &Statement::Restore(..) => panic!("invalid state"),
+        &Statement::Insert(ref insert) => table_manager.borrow().get_table(&insert.table).unwrap(),
+        &Statement::Update(ref update) => table_manager.borrow().get_table(&update.table).unwrap(),
+        &Statement::Delete(ref delete) => table_manager.borrow().get_table(&delete.table).unwrap(),
+        &Statement::CopyFrom(ref copy) => table_manager.borrow().get_table(&copy.table).unwrap(),
+        &Statement::CopyTo(ref copy) => {
+            let table_name = extract!(
+                copy.select.relation_list[0], Relation::TableName(ref name), name);
+            table_manager.borrow().get_table(&table_name).unwrap()
+        }
+        &Statement::Select(ref select) => {
+            let table_name = extract!(
+                select.relation_list[0], Relation::TableName(ref name), name);
+            table_manager.borrow().get_table(&table_name).unwrap()
+        }
+    }
+}
+
+pub fn gen_delete_plan(stmt : DeleteStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
+    let mut data_source = FileScan::new(&stmt.table, table_manager);
+    if let Some(cond) = stmt.where_condition {
+        data_source = Filter::new(Box::new(cond),
+            table.borrow().gen_index_map(),
+            table.borrow().gen_tuple_desc(), data_source);
+    }
+    Delete::new(&stmt.table, data_source, table_manager)
+}
+
+pub fn gen_insert_plan(mut stmt : InsertStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
+    let generated = GeneratedColumns::new(&table.borrow());
+    if !generated.is_empty() {
+        stmt.value_list = generated.expand_values(stmt.value_list);
+    }
+    let pk_index = table.borrow().get_primary_key_index();
+    let pk = extract!(stmt.value_list[pk_index].literal(), &Literal::Int(pk), pk);
+    let check = gen_check_primary_key_exist_plan(pk, &stmt.table, table_manager);
+    CheckAndInsert::new(check, Insert::new(stmt, table_manager))
+}
+
+pub fn gen_update_plan(stmt : UpdateStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    let table = table_manager.borrow().get_table(&stmt.table).unwrap();
+    let mut data_source = FileScan::new(&stmt.table, table_manager);
+    if let Some(cond) = stmt.where_condition {
+        data_source = Filter::new(Box::new(cond),
+            table.borrow().gen_index_map(),
+            table.borrow().gen_tuple_desc(), data_source);
+    }
+    let mut set_values = HashMap::new();
+    let index_map = table.borrow().gen_index_map();
+    for assign in stmt.set_list.iter() {
+        let attr = &assign.attr;
+        let value = &assign.value;
+        let index = index_map.get(&(stmt.table.clone(), attr.clone())).unwrap();
+        let tuple_value = value_expr_to_tuple_value(value);
+        set_values.insert(*index, tuple_value);
+    }
+    let tuple_desc = table.borrow().gen_tuple_desc();
+    let generated = GeneratedColumns::new(&table.borrow());
+    let generated = if generated.is_empty() { None } else { Some(generated) };
+    Update::with_generated(&stmt.table, tuple_desc, set_values, generated, data_source, table_manager)
+}
+
+pub fn value_expr_to_tuple_value(expr : &ValueExpr) -> TupleValue {
+    match expr.literal() {
+        &Literal::Int(n) => TupleValue::Int(n),
+        &Literal::Float(f) => TupleValue::Float(f),
+        &Literal::Str(ref s) => TupleValue::Char(s.clone()),
+        &Literal::Null => TupleValue::Null,
+    }
+}
+
+pub fn gen_check_primary_key_exist_plan(
+        pk : i32,
+        table_name : &String,
+        table_manager : &TableManagerRef) -> ExecIterRef {
+    let table = table_manager.borrow().get_table(table_name).unwrap();
+    let pk_attr = table.borrow().get_primary_key_attr();
+    let cond = gen_check_primary_key_condition_expr(table_name, &pk_attr.name, pk);
+    let scan = FileScan::new(table_name, table_manager);
+    let filter = Filter::new(Box::new(cond),
+        table.borrow().gen_index_map(),
+        table.borrow().gen_tuple_desc(), scan);
+    filter
+}
+
+pub fn gen_table_set(stmt : &Statement, table_manager : &TableManagerRef) -> TableSet {
+    let mut table_list = Vec::new();
+    match stmt {
+        &Statement::Select(ref select) => {
+            let mut tables = gen_select_table_set_helper(select);
+            for name in tables.drain(..) {
+                table_list.push(name);
+            }
+        }
+        &Statement::Delete(ref delete) =>
+            { table_list.push(delete.table.clone()); }
+        &Statement::Update(ref update) =>
+            { table_list.push(update.table.clone()); }
+        &Statement::Insert(ref insert) =>
+            { table_list.push(insert.table.clone()); }
+        &Statement::CopyFrom(ref copy) =>
+            { table_list.push(copy.table.clone()); }
+        &Statement::CopyTo(ref copy) => {
+            let mut tables = gen_select_table_set_helper(&copy.select);
+            for name in tables.drain(..) {
+                table_list.push(name);
+            }
+        }
+        &Statement::Create(ref create) => {
+            if let Some(..) = table_manager.borrow().get_table(&create.table) {
+                table_list.push(create.table.clone());
+            }
+        }
+        &Statement::Drop(ref drop) => {
+            if let Some(..) = table_manager.borrow().get_table(&drop.table) {
+                table_list.push(drop.table.clone());
+            }
+        }
+        &Statement::Alter(ref alter) => {
+            if let Some(..) = table_manager.borrow().get_table(&alter.table) {
+                table_list.push(alter.table.clone());
+            }
+        }
+        &Statement::Analyze(ref analyze) =>
+            { table_list.push(analyze.table.clone()); }
+        &Statement::Backup(..) | &Statement::Restore(..) => (),  // all the tables
+
+    }
+    table_manager.borrow().gen_table_set(&table_list)
+}
+
+fn gen_select_table_set_helper(stmt : &SelectStatement) -> Vec<String> {
+    let mut result = Vec::new();
+    // a with table hides the table of the same name only after it
+    let mut with_names : Vec<String> = Vec::new();
+    for with_table in &stmt.with_list {
+        let tables = gen_select_table_set_helper(&with_table.select);
+        result.extend(tables.into_iter().filter(|name| !with_names.contains(name)));
+        with_names.push(with_table.name.clone());
+    }
+    for rel in &stmt.relation_list {
+        match rel {
+            &Relation::TableName(ref name) => if !with_names.contains(name) {
+                result.push(name.clone());
+            },
+            &Relation::Select(ref sub_select) =>
+                result.extend_from_slice(&gen_select_table_set_helper(sub_select))
+        }
+    }
+    result
+}
@@ -0,0 +1,119 @@
+use ::parser::common::{ValueExpr, ValueList, Literal};
+use ::parser::condition::ArithExpr;
+use ::parser::attribute::AttributeExpr;
+use ::parser::create_drop::parse_generated_expr;
+use ::store::table::{Table, AttrType, IndexMap};
+use ::store::tuple::{TupleData, TupleDesc};
+use ::store::buffer::DataPtr;
+use ::utils::pointer::{write_i32_le, write_f32_le};
+use super::evaluate::{PtrMap, eval_arith};
+
+
+// The generated attributes of a table, computed in f32 like the other arithmetic
+// and truncated for an int attribute. A null attribute counts as 0 since it's stored as zero bytes.
+#[derive(Debug)]
+pub struct GeneratedColumns {
+    columns : Vec<(usize, ArithExpr)>,
+    index_map : IndexMap,
+    tuple_desc : TupleDesc,
+}
+
+impl GeneratedColumns {
+    pub fn new(table : &Table) -> GeneratedColumns {
+        let mut columns = Vec::new();
+        for (i, attr) in table.attr_list.iter().enumerate() {
+            if let Some(ref sql) = attr.generated {
+                // checked when the table is created
+                let mut expr = check_ok!(parse_generated_expr(sql));
+                qualify_attrs(&mut expr, &table.name);
+                columns.push((i, expr));
+            }
+        }
+        GeneratedColumns{
+            columns : columns,
+            index_map : table.gen_index_map(),
+            tuple_desc : table.gen_tuple_desc(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    // recompute the generated attributes of a tuple in place, after the others are written
+    pub fn fill_tuple(&self, tuple_data : &TupleData) {
+        let mut ptr_map = PtrMap::new();
+        for (k, index) in &self.index_map {
+            ptr_map.insert(k.clone(), (tuple_data[*index], self.tuple_desc.attr_desc[*index].clone()));
+        }
+        for &(i, ref expr) in self.columns.iter() {
+            let num = eval_arith(expr, &ptr_map);
+            unsafe {
+                match self.tuple_desc.attr_desc[i] {
+                    AttrType::Int => write_i32_le(tuple_data[i], num as i32),
+                    AttrType::Float => write_f32_le(tuple_data[i], num),
+                    AttrType::Char{..} => panic!("generated attribute should be a number"),
+                }
+            }
+        }
+    }
+
+    // the values of an insert only have the attributes not generated
+    pub fn expand_values(&self, values : ValueList) -> ValueList {
+        let mut values = values.into_iter();
+        let mut value_list = ValueList::new();
+        for i in 0..self.tuple_desc.attr_desc.len() {
+            if self.columns.iter().any(|&(index, _)| index == i) {
+                value_list.push(ValueExpr::from_literal(Literal::Null));
+            } else {
+                value_list.push(values.next().unwrap());
+            }
+        }
+        self.fill_values(&mut value_list);
+        value_list
+    }
+
+    // overwrite the generated attributes of a whole row
+    pub fn fill_values(&self, value_list : &mut ValueList) {
+        if self.is_empty() {
+            return;
+        }
+        // the number attributes are written to the scratch buffers for eval_arith
+        let mut buffers = vec![[0u8; 4]; value_list.len()];
+        let mut ptr_map = PtrMap::new();
+        for (k, index) in &self.index_map {
+            let attr_type = self.tuple_desc.attr_desc[*index].clone();
+            let p = buffers[*index].as_mut_ptr() as DataPtr;
+            unsafe {
+                match (value_list[*index].literal(), attr_type) {
+                    (&Literal::Int(n), AttrType::Int) => write_i32_le(p, n),
+                    (&Literal::Int(n), AttrType::Float) => write_f32_le(p, n as f32),
+                    (&Literal::Float(f), AttrType::Float) => write_f32_le(p, f),
+                    // a char attribute is never used by a generated one
+                    _ => (),
+                }
+            }
+            ptr_map.insert(k.clone(), (p, attr_type));
+        }
+        for &(i, ref expr) in self.columns.iter() {
+            let num = eval_arith(expr, &ptr_map);
+            let literal = match self.tuple_desc.attr_desc[i] {
+                AttrType::Int => Literal::Int(num as i32),
+                _ => Literal::Float(num),
+            };
+            value_list[i] = ValueExpr::from_literal(literal);
+        }
+    }
+}
+
+fn qualify_attrs(expr : &mut ArithExpr, table : &String) {
+    match expr {
+        &mut ArithExpr::BinaryExpr{ref mut lhs, ref mut rhs, ..} => {
+            qualify_attrs(lhs, table);
+            qualify_attrs(rhs, table);
+        }
+        &mut ArithExpr::MinusExpr{ref mut operant} => qualify_attrs(operant, table),
+        &mut ArithExpr::Value(..) => (),
+        &mut ArithExpr::Attr(AttributeExpr::TableAttr{table : ref mut t, ..}) => *t = Some(table.clone()),
+        &mut ArithExpr::Attr(..) => panic!("generated attribute should not use aggregate function"),
+    }
+}
@@ -0,0 +1,114 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::mem::replace;
+use std::time::{Duration, Instant};
+use ::store::tuple::TupleData;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::ExecError;
+
+
+// the time includes the children, which are instrumented separately
+#[derive(Debug, Clone, Default)]
+pub struct OperatorStats {
+    pub calls : usize,  // of get_next
+    pub rows : usize,
+    pub elapsed : Duration,  // in open, get_next and close
+}
+
+pub type OperatorStatsRef = Rc<RefCell<OperatorStats>>;
+
+// an operator of the instrumented plan, in the order of the explain tree
+#[derive(Debug)]
+pub struct OperatorNode {
+    pub depth : usize,  // 0 for the root
+    pub describe : String,
+    pub stats : OperatorStatsRef,
+    children : Vec<OperatorStatsRef>,
+}
+
+impl OperatorNode {
+    // the time inside the operator itself
+    pub fn exclusive_elapsed(&self) -> Duration {
+        let children = self.children.iter().fold(Duration::new(0, 0), |sum, c| sum + c.borrow().elapsed);
+        self.stats.borrow().elapsed.checked_sub(children).unwrap_or(Duration::new(0, 0))
+    }
+}
+
+// Wraps every operator of the plan to count its rows and time, only for explain analyze,
+// so the other statements don't pay for it.
+pub fn instrument(mut plan : ExecIterRef) -> (ExecIterRef, Vec<OperatorNode>) {
+    let mut nodes = Vec::new();
+    instrument_at(&mut plan, 0, &mut nodes);
+    (plan, nodes)
+}
+
+fn instrument_at(plan : &mut ExecIterRef, depth : usize, nodes : &mut Vec<OperatorNode>) -> OperatorStatsRef {
+    let index = nodes.len();
+    let stats = Rc::new(RefCell::new(OperatorStats::default()));
+    nodes.push(OperatorNode{
+        depth : depth,
+        describe : plan.describe(),
+        stats : stats.clone(),
+        children : Vec::new(),
+    });
+    let children = plan.get_children().into_iter()
+        .map(|child| instrument_at(child, depth + 1, nodes)).collect();
+    nodes[index].children = children;
+    let inner = replace(plan, Box::new(Detached));
+    *plan = Box::new(Instrumented{
+        inner : inner,
+        stats : stats.clone(),
+    });
+    stats
+}
+
+#[derive(Debug)]
+struct Instrumented {
+    inner : ExecIterRef,
+    stats : OperatorStatsRef,
+}
+
+impl ExecIter for Instrumented {
+    fn open(&mut self) {
+        let start = Instant::now();
+        self.inner.open();
+        self.stats.borrow_mut().elapsed += start.elapsed();
+    }
+    fn close(&mut self) {
+        let start = Instant::now();
+        self.inner.close();
+        self.stats.borrow_mut().elapsed += start.elapsed();
+    }
+    fn explain(&self) -> String { self.inner.explain() }
+    fn get_next(&mut self) -> Option<TupleData> {
+        let start = Instant::now();
+        let tuple_data = self.inner.get_next();
+        let mut stats = self.stats.borrow_mut();
+        stats.elapsed += start.elapsed();
+        stats.calls += 1;
+        if tuple_data.is_some() {
+            stats.rows += 1;
+        }
+        tuple_data
+    }
+    fn get_error(&self) -> Option<ExecError> { self.inner.get_error() }
+    fn get_position(&self) -> Option<usize> { self.inner.get_position() }
+    fn get_affected_num(&self) -> usize { self.inner.get_affected_num() }
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> { self.inner.get_children() }
+    fn describe(&self) -> String { self.inner.describe() }
+    fn get_min_pages(&self) -> usize { self.inner.get_min_pages() }
+}
+
+// holds the place of an operator while it's moved into its wrapper
+#[derive(Debug)]
+struct Detached;
+
+impl ExecIter for Detached {
+    fn open(&mut self) { panic!("detached operator") }
+    fn close(&mut self) { panic!("detached operator") }
+    fn explain(&self) -> String { "detached".to_string() }
+    fn get_next(&mut self) -> Option<TupleData> { panic!("detached operator") }
+    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+}
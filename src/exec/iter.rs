@@ -1,17 +1,29 @@
-use std::boxed::Box;
-use std::option::Option;
-use std::fmt::Debug;
-use ::store::tuple::TupleData;
-use super::error::ExecError;
-
-
-// must be object-safe
-pub trait ExecIter : Debug {
-    fn open(&mut self);
-    fn close(&mut self);
-    fn get_next(&mut self) -> Option<TupleData>;
-    fn explain(&self) -> String;
-    fn get_error(&self) -> Option<ExecError>;
-}
-
-pub type ExecIterRef = Box<ExecIter>;
+use std::boxed::Box;
+use std::option::Option;
+use std::fmt::Debug;
+use ::store::tuple::TupleData;
+use super::error::ExecError;
+
+
+// must be object-safe
+pub trait ExecIter : Debug {
+    fn open(&mut self);
+    fn close(&mut self);
+    fn get_next(&mut self) -> Option<TupleData>;
+    fn explain(&self) -> String;
+    fn get_error(&self) -> Option<ExecError>;
+    // position in the table file of the tuple last returned by get_next(),
+    // None if the tuples don't come from a table file
+    fn get_position(&self) -> Option<usize>;
+    // the tuples inserted, updated or deleted so far, 0 for the plans not changing the tables
+    fn get_affected_num(&self) -> usize;
+    // the plans it reads from, for explain analyze to instrument them
+    fn get_children(&mut self) -> Vec<&mut ExecIterRef> { Vec::new() }
+    // explain without the children
+    fn describe(&self) -> String { self.explain() }
+    // the pages of the pool it needs at the same time, pinned or loaded, including its children,
+    // checked before it's opened
+    fn get_min_pages(&self) -> usize { 0 }
+}
+
+pub type ExecIterRef = Box<ExecIter>;
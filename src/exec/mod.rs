@@ -1,13 +1,23 @@
-#[allow(dead_code)]
-pub mod gen_plan;
-#[allow(dead_code)]
-pub mod error;
-pub mod iter;
-#[allow(dead_code)]
-pub mod create_drop;
-#[allow(dead_code)]
-pub mod change;
-#[allow(dead_code)]
-pub mod query;
-#[allow(dead_code)]
-pub mod evaluate;
+#[allow(dead_code)]
+pub mod gen_plan;
+#[allow(dead_code)]
+pub mod error;
+pub mod iter;
+#[allow(dead_code)]
+pub mod create_drop;
+#[allow(dead_code)]
+pub mod alter;
+#[allow(dead_code)]
+pub mod change;
+#[allow(dead_code)]
+pub mod copy;
+#[allow(dead_code)]
+pub mod backup;
+#[allow(dead_code)]
+pub mod analyze;
+#[allow(dead_code)]
+pub mod query;
+#[allow(dead_code)]
+pub mod evaluate;
+pub mod generated;
+pub mod instrument;
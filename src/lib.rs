@@ -1,18 +1,30 @@
-extern crate libc;
-extern crate rustc_serialize;
-extern crate toml;
-extern crate mio;
-extern crate bytes;
-
-#[macro_use]
-mod utils;
-
-mod parser;
-mod store;
-mod exec;
-mod server;
-
-mod test;
-
-pub use server::local_client::LocalClient;
-pub use server::server::run_server;
+extern crate libc;
+extern crate rustc_serialize;
+extern crate toml;
+extern crate mio;
+extern crate bytes;
+#[cfg(feature = "tls")]
+extern crate openssl;
+
+#[macro_use]
+mod utils;
+
+mod parser;
+mod store;
+mod exec;
+mod server;
+pub mod client;
+
+mod test;
+
+pub use server::local_client::LocalClient;
+pub use server::format_result::OutputFormat;
+pub use server::server::run_server;
+pub use server::auth::gen_user_entry;
+pub use server::database::{Database, QueryResult, DatabaseError, Row, Rows};
+pub use server::handler::{ColumnInfo, Summary, StatementKind};
+pub use server::result_error::ResultError;
+pub use store::tuple::TupleValue;
+pub use store::table::AttrType;
+pub use store::check::{CheckReport, CheckProblem, check_data_dir};
+pub use utils::config::{Config, ConfigError, render_config_errors};
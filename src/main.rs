@@ -3,15 +3,50 @@ extern crate blastoise;
 
 
 fn main() {
-    if let Some(ref opt) = env::args().nth(1) {
-        if opt == "-c" {
-            let mut client = blastoise::LocalClient;
+    let args : Vec<String> = env::args().skip(1).collect();
+    match args.first() {
+        Some(opt) if opt == "-c" => {
+            // -c [--format table|csv|tsv|json] [--no-header] [--config file] [--name value ...]
+            let mut format = blastoise::OutputFormat::Table;
+            let mut header = true;
+            let mut config_args = Vec::new();
+            let mut it = args.iter().skip(1);
+            while let Some(arg) = it.next() {
+                if arg == "--no-header" {
+                    header = false;
+                } else if arg == "--format" {
+                    match it.next().and_then(|name| blastoise::OutputFormat::from_name(&name)) {
+                        Some(f) => format = f,
+                        None => return println!("--format should be table, csv, tsv or json"),
+                    }
+                } else {
+                    config_args.push(arg.clone());
+                }
+            }
+            let config = match blastoise::Config::from_args(&config_args) {
+                Ok(config) => config,
+                Err(errors) => return println!("{}", blastoise::render_config_errors(&errors)),
+            };
+            let mut client = blastoise::LocalClient::new(format, header, config);
             println!("starting Blastoise shell");
             client.shell_loop();
-        } else {
-            println!("invalid option {}, only support `-c`", opt);
         }
-    } else {
-        blastoise::run_server();
+        Some(opt) if opt == "-u" => {
+            // print a line of the users file
+            match (args.get(1), args.get(2)) {
+                (Some(user), Some(password)) => println!("{}", blastoise::gen_user_entry(user, password)),
+                _ => println!("usage: -u user password"),
+            }
+        }
+        Some(opt) if !opt.starts_with("--") => {
+            println!("invalid option {}, only support `-c`, `-u` and the config options", opt);
+        }
+        _ => {
+            // [--config file] [--name value ...]
+            match blastoise::Config::from_args(&args) {
+                Ok(config) => blastoise::run_server(config),
+                Err(errors) => println!("{}", blastoise::render_config_errors(&errors)),
+            }
+        }
     }
 }
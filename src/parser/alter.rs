@@ -0,0 +1,93 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::option::Option::{Some, None};
+use std::rc::Rc;
+use super::lexer::{TokenIter, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::create_drop::AttributeDeclaration;
+use super::common::{
+    ToSql,
+    consume_next_token_with_type,
+    consume_next_token_with_type_list,
+    check_parse_to_end,
+};
+
+
+#[derive(Debug, Clone)]
+pub enum AlterAction {
+    AddAttr(AttributeDeclaration),
+    DropAttr(String),
+    ModifyAttr(AttributeDeclaration),  // change the type or nullable of an attribute
+}
+
+impl Display for AlterAction {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &AlterAction::AddAttr(ref decl) => write!(f, "add {}", decl),
+            &AlterAction::DropAttr(ref name) => write!(f, "drop {}", name),
+            &AlterAction::ModifyAttr(ref decl) => write!(f, "modify {}", decl),
+        }
+    }
+}
+
+impl ToSql for AlterAction {
+    fn to_sql(&self) -> String {
+        match self {
+            &AlterAction::AddAttr(ref decl) => format!("add {}", decl.to_sql()),
+            &AlterAction::DropAttr(ref name) => format!("drop {}", name),
+            &AlterAction::ModifyAttr(ref decl) => format!("modify {}", decl.to_sql()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlterStatement {
+    pub table : String,
+    pub action : AlterAction,
+}
+
+impl Display for AlterStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "alter table {} {}", self.table, self.action)
+    }
+}
+
+impl ToSql for AlterStatement {
+    fn to_sql(&self) -> String {
+        format!("alter table {} {}", self.table, self.action.to_sql())
+    }
+}
+
+impl AlterStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<AlterStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Alter));
+        try!(consume_next_token_with_type(it, TokenType::Table));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let action = try!(AlterStatement::parse_action(it));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(AlterStatement{
+                table : table_token.value.clone(),
+                action : action,
+            })
+        }
+    }
+    fn parse_action(it : &mut TokenIter) -> Result<AlterAction, ErrorList> {
+        // `add` and `modify` are not keywords
+        let action_types = vec![TokenType::Identifier, TokenType::Drop];
+        let token = try!(consume_next_token_with_type_list(it, &action_types));
+        if token.token_type == TokenType::Drop {
+            let attr_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+            return Ok(AlterAction::DropAttr(attr_token.value.clone()));
+        }
+        match &token.value.to_lowercase()[..] {
+            "add" => Ok(AlterAction::AddAttr(try!(AttributeDeclaration::parse_decl(it)))),
+            "modify" => Ok(AlterAction::ModifyAttr(try!(AttributeDeclaration::parse_decl(it)))),
+            _ => Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : token.clone(),
+                error_msg : format!("expect `add`, `drop` or `modify`, but got {}", token.value),
+            })]),
+        }
+    }
+}
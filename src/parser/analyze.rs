@@ -0,0 +1,37 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenType};
+use super::compile_error::ErrorList;
+use super::common::{ToSql, consume_next_token_with_type, check_parse_to_end};
+
+
+#[derive(Debug, Clone)]
+pub struct AnalyzeStatement {
+    pub table : String,
+}
+
+impl Display for AnalyzeStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "analyze {}", self.table)
+    }
+}
+
+impl ToSql for AnalyzeStatement {
+    fn to_sql(&self) -> String {
+        format!("analyze {}", self.table)
+    }
+}
+
+impl AnalyzeStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<AnalyzeStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Analyze));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(AnalyzeStatement{
+                table : table_token.value.clone(),
+            })
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::option::Option::{Some, None};
+use std::rc::Rc;
+use super::lexer::{TokenIter, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::copy::consume_to_keyword;
+use super::common::{
+    ToSql,
+    quote_string,
+    consume_next_token_with_type,
+    check_parse_to_end,
+    get_next_token,
+};
+
+
+#[derive(Debug, Clone)]
+pub struct BackupStatement {
+    pub path : String,  // directory of the snapshot
+}
+
+impl Display for BackupStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "backup to {:?}", self.path)
+    }
+}
+
+impl ToSql for BackupStatement {
+    fn to_sql(&self) -> String {
+        format!("backup to {}", quote_string(&self.path))
+    }
+}
+
+impl BackupStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<BackupStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Backup));
+        try!(consume_to_keyword(it));
+        let path_token = try!(consume_next_token_with_type(it, TokenType::StringLiteral));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(BackupStatement{
+                path : path_token.value.clone(),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestoreStatement {
+    pub path : String,  // directory written by backup
+    pub force : bool,  // replace the existing tables
+}
+
+impl Display for RestoreStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let force = if self.force { " with force" } else { "" };
+        write!(f, "restore from {:?}{}", self.path, force)
+    }
+}
+
+impl ToSql for RestoreStatement {
+    fn to_sql(&self) -> String {
+        let force = if self.force { " with force" } else { "" };
+        format!("restore from {}{}", quote_string(&self.path), force)
+    }
+}
+
+impl RestoreStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<RestoreStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Restore));
+        try!(consume_next_token_with_type(it, TokenType::From));
+        let path_token = try!(consume_next_token_with_type(it, TokenType::StringLiteral));
+        let force = try!(RestoreStatement::parse_force(it));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(RestoreStatement{
+                path : path_token.value.clone(),
+                force : force,
+            })
+        }
+    }
+    fn parse_force(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        // the same as copy options, `with force` are identifiers
+        match get_next_token(it) {
+            Ok(ref token) if token.token_type == TokenType::Identifier
+                && token.value.to_lowercase() == "with" => { it.next(); }
+            _ => return Ok(false),
+        }
+        let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if token.value.to_lowercase() != "force" {
+            return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : token.clone(),
+                error_msg : format!("expect `force`, but got {}", token.value),
+            })]);
+        }
+        Ok(true)
+    }
+}
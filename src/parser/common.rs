@@ -1,345 +1,577 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::rc::Rc;
-use std::result::Result::{Ok, Err};
-use std::iter::ExactSizeIterator;
-use super::lexer::{Token, TokenRef, TokenType, TokenIter};
-use super::compile_error::{CompileError, CompileErrorType, ErrorRef, ErrorList};
-use super::select::SelectStatement;
-use super::update::UpdateStatement;
-use super::insert::InsertStatement;
-use super::delete::DeleteStatement;
-use super::create_drop::{CreateStatement, DropStatement};
-
-
-#[allow(dead_code)]  // lint bug
-#[derive(Eq, PartialEq, Copy, Clone, Debug)]
-pub enum ValueType {
-    Integer,
-    Float,
-    String,
-    Null,
-}
-
-pub type ValueList = Vec<ValueExpr>;
-
-#[derive(Debug)]
-pub struct ValueExpr {
-    pub value : String,
-    pub value_type : ValueType,
-}
-
-impl Display for ValueExpr {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        write!(f, "{:?}({})", self.value_type, self.value)
-    }
-}
-
-impl ValueExpr {
-    pub fn parse(it : &mut TokenIter) -> Result<ValueExpr, ErrorList> {
-        let literals = vec![
-            TokenType::IntegerLiteral,
-            TokenType::FloatLiteral,
-            TokenType::StringLiteral,
-            TokenType::Null,
-        ];
-        let token = try!(consume_next_token_with_type_list(it, &literals));
-        Ok(ValueExpr{
-            value : token.value.clone(),
-            value_type : token_type_to_value_type(token.token_type),
-        })
-    }
-}
-
-fn token_type_to_value_type(t : TokenType) -> ValueType {
-    match t {
-        TokenType::IntegerLiteral => ValueType::Integer,
-        TokenType::FloatLiteral => ValueType::Float,
-        TokenType::StringLiteral => ValueType::String,
-        TokenType::Null => ValueType::Null,
-        _ => panic!("unexpected TokenType: {:?}", t),
-    }
-}
-
-fn gen_end_token(it : &TokenIter) -> TokenRef {
-    let mut it = it.clone();
-    match it.next_back() {
-        Some(token_ref) => token_ref.clone(),
-        // dummy token
-        None => Rc::new(Token{
-            column : 0,
-            value : "".to_string(),
-            token_type : TokenType::UnKnown
-        }),
-    }
-}
-
-fn gen_reach_the_end_err_with_type(it : &TokenIter, expected_token_type : TokenType) -> ErrorList {
-    let token = gen_end_token(it);
-    let err_msg = format!("expect {:?} but no more token found", expected_token_type);
-    let err = Rc::new(CompileError{
-        error_type : CompileErrorType::ParserNoMoreToken,
-        token : token,
-        error_msg : err_msg,
-    });
-    vec![err]
-}
-
-fn gen_reach_the_end_err(it : &TokenIter) -> ErrorList {
-    let token = gen_end_token(it);
-    let error_msg = format!("expect token but no more token found");
-    let err = Rc::new(CompileError{
-        error_type : CompileErrorType::ParserNoMoreToken,
-        token : token,
-        error_msg : error_msg,
-    });
-    vec![err]
-}
-
-pub fn consume_next_token_with_type(it : &mut TokenIter, token_type : TokenType)
-        -> Result<TokenRef, ErrorList> {
-    let token : TokenRef = match it.next() {
-        Some(token) => (*token).clone(),
-        None => return Err(gen_reach_the_end_err_with_type(it, token_type)),
-    };
-    if token_type == token.token_type {
-        return Ok(token);
-    }
-
-    let err_msg = format!("expect token type: {:?}, but got {:?}",
-        token_type, token.token_type);
-    let err = Rc::new(CompileError{
-        error_type : CompileErrorType::ParserUnExpectedTokenType,
-        token : token.clone(),
-        error_msg : err_msg,
-    });
-    Err(vec![err])
-}
-
-pub fn consume_next_token_with_type_list(it : &mut TokenIter, type_list : &Vec<TokenType>)
-        -> Result<TokenRef, ErrorList> {
-    let token : TokenRef = match it.next() {
-        Some(token) => (*token).clone(),
-        None => return Err(gen_reach_the_end_err(it)),
-    };
-    if type_list.contains(&token.token_type) {
-        return Ok(token);
-    }
-    let err_msg = format!("expect token type: {:?}, but got {:?}",
-        type_list, token.token_type);
-    let err = Rc::new(CompileError{
-        error_type : CompileErrorType::ParserUnExpectedTokenType,
-        token : token.clone(),
-        error_msg : err_msg,
-    });
-    Err(vec![err])
-}
-
-pub fn get_next_token(it : &TokenIter) -> Result<TokenRef, ErrorList> {
-    match it.clone().peekable().peek() {
-        Some(token) => Ok((*token).clone()),
-        None => Err(gen_reach_the_end_err(it)),
-    }
-}
-
-pub fn consume_next_token(it : &mut TokenIter)
-        -> Result<TokenRef, ErrorList> {
-    match it.next() {
-        Some(token) => Ok(token.clone()),
-        None => Err(gen_reach_the_end_err(it)),
-    }
-}
-
-pub fn check_parse_to_end(it : &TokenIter) -> Option<ErrorRef> {
-    match it.clone().peekable().peek() {
-        None => None,
-        Some(token) => Some(Rc::new(CompileError{
-            error_type : CompileErrorType::ParserCanNotParseLeftToken,
-            token : (*token).clone(),
-            error_msg : format!("Can not parse the left tokens : {}", token.value),
-        })),
-    }
-}
-
-pub fn align_iter(it1 : &mut TokenIter, it2 : &mut TokenIter) {
-    let (l1, l2) = (it1.len(), it2.len());
-    if l1 < l2 {
-        return align_iter(it2, it1);
-    }
-    if l1 - l2 > 0 {
-        it1.nth(l1 - l2 - 1);
-    }
-}
-
-macro_rules! try_parse_helper {
-    ($result:expr, $iter:expr, $tmp:expr) => ({
-        use std::result::Result::{Ok, Err};
-        use std::convert::From;
-        match $result {
-            Ok(val) => {
-                ::parser::common::align_iter($iter, &mut $tmp);
-                val
-            },
-            Err(err) => {
-                return Err(From::from(err))
-            },
-        }
-    });
-}
-
-// when success, iter will be changed
-// while not, iter stay still
-macro_rules! try_parse {
-    ($parse_func:ident, $iter:expr) => ({
-        let mut tmp = $iter.clone();
-        try_parse_helper!($parse_func(&mut tmp), $iter, tmp)
-    });
-    ($parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
-        let mut tmp = $iter.clone();
-        try_parse_helper!($parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
-    });
-    ($type_name:ident :: $parse_func:ident, $iter:expr) => ({
-        let mut tmp = $iter.clone();
-        try_parse_helper!($type_name::$parse_func(&mut tmp), $iter, tmp)
-    });
-    ($type_name:ident :: $parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
-        let mut tmp = $iter.clone();
-        try_parse_helper!($type_name::$parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
-    });
-}
-
-macro_rules! or_parse_helper {
-    ($result:expr, $iter:expr, $tmp:expr) => ({
-        use std::result::Result::{Ok, Err};
-        match $result {
-            Ok(val) => {
-                ::parser::common::align_iter($iter, &mut $tmp);
-                return Ok(val)
-            },
-            Err(err) => {
-                err
-            },
-        }
-    });
-}
-
-// when success, iter will be changed and return the parsed Expr
-// while not, iter stay still
-macro_rules! or_parse {
-    ($parse_func:ident, $iter:expr) => ({
-        let mut tmp = $iter.clone();
-        or_parse_helper!($parse_func(&mut tmp), $iter, tmp)
-    });
-    ($parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
-        let mut tmp = $iter.clone();
-        or_parse_helper!($parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
-    });
-    ($type_name:ident :: $parse_func:ident, $iter:expr) => ({
-        let mut tmp = $iter.clone();
-        or_parse_helper!($type_name::$parse_func(&mut tmp), $iter, tmp)
-    });
-    ($type_name:ident :: $parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
-        let mut tmp = $iter.clone();
-        or_parse_helper!($type_name::$parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
-    });
-}
-
-macro_rules! or_parse_combine {
-    ($iter:expr, $( $type_name:ident :: $parse_func:ident ),+ ) => ({
-        use std::vec::Vec;
-        let mut errors = Vec::new();
-        $(
-            let errs = or_parse!($type_name::$parse_func, $iter);
-            ::parser::common::extend_from_other(&mut errors, &errs);
-        )+
-        Err(errors)
-    });
-}
-
-// should be replaced when upgrade rust to 1.6
-pub fn extend_from_other(errors : &mut Vec<ErrorRef>, other : &Vec<ErrorRef>) {
-    errors.extend(other.iter().cloned());
-}
-
-pub fn concat_error_list(es : Vec<ErrorList>) -> ErrorList {
-    let mut res = ErrorList::new();
-    for e in es.iter().flat_map(|es| es.iter()) {
-        res.push(e.clone());
-    }
-    res
-}
-
-pub fn seq_parse_helper<Res>(parse_func : fn(&mut TokenIter) -> Result<Res, ErrorList>,
-        it : &mut TokenIter) -> (Option<Res>, ErrorList) {
-    let mut tmp = it.clone();
-    match parse_func(&mut tmp) {
-        Ok(res) => {
-            align_iter(it, &mut tmp);
-            (Some(res), vec![])
-        }
-        Err(errs) => (None, errs),
-    }
-}
-
-pub fn exp_list_to_string<Exp : Display>(exp_list : &Vec<Exp>) -> String {
-    if exp_list.len() == 1 {
-        return format!("{}", exp_list.first().unwrap());
-    }
-    let mut it = exp_list.iter();
-    let mut s = format!("{}", it.next().unwrap());
-    for r in it {
-        s.push_str(&format!(", {}", r));
-    };
-    s
-}
-
-pub fn concat_format<Type : Display>(s : String, additional : &str, obj : &Option<Type>) -> String {
-    match obj {
-        &Some(ref obj) => format!("{} {}{}", s, additional, obj),
-        &None => s,
-    }
-}
-
-pub fn parse_list_helper<Expr>(parse_func : fn(it : &mut TokenIter) -> Result<Expr, ErrorList>,
-        it : &mut TokenIter) -> Result<Vec<Expr>, ErrorList> {
-    let mut exp_list = vec![];
-    exp_list.push(try!(parse_func(it)));
-    loop {
-        let mut tmp = it.clone();
-        if let Err(..) = ::parser::common::consume_next_token_with_type(
-                &mut tmp, ::parser::lexer::TokenType::Comma) {
-            return Ok(exp_list);
-        }
-        exp_list.push(try!(parse_func(&mut tmp)));
-        align_iter(it, &mut tmp);
-    }
-}
-
-
-#[derive(Debug)]
-pub enum Statement {
-    Select(SelectStatement),
-    Update(UpdateStatement),
-    Insert(InsertStatement),
-    Delete(DeleteStatement),
-    Create(CreateStatement),
-    Drop(DropStatement),
-}
-
-impl Statement {
-    pub fn parse(it : &mut TokenIter) -> Result<Statement, ErrorList> {
-        let mut tmp = it.clone();
-        let type_list = vec![TokenType::Select, TokenType::Update,TokenType::Insert,
-            TokenType::Delete, TokenType::Create, TokenType::Drop];
-        let token = try!(consume_next_token_with_type_list(&mut tmp, &type_list));
-        Ok(match token.token_type {
-            TokenType::Select => Statement::Select(try!(SelectStatement::parse(it))),
-            TokenType::Update => Statement::Update(try!(UpdateStatement::parse(it))),
-            TokenType::Insert => Statement::Insert(try!(InsertStatement::parse(it))),
-            TokenType::Delete => Statement::Delete(try!(DeleteStatement::parse(it))),
-            TokenType::Create => Statement::Create(try!(CreateStatement::parse(it))),
-            TokenType::Drop => Statement::Drop(try!(DropStatement::parse(it))),
-            _ => panic!("invalid state"),
-        })
-    }
-}
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::result::Result::{Ok, Err};
+use std::iter::ExactSizeIterator;
+use super::lexer::{Token, TokenRef, TokenType, TokenIter, TokenLine, TokenList};
+use super::compile_error::{CompileError, CompileErrorType, ErrorRef, ErrorList};
+use super::select::SelectStatement;
+use super::update::UpdateStatement;
+use super::insert::InsertStatement;
+use super::delete::DeleteStatement;
+use super::create_drop::{CreateStatement, DropStatement};
+use super::copy::{CopyFromStatement, CopyToStatement};
+use super::alter::AlterStatement;
+use super::backup::{BackupStatement, RestoreStatement};
+use super::analyze::AnalyzeStatement;
+use ::utils::float::{parse_float, format_float};
+
+
+#[allow(dead_code)]  // lint bug
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum ValueType {
+    Integer,
+    Float,
+    String,
+    Null,
+    Placeholder,  // value holds the index of the parameter
+}
+
+// the value of a literal parsed from its text once by check_sem
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Null,
+}
+
+pub type ValueList = Vec<ValueExpr>;
+
+#[derive(Debug, Clone)]
+pub struct ValueExpr {
+    pub value : String,  // the text in the statement for the error messages
+    pub value_type : ValueType,
+    pub literal : Option<Literal>,  // None before check_literal and for the placeholders
+}
+
+impl Display for ValueExpr {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "{:?}({})", self.value_type, self.value)
+    }
+}
+
+impl ValueExpr {
+    pub fn parse(it : &mut TokenIter) -> Result<ValueExpr, ErrorList> {
+        let literals = vec![
+            TokenType::IntegerLiteral,
+            TokenType::FloatLiteral,
+            TokenType::StringLiteral,
+            TokenType::Null,
+            TokenType::Placeholder,
+        ];
+        let token = try!(consume_next_token_with_type_list(it, &literals));
+        Ok(ValueExpr{
+            value : token.value.clone(),
+            value_type : token_type_to_value_type(token.token_type),
+            literal : None,
+        })
+    }
+    pub fn from_literal(literal : Literal) -> ValueExpr {
+        let (value, value_type) = match literal {
+            Literal::Int(n) => (n.to_string(), ValueType::Integer),
+            Literal::Float(f) => (format_float(f), ValueType::Float),
+            Literal::Str(ref s) => (s.clone(), ValueType::String),
+            Literal::Null => ("null".to_string(), ValueType::Null),
+        };
+        ValueExpr{
+            value : value,
+            value_type : value_type,
+            literal : Some(literal),
+        }
+    }
+    // does nothing for a checked value or a placeholder, which is checked after binding
+    pub fn check_literal(&mut self) -> Result<(), String> {
+        if self.literal.is_some() {
+            return Ok(());
+        }
+        let literal = match self.value_type {
+            ValueType::Integer => match self.value.parse::<i32>() {
+                Ok(n) => Literal::Int(n),
+                Err(..) => return Err(format!("invalid int literal: {}", self.value)),
+            },
+            ValueType::Float => match parse_float(&self.value) {
+                Some(f) => Literal::Float(f),
+                None => return Err(format!("float literal out of range: {}", self.value)),
+            },
+            ValueType::String => Literal::Str(self.value.clone()),
+            ValueType::Null => Literal::Null,
+            ValueType::Placeholder => return Ok(()),
+        };
+        self.literal = Some(literal);
+        Ok(())
+    }
+    pub fn literal(&self) -> &Literal {
+        match self.literal {
+            Some(ref literal) => literal,
+            None => panic!("unchecked literal {}", self),
+        }
+    }
+}
+
+impl ToSql for ValueExpr {
+    fn to_sql(&self) -> String {
+        match self.value_type {
+            ValueType::String => quote_string(&self.value),
+            ValueType::Null => "null".to_string(),
+            ValueType::Placeholder => "?".to_string(),
+            // from_literal writes 1.0 as 1, which would be read back as an int
+            ValueType::Float if !self.value.contains('.') => format!("{}.0", self.value),
+            ValueType::Integer | ValueType::Float => self.value.clone(),
+        }
+    }
+}
+
+fn token_type_to_value_type(t : TokenType) -> ValueType {
+    match t {
+        TokenType::IntegerLiteral => ValueType::Integer,
+        TokenType::FloatLiteral => ValueType::Float,
+        TokenType::StringLiteral => ValueType::String,
+        TokenType::Null => ValueType::Null,
+        TokenType::Placeholder => ValueType::Placeholder,
+        _ => panic!("unexpected TokenType: {:?}", t),
+    }
+}
+
+fn gen_end_token(it : &TokenIter) -> TokenRef {
+    let mut it = it.clone();
+    match it.next_back() {
+        Some(token_ref) => token_ref.clone(),
+        // dummy token at the start of the empty statement
+        None => Rc::new(Token{
+            column : 1,
+            value : "".to_string(),
+            token_type : TokenType::UnKnown
+        }),
+    }
+}
+
+fn gen_reach_the_end_err_with_type(it : &TokenIter, expected_token_type : TokenType) -> ErrorList {
+    let token = gen_end_token(it);
+    let err_msg = format!("expect {:?} but no more token found", expected_token_type);
+    let err = Rc::new(CompileError{
+        error_type : CompileErrorType::ParserNoMoreToken,
+        token : token,
+        error_msg : err_msg,
+    });
+    vec![err]
+}
+
+fn gen_reach_the_end_err(it : &TokenIter) -> ErrorList {
+    let token = gen_end_token(it);
+    let error_msg = format!("expect token but no more token found");
+    let err = Rc::new(CompileError{
+        error_type : CompileErrorType::ParserNoMoreToken,
+        token : token,
+        error_msg : error_msg,
+    });
+    vec![err]
+}
+
+pub fn consume_next_token_with_type(it : &mut TokenIter, token_type : TokenType)
+        -> Result<TokenRef, ErrorList> {
+    let token : TokenRef = match it.next() {
+        Some(token) => (*token).clone(),
+        None => return Err(gen_reach_the_end_err_with_type(it, token_type)),
+    };
+    if token_type == token.token_type {
+        return Ok(token);
+    }
+
+    let err_msg = format!("expect token type: {:?}, but got {:?}",
+        token_type, token.token_type);
+    let err = Rc::new(CompileError{
+        error_type : CompileErrorType::ParserUnExpectedTokenType,
+        token : token.clone(),
+        error_msg : err_msg,
+    });
+    Err(vec![err])
+}
+
+// the words such as `to` and `with` are not keywords, so that they can still be used as names
+pub fn consume_word(it : &mut TokenIter, word : &str) -> Result<TokenRef, ErrorList> {
+    let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+    if token.value.to_lowercase() != word {
+        return Err(vec![Rc::new(CompileError{
+            error_type : CompileErrorType::ParserUnExpectedTokenType,
+            token : token.clone(),
+            error_msg : format!("expect `{}`, but got {}", word, token.value),
+        })]);
+    }
+    Ok(token)
+}
+
+pub fn consume_next_token_with_type_list(it : &mut TokenIter, type_list : &Vec<TokenType>)
+        -> Result<TokenRef, ErrorList> {
+    let token : TokenRef = match it.next() {
+        Some(token) => (*token).clone(),
+        None => return Err(gen_reach_the_end_err(it)),
+    };
+    if type_list.contains(&token.token_type) {
+        return Ok(token);
+    }
+    let err_msg = format!("expect token type: {:?}, but got {:?}",
+        type_list, token.token_type);
+    let err = Rc::new(CompileError{
+        error_type : CompileErrorType::ParserUnExpectedTokenType,
+        token : token.clone(),
+        error_msg : err_msg,
+    });
+    Err(vec![err])
+}
+
+pub fn get_next_token(it : &TokenIter) -> Result<TokenRef, ErrorList> {
+    match it.clone().peekable().peek() {
+        Some(token) => Ok((*token).clone()),
+        None => Err(gen_reach_the_end_err(it)),
+    }
+}
+
+pub fn consume_next_token(it : &mut TokenIter)
+        -> Result<TokenRef, ErrorList> {
+    match it.next() {
+        Some(token) => Ok(token.clone()),
+        None => Err(gen_reach_the_end_err(it)),
+    }
+}
+
+pub fn check_parse_to_end(it : &TokenIter) -> Option<ErrorRef> {
+    match it.clone().peekable().peek() {
+        None => None,
+        Some(token) => Some(Rc::new(CompileError{
+            error_type : CompileErrorType::ParserCanNotParseLeftToken,
+            token : (*token).clone(),
+            error_msg : format!("Can not parse the left tokens : {}", token.value),
+        })),
+    }
+}
+
+pub fn align_iter(it1 : &mut TokenIter, it2 : &mut TokenIter) {
+    let (l1, l2) = (it1.len(), it2.len());
+    if l1 < l2 {
+        return align_iter(it2, it1);
+    }
+    if l1 - l2 > 0 {
+        it1.nth(l1 - l2 - 1);
+    }
+}
+
+macro_rules! try_parse_helper {
+    ($result:expr, $iter:expr, $tmp:expr) => ({
+        use std::result::Result::{Ok, Err};
+        use std::convert::From;
+        match $result {
+            Ok(val) => {
+                ::parser::common::align_iter($iter, &mut $tmp);
+                val
+            },
+            Err(err) => {
+                return Err(From::from(err))
+            },
+        }
+    });
+}
+
+// when success, iter will be changed
+// while not, iter stay still
+macro_rules! try_parse {
+    ($parse_func:ident, $iter:expr) => ({
+        let mut tmp = $iter.clone();
+        try_parse_helper!($parse_func(&mut tmp), $iter, tmp)
+    });
+    ($parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
+        let mut tmp = $iter.clone();
+        try_parse_helper!($parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
+    });
+    ($type_name:ident :: $parse_func:ident, $iter:expr) => ({
+        let mut tmp = $iter.clone();
+        try_parse_helper!($type_name::$parse_func(&mut tmp), $iter, tmp)
+    });
+    ($type_name:ident :: $parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
+        let mut tmp = $iter.clone();
+        try_parse_helper!($type_name::$parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
+    });
+}
+
+macro_rules! or_parse_helper {
+    ($result:expr, $iter:expr, $tmp:expr) => ({
+        use std::result::Result::{Ok, Err};
+        match $result {
+            Ok(val) => {
+                ::parser::common::align_iter($iter, &mut $tmp);
+                return Ok(val)
+            },
+            Err(err) => {
+                err
+            },
+        }
+    });
+}
+
+// when success, iter will be changed and return the parsed Expr
+// while not, iter stay still
+macro_rules! or_parse {
+    ($parse_func:ident, $iter:expr) => ({
+        let mut tmp = $iter.clone();
+        or_parse_helper!($parse_func(&mut tmp), $iter, tmp)
+    });
+    ($parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
+        let mut tmp = $iter.clone();
+        or_parse_helper!($parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
+    });
+    ($type_name:ident :: $parse_func:ident, $iter:expr) => ({
+        let mut tmp = $iter.clone();
+        or_parse_helper!($type_name::$parse_func(&mut tmp), $iter, tmp)
+    });
+    ($type_name:ident :: $parse_func:ident, $iter:expr, $( $add_args:expr ),*) => ({
+        let mut tmp = $iter.clone();
+        or_parse_helper!($type_name::$parse_func(&mut tmp, $($add_args),* ), $iter, tmp)
+    });
+}
+
+macro_rules! or_parse_combine {
+    ($iter:expr, $( $type_name:ident :: $parse_func:ident ),+ ) => ({
+        use std::vec::Vec;
+        let mut errors = Vec::new();
+        $(
+            let errs = or_parse!($type_name::$parse_func, $iter);
+            ::parser::common::extend_from_other(&mut errors, &errs);
+        )+
+        Err(errors)
+    });
+}
+
+// should be replaced when upgrade rust to 1.6
+pub fn extend_from_other(errors : &mut Vec<ErrorRef>, other : &Vec<ErrorRef>) {
+    errors.extend(other.iter().cloned());
+}
+
+pub fn concat_error_list(es : Vec<ErrorList>) -> ErrorList {
+    let mut res = ErrorList::new();
+    for e in es.iter().flat_map(|es| es.iter()) {
+        res.push(e.clone());
+    }
+    res
+}
+
+pub fn seq_parse_helper<Res>(parse_func : fn(&mut TokenIter) -> Result<Res, ErrorList>,
+        it : &mut TokenIter) -> (Option<Res>, ErrorList) {
+    let mut tmp = it.clone();
+    match parse_func(&mut tmp) {
+        Ok(res) => {
+            align_iter(it, &mut tmp);
+            (Some(res), vec![])
+        }
+        Err(errs) => (None, errs),
+    }
+}
+
+pub fn exp_list_to_string<Exp : Display>(exp_list : &Vec<Exp>) -> String {
+    if exp_list.len() == 1 {
+        return format!("{}", exp_list.first().unwrap());
+    }
+    let mut it = exp_list.iter();
+    let mut s = format!("{}", it.next().unwrap());
+    for r in it {
+        s.push_str(&format!(", {}", r));
+    };
+    s
+}
+
+pub fn concat_format<Type : Display>(s : String, additional : &str, obj : &Option<Type>) -> String {
+    match obj {
+        &Some(ref obj) => format!("{} {}{}", s, additional, obj),
+        &None => s,
+    }
+}
+
+// The canonical text of a parsed statement or expression, it's parsed back into the same tree.
+// The brackets are only written where the precedence needs them or to show the end of a prefix operator,
+// and the string literals are single quoted.
+pub trait ToSql {
+    fn to_sql(&self) -> String;
+}
+
+pub fn sql_list_to_string<Exp : ToSql>(exp_list : &Vec<Exp>) -> String {
+    let sql_list : Vec<String> = exp_list.iter().map(|exp| exp.to_sql()).collect();
+    sql_list.join(", ")
+}
+
+pub fn concat_sql<Exp : ToSql>(s : String, additional : &str, obj : &Option<Exp>) -> String {
+    match obj {
+        &Some(ref obj) => format!("{} {}{}", s, additional, obj.to_sql()),
+        &None => s,
+    }
+}
+
+// with the escapes read by the lexer
+pub fn quote_string(s : &str) -> String {
+    let mut quoted = "'".to_string();
+    for c in s.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\0' => quoted.push_str("\\0"),
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+pub fn parse_list_helper<Expr>(parse_func : fn(it : &mut TokenIter) -> Result<Expr, ErrorList>,
+        it : &mut TokenIter) -> Result<Vec<Expr>, ErrorList> {
+    let mut exp_list = vec![];
+    exp_list.push(try!(parse_func(it)));
+    loop {
+        let mut tmp = it.clone();
+        if let Err(..) = ::parser::common::consume_next_token_with_type(
+                &mut tmp, ::parser::lexer::TokenType::Comma) {
+            return Ok(exp_list);
+        }
+        exp_list.push(try!(parse_func(&mut tmp)));
+        align_iter(it, &mut tmp);
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Select(SelectStatement),
+    Update(UpdateStatement),
+    Insert(InsertStatement),
+    Delete(DeleteStatement),
+    Create(CreateStatement),
+    Drop(DropStatement),
+    Alter(AlterStatement),
+    CopyFrom(CopyFromStatement),
+    CopyTo(CopyToStatement),
+    Backup(BackupStatement),
+    Restore(RestoreStatement),
+    Analyze(AnalyzeStatement),
+}
+
+impl Statement {
+    pub fn parse(it : &mut TokenIter) -> Result<Statement, ErrorList> {
+        match get_next_token(it) {
+            Ok(ref token) if token.token_type == TokenType::Identifier
+                && token.value.to_lowercase() == "with" =>
+                return Ok(Statement::Select(try!(SelectStatement::parse_with(it)))),
+            _ => (),
+        }
+        let mut tmp = it.clone();
+        let type_list = vec![TokenType::Select, TokenType::Update,TokenType::Insert,
+            TokenType::Delete, TokenType::Create, TokenType::Drop, TokenType::Alter,
+            TokenType::Copy, TokenType::Backup, TokenType::Restore, TokenType::Analyze];
+        let token = try!(consume_next_token_with_type_list(&mut tmp, &type_list));
+        Ok(match token.token_type {
+            TokenType::Select => Statement::Select(try!(SelectStatement::parse(it))),
+            TokenType::Update => Statement::Update(try!(UpdateStatement::parse(it))),
+            TokenType::Insert => Statement::Insert(try!(InsertStatement::parse(it))),
+            TokenType::Delete => Statement::Delete(try!(DeleteStatement::parse(it))),
+            TokenType::Create => Statement::Create(try!(CreateStatement::parse(it))),
+            TokenType::Drop => Statement::Drop(try!(DropStatement::parse(it))),
+            TokenType::Alter => Statement::Alter(try!(AlterStatement::parse(it))),
+            TokenType::Copy => {
+                match tmp.next().map(|token| token.token_type) {
+                    Some(TokenType::OpenBracket) => Statement::CopyTo(try!(CopyToStatement::parse(it))),
+                    _ => Statement::CopyFrom(try!(CopyFromStatement::parse(it))),
+                }
+            }
+            TokenType::Backup => Statement::Backup(try!(BackupStatement::parse(it))),
+            TokenType::Restore => Statement::Restore(try!(RestoreStatement::parse(it))),
+            TokenType::Analyze => Statement::Analyze(try!(AnalyzeStatement::parse(it))),
+            _ => panic!("invalid state"),
+        })
+    }
+}
+
+impl ToSql for Statement {
+    fn to_sql(&self) -> String {
+        match self {
+            &Statement::Select(ref stmt) => stmt.to_sql(),
+            &Statement::Update(ref stmt) => stmt.to_sql(),
+            &Statement::Insert(ref stmt) => stmt.to_sql(),
+            &Statement::Delete(ref stmt) => stmt.to_sql(),
+            &Statement::Create(ref stmt) => stmt.to_sql(),
+            &Statement::Drop(ref stmt) => stmt.to_sql(),
+            &Statement::Alter(ref stmt) => stmt.to_sql(),
+            &Statement::CopyFrom(ref stmt) => stmt.to_sql(),
+            &Statement::CopyTo(ref stmt) => stmt.to_sql(),
+            &Statement::Backup(ref stmt) => stmt.to_sql(),
+            &Statement::Restore(ref stmt) => stmt.to_sql(),
+            &Statement::Analyze(ref stmt) => stmt.to_sql(),
+        }
+    }
+}
+
+// Every bracket and prefix operator is a level of recursion in the parser,
+// a statement nesting deeper is rejected before being parsed.
+pub const MAX_NESTING_DEPTH : usize = 32;
+
+// lexing and parsing of a single statement, an error for any input it can't parse instead of a panic
+pub fn parse_statement(input : &str) -> Result<Statement, ErrorList> {
+    let line = TokenLine::parse(input);
+    if line.errors.len() > 0 {
+        return Err(line.errors);
+    }
+    parse_tokens(&line.tokens)
+}
+
+pub fn parse_tokens(tokens : &TokenList) -> Result<Statement, ErrorList> {
+    try!(check_nesting_depth(tokens));
+    Statement::parse(&mut tokens.iter())
+}
+
+fn check_nesting_depth(tokens : &TokenList) -> Result<(), ErrorList> {
+    // the prefix operators inside each open bracket, they nest until the bracket is closed
+    let mut prefix_nums = vec![0];
+    let mut depth = 0;
+    let mut last_type = None;
+    for token in tokens.iter() {
+        match token.token_type {
+            TokenType::OpenBracket => {
+                prefix_nums.push(0);
+                depth += 1;
+            }
+            TokenType::CloseBracket if prefix_nums.len() > 1 => {
+                depth -= prefix_nums.pop().unwrap_or(0) + 1;
+            }
+            TokenType::Not => {
+                if let Some(n) = prefix_nums.last_mut() {
+                    *n += 1;
+                }
+                depth += 1;
+            }
+            TokenType::Add | TokenType::Sub if !ends_operand(last_type) => {
+                if let Some(n) = prefix_nums.last_mut() {
+                    *n += 1;
+                }
+                depth += 1;
+            }
+            _ => (),
+        }
+        if depth > MAX_NESTING_DEPTH {
+            return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserTooDeepNesting,
+                token : token.clone(),
+                error_msg : format!("the statement nests deeper than {} levels", MAX_NESTING_DEPTH),
+            })]);
+        }
+        last_type = Some(token.token_type);
+    }
+    Ok(())
+}
+
+fn ends_operand(token_type : Option<TokenType>) -> bool {
+    // otherwise the following '+' or '-' is a prefix operator
+    match token_type {
+        Some(TokenType::IntegerLiteral) | Some(TokenType::FloatLiteral) | Some(TokenType::StringLiteral)
+        | Some(TokenType::Identifier) | Some(TokenType::Null) | Some(TokenType::Placeholder)
+        | Some(TokenType::CloseBracket) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::rc::Rc;
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenRef, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::select::SelectStatement;
+use super::common::{
+    ToSql,
+    quote_string,
+    consume_next_token_with_type,
+    consume_word,
+    check_parse_to_end,
+    get_next_token,
+};
+
+
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    pub header : bool,
+    pub delimiter : String,
+    pub empty_as_null : bool,
+    pub null_as : Option<String>,
+    pub max_errors : usize,
+    pub overwrite : bool,
+}
+
+impl CopyOptions {
+    pub fn new() -> CopyOptions {
+        CopyOptions{
+            header : false,
+            delimiter : ",".to_string(),
+            empty_as_null : false,
+            null_as : None,
+            max_errors : 0,
+            overwrite : false,
+        }
+    }
+
+    // the unquoted field read as null, `empty_as_null` is the same as `null_as ''`
+    pub fn import_null(&self) -> Option<&str> {
+        match self.null_as {
+            Some(ref s) => Some(s),
+            None if self.empty_as_null => Some(""),
+            None => None,
+        }
+    }
+
+    // null is written as an empty unquoted field by default
+    pub fn export_null(&self) -> &str {
+        self.null_as.as_ref().map(|s| s.as_ref()).unwrap_or("")
+    }
+}
+
+impl Display for CopyOptions {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let mut s = "with".to_string();
+        if self.header { s.push_str(" header"); }
+        s.push_str(&format!(" delimiter {:?}", self.delimiter));
+        if self.empty_as_null { s.push_str(" empty_as_null"); }
+        if let Some(ref null_as) = self.null_as { s.push_str(&format!(" null_as {:?}", null_as)); }
+        if self.max_errors > 0 { s.push_str(&format!(" max_errors {}", self.max_errors)); }
+        if self.overwrite { s.push_str(" overwrite"); }
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for CopyOptions {
+    // only the options different from the defaults, empty if there is none
+    fn to_sql(&self) -> String {
+        let mut s = String::new();
+        if self.header { s.push_str(" header"); }
+        if self.delimiter != "," { s.push_str(&format!(" delimiter {}", quote_string(&self.delimiter))); }
+        if self.empty_as_null { s.push_str(" empty_as_null"); }
+        if let Some(ref null_as) = self.null_as {
+            s.push_str(&format!(" null_as {}", quote_string(null_as)));
+        }
+        if self.max_errors > 0 { s.push_str(&format!(" max_errors {}", self.max_errors)); }
+        if self.overwrite { s.push_str(" overwrite"); }
+        if s.is_empty() { s } else { format!(" with{}", s) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyFromStatement {
+    pub table : String,
+    pub path : Option<String>,  // None for `from stdin`, the data streamed by the client
+    pub options : CopyOptions,
+}
+
+impl Display for CopyFromStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self.path {
+            Some(ref path) => write!(f, "copy {} from {:?} {}", self.table, path, self.options),
+            None => write!(f, "copy {} from stdin {}", self.table, self.options),
+        }
+    }
+}
+
+impl ToSql for CopyFromStatement {
+    fn to_sql(&self) -> String {
+        let source = match self.path {
+            Some(ref path) => quote_string(path),
+            None => "stdin".to_string(),
+        };
+        format!("copy {} from {}{}", self.table, source, self.options.to_sql())
+    }
+}
+
+impl CopyFromStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<CopyFromStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Copy));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::From));
+        // `stdin` is not a keyword, a table can still be named so
+        let path = match get_next_token(it) {
+            Ok(ref token) if token.token_type == TokenType::Identifier
+                && token.value.to_lowercase() == "stdin" => {
+                it.next();
+                None
+            }
+            _ => Some(try!(consume_next_token_with_type(it, TokenType::StringLiteral)).value.clone()),
+        };
+        let options = try!(parse_options(it,
+            &["header", "delimiter", "empty_as_null", "null_as", "max_errors"]));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CopyFromStatement{
+                table : table_token.value.clone(),
+                path : path,
+                options : options,
+            })
+        }
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.path.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyToStatement {
+    pub select : SelectStatement,
+    pub path : String,
+    pub options : CopyOptions,
+}
+
+impl Display for CopyToStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "copy ({}) to {:?} {}", self.select, self.path, self.options)
+    }
+}
+
+impl ToSql for CopyToStatement {
+    fn to_sql(&self) -> String {
+        format!("copy ({}) to {}{}", self.select.to_sql(), quote_string(&self.path), self.options.to_sql())
+    }
+}
+
+impl CopyToStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<CopyToStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Copy));
+        let select = try!(SelectStatement::parse_as_sub_relation(it));
+        try!(consume_to_keyword(it));
+        let path_token = try!(consume_next_token_with_type(it, TokenType::StringLiteral));
+        let options = try!(parse_options(it, &["header", "delimiter", "null_as", "overwrite"]));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CopyToStatement{
+                select : select,
+                path : path_token.value.clone(),
+                options : options,
+            })
+        }
+    }
+}
+
+pub fn consume_to_keyword(it : &mut TokenIter) -> Result<(), ErrorList> {
+    try!(consume_word(it, "to"));
+    Ok(())
+}
+
+pub fn parse_options(it : &mut TokenIter, allowed : &[&str]) -> Result<CopyOptions, ErrorList> {
+    // options are not keywords, so `with` and the option names come as identifiers
+    let mut options = CopyOptions::new();
+    match get_next_token(it) {
+        Ok(ref token) if token.token_type == TokenType::Identifier
+            && token.value.to_lowercase() == "with" => { it.next(); }
+        _ => return Ok(options),
+    }
+    loop {
+        let token = match get_next_token(it) {
+            Ok(token) => token,
+            Err(..) => return Ok(options),
+        };
+        if token.token_type != TokenType::Identifier {
+            return Ok(options);  // let check_parse_to_end report it
+        }
+        it.next();
+        let option = token.value.to_lowercase();
+        if !allowed.contains(&&option[..]) {
+            return Err(gen_invalid_option_err(&token,
+                format!("invalid copy option: {}, expected {:?}", token.value, allowed)));
+        }
+        match &option[..] {
+            "header" => options.header = true,
+            "overwrite" => options.overwrite = true,
+            "empty_as_null" => options.empty_as_null = true,
+            "delimiter" => {
+                let t = try!(consume_next_token_with_type(it, TokenType::StringLiteral));
+                if t.value.chars().count() != 1 {
+                    return Err(gen_invalid_option_err(&t,
+                        format!("invalid delimiter: {:?}, expected a single character", t.value)));
+                }
+                options.delimiter = t.value.clone();
+            }
+            "null_as" => {
+                let t = try!(consume_next_token_with_type(it, TokenType::StringLiteral));
+                options.null_as = Some(t.value.clone());
+            }
+            "max_errors" => {
+                let t = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
+                options.max_errors = match t.value.parse::<usize>() {
+                    Ok(n) => n,
+                    Err(..) => return Err(gen_invalid_option_err(&t,
+                        format!("invalid max_errors: {}", t.value))),
+                };
+            }
+            _ => panic!("unexpected copy option: {}", option),
+        }
+    }
+}
+
+fn gen_invalid_option_err(token : &TokenRef, error_msg : String) -> ErrorList {
+    vec![Rc::new(CompileError{
+        error_type : CompileErrorType::ParserInvalidCopyOption,
+        token : token.clone(),
+        error_msg : error_msg,
+    })]
+}
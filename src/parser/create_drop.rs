@@ -1,161 +1,286 @@
-use std::fmt;
-use std::fmt::{Formatter, Display};
-use std::option::Option::{Some, None};
-use super::lexer::{TokenIter, TokenType};
-use super::compile_error::ErrorList;
-use super::common::{
-    consume_next_token_with_type,
-    consume_next_token_with_type_list,
-    check_parse_to_end,
-    exp_list_to_string,
-    parse_list_helper,
-    seq_parse_helper,
-};
-
-
-#[derive(Debug)]
-pub struct CreateStatement {
-    pub table : String,
-    pub decl_list : AttrDeclList,
-}
-
-impl Display for CreateStatement {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        write!(f, "create table {} ({})", self.table, exp_list_to_string(&self.decl_list))
-    }
-}
-
-impl CreateStatement {
-    pub fn parse(it : &mut TokenIter) -> Result<CreateStatement, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Create));
-        try!(consume_next_token_with_type(it, TokenType::Table));
-        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
-        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
-        let decl_list = try!(AttributeDeclaration::parse_list(it));
-        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
-        match check_parse_to_end(it) {
-            Some(err) => Err(vec![err]),
-            None => Ok(CreateStatement {
-                table : table_token.value.clone(),
-                decl_list : decl_list,
-            }),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub enum AttrType {
-    Int,
-    Float,
-    Char{ len : String },
-}
-
-impl Display for AttrType {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        match self {
-            &AttrType::Int => write!(f, "Int"),
-            &AttrType::Float => write!(f, "Float"),
-            &AttrType::Char{ ref len } => write!(f, "Char({})", len),
-        }
-    }
-}
-
-impl AttrType {
-    pub fn parse(it : &mut TokenIter) -> Result<AttrType, ErrorList> {
-        let data_type_tokens = vec![TokenType::Int, TokenType::Float, TokenType::Char];
-        let token = try!(consume_next_token_with_type_list(it, &data_type_tokens));
-        match token.token_type {
-            TokenType::Int => Ok(AttrType::Int),
-            TokenType::Float => Ok(AttrType::Float),
-            TokenType::Char => {
-                try!(consume_next_token_with_type(it, TokenType::OpenBracket));
-                let len_token = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
-                try!(consume_next_token_with_type(it, TokenType::CloseBracket));
-                Ok(AttrType::Char{ len : len_token.value.clone() })
-            }
-            other => panic!("unexpected token: {:?}", other),
-        }
-    }
-}
-
-pub type AttrDeclList = Vec<AttributeDeclaration>;
-
-#[derive(Debug)]
-pub struct AttributeDeclaration {
-    pub name : String,
-    pub attr_type : AttrType,
-    pub nullable : bool,
-    pub primary : bool,
-}
-
-impl Display for AttributeDeclaration {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        let null = if self.nullable {" null"} else {" not null"};
-        let primary = if self.primary {" primary"} else {""};
-        write!(f, "({} {}{}{})", self.name, self.attr_type, null, primary)
-    }
-}
-
-impl AttributeDeclaration {
-    pub fn parse_list(it : &mut TokenIter) -> Result<AttrDeclList, ErrorList> {
-        parse_list_helper(AttributeDeclaration::parse_decl, it)
-    }
-    pub fn parse_decl(it : &mut TokenIter) -> Result<AttributeDeclaration, ErrorList> {
-        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
-        let attr_type = try!(AttrType::parse(it));
-        let nullable = !is_match!(seq_parse_helper(
-            AttributeDeclaration::parse_null_specifier, it), (Some(false), _));
-        let primary = is_match!(seq_parse_helper(
-            AttributeDeclaration::parse_primary, it), (Some(true), _));
-        Ok(AttributeDeclaration{
-            name : table_token.value.clone(),
-            attr_type : attr_type,
-            nullable : nullable,
-            primary : primary,
-        })
-    }
-    fn parse_primary(it : &mut TokenIter) -> Result<bool, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Primary));
-        Ok(true)
-    }
-    fn parse_null_specifier(it : &mut TokenIter) -> Result<bool, ErrorList> {
-        or_parse_combine!(it,
-            AttributeDeclaration::parse_null,
-            AttributeDeclaration::parse_not_null
-        )
-    }
-    fn parse_null(it : &mut TokenIter) -> Result<bool, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Null));
-        Ok(true)
-    }
-    fn parse_not_null(it : &mut TokenIter) -> Result<bool, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Not));
-        try!(consume_next_token_with_type(it, TokenType::Null));
-        Ok(false)
-    }
-}
-
-#[derive(Debug)]
-pub struct DropStatement {
-    pub table : String,
-}
-
-impl Display for DropStatement {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        write!(f, "drop table {}", self.table)
-    }
-}
-
-impl DropStatement {
-    pub fn parse(it : &mut TokenIter) -> Result<DropStatement, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Drop));
-        try!(consume_next_token_with_type(it, TokenType::Table));
-        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
-        match check_parse_to_end(it) {
-            Some(err) => Err(vec![err]),
-            None => Ok(DropStatement{
-                table : table_token.value.clone(),
-            })
-        }
-    }
-}
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::option::Option::{Some, None};
+use std::rc::Rc;
+use super::lexer::{TokenIter, TokenType, TokenLine};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::sem_check::create_error;
+use super::condition::ArithExpr;
+use super::common::{
+    ToSql,
+    consume_next_token_with_type,
+    get_next_token,
+    consume_next_token_with_type_list,
+    check_parse_to_end,
+    exp_list_to_string,
+    parse_list_helper,
+    seq_parse_helper,
+    sql_list_to_string,
+};
+
+
+#[derive(Debug, Clone)]
+pub struct CreateStatement {
+    pub table : String,
+    pub decl_list : AttrDeclList,
+    pub compression : bool,
+    pub fill_factor : Option<String>,
+}
+
+impl Display for CreateStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let mut options = String::new();
+        if self.compression { options.push_str(" compression"); }
+        if let Some(ref n) = self.fill_factor { options.push_str(&format!(" fill_factor {}", n)); }
+        if !options.is_empty() { options = format!(" with{}", options); }
+        write!(f, "create table {} ({}){}", self.table, exp_list_to_string(&self.decl_list), options)
+    }
+}
+
+impl ToSql for CreateStatement {
+    fn to_sql(&self) -> String {
+        let mut options = String::new();
+        if self.compression { options.push_str(" compression"); }
+        if let Some(ref n) = self.fill_factor { options.push_str(&format!(" fill_factor {}", n)); }
+        if !options.is_empty() { options = format!(" with{}", options); }
+        format!("create table {} ({}){}", self.table, sql_list_to_string(&self.decl_list), options)
+    }
+}
+
+impl CreateStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<CreateStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Create));
+        try!(consume_next_token_with_type(it, TokenType::Table));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let decl_list = try!(AttributeDeclaration::parse_list(it));
+        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        let (compression, fill_factor) = try!(CreateStatement::parse_table_options(it));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CreateStatement {
+                table : table_token.value.clone(),
+                decl_list : decl_list,
+                compression : compression,
+                fill_factor : fill_factor,
+            }),
+        }
+    }
+    fn parse_table_options(it : &mut TokenIter) -> Result<(bool, Option<String>), ErrorList> {
+        // `with`, `compression` and `fill_factor` are not keywords
+        let mut compression = false;
+        let mut fill_factor = None;
+        match get_next_token(it) {
+            Ok(ref token) if token.token_type == TokenType::Identifier
+                && token.value.to_lowercase() == "with" => { it.next(); }
+            _ => return Ok((compression, fill_factor)),
+        }
+        loop {
+            let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+            match &token.value.to_lowercase()[..] {
+                "compression" => compression = true,
+                "fill_factor" => {
+                    let t = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
+                    fill_factor = Some(t.value.clone());
+                }
+                _ => return Err(vec![Rc::new(CompileError{
+                    error_type : CompileErrorType::ParserUnExpectedTokenType,
+                    token : token.clone(),
+                    error_msg : format!("expect `compression` or `fill_factor`, but got {}", token.value),
+                })]),
+            }
+            match get_next_token(it) {
+                Ok(ref token) if token.token_type == TokenType::Identifier => (),
+                _ => return Ok((compression, fill_factor)),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AttrType {
+    Int,
+    Float,
+    Char{ len : String },
+}
+
+impl Display for AttrType {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &AttrType::Int => write!(f, "Int"),
+            &AttrType::Float => write!(f, "Float"),
+            &AttrType::Char{ ref len } => write!(f, "Char({})", len),
+        }
+    }
+}
+
+impl ToSql for AttrType {
+    fn to_sql(&self) -> String {
+        match self {
+            &AttrType::Int => "int".to_string(),
+            &AttrType::Float => "float".to_string(),
+            &AttrType::Char{ ref len } => format!("char({})", len),
+        }
+    }
+}
+
+impl AttrType {
+    pub fn parse(it : &mut TokenIter) -> Result<AttrType, ErrorList> {
+        let data_type_tokens = vec![TokenType::Int, TokenType::Float, TokenType::Char];
+        let token = try!(consume_next_token_with_type_list(it, &data_type_tokens));
+        match token.token_type {
+            TokenType::Int => Ok(AttrType::Int),
+            TokenType::Float => Ok(AttrType::Float),
+            TokenType::Char => {
+                try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+                let len_token = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
+                try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+                Ok(AttrType::Char{ len : len_token.value.clone() })
+            }
+            other => panic!("unexpected token: {:?}", other),
+        }
+    }
+}
+
+pub type AttrDeclList = Vec<AttributeDeclaration>;
+
+#[derive(Debug, Clone)]
+pub struct AttributeDeclaration {
+    pub name : String,
+    pub attr_type : AttrType,
+    pub nullable : bool,
+    pub primary : bool,
+    pub generated : Option<ArithExpr>,  // computed from the other attributes of the row
+}
+
+impl Display for AttributeDeclaration {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let null = if self.nullable {" null"} else {" not null"};
+        let primary = if self.primary {" primary"} else {""};
+        let generated = match self.generated {
+            Some(ref expr) => format!(" generated as {}", expr),
+            None => String::new(),
+        };
+        write!(f, "({} {}{}{}{})", self.name, self.attr_type, null, primary, generated)
+    }
+}
+
+impl ToSql for AttributeDeclaration {
+    fn to_sql(&self) -> String {
+        // nullable by default
+        let null = if self.nullable {""} else {" not null"};
+        let primary = if self.primary {" primary"} else {""};
+        let generated = match self.generated {
+            Some(ref expr) => format!(" generated as ({})", expr.to_sql()),
+            None => String::new(),
+        };
+        format!("{} {}{}{}{}", self.name, self.attr_type.to_sql(), null, primary, generated)
+    }
+}
+
+impl AttributeDeclaration {
+    pub fn parse_list(it : &mut TokenIter) -> Result<AttrDeclList, ErrorList> {
+        parse_list_helper(AttributeDeclaration::parse_decl, it)
+    }
+    pub fn parse_decl(it : &mut TokenIter) -> Result<AttributeDeclaration, ErrorList> {
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let attr_type = try!(AttrType::parse(it));
+        let nullable = !is_match!(seq_parse_helper(
+            AttributeDeclaration::parse_null_specifier, it), (Some(false), _));
+        let primary = is_match!(seq_parse_helper(
+            AttributeDeclaration::parse_primary, it), (Some(true), _));
+        let generated = try!(AttributeDeclaration::parse_generated(it));
+        Ok(AttributeDeclaration{
+            name : table_token.value.clone(),
+            attr_type : attr_type,
+            nullable : nullable,
+            primary : primary,
+            generated : generated,
+        })
+    }
+    fn parse_generated(it : &mut TokenIter) -> Result<Option<ArithExpr>, ErrorList> {
+        // `generated` and `as` are not keywords
+        match get_next_token(it) {
+            Ok(ref token) if token.token_type == TokenType::Identifier
+                && token.value.to_lowercase() == "generated" => { it.next(); }
+            _ => return Ok(None),
+        }
+        let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if token.value.to_lowercase() != "as" {
+            return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : token.clone(),
+                error_msg : format!("expect `as` after `generated`, but got {}", token.value),
+            })]);
+        }
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let expr = try!(ArithExpr::parse(it));
+        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        Ok(Some(expr))
+    }
+    fn parse_primary(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Primary));
+        Ok(true)
+    }
+    fn parse_null_specifier(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        or_parse_combine!(it,
+            AttributeDeclaration::parse_null,
+            AttributeDeclaration::parse_not_null
+        )
+    }
+    fn parse_null(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Null));
+        Ok(true)
+    }
+    fn parse_not_null(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Not));
+        try!(consume_next_token_with_type(it, TokenType::Null));
+        Ok(false)
+    }
+}
+
+// the expression of a generated attribute saved in the catalog, with its literals checked
+pub fn parse_generated_expr(sql : &str) -> Result<ArithExpr, ErrorList> {
+    let line = TokenLine::parse(sql);
+    if !line.errors.is_empty() {
+        return Err(line.errors);
+    }
+    let mut it = line.tokens.iter();
+    let mut expr = try!(ArithExpr::parse(&mut it));
+    if let Some(err) = check_parse_to_end(&it) {
+        return Err(vec![err]);
+    }
+    try!(expr.check_literals().map_err(|err_msg| create_error(CompileErrorType::SemInvalidLiteral, err_msg)));
+    Ok(expr)
+}
+
+#[derive(Debug, Clone)]
+pub struct DropStatement {
+    pub table : String,
+}
+
+impl Display for DropStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "drop table {}", self.table)
+    }
+}
+
+impl ToSql for DropStatement {
+    fn to_sql(&self) -> String {
+        format!("drop table {}", self.table)
+    }
+}
+
+impl DropStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<DropStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Drop));
+        try!(consume_next_token_with_type(it, TokenType::Table));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(DropStatement{
+                table : table_token.value.clone(),
+            })
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::rc::Rc;
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenList, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::common::{
+    consume_next_token_with_type,
+    check_parse_to_end,
+};
+use super::prepare::check_no_placeholder;
+
+
+// `declare name cursor for select ...`, `fetch n from name` and `close name`
+// belong to a connection like the prepare commands
+#[derive(Debug)]
+pub enum CursorCommand {
+    Declare{ name : String, tokens : TokenList },
+    Fetch{ name : String, count : usize },
+    Close{ name : String },
+}
+
+impl CursorCommand {
+    // None if the tokens are not a cursor command
+    pub fn parse(tokens : &TokenList) -> Option<Result<CursorCommand, ErrorList>> {
+        let command = match tokens.first() {
+            Some(token) if token.token_type == TokenType::Identifier => token.value.to_lowercase(),
+            _ => return None,
+        };
+        let mut it = tokens.iter();
+        let result = match command.as_ref() {
+            "declare" => CursorCommand::parse_declare(&mut it),
+            "fetch" => CursorCommand::parse_fetch(&mut it),
+            "close" => CursorCommand::parse_close(&mut it),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn parse_declare(it : &mut TokenIter) -> Result<CursorCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        // `cursor` and `for` are not keywords either
+        for word in vec!["cursor", "for"] {
+            let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+            if token.value.to_lowercase() != word {
+                return Err(vec![Rc::new(CompileError{
+                    error_type : CompileErrorType::ParserUnExpectedTokenType,
+                    token : token.clone(),
+                    error_msg : format!("expect `{}`, but got {}", word, token.value),
+                })]);
+            }
+        }
+        let tokens : TokenList = it.cloned().collect();
+        try!(check_no_placeholder(&tokens));
+        Ok(CursorCommand::Declare{ name : name_token.value.clone(), tokens : tokens })
+    }
+
+    fn parse_fetch(it : &mut TokenIter) -> Result<CursorCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let count_token = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
+        let count = match count_token.value.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : count_token.clone(),
+                error_msg : format!("expect a positive number of rows, but got {}", count_token.value),
+            })]),
+        };
+        try!(consume_next_token_with_type(it, TokenType::From));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CursorCommand::Fetch{ name : name_token.value.clone(), count : count }),
+        }
+    }
+
+    fn parse_close(it : &mut TokenIter) -> Result<CursorCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CursorCommand::Close{ name : name_token.value.clone() }),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+use std::option::Option::{Some, None};
+use ::store::table::Privilege;
+use super::lexer::{TokenIter, TokenList, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::common::{
+    consume_next_token_with_type,
+    consume_next_token_with_type_list,
+    consume_word,
+    check_parse_to_end,
+};
+
+
+// `grant privilege, ... on table to user` and `revoke privilege, ... on table from user`,
+// a privilege is select, insert, update, delete or all of them
+#[derive(Debug, PartialEq)]
+pub struct GrantCommand {
+    pub grant : bool,  // false for revoke
+    pub privileges : Vec<Privilege>,
+    pub table : String,
+    pub user : String,
+}
+
+impl GrantCommand {
+    // None if the tokens are not a grant or revoke
+    pub fn parse(tokens : &TokenList) -> Option<Result<GrantCommand, ErrorList>> {
+        let command = match tokens.first() {
+            Some(token) if token.token_type == TokenType::Identifier => token.value.to_lowercase(),
+            _ => return None,
+        };
+        let grant = match command.as_ref() {
+            "grant" => true,
+            "revoke" => false,
+            _ => return None,
+        };
+        let mut it = tokens.iter();
+        it.next();
+        Some(GrantCommand::parse_rest(grant, &mut it))
+    }
+
+    fn parse_rest(grant : bool, it : &mut TokenIter) -> Result<GrantCommand, ErrorList> {
+        let privileges = try!(GrantCommand::parse_privileges(it));
+        try!(consume_word(it, "on"));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if grant {
+            try!(consume_word(it, "to"));
+        } else {
+            try!(consume_next_token_with_type(it, TokenType::From));
+        }
+        let user_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if let Some(err) = check_parse_to_end(it) {
+            return Err(vec![err]);
+        }
+        Ok(GrantCommand{
+            grant : grant,
+            privileges : privileges,
+            table : table_token.value.clone(),
+            user : user_token.value.clone(),
+        })
+    }
+
+    fn parse_privileges(it : &mut TokenIter) -> Result<Vec<Privilege>, ErrorList> {
+        let type_list = vec![TokenType::Select, TokenType::Insert, TokenType::Update, TokenType::Delete,
+            TokenType::Identifier];
+        let mut privileges = Vec::new();
+        loop {
+            let token = try!(consume_next_token_with_type_list(it, &type_list));
+            match token.token_type {
+                TokenType::Select => privileges.push(Privilege::Select),
+                TokenType::Insert => privileges.push(Privilege::Insert),
+                TokenType::Update => privileges.push(Privilege::Update),
+                TokenType::Delete => privileges.push(Privilege::Delete),
+                // `all` is not a keyword either
+                _ if token.value.to_lowercase() == "all" => privileges.extend(Privilege::all()),
+                _ => return Err(vec![Rc::new(CompileError{
+                    error_type : CompileErrorType::ParserUnExpectedTokenType,
+                    token : token.clone(),
+                    error_msg : format!("expect a privilege, but got {}", token.value),
+                })]),
+            }
+            match it.clone().next() {
+                Some(token) if token.token_type == TokenType::Comma => { it.next(); }
+                _ => return Ok(privileges),
+            }
+        }
+    }
+}
@@ -1,31 +1,52 @@
-pub mod lexer;
-pub mod compile_error;
-
-#[macro_use]
-#[allow(dead_code)]
-pub mod common;
-#[allow(dead_code)]
-pub mod attribute;
-#[allow(dead_code)]
-pub mod condition;
-
-#[allow(dead_code)]
-pub mod select;
-#[allow(dead_code)]
-pub mod update;
-#[allow(dead_code)]
-pub mod insert;
-#[allow(dead_code)]
-pub mod delete;
-#[allow(dead_code)]
-pub mod create_drop;
-#[allow(dead_code)]
-pub mod sem_check;
-#[allow(dead_code)]
-pub mod unimpl;
-
-pub use self::select::SelectStatement;
-pub use self::update::UpdateStatement;
-pub use self::insert::InsertStatement;
-pub use self::delete::DeleteStatement;
-pub use self::create_drop::{CreateStatement, DropStatement};
+pub mod lexer;
+pub mod compile_error;
+
+#[macro_use]
+#[allow(dead_code)]
+pub mod common;
+#[allow(dead_code)]
+pub mod attribute;
+#[allow(dead_code)]
+pub mod condition;
+
+#[allow(dead_code)]
+pub mod select;
+#[allow(dead_code)]
+pub mod update;
+#[allow(dead_code)]
+pub mod insert;
+#[allow(dead_code)]
+pub mod delete;
+#[allow(dead_code)]
+pub mod create_drop;
+#[allow(dead_code)]
+pub mod alter;
+#[allow(dead_code)]
+pub mod copy;
+#[allow(dead_code)]
+pub mod backup;
+#[allow(dead_code)]
+pub mod analyze;
+#[allow(dead_code)]
+pub mod sem_check;
+#[allow(dead_code)]
+pub mod unimpl;
+#[allow(dead_code)]
+pub mod prepare;
+#[allow(dead_code)]
+pub mod variable;
+#[allow(dead_code)]
+pub mod cursor;
+#[allow(dead_code)]
+pub mod grant;
+
+pub use self::common::{Statement, parse_statement};
+pub use self::select::SelectStatement;
+pub use self::update::UpdateStatement;
+pub use self::insert::InsertStatement;
+pub use self::delete::DeleteStatement;
+pub use self::create_drop::{CreateStatement, DropStatement};
+pub use self::alter::AlterStatement;
+pub use self::copy::{CopyFromStatement, CopyToStatement};
+pub use self::backup::{BackupStatement, RestoreStatement};
+pub use self::analyze::AnalyzeStatement;
@@ -0,0 +1,208 @@
+use std::rc::Rc;
+use std::option::Option::{Some, None};
+use super::lexer::{Token, TokenIter, TokenList, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::common::{
+    Statement,
+    ValueExpr,
+    ValueType,
+    ValueList,
+    consume_next_token_with_type,
+    check_parse_to_end,
+    get_next_token,
+    parse_list_helper,
+};
+use super::select::{SelectStatement, Relation};
+use super::condition::{ConditionExpr, CmpOperantExpr, ArithExpr};
+
+
+// `prepare name as statement`, `execute name (value, ...)` and `deallocate name`
+// belong to a connection and never reach the plan
+#[derive(Debug)]
+pub enum PrepareCommand {
+    Prepare{ name : String, tokens : TokenList },
+    Execute{ name : String, params : ValueList },
+    Deallocate{ name : String },
+}
+
+impl PrepareCommand {
+    // None if the tokens are not a prepare command
+    pub fn parse(tokens : &TokenList) -> Option<Result<PrepareCommand, ErrorList>> {
+        let command = match tokens.first() {
+            Some(token) if token.token_type == TokenType::Identifier => token.value.to_lowercase(),
+            _ => return None,
+        };
+        if command == "execute" || command == "deallocate" {
+            if let Err(err_list) = check_no_placeholder(tokens) {
+                return Some(Err(err_list));
+            }
+        }
+        let mut it = tokens.iter();
+        let result = match command.as_ref() {
+            "prepare" => PrepareCommand::parse_prepare(&mut it),
+            "execute" => PrepareCommand::parse_execute(&mut it),
+            "deallocate" => PrepareCommand::parse_deallocate(&mut it),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn parse_prepare(it : &mut TokenIter) -> Result<PrepareCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        // `as` is not a keyword either
+        let as_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if as_token.value.to_lowercase() != "as" {
+            return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : as_token.clone(),
+                error_msg : format!("expect `as`, but got {}", as_token.value),
+            })]);
+        }
+        Ok(PrepareCommand::Prepare{
+            name : name_token.value.clone(),
+            tokens : it.cloned().collect(),
+        })
+    }
+
+    fn parse_execute(it : &mut TokenIter) -> Result<PrepareCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let mut params = ValueList::new();
+        if get_next_token(it).is_ok() {
+            try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+            params = try!(parse_list_helper(ValueExpr::parse, it));
+            try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        }
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(PrepareCommand::Execute{
+                name : name_token.value.clone(),
+                params : params,
+            }),
+        }
+    }
+
+    fn parse_deallocate(it : &mut TokenIter) -> Result<PrepareCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(PrepareCommand::Deallocate{ name : name_token.value.clone() }),
+        }
+    }
+}
+
+pub fn check_no_placeholder(tokens : &TokenList) -> Result<(), ErrorList> {
+    match tokens.iter().filter(|t| t.token_type == TokenType::Placeholder).next() {
+        None => Ok(()),
+        Some(token) => Err(vec![Rc::new(CompileError{
+            error_type : CompileErrorType::ParserUnboundPlaceholder,
+            token : token.clone(),
+            error_msg : "placeholder is only allowed in prepared statements".to_string(),
+        })]),
+    }
+}
+
+// the value of each placeholder becomes the index of its parameter
+pub fn number_placeholders(tokens : &TokenList) -> (TokenList, usize) {
+    let mut param_num = 0;
+    let mut numbered = TokenList::new();
+    for token in tokens.iter() {
+        if token.token_type == TokenType::Placeholder {
+            numbered.push(Rc::new(Token{
+                column : token.column,
+                value : param_num.to_string(),
+                token_type : TokenType::Placeholder,
+            }));
+            param_num += 1;
+        } else {
+            numbered.push(token.clone());
+        }
+    }
+    (numbered, param_num)
+}
+
+// the number of params should have been checked,
+// the types are checked by check_sem after binding
+pub fn bind_params(stmt : &mut Statement, params : &ValueList) {
+    match stmt {
+        &mut Statement::Select(ref mut select) => bind_select(select, params),
+        &mut Statement::Update(ref mut update) => {
+            for assign in update.set_list.iter_mut() {
+                bind_value(&mut assign.value, params);
+            }
+            bind_opt_cond(&mut update.where_condition, params);
+        }
+        &mut Statement::Insert(ref mut insert) => {
+            for value in insert.value_list.iter_mut() {
+                bind_value(value, params);
+            }
+        }
+        &mut Statement::Delete(ref mut delete) => bind_opt_cond(&mut delete.where_condition, params),
+        &mut Statement::CopyTo(ref mut copy) => bind_select(&mut copy.select, params),
+        _ => (),
+    }
+}
+
+fn bind_select(select : &mut SelectStatement, params : &ValueList) {
+    for with_table in select.with_list.iter_mut() {
+        bind_select(&mut with_table.select, params);
+    }
+    for relation in select.relation_list.iter_mut() {
+        if let &mut Relation::Select(ref mut sub_select) = relation {
+            bind_select(sub_select, params);
+        }
+    }
+    bind_opt_cond(&mut select.where_condition, params);
+    if let Some(ref mut groupby_having) = select.groupby_having {
+        bind_opt_cond(&mut groupby_having.having_condition, params);
+    }
+}
+
+fn bind_opt_cond(condition : &mut Option<ConditionExpr>, params : &ValueList) {
+    if let &mut Some(ref mut cond) = condition {
+        bind_cond(cond, params);
+    }
+}
+
+fn bind_cond(condition : &mut ConditionExpr, params : &ValueList) {
+    match condition {
+        &mut ConditionExpr::NotExpr{ref mut operant} => bind_cond(operant, params),
+        &mut ConditionExpr::LogicExpr{ref mut lhs, ref mut rhs, .. } => {
+            bind_cond(lhs, params);
+            bind_cond(rhs, params);
+        }
+        &mut ConditionExpr::CmpExpr{ref mut lhs, ref mut rhs, .. } => {
+            bind_cmp_operant(lhs, params);
+            bind_cmp_operant(rhs, params);
+        }
+    }
+}
+
+fn bind_cmp_operant(operant : &mut CmpOperantExpr, params : &ValueList) {
+    let bound = match operant {
+        &mut CmpOperantExpr::Value(ref value) if value.value_type == ValueType::Placeholder => {
+            let param = get_param(value, params);
+            // numbers are parsed as arithmetic expressions
+            match param.value_type {
+                ValueType::Integer | ValueType::Float =>
+                    CmpOperantExpr::Arith(ArithExpr::Value(param)),
+                _ => CmpOperantExpr::Value(param),
+            }
+        }
+        _ => return,
+    };
+    *operant = bound;
+}
+
+fn bind_value(value : &mut ValueExpr, params : &ValueList) {
+    if value.value_type == ValueType::Placeholder {
+        *value = get_param(value, params);
+    }
+}
+
+fn get_param(placeholder : &ValueExpr, params : &ValueList) -> ValueExpr {
+    let index = placeholder.value.parse::<usize>().unwrap();
+    params[index].clone()
+}
@@ -1,190 +1,293 @@
-use std::fmt;
-use std::fmt::{Formatter, Display};
-use std::vec::Vec;
-use std::option::Option::{Some, None};
-use super::lexer::{TokenIter, TokenType};
-use super::attribute::{AttributeExpr, AttributeList};
-use super::condition::ConditionExpr;
-use super::compile_error::ErrorList;
-use super::common::{
-    get_next_token,
-    consume_next_token_with_type,
-    check_parse_to_end,
-    seq_parse_helper,
-    exp_list_to_string,
-    concat_format,
-    concat_error_list,
-    parse_list_helper,
-    align_iter,
-};
-
-
-#[derive(Debug)]
-pub struct SelectStatement {
-    pub select_expr : SelectExpr,
-    pub relation_list : Vec<Relation>,
-    pub where_condition : Option<ConditionExpr>,
-    pub groupby_having : Option<GroupbyHaving>,
-    pub order_by_attr : Option<AttributeExpr>,
-}
-
-impl Display for SelectStatement {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        let mut s = format!("{} from {}", self.select_expr, exp_list_to_string(&self.relation_list));
-        s = concat_format(s, "where ", &self.where_condition);
-        s = concat_format(s, "", &self.groupby_having);
-        s = concat_format(s, "order by ", &self.order_by_attr);
-        write!(f, "{}", s)
-    }
-}
-
-impl SelectStatement {
-    pub fn parse_as_sub_relation(it : &mut TokenIter) -> Result<SelectStatement, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
-        let select_expr = try!(SelectExpr::parse(it));
-        let relation_list = try!(Relation::parse(it));
-        let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
-        let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
-        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
-        match consume_next_token_with_type(it, TokenType::CloseBracket) {
-            Err(errs) => Err(concat_error_list(vec![errs, es1, es2, es3])),
-            Ok(..) => Ok(SelectStatement {
-                    select_expr : select_expr,
-                    relation_list : relation_list,
-                    where_condition : where_condition,
-                    groupby_having : groupby_having,
-                    order_by_attr : order_by_attr,
-                })
-        }
-    }
-    pub fn parse(it : &mut TokenIter) -> Result<SelectStatement, ErrorList> {
-        let select_expr = try!(SelectExpr::parse(it));
-        let relation_list = try!(Relation::parse(it));
-        let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
-        let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
-        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
-        match check_parse_to_end(it) {
-            Some(err) => Err(concat_error_list(vec![vec![err], es1, es2, es3])),
-            None => Ok(SelectStatement {
-                select_expr : select_expr,
-                relation_list : relation_list,
-                where_condition : where_condition,
-                groupby_having : groupby_having,
-                order_by_attr : order_by_attr,
-            }),
-        }
-    }
-    pub fn parse_where(it : &mut TokenIter) -> Result<ConditionExpr, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Where));
-        ConditionExpr::parse(it)
-    }
-    pub fn parse_order_by(it : &mut TokenIter) -> Result<AttributeExpr, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Order));
-        try!(consume_next_token_with_type(it, TokenType::By));
-        AttributeExpr::parse(it)
-    }
-}
-
-#[derive(Debug)]
-pub enum SelectExpr {
-    AllAttribute,
-    AttrList(AttributeList),
-}
-
-impl SelectExpr {
-    pub fn parse(it : &mut TokenIter) -> Result<SelectExpr, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Select));
-        let token = try!(get_next_token(it));
-        match token.token_type {
-            TokenType::Star => {
-                it.next();
-                Ok(SelectExpr::AllAttribute)
-            }
-            _ => Ok(SelectExpr::AttrList(try!(AttributeExpr::parse_list(it))))
-        }
-    }
-}
-
-impl Display for SelectExpr {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        match self {
-            &SelectExpr::AllAttribute => write!(f, "select *"),
-            &SelectExpr::AttrList(ref attr_list) => write!(f, "select {}", exp_list_to_string(attr_list)),
-        }
-    }
-}
-
-
-pub type RelationList = Vec<Relation>;
-
-#[derive(Debug)]
-pub enum Relation {
-    TableName(String),
-    Select(SelectStatement),
-}
-
-impl Display for Relation {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        match self {
-            &Relation::TableName(ref name) => write!(f, "{}", name),
-            &Relation::Select(ref select) => write!(f, "({})", select),
-        }
-    }
-}
-
-impl Relation {
-    pub fn parse(it : &mut TokenIter) -> Result<RelationList, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::From));
-        Relation::parse_list(it)
-    }
-    pub fn parse_list(it : &mut TokenIter) -> Result<RelationList, ErrorList> {
-        parse_list_helper(Relation::parse_relation, it)
-    }
-    pub fn parse_relation(it : &mut TokenIter) -> Result<Relation, ErrorList> {
-        let token = try!(get_next_token(it));
-        match token.token_type {
-            TokenType::OpenBracket =>
-                Ok(Relation::Select(try!(SelectStatement::parse_as_sub_relation(it)))),
-            _ => {
-                let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
-                Ok(Relation::TableName(token.value.clone()))
-            }
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct GroupbyHaving {
-    pub attr : AttributeExpr,
-    pub having_condition : Option<ConditionExpr>,
-}
-
-impl Display for GroupbyHaving {
-    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        match &self.having_condition {
-            &Some(ref cond_expr) => write!(f, "group by {} having {}", self.attr, cond_expr),
-            &None => write!(f, "group by {}", self.attr),
-        }
-    }
-}
-
-impl GroupbyHaving {
-    pub fn parse(it : &mut TokenIter) -> Result<GroupbyHaving, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Group));
-        try!(consume_next_token_with_type(it, TokenType::By));
-        let attr = try!(AttributeExpr::parse(it));
-        let mut tmp = it.clone();
-        match GroupbyHaving::parse_having(&mut tmp) {
-            Ok(cond) => {
-                align_iter(it, &mut tmp);
-                Ok(GroupbyHaving{ attr : attr, having_condition : Some(cond) })
-            }
-            Err(..) => Ok(GroupbyHaving{ attr : attr, having_condition : None }),
-        }
-    }
-
-    pub fn parse_having(it : &mut TokenIter) -> Result<ConditionExpr, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Having));
-        ConditionExpr::parse(it)
-    }
-}
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::vec::Vec;
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenType};
+use super::attribute::{AttributeExpr, AttributeList};
+use super::condition::ConditionExpr;
+use super::compile_error::ErrorList;
+use super::common::{
+    ToSql,
+    get_next_token,
+    consume_next_token_with_type,
+    consume_word,
+    check_parse_to_end,
+    seq_parse_helper,
+    exp_list_to_string,
+    concat_format,
+    concat_error_list,
+    parse_list_helper,
+    align_iter,
+    sql_list_to_string,
+    concat_sql,
+};
+
+
+#[derive(Debug, Clone)]
+pub struct SelectStatement {
+    pub with_list : Vec<WithTable>,  // empty without the with clause
+    pub select_expr : SelectExpr,
+    pub relation_list : Vec<Relation>,
+    pub where_condition : Option<ConditionExpr>,
+    pub groupby_having : Option<GroupbyHaving>,
+    pub order_by_attr : Option<AttributeExpr>,
+}
+
+impl Display for SelectStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let mut s = format!("{} from {}", self.select_expr, exp_list_to_string(&self.relation_list));
+        if !self.with_list.is_empty() {
+            s = format!("with {} {}", exp_list_to_string(&self.with_list), s);
+        }
+        s = concat_format(s, "where ", &self.where_condition);
+        s = concat_format(s, "", &self.groupby_having);
+        s = concat_format(s, "order by ", &self.order_by_attr);
+        write!(f, "{}", s)
+    }
+}
+
+impl ToSql for SelectStatement {
+    fn to_sql(&self) -> String {
+        let mut s = format!("{} from {}", self.select_expr.to_sql(), sql_list_to_string(&self.relation_list));
+        if !self.with_list.is_empty() {
+            s = format!("with {} {}", sql_list_to_string(&self.with_list), s);
+        }
+        s = concat_sql(s, "where ", &self.where_condition);
+        s = concat_sql(s, "", &self.groupby_having);
+        s = concat_sql(s, "order by ", &self.order_by_attr);
+        s
+    }
+}
+
+impl SelectStatement {
+    pub fn parse_as_sub_relation(it : &mut TokenIter) -> Result<SelectStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let select_expr = try!(SelectExpr::parse(it));
+        let relation_list = try!(Relation::parse(it));
+        let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
+        let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
+        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
+        match consume_next_token_with_type(it, TokenType::CloseBracket) {
+            Err(errs) => Err(concat_error_list(vec![errs, es1, es2, es3])),
+            Ok(..) => Ok(SelectStatement {
+                    with_list : Vec::new(),
+                    select_expr : select_expr,
+                    relation_list : relation_list,
+                    where_condition : where_condition,
+                    groupby_having : groupby_having,
+                    order_by_attr : order_by_attr,
+                })
+        }
+    }
+    pub fn parse(it : &mut TokenIter) -> Result<SelectStatement, ErrorList> {
+        let select_expr = try!(SelectExpr::parse(it));
+        let relation_list = try!(Relation::parse(it));
+        let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
+        let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
+        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
+        match check_parse_to_end(it) {
+            Some(err) => Err(concat_error_list(vec![vec![err], es1, es2, es3])),
+            None => Ok(SelectStatement {
+                with_list : Vec::new(),
+                select_expr : select_expr,
+                relation_list : relation_list,
+                where_condition : where_condition,
+                groupby_having : groupby_having,
+                order_by_attr : order_by_attr,
+            }),
+        }
+    }
+    // `with` and `as` are not keywords
+    pub fn parse_with(it : &mut TokenIter) -> Result<SelectStatement, ErrorList> {
+        try!(consume_word(it, "with"));
+        let with_list = try!(parse_list_helper(WithTable::parse, it));
+        let mut stmt = try!(SelectStatement::parse(it));
+        stmt.with_list = with_list;
+        Ok(stmt)
+    }
+    pub fn parse_where(it : &mut TokenIter) -> Result<ConditionExpr, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Where));
+        ConditionExpr::parse(it)
+    }
+    pub fn parse_order_by(it : &mut TokenIter) -> Result<AttributeExpr, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Order));
+        try!(consume_next_token_with_type(it, TokenType::By));
+        AttributeExpr::parse(it)
+    }
+}
+
+// a named select used as a table by the with tables after it and the select of the statement
+#[derive(Debug, Clone)]
+pub struct WithTable {
+    pub name : String,
+    pub select : SelectStatement,
+}
+
+impl Display for WithTable {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "{} as ({})", self.name, self.select)
+    }
+}
+
+impl ToSql for WithTable {
+    fn to_sql(&self) -> String {
+        format!("{} as ({})", self.name, self.select.to_sql())
+    }
+}
+
+impl WithTable {
+    pub fn parse(it : &mut TokenIter) -> Result<WithTable, ErrorList> {
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_word(it, "as"));
+        let select = try!(SelectStatement::parse_as_sub_relation(it));
+        Ok(WithTable{
+            name : name_token.value.clone(),
+            select : select,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SelectExpr {
+    AllAttribute,
+    TableAllAttribute(String),  // table.*
+    AttrList(AttributeList),
+}
+
+impl SelectExpr {
+    pub fn parse(it : &mut TokenIter) -> Result<SelectExpr, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Select));
+        let token = try!(get_next_token(it));
+        match token.token_type {
+            TokenType::Star => {
+                it.next();
+                Ok(SelectExpr::AllAttribute)
+            }
+            _ => match SelectExpr::parse_table_all_attribute(it) {
+                Some(table) => Ok(SelectExpr::TableAllAttribute(table)),
+                None => Ok(SelectExpr::AttrList(try!(AttributeExpr::parse_list(it)))),
+            }
+        }
+    }
+    fn parse_table_all_attribute(it : &mut TokenIter) -> Option<String> {
+        // it is not moved if the next tokens are not `table . *`
+        let mut look_ahead = it.clone();
+        let token_list : Vec<_> = look_ahead.by_ref().take(3).collect();
+        let token_type_list : Vec<TokenType> = token_list.iter().map(|tk| tk.token_type).collect();
+        if token_type_list != vec![TokenType::Identifier, TokenType::GetMember, TokenType::Star] {
+            return None;
+        }
+        align_iter(it, &mut look_ahead);
+        Some(token_list[0].value.clone())
+    }
+}
+
+impl Display for SelectExpr {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &SelectExpr::AllAttribute => write!(f, "select *"),
+            &SelectExpr::TableAllAttribute(ref table) => write!(f, "select {}.*", table),
+            &SelectExpr::AttrList(ref attr_list) => write!(f, "select {}", exp_list_to_string(attr_list)),
+        }
+    }
+}
+
+impl ToSql for SelectExpr {
+    fn to_sql(&self) -> String {
+        match self {
+            &SelectExpr::AllAttribute => "select *".to_string(),
+            &SelectExpr::TableAllAttribute(ref table) => format!("select {}.*", table),
+            &SelectExpr::AttrList(ref attr_list) => format!("select {}", sql_list_to_string(attr_list)),
+        }
+    }
+}
+
+
+pub type RelationList = Vec<Relation>;
+
+#[derive(Debug, Clone)]
+pub enum Relation {
+    TableName(String),
+    Select(SelectStatement),
+}
+
+impl Display for Relation {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &Relation::TableName(ref name) => write!(f, "{}", name),
+            &Relation::Select(ref select) => write!(f, "({})", select),
+        }
+    }
+}
+
+impl ToSql for Relation {
+    fn to_sql(&self) -> String {
+        match self {
+            &Relation::TableName(ref name) => name.clone(),
+            &Relation::Select(ref select) => format!("({})", select.to_sql()),
+        }
+    }
+}
+
+impl Relation {
+    pub fn parse(it : &mut TokenIter) -> Result<RelationList, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::From));
+        Relation::parse_list(it)
+    }
+    pub fn parse_list(it : &mut TokenIter) -> Result<RelationList, ErrorList> {
+        parse_list_helper(Relation::parse_relation, it)
+    }
+    pub fn parse_relation(it : &mut TokenIter) -> Result<Relation, ErrorList> {
+        let token = try!(get_next_token(it));
+        match token.token_type {
+            TokenType::OpenBracket =>
+                Ok(Relation::Select(try!(SelectStatement::parse_as_sub_relation(it)))),
+            _ => {
+                let token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+                Ok(Relation::TableName(token.value.clone()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupbyHaving {
+    pub attr : AttributeExpr,
+    pub having_condition : Option<ConditionExpr>,
+}
+
+impl Display for GroupbyHaving {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match &self.having_condition {
+            &Some(ref cond_expr) => write!(f, "group by {} having {}", self.attr, cond_expr),
+            &None => write!(f, "group by {}", self.attr),
+        }
+    }
+}
+
+impl ToSql for GroupbyHaving {
+    fn to_sql(&self) -> String {
+        concat_sql(format!("group by {}", self.attr.to_sql()), "having ", &self.having_condition)
+    }
+}
+
+impl GroupbyHaving {
+    pub fn parse(it : &mut TokenIter) -> Result<GroupbyHaving, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Group));
+        try!(consume_next_token_with_type(it, TokenType::By));
+        let attr = try!(AttributeExpr::parse(it));
+        let mut tmp = it.clone();
+        match GroupbyHaving::parse_having(&mut tmp) {
+            Ok(cond) => {
+                align_iter(it, &mut tmp);
+                Ok(GroupbyHaving{ attr : attr, having_condition : Some(cond) })
+            }
+            Err(..) => Ok(GroupbyHaving{ attr : attr, having_condition : None }),
+        }
+    }
+
+    pub fn parse_having(it : &mut TokenIter) -> Result<ConditionExpr, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Having));
+        ConditionExpr::parse(it)
+    }
+}
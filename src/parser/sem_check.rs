@@ -1,404 +1,821 @@
-use std::vec::Vec;
-use std::collections::HashSet;
-use super::attribute::AttributeExpr;
-use super::lexer::{Token, TokenRef, TokenType};
-use super::compile_error::{CompileError, CompileErrorType, ErrorList, ErrorRef};
-use super::common::{Statement, ValueExpr, ValueType};
-use super::select::{SelectStatement, GroupbyHaving, SelectExpr, Relation};
-use super::update::UpdateStatement;
-use super::insert::InsertStatement;
-use super::delete::DeleteStatement;
-use super::create_drop::{CreateStatement, DropStatement};
-use super::condition::{ConditionExpr, ArithExpr, CmpOperantExpr, CmpOp};
-use ::store::table::{TableSet, AttrType, Attr};
-
-
-pub type SemResult = Result<(), ErrorList>;
-
-
-pub fn check_sem(statement : &mut Statement, table_set : &TableSet) -> SemResult {
-    match statement {
-        &mut Statement::Select(ref mut stmt) => check_select(stmt, table_set),
-        &mut Statement::Update(ref mut stmt) => check_update(stmt, table_set),
-        &mut Statement::Insert(ref mut stmt) => check_insert(stmt, table_set),
-        &mut Statement::Delete(ref mut stmt) => check_delete(stmt, table_set),
-        &mut Statement::Create(ref stmt) => check_create(stmt, table_set),
-        &mut Statement::Drop(ref stmt) => check_drop(stmt, table_set),
-    }
-}
-
-pub fn check_select(stmt : &mut SelectStatement, table_set : &TableSet) -> SemResult {
-    // join not supported now
-    assert_eq!(stmt.relation_list.len(), 1);
-    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
-    try!(check_table_exist(&table_name, table_set));
-
-    if let Some(ref mut cond) = stmt.where_condition {
-        try!(check_condition(cond, table_set, &None));
-    }
-    if let Some(GroupbyHaving{ref mut attr, ref mut having_condition}) = stmt.groupby_having {
-        let (table, attr) = attr.get_attr();
-        try!(check_attr_exist(table, attr, table_set));
-        let group_by_attr = Some((table.clone(), attr.clone()));
-        if let &mut Some(ref mut cond) = having_condition {
-            try!(check_condition(cond, table_set, &group_by_attr));
-        }
-        match stmt.select_expr {
-            SelectExpr::AllAttribute =>
-                return Err(create_error(CompileErrorType::SemSelectAllWithGroupBy,
-                    "can't select all when using group by".to_string())),
-            SelectExpr::AttrList(ref mut attr_list) => {
-                for attr_expr in attr_list {
-                    try!(check_attr(attr_expr, table_set, &group_by_attr));
-                }
-            }
-        }
-        if let Some(ref mut attr) = stmt.order_by_attr {
-            try!(check_attr(attr, table_set, &group_by_attr));
-        }
-    } else {
-        if let SelectExpr::AttrList(ref mut attr_list) = stmt.select_expr {
-            for attr_expr in attr_list {
-                try!(check_attr(attr_expr, table_set, &None));
-            }
-        }
-        if let Some(ref mut attr) = stmt.order_by_attr {
-            try!(check_attr(attr, table_set, &None));
-        }
-    }
-    Ok(())
-}
-
-pub fn check_update(stmt : &mut UpdateStatement, table_set : &TableSet) -> SemResult {
-    try!(check_table_exist(&stmt.table, table_set));
-    if let Some(ref mut cond) = stmt.where_condition {
-        try!(check_condition(cond, table_set, &None));
-    }
-    for assign in &mut stmt.set_list {
-        try!(check_attr_exist(&mut Some(stmt.table.clone()), &mut assign.attr, table_set));
-        let attr = table_set.get_attr(&Some(stmt.table.clone()), &assign.attr).unwrap();
-        if attr.primary {
-            return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
-                format!("can't change primary attribute: {}", attr.name)));
-        }
-        try!(check_assign(&assign.value, &attr));
-    }
-    Ok(())
-}
-
-pub fn check_insert(stmt : &mut InsertStatement, table_set : &TableSet) -> SemResult {
-    try!(check_table_exist(&stmt.table, table_set));
-    let value_list  = &stmt.value_list;
-    let attr_list = table_set.gen_attr_list(&stmt.table);  // table should exist
-    if attr_list.len() != value_list.len() {
-        return Err(create_error(CompileErrorType::SemInvalidInsertValuesNum,
-            format!("invalid insert values number, expected {}, found {}",
-                attr_list.len(), value_list.len())));
-    }
-    for (value, attr) in value_list.iter().zip(attr_list.iter()) {
-        try!(check_assign(value, attr));
-    }
-    Ok(())
-}
-
-pub fn check_assign(value : &ValueExpr, attr : &Attr) -> SemResult {
-    match (value.value_type, attr.attr_type) {
-            (ValueType::Integer, AttrType::Int)
-        | (ValueType::Integer, AttrType::Float)
-        | (ValueType::Float, AttrType::Float) => (),
-        (ValueType::String, AttrType::Char{len}) => {
-            if value.value.len() > len {
-                return Err(create_error(CompileErrorType::SemInvalidInsertCharLen,
-                    format!("invalid char len, expected {}, found {}", len, value.value.len())));
-            }
-        }
-        (ValueType::Null, _) => {
-            if !attr.nullable {
-                return Err(create_error(CompileErrorType::SemAttributeNotNullable,
-                    format!("attribute {} is not nullable", attr.name)));
-            }
-        }
-        (value_type, attr_type) =>
-            return Err(create_error(CompileErrorType::SemInvalidInsertValueType,
-                format!("invalid insert value type, attribute type is {:?}, found {:?}",
-                    attr_type, value_type))),
-    }
-    Ok(())
-}
-
-pub fn check_delete(stmt : &mut DeleteStatement, table_set : &TableSet) -> SemResult {
-    try!(check_table_exist(&stmt.table, table_set));
-    match &mut stmt.where_condition {
-        &mut Some(ref mut cond) => check_condition(cond, table_set, &None),
-        &mut None => Ok(()),
-    }
-}
-
-pub fn check_condition(
-        condition : &mut ConditionExpr,
-        table_set : &TableSet,
-        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
-    match condition {
-        &mut ConditionExpr::NotExpr{ref mut operant} => check_condition(operant, table_set, &group_by_attr),
-        &mut ConditionExpr::LogicExpr{ref mut lhs, ref mut rhs, .. } => {
-            try!(check_condition(lhs, table_set, &group_by_attr));
-            check_condition(rhs, table_set, &group_by_attr)
-        }
-        &mut ConditionExpr::CmpExpr{ref mut lhs, ref mut rhs, op } => {
-            let must_be_num_type = match op {
-                CmpOp::LT | CmpOp::GT | CmpOp::LE | CmpOp::GE => {
-                    match (lhs.get_type(), rhs.get_type()) {
-                        (ValueType::String, _) | (ValueType::Null, _)
-                        | (_, ValueType::String) | (_, ValueType::Null) => {
-                                return Err(create_error(CompileErrorType::SemInvalidValueType,
-                                    format!("invalid operant type: {} {} {}", lhs, rhs, op)))
-                            }
-                        _ => ()
-                    }
-                    true
-                }
-                CmpOp::EQ | CmpOp::NE => {
-                    match (lhs.get_type(), rhs.get_type()) {
-                        (ValueType::Null, _) | (_, ValueType::Null)  => {
-                            return Err(create_error(CompileErrorType::SemInvalidValueType,
-                                format!("invalid operant type: {} {} {}", lhs, rhs, op)))
-                        }
-                        _ => ()
-                    }
-                    false
-                }
-                CmpOp::Is | CmpOp::IsNot => {
-                    match lhs {
-                        &mut CmpOperantExpr::Arith(ArithExpr::Attr(ref mut attr)) => {
-                            try!(check_is_nullable(attr, table_set));
-                        }
-                        _ => return Err(create_error(CompileErrorType::SemInvalidValueType,
-                            format!("expected attribute or aggregate function\
-                                in the left of `is` and `is not`, found {}", lhs)))
-                    }
-                    match rhs {
-                        &mut CmpOperantExpr::Value(ValueExpr{value_type : ValueType::Null, ..}) => (),
-                        _ => return Err(create_error(CompileErrorType::SemInvalidValueType,
-                            format!("only null is allowd after `is` or `is not`, found {}", rhs)))
-                    }
-                    false
-                }
-            };
-            if let &mut CmpOperantExpr::Arith(ref mut lhs_arith) = lhs {
-                try!(check_arith_expr(lhs_arith, table_set, must_be_num_type, &group_by_attr));
-            }
-            if let &mut CmpOperantExpr::Arith(ref mut rhs_arith) = rhs {
-                try!(check_arith_expr(rhs_arith, table_set, must_be_num_type, &group_by_attr));
-            }
-            Ok(())
-        }
-    }
-}
-
-pub fn check_is_nullable(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
-    let (table, attr) = attr_expr.get_attr();
-    try!(check_attr_exist(table, attr, table_set));
-    if !table_set.get_attr(table, attr).unwrap().nullable {
-        return Err(create_error(CompileErrorType::SemAttributeNotNullable,
-            format!("attribute `{}` is not nullale", attr)));
-    }
-    Ok(())
-}
-
-pub fn check_arith_expr(
-        arith : &mut ArithExpr,
-        table_set : &TableSet,
-        must_be_num_type : bool,
-        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
-    match arith {
-        &mut ArithExpr::Value(ValueExpr{value_type, ..}) => {
-            // already guranteed by grammar
-            assert!(value_type == ValueType::Integer || value_type == ValueType::Float);
-            Ok(())
-        }
-        &mut ArithExpr::MinusExpr{ref mut operant} => {
-            check_arith_expr(operant, table_set, must_be_num_type, &group_by_attr)
-        }
-        &mut ArithExpr::BinaryExpr{ref mut lhs, ref mut rhs, ..} => {
-            try!(check_arith_expr(lhs, table_set, must_be_num_type, &group_by_attr));
-            check_arith_expr(rhs, table_set, must_be_num_type, &group_by_attr)
-        }
-        &mut ArithExpr::Attr(ref mut attr) => {
-            try!(check_attr(attr, table_set, &group_by_attr));
-            if must_be_num_type {
-                check_attr_num_type(attr, table_set)
-            } else {
-                Ok(())
-            }
-        }
-    }
-}
-
-pub fn check_attr_num_type(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
-    let err_msg = format!("invalid attribute type: {}", attr_expr);
-    let (table, attr) = attr_expr.get_attr();
-    let attr = table_set.get_attr(table, attr).unwrap();
-    if let AttrType::Char{..} = attr.attr_type {
-        return Err(create_error(CompileErrorType::SemInvalidValueType, err_msg));
-    }
-    Ok(())
-}
-
-pub fn check_attr(
-        attr_expr : &mut AttributeExpr,
-        table_set : &TableSet,
-        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
-    let invalid_aggre_func_use_err_msg = format!("can't use {} in `where`", attr_expr);
-    let should_use_group_by_attr_err_msg =
-        format!("expected group by attribute {:?}, got {}", group_by_attr, attr_expr);
-    let (table, attr) = match attr_expr {
-        &mut AttributeExpr::TableAttr{ref mut table, ref mut attr} => {
-            try!(check_attr_exist(table, attr, table_set));
-            (table, attr)
-        }
-        &mut AttributeExpr::AggreFuncCall{ref func, ref mut table, ref mut attr} => {
-            try!(check_aggre_func_name(func));
-            try!(check_attr_exist(table, attr, table_set));
-            if let &None = group_by_attr {
-                return Err(create_error(CompileErrorType::SemInvalidAggregateFunctionUse,
-                    invalid_aggre_func_use_err_msg));
-            }
-            (table, attr)
-        }
-    };
-    let group_by_attr = match group_by_attr {
-        &Some(ref expr) => expr,
-        &None => return Ok(()),
-    };
-    // unique already guranteed
-    if (!is_match!(group_by_attr.0, None) && !is_match!(table, &mut None) && group_by_attr.0 != *table)
-            || group_by_attr.1 != *attr {
-        return Err(create_error(CompileErrorType::SemShouldUseGroupByAttribute,
-            should_use_group_by_attr_err_msg))
-    }
-    Ok(())
-}
-
-pub fn check_attr_exist(table : &mut Option<String>, attr : &mut String,
-        table_set : &TableSet) -> SemResult {
-    if table_set.get_attr(table, attr).is_some() {
-        table_set.complete_table_name(table, attr);
-        Ok(())
-    } else {
-        Err(create_error(CompileErrorType::SemInvalidAttribute,
-            format!("{} not exist or multiple found", attr)))
-    }
-}
-
-pub fn check_aggre_func_name(name : &String) -> SemResult {
-    let aggre_func_list = ["max", "min", "count", "sum"];
-    if aggre_func_list.into_iter().filter(|s| *name == s.to_string()).next().is_some() {
-        Ok(())
-    } else {
-        Err(create_error(CompileErrorType::SemInvalidAggreFuncName,
-            format!("invalid aggregate function name: {}, expected {:?}", name, aggre_func_list)))
-    }
-}
-
-pub fn check_create(stmt : &CreateStatement, table_set : &TableSet) -> SemResult {
-    try!(check_create_table_exit(stmt, table_set));
-    try!(check_unique_primary(stmt));
-    try!(check_primary_not_null(stmt));
-    try!(check_attr_unique(stmt));
-    Ok(())
-}
-
-pub fn check_create_table_exit(stmt : &CreateStatement, table_set : &TableSet) -> SemResult {
-    if table_set.exist(&stmt.table) {
-        Err(vec![ErrorRef::new(CompileError{
-            error_type : CompileErrorType::SemTableExist,
-            token : dummy_token(),
-            error_msg : format!("table {} already exist", &stmt.table),
-        })])
-    } else {
-        Ok(())
-    }
-}
-
-pub fn check_unique_primary(stmt : &CreateStatement) -> SemResult {
-    let primary_attr_list : Vec<String> =
-        stmt.decl_list.iter().filter(|d| d.primary).map(|d| d.name.clone()).collect();
-    if primary_attr_list.len() == 1 {
-        Ok(())
-    } else if primary_attr_list.is_empty() {
-        Err(vec![ErrorRef::new(CompileError{
-            error_type : CompileErrorType::SemNoPrimary,
-            token : dummy_token(),
-            error_msg : "no primary attribute found".to_string(),
-        })])
-    } else {
-        Err(vec![ErrorRef::new(CompileError{
-            error_type : CompileErrorType::SemMultiplePrimary,
-            token : dummy_token(),
-            error_msg : format!("multiple primary not support: {:?}", primary_attr_list),
-        })])
-    }
-}
-
-pub fn check_primary_not_null(stmt : &CreateStatement) -> SemResult {
-    for decl in stmt.decl_list.iter().filter(|d| d.primary && d.nullable) {
-        return Err(vec![ErrorRef::new(CompileError{
-            error_type : CompileErrorType::SemNullablePrimary,
-            token : dummy_token(),
-            error_msg : format!("primary attribute can't be null: {}", decl.name),
-        })]);
-    }
-    Ok(())
-}
-
-pub fn check_attr_unique(stmt : &CreateStatement) -> SemResult {
-    let mut table_set = HashSet::new();
-    for name in stmt.decl_list.iter().map(|d| &d.name) {
-        if table_set.contains(name) {
-            return Err(vec![ErrorRef::new(CompileError{
-                error_type : CompileErrorType::SemDuplicateAttr,
-                token : dummy_token(),
-                error_msg : format!("duplicate attribute name :{}", name),
-            })])
-        } else {
-            table_set.insert(name);
-        }
-    }
-    Ok(())
-}
-
-pub fn check_drop(stmt : &DropStatement, table_set : &TableSet) -> SemResult {
-    check_table_exist(&stmt.table, table_set)
-}
-
-pub fn check_table_exist(table : &str, table_set : &TableSet) -> SemResult {
-    if table_set.exist(table) {
-        Ok(())
-    } else {
-        Err(vec![ErrorRef::new(CompileError{
-            error_type : CompileErrorType::SemTableNotExist,
-            token : dummy_token(),
-            error_msg : table_not_exist(table),
-        })])
-    }
-}
-
-pub fn table_not_exist(table : &str) -> String {
-    format!("table `{}` not exist", table)
-}
-
-pub fn dummy_token() -> TokenRef {
-    TokenRef::new(Token{
-        column : 0,
-        value : "".to_string(),
-        token_type : TokenType::UnKnown
-    })
-}
-
-pub fn create_error(error_type : CompileErrorType, error_msg : String) -> ErrorList {
-    vec![ErrorRef::new(CompileError{
-            error_type : error_type,
-            token : dummy_token(),
-            error_msg : error_msg,
-        })]
-}
+use std::vec::Vec;
+use std::mem;
+use std::collections::HashSet;
+use std::path::{Path, Component};
+use super::attribute::AttributeExpr;
+use super::lexer::{Token, TokenRef, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList, ErrorRef};
+use super::common::{Statement, ValueExpr, ValueType};
+use super::select::{SelectStatement, GroupbyHaving, SelectExpr, Relation, WithTable};
+use super::update::{UpdateStatement, AssignExpr};
+use super::insert::InsertStatement;
+use super::delete::DeleteStatement;
+use super::create_drop::{CreateStatement, DropStatement, AttributeDeclaration, parse_generated_expr};
+use super::create_drop::AttrType as DeclAttrType;
+use super::alter::{AlterStatement, AlterAction};
+use super::copy::{CopyFromStatement, CopyToStatement};
+use super::backup::{BackupStatement, RestoreStatement};
+use super::analyze::AnalyzeStatement;
+use super::condition::{ConditionExpr, ArithExpr, CmpOperantExpr, CmpOp, LogicOp, CondRef};
+use ::store::table::{TableSet, AttrType, Attr};
+use ::utils::pointer::canonical_str;
+
+
+pub type SemResult = Result<(), ErrorList>;
+
+
+pub fn check_sem(statement : &mut Statement, table_set : &TableSet) -> SemResult {
+    match statement {
+        &mut Statement::Select(ref mut stmt) => check_select(stmt, table_set),
+        &mut Statement::Update(ref mut stmt) => check_update(stmt, table_set),
+        &mut Statement::Insert(ref mut stmt) => check_insert(stmt, table_set),
+        &mut Statement::Delete(ref mut stmt) => check_delete(stmt, table_set),
+        &mut Statement::Create(ref stmt) => check_create(stmt, table_set),
+        &mut Statement::Drop(ref stmt) => check_drop(stmt, table_set),
+        &mut Statement::Alter(ref stmt) => check_alter(stmt, table_set),
+        &mut Statement::CopyFrom(ref stmt) => check_copy_from(stmt, table_set),
+        &mut Statement::CopyTo(ref mut stmt) => check_copy_to(stmt, table_set),
+        &mut Statement::Backup(ref stmt) => check_backup(stmt),
+        &mut Statement::Restore(ref stmt) => check_restore(stmt),
+        &mut Statement::Analyze(ref stmt) => check_analyze(stmt, table_set),
+    }
+}
+
+pub fn check_select(stmt : &mut SelectStatement, table_set : &TableSet) -> SemResult {
+    if !stmt.with_list.is_empty() {
+        try!(inline_with_tables(stmt, table_set));
+    }
+    // join not supported now
+    assert_eq!(stmt.relation_list.len(), 1);
+    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
+    // the attributes can't be checked without the table
+    try!(check_table_exist(&table_name, table_set));
+    // a with statement has the tables of the with tables as well
+    let table_set = &table_set.gen_scope(&table_name);
+
+    let group_by_result = match stmt.groupby_having {
+        Some(GroupbyHaving{ref mut attr, ..}) => {
+            let (table, attr) = attr.get_attr();
+            Some(check_attr_exist(table, attr, table_set).map(|()| (table.clone(), attr.clone())))
+        }
+        None => None,
+    };
+    // the attributes depending on an invalid group by attribute are not checked
+    let (group_by_attr, check_grouped) = match group_by_result {
+        Some(Ok(ref attr)) => (Some(attr.clone()), true),
+        Some(Err(..)) => (None, false),
+        None => (None, true),
+    };
+
+    // in the order of the statement
+    let mut result : SemResult = Ok(());
+    match stmt.select_expr {
+        SelectExpr::AllAttribute => {
+            if stmt.groupby_having.is_some() {
+                result = Err(create_error(CompileErrorType::SemSelectAllWithGroupBy,
+                    "can't select all when using group by".to_string()));
+            }
+        }
+        SelectExpr::TableAllAttribute(ref table) => {
+            if *table != table_name {
+                result = Err(create_error(CompileErrorType::SemInvalidAttribute,
+                    format!("{}.* not exist, {} is not in the from list", table, table)));
+            } else if stmt.groupby_having.is_some() {
+                result = Err(create_error(CompileErrorType::SemSelectAllWithGroupBy,
+                    "can't select all when using group by".to_string()));
+            }
+        }
+        SelectExpr::AttrList(ref mut attr_list) => {
+            if check_grouped {
+                for attr_expr in attr_list {
+                    result = concat_result(result, check_attr(attr_expr, table_set, &group_by_attr));
+                }
+            }
+        }
+    }
+    if let Some(ref mut cond) = stmt.where_condition {
+        result = concat_result(result, check_condition(cond, table_set, &None));
+    }
+    if let Some(Err(errors)) = group_by_result {
+        result = concat_result(result, Err(errors));
+    }
+    if check_grouped {
+        if let Some(GroupbyHaving{having_condition : Some(ref mut cond), ..}) = stmt.groupby_having {
+            result = concat_result(result, check_condition(cond, table_set, &group_by_attr));
+        }
+        if let Some(ref mut attr) = stmt.order_by_attr {
+            result = concat_result(result, check_attr(attr, table_set, &group_by_attr));
+        }
+    }
+    result
+}
+
+// The with tables are inlined into the select using them, which reads the table again for every use.
+// The select of a with table is only a scan with a filter and a projection now, so it's merged into
+// the select using it instead of being saved as a temporary table. A with table hides the table of the
+// same name in the with tables after it and the select of the statement.
+pub fn inline_with_tables(stmt : &mut SelectStatement, table_set : &TableSet) -> SemResult {
+    let with_list = mem::replace(&mut stmt.with_list, Vec::new());
+    let mut checked : Vec<WithTable> = Vec::new();
+    for mut with_table in with_list.into_iter() {
+        if checked.iter().any(|t| t.name == with_table.name) {
+            return Err(create_error(CompileErrorType::SemTableExist,
+                format!("with table {} is defined more than once", with_table.name)));
+        }
+        // only the with tables before it can be used
+        try!(inline_relation(&mut with_table.select, &checked));
+        try!(check_select(&mut with_table.select, table_set));
+        checked.push(with_table);
+    }
+    inline_relation(stmt, &checked)
+}
+
+fn inline_relation(stmt : &mut SelectStatement, with_tables : &Vec<WithTable>) -> SemResult {
+    let name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
+    let with_select = match with_tables.iter().find(|t| t.name == name) {
+        Some(with_table) => &with_table.select,
+        None => return Ok(()),
+    };
+    // already checked, so the attributes have their tables
+    let table = extract!(&with_select.relation_list[0], &Relation::TableName(ref table), table.clone());
+    let columns = match with_select.select_expr {
+        SelectExpr::AttrList(ref attr_list) => Some(attr_list),
+        _ => None,
+    };
+    let mut result : SemResult = Ok(());
+    {
+        let mut inline_attr = |attr_expr : &mut AttributeExpr| {
+            let (attr_table, attr) = attr_expr.get_attr();
+            inline_with_attr(attr_table, attr, &name, &table, columns)
+        };
+        match stmt.select_expr {
+            SelectExpr::AllAttribute | SelectExpr::TableAllAttribute(..) => (),
+            SelectExpr::AttrList(ref mut attr_list) => {
+                for attr_expr in attr_list.iter_mut() {
+                    result = concat_result(result, inline_attr(attr_expr));
+                }
+            }
+        }
+        if let Some(ref mut cond) = stmt.where_condition {
+            result = concat_result(result, inline_cond_attrs(cond, &mut inline_attr));
+        }
+        if let Some(ref mut groupby_having) = stmt.groupby_having {
+            result = concat_result(result, inline_attr(&mut groupby_having.attr));
+            if let Some(ref mut cond) = groupby_having.having_condition {
+                result = concat_result(result, inline_cond_attrs(cond, &mut inline_attr));
+            }
+        }
+        if let Some(ref mut attr_expr) = stmt.order_by_attr {
+            result = concat_result(result, inline_attr(attr_expr));
+        }
+    }
+    try!(result);
+    let select_all = match stmt.select_expr {
+        SelectExpr::TableAllAttribute(ref t) if *t != name => return Err(create_error(
+            CompileErrorType::SemInvalidAttribute,
+            format!("{}.* not exist, {} is not in the from list", t, t))),
+        SelectExpr::AllAttribute | SelectExpr::TableAllAttribute(..) => true,
+        SelectExpr::AttrList(..) => false,
+    };
+    if select_all {
+        stmt.select_expr = match columns {
+            Some(attr_list) => SelectExpr::AttrList(attr_list.clone()),
+            None => SelectExpr::AllAttribute,
+        };
+    }
+    // the condition of the with table goes first
+    if let Some(ref with_cond) = with_select.where_condition {
+        stmt.where_condition = Some(match stmt.where_condition.take() {
+            Some(cond) => ConditionExpr::LogicExpr{
+                lhs : CondRef::new(with_cond.clone()),
+                rhs : CondRef::new(cond),
+                op : LogicOp::And,
+            },
+            None => with_cond.clone(),
+        });
+    }
+    stmt.relation_list[0] = Relation::TableName(table);
+    Ok(())
+}
+
+// an attribute of the with table name becomes the attribute it selects from table
+fn inline_with_attr(attr_table : &mut Option<String>, attr : &mut String, name : &String, table : &String,
+        columns : Option<&Vec<AttributeExpr>>) -> SemResult {
+    if let &mut Some(ref t) = attr_table {
+        if t != name {
+            return Err(create_error(CompileErrorType::SemInvalidAttribute,
+                format!("{}.{} not exist, {} is not in the from list", t, attr, t)));
+        }
+    }
+    let columns = match columns {
+        Some(columns) => columns,
+        None => {
+            // all the attributes of the table
+            if attr_table.is_some() {
+                *attr_table = Some(table.clone());
+            }
+            return Ok(());
+        }
+    };
+    let column = columns.iter().filter_map(|c| match c {
+        &AttributeExpr::TableAttr{ref table, ref attr} => Some((table, attr)),
+        &AttributeExpr::AggreFuncCall{..} => None,
+    }).find(|&(_, a)| *a == *attr);
+    match column {
+        Some((t, a)) => {
+            *attr_table = t.clone();
+            *attr = a.clone();
+            Ok(())
+        }
+        None => Err(create_error(CompileErrorType::SemInvalidAttribute,
+            format!("{} not exist in with table {}", attr, name))),
+    }
+}
+
+fn inline_cond_attrs<F>(cond : &mut ConditionExpr, inline_attr : &mut F) -> SemResult
+        where F : FnMut(&mut AttributeExpr) -> SemResult {
+    match cond {
+        &mut ConditionExpr::LogicExpr{ref mut lhs, ref mut rhs, ..} => {
+            let result = inline_cond_attrs(lhs, inline_attr);
+            concat_result(result, inline_cond_attrs(rhs, inline_attr))
+        }
+        &mut ConditionExpr::NotExpr{ref mut operant} => inline_cond_attrs(operant, inline_attr),
+        &mut ConditionExpr::CmpExpr{ref mut lhs, ref mut rhs, ..} => {
+            let mut result : SemResult = Ok(());
+            for operant in vec![lhs, rhs] {
+                if let &mut CmpOperantExpr::Arith(ref mut arith) = operant {
+                    result = concat_result(result, inline_arith_attrs(arith, inline_attr));
+                }
+            }
+            result
+        }
+    }
+}
+
+fn inline_arith_attrs<F>(arith : &mut ArithExpr, inline_attr : &mut F) -> SemResult
+        where F : FnMut(&mut AttributeExpr) -> SemResult {
+    match arith {
+        &mut ArithExpr::BinaryExpr{ref mut lhs, ref mut rhs, ..} => {
+            let result = inline_arith_attrs(lhs, inline_attr);
+            concat_result(result, inline_arith_attrs(rhs, inline_attr))
+        }
+        &mut ArithExpr::MinusExpr{ref mut operant} => inline_arith_attrs(operant, inline_attr),
+        &mut ArithExpr::Value(..) => Ok(()),
+        &mut ArithExpr::Attr(ref mut attr_expr) => inline_attr(attr_expr),
+    }
+}
+
+pub fn check_update(stmt : &mut UpdateStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let mut result : SemResult = Ok(());
+    for assign in &mut stmt.set_list {
+        result = concat_result(result, check_assign_expr(assign, &stmt.table, table_set));
+    }
+    if let Some(ref mut cond) = stmt.where_condition {
+        result = concat_result(result, check_condition(cond, table_set, &None));
+    }
+    result
+}
+
+pub fn check_assign_expr(assign : &mut AssignExpr, table : &String, table_set : &TableSet) -> SemResult {
+    try!(check_attr_exist(&mut Some(table.clone()), &mut assign.attr, table_set));
+    let attr = table_set.get_attr(&Some(table.clone()), &assign.attr).unwrap();
+    if attr.primary {
+        return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
+            format!("can't change primary attribute: {}", attr.name)));
+    }
+    if attr.generated.is_some() {
+        return Err(create_error(CompileErrorType::SemChangeGeneratedAttr,
+            format!("can't set generated attribute: {}", attr.name)));
+    }
+    check_assign(&mut assign.value, &attr)
+}
+
+pub fn check_insert(stmt : &mut InsertStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let value_list  = &mut stmt.value_list;
+    // the generated attributes are computed
+    let attr_list : Vec<Attr> = table_set.gen_attr_list(&stmt.table)  // table should exist
+        .into_iter().filter(|a| a.generated.is_none()).collect();
+    if attr_list.len() != value_list.len() {
+        return Err(create_error(CompileErrorType::SemInvalidInsertValuesNum,
+            format!("invalid insert values number, expected {}, found {}",
+                attr_list.len(), value_list.len())));
+    }
+    let mut result : SemResult = Ok(());
+    for (value, attr) in value_list.iter_mut().zip(attr_list.iter()) {
+        result = concat_result(result, check_assign(value, attr));
+    }
+    result
+}
+
+pub fn check_assign(value : &mut ValueExpr, attr : &Attr) -> SemResult {
+    try!(value.check_literal().map_err(invalid_literal));
+    match (value.value_type, attr.attr_type) {
+            (ValueType::Integer, AttrType::Int)
+        | (ValueType::Integer, AttrType::Float)
+        | (ValueType::Float, AttrType::Float) => (),
+        (ValueType::String, AttrType::Char{len}) => {
+            // only the part before a NUL is stored
+            let value_len = canonical_str(&value.value).len();
+            if value_len > len {
+                return Err(create_error(CompileErrorType::SemInvalidInsertCharLen,
+                    format!("invalid char len, expected {}, found {}", len, value_len)));
+            }
+        }
+        // checked again when the parameter is bound
+        (ValueType::Placeholder, _) => (),
+        (ValueType::Null, _) => {
+            if !attr.nullable {
+                return Err(create_error(CompileErrorType::SemAttributeNotNullable,
+                    format!("attribute {} is not nullable", attr.name)));
+            }
+        }
+        (value_type, attr_type) =>
+            return Err(create_error(CompileErrorType::SemInvalidInsertValueType,
+                format!("invalid insert value type, attribute type is {:?}, found {:?}",
+                    attr_type, value_type))),
+    }
+    Ok(())
+}
+
+pub fn invalid_literal(err_msg : String) -> ErrorList {
+    create_error(CompileErrorType::SemInvalidLiteral, err_msg)
+}
+
+pub fn check_delete(stmt : &mut DeleteStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    match &mut stmt.where_condition {
+        &mut Some(ref mut cond) => check_condition(cond, table_set, &None),
+        &mut None => Ok(()),
+    }
+}
+
+pub fn check_condition(
+        condition : &mut ConditionExpr,
+        table_set : &TableSet,
+        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
+    match condition {
+        &mut ConditionExpr::NotExpr{ref mut operant} => check_condition(operant, table_set, &group_by_attr),
+        &mut ConditionExpr::LogicExpr{ref mut lhs, ref mut rhs, .. } => {
+            let lhs_result = check_condition(lhs, table_set, &group_by_attr);
+            concat_result(lhs_result, check_condition(rhs, table_set, &group_by_attr))
+        }
+        &mut ConditionExpr::CmpExpr{ref mut lhs, ref mut rhs, op } => {
+            let must_be_num_type = match op {
+                CmpOp::LT | CmpOp::GT | CmpOp::LE | CmpOp::GE => {
+                    match (lhs.get_type(), rhs.get_type()) {
+                        (ValueType::String, _) | (ValueType::Null, _)
+                        | (_, ValueType::String) | (_, ValueType::Null) => {
+                                return Err(create_error(CompileErrorType::SemInvalidValueType,
+                                    format!("invalid operant type: {} {} {}", lhs, rhs, op)))
+                            }
+                        _ => ()
+                    }
+                    true
+                }
+                CmpOp::EQ | CmpOp::NE => {
+                    match (lhs.get_type(), rhs.get_type()) {
+                        (ValueType::Null, _) | (_, ValueType::Null)  => {
+                            return Err(create_error(CompileErrorType::SemInvalidValueType,
+                                format!("invalid operant type: {} {} {}", lhs, rhs, op)))
+                        }
+                        _ => ()
+                    }
+                    false
+                }
+                CmpOp::Is | CmpOp::IsNot => {
+                    match lhs {
+                        &mut CmpOperantExpr::Arith(ArithExpr::Attr(ref mut attr)) => {
+                            try!(check_is_nullable(attr, table_set));
+                        }
+                        _ => return Err(create_error(CompileErrorType::SemInvalidValueType,
+                            format!("expected attribute or aggregate function\
+                                in the left of `is` and `is not`, found {}", lhs)))
+                    }
+                    match rhs {
+                        &mut CmpOperantExpr::Value(ValueExpr{value_type : ValueType::Null, ..}) => (),
+                        _ => return Err(create_error(CompileErrorType::SemInvalidValueType,
+                            format!("only null is allowd after `is` or `is not`, found {}", rhs)))
+                    }
+                    false
+                }
+            };
+            let lhs_result = check_cmp_operant(lhs, table_set, must_be_num_type, &group_by_attr);
+            concat_result(lhs_result, check_cmp_operant(rhs, table_set, must_be_num_type, &group_by_attr))
+        }
+    }
+}
+
+pub fn check_cmp_operant(
+        operant : &mut CmpOperantExpr,
+        table_set : &TableSet,
+        must_be_num_type : bool,
+        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
+    match operant {
+        &mut CmpOperantExpr::Arith(ref mut arith) =>
+            check_arith_expr(arith, table_set, must_be_num_type, &group_by_attr),
+        &mut CmpOperantExpr::Value(ref mut value) => value.check_literal().map_err(invalid_literal),
+    }
+}
+
+pub fn check_is_nullable(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
+    let (table, attr) = attr_expr.get_attr();
+    try!(check_attr_exist(table, attr, table_set));
+    if !table_set.get_attr(table, attr).unwrap().nullable {
+        return Err(create_error(CompileErrorType::SemAttributeNotNullable,
+            format!("attribute `{}` is not nullale", attr)));
+    }
+    Ok(())
+}
+
+pub fn check_arith_expr(
+        arith : &mut ArithExpr,
+        table_set : &TableSet,
+        must_be_num_type : bool,
+        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
+    match arith {
+        &mut ArithExpr::Value(ref mut value) => {
+            // already guranteed by grammar
+            assert!(value.value_type == ValueType::Integer || value.value_type == ValueType::Float);
+            value.check_literal().map_err(invalid_literal)
+        }
+        &mut ArithExpr::MinusExpr{ref mut operant} => {
+            check_arith_expr(operant, table_set, must_be_num_type, &group_by_attr)
+        }
+        &mut ArithExpr::BinaryExpr{ref mut lhs, ref mut rhs, ..} => {
+            let lhs_result = check_arith_expr(lhs, table_set, must_be_num_type, &group_by_attr);
+            concat_result(lhs_result, check_arith_expr(rhs, table_set, must_be_num_type, &group_by_attr))
+        }
+        &mut ArithExpr::Attr(ref mut attr) => {
+            try!(check_attr(attr, table_set, &group_by_attr));
+            if must_be_num_type {
+                check_attr_num_type(attr, table_set)
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn check_attr_num_type(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
+    let err_msg = format!("invalid attribute type: {}", attr_expr);
+    let (table, attr) = attr_expr.get_attr();
+    let attr = table_set.get_attr(table, attr).unwrap();
+    if let AttrType::Char{..} = attr.attr_type {
+        return Err(create_error(CompileErrorType::SemInvalidValueType, err_msg));
+    }
+    Ok(())
+}
+
+pub fn check_attr(
+        attr_expr : &mut AttributeExpr,
+        table_set : &TableSet,
+        group_by_attr : &Option<(Option<String>, String)>) -> SemResult {
+    let invalid_aggre_func_use_err_msg = format!("can't use {} in `where`", attr_expr);
+    let should_use_group_by_attr_err_msg =
+        format!("expected group by attribute {:?}, got {}", group_by_attr, attr_expr);
+    let (table, attr) = match attr_expr {
+        &mut AttributeExpr::TableAttr{ref mut table, ref mut attr} => {
+            try!(check_attr_exist(table, attr, table_set));
+            (table, attr)
+        }
+        &mut AttributeExpr::AggreFuncCall{ref func, ref mut table, ref mut attr} => {
+            try!(check_aggre_func_name(func));
+            try!(check_attr_exist(table, attr, table_set));
+            if let &None = group_by_attr {
+                return Err(create_error(CompileErrorType::SemInvalidAggregateFunctionUse,
+                    invalid_aggre_func_use_err_msg));
+            }
+            (table, attr)
+        }
+    };
+    let group_by_attr = match group_by_attr {
+        &Some(ref expr) => expr,
+        &None => return Ok(()),
+    };
+    // unique already guranteed
+    if (!is_match!(group_by_attr.0, None) && !is_match!(table, &mut None) && group_by_attr.0 != *table)
+            || group_by_attr.1 != *attr {
+        return Err(create_error(CompileErrorType::SemShouldUseGroupByAttribute,
+            should_use_group_by_attr_err_msg))
+    }
+    Ok(())
+}
+
+pub fn check_attr_exist(table : &mut Option<String>, attr : &mut String,
+        table_set : &TableSet) -> SemResult {
+    if table_set.get_attr(table, attr).is_some() {
+        table_set.complete_table_name(table, attr);
+        Ok(())
+    } else {
+        Err(create_error(CompileErrorType::SemInvalidAttribute,
+            format!("{} not exist or multiple found", attr)))
+    }
+}
+
+pub fn check_aggre_func_name(name : &String) -> SemResult {
+    let aggre_func_list = ["max", "min", "count", "sum"];
+    if aggre_func_list.into_iter().filter(|s| *name == s.to_string()).next().is_some() {
+        Ok(())
+    } else {
+        Err(create_error(CompileErrorType::SemInvalidAggreFuncName,
+            format!("invalid aggregate function name: {}, expected {:?}", name, aggre_func_list)))
+    }
+}
+
+pub fn check_create(stmt : &CreateStatement, table_set : &TableSet) -> SemResult {
+    let mut result = check_create_table_exit(stmt, table_set);
+    result = concat_result(result, check_attr_unique(stmt));
+    result = concat_result(result, check_unique_primary(stmt));
+    result = concat_result(result, check_primary_not_null(stmt));
+    for decl in stmt.decl_list.iter() {
+        result = concat_result(result, check_generated_decl(decl, stmt));
+    }
+    if let Some(ref fill_factor) = stmt.fill_factor {
+        result = concat_result(result, check_fill_factor(fill_factor));
+    }
+    result
+}
+
+pub fn check_fill_factor(fill_factor : &String) -> SemResult {
+    match fill_factor.parse::<usize>() {
+        Ok(n) if n >= 10 && n <= 100 => Ok(()),
+        _ => Err(create_error(CompileErrorType::SemInvalidFillFactor,
+            format!("fill_factor should be between 10 and 100, found {}", fill_factor))),
+    }
+}
+
+pub fn check_create_table_exit(stmt : &CreateStatement, table_set : &TableSet) -> SemResult {
+    if table_set.exist(&stmt.table) {
+        Err(vec![ErrorRef::new(CompileError{
+            error_type : CompileErrorType::SemTableExist,
+            token : dummy_token(),
+            error_msg : format!("table {} already exist", &stmt.table),
+        })])
+    } else {
+        Ok(())
+    }
+}
+
+pub fn check_unique_primary(stmt : &CreateStatement) -> SemResult {
+    let primary_attr_list : Vec<String> =
+        stmt.decl_list.iter().filter(|d| d.primary).map(|d| d.name.clone()).collect();
+    if primary_attr_list.len() == 1 {
+        Ok(())
+    } else if primary_attr_list.is_empty() {
+        Err(vec![ErrorRef::new(CompileError{
+            error_type : CompileErrorType::SemNoPrimary,
+            token : dummy_token(),
+            error_msg : "no primary attribute found".to_string(),
+        })])
+    } else {
+        Err(vec![ErrorRef::new(CompileError{
+            error_type : CompileErrorType::SemMultiplePrimary,
+            token : dummy_token(),
+            error_msg : format!("multiple primary not support: {:?}", primary_attr_list),
+        })])
+    }
+}
+
+pub fn check_primary_not_null(stmt : &CreateStatement) -> SemResult {
+    for decl in stmt.decl_list.iter().filter(|d| d.primary && d.nullable) {
+        return Err(vec![ErrorRef::new(CompileError{
+            error_type : CompileErrorType::SemNullablePrimary,
+            token : dummy_token(),
+            error_msg : format!("primary attribute can't be null: {}", decl.name),
+        })]);
+    }
+    Ok(())
+}
+
+pub fn check_attr_unique(stmt : &CreateStatement) -> SemResult {
+    let mut table_set = HashSet::new();
+    for name in stmt.decl_list.iter().map(|d| &d.name) {
+        if table_set.contains(name) {
+            return Err(vec![ErrorRef::new(CompileError{
+                error_type : CompileErrorType::SemDuplicateAttr,
+                token : dummy_token(),
+                error_msg : format!("duplicate attribute name :{}", name),
+            })])
+        } else {
+            table_set.insert(name);
+        }
+    }
+    Ok(())
+}
+
+// A generated attribute is an int or a float computed from the number attributes
+// of the same tuple, which are not generated themselves.
+pub fn check_generated_decl(decl : &AttributeDeclaration, stmt : &CreateStatement) -> SemResult {
+    let expr = match decl.generated {
+        Some(ref expr) => expr,
+        None => return Ok(()),
+    };
+    let invalid = |err_msg : String| Err(create_error(CompileErrorType::SemInvalidGeneratedAttr, err_msg));
+    if decl.primary {
+        return invalid(format!("primary attribute can't be generated: {}", decl.name));
+    }
+    if let DeclAttrType::Char{..} = decl.attr_type {
+        return invalid(format!("generated attribute should be int or float: {}", decl.name));
+    }
+    try!(expr.clone().check_literals().map_err(invalid_literal));
+    for attr_expr in expr.get_attr_list() {
+        let (table, attr) = match attr_expr {
+            &AttributeExpr::TableAttr{ref table, ref attr} => (table, attr),
+            &AttributeExpr::AggreFuncCall{..} =>
+                return invalid(format!("can't use {} in generated attribute {}", attr_expr, decl.name)),
+        };
+        if table.as_ref().map_or(false, |t| *t != stmt.table) {
+            return invalid(format!("generated attribute {} can't use {}", decl.name, attr_expr));
+        }
+        match stmt.decl_list.iter().filter(|d| d.name == *attr).next() {
+            Some(d) if d.generated.is_none() && !is_match!(d.attr_type, DeclAttrType::Char{..}) => (),
+            _ => return invalid(format!(
+                "generated attribute {} should use the number attributes not generated, found {}",
+                decl.name, attr_expr)),
+        }
+    }
+    Ok(())
+}
+
+// the generated attributes of the table computed from attr
+fn get_generated_users(table : &String, attr : &str, table_set : &TableSet) -> Vec<String> {
+    let mut users = Vec::new();
+    for a in table_set.gen_attr_list(table).iter() {
+        if let Some(ref sql) = a.generated {
+            // checked when the table is created
+            let expr = parse_generated_expr(sql).unwrap();
+            let used = expr.get_attr_list().iter().any(|attr_expr| match *attr_expr {
+                &AttributeExpr::TableAttr{attr : ref name, ..} => name == attr,
+                &AttributeExpr::AggreFuncCall{..} => false,
+            });
+            if used {
+                users.push(a.name.clone());
+            }
+        }
+    }
+    users
+}
+
+fn check_not_generated_used(table : &String, attr : &str, table_set : &TableSet) -> SemResult {
+    let users = get_generated_users(table, attr, table_set);
+    if users.is_empty() {
+        Ok(())
+    } else {
+        Err(create_error(CompileErrorType::SemChangeGeneratedAttr,
+            format!("attribute {} is used by generated attribute {:?}", attr, users)))
+    }
+}
+
+pub fn check_drop(stmt : &DropStatement, table_set : &TableSet) -> SemResult {
+    check_table_exist(&stmt.table, table_set)
+}
+
+pub fn check_alter(stmt : &AlterStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let table = Some(stmt.table.clone());
+    match stmt.action {
+        AlterAction::AddAttr(ref decl) => {
+            if table_set.get_attr(&table, &decl.name).is_some() {
+                return Err(create_error(CompileErrorType::SemDuplicateAttr,
+                    format!("duplicate attribute name :{}", decl.name)));
+            }
+            if decl.primary {
+                return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
+                    format!("can't add primary attribute: {}", decl.name)));
+            }
+            // the existing tuples would have to be computed in the rewrite
+            if decl.generated.is_some() {
+                return Err(create_error(CompileErrorType::SemChangeGeneratedAttr,
+                    format!("can't add generated attribute: {}", decl.name)));
+            }
+            // the existing tuples will get null
+            if !decl.nullable {
+                return Err(create_error(CompileErrorType::SemAttributeNotNullable,
+                    format!("new attribute should be nullable: {}", decl.name)));
+            }
+        }
+        AlterAction::DropAttr(ref name) => {
+            match table_set.get_attr(&table, name) {
+                Some(ref attr) if attr.primary =>
+                    return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
+                        format!("can't drop primary attribute: {}", name))),
+                Some(..) => (),
+                None => return Err(create_error(CompileErrorType::SemInvalidAttribute,
+                    format!("{} not exist", name))),
+            }
+            try!(check_not_generated_used(&stmt.table, name, table_set));
+        }
+        AlterAction::ModifyAttr(ref decl) => {
+            match table_set.get_attr(&table, &decl.name) {
+                Some(ref attr) if attr.primary != decl.primary || (decl.primary && decl.nullable) =>
+                    return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
+                        format!("can't change primary attribute: {}", decl.name))),
+                Some(ref attr) if attr.generated.is_some() || decl.generated.is_some() =>
+                    return Err(create_error(CompileErrorType::SemChangeGeneratedAttr,
+                        format!("can't modify generated attribute: {}", decl.name))),
+                Some(..) => (),
+                None => return Err(create_error(CompileErrorType::SemInvalidAttribute,
+                    format!("{} not exist", decl.name))),
+            }
+            try!(check_not_generated_used(&stmt.table, &decl.name, table_set));
+        }
+    }
+    Ok(())
+}
+
+pub fn check_copy_from(stmt : &CopyFromStatement, table_set : &TableSet) -> SemResult {
+    let mut result = check_table_exist(&stmt.table, table_set);
+    if let Some(ref path) = stmt.path {
+        result = concat_result(result, check_copy_path(path));
+    }
+    concat_result(result, check_copy_delimiter(&stmt.options.delimiter))
+}
+
+pub fn check_copy_to(stmt : &mut CopyToStatement, table_set : &TableSet) -> SemResult {
+    let mut result = check_select(&mut stmt.select, table_set);
+    result = concat_result(result, check_copy_path(&stmt.path));
+    concat_result(result, check_copy_delimiter(&stmt.options.delimiter))
+}
+
+pub fn check_backup(stmt : &BackupStatement) -> SemResult {
+    check_copy_path(&stmt.path)
+}
+
+pub fn check_restore(stmt : &RestoreStatement) -> SemResult {
+    check_copy_path(&stmt.path)
+}
+
+pub fn check_analyze(stmt : &AnalyzeStatement, table_set : &TableSet) -> SemResult {
+    check_table_exist(&stmt.table, table_set)
+}
+
+pub fn check_copy_delimiter(delimiter : &String) -> SemResult {
+    if delimiter.len() != 1 || delimiter == "\"" || delimiter == "\n" || delimiter == "\r" {
+        return Err(create_error(CompileErrorType::SemInvalidCopyDelimiter,
+            format!("delimiter should be a single char other than quote and newline, found {:?}",
+                delimiter)));
+    }
+    Ok(())
+}
+
+pub fn check_copy_path(path : &str) -> SemResult {
+    // only relative path inside the working directory is allowed
+    let invalid_path_err = create_error(CompileErrorType::SemInvalidCopyPath,
+        format!("invalid path {:?}, should be a relative path without `..`", path));
+    if path.is_empty() {
+        return Err(invalid_path_err);
+    }
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(..) | Component::CurDir => (),
+            _ => return Err(invalid_path_err),
+        }
+    }
+    Ok(())
+}
+
+pub fn check_table_exist(table : &str, table_set : &TableSet) -> SemResult {
+    if table_set.exist(table) {
+        Ok(())
+    } else {
+        Err(vec![ErrorRef::new(CompileError{
+            error_type : CompileErrorType::SemTableNotExist,
+            token : dummy_token(),
+            error_msg : table_not_exist(table),
+        })])
+    }
+}
+
+pub fn table_not_exist(table : &str) -> String {
+    format!("table `{}` not exist", table)
+}
+
+pub fn dummy_token() -> TokenRef {
+    TokenRef::new(Token{
+        column : 0,
+        value : "".to_string(),
+        token_type : TokenType::UnKnown
+    })
+}
+
+// the errors of both, so that the checking can go on after an error
+pub fn concat_result(lhs : SemResult, rhs : SemResult) -> SemResult {
+    match (lhs, rhs) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(errors), Ok(())) | (Ok(()), Err(errors)) => Err(errors),
+        (Err(mut lhs_errors), Err(rhs_errors)) => {
+            lhs_errors.extend(rhs_errors);
+            Err(lhs_errors)
+        }
+    }
+}
+
+pub fn create_error(error_type : CompileErrorType, error_msg : String) -> ErrorList {
+    vec![ErrorRef::new(CompileError{
+            error_type : error_type,
+            token : dummy_token(),
+            error_msg : error_msg,
+        })]
+}
@@ -0,0 +1,55 @@
+use std::option::Option::{Some, None};
+use super::lexer::{TokenRef, TokenIter, TokenList, TokenType};
+use super::compile_error::ErrorList;
+use super::common::{
+    consume_next_token_with_type,
+    consume_next_token_with_type_list,
+    check_parse_to_end,
+};
+
+
+// `set name = value` and `show variables` belong to a connection like the prepare commands,
+// the names and the values are checked by the session
+#[derive(Debug)]
+pub enum VariableCommand {
+    Set{ name : String, value : TokenRef },
+    ShowVariables,
+}
+
+impl VariableCommand {
+    // None if the tokens are not a variable command
+    pub fn parse(tokens : &TokenList) -> Option<Result<VariableCommand, ErrorList>> {
+        let mut it = tokens.iter();
+        let result = match (tokens.get(0), tokens.get(1)) {
+            (Some(first), _) if first.token_type == TokenType::Set => VariableCommand::parse_set(&mut it),
+            (Some(first), Some(second)) if first.token_type == TokenType::Identifier
+                    && first.value.to_lowercase() == "show"
+                    && second.value.to_lowercase() == "variables" => {
+                it.next();
+                it.next();
+                match check_parse_to_end(&it) {
+                    Some(err) => Err(vec![err]),
+                    None => Ok(VariableCommand::ShowVariables),
+                }
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn parse_set(it : &mut TokenIter) -> Result<VariableCommand, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Set));
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::EQ));
+        // `on`, `off`, `true` and `false` are identifiers
+        let value = try!(consume_next_token_with_type_list(it, &vec![TokenType::IntegerLiteral,
+            TokenType::FloatLiteral, TokenType::StringLiteral, TokenType::Identifier]));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(VariableCommand::Set{
+                name : name_token.value.to_lowercase(),
+                value : value,
+            }),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ::utils::config::Config;
+use ::utils::sha256::{sha256, to_hex};
+
+
+const HASH_ROUNDS : usize = 1000;
+
+// One user for each line of the users file: `name:salt:hash`,
+// hash is the hex of hash_password(salt, password). Use gen_user_entry to make a line.
+#[derive(Debug)]
+pub struct UserTable {
+    users : HashMap<String, (String, String)>,  // name -> (salt, hash)
+}
+
+impl UserTable {
+    // None if users_file is not configured, and then no authentication is needed
+    pub fn from_config(config : &Config) -> Option<UserTable> {
+        let path = config.get_str("users_file");
+        if path.is_empty() {
+            return None;
+        }
+        match UserTable::from_file(&path) {
+            Ok(users) => Some(users),
+            Err(err) => panic!("can't load users file {}: {}", path, err),
+        }
+    }
+
+    pub fn from_file(path : &str) -> Result<UserTable, String> {
+        let file = try!(File::open(path).map_err(|e| e.to_string()));
+        let mut users = HashMap::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = try!(line.map_err(|e| e.to_string()));
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields : Vec<&str> = line.split(':').collect();
+            if fields.len() != 3 || fields[0].is_empty() {
+                return Err(format!("line {}: expect name:salt:hash", i + 1));
+            }
+            users.insert(fields[0].to_string(), (fields[1].to_string(), fields[2].to_string()));
+        }
+        Ok(UserTable{ users : users })
+    }
+
+    pub fn verify(&self, user : &str, password : &str) -> bool {
+        match self.users.get(user) {
+            Some(&(ref salt, ref hash)) => constant_time_eq(
+                hash_password(salt, password).as_bytes(), hash.as_bytes()),
+            None => false,
+        }
+    }
+}
+
+pub fn hash_password(salt : &str, password : &str) -> String {
+    let mut input = salt.as_bytes().to_vec();
+    input.extend_from_slice(password.as_bytes());
+    let mut digest = sha256(&input);
+    for _ in 1..HASH_ROUNDS {
+        let mut round_input = digest.to_vec();
+        round_input.extend_from_slice(&input);
+        digest = sha256(&round_input);
+    }
+    to_hex(&digest)
+}
+
+pub fn gen_user_entry(user : &str, password : &str) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let seed = format!("{}{}{}", now.as_secs(), now.subsec_nanos(), user);
+    let salt = to_hex(&sha256(seed.as_bytes())[..8]);
+    format!("{}:{}:{}", user, salt, hash_password(&salt, password))
+}
+
+// `auth user password`, the first line of a connection when users_file is configured
+pub fn parse_auth_command(line : &str) -> Option<(String, String)> {
+    let mut parts = line.splitn(3, ' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("auth"), Some(user), Some(password)) if !user.is_empty() =>
+            Some((user.to_string(), password.to_string())),
+        _ => None,
+    }
+}
+
+fn constant_time_eq(a : &[u8], b : &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,60 @@
+use ::store::tuple::TupleValue;
+use super::handler::{ResultHandler, ColumnInfo, Summary, TRUNCATED_WARNING};
+use super::result_error::ResultError;
+
+
+// The whole result of a statement copied out of the pages,
+// so it can be sent to the thread of a connection not using the native protocol.
+#[derive(Debug, Clone)]
+pub struct CollectedResult {
+    pub columns : Vec<ColumnInfo>,
+    pub rows : Vec<Vec<TupleValue>>,
+    pub summary : Option<Summary>,
+    pub errors : Vec<ResultError>,
+    pub warnings : Vec<String>,
+    pub truncated : bool,  // the rows after max_result_rows are dropped
+}
+
+impl CollectedResult {
+    pub fn new() -> CollectedResult {
+        CollectedResult{
+            columns : Vec::new(),
+            rows : Vec::new(),
+            summary : None,
+            errors : Vec::new(),
+            warnings : Vec::new(),
+            truncated : false,
+        }
+    }
+    pub fn from_error(error : ResultError) -> CollectedResult {
+        let mut result = CollectedResult::new();
+        result.errors.push(error);
+        result
+    }
+    // a select, even without any row
+    pub fn is_query(&self) -> bool {
+        !self.columns.is_empty()
+    }
+}
+
+impl ResultHandler for CollectedResult {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        self.errors = errors;
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        if let Some(row) = row {
+            self.rows.push(row);
+        }
+    }
+    fn handle_non_query_finished(&mut self) {}
+    fn handle_complete(&mut self, summary : Summary) {
+        self.summary = Some(summary);
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.columns = columns.to_vec();
+    }
+    fn handle_warning(&mut self, msg : String) {
+        self.truncated |= msg.starts_with(TRUNCATED_WARNING);
+        self.warnings.push(msg);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{HashMap, VecDeque};
+use std::cmp::min;
+use ::parser::common::{Statement, parse_tokens};
+use ::parser::lexer::{TokenLine, TokenList};
+use ::parser::cursor::CursorCommand;
+use ::store::table::TableManagerRef;
+use ::store::tuple::TupleValue;
+use super::handler::{ResultHandler, ColumnInfo, Summary, StatementKind, stmt_handler};
+use super::result_error::{ResultError, ResultErrorType, from_compile_errors};
+use super::collect::CollectedResult;
+
+
+// The rows of the select are copied out when the cursor is declared.
+// A plan can't be kept between the statements of a connection: it holds the tables by Rc
+// while the session is shared with the thread of the connection, and the pages it pins
+// could be changed by the statements run before the next fetch.
+#[derive(Debug)]
+pub struct Cursor {
+    columns : Vec<ColumnInfo>,
+    rows : VecDeque<Vec<TupleValue>>,
+    warnings : Vec<String>,  // of the select, sent with the first fetch
+}
+
+// the cursors of a connection, dropped with the connection
+pub type Cursors = HashMap<String, Cursor>;
+
+pub fn process_cursor_command(input : &String, cursors : &mut Cursors,
+        result_handler : &mut ResultHandler, manager : &TableManagerRef) -> Result<(), ()> {
+    let line = TokenLine::parse(input);
+    let command = match CursorCommand::parse(&line.tokens) {
+        Some(command) => command,
+        None => return Err(()),
+    };
+    if line.errors.len() > 0 {
+        result_handler.handle_error(from_compile_errors(&line.errors));
+        return Ok(());
+    }
+    match command {
+        Err(ref err_list) => result_handler.handle_error(from_compile_errors(err_list)),
+        Ok(CursorCommand::Declare{name, tokens}) => {
+            if cursors.contains_key(&name) {
+                let err_msg = format!("cursor {} already exist", name);
+                result_handler.handle_error(vec![ResultError::new(ResultErrorType::CursorExist, err_msg)]);
+                return Ok(());
+            }
+            match declare_cursor(&tokens, manager) {
+                Ok(cursor) => {
+                    cursors.insert(name, cursor);
+                    result_handler.handle_non_query_finished();
+                }
+                Err(errors) => result_handler.handle_error(errors),
+            }
+        }
+        Ok(CursorCommand::Fetch{name, count}) => {
+            match cursors.get_mut(&name) {
+                Some(cursor) => fetch_rows(&name, cursor, count, result_handler),
+                None => result_handler.handle_error(vec![cursor_not_exist_error(&name)]),
+            }
+        }
+        Ok(CursorCommand::Close{name}) => {
+            match cursors.remove(&name) {
+                Some(..) => result_handler.handle_non_query_finished(),
+                None => result_handler.handle_error(vec![cursor_not_exist_error(&name)]),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn declare_cursor(tokens : &TokenList, manager : &TableManagerRef)
+        -> Result<Cursor, Vec<ResultError>> {
+    let stmt = match parse_tokens(tokens) {
+        Ok(stmt) => stmt,
+        Err(ref err_list) => return Err(from_compile_errors(err_list)),
+    };
+    if let Statement::Select(..) = stmt {} else {
+        return Err(vec![ResultError::new(ResultErrorType::CursorNotSelect,
+            "a cursor can only be declared for a select".to_string())]);
+    }
+    let mut result = CollectedResult::new();
+    stmt_handler(stmt, &mut result, manager);
+    if !result.errors.is_empty() {
+        return Err(result.errors);
+    }
+    Ok(Cursor{
+        columns : result.columns,
+        rows : result.rows.into_iter().collect(),
+        warnings : result.warnings,
+    })
+}
+
+// A result of at most count rows, as a select.
+// The fetch reaching the end of the rows has a warning, and so do the ones after it.
+fn fetch_rows(name : &str, cursor : &mut Cursor, count : usize, result_handler : &mut ResultHandler) {
+    result_handler.handle_metadata(&cursor.columns);
+    let row_num = min(count, cursor.rows.len());
+    for _ in 0..row_num {
+        result_handler.handle_row(cursor.rows.pop_front());
+    }
+    for msg in cursor.warnings.drain(..) {
+        result_handler.handle_warning(msg);
+    }
+    if cursor.rows.is_empty() {
+        result_handler.handle_warning(format!("cursor {} has no more rows", name));
+    }
+    result_handler.handle_row(None);
+    result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+}
+
+fn cursor_not_exist_error(name : &String) -> ResultError {
+    ResultError::new(ResultErrorType::CursorNotExist, format!("cursor {} not exist", name))
+}
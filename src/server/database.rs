@@ -0,0 +1,126 @@
+use std::fmt;
+use std::error::Error;
+use std::path::Path;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::vec::IntoIter;
+use ::store::table::{TableManager, TableManagerRef};
+use ::store::tuple::TupleValue;
+use ::utils::config::{Config, render_config_errors};
+use super::handler::{ColumnInfo, Summary};
+use super::result_error::{ResultError, render_errors};
+use super::collect::CollectedResult;
+use super::session::{Session, session_handler};
+
+
+pub type Row = Vec<TupleValue>;
+pub type Rows = IntoIter<Row>;
+
+// the whole result of a statement, owned by the caller
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    pub columns : Vec<ColumnInfo>,  // empty for the statements other than select
+    pub rows : Vec<Row>,
+    pub summary : Option<Summary>,  // None for create, drop and the other non-query statements
+    pub warnings : Vec<String>,
+    pub truncated : bool,  // by max_result_rows, with a warning
+}
+
+// the errors of a statement, with the same codes as the server sends
+#[derive(Debug, Clone)]
+pub struct DatabaseError {
+    pub errors : Vec<ResultError>,
+}
+
+impl DatabaseError {
+    // of the first error when there are several
+    pub fn code(&self) -> u32 {
+        self.errors[0].code()
+    }
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", render_errors(&self.errors))
+    }
+}
+
+impl Error for DatabaseError {
+    fn description(&self) -> &str {
+        &self.errors[0].error_msg
+    }
+}
+
+// Runs the statements in the calling thread without a server, as the local client does.
+// It is single-threaded: the tables are shared with Rc, so a Database is not Send.
+// The tables are saved to the files when it is closed or dropped.
+pub struct Database {
+    manager : TableManagerRef,
+    session : Session,
+    closed : bool,
+}
+
+impl Database {
+    // either the path of a config file or the config itself, an invalid config is returned as its errors
+    pub fn open(config_or_path : &str) -> Result<Database, String> {
+        let config = if Path::new(config_or_path).is_file() {
+            Config::from_file(config_or_path)
+        } else {
+            Config::parse(config_or_path, "config")
+        };
+        let config = try!(config.map_err(|errors| render_config_errors(&errors)));
+        Ok(Database{
+            manager : Rc::new(RefCell::new(TableManager::from_json_file(&config))),
+            session : Session::new(0),
+            closed : false,
+        })
+    }
+
+    pub fn execute(&mut self, sql : &str) -> Result<QueryResult, DatabaseError> {
+        let mut result = CollectedResult::new();
+        session_handler(&sql.to_string(), &mut self.session, &mut result, &self.manager);
+        if !result.errors.is_empty() {
+            return Err(DatabaseError{ errors : result.errors });
+        }
+        Ok(QueryResult{
+            columns : result.columns,
+            rows : result.rows,
+            summary : result.summary,
+            warnings : result.warnings,
+            truncated : result.truncated,
+        })
+    }
+
+    // the rows of a select
+    pub fn query(&mut self, sql : &str) -> Result<Rows, DatabaseError> {
+        let result = try!(self.execute(sql));
+        Ok(result.rows.into_iter())
+    }
+
+    // the same as dropping it
+    pub fn close(mut self) {
+        self.close_tables();
+    }
+
+    // remove all the tables and their files instead of saving them
+    pub fn drop_all(mut self) {
+        self.session.close();
+        self.manager.borrow_mut().drop_all();
+        self.closed = true;
+    }
+
+    fn close_tables(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.session.close();
+        self.manager.borrow_mut().close();
+        self.closed = true;
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        self.close_tables();
+    }
+}
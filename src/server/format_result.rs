@@ -0,0 +1,173 @@
+use std::io::{Write, stderr};
+use ::store::tuple::TupleValue;
+use ::utils::csv::{write_record, write_record_fields, value_to_field};
+use super::handler::{ResultHandler, ColumnInfo, Summary};
+use super::result_error::{ResultError, render_errors};
+use super::json_result::{JsonLayout, ColumnList, result_to_json};
+
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    Table,  // for reading, with the row counts and the warnings
+    Csv,
+    Tsv,
+    Json,   // a list of objects, written when all the rows are received
+}
+
+impl OutputFormat {
+    pub fn from_name(name : &str) -> Option<OutputFormat> {
+        match name.to_lowercase().as_ref() {
+            "table" => Some(OutputFormat::Table),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            OutputFormat::Table => "table",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    // json always has null
+    pub fn default_null_string(&self) -> &'static str {
+        match *self {
+            OutputFormat::Table => "NULL",
+            _ => "",
+        }
+    }
+}
+
+// Writes the results in the format for the local client.
+// Only the rows and the errors are written in csv, tsv and json so that the output can be piped,
+// the warnings go to stderr.
+pub struct FormatResult<W : Write> {
+    writer : W,
+    format : OutputFormat,
+    header : bool,
+    columns : Vec<ColumnInfo>,
+    rows : Vec<Vec<TupleValue>>,  // only kept for json
+    null_string : Option<String>,  // None for the default of the format
+}
+
+impl<W : Write> FormatResult<W> {
+    pub fn new(writer : W, format : OutputFormat, header : bool) -> FormatResult<W> {
+        FormatResult{
+            writer : writer,
+            format : format,
+            header : header,
+            columns : Vec::new(),
+            rows : Vec::new(),
+            null_string : None,
+        }
+    }
+
+    pub fn set_null_string(&mut self, null_string : Option<String>) {
+        self.null_string = null_string;
+    }
+
+    fn get_null_string(&self) -> String {
+        match self.null_string {
+            Some(ref s) => s.clone(),
+            None => self.format.default_null_string().to_string(),
+        }
+    }
+
+    pub fn get_writer(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn write_header(&mut self) {
+        let names = self.columns.iter().map(|c| c.name.clone()).collect();
+        match self.format {
+            OutputFormat::Table => {
+                let header : Vec<String> = self.columns.iter()
+                    .map(|c| format!("{} {:?}", c.name, c.attr_type)).collect();
+                let line = header.join(", ");
+                self.write_line(&line);
+            }
+            OutputFormat::Csv => check_ok!(write_record(&mut self.writer, &names, ',')),
+            OutputFormat::Tsv => check_ok!(write_record(&mut self.writer, &names, '\t')),
+            OutputFormat::Json => (),
+        }
+    }
+
+    pub fn write_row(&mut self, row : Vec<TupleValue>) {
+        // the same text as copy to
+        let null_string = self.get_null_string();
+        let fields = row.iter().map(|v| value_to_field(v, &null_string)).collect();
+        match self.format {
+            OutputFormat::Table => {
+                let values : Vec<String> = row.iter().map(|v| match v {
+                    &TupleValue::Null => null_string.clone(),
+                    v => format!("{:?}", v),
+                }).collect();
+                let line = format!("[{}]", values.join(", "));
+                self.write_line(&line);
+            }
+            OutputFormat::Csv => check_ok!(write_record_fields(&mut self.writer, &fields, ',')),
+            OutputFormat::Tsv => check_ok!(write_record_fields(&mut self.writer, &fields, '\t')),
+            OutputFormat::Json => self.rows.push(row),
+        }
+    }
+
+    pub fn finish_rows(&mut self) {
+        match self.format {
+            OutputFormat::Table => self.write_line("end"),
+            OutputFormat::Json => {
+                let columns : ColumnList = self.columns.iter()
+                    .map(|c| (c.name.clone(), c.attr_type.clone())).collect();
+                let json = result_to_json(&columns, &self.rows, JsonLayout::Objects);
+                self.write_line(&json);
+                self.rows.clear();
+            }
+            _ => (),
+        }
+    }
+
+    fn write_line(&mut self, line : &str) {
+        check_ok!(writeln!(self.writer, "{}", line));
+    }
+
+    fn write_message(&mut self, msg : &str) {
+        if self.format == OutputFormat::Table {
+            self.write_line(msg);
+        }
+    }
+}
+
+impl<W : Write> ResultHandler for FormatResult<W> {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        let msg = render_errors(&errors);
+        self.write_line(&msg);
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        match row {
+            Some(row) => self.write_row(row),
+            None => self.finish_rows(),
+        }
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.columns = columns.to_vec();
+        if self.header && !columns.is_empty() {
+            self.write_header();
+        }
+    }
+    fn handle_non_query_finished(&mut self) {
+        self.write_message("end");
+    }
+    fn handle_complete(&mut self, summary : Summary) {
+        self.write_message(&summary.message());
+    }
+    fn handle_warning(&mut self, msg : String) {
+        match self.format {
+            OutputFormat::Table => self.write_line(&format!("warning: {}", msg)),
+            _ => check_ok!(writeln!(stderr(), "warning: {}", msg)),
+        }
+    }
+}
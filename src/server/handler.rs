@@ -1,132 +1,715 @@
-use std::result::Result;
-use ::parser::common::Statement;
-use ::parser::compile_error::ErrorList;
-use ::parser::lexer::{TokenLine, TokenType};
-use ::parser::sem_check::check_sem;
-use ::parser::unimpl::check_stmt_unimpl;
-use ::store::tuple::TupleData;
-use ::store::table::{TableManagerRef, Table, TableSet, AttrType};
-use ::exec::gen_plan::{gen_table_set, gen_plan};
-use ::exec::gen_plan::gen_proj_info;
-use ::exec::error::ExecError;
-use ::utils::array::projection;
-
-
-pub type ResultHandlerRef = Box<ResultHandler>;
-
-pub trait ResultHandler {
-    fn handle_error(&mut self, err_msg : String);
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>);
-    fn handle_non_query_finished(&mut self);
-    fn set_tuple_info(&mut self, attr_desc : Vec<AttrType>, attr_index : Vec<usize>);
-}
-
-
-fn gen_parse_result(input : &String) -> Result<Statement, ErrorList> {
-    let line = TokenLine::parse(input);
-    if line.errors.len() > 0 {
-        return Err(line.errors);
-    }
-    Statement::parse(&mut line.tokens.iter())
-}
-
-
-pub fn process_table_command(input : &String, manager : &TableManagerRef) -> Result<String, ()> {
-    match input.as_ref() {
-        "show tables" => Ok(show_tables(manager)),
-        _ => Err(()),
-    }
-}
-
-fn show_tables(manager : &TableManagerRef) -> String {
-    manager.borrow().show_tables()
-}
-
-
-pub fn sql_handler(input : &String, result_handler : &mut ResultHandler, manager : &TableManagerRef) {
-    let parse_result = gen_parse_result(input);
-    let mut stmt = match parse_result {
-        Ok(stmt) => stmt,
-        Err(ref err_list) => return result_handler.handle_error(handle_sql_err(err_list)),
-    };
-    if let Err(ref err_list) = check_stmt_unimpl(&stmt) {
-        return result_handler.handle_error(handle_sql_err(err_list));
-    }
-    let table_set = gen_table_set(&stmt, manager);
-    if let Err(ref err_list) = check_sem(&mut stmt, &table_set) {
-        return result_handler.handle_error(handle_sql_err(err_list));
-    }
-
-    match &stmt {
-        &Statement::Select(..) => {
-            let table = get_table(&table_set);
-            let mut attr_desc = table.gen_tuple_desc().attr_desc;
-            let (attr_index, _) = gen_proj_info(&stmt, &manager);
-            attr_desc = projection(&attr_index, attr_desc);
-            result_handler.set_tuple_info(attr_desc, attr_index);
-
-            let mut plan = gen_plan(stmt, manager);
-            plan.open();
-            loop {
-                match plan.get_next() {
-                    Some(tuple_data) => {
-                        result_handler.handle_tuple_data(Some(tuple_data));
-                    }
-                    None => {
-                        if let Some(ref err) = plan.get_error() {
-                            result_handler.handle_error(handle_exec_err(err));
-                        } else {
-                            result_handler.handle_tuple_data(None);
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-        _ => {
-            let mut plan = gen_plan(stmt, manager);
-            plan.open();
-            loop {
-                match plan.get_next() {
-                    Some(..) => continue,
-                    None => break,
-                }
-            }
-            if let Some(ref err) = plan.get_error() {
-                result_handler.handle_error(handle_exec_err(err));
-            } else {
-                result_handler.handle_non_query_finished();
-                manager.borrow_mut().save_to_file();
-            }
-        }
-    }
-}
-
-fn get_table(table_set : &TableSet) -> Table {
-    // only suport one table now
-    assert_eq!(table_set.tables.len(), 1);
-    for (_, t) in table_set.tables.iter() {
-        return t.clone();
-    }
-    panic!("empty table set");
-}
-
-fn handle_sql_err(err_list : &ErrorList) -> String {
-    let mut err_msg = String::new();
-    for err in err_list.iter() {
-        let msg = match err.token.token_type {
-            TokenType::UnKnown =>
-                format!("{:?}: {}", err.error_type, err.error_msg),
-            _ => format!("{:?} column {} `{}`: {}",
-                err.error_type, err.token.column, err.token.value, err.error_msg),
-        };
-        err_msg.push_str(&msg);
-        err_msg.push('\n');
-    }
-    err_msg.pop();  // pop the last \n
-    err_msg
-}
-
-fn handle_exec_err(err : &ExecError) -> String {
-    format!("{:?}: {}", err.error_type, err.error_msg)
-}
+use std::result::Result;
+use std::collections::HashMap;
+use std::time::Instant;
+use ::parser::common::{Statement, ValueList, parse_tokens};
+use ::parser::compile_error::{CompileErrorType, ErrorList};
+use ::parser::lexer::{TokenLine, TokenList};
+use ::parser::sem_check::{check_sem, create_error, table_not_exist};
+use ::parser::unimpl::check_stmt_unimpl;
+use ::parser::prepare::{PrepareCommand, check_no_placeholder, number_placeholders, bind_params};
+use ::parser::grant::GrantCommand;
+use ::store::tuple::{TupleValue, gen_tuple_value};
+use ::store::table::{TableManagerRef, TableSet, AttrType, Privilege};
+use ::store::lock::{TableLockGuard, LockMode};
+use ::exec::gen_plan::{gen_table_set, gen_plan, check_plan_pages};
+use ::exec::gen_plan::{gen_proj_info, gen_column_names};
+use ::exec::iter::ExecIterRef;
+use ::exec::instrument::{OperatorNode, instrument};
+use ::utils::guard::QueryGuard;
+use ::exec::error::ExecErrorType;
+use super::result_error::{ResultError, ResultErrorType, from_compile_errors, from_exec_error};
+
+
+pub type ResultHandlerRef = Box<ResultHandler>;
+
+// the column of a result, in the order of the values in a row
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct ColumnInfo {
+    pub name : String,
+    pub attr_type : AttrType,
+    pub nullable : bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StatementKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    CopyTo,
+}
+
+impl StatementKind {
+    fn from_stmt(stmt : &Statement) -> Option<StatementKind> {
+        match stmt {
+            &Statement::Select(..) => Some(StatementKind::Select),
+            &Statement::Insert(..) => Some(StatementKind::Insert),
+            &Statement::Update(..) => Some(StatementKind::Update),
+            &Statement::Delete(..) => Some(StatementKind::Delete),
+            &Statement::CopyTo(..) => Some(StatementKind::CopyTo),
+            // the client streaming the rows gets the count of them inserted
+            &Statement::CopyFrom(ref copy) if copy.is_stdin() => Some(StatementKind::Insert),
+            _ => None,
+        }
+    }
+    // sent in the native protocol, never changes once assigned
+    pub fn code(&self) -> u8 {
+        match self {
+            &StatementKind::Select => 0,
+            &StatementKind::Insert => 1,
+            &StatementKind::Update => 2,
+            &StatementKind::Delete => 3,
+            &StatementKind::CopyTo => 4,
+        }
+    }
+    pub fn from_code(code : u8) -> Option<StatementKind> {
+        match code {
+            0 => Some(StatementKind::Select),
+            1 => Some(StatementKind::Insert),
+            2 => Some(StatementKind::Update),
+            3 => Some(StatementKind::Delete),
+            4 => Some(StatementKind::CopyTo),
+            _ => None,
+        }
+    }
+}
+
+// the rows returned by a select, or the rows affected by the other statements
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Summary {
+    pub kind : StatementKind,
+    pub row_num : usize,
+}
+
+impl Summary {
+    pub fn new(kind : StatementKind, row_num : usize) -> Summary {
+        Summary{ kind : kind, row_num : row_num }
+    }
+    pub fn message(&self) -> String {
+        let rows = match self.row_num {
+            1 => "1 row".to_string(),
+            n => format!("{} rows", n),
+        };
+        match self.kind {
+            StatementKind::Select => format!("{} in set", rows),
+            _ => format!("Query OK, {} affected", rows),
+        }
+    }
+}
+
+// the warning of a select whose rows after max_result_rows are dropped
+pub const TRUNCATED_WARNING : &'static str = "result truncated to max_result_rows";
+
+pub trait ResultHandler {
+    // compile errors may come in a list, an execution error is always alone
+    fn handle_error(&mut self, errors : Vec<ResultError>);
+    // decoded while the page is still pinned, None after the last row
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>);
+    fn handle_non_query_finished(&mut self);
+    // the last call for a select after handle_row(None), and for insert, update, delete and copy to
+    fn handle_complete(&mut self, summary : Summary);
+    // called once before the first row or the summary, the columns follow the projection
+    // and don't change until the statement completes, empty for insert, update, delete and copy to
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]);
+    // sent before the end of the rows, the result is still complete for the client
+    fn handle_warning(&mut self, msg : String);
+}
+
+
+#[derive(Debug)]
+pub struct PreparedStatement {
+    input : String,  // parsed again when the tables have changed
+    stmt : Statement,
+    param_num : usize,
+    schema_version : usize,
+}
+
+// the prepared statements of a connection, dropped with the connection
+pub type PreparedStatements = HashMap<String, PreparedStatement>;
+
+
+fn gen_parse_result(input : &String) -> Result<Statement, ErrorList> {
+    let line = TokenLine::parse(input);
+    if line.errors.len() > 0 {
+        return Err(line.errors);
+    }
+    try!(check_no_placeholder(&line.tokens));
+    parse_tokens(&line.tokens)
+}
+
+
+pub fn process_table_command(input : &String, manager : &TableManagerRef) -> Result<String, ()> {
+    match input.as_ref() {
+        "show tables" => Ok(show_tables(manager)),
+        "show status" => Ok(manager.borrow().show_status()),
+        // for the completion of the clients, a line of json and the version alone to check it
+        "show namespace" => Ok(format!("{}\r\n", manager.borrow().namespace_snapshot().to_json())),
+        "show namespace version" => Ok(format!("{}\r\n", manager.borrow().get_schema_version())),
+        _ => Err(()),
+    }
+}
+
+fn show_tables(manager : &TableManagerRef) -> String {
+    manager.borrow().show_tables()
+}
+
+// `show buffer`, a row for each page in the pool from the most recently accessed, as a select
+pub fn process_show_buffer(input : &String, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    if input != "show buffer" {
+        return Err(());
+    }
+    let pages = manager.borrow().file_manager.get_buffer_snapshot();
+    let columns = vec![
+        ("table", AttrType::Char{ len : 32 }),
+        ("page_index", AttrType::Int),
+        ("pin_count", AttrType::Int),
+        ("dirty", AttrType::Char{ len : 5 }),
+        // may not fit in an int for a long running server
+        ("last_access", AttrType::Char{ len : 20 }),
+    ];
+    let columns : Vec<ColumnInfo> = columns.into_iter().map(|(name, attr_type)| ColumnInfo{
+        name : name.to_string(),
+        attr_type : attr_type,
+        nullable : false,
+    }).collect();
+    result_handler.handle_metadata(&columns);
+    let row_num = pages.len();
+    for (table, page) in pages.into_iter() {
+        result_handler.handle_row(Some(vec![
+            TupleValue::Char(table),
+            TupleValue::Int(page.page_index as i32),
+            TupleValue::Int(page.pin_count as i32),
+            TupleValue::Char(page.dirty.to_string()),
+            TupleValue::Char(page.last_access.to_string()),
+        ]));
+    }
+    result_handler.handle_row(None);
+    result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+    Ok(())
+}
+
+// `flush`, the changes in memory are written and synced to the disk, with an error for each table failed
+pub fn process_flush(input : &String, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    if input != "flush" {
+        return Err(());
+    }
+    let errors = manager.borrow_mut().flush_all();
+    if errors.is_empty() {
+        result_handler.handle_non_query_finished();
+    } else {
+        result_handler.handle_error(errors.into_iter().map(|error_msg|
+            ResultError::new(ResultErrorType::Exec(ExecErrorType::FlushFailed), error_msg)).collect());
+    }
+    Ok(())
+}
+
+// `grant` and `revoke`, only by the admin, the result is non-query
+pub fn process_grant_command(input : &String, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    let line = TokenLine::parse(input);
+    let command = match GrantCommand::parse(&line.tokens) {
+        Some(command) => command,
+        None => return Err(()),
+    };
+    if line.errors.len() > 0 {
+        result_handler.handle_error(from_compile_errors(&line.errors));
+        return Ok(());
+    }
+    let command = match command {
+        Ok(command) => command,
+        Err(ref err_list) => {
+            result_handler.handle_error(from_compile_errors(err_list));
+            return Ok(());
+        }
+    };
+    if !manager.borrow().is_admin() {
+        let action = if command.grant { "grant" } else { "revoke" };
+        result_handler.handle_error(vec![permission_denied_error(action)]);
+        return Ok(());
+    }
+    if manager.borrow().get_table(&command.table).is_none() {
+        let err_list = create_error(CompileErrorType::SemTableNotExist, table_not_exist(&command.table));
+        result_handler.handle_error(from_compile_errors(&err_list));
+        return Ok(());
+    }
+    if command.grant {
+        manager.borrow_mut().grant(&command.table, &command.user, &command.privileges);
+    } else {
+        manager.borrow_mut().revoke(&command.table, &command.user, &command.privileges);
+    }
+    result_handler.handle_non_query_finished();
+    Ok(())
+}
+
+fn permission_denied_error(object : &str) -> ResultError {
+    ResultError::new(ResultErrorType::PermissionDenied, format!("permission denied for {}", object))
+}
+
+// `show stats for t`, a row for each column with the stats of the last `analyze t`, no row before it
+pub fn process_show_stats(input : &String, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    if !input.starts_with("show stats for ") {
+        return Err(());
+    }
+    let name = input["show stats for ".len()..].trim();
+    let table = manager.borrow().get_table(name);
+    let table = match table {
+        Some(table) => table,
+        None => {
+            let err_list = create_error(CompileErrorType::SemTableNotExist, table_not_exist(name));
+            result_handler.handle_error(from_compile_errors(&err_list));
+            return Ok(());
+        }
+    };
+    // the min and max are the values of the rows
+    if !manager.borrow().has_privilege(name, Privilege::Select) {
+        result_handler.handle_error(vec![table_permission_error(name)]);
+        return Ok(());
+    }
+    let columns = vec![
+        ("column", AttrType::Char{ len : 32 }),
+        ("row_num", AttrType::Int),
+        ("distinct_num", AttrType::Int),
+        ("null_fraction", AttrType::Float),
+        ("min", AttrType::Char{ len : 255 }),
+        ("max", AttrType::Char{ len : 255 }),
+        // the bounds of the buckets separated by spaces, empty for a char column
+        ("histogram", AttrType::Char{ len : 1024 }),
+    ];
+    let columns : Vec<ColumnInfo> = columns.into_iter().map(|(name, attr_type)| ColumnInfo{
+        name : name.to_string(),
+        attr_type : attr_type,
+        nullable : false,
+    }).collect();
+    result_handler.handle_metadata(&columns);
+    let mut row_num = 0;
+    if let Some(ref stats) = table.borrow().stats {
+        for column in stats.columns.iter() {
+            let histogram : Vec<String> = column.histogram.iter().map(|b| b.to_string()).collect();
+            result_handler.handle_row(Some(vec![
+                TupleValue::Char(column.name.clone()),
+                TupleValue::Int(stats.row_num as i32),
+                TupleValue::Int(column.distinct_num as i32),
+                TupleValue::Float(column.null_fraction as f32),
+                TupleValue::Char(column.min.clone().unwrap_or(String::new())),
+                TupleValue::Char(column.max.clone().unwrap_or(String::new())),
+                TupleValue::Char(histogram.join(" ")),
+            ]));
+            row_num += 1;
+        }
+    }
+    result_handler.handle_row(None);
+    result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+    Ok(())
+}
+
+// `explain analyze stmt` runs the statement, then returns a row for each operator of its plan from the root,
+// with the get_next calls, the rows returned and the time inside the operator without its children
+pub fn process_explain_analyze(input : &String, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    if !input.starts_with("explain analyze ") {
+        return Err(());
+    }
+    let input = input["explain analyze ".len()..].to_string();
+    let mut stmt = match gen_parse_result(&input) {
+        Ok(stmt) => stmt,
+        Err(ref err_list) => {
+            result_handler.handle_error(from_compile_errors(err_list));
+            return Ok(());
+        }
+    };
+    let table_set = match check_stmt(&mut stmt, manager) {
+        Ok(table_set) => table_set,
+        Err(errors) => {
+            result_handler.handle_error(errors);
+            return Ok(());
+        }
+    };
+    let mut discarded = DiscardedResult{ errors : None };
+    let nodes = execute_stmt(stmt, &table_set, &mut discarded, manager, true);
+    if let Some(errors) = discarded.errors {
+        result_handler.handle_error(errors);
+        return Ok(());
+    }
+    let columns = vec![
+        ("operator", AttrType::Char{ len : 255 }),  // indented by the depth in the plan
+        ("calls", AttrType::Int),
+        ("rows", AttrType::Int),
+        ("time_us", AttrType::Int),
+    ];
+    let columns : Vec<ColumnInfo> = columns.into_iter().map(|(name, attr_type)| ColumnInfo{
+        name : name.to_string(),
+        attr_type : attr_type,
+        nullable : false,
+    }).collect();
+    result_handler.handle_metadata(&columns);
+    let row_num = nodes.len();
+    for node in nodes.iter() {
+        let mut operator = format!("{}{}", "  ".repeat(node.depth), node.describe);
+        while operator.len() > 255 {
+            operator.pop();
+        }
+        let elapsed = node.exclusive_elapsed();
+        let stats = node.stats.borrow();
+        result_handler.handle_row(Some(vec![
+            TupleValue::Char(operator),
+            TupleValue::Int(stats.calls as i32),
+            TupleValue::Int(stats.rows as i32),
+            TupleValue::Int((elapsed.as_secs() * 1000000 + (elapsed.subsec_nanos() / 1000) as u64) as i32),
+        ]));
+    }
+    result_handler.handle_row(None);
+    result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+    Ok(())
+}
+
+// the result of the statement run by explain analyze, only its errors are returned
+struct DiscardedResult {
+    errors : Option<Vec<ResultError>>,
+}
+
+impl ResultHandler for DiscardedResult {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        self.errors = Some(errors);
+    }
+    fn handle_row(&mut self, _row : Option<Vec<TupleValue>>) {}
+    fn handle_non_query_finished(&mut self) {}
+    fn handle_complete(&mut self, _summary : Summary) {}
+    fn handle_metadata(&mut self, _columns : &[ColumnInfo]) {}
+    fn handle_warning(&mut self, _msg : String) {}
+}
+
+
+pub fn process_prepare_command(input : &String, prepared : &mut PreparedStatements,
+        result_handler : &mut ResultHandler, manager : &TableManagerRef) -> Result<(), ()> {
+    let line = TokenLine::parse(input);
+    let command = match PrepareCommand::parse(&line.tokens) {
+        Some(command) => command,
+        None => return Err(()),
+    };
+    if line.errors.len() > 0 {
+        result_handler.handle_error(from_compile_errors(&line.errors));
+        return Ok(());
+    }
+    match command {
+        Err(ref err_list) => result_handler.handle_error(from_compile_errors(err_list)),
+        Ok(PrepareCommand::Prepare{name, tokens}) => {
+            if prepared.contains_key(&name) {
+                let err_msg = format!("prepared statement {} already exist", name);
+                result_handler.handle_error(vec![
+                    ResultError::new(ResultErrorType::PreparedStatementExist, err_msg)]);
+                return Ok(());
+            }
+            match prepare_stmt(input, &tokens, manager) {
+                Ok(prepared_stmt) => {
+                    prepared.insert(name, prepared_stmt);
+                    result_handler.handle_non_query_finished();
+                }
+                Err(errors) => result_handler.handle_error(errors),
+            }
+        }
+        Ok(PrepareCommand::Execute{name, params}) => {
+            match bind_prepared_stmt(&name, &params, prepared, manager) {
+                Ok((stmt, table_set)) => run_stmt(stmt, &table_set, result_handler, manager),
+                Err(errors) => result_handler.handle_error(errors),
+            }
+        }
+        Ok(PrepareCommand::Deallocate{name}) => {
+            match prepared.remove(&name) {
+                Some(..) => result_handler.handle_non_query_finished(),
+                None => result_handler.handle_error(vec![prepared_not_exist_error(&name)]),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn prepare_stmt(input : &String, tokens : &TokenList, manager : &TableManagerRef)
+        -> Result<PreparedStatement, Vec<ResultError>> {
+    let (tokens, param_num) = number_placeholders(tokens);
+    let mut stmt = match parse_tokens(&tokens) {
+        Ok(stmt) => stmt,
+        Err(ref err_list) => return Err(from_compile_errors(err_list)),
+    };
+    try!(check_stmt(&mut stmt, manager));
+    Ok(PreparedStatement{
+        input : input.clone(),
+        stmt : stmt,
+        param_num : param_num,
+        schema_version : manager.borrow().get_schema_version(),
+    })
+}
+
+fn bind_prepared_stmt(name : &String, params : &ValueList, prepared : &mut PreparedStatements,
+        manager : &TableManagerRef) -> Result<(Statement, TableSet), Vec<ResultError>> {
+    let schema_version = manager.borrow().get_schema_version();
+    let (input, param_num) = match prepared.get(name) {
+        Some(stmt) if stmt.schema_version == schema_version => (None, stmt.param_num),
+        Some(stmt) => (Some(stmt.input.clone()), stmt.param_num),
+        None => return Err(vec![prepared_not_exist_error(name)]),
+    };
+    // the placeholders are in the text, which the ddl doesn't change
+    if params.len() != param_num {
+        let err_list = create_error(CompileErrorType::SemInvalidParameterNum,
+            format!("invalid parameter number, expected {}, found {}", param_num, params.len()));
+        return Err(from_compile_errors(&err_list));
+    }
+    if let Some(ref input) = input {
+        // re-checked as the ddl since preparing may have changed the meaning of the statement,
+        // it's left stale if that fails and checked again by the next execute
+        let line = TokenLine::parse(input);
+        let tokens = match PrepareCommand::parse(&line.tokens) {
+            Some(Ok(PrepareCommand::Prepare{tokens, ..})) => tokens,
+            _ => panic!("invalid prepare command {:?}", input),
+        };
+        let prepared_stmt = try!(prepare_stmt(input, &tokens, manager));
+        prepared.insert(name.clone(), prepared_stmt);
+    }
+    let prepared_stmt = prepared.get(name).unwrap();
+    let mut stmt = prepared_stmt.stmt.clone();
+    bind_params(&mut stmt, params);
+    let table_set = try!(check_stmt(&mut stmt, manager));
+    Ok((stmt, table_set))
+}
+
+fn prepared_not_exist_error(name : &String) -> ResultError {
+    ResultError::new(ResultErrorType::PreparedStatementNotExist,
+        format!("prepared statement {} not exist", name))
+}
+
+
+pub fn sql_handler(input : &String, result_handler : &mut ResultHandler, manager : &TableManagerRef) {
+    log_debug!("statement begin: {}", input);
+    let start = Instant::now();
+    match gen_parse_result(input) {
+        Ok(stmt) => stmt_handler(stmt, result_handler, manager),
+        Err(ref err_list) => result_handler.handle_error(from_compile_errors(err_list)),
+    }
+    let elapsed = start.elapsed();
+    log_debug!("statement end after {} us: {}",
+        elapsed.as_secs() * 1000000 + (elapsed.subsec_nanos() / 1000) as u64, input);
+}
+
+// a statement parsed out of a command, such as the select of a cursor
+pub fn stmt_handler(mut stmt : Statement, result_handler : &mut ResultHandler, manager : &TableManagerRef) {
+    match check_stmt(&mut stmt, manager) {
+        Ok(table_set) => run_stmt(stmt, &table_set, result_handler, manager),
+        Err(errors) => result_handler.handle_error(errors),
+    }
+}
+
+// shared for the tables the statement reads or changes, exclusive for the ones it creates, drops or alters
+fn lock_stmt_tables(stmt : &Statement, table_set : &TableSet, guard : &QueryGuard, manager : &TableManagerRef)
+        -> Result<TableLockGuard, ResultError> {
+    let (tables, mode) = match stmt {
+        &Statement::Create(ref create) => (vec![create.table.clone()], LockMode::Exclusive),
+        &Statement::Drop(ref drop) => (vec![drop.table.clone()], LockMode::Exclusive),
+        &Statement::Alter(ref alter) => (vec![alter.table.clone()], LockMode::Exclusive),
+        &Statement::Backup(..) => (manager.borrow().get_table_names(), LockMode::Shared),
+        &Statement::Restore(..) => (manager.borrow().get_table_names(), LockMode::Exclusive),
+        _ => (table_set.tables.keys().cloned().collect(), LockMode::Shared),
+    };
+    let requests = tables.into_iter().map(|table| (table, mode)).collect();
+    let lock_timeout_ms = manager.borrow().get_table_lock_timeout_ms();
+    // the wait never outlives the statement
+    let remaining_ms = guard.get_remaining()
+        .map(|d| d.as_secs() as usize * 1000 + d.subsec_nanos() as usize / 1000000);
+    match remaining_ms {
+        Some(remaining_ms) if remaining_ms < lock_timeout_ms => {
+            manager.borrow().lock_tables(requests, remaining_ms).map_err(|error_msg| {
+                let error_msg = format!("{}, statement timeout of {} ms exceeded",
+                    error_msg, guard.get_timeout_ms());
+                ResultError::new(ResultErrorType::Exec(ExecErrorType::StatementTimeout), error_msg)
+            })
+        }
+        _ => manager.borrow().lock_tables(requests, lock_timeout_ms).map_err(|error_msg|
+            ResultError::new(ResultErrorType::Exec(ExecErrorType::TableInUse), error_msg)),
+    }
+}
+
+fn check_stmt(stmt : &mut Statement, manager : &TableManagerRef) -> Result<TableSet, Vec<ResultError>> {
+    if let Err(ref err_list) = check_stmt_unimpl(stmt) {
+        return Err(from_compile_errors(err_list));
+    }
+    let table_set = gen_table_set(stmt, manager);
+    if let Err(ref err_list) = check_sem(stmt, &table_set) {
+        return Err(from_compile_errors(err_list));
+    }
+    try!(check_privileges(stmt, &table_set, manager));
+    Ok(table_set)
+}
+
+// The privileges of the user of the session on the tables the statement uses, the tables of a with table
+// or a sub query are read by the select using it. Only the admin can run ddl, analyze, backup and restore.
+fn check_privileges(stmt : &Statement, table_set : &TableSet, manager : &TableManagerRef)
+        -> Result<(), Vec<ResultError>> {
+    let manager = manager.borrow();
+    if manager.is_admin() {
+        return Ok(());
+    }
+    let required : Vec<(String, Privilege)> = match stmt {
+        &Statement::Select(..) | &Statement::CopyTo(..) =>
+            table_set.tables.keys().map(|table| (table.clone(), Privilege::Select)).collect(),
+        &Statement::Insert(ref insert) => vec![(insert.table.clone(), Privilege::Insert)],
+        &Statement::CopyFrom(ref copy) => vec![(copy.table.clone(), Privilege::Insert)],
+        &Statement::Update(ref update) => vec![(update.table.clone(), Privilege::Update)],
+        &Statement::Delete(ref delete) => vec![(delete.table.clone(), Privilege::Delete)],
+        &Statement::Create(ref create) => return Err(vec![table_permission_error(&create.table)]),
+        &Statement::Drop(ref drop) => return Err(vec![table_permission_error(&drop.table)]),
+        &Statement::Alter(ref alter) => return Err(vec![table_permission_error(&alter.table)]),
+        &Statement::Analyze(ref analyze) => return Err(vec![table_permission_error(&analyze.table)]),
+        &Statement::Backup(..) => return Err(vec![permission_denied_error("backup")]),
+        &Statement::Restore(..) => return Err(vec![permission_denied_error("restore")]),
+    };
+    for (table, privilege) in required.into_iter() {
+        if !manager.has_privilege(&table, privilege) {
+            return Err(vec![table_permission_error(&table)]);
+        }
+    }
+    Ok(())
+}
+
+fn table_permission_error(table : &str) -> ResultError {
+    permission_denied_error(&format!("table {}", table))
+}
+
+fn run_stmt(stmt : Statement, table_set : &TableSet, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) {
+    execute_stmt(stmt, table_set, result_handler, manager, false);
+}
+
+// the operators of the plan with their stats when analyze is true, empty otherwise
+fn execute_stmt(stmt : Statement, table_set : &TableSet, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef, analyze : bool) -> Vec<OperatorNode> {
+    let guard = manager.borrow_mut().start_query();
+    // held until the plan is closed at the end
+    let _table_lock = match lock_stmt_tables(&stmt, table_set, &guard, manager) {
+        Ok(table_lock) => table_lock,
+        Err(error) => {
+            manager.borrow_mut().finish_query();
+            result_handler.handle_error(vec![error]);
+            return Vec::new();
+        }
+    };
+    let operator_nodes;
+    match &stmt {
+        &Statement::Select(..) => {
+            let columns = gen_column_info(&stmt, table_set, manager);
+            let attr_desc = columns.iter().map(|c| c.attr_type.clone()).collect();
+            let max_rows = manager.borrow().get_max_result_rows();
+            let (mut plan, nodes) = gen_run_plan(stmt, manager, analyze);
+            if let Err(ref err) = check_plan_pages(&plan, manager) {
+                manager.borrow_mut().finish_query();
+                result_handler.handle_error(vec![from_exec_error(err)]);
+                return Vec::new();
+            }
+            let mut row_num = 0;
+            result_handler.handle_metadata(&columns);
+            plan.open();
+            loop {
+                match plan.get_next() {
+                    Some(..) if max_rows > 0 && row_num == max_rows => {
+                        // the rows after the limit are dropped
+                        plan.close();
+                        result_handler.handle_warning(format!("{} {}", TRUNCATED_WARNING, max_rows));
+                        result_handler.handle_row(None);
+                        result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+                        break;
+                    }
+                    Some(tuple_data) => {
+                        // the rows may be sent to a slow client
+                        if let Err(error_msg) = guard.check() {
+                            plan.close();
+                            let error_type = ResultErrorType::Exec(ExecErrorType::QueryCancelled);
+                            result_handler.handle_error(vec![ResultError::new(error_type, error_msg)]);
+                            break;
+                        }
+                        result_handler.handle_row(Some(gen_tuple_value(&attr_desc, tuple_data)));
+                        row_num += 1;
+                    }
+                    None => {
+                        if let Some(ref err) = plan.get_error() {
+                            result_handler.handle_error(vec![from_exec_error(err)]);
+                        } else {
+                            result_handler.handle_row(None);
+                            result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+                        }
+                        break;
+                    }
+                }
+            }
+            record_slow_plan(&guard, &plan, manager);
+            operator_nodes = nodes;
+        }
+        _ => {
+            let kind = StatementKind::from_stmt(&stmt);
+            let (mut plan, nodes) = gen_run_plan(stmt, manager, analyze);
+            if let Err(ref err) = check_plan_pages(&plan, manager) {
+                manager.borrow_mut().finish_query();
+                result_handler.handle_error(vec![from_exec_error(err)]);
+                return Vec::new();
+            }
+            let mut row_num = 0;
+            plan.open();
+            loop {
+                match plan.get_next() {
+                    Some(..) => row_num += 1,
+                    None => break,
+                }
+            }
+            record_slow_plan(&guard, &plan, manager);
+            if let Some(ref err) = plan.get_error() {
+                result_handler.handle_error(vec![from_exec_error(err)]);
+            } else if kind == Some(StatementKind::CopyTo) {
+                // copy to returns the tuples written to the file
+                result_handler.handle_metadata(&[]);
+                result_handler.handle_complete(Summary::new(StatementKind::CopyTo, row_num));
+            } else if let Some(kind) = kind {
+                result_handler.handle_metadata(&[]);
+                result_handler.handle_complete(Summary::new(kind, plan.get_affected_num()));
+            } else {
+                result_handler.handle_non_query_finished();
+            }
+            // copy may have inserted part of the rows before failing
+            manager.borrow_mut().save_to_file();
+            operator_nodes = nodes;
+        }
+    }
+    manager.borrow_mut().finish_query();
+    operator_nodes
+}
+
+fn gen_run_plan(stmt : Statement, manager : &TableManagerRef, analyze : bool)
+        -> (ExecIterRef, Vec<OperatorNode>) {
+    let plan = gen_plan(stmt, manager);
+    if analyze { instrument(plan) } else { (plan, Vec::new()) }
+}
+
+// kept in the manager for the slow query log, after the plan is run
+pub fn record_slow_plan(guard : &QueryGuard, plan : &ExecIterRef, manager : &TableManagerRef) {
+    let slow_query_ms = manager.borrow().get_slow_query_ms();
+    let elapsed = guard.elapsed();
+    let elapsed_ms = elapsed.as_secs() as usize * 1000 + elapsed.subsec_nanos() as usize / 1000000;
+    if slow_query_ms > 0 && elapsed_ms >= slow_query_ms {
+        manager.borrow_mut().set_slow_plan(plan.explain());
+    }
+}
+
+fn gen_column_info(stmt : &Statement, table_set : &TableSet, manager : &TableManagerRef) -> Vec<ColumnInfo> {
+    // follow the projection of the plan
+    let (_, proj_attr_list) = gen_proj_info(stmt, manager);
+    let names = gen_column_names(&proj_attr_list);
+    let mut columns = Vec::new();
+    for (&(ref table_name, ref attr_name), name) in proj_attr_list.iter().zip(names) {
+        let table = table_set.tables.get(table_name).unwrap();
+        let attr = table.attr_list.iter().filter(|a| a.name == *attr_name).next().unwrap();
+        columns.push(ColumnInfo{
+            name : name,
+            attr_type : attr.attr_type.clone(),
+            nullable : attr.nullable,
+        });
+    }
+    columns
+}
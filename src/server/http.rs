@@ -0,0 +1,288 @@
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use ::utils::base64::decode_base64;
+use ::utils::config::Config;
+use super::auth::UserTable;
+use super::collect::CollectedResult;
+use super::json_result::{JsonLayout, JsonResultWriter, json_string};
+use super::result_error::{ResultError, ResultErrorType};
+use super::processlist::ProcessListRef;
+use super::server::{Task, TaskQueueRef};
+
+
+const MAX_BODY_BYTES : usize = 1 << 20;
+const CHUNK_BYTES : usize = 8192;
+
+// POST /query with the statement as the body. A query is answered with the json envelope
+// in chunks, the other statements with {"row_count":n} or {}, an error with {"errors":[...]}.
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method : String,
+    pub path : String,
+    pub headers : Vec<(String, String)>,  // the names in lowercase
+    pub body : Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn get_header(&self, name : &str) -> Option<&str> {
+        self.headers.iter().find(|&&(ref n, _)| *n == name).map(|&(_, ref v)| v.as_str())
+    }
+}
+
+// Err with the status for a malformed request
+pub fn read_request<R : BufRead>(reader : &mut R) -> Result<HttpRequest, (u16, String)> {
+    let mut line = String::new();
+    try!(reader.read_line(&mut line).map_err(|e| (400, e.to_string())));
+    let (method, path) = {
+        let parts : Vec<&str> = line.trim().split(' ').collect();
+        if parts.len() != 3 || !parts[2].starts_with("HTTP/1.") {
+            return Err((400, "invalid request line".to_string()));
+        }
+        (parts[0].to_string(), parts[1].to_string())
+    };
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        try!(reader.read_line(&mut line).map_err(|e| (400, e.to_string())));
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        match header.find(':') {
+            Some(i) => headers.push((header[..i].trim().to_lowercase(), header[i + 1..].trim().to_string())),
+            None => return Err((400, format!("invalid header `{}`", header))),
+        }
+    }
+    let mut request = HttpRequest{ method : method, path : path, headers : headers, body : Vec::new() };
+    let body_len = match request.get_header("content-length") {
+        Some(len) => try!(len.parse::<usize>().map_err(|_| (400, "invalid content-length".to_string()))),
+        None => 0,
+    };
+    if body_len > MAX_BODY_BYTES {
+        return Err((413, format!("the body is over {} bytes", MAX_BODY_BYTES)));
+    }
+    request.body = vec![0u8; body_len];
+    try!(reader.read_exact(&mut request.body).map_err(|e| (400, e.to_string())));
+    Ok(request)
+}
+
+// the user and password of `Authorization: Basic ...`
+pub fn parse_basic_auth(header : &str) -> Option<(String, String)> {
+    if !header.starts_with("Basic ") {
+        return None;
+    }
+    let decoded = match decode_base64(header[6..].trim()) {
+        Some(decoded) => String::from_utf8_lossy(&decoded).into_owned(),
+        None => return None,
+    };
+    let mut parts = decoded.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(user), Some(password)) => Some((user.to_string(), password.to_string())),
+        _ => None,
+    }
+}
+
+fn status_text(status : u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+// 400 for the statements that can't be compiled, 401 for authentication and 500 for the others
+pub fn error_status(error_type : &ResultErrorType) -> u16 {
+    match error_type {
+        &ResultErrorType::Compile(..) => 400,
+        &ResultErrorType::AuthFailed => 401,
+        _ => 500,
+    }
+}
+
+pub fn errors_to_json(errors : &[ResultError]) -> String {
+    let list : Vec<String> = errors.iter().map(|e| {
+        let type_name = match e.error_type {
+            ResultErrorType::Compile(ref error_type) => format!("{:?}", error_type),
+            ResultErrorType::Exec(ref error_type) => format!("{:?}", error_type),
+            ref error_type => format!("{:?}", error_type),
+        };
+        let mut fields = vec![
+            format!("\"code\":{}", e.code()),
+            format!("\"type\":{}", json_string(&type_name)),
+            format!("\"message\":{}", json_string(&e.error_msg)),
+        ];
+        if let (&Some(column), &Some(ref token)) = (&e.column, &e.token) {
+            fields.push(format!("\"column\":{},\"token\":{}", column, json_string(token)));
+        }
+        format!("{{{}}}", fields.join(","))
+    }).collect();
+    format!("{{\"errors\":[{}]}}", list.join(","))
+}
+
+fn write_response<W : Write>(writer : &mut W, status : u16, extra_headers : &str, body : &str) -> io::Result<()> {
+    let head = format!("HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+        Connection: close\r\n{}\r\n", status, status_text(status), body.len(), extra_headers);
+    try!(writer.write_all(head.as_bytes()));
+    try!(writer.write_all(body.as_bytes()));
+    writer.flush()
+}
+
+fn write_error<W : Write>(writer : &mut W, status : u16, msg : &str) -> io::Result<()> {
+    let body = format!("{{\"errors\":[{{\"message\":{}}}]}}", json_string(msg));
+    write_response(writer, status, "", &body)
+}
+
+// Transfer-Encoding: chunked, a chunk is sent when CHUNK_BYTES are buffered or on flush
+pub struct ChunkedWriter<W : Write> {
+    writer : W,
+    buf : Vec<u8>,
+}
+
+impl<W : Write> ChunkedWriter<W> {
+    pub fn new(writer : W) -> ChunkedWriter<W> {
+        ChunkedWriter{ writer : writer, buf : Vec::new() }
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());  // an empty chunk ends the body
+        }
+        try!(write!(self.writer, "{:x}\r\n", self.buf.len()));
+        try!(self.writer.write_all(&self.buf));
+        try!(self.writer.write_all(b"\r\n"));
+        self.buf.clear();
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        try!(self.write_chunk());
+        try!(self.writer.write_all(b"0\r\n\r\n"));
+        try!(self.writer.flush());
+        Ok(self.writer)
+    }
+}
+
+impl<W : Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, data : &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_BYTES {
+            try!(self.write_chunk());
+        }
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.write_chunk());
+        self.writer.flush()
+    }
+}
+
+pub fn write_result<W : Write>(mut writer : W, result : &CollectedResult) -> io::Result<()> {
+    if !result.errors.is_empty() {
+        let status = error_status(&result.errors[0].error_type);
+        return write_response(&mut writer, status, "", &errors_to_json(&result.errors));
+    }
+    // the warnings such as the truncated rows
+    let warnings : Vec<String> = result.warnings.iter()
+        .map(|w| format!("Warning: 199 blastoise {}\r\n", json_string(w))).collect();
+    if !result.is_query() {
+        let body = match result.summary {
+            Some(summary) => format!("{{\"row_count\":{}}}", summary.row_num),
+            None => "{}".to_string(),
+        };
+        return write_response(&mut writer, 200, &warnings.concat(), &body);
+    }
+    let head = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\
+        Connection: close\r\n{}\r\n", warnings.concat());
+    try!(writer.write_all(head.as_bytes()));
+    let columns = result.columns.iter().map(|c| (c.name.clone(), c.attr_type.clone())).collect();
+    let mut json_writer = try!(JsonResultWriter::new(ChunkedWriter::new(writer), columns, JsonLayout::Envelope));
+    for row in result.rows.iter() {
+        try!(json_writer.write_row(row));
+    }
+    try!(try!(json_writer.finish()).finish());
+    Ok(())
+}
+
+fn serve_client(stream : TcpStream, conn_id : usize, users : Arc<Option<UserTable>>,
+        req_que : TaskQueueRef, processes : ProcessListRef) -> io::Result<()> {
+    let mut writer = try!(stream.try_clone());
+    let mut reader = BufReader::new(stream);
+    let request = match read_request(&mut reader) {
+        Ok(request) => request,
+        Err((status, msg)) => return write_error(&mut writer, status, &msg),
+    };
+    if request.path != "/query" {
+        return write_error(&mut writer, 404, &format!("no {}, use POST /query", request.path));
+    }
+    if request.method != "POST" {
+        return write_error(&mut writer, 405, "use POST /query");
+    }
+    let mut user = None;
+    if let Some(ref users) = *users {
+        let authorized = match request.get_header("authorization").and_then(parse_basic_auth) {
+            Some((name, password)) => {
+                user = Some(name.clone());
+                users.verify(&name, &password)
+            }
+            None => false,
+        };
+        if !authorized {
+            let error = ResultError::new(ResultErrorType::AuthFailed, "invalid user or password".to_string());
+            return write_response(&mut writer, 401, "WWW-Authenticate: Basic realm=\"blastoise\"\r\n",
+                &errors_to_json(&[error]));
+        }
+    }
+    let sql = String::from_utf8_lossy(&request.body).trim().trim_right_matches(';').to_string();
+    let query_id = match processes.enqueue(conn_id, &sql) {
+        Ok(query_id) => query_id,
+        Err(error) => return write_result(writer, &CollectedResult::from_error(error)),
+    };
+    let (sender, receiver) = channel();
+    req_que.push_back(Task::Collect{
+        sql : sql, conn_id : conn_id, user : user, query_id : query_id, result_sender : sender });
+    match receiver.recv() {
+        Ok(result) => write_result(writer, &result),
+        Err(..) => write_error(&mut writer, 500, "the server is shutting down"),
+    }
+}
+
+// None if http_enabled is not set, a thread is started for each request
+pub fn start_http_listener(config : &Config, req_que : TaskQueueRef, processes : ProcessListRef)
+        -> Option<SocketAddr> {
+    if !config.get_bool("http_enabled") {
+        return None;
+    }
+    let host = config.get_str("host");
+    let port = config.get_usize("http_port");
+    let listener = check_ok!(TcpListener::bind(format!("{}:{}", host, port).as_str()));
+    let addr = check_ok!(listener.local_addr());
+    let users = Arc::new(UserTable::from_config(config));
+    spawn(move || {
+        let mut conn_id = 0;
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log_warn!("failed to accept http client; err={:?}", e);
+                    continue;
+                }
+            };
+            conn_id += 1;
+            let (users, req_que, processes) = (users.clone(), req_que.clone(), processes.clone());
+            spawn(move || {
+                if let Err(e) = serve_client(stream, conn_id, users, req_que, processes) {
+                    log_info!("http connection {} closed; err={:?}", conn_id, e);
+                }
+            });
+        }
+    });
+    Some(addr)
+}
@@ -0,0 +1,108 @@
+use std::io;
+use std::io::Write;
+use std::vec::Vec;
+use rustc_serialize::json::encode;
+use ::store::table::AttrType;
+use ::store::tuple::TupleValue;
+use ::utils::float::format_float;
+
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum JsonLayout {
+    Objects,   // [{"id":1,"name":"a"}, ...]
+    Envelope,  // {"columns":[{"name":"id","type":"Int"}, ...],"rows":[[1,"a"], ...]}
+}
+
+pub type ColumnList = Vec<(String, AttrType)>;
+
+pub fn json_string(s : &str) -> String {
+    encode(&s).unwrap()
+}
+
+pub fn tuple_value_to_json(value : &TupleValue) -> String {
+    match value {
+        &TupleValue::Int(..) => value.to_string(),
+        // json has no representation for nan and inf
+        &TupleValue::Float(n) => if n.is_finite() { format_float(n) } else { "null".to_string() },
+        &TupleValue::Char(ref s) => json_string(s),
+        &TupleValue::Null => "null".to_string(),
+    }
+}
+
+pub fn column_to_json(name : &str, attr_type : &AttrType) -> String {
+    let type_str = match attr_type {
+        &AttrType::Int => "\"type\":\"Int\"".to_string(),
+        &AttrType::Float => "\"type\":\"Float\"".to_string(),
+        &AttrType::Char{len} => format!("\"type\":\"Char\",\"len\":{}", len),
+    };
+    format!("{{\"name\":{},{}}}", json_string(name), type_str)
+}
+
+// emit the json incrementally, the output is only valid after finish is called
+pub struct JsonResultWriter<W : Write> {
+    writer : W,
+    columns : ColumnList,
+    layout : JsonLayout,
+    row_num : usize,
+}
+
+impl<W : Write> JsonResultWriter<W> {
+    pub fn new(mut writer : W, columns : ColumnList, layout : JsonLayout)
+            -> io::Result<JsonResultWriter<W>> {
+        match layout {
+            JsonLayout::Objects => try!(writer.write_all(b"[")),
+            JsonLayout::Envelope => {
+                let column_list : Vec<String> = columns.iter()
+                    .map(|&(ref name, ref attr_type)| column_to_json(name, attr_type)).collect();
+                let prefix = format!("{{\"columns\":[{}],\"rows\":[", column_list.join(","));
+                try!(writer.write_all(prefix.as_bytes()));
+            }
+        }
+        Ok(JsonResultWriter{
+            writer : writer,
+            columns : columns,
+            layout : layout,
+            row_num : 0,
+        })
+    }
+
+    pub fn write_row(&mut self, row : &Vec<TupleValue>) -> io::Result<()> {
+        assert_eq!(row.len(), self.columns.len());
+        let mut s = if self.row_num == 0 { String::new() } else { ",".to_string() };
+        match self.layout {
+            JsonLayout::Objects => {
+                let fields : Vec<String> = self.columns.iter().zip(row.iter())
+                    .map(|(&(ref name, _), v)| format!("{}:{}", json_string(name), tuple_value_to_json(v)))
+                    .collect();
+                s.push_str(&format!("{{{}}}", fields.join(",")));
+            }
+            JsonLayout::Envelope => {
+                let values : Vec<String> = row.iter().map(tuple_value_to_json).collect();
+                s.push_str(&format!("[{}]", values.join(",")));
+            }
+        }
+        self.row_num += 1;
+        self.writer.write_all(s.as_bytes())
+    }
+
+    pub fn get_row_num(&self) -> usize {
+        self.row_num
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.layout {
+            JsonLayout::Objects => try!(self.writer.write_all(b"]")),
+            JsonLayout::Envelope => try!(self.writer.write_all(b"]}")),
+        }
+        try!(self.writer.flush());
+        Ok(self.writer)
+    }
+}
+
+pub fn result_to_json(columns : &ColumnList, rows : &Vec<Vec<TupleValue>>, layout : JsonLayout) -> String {
+    let mut writer = JsonResultWriter::new(Vec::new(), columns.clone(), layout).unwrap();
+    for row in rows.iter() {
+        writer.write_row(row).unwrap();
+    }
+    String::from_utf8(writer.finish().unwrap()).unwrap()
+}
@@ -1,86 +1,71 @@
-use std::io::{stdin, stdout};
-use std::io::Write;
-use std::rc::Rc;
-use std::cell::RefCell;
-use ::store::table::TableManager;
-use ::store::tuple::TupleData;
-use ::store::table::AttrType;
-use ::store::tuple::gen_tuple_value;
-use ::utils::config::Config;
-use super::handler::{sql_handler, ResultHandler, process_table_command};
-
-
-#[derive(Debug)]
-pub struct LocalClient;
-
-impl LocalClient {
-    pub fn shell_loop(&mut self) {
-        let config = Config::from_cwd_config();
-        let mut manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
-        let mut sql = String::new();
-        let mut line = String::new();
-        let mut process = Process::new();
-        loop {
-            print!("Blastoise> ");
-            stdout().flush().ok();
-            match stdin().read_line(&mut line) {
-                Ok(n) => {
-                    line.pop();  // remove '\n'
-                    if n == 0 { continue }
-                    if line == "q" { break; }
-                    sql.push_str(&line);
-                    if let Some(';') = line.chars().rev().take(1).next() {
-                        sql.pop();  // remove ';'
-                        if let Ok(out) = process_table_command(&sql, &manager) {
-                            println!("{}", out);
-                        } else {
-                            println!("processing {:?}", sql);
-                            sql_handler(&sql, &mut process, &mut manager);
-                            process = Process::new();
-                        }
-                        sql.clear();
-                    }
-                    line.clear();
-                }
-                Err(error) => println!("error: {}", error),
-            }
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Process {
-    attr_desc : Vec<AttrType>,
-    attr_index : Vec<usize>,
-}
-
-impl Process {
-    pub fn new() -> Process {
-        Process{
-            attr_desc : Vec::new(),
-            attr_index : Vec::new(),
-        }
-    }
-}
-
-impl ResultHandler for Process {
-    fn handle_error(&mut self, err_msg : String) {
-        println!("{}", err_msg);
-    }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
-        match tuple_data {
-            Some(data) => {
-                let value = gen_tuple_value(&self.attr_desc, data);
-                println!("{:?}", value);
-            }
-            None => println!("end"),
-        }
-    }
-    fn set_tuple_info(&mut self, attr_desc : Vec<AttrType>, attr_index : Vec<usize>) {
-        self.attr_desc = attr_desc;
-        self.attr_index = attr_index;
-    }
-    fn handle_non_query_finished(&mut self) {
-        print!("end");
-    }
-}
+use std::io::{stdin, stdout};
+use std::io::Write;
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::store::table::TableManager;
+use ::utils::config::Config;
+use ::utils::log::init_log;
+use super::handler::process_table_command;
+use super::session::{Session, session_handler};
+use super::format_result::{FormatResult, OutputFormat};
+use super::query_log::{QueryLogs, run_logged};
+
+
+#[derive(Debug)]
+pub struct LocalClient {
+    format : OutputFormat,
+    header : bool,  // the line of column names for csv and tsv
+    config : Config,
+}
+
+impl LocalClient {
+    pub fn new(format : OutputFormat, header : bool, config : Config) -> LocalClient {
+        LocalClient{ format : format, header : header, config : config }
+    }
+
+    pub fn shell_loop(&mut self) {
+        let config = self.config.clone();
+        init_log(&config);
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        let mut sql = String::new();
+        let mut line = String::new();
+        // the local client is connection 0
+        let mut session = Session::new(0);
+        session.output_format = self.format;
+        session.null_string = config.get_str_opt("null_string");
+        let mut process = FormatResult::new(stdout(), session.output_format, self.header);
+        process.set_null_string(session.null_string.clone());
+        let mut query_logs = QueryLogs::from_config(&config);
+        loop {
+            print!("Blastoise> ");
+            stdout().flush().ok();
+            match stdin().read_line(&mut line) {
+                Ok(n) => {
+                    line.pop();  // remove '\n'
+                    if n == 0 { continue }
+                    if line == "q" { break; }
+                    sql.push_str(&line);
+                    if let Some(';') = line.chars().rev().take(1).next() {
+                        sql.pop();  // remove ';'
+                        if let Ok(out) = process_table_command(&sql, &manager) {
+                            println!("{}", out);
+                        } else {
+                            if session.output_format == OutputFormat::Table {
+                                println!("processing {:?}", sql);
+                            }
+                            run_logged(&mut query_logs, session.conn_id, &sql, &mut process, &manager, |result_handler| {
+                                session_handler(&sql, &mut session, result_handler, &manager);
+                            });
+                            process = FormatResult::new(stdout(), session.output_format, self.header);
+                            process.set_null_string(session.null_string.clone());
+                        }
+                        sql.clear();
+                    }
+                    line.clear();
+                }
+                Err(error) => println!("error: {}", error),
+            }
+        }
+        manager.borrow_mut().close();
+    }
+}
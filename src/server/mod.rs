@@ -1,10 +1,38 @@
-#[allow(dead_code)]
-pub mod handler;
-#[allow(dead_code)]
-pub mod local_client;
-#[allow(dead_code)]
-pub mod server;
-#[allow(dead_code)]
-pub mod queue;
-#[allow(dead_code)]
-pub mod buf;
+#[allow(dead_code)]
+pub mod handler;
+#[allow(dead_code)]
+pub mod local_client;
+#[allow(dead_code)]
+pub mod server;
+#[allow(dead_code)]
+pub mod queue;
+#[allow(dead_code)]
+pub mod buf;
+#[allow(dead_code)]
+pub mod json_result;
+#[allow(dead_code)]
+pub mod result_error;
+#[allow(dead_code)]
+pub mod auth;
+#[allow(dead_code)]
+pub mod stats;
+#[allow(dead_code)]
+pub mod format_result;
+#[allow(dead_code)]
+pub mod query_log;
+#[allow(dead_code)]
+pub mod collect;
+#[allow(dead_code)]
+pub mod mysql;
+#[allow(dead_code)]
+pub mod http;
+#[allow(dead_code)]
+pub mod socket;
+#[allow(dead_code)]
+pub mod session;
+#[allow(dead_code)]
+pub mod cursor;
+#[allow(dead_code)]
+pub mod database;
+#[allow(dead_code)]
+pub mod processlist;
@@ -0,0 +1,395 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, SocketAddr};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ::store::table::AttrType;
+use ::store::tuple::TupleValue;
+use ::utils::config::Config;
+use ::utils::sha256::sha256;
+use super::auth::UserTable;
+use super::collect::CollectedResult;
+use super::handler::ColumnInfo;
+use super::result_error::{ResultErrorType, render_errors};
+use super::processlist::{ProcessList, ProcessListRef};
+use super::server::{Task, TaskQueueRef};
+
+
+// A minimal subset of the mysql client/server protocol: the handshake, COM_QUERY with the
+// text result sets, COM_PING, COM_INIT_DB and COM_QUIT. The password is sent in clear text
+// (mysql_clear_password) since the users file only keeps our own hashes.
+const SERVER_VERSION : &'static str = "5.7.0-blastoise";
+const AUTH_PLUGIN : &'static str = "mysql_clear_password";
+
+const CLIENT_LONG_PASSWORD : u32 = 0x1;
+const CLIENT_PROTOCOL_41 : u32 = 0x200;
+const CLIENT_TRANSACTIONS : u32 = 0x2000;
+const CLIENT_SECURE_CONNECTION : u32 = 0x8000;
+const CLIENT_PLUGIN_AUTH : u32 = 0x80000;
+const SERVER_CAPABILITIES : u32 = CLIENT_LONG_PASSWORD | CLIENT_PROTOCOL_41 | CLIENT_TRANSACTIONS
+    | CLIENT_SECURE_CONNECTION | CLIENT_PLUGIN_AUTH;
+const SERVER_STATUS_AUTOCOMMIT : u16 = 0x2;
+
+const CHARSET_UTF8 : u8 = 33;
+const CHARSET_BINARY : u8 = 63;
+
+pub const COM_QUIT : u8 = 0x01;
+pub const COM_INIT_DB : u8 = 0x02;
+pub const COM_QUERY : u8 = 0x03;
+pub const COM_PING : u8 = 0x0e;
+
+pub const TYPE_LONG : u8 = 0x03;
+pub const TYPE_FLOAT : u8 = 0x04;
+pub const TYPE_STRING : u8 = 0xfe;
+const NOT_NULL_FLAG : u16 = 0x1;
+const NUM_FLAG : u16 = 0x8000;
+
+const MAX_PAYLOAD_LEN : usize = 0xffffff;
+
+
+// 3 bytes of payload length and 1 byte of sequence id before each payload,
+// used by both sides so the tests can act as a client
+pub struct PacketStream<S : Read + Write> {
+    stream : S,
+    seq : u8,
+}
+
+impl<S : Read + Write> PacketStream<S> {
+    pub fn new(stream : S) -> PacketStream<S> {
+        PacketStream{ stream : stream, seq : 0 }
+    }
+
+    pub fn read_packet(&mut self) -> io::Result<Vec<u8>> {
+        let mut header = [0u8; 4];
+        try!(self.stream.read_exact(&mut header));
+        let len = header[0] as usize | (header[1] as usize) << 8 | (header[2] as usize) << 16;
+        if len == MAX_PAYLOAD_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "packet over 16MB is not supported"));
+        }
+        // the reply continues the sequence
+        self.seq = header[3].wrapping_add(1);
+        let mut payload = vec![0u8; len];
+        try!(self.stream.read_exact(&mut payload));
+        Ok(payload)
+    }
+
+    pub fn write_packet(&mut self, payload : &[u8]) -> io::Result<()> {
+        if payload.len() >= MAX_PAYLOAD_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "packet over 16MB is not supported"));
+        }
+        let len = payload.len();
+        let mut packet = vec![len as u8, (len >> 8) as u8, (len >> 16) as u8, self.seq];
+        packet.extend_from_slice(payload);
+        self.seq = self.seq.wrapping_add(1);
+        self.stream.write_all(&packet)
+    }
+
+    // a client starts each command from 0
+    pub fn reset_seq(&mut self) {
+        self.seq = 0;
+    }
+}
+
+fn push_u16(buf : &mut Vec<u8>, n : u16) {
+    buf.push(n as u8);
+    buf.push((n >> 8) as u8);
+}
+
+fn push_u32(buf : &mut Vec<u8>, n : u32) {
+    push_u16(buf, n as u16);
+    push_u16(buf, (n >> 16) as u16);
+}
+
+pub fn read_u16(buf : &[u8], pos : usize) -> u16 {
+    buf[pos] as u16 | (buf[pos + 1] as u16) << 8
+}
+
+pub fn read_u32(buf : &[u8], pos : usize) -> u32 {
+    read_u16(buf, pos) as u32 | (read_u16(buf, pos + 2) as u32) << 16
+}
+
+pub fn lenenc_int(n : u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if n < 251 {
+        buf.push(n as u8);
+    } else if n < 1 << 16 {
+        buf.push(0xfc);
+        push_u16(&mut buf, n as u16);
+    } else if n < 1 << 24 {
+        buf.push(0xfd);
+        buf.extend_from_slice(&[n as u8, (n >> 8) as u8, (n >> 16) as u8]);
+    } else {
+        buf.push(0xfe);
+        push_u32(&mut buf, n as u32);
+        push_u32(&mut buf, (n >> 32) as u32);
+    }
+    buf
+}
+
+pub fn lenenc_str(s : &[u8]) -> Vec<u8> {
+    let mut buf = lenenc_int(s.len() as u64);
+    buf.extend_from_slice(s);
+    buf
+}
+
+fn read_null_str(buf : &[u8], pos : &mut usize) -> Option<Vec<u8>> {
+    let end = match buf[*pos..].iter().position(|b| *b == 0) {
+        Some(i) => *pos + i,
+        None => return None,
+    };
+    let s = buf[*pos..end].to_vec();
+    *pos = end + 1;
+    Some(s)
+}
+
+// 20 printable bytes, no 0 in them
+fn gen_scramble(conn_id : u32) -> Vec<u8> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let seed = format!("{}{}{}", now.as_secs(), now.subsec_nanos(), conn_id);
+    sha256(seed.as_bytes())[..20].iter().map(|b| b % 94 + 33).collect()
+}
+
+pub fn gen_handshake(conn_id : u32, scramble : &[u8]) -> Vec<u8> {
+    assert_eq!(scramble.len(), 20);
+    let mut p = vec![10u8];  // protocol version
+    p.extend_from_slice(SERVER_VERSION.as_bytes());
+    p.push(0);
+    push_u32(&mut p, conn_id);
+    p.extend_from_slice(&scramble[..8]);
+    p.push(0);
+    push_u16(&mut p, SERVER_CAPABILITIES as u16);
+    p.push(CHARSET_UTF8);
+    push_u16(&mut p, SERVER_STATUS_AUTOCOMMIT);
+    push_u16(&mut p, (SERVER_CAPABILITIES >> 16) as u16);
+    p.push(scramble.len() as u8 + 1);
+    p.extend_from_slice(&[0u8; 10]);
+    p.extend_from_slice(&scramble[8..]);
+    p.push(0);
+    p.extend_from_slice(AUTH_PLUGIN.as_bytes());
+    p.push(0);
+    p
+}
+
+// the user and the auth response of HandshakeResponse41
+pub fn parse_handshake_response(payload : &[u8]) -> Option<(String, Vec<u8>)> {
+    // capabilities, max packet size, charset and 23 bytes reserved
+    if payload.len() < 32 || read_u32(payload, 0) & CLIENT_PROTOCOL_41 == 0 {
+        return None;
+    }
+    let capabilities = read_u32(payload, 0);
+    let mut pos = 32;
+    let user = match read_null_str(payload, &mut pos) {
+        Some(user) => String::from_utf8_lossy(&user).into_owned(),
+        None => return None,
+    };
+    let auth_response = if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+        let len = match payload.get(pos) {
+            Some(len) => *len as usize,
+            None => return None,
+        };
+        if pos + 1 + len > payload.len() {
+            return None;
+        }
+        payload[pos + 1 .. pos + 1 + len].to_vec()
+    } else {
+        match read_null_str(payload, &mut pos) {
+            Some(auth_response) => auth_response,
+            None => return None,
+        }
+    };
+    Some((user, auth_response))
+}
+
+pub fn gen_ok(affected_rows : u64) -> Vec<u8> {
+    let mut p = vec![0u8];
+    p.extend(lenenc_int(affected_rows));
+    p.extend(lenenc_int(0));  // last insert id
+    push_u16(&mut p, SERVER_STATUS_AUTOCOMMIT);
+    push_u16(&mut p, 0);  // warnings
+    p
+}
+
+pub fn gen_err(code : u16, sql_state : &str, msg : &str) -> Vec<u8> {
+    let mut p = vec![0xffu8];
+    push_u16(&mut p, code);
+    p.push(b'#');
+    p.extend_from_slice(sql_state.as_bytes());
+    p.extend_from_slice(msg.as_bytes());
+    p
+}
+
+pub fn gen_eof(warnings : u16) -> Vec<u8> {
+    let mut p = vec![0xfeu8];
+    push_u16(&mut p, warnings);
+    push_u16(&mut p, SERVER_STATUS_AUTOCOMMIT);
+    p
+}
+
+pub fn gen_column_definition(column : &ColumnInfo) -> Vec<u8> {
+    let (column_type, charset, len, decimals) = match column.attr_type {
+        AttrType::Int => (TYPE_LONG, CHARSET_BINARY, 11, 0),
+        AttrType::Float => (TYPE_FLOAT, CHARSET_BINARY, 12, 0x1f),
+        AttrType::Char{len} => (TYPE_STRING, CHARSET_UTF8, len as u32, 0),
+    };
+    let mut flags = if column.nullable { 0 } else { NOT_NULL_FLAG };
+    if column_type != TYPE_STRING {
+        flags |= NUM_FLAG;
+    }
+    let mut p = lenenc_str(b"def");
+    p.extend(lenenc_str(b""));  // schema
+    p.extend(lenenc_str(b""));  // table
+    p.extend(lenenc_str(b""));  // org_table
+    p.extend(lenenc_str(column.name.as_bytes()));
+    p.extend(lenenc_str(column.name.as_bytes()));
+    p.push(0x0c);  // length of the fixed fields
+    push_u16(&mut p, charset as u16);
+    push_u32(&mut p, len);
+    p.push(column_type);
+    push_u16(&mut p, flags);
+    p.push(decimals);
+    push_u16(&mut p, 0);
+    p
+}
+
+pub fn gen_text_row(row : &Vec<TupleValue>) -> Vec<u8> {
+    let mut p = Vec::new();
+    for value in row.iter() {
+        match value {
+            &TupleValue::Null => p.push(0xfb),
+            v => p.extend(lenenc_str(v.to_string().as_bytes())),
+        }
+    }
+    p
+}
+
+// the mysql error code and sql state for our error
+pub fn to_mysql_error(error_type : &ResultErrorType) -> (u16, &'static str) {
+    use ::parser::compile_error::CompileErrorType;
+    use ::exec::error::ExecErrorType;
+    match error_type {
+        &ResultErrorType::Compile(CompileErrorType::SemTableNotExist) => (1146, "42S02"),
+        &ResultErrorType::Compile(CompileErrorType::SemTableExist) => (1050, "42S01"),
+        &ResultErrorType::Compile(CompileErrorType::SemInvalidAttribute) => (1054, "42S22"),
+        &ResultErrorType::Compile(CompileErrorType::SemUnimplemented) => (1235, "42000"),
+        // lexer and parser
+        &ResultErrorType::Compile(..) if error_type.code() < 1200 => (1064, "42000"),
+        &ResultErrorType::Exec(ExecErrorType::PrimaryKeyExist) => (1062, "23000"),
+        &ResultErrorType::Exec(ExecErrorType::QueryCancelled) => (1317, "70100"),
+        &ResultErrorType::Exec(ExecErrorType::TableInUse) => (1205, "HY000"),
+        &ResultErrorType::Exec(ExecErrorType::StatementTimeout) => (3024, "HY000"),
+        &ResultErrorType::TooManyConnections => (1040, "08004"),
+        &ResultErrorType::AuthFailed => (1045, "28000"),
+        &ResultErrorType::PermissionDenied => (1142, "42000"),
+        _ => (1105, "HY000"),
+    }
+}
+
+fn write_result<S : Read + Write>(packets : &mut PacketStream<S>, result : &CollectedResult) -> io::Result<()> {
+    if !result.errors.is_empty() {
+        let (code, sql_state) = to_mysql_error(&result.errors[0].error_type);
+        return packets.write_packet(&gen_err(code, sql_state, &render_errors(&result.errors)));
+    }
+    if !result.is_query() {
+        let affected_num = result.summary.map_or(0, |s| s.row_num);
+        return packets.write_packet(&gen_ok(affected_num as u64));
+    }
+    try!(packets.write_packet(&lenenc_int(result.columns.len() as u64)));
+    for column in result.columns.iter() {
+        try!(packets.write_packet(&gen_column_definition(column)));
+    }
+    try!(packets.write_packet(&gen_eof(0)));
+    for row in result.rows.iter() {
+        try!(packets.write_packet(&gen_text_row(row)));
+    }
+    packets.write_packet(&gen_eof(result.warnings.len() as u16))
+}
+
+fn run_query(sql : String, conn_id : u32, user : &Option<String>, req_que : &TaskQueueRef,
+        processes : &ProcessList) -> Option<CollectedResult> {
+    let conn_id = conn_id as usize;
+    let query_id = match processes.enqueue(conn_id, &sql) {
+        Ok(query_id) => query_id,
+        Err(error) => return Some(CollectedResult::from_error(error)),
+    };
+    let (sender, receiver) = channel();
+    req_que.push_back(Task::Collect{
+        sql : sql, conn_id : conn_id, user : user.clone(), query_id : query_id, result_sender : sender });
+    // None if the task is dropped without running
+    receiver.recv().ok()
+}
+
+fn serve_client(stream : TcpStream, conn_id : u32, users : Arc<Option<UserTable>>,
+        req_que : TaskQueueRef, processes : ProcessListRef) -> io::Result<()> {
+    let mut packets = PacketStream::new(stream);
+    try!(packets.write_packet(&gen_handshake(conn_id, &gen_scramble(conn_id))));
+    let response = try!(packets.read_packet());
+    let (user, mut password) = match parse_handshake_response(&response) {
+        Some(user_password) => user_password,
+        None => return packets.write_packet(&gen_err(1043, "08S01", "bad handshake")),
+    };
+    while password.last() == Some(&0) {
+        password.pop();  // mysql_clear_password ends it with 0
+    }
+    if let Some(ref users) = *users {
+        if !users.verify(&user, &String::from_utf8_lossy(&password)) {
+            let msg = format!("access denied for user '{}'", user);
+            return packets.write_packet(&gen_err(1045, "28000", &msg));
+        }
+    }
+    try!(packets.write_packet(&gen_ok(0)));
+    // the privileges are checked for the users of users_file
+    let user = if users.is_some() { Some(user) } else { None };
+    loop {
+        let command = try!(packets.read_packet());
+        match command.first() {
+            None | Some(&COM_QUIT) => return Ok(()),
+            Some(&COM_PING) | Some(&COM_INIT_DB) => try!(packets.write_packet(&gen_ok(0))),
+            Some(&COM_QUERY) => {
+                let sql = String::from_utf8_lossy(&command[1..]).trim().trim_right_matches(';').to_string();
+                match run_query(sql, conn_id, &user, &req_que, &processes) {
+                    Some(result) => try!(write_result(&mut packets, &result)),
+                    None => return Ok(()),  // shutting down
+                }
+            }
+            Some(command) => {
+                let msg = format!("unsupported command {}", command);
+                try!(packets.write_packet(&gen_err(1047, "08S01", &msg)));
+            }
+        }
+    }
+}
+
+// None if mysql_enabled is not set, a thread is started for each client
+pub fn start_mysql_listener(config : &Config, req_que : TaskQueueRef, processes : ProcessListRef)
+        -> Option<SocketAddr> {
+    if !config.get_bool("mysql_enabled") {
+        return None;
+    }
+    let host = config.get_str("host");
+    let port = config.get_usize("mysql_port");
+    let listener = check_ok!(TcpListener::bind(format!("{}:{}", host, port).as_str()));
+    let addr = check_ok!(listener.local_addr());
+    let users = Arc::new(UserTable::from_config(config));
+    spawn(move || {
+        let mut conn_id = 0;
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log_warn!("failed to accept mysql client; err={:?}", e);
+                    continue;
+                }
+            };
+            conn_id += 1;
+            let (users, req_que, processes) = (users.clone(), req_que.clone(), processes.clone());
+            spawn(move || {
+                if let Err(e) = serve_client(stream, conn_id, users, req_que, processes) {
+                    log_info!("mysql connection {} closed; err={:?}", conn_id, e);
+                }
+            });
+        }
+    });
+    Some(addr)
+}
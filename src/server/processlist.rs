@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use ::store::table::{TableManagerRef, AttrType};
+use ::store::tuple::TupleValue;
+use ::exec::error::ExecErrorType;
+use ::utils::config::Config;
+use super::handler::{ResultHandler, ColumnInfo, Summary, StatementKind};
+use super::result_error::{ResultError, ResultErrorType};
+
+
+// the statements in `show processlist` are cut to it
+const MAX_STATEMENT_LEN : usize = 256;
+
+pub type ProcessListRef = Arc<ProcessList>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryState {
+    Queued,  // sent to the worker, which is running the statements before it
+    Executing,
+}
+
+impl QueryState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            &QueryState::Queued => "queued",
+            &QueryState::Executing => "executing",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunningQuery {
+    pub query_id : usize,
+    pub conn_id : usize,
+    pub statement : String,
+    pub state : QueryState,
+    pub start : Instant,  // of the state
+    killed : Arc<AtomicBool>,  // checked by the guard of the statement
+}
+
+// The statements queued for and running on the worker, shared with the event loop which serves
+// `show processlist` and `kill` while the worker is busy, and with the threads of the other protocols.
+#[derive(Debug)]
+pub struct ProcessList {
+    next_query_id : AtomicUsize,
+    queries : Mutex<BTreeMap<usize, RunningQuery>>,
+    max_queued : usize,  // 0 for no limit
+    queue_timeout_ms : usize,  // 0 for no limit
+}
+
+impl ProcessList {
+    pub fn make_ref() -> ProcessListRef {
+        Arc::new(ProcessList::new())
+    }
+    pub fn from_config(config : &Config) -> ProcessListRef {
+        Arc::new(ProcessList::with_limits(
+            config.get_usize("max_queued_statements"), config.get_usize("queue_timeout_ms")))
+    }
+    pub fn new() -> ProcessList {
+        ProcessList::with_limits(0, 0)
+    }
+    pub fn with_limits(max_queued : usize, queue_timeout_ms : usize) -> ProcessList {
+        ProcessList{
+            next_query_id : AtomicUsize::new(1),
+            queries : Mutex::new(BTreeMap::new()),
+            max_queued : max_queued,
+            queue_timeout_ms : queue_timeout_ms,
+        }
+    }
+    fn insert(&self, conn_id : usize, statement : &String, state : QueryState,
+            queries : &mut BTreeMap<usize, RunningQuery>) -> (usize, Arc<AtomicBool>) {
+        let query_id = self.next_query_id.fetch_add(1, Ordering::SeqCst);
+        let killed = Arc::new(AtomicBool::new(false));
+        queries.insert(query_id, RunningQuery{
+            query_id : query_id,
+            conn_id : conn_id,
+            statement : statement.clone(),
+            state : state,
+            start : Instant::now(),
+            killed : killed.clone(),
+        });
+        (query_id, killed)
+    }
+    // a new id for the statement, and the flag set when it is killed
+    pub fn start(&self, conn_id : usize, statement : &String) -> (usize, Arc<AtomicBool>) {
+        let mut queries = lock_unwrap!(self.queries.lock());
+        self.insert(conn_id, statement, QueryState::Executing, &mut queries)
+    }
+    // Before the statement is sent to the worker, it's refused when max_queued statements
+    // are waiting already. The worker runs them in the order they are queued.
+    pub fn enqueue(&self, conn_id : usize, statement : &String) -> Result<usize, ResultError> {
+        let mut queries = lock_unwrap!(self.queries.lock());
+        let queued = queries.values().filter(|q| q.state == QueryState::Queued).count();
+        if self.max_queued > 0 && queued >= self.max_queued {
+            return Err(ResultError::new(ResultErrorType::QueueFull,
+                format!("{} statements are waiting for the worker, the most of max_queued_statements",
+                    queued)));
+        }
+        Ok(self.insert(conn_id, statement, QueryState::Queued, &mut queries).0)
+    }
+    // Taken by the worker, the statement is executing from now on. It's removed instead
+    // if it was killed while queued or has waited over queue_timeout_ms.
+    pub fn dequeue(&self, query_id : usize) -> Result<Arc<AtomicBool>, ResultError> {
+        let mut queries = lock_unwrap!(self.queries.lock());
+        let error = {
+            let query = queries.get_mut(&query_id).unwrap();
+            assert_eq!(query.state, QueryState::Queued);
+            let waited_ms = to_ms(query.start.elapsed()) as usize;
+            if query.killed.load(Ordering::SeqCst) {
+                ResultError::new(ResultErrorType::Exec(ExecErrorType::QueryCancelled),
+                    "query killed".to_string())
+            } else if self.queue_timeout_ms > 0 && waited_ms > self.queue_timeout_ms {
+                ResultError::new(ResultErrorType::QueueTimeout, format!("waited {} ms for the worker, \
+                    over queue_timeout_ms {}", waited_ms, self.queue_timeout_ms))
+            } else {
+                query.state = QueryState::Executing;
+                query.start = Instant::now();
+                return Ok(query.killed.clone());
+            }
+        };
+        queries.remove(&query_id);
+        Err(error)
+    }
+    pub fn finish(&self, query_id : usize) {
+        lock_unwrap!(self.queries.lock()).remove(&query_id);
+    }
+    // false if the query has finished or never existed
+    pub fn kill(&self, query_id : usize) -> bool {
+        match lock_unwrap!(self.queries.lock()).get(&query_id) {
+            Some(query) => {
+                query.killed.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+    // in the order of the query ids
+    pub fn get_snapshot(&self) -> Vec<RunningQuery> {
+        lock_unwrap!(self.queries.lock()).values().cloned().collect()
+    }
+}
+
+// run the statement in the process list, it stops at the next tuple after it's killed
+pub fn run_tracked<F>(processes : &ProcessList, conn_id : usize, input : &String,
+        manager : &TableManagerRef, run : F) where F : FnOnce() {
+    let (query_id, killed) = processes.start(conn_id, input);
+    run_killable(processes, query_id, killed, manager, run);
+}
+
+// the same as run_tracked for a statement queued already, the error is returned if it can't run
+pub fn run_dequeued<F>(processes : &ProcessList, query_id : usize,
+        manager : &TableManagerRef, run : F) -> Result<(), ResultError> where F : FnOnce() {
+    let killed = try!(processes.dequeue(query_id));
+    run_killable(processes, query_id, killed, manager, run);
+    Ok(())
+}
+
+pub fn run_killable<F>(processes : &ProcessList, query_id : usize, killed : Arc<AtomicBool>,
+        manager : &TableManagerRef, run : F) where F : FnOnce() {
+    manager.borrow_mut().set_kill_flag(Some(killed));
+    run();
+    manager.borrow_mut().set_kill_flag(None);
+    processes.finish(query_id);
+}
+
+pub fn is_process_command(input : &String) -> bool {
+    input == "show processlist" || parse_kill_command(input).is_some()
+}
+
+// `kill 3`
+fn parse_kill_command(input : &String) -> Option<usize> {
+    let mut words = input.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("kill"), Some(id), None) => id.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn process_processlist_command(input : &String, processes : &ProcessList,
+        result_handler : &mut ResultHandler) -> Result<(), ()> {
+    if let Some(query_id) = parse_kill_command(input) {
+        if processes.kill(query_id) {
+            result_handler.handle_non_query_finished();
+        } else {
+            result_handler.handle_error(vec![ResultError::new(ResultErrorType::QueryNotExist,
+                format!("query {} is not running", query_id))]);
+        }
+        return Ok(());
+    }
+    if input != "show processlist" {
+        return Err(());
+    }
+    let queries = processes.get_snapshot();
+    let columns = vec![
+        ("conn_id", AttrType::Int),
+        ("query_id", AttrType::Int),
+        ("statement", AttrType::Char{ len : MAX_STATEMENT_LEN }),
+        ("state", AttrType::Char{ len : 9 }),
+        ("elapsed_ms", AttrType::Int),  // in the state
+    ];
+    let columns : Vec<ColumnInfo> = columns.into_iter().map(|(name, attr_type)| ColumnInfo{
+        name : name.to_string(),
+        attr_type : attr_type,
+        nullable : false,
+    }).collect();
+    result_handler.handle_metadata(&columns);
+    let row_num = queries.len();
+    for query in queries.into_iter() {
+        result_handler.handle_row(Some(vec![
+            TupleValue::Int(query.conn_id as i32),
+            TupleValue::Int(query.query_id as i32),
+            TupleValue::Char(query.statement.chars().take(MAX_STATEMENT_LEN).collect()),
+            TupleValue::Char(query.state.name().to_string()),
+            TupleValue::Int(to_ms(query.start.elapsed()) as i32),
+        ]));
+    }
+    result_handler.handle_row(None);
+    result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+    Ok(())
+}
+
+fn to_ms(duration : Duration) -> u64 {
+    duration.as_secs() * 1000 + (duration.subsec_nanos() / 1000000) as u64
+}
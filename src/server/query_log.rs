@@ -0,0 +1,187 @@
+use std::io::{BufWriter, Write};
+use std::fs::{File, OpenOptions, rename};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use rustc_serialize::json::encode;
+use ::store::tuple::TupleValue;
+use ::store::table::TableManagerRef;
+use ::utils::config::Config;
+use super::handler::{ResultHandler, ColumnInfo, Summary};
+use super::result_error::ResultError;
+
+
+// one json object for each line of the log
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct QueryLogEntry {
+    pub timestamp_ms : u64,
+    pub conn_id : usize,
+    pub statement : String,  // cut to max_statement_len chars
+    pub rows : usize,  // returned by select, or affected by the others
+    pub duration_us : u64,
+    pub error_code : Option<u32>,
+    pub plan : Option<String>,  // only in the slow query log
+}
+
+// The lines are buffered and flushed at most every flush_interval,
+// the file is renamed to `path.1` when it grows over max_bytes.
+#[derive(Debug)]
+pub struct QueryLog {
+    path : String,
+    writer : BufWriter<File>,
+    size : usize,
+    max_statement_len : usize,
+    max_bytes : usize,  // 0 for no rotation
+    flush_interval : Duration,
+    last_flush : Instant,
+}
+
+impl QueryLog {
+    // None if the path is not configured,
+    // the query log and the slow query log share the other options
+    pub fn from_config(config : &Config, path_key : &str) -> Option<QueryLog> {
+        let path = config.get_str(path_key);
+        if path.is_empty() {
+            return None;
+        }
+        Some(QueryLog::new(
+            path,
+            config.get_usize("query_log_max_statement_len"),
+            config.get_usize("query_log_max_bytes"),
+            Duration::from_millis(config.get_usize("query_log_flush_interval_ms") as u64)))
+    }
+
+    pub fn new(path : String, max_statement_len : usize, max_bytes : usize, flush_interval : Duration) -> QueryLog {
+        let (file, size) = open_log_file(&path);
+        QueryLog{
+            path : path,
+            writer : BufWriter::new(file),
+            size : size,
+            max_statement_len : max_statement_len,
+            max_bytes : max_bytes,
+            flush_interval : flush_interval,
+            last_flush : Instant::now(),
+        }
+    }
+
+    pub fn append(&mut self, entry : &QueryLogEntry) {
+        let mut entry = entry.clone();
+        if entry.statement.chars().count() > self.max_statement_len {
+            entry.statement = entry.statement.chars().take(self.max_statement_len).collect();
+        }
+        let mut line = encode(&entry).unwrap();
+        line.push('\n');
+        if self.max_bytes > 0 && self.size > 0 && self.size + line.len() > self.max_bytes {
+            self.rotate();
+        }
+        check_ok!(self.writer.write_all(line.as_bytes()));
+        self.size += line.len();
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        check_ok!(self.writer.flush());
+        self.last_flush = Instant::now();
+    }
+
+    fn rotate(&mut self) {
+        self.flush();
+        check_ok!(rename(&self.path, format!("{}.1", self.path)));
+        let (file, size) = open_log_file(&self.path);
+        self.writer = BufWriter::new(file);
+        self.size = size;
+    }
+}
+
+fn open_log_file(path : &String) -> (File, usize) {
+    let file = match OpenOptions::new().append(true).create(true).open(path) {
+        Ok(file) => file,
+        Err(err) => panic!("can't open query log {}: {}", path, err),
+    };
+    let size = check_ok!(file.metadata()).len() as usize;
+    (file, size)
+}
+
+#[derive(Debug)]
+pub struct QueryLogs {
+    pub query_log : Option<QueryLog>,
+    // the statements slower than slow_query_ms, with the plans
+    pub slow_log : Option<QueryLog>,
+}
+
+impl QueryLogs {
+    pub fn from_config(config : &Config) -> QueryLogs {
+        QueryLogs{
+            query_log : QueryLog::from_config(config, "query_log_path"),
+            slow_log : QueryLog::from_config(config, "slow_query_log_path"),
+        }
+    }
+}
+
+// run the statement with the handler, and log it if the logs are enabled
+pub fn run_logged<F>(logs : &mut QueryLogs, conn_id : usize, input : &String,
+        result_handler : &mut ResultHandler, manager : &TableManagerRef, run : F)
+        where F : FnOnce(&mut ResultHandler) {
+    if logs.query_log.is_none() && logs.slow_log.is_none() {
+        return run(result_handler);
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let start = Instant::now();
+    let (rows, error_code) = {
+        let mut logged = LoggedResult{ result_handler : result_handler, rows : 0, error_code : None };
+        run(&mut logged);
+        (logged.rows, logged.error_code)
+    };
+    let duration = start.elapsed();
+    let mut entry = QueryLogEntry{
+        timestamp_ms : timestamp.as_secs() * 1000 + (timestamp.subsec_nanos() / 1000000) as u64,
+        conn_id : conn_id,
+        statement : input.clone(),
+        rows : rows,
+        duration_us : duration.as_secs() * 1000000 + (duration.subsec_nanos() / 1000) as u64,
+        error_code : error_code,
+        plan : None,
+    };
+    if let Some(ref mut log) = logs.query_log {
+        log.append(&entry);
+    }
+    // only the statements running a plan can be slow
+    let slow_plan = manager.borrow_mut().take_slow_plan();
+    if let (&mut Some(ref mut log), Some(plan)) = (&mut logs.slow_log, slow_plan) {
+        entry.plan = Some(plan);
+        log.append(&entry);
+    }
+}
+
+// passes everything to the inner handler and counts the rows
+struct LoggedResult<'a> {
+    result_handler : &'a mut ResultHandler,
+    rows : usize,
+    error_code : Option<u32>,
+}
+
+impl<'a> ResultHandler for LoggedResult<'a> {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        self.error_code = Some(errors[0].code());
+        self.result_handler.handle_error(errors);
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        if row.is_some() {
+            self.rows += 1;
+        }
+        self.result_handler.handle_row(row);
+    }
+    fn handle_non_query_finished(&mut self) {
+        self.result_handler.handle_non_query_finished();
+    }
+    fn handle_complete(&mut self, summary : Summary) {
+        self.rows = summary.row_num;
+        self.result_handler.handle_complete(summary);
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.result_handler.handle_metadata(columns);
+    }
+    fn handle_warning(&mut self, msg : String) {
+        self.result_handler.handle_warning(msg);
+    }
+}
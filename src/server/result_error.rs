@@ -0,0 +1,184 @@
+use std::vec::Vec;
+use ::parser::compile_error::{CompileErrorType, ErrorList};
+use ::parser::lexer::TokenType;
+use ::exec::error::{ExecError, ExecErrorType};
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultErrorType {
+    Compile(CompileErrorType),
+    Exec(ExecErrorType),
+    TooManyConnections,
+    PreparedStatementNotExist,
+    PreparedStatementExist,
+    AuthFailed,
+    BatchStatementSkipped,  // after a failed statement of a stop_on_error batch
+    UnknownVariable,
+    InvalidVariableValue,
+    CursorNotExist,
+    CursorExist,
+    CursorNotSelect,
+    QueryNotExist,  // killed after it finished, or never started
+    QueueFull,  // max_queued_statements are waiting for the worker
+    QueueTimeout,  // waited over queue_timeout_ms for the worker
+    PermissionDenied,  // the user is not granted the privilege on a table, or is not the admin
+}
+
+// The codes are part of the wire protocol, never reuse or change an assigned code.
+// Append new error types to the end of their group.
+//
+//   1000 - 1099   lexer
+//   1100 - 1199   parser
+//   1200 - 1299   semantic check, 1299 for unimplemented statements
+//   2000 - 2099   execution
+//   2100 - 2199   server
+//
+// All the codes are below 2573 (b"\r\n" in Little-Endian), see doc/README.md.
+impl ResultErrorType {
+    pub fn code(&self) -> u32 {
+        match self {
+            &ResultErrorType::Compile(ref error_type) => match *error_type {
+                CompileErrorType::LexerInvalidEscapeChar => 1000,
+                CompileErrorType::LexerUnexpectedChar => 1001,
+                CompileErrorType::LexerInCompleteString => 1002,
+                CompileErrorType::LexerInvalidFloat => 1003,
+                CompileErrorType::LexerInvalidAscii => 1004,
+                CompileErrorType::LexerInvalidInteger => 1005,
+
+                CompileErrorType::ParserNoMoreToken => 1100,
+                CompileErrorType::ParserUnExpectedTokenType => 1101,
+                CompileErrorType::ParserTableAttrNotExist => 1102,
+                CompileErrorType::ParserNoTable => 1103,
+                CompileErrorType::ParserLackOfSpecifyingTable => 1104,
+                CompileErrorType::ParserCanNotParseLeftToken => 1105,
+                CompileErrorType::ParserInvalidCopyOption => 1106,
+                CompileErrorType::ParserUnboundPlaceholder => 1107,
+                CompileErrorType::ParserTooDeepNesting => 1108,
+
+                CompileErrorType::SemTableNotExist => 1200,
+                CompileErrorType::SemTableExist => 1201,
+                CompileErrorType::SemDuplicateAttr => 1202,
+                CompileErrorType::SemNullablePrimary => 1203,
+                CompileErrorType::SemMultiplePrimary => 1204,
+                CompileErrorType::SemNoPrimary => 1205,
+                CompileErrorType::SemInvalidValueType => 1206,
+                CompileErrorType::SemInvalidAggreFuncName => 1207,
+                CompileErrorType::SemInvalidAttribute => 1208,
+                CompileErrorType::SemShouldUseGroupByAttribute => 1209,
+                CompileErrorType::SemInvalidAggregateFunctionUse => 1210,
+                CompileErrorType::SemAttributeNotNullable => 1211,
+                CompileErrorType::SemInvalidInsertValuesNum => 1212,
+                CompileErrorType::SemInvalidInsertValueType => 1213,
+                CompileErrorType::SemInvalidInsertCharLen => 1214,
+                CompileErrorType::SemChangePrimaryAttr => 1215,
+                CompileErrorType::SemSelectAllWithGroupBy => 1216,
+                CompileErrorType::SemInvalidCopyPath => 1217,
+                CompileErrorType::SemInvalidCopyDelimiter => 1218,
+                CompileErrorType::SemInvalidFillFactor => 1219,
+                CompileErrorType::SemInvalidParameterNum => 1220,
+                CompileErrorType::SemInvalidLiteral => 1221,
+                CompileErrorType::SemInvalidGeneratedAttr => 1222,
+                CompileErrorType::SemChangeGeneratedAttr => 1223,
+
+                CompileErrorType::SemUnimplemented => 1299,
+            },
+            &ResultErrorType::Exec(ref error_type) => match *error_type {
+                ExecErrorType::PrimaryKeyExist => 2000,
+                ExecErrorType::DeleteTupleNotFound => 2001,
+                ExecErrorType::AlterRewriteFailed => 2002,
+                ExecErrorType::CopyOpenFileFailed => 2003,
+                ExecErrorType::CopyInvalidHeader => 2004,
+                ExecErrorType::CopyTooManyErrors => 2005,
+                ExecErrorType::CopyLinesRejected => 2006,
+                ExecErrorType::CopyFileExist => 2007,
+                ExecErrorType::CopyWriteFileFailed => 2008,
+                ExecErrorType::BackupFailed => 2009,
+                ExecErrorType::RestoreFailed => 2010,
+                ExecErrorType::QueryCancelled => 2011,
+                ExecErrorType::TableInUse => 2012,
+                ExecErrorType::StatementTimeout => 2013,
+                ExecErrorType::PagePoolExhausted => 2014,
+                ExecErrorType::InvalidInsertValuesNum => 2015,
+                ExecErrorType::FlushFailed => 2016,
+                ExecErrorType::ChecksumMismatch => 2017,
+                ExecErrorType::CopyStdinFailed => 2018,
+                ExecErrorType::TableReadOnly => 2019,
+                ExecErrorType::WriteFailed => 2020,
+            },
+            &ResultErrorType::TooManyConnections => 2100,
+            &ResultErrorType::PreparedStatementNotExist => 2101,
+            &ResultErrorType::PreparedStatementExist => 2102,
+            &ResultErrorType::AuthFailed => 2103,
+            &ResultErrorType::BatchStatementSkipped => 2104,
+            &ResultErrorType::UnknownVariable => 2105,
+            &ResultErrorType::InvalidVariableValue => 2106,
+            &ResultErrorType::CursorNotExist => 2107,
+            &ResultErrorType::CursorExist => 2108,
+            &ResultErrorType::CursorNotSelect => 2109,
+            &ResultErrorType::QueryNotExist => 2110,
+            &ResultErrorType::QueueFull => 2111,
+            &ResultErrorType::QueueTimeout => 2112,
+            &ResultErrorType::PermissionDenied => 2113,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResultError {
+    pub error_type : ResultErrorType,
+    pub error_msg : String,
+    pub column : Option<i32>,  // position of the token in the statement
+    pub token : Option<String>,
+}
+
+impl ResultError {
+    pub fn new(error_type : ResultErrorType, error_msg : String) -> ResultError {
+        ResultError{
+            error_type : error_type,
+            error_msg : error_msg,
+            column : None,
+            token : None,
+        }
+    }
+    pub fn code(&self) -> u32 {
+        self.error_type.code()
+    }
+    pub fn to_string(&self) -> String {
+        let type_name = match self.error_type {
+            ResultErrorType::Compile(ref error_type) => format!("{:?}", error_type),
+            ResultErrorType::Exec(ref error_type) => format!("{:?}", error_type),
+            ref error_type => format!("{:?}", error_type),
+        };
+        match (&self.column, &self.token) {
+            (&Some(column), &Some(ref token)) =>
+                format!("{} column {} `{}`: {}", type_name, column, token, self.error_msg),
+            _ => format!("{}: {}", type_name, self.error_msg),
+        }
+    }
+}
+
+pub fn from_compile_errors(err_list : &ErrorList) -> Vec<ResultError> {
+    let mut errors = Vec::new();
+    for err in err_list.iter() {
+        let mut result_error = ResultError::new(
+            ResultErrorType::Compile(err.error_type), err.error_msg.clone());
+        // a lexer error has the chars which failed in a token of no type,
+        // while the semantic errors without a position have an empty dummy one
+        if err.token.token_type != TokenType::UnKnown || !err.token.value.is_empty() {
+            result_error.column = Some(err.token.column);
+            result_error.token = Some(err.token.value.clone());
+        }
+        errors.push(result_error);
+    }
+    errors
+}
+
+pub fn from_exec_error(err : &ExecError) -> ResultError {
+    ResultError::new(ResultErrorType::Exec(err.error_type.clone()), err.error_msg.clone())
+}
+
+// the message for the shell, one line for each error
+pub fn render_errors(errors : &[ResultError]) -> String {
+    let lines : Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    lines.join("\n")
+}
@@ -1,425 +1,1193 @@
-use std::io::{Write, ErrorKind};
-use std::sync::{Arc, Mutex};
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::thread::{JoinHandle, spawn};
-use std::mem::transmute;
-use std::ptr::read;
-use std::slice;
-use mio::*;
-use mio::tcp::{TcpListener, TcpStream};
-use mio::util::Slab;
-use bytes::Buf;
-use rustc_serialize::json::encode;
-use ::utils::config::Config;
-use ::utils::pointer::to_cstring;
-use ::store::table::{TableManager, AttrType};
-use ::store::tuple::TupleData;
-use super::queue::{BlockingQueueRef, BlockingQueue};
-use super::handler::{sql_handler, ResultHandler, process_table_command};
-use super::buf::Buffer;
-
-
-const SERVER : Token = Token(0);
-type TaskQueueRef = BlockingQueueRef<(String, ConnRef)>;
-
-struct SqlServer {
-    listener : TcpListener,
-    conn_list : Slab<ConnRef>,
-    req_que : TaskQueueRef,
-    worker : JoinHandle<()>,
-}
-
-impl SqlServer {
-    fn new(listener : TcpListener) -> Self {
-        let q = BlockingQueueRef::new(BlockingQueue::new(64));
-        let q_clone = q.clone();
-        let worker = spawn(|| {
-            consume_task_loop(q_clone);
-        });
-        SqlServer{
-            listener : listener,
-            conn_list : Slab::new_starting_at(Token(1), 1024),
-            req_que : q,
-            worker : worker,
-        }
-    }
-}
-
-impl Handler for SqlServer {
-    type Timeout = ();
-    type Message = SenderMsg;
-
-    fn ready(&mut self, event_loop : &mut EventLoop<SqlServer>, token : Token, events : EventSet) {
-        match token {
-            SERVER => {
-                assert!(events.is_readable());
-                println!("server accept");
-                match self.listener.accept() {
-                    Ok(Some((socket, _))) => {
-                        println!("accepted a new client socket");
-                        let token = self.conn_list
-                            .insert_with(|token| Arc::new(Mutex::new(Connection::new(
-                                socket, token, event_loop.channel()
-                                ))))
-                            .unwrap();
-                        self.conn_list[token].lock().unwrap().init_reading_state(event_loop);
-                    }
-                    Ok(None) => { println!("the server socket wasn't actually ready"); },
-                    Err(e) => {
-                        println!("encountered error while accepting connection; err={:?}", e);
-                        event_loop.shutdown();
-                    }
-                }
-            }
-            _ => {
-                let mut closed = false;
-                {
-                    let clone = self.conn_list[token].clone();
-                    let mut conn = self.conn_list[token].lock().unwrap();
-                    conn.dispatch(event_loop, events);
-                    match conn.get_state() {
-                        State::Ready => {
-                            let sql = conn.get_sql();
-                            self.req_que.push_back((sql, clone));
-                        }
-                        State::Closed => closed = true,
-                        _ => (),
-                    }
-                }
-                if closed {
-                    self.conn_list[token].lock().unwrap().deregister_all(event_loop);
-                    is_match!(self.conn_list.remove(token), Some(..));
-                }
-            }
-        }
-    }
-
-    fn notify(&mut self, event_loop: &mut EventLoop<SqlServer>, msg: SenderMsg) {
-        let (token, curr_state, req_state) = msg;
-        let mut conn = self.conn_list[token].lock().unwrap();
-        match (curr_state, req_state) {
-            (State::Writing, State::Writing) => conn.ensure_write_registered(event_loop),
-            (State::Writing, State::Finished) => conn.transition_to_finished(event_loop),
-            other => panic!("invalid request {:?}", other),
-        }
-    }
-}
-
-type ConnRef = Arc<Mutex<Connection>>;
-type SenderMsg = (Token, State, State);
-
-#[derive(Debug)]
-pub struct Connection {
-    socket : TcpStream,
-    token : Token,
-    sender : Sender<SenderMsg>,
-    state : State,
-    read_buf : Vec<u8>,
-    write_buf : Buffer,
-    event_added : bool,
-}
-
-impl Connection {
-    fn new(socket: TcpStream, token: Token, sender : Sender<SenderMsg>) -> Connection {
-        Connection {
-            socket : socket,
-            token : token,
-            sender : sender,
-            state : State::Reading,
-            read_buf : Vec::new(),
-            write_buf : Buffer::new(64),
-            event_added : false,
-        }
-    }
-}
-
-impl Connection {
-    fn write_buffer(&mut self, data : &[u8]) {
-        check_ok!(self.write_buf.write(data));
-        self.ensure_write_registered_in_loop();
-    }
-
-    fn get_state(&self) -> State {
-        self.state.clone()
-    }
-
-    fn get_socket(&self) -> &TcpStream {
-        &self.socket
-    }
-
-    fn get_sql(&self) -> String {
-        assert_eq!(self.get_state(), State::Ready);
-        let end = match self.read_buf.iter().position(|b| *b == b'\r') {
-            Some(i) => i,
-            None => self.read_buf.iter().position(|b| *b == b'\n').unwrap(),
-        };
-        String::from_utf8(Vec::from(&self.read_buf[..end])).unwrap()
-    }
-
-    fn dispatch(&mut self, event_loop: &mut EventLoop<SqlServer>, events: EventSet) {
-        println!("connection state: {:?}", self.state);
-
-        match self.state {
-            State::Reading => {
-                assert!(events.is_readable(), "unexpected events; events={:?}", events);
-                self.read(event_loop)
-            }
-            State::Writing | State::Finished => {
-                assert!(events.is_writable(), "unexpected events; events={:?}", events);
-                self.write(event_loop)
-            }
-            _ => panic!("invalid state {:?}", self.state),
-        }
-    }
-
-    fn read(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        assert_eq!(self.state, State::Reading);
-        match self.socket.try_read_buf(&mut self.read_buf) {
-            Ok(Some(0)) => {
-                println!("read 0 bytes from client; buffered={}", self.read_buf.len());
-                assert_eq!(self.state, State::Reading);
-                self.state = State::Closed;
-            }
-            Ok(Some(n)) => {
-                println!("read {} bytes", n);
-                self.try_transition_to_ready(event_loop);
-            }
-            Ok(None) => {
-                println!("nothing read");
-            }
-            Err(e) => {
-                match e.kind() {
-                    ErrorKind::ConnectionReset => {
-                        println!("{:?}", e);
-                        self.state = State::Closed;
-                    },
-                    _ => panic!("got an error trying to read; err={:?}", e),
-                }
-            }
-        }
-    }
-
-    fn write(&mut self, event_loop: &mut EventLoop<SqlServer>) {
-        match self.socket.try_write_buf(&mut self.write_buf) {
-            Ok(Some(n)) => {
-                println!("write {:?} bytes", n);
-                if !self.write_buf.has_remaining() {
-                    self.deregister_all(event_loop);
-                }
-                self.try_transition_to_reading(event_loop);
-            }
-            Ok(None) => {
-                println!("nothing write");
-            }
-            Err(e) => {
-                panic!("got an error trying to write; err={:?}", e);
-            }
-        }
-    }
-
-    fn init_reading_state(&mut self, event_loop: &mut EventLoop<SqlServer>) {
-        self.state = State::Reading;
-        self.register_read(event_loop);
-    }
-
-    fn try_transition_to_ready(&mut self, event_loop: &mut EventLoop<SqlServer>) {
-        assert_eq!(self.state, State::Reading);
-        if let Some(..) = self.read_buf.iter().position(|b| *b == b'\n') {
-            println!("change to ready");
-            self.write_buf.reset();
-            self.state = State::Ready;
-            self.deregister_all(event_loop);
-        }
-    }
-
-    fn change_to_finished_in_loop(&self) {
-        assert_eq!(self.state, State::Writing);
-        check_ok!(self.sender.send((self.token, State::Writing, State::Finished)));
-    }
-
-    fn register_write_in_loop(&self) {
-        assert_eq!(self.state, State::Writing);
-        check_ok!(self.sender.send((self.token, State::Writing, State::Writing)));
-    }
-
-    fn transition_to_writing(&mut self) {
-        assert_eq!(self.state, State::Ready);
-        println!("change to writing");
-        self.state = State::Writing;
-    }
-
-    fn transition_to_finished(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        assert_eq!(self.state, State::Writing);
-        println!("change to finished");
-        self.state = State::Finished;
-        // this will trigger a writable event then call try_transition_to_reading
-        // should not delete this!
-        check_ok!(self.write_buf.write(b"\r\n"));
-        if !self.event_added {
-            self.register_write(event_loop);
-        }
-    }
-
-    fn try_transition_to_reading(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        if self.state == State::Finished && !self.write_buf.has_remaining() {
-            println!("change to reading");
-            self.read_buf.clear();
-            self.state = State::Reading;
-            self.register_read(event_loop);
-        }
-    }
-
-    fn ensure_write_registered_in_loop(&mut self) {
-        if !self.event_added {
-            self.register_write_in_loop();
-        }
-    }
-
-    fn ensure_write_registered(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        if !self.event_added {
-            self.register_write(event_loop);
-        }
-    }
-
-    fn register_read(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        assert!(!self.event_added);
-        check_ok!(
-            event_loop.register(self.get_socket(), self.token, EventSet::readable(), PollOpt::level())
-        );
-        self.event_added = true;
-    }
-
-    fn register_write(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        assert!(!self.event_added);
-        check_ok!(
-            event_loop.register(self.get_socket(), self.token, EventSet::writable(), PollOpt::level())
-        );
-        self.event_added = true;
-    }
-
-    fn deregister_all(&mut self, event_loop : &mut EventLoop<SqlServer>) {
-        assert!(self.event_added);
-        check_ok!(
-            event_loop.deregister(self.get_socket())
-        );
-        self.event_added = false;
-    }
-}
-
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum State {
-    Reading,
-    Ready,
-    Writing,
-    Finished,
-    Closed,
-}
-
-fn consume_task_loop(req_que : TaskQueueRef) {
-    let config = Config::from_cwd_config();
-    let mut manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
-    loop {
-        let (sql, conn) = req_que.pop_front();
-        conn.lock().unwrap().transition_to_writing();
-        if let Ok(out) = process_table_command(&sql, &manager) {
-            let mut c = conn.lock().unwrap();
-            c.write_buffer(to_cstring(out).as_bytes());
-            c.change_to_finished_in_loop();
-        } else {
-            println!("processing {:?}", sql);
-            let mut process = Process::new(conn);
-            sql_handler(&sql, &mut process, &mut manager);
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Process {
-    attr_desc : Vec<AttrType>,
-    attr_index : Vec<usize>,
-    conn : ConnRef,
-    header_sended : bool,
-}
-
-impl Process {
-    pub fn new(conn : ConnRef) -> Process {
-        Process{
-            attr_desc : Vec::new(),
-            attr_index : Vec::new(),
-            conn : conn,
-            header_sended : false,
-        }
-    }
-    fn send_header(&mut self) {
-        assert!(!self.header_sended);
-        let json_header = encode(&self.attr_desc).unwrap();
-        let json_len = json_header.len() as u32;
-        let cstring = to_cstring(json_header);
-        let len_bytes : [u8; 4] = unsafe { transmute(json_len.to_le()) };
-        {
-            let mut c = self.conn.lock().unwrap();
-            c.write_buffer(&len_bytes);
-            c.write_buffer(cstring.as_bytes());
-        }
-        self.header_sended = true;
-    }
-}
-
-impl ResultHandler for Process {
-    fn handle_non_query_finished(&mut self) {
-        let non_query_header_tag : [u8; 4] = [0, 0, 0, 0];
-        let mut c = self.conn.lock().unwrap();
-        c.write_buffer(&non_query_header_tag);
-        c.change_to_finished_in_loop();
-    }
-    fn handle_error(&mut self, err_msg : String) {
-        let cstring = to_cstring(err_msg);
-        let error_header_tag : [u8; 4] = [0, 0, 0, 0];
-        let mut c = self.conn.lock().unwrap();
-        c.write_buffer(&error_header_tag);
-        c.write_buffer(cstring.as_bytes());
-        c.change_to_finished_in_loop();
-    }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
-        if !self.header_sended {
-            self.send_header();
-        }
-        match tuple_data {
-            Some(data) => {
-                assert_eq!(self.attr_desc.len(), data.len());
-                let mut c = self.conn.lock().unwrap();
-                for (attr, p) in self.attr_desc.iter().zip(data.iter()) {
-                    match attr {
-                        &AttrType::Int | &AttrType::Float => {
-                            let bytes = unsafe{read::<[u8; 4]>(*p as *const [u8; 4])};
-                            c.write_buffer(&bytes);
-                        }
-                        &AttrType::Char{len} => {
-                            let bytes : &[u8] = unsafe{ slice::from_raw_parts(*p as *const u8, len) };
-                            c.write_buffer(bytes);
-                        }
-                    };
-                }
-            }
-            None => self.conn.lock().unwrap().change_to_finished_in_loop(),
-        }
-    }
-    fn set_tuple_info(&mut self, attr_desc : Vec<AttrType>, attr_index : Vec<usize>) {
-        self.attr_desc = attr_desc;
-        self.attr_index = attr_index;
-    }
-}
-
-pub fn run_server() {
-    let config = Config::from_cwd_config();
-    let port = config.get_int("port");
-    let addr = format!("0.0.0.0:{}", port).parse().unwrap();
-    let listener = TcpListener::bind(&addr).unwrap();
-    let mut event_loop = EventLoop::new().unwrap();
-
-    event_loop.register(&listener, SERVER, EventSet::readable(),
-                        PollOpt::level()).unwrap();
-    let mut sqlserver = SqlServer::new(listener);
-    event_loop.run(&mut sqlserver).unwrap();
-}
+use std::io;
+use std::io::{Read, Write, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::mpsc::Sender as ResultSender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::thread::{JoinHandle, spawn};
+use std::mem::{transmute, replace};
+use std::cmp::min;
+use std::iter::repeat;
+use mio::*;
+use mio::tcp::TcpListener;
+use mio::unix::UnixListener;
+use mio::util::Slab;
+use bytes::Buf;
+use rustc_serialize::json::encode;
+use ::utils::config::Config;
+use ::utils::pointer::to_cstring;
+use ::utils::log::init_log;
+use ::utils::libwrapper::{catch_stop_signal, stop_signaled};
+use ::store::table::{TableManager, TableManagerRef, AttrType, CopyInput};
+use ::store::tuple::TupleValue;
+use super::queue::{BlockingQueueRef, BlockingQueue};
+use super::handler::{sql_handler, ResultHandler, ColumnInfo, Summary, process_table_command};
+use super::session::{Session, Batch, session_handler, parse_batch_command, is_copy_from_stdin};
+use super::result_error::{ResultError, ResultErrorType, render_errors};
+use super::auth::{UserTable, parse_auth_command};
+use super::stats::{ServerStats, ServerStatsRef};
+use super::query_log::{QueryLogs, run_logged};
+use super::processlist::{ProcessList, ProcessListRef, run_tracked, run_dequeued, run_killable,
+    is_process_command, process_processlist_command};
+use super::collect::CollectedResult;
+use super::mysql::start_mysql_listener;
+use super::http::start_http_listener;
+use super::buf::Buffer;
+use super::socket::{Socket, accept_socket, bind_unix_listener, remove_unix_socket,
+    load_tls_context, start_tls, TlsContext};
+
+
+const SERVER : Token = Token(0);
+const UNIX_SERVER : Token = Token(1);
+const TLS_SERVER : Token = Token(2);
+// the tag before each batch of rows of a query result, and the ones ending the rows
+const ROW_BATCH_TAG : u8 = 3;
+const END_TAG : u8 = 0;
+const ERROR_TAG : u8 = 2;
+// followed by the u32 length and the message, before the end of the rows
+const WARNING_TAG : u8 = 4;
+// the copy data read ahead of the worker, the loop stops reading the client over it
+const MAX_COPY_BUFFERED : usize = 65536;
+pub enum Task {
+    // with the id in the process list, None for the line authenticating the connection
+    Native(String, ConnRef, Option<usize>),
+    // from the other protocols, the whole result is sent back to the thread of the connection,
+    // the user is None without users_file
+    Collect{ sql : String, conn_id : usize, user : Option<String>, query_id : usize,
+        result_sender : ResultSender<CollectedResult> },
+    Shutdown,  // save everything and exit
+}
+
+pub type TaskQueueRef = BlockingQueueRef<Task>;
+const SHUTDOWN_CHECK_INTERVAL_MS : u64 = 100;
+
+struct SqlServer {
+    listener : TcpListener,
+    unix_listener : Option<(UnixListener, String)>,  // with the path, removed on shutdown
+    tls_listener : Option<TcpListener>,
+    tls_context : Option<TlsContext>,
+    plaintext : bool,  // false when the listener on `port` also requires tls
+    conn_list : Slab<ConnRef>,
+    req_que : TaskQueueRef,
+    worker : JoinHandle<()>,
+    stats : ServerStatsRef,
+    // `show processlist` and `kill` are served by the event loop, since the worker is busy with the query
+    processes : ProcessListRef,
+    auth_required : bool,
+    shutdown_requested : Arc<AtomicBool>,  // by the `shutdown` statement
+    worker_done : Arc<AtomicBool>,
+    shutting_down : bool,
+}
+
+impl SqlServer {
+    fn new(listener : TcpListener, unix_listener : Option<(UnixListener, String)>, tls : TlsListener,
+            config : Config, q : TaskQueueRef, processes : ProcessListRef) -> Self {
+        let max_connections = config.get_usize("max_connections");
+        let q_clone = q.clone();
+        let stats = ServerStats::make_ref();
+        let stats_clone = stats.clone();
+        let processes_clone = processes.clone();
+        let auth_required = UserTable::from_config(&config).is_some();
+        let shutdown_requested = Arc::new(AtomicBool::new(false));
+        let requested_clone = shutdown_requested.clone();
+        let worker_done = Arc::new(AtomicBool::new(false));
+        let done_clone = worker_done.clone();
+        let worker = spawn(move || {
+            consume_task_loop(q_clone, config, stats_clone, processes_clone, requested_clone);
+            done_clone.store(true, Ordering::SeqCst);
+        });
+        SqlServer{
+            listener : listener,
+            unix_listener : unix_listener,
+            tls_listener : tls.listener,
+            tls_context : tls.context,
+            plaintext : tls.plaintext,
+            conn_list : Slab::new_starting_at(Token(3), max_connections),
+            req_que : q,
+            worker : worker,
+            stats : stats,
+            processes : processes,
+            auth_required : auth_required,
+            shutdown_requested : shutdown_requested,
+            worker_done : worker_done,
+            shutting_down : false,
+        }
+    }
+
+    fn accept_connection(&mut self, event_loop : &mut EventLoop<SqlServer>, accepted : io::Result<Option<Socket>>) {
+        match accepted {
+            Ok(Some(mut socket)) if !self.conn_list.has_remaining() => {
+                log_warn!("too many connections, reject the client");
+                reject_connection(&mut socket);
+                self.stats.connection_rejected();
+            }
+            Ok(Some(socket)) => {
+                let token = self.conn_list
+                    .insert_with(|token| Arc::new(Mutex::new(Connection::new(
+                        socket, token, event_loop.channel()
+                        ))))
+                    .unwrap();
+                log_info!("connection {} opened, {} connection(s)", token.0, self.conn_list.count());
+                self.conn_list[token].lock().unwrap().init_reading_state(event_loop);
+                self.stats.connection_opened();
+            }
+            Ok(None) => { log_debug!("the server socket wasn't actually ready"); },
+            Err(e) => {
+                log_error!("encountered error while accepting connection; err={:?}", e);
+                event_loop.shutdown();
+            }
+        }
+    }
+
+    fn all_results_sent(&self) -> bool {
+        self.conn_list.iter().all(|conn| match conn.lock().unwrap().get_state() {
+            State::CopyIn | State::Writing | State::Finished => false,
+            _ => true,
+        })
+    }
+}
+
+impl Handler for SqlServer {
+    type Timeout = ();
+    type Message = SenderMsg;
+
+    fn ready(&mut self, event_loop : &mut EventLoop<SqlServer>, token : Token, events : EventSet) {
+        match token {
+            SERVER | UNIX_SERVER | TLS_SERVER => {
+                assert!(events.is_readable());
+                log_trace!("server accept");
+                let accepted = match (token, &self.unix_listener, &self.tls_listener) {
+                    (UNIX_SERVER, &Some((ref unix_listener, _)), _) => accept_socket(unix_listener),
+                    (TLS_SERVER, _, &Some(ref tls_listener)) => accept_socket(tls_listener),
+                    _ => accept_socket(&self.listener),
+                };
+                let accepted = match accepted {
+                    Ok(Some(socket)) if token == TLS_SERVER || (token == SERVER && !self.plaintext) => {
+                        let context = self.tls_context.as_ref().unwrap();
+                        match start_tls(socket, context) {
+                            Ok(socket) => Ok(Some(socket)),
+                            // only this client is dropped
+                            Err(e) => {
+                                log_warn!("tls handshake failed; err={:?}", e);
+                                return;
+                            }
+                        }
+                    }
+                    other => other,
+                };
+                self.accept_connection(event_loop, accepted);
+            }
+            _ => {
+                let mut closed = false;
+                let mut process_command = None;
+                let mut refused = None;
+                {
+                    let clone = self.conn_list[token].clone();
+                    let mut conn = self.conn_list[token].lock().unwrap();
+                    conn.dispatch(event_loop, events);
+                    match conn.get_state() {
+                        // no more statement is run after shutting down
+                        State::Ready if self.shutting_down => closed = true,
+                        State::Ready => {
+                            let sql = conn.take_sql();
+                            let authenticated = conn.session.user.is_some() || !self.auth_required;
+                            if authenticated && is_process_command(&sql) {
+                                conn.transition_to_writing();
+                                process_command = Some(sql);
+                            } else if !authenticated {
+                                self.req_que.push_back(Task::Native(sql, clone, None));
+                            } else {
+                                match self.processes.enqueue(token.0, &sql) {
+                                    Ok(query_id) => {
+                                        self.req_que.push_back(Task::Native(sql, clone, Some(query_id)));
+                                    }
+                                    Err(error) => {
+                                        conn.transition_to_writing();
+                                        // the copy data can't be told apart from the next statements
+                                        conn.close_after_write = is_copy_from_stdin(&sql);
+                                        refused = Some(error);
+                                    }
+                                }
+                            }
+                        }
+                        State::Closed => closed = true,
+                        _ => (),
+                    }
+                }
+                if let Some(sql) = process_command {
+                    // the loop can't wait for the client, the result is small enough to be buffered at once
+                    let conn = self.conn_list[token].clone();
+                    let mut process = Process::new(conn, StreamLimit::unlimited(), self.stats.clone());
+                    is_match!(process_processlist_command(&sql, &self.processes, &mut process), Ok(..));
+                }
+                if let Some(error) = refused {
+                    let conn = self.conn_list[token].clone();
+                    let mut process = Process::new(conn, StreamLimit::unlimited(), self.stats.clone());
+                    process.handle_error(vec![error]);
+                }
+                if closed {
+                    {
+                        let mut conn = self.conn_list[token].lock().unwrap();
+                        conn.deregister_if_added(event_loop);
+                        conn.session.close();
+                    }
+                    is_match!(self.conn_list.remove(token), Some(..));
+                    self.stats.connection_closed();
+                    log_info!("connection {} closed, {} connection(s)", token.0, self.conn_list.count());
+                }
+            }
+        }
+    }
+
+    fn notify(&mut self, event_loop: &mut EventLoop<SqlServer>, msg: SenderMsg) {
+        let (token, curr_state, req_state) = msg;
+        let closed = {
+            let mut conn = self.conn_list[token].lock().unwrap();
+            if conn.disconnected {
+                // the connection is kept until the worker finishes the result
+                req_state == State::Finished
+            } else {
+                match (curr_state, req_state) {
+                    (State::Writing, State::Writing) => conn.ensure_write_registered(event_loop),
+                    (State::Writing, State::Finished) => conn.transition_to_finished(event_loop),
+                    (State::CopyIn, State::CopyIn) => conn.ensure_read_registered(event_loop),
+                    (State::CopyIn, State::Writing) => conn.finish_copy_in(event_loop),
+                    other => panic!("invalid request {:?}", other),
+                }
+                false
+            }
+        };
+        if closed {
+            is_match!(self.conn_list.remove(token), Some(..));
+            self.stats.connection_closed();
+            log_info!("connection {} disconnected before the result is finished, {} connection(s)",
+                token.0, self.conn_list.count());
+        }
+    }
+
+    fn timeout(&mut self, event_loop : &mut EventLoop<SqlServer>, _ : ()) {
+        let requested = self.shutdown_requested.load(Ordering::SeqCst) || stop_signaled();
+        if requested && !self.shutting_down {
+            log_info!("shutting down, refuse new connections");
+            self.shutting_down = true;
+            check_ok!(event_loop.deregister(&self.listener));
+            if let Some((ref unix_listener, _)) = self.unix_listener {
+                check_ok!(event_loop.deregister(unix_listener));
+            }
+            if let Some(ref tls_listener) = self.tls_listener {
+                check_ok!(event_loop.deregister(tls_listener));
+            }
+            // the statements queued before are still run
+            self.req_que.push_back(Task::Shutdown);
+        }
+        if self.shutting_down && self.worker_done.load(Ordering::SeqCst) && self.all_results_sent() {
+            log_info!("all the tables are saved, exit");
+            if let Some((_, ref path)) = self.unix_listener {
+                remove_unix_socket(path);
+            }
+            event_loop.shutdown();
+            return;
+        }
+        check_ok!(event_loop.timeout_ms((), SHUTDOWN_CHECK_INTERVAL_MS));
+    }
+}
+
+fn reject_connection(socket : &mut Socket) {
+    // sent as an error result, the client reads it as the result of its first statement
+    let code = ResultErrorType::TooManyConnections.code();
+    let code_bytes : [u8; 4] = unsafe{ transmute(code.to_le()) };
+    let mut response = vec![0, 0, 0, 0];
+    response.extend_from_slice(&code_bytes);
+    response.extend_from_slice(b"too many connections\r\n");
+    if let Err(e) = socket.try_write(&response) {
+        log_warn!("failed to reject the client; err={:?}", e);
+    }
+}
+
+type ConnRef = Arc<Mutex<Connection>>;
+type SenderMsg = (Token, State, State);
+
+#[derive(Debug)]
+pub struct Connection {
+    socket : Socket,
+    token : Token,
+    sender : Sender<SenderMsg>,
+    state : State,
+    read_buf : Vec<u8>,
+    write_buf : Buffer,
+    event_added : bool,
+    disconnected : bool,  // the client is gone while the worker is still writing the result
+    session : Session,  // only used by the worker
+    drained : Arc<Condvar>,  // notified when the client has read some of the result
+    close_after_write : bool,  // closed when the result is written, after failing to authenticate
+    received : Arc<Condvar>,  // notified when the loop has read more of the copy data
+    copy_frame : CopyFrame,
+    copy_requested : bool,  // the worker has asked the loop to read more of the copy data
+}
+
+impl Connection {
+    fn new(socket: Socket, token: Token, sender : Sender<SenderMsg>) -> Connection {
+        Connection {
+            socket : socket,
+            token : token,
+            sender : sender,
+            state : State::Reading,
+            read_buf : Vec::new(),
+            write_buf : Buffer::new(64),
+            event_added : false,
+            disconnected : false,
+            session : Session::new(token.0),
+            drained : Arc::new(Condvar::new()),
+            close_after_write : false,
+            received : Arc::new(Condvar::new()),
+            copy_frame : CopyFrame::End,
+            copy_requested : false,
+        }
+    }
+}
+
+impl Connection {
+    fn write_buffer(&mut self, data : &[u8]) {
+        if self.disconnected {
+            return;
+        }
+        check_ok!(self.write_buf.write(data));
+        self.ensure_write_registered_in_loop();
+    }
+
+    fn get_state(&self) -> State {
+        self.state.clone()
+    }
+
+    fn get_socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    fn take_sql(&mut self) -> String {
+        // the statements after the first line are kept for the next request
+        assert_eq!(self.get_state(), State::Ready);
+        let line_end = self.read_buf.iter().position(|b| *b == b'\n').unwrap();
+        let line : Vec<u8> = self.read_buf.drain(..line_end + 1).collect();
+        let mut end = line_end;
+        if end > 0 && line[end - 1] == b'\r' {
+            end -= 1;
+        }
+        String::from_utf8_lossy(&line[..end]).into_owned()
+    }
+
+    fn dispatch(&mut self, event_loop: &mut EventLoop<SqlServer>, events: EventSet) {
+        log_trace!("connection {} state: {:?}", self.token.0, self.state);
+
+        match self.state {
+            State::Reading => {
+                assert!(events.is_readable(), "unexpected events; events={:?}", events);
+                self.read(event_loop)
+            }
+            State::CopyIn => {
+                assert!(events.is_readable(), "unexpected events; events={:?}", events);
+                self.read_copy_data(event_loop)
+            }
+            State::Writing | State::Finished => {
+                assert!(events.is_writable(), "unexpected events; events={:?}", events);
+                self.write(event_loop)
+            }
+            _ => panic!("invalid state {:?}", self.state),
+        }
+    }
+
+    fn read(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert_eq!(self.state, State::Reading);
+        match self.socket.try_read_buf(&mut self.read_buf) {
+            Ok(Some(0)) => {
+                log_trace!("read 0 bytes from client; buffered={}", self.read_buf.len());
+                assert_eq!(self.state, State::Reading);
+                self.state = State::Closed;
+            }
+            Ok(Some(n)) => {
+                log_trace!("read {} bytes", n);
+                self.try_transition_to_ready(event_loop);
+            }
+            Ok(None) => {
+                log_trace!("nothing read");
+            }
+            Err(e) => {
+                match e.kind() {
+                    // ConnectionAborted for the tls errors
+                    ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => {
+                        log_debug!("connection {} lost; err={:?}", self.token.0, e);
+                        self.state = State::Closed;
+                    },
+                    _ => panic!("got an error trying to read; err={:?}", e),
+                }
+            }
+        }
+    }
+
+    // the frames of copy from stdin, taken by the worker while it runs the statement
+    fn read_copy_data(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert_eq!(self.state, State::CopyIn);
+        match self.socket.try_read_buf(&mut self.read_buf) {
+            Ok(Some(0)) => {
+                log_debug!("connection {} closed in the middle of the copy data", self.token.0);
+                self.deregister_all(event_loop);
+                self.disconnected = true;
+            }
+            Ok(Some(n)) => {
+                log_trace!("read {} bytes of copy data", n);
+                // backpressure, the client waits until the worker takes the data
+                if self.read_buf.len() >= MAX_COPY_BUFFERED {
+                    self.deregister_all(event_loop);
+                }
+            }
+            Ok(None) => {
+                log_trace!("nothing read");
+                return;
+            }
+            Err(e) => {
+                match e.kind() {
+                    ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => {
+                        log_debug!("connection {} lost; err={:?}", self.token.0, e);
+                        self.deregister_all(event_loop);
+                        self.disconnected = true;
+                    },
+                    _ => panic!("got an error trying to read; err={:?}", e),
+                }
+            }
+        }
+        self.received.notify_all();
+    }
+
+    fn write(&mut self, event_loop: &mut EventLoop<SqlServer>) {
+        match self.socket.try_write_buf(&mut self.write_buf) {
+            Ok(Some(n)) => {
+                log_trace!("write {:?} bytes", n);
+                self.drained.notify_all();
+                if !self.write_buf.has_remaining() {
+                    self.deregister_all(event_loop);
+                }
+                self.try_transition_to_reading(event_loop);
+            }
+            Ok(None) => {
+                log_trace!("nothing write");
+            }
+            Err(e) => {
+                match e.kind() {
+                    ErrorKind::ConnectionReset | ErrorKind::BrokenPipe | ErrorKind::ConnectionAborted => {
+                        log_debug!("connection {} lost; err={:?}", self.token.0, e);
+                        self.handle_disconnect(event_loop);
+                    },
+                    _ => panic!("got an error trying to write; err={:?}", e),
+                }
+            }
+        }
+    }
+
+    fn handle_disconnect(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        self.write_buf.reset();
+        if self.state == State::Writing {
+            // the worker still owns the connection, wait for the result to finish
+            self.deregister_all(event_loop);
+            self.disconnected = true;
+            self.drained.notify_all();
+        } else {
+            self.state = State::Closed;
+        }
+    }
+
+    fn init_reading_state(&mut self, event_loop: &mut EventLoop<SqlServer>) {
+        self.state = State::Reading;
+        self.register_read(event_loop);
+    }
+
+    fn try_transition_to_ready(&mut self, event_loop: &mut EventLoop<SqlServer>) {
+        assert_eq!(self.state, State::Reading);
+        if let Some(..) = self.read_buf.iter().position(|b| *b == b'\n') {
+            log_trace!("change to ready");
+            self.write_buf.reset();
+            self.state = State::Ready;
+            self.deregister_all(event_loop);
+        }
+    }
+
+    fn change_to_finished_in_loop(&self) {
+        assert_eq!(self.state, State::Writing);
+        check_ok!(self.sender.send((self.token, State::Writing, State::Finished)));
+    }
+
+    // the last result of a request finishes the connection, the ones before it only end with \r\n
+    fn finish_result(&mut self, more_results : bool) {
+        if more_results {
+            self.write_buffer(b"\r\n");
+        } else {
+            self.change_to_finished_in_loop();
+        }
+    }
+
+    fn register_write_in_loop(&self) {
+        assert_eq!(self.state, State::Writing);
+        check_ok!(self.sender.send((self.token, State::Writing, State::Writing)));
+    }
+
+    fn transition_to_writing(&mut self) {
+        assert_eq!(self.state, State::Ready);
+        log_trace!("change to writing");
+        self.state = State::Writing;
+    }
+
+    fn transition_to_finished(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert_eq!(self.state, State::Writing);
+        log_trace!("change to finished");
+        self.state = State::Finished;
+        // this will trigger a writable event then call try_transition_to_reading
+        // should not delete this!
+        check_ok!(self.write_buf.write(b"\r\n"));
+        if !self.event_added {
+            self.register_write(event_loop);
+        }
+    }
+
+    fn try_transition_to_reading(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        if self.state == State::Finished && !self.write_buf.has_remaining() {
+            if self.close_after_write {
+                log_trace!("close the connection");
+                self.state = State::Closed;
+                return;
+            }
+            log_trace!("change to reading");
+            self.state = State::Reading;
+            if self.read_buf.iter().position(|b| *b == b'\n').is_some() {
+                // the client sent the next statement before reading the result
+                log_trace!("change to ready");
+                self.write_buf.reset();
+                self.state = State::Ready;
+            } else {
+                self.register_read(event_loop);
+            }
+        }
+    }
+
+    // by the worker once it has taken some of the copy data
+    fn request_copy_data(&mut self) {
+        assert_eq!(self.state, State::CopyIn);
+        if !self.event_added && !self.copy_requested && !self.disconnected
+                && self.read_buf.len() < MAX_COPY_BUFFERED {
+            self.copy_requested = true;
+            check_ok!(self.sender.send((self.token, State::CopyIn, State::CopyIn)));
+        }
+    }
+
+    fn ensure_read_registered(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        self.copy_requested = false;
+        if !self.event_added {
+            self.register_read(event_loop);
+        }
+    }
+
+    // the worker has read all the copy data, and waits for this before writing the result
+    fn finish_copy_in(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert_eq!(self.state, State::CopyIn);
+        self.deregister_if_added(event_loop);
+        self.copy_requested = false;
+        self.state = State::Writing;
+        self.received.notify_all();
+    }
+
+    fn ensure_write_registered_in_loop(&mut self) {
+        if !self.event_added {
+            self.register_write_in_loop();
+        }
+    }
+
+    fn ensure_write_registered(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        if !self.event_added && !self.disconnected {
+            self.register_write(event_loop);
+        }
+    }
+
+    fn register_read(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert!(!self.event_added);
+        check_ok!(
+            event_loop.register(self.get_socket(), self.token, EventSet::readable(), PollOpt::level())
+        );
+        self.event_added = true;
+    }
+
+    fn register_write(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert!(!self.event_added);
+        check_ok!(
+            event_loop.register(self.get_socket(), self.token, EventSet::writable(), PollOpt::level())
+        );
+        self.event_added = true;
+    }
+
+    fn deregister_all(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        assert!(self.event_added);
+        check_ok!(
+            event_loop.deregister(self.get_socket())
+        );
+        self.event_added = false;
+    }
+
+    fn deregister_if_added(&mut self, event_loop : &mut EventLoop<SqlServer>) {
+        if self.event_added {
+            self.deregister_all(event_loop);
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum State {
+    Reading,
+    Ready,
+    CopyIn,  // the worker is reading the data of copy from stdin, set by the worker
+    Writing,
+    Finished,
+    Closed,
+}
+
+// the rows of a query are sent in batches of about batch_bytes,
+// the plan waits while more than max_buffered bytes are not read by the client
+#[derive(Debug, Clone, Copy)]
+struct StreamLimit {
+    batch_bytes : usize,
+    max_buffered : usize,
+}
+
+impl StreamLimit {
+    fn from_config(config : &Config) -> StreamLimit {
+        StreamLimit{
+            batch_bytes : config.get_usize("result_batch_bytes"),
+            max_buffered : config.get_usize("max_write_buffer_bytes"),
+        }
+    }
+    fn unlimited() -> StreamLimit {
+        StreamLimit{ batch_bytes : ::std::usize::MAX, max_buffered : ::std::usize::MAX }
+    }
+}
+
+// Where the worker is in the data of copy from stdin, sent in frames of a u32 length (Little-Endian)
+// and the bytes, until an empty frame.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum CopyFrame {
+    Length,  // before the length of the next frame
+    Data(usize),  // the bytes left in the current frame
+    End,  // after the empty frame
+}
+
+// The copy data taken out of the connection by the worker. A frame over max_frame_bytes fails the read
+// before any of it is taken, so the frames can still be dropped by a stream without the limit.
+#[derive(Debug)]
+struct CopyInStream {
+    conn : ConnRef,
+    max_frame_bytes : usize,
+}
+
+impl Read for CopyInStream {
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+        let mut c = self.conn.lock().unwrap();
+        loop {
+            let frame = c.copy_frame;
+            match frame {
+                CopyFrame::End => return Ok(0),
+                CopyFrame::Length if c.read_buf.len() >= 4 => {
+                    let len = c.read_buf[..4].iter().rev().fold(0, |len, b| len << 8 | *b as usize);
+                    if len > self.max_frame_bytes {
+                        let msg = format!("a frame of {} bytes is over max_copy_frame_bytes {}",
+                            len, self.max_frame_bytes);
+                        return Err(io::Error::new(ErrorKind::InvalidData, msg));
+                    }
+                    c.read_buf.drain(..4);
+                    c.copy_frame = if len == 0 { CopyFrame::End } else { CopyFrame::Data(len) };
+                    continue;
+                }
+                CopyFrame::Data(left) if !c.read_buf.is_empty() => {
+                    let n = min(min(left, buf.len()), c.read_buf.len());
+                    buf[..n].copy_from_slice(&c.read_buf[..n]);
+                    c.read_buf.drain(..n);
+                    c.copy_frame = if n == left { CopyFrame::Length } else { CopyFrame::Data(left - n) };
+                    c.request_copy_data();
+                    return Ok(n);
+                }
+                _ => (),
+            }
+            if c.disconnected {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof,
+                    "the client is gone before the end of the copy data"));
+            }
+            c.request_copy_data();
+            let received = c.received.clone();
+            c = received.wait(c).unwrap();
+        }
+    }
+}
+
+// The statement of copy from stdin runs while the loop reads the frames after its line.
+// The loop can't read and write the client at once, so the result is collected and sent
+// after the rest of the frames are dropped, they are all read even if the statement fails early.
+fn run_copy_in<F>(conn : &ConnRef, max_frame_bytes : usize, manager : &TableManagerRef,
+        process : &mut Process, run : F) where F : FnOnce(&mut ResultHandler) {
+    {
+        let mut c = conn.lock().unwrap();
+        assert_eq!(c.state, State::Writing);
+        c.state = State::CopyIn;
+        c.copy_frame = CopyFrame::Length;
+        c.request_copy_data();
+    }
+    let stream = CopyInStream{ conn : conn.clone(), max_frame_bytes : max_frame_bytes };
+    manager.borrow_mut().set_copy_input(Some(CopyInput(Box::new(stream))));
+    let mut result = CollectedResult::new();
+    run(&mut result as &mut ResultHandler);
+    manager.borrow_mut().set_copy_input(None);
+    // the rest of the frames, only failed when the client is gone
+    let mut rest = CopyInStream{ conn : conn.clone(), max_frame_bytes : ::std::usize::MAX };
+    io::copy(&mut rest, &mut io::sink()).ok();
+    {
+        let mut c = conn.lock().unwrap();
+        if !c.disconnected {
+            check_ok!(c.sender.send((c.token, State::CopyIn, State::Writing)));
+        }
+        while c.state != State::Writing && !c.disconnected {
+            let received = c.received.clone();
+            c = received.wait(c).unwrap();
+        }
+        // nothing is registered in the loop once the client is gone
+        c.state = State::Writing;
+    }
+    if !result.errors.is_empty() {
+        process.handle_error(result.errors);
+    } else if let Some(summary) = result.summary {
+        process.handle_metadata(&result.columns);
+        process.handle_complete(summary);
+    } else {
+        process.handle_non_query_finished();
+    }
+}
+
+fn consume_task_loop(req_que : TaskQueueRef, config : Config, stats : ServerStatsRef,
+        processes : ProcessListRef, shutdown_requested : Arc<AtomicBool>) {
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let limit = StreamLimit::from_config(&config);
+    let max_copy_frame_bytes = config.get_usize("max_copy_frame_bytes");
+    let users = UserTable::from_config(&config);
+    let mut query_logs = QueryLogs::from_config(&config);
+    loop {
+        let (sql, conn, query_id) = match req_que.pop_front() {
+            Task::Native(sql, conn, query_id) => (sql, conn, query_id),
+            Task::Collect{ sql, conn_id, user, query_id, result_sender } => {
+                let mut result = CollectedResult::new();
+                let admitted = run_dequeued(&processes, query_id, &manager, || {
+                    run_logged(&mut query_logs, conn_id, &sql, &mut result, &manager, |result_handler| {
+                        manager.borrow_mut().set_session_user(user);
+                        sql_handler(&sql, result_handler, &manager);
+                        manager.borrow_mut().set_session_user(None);
+                    });
+                });
+                if let Err(error) = admitted {
+                    result = CollectedResult::from_error(error);
+                }
+                result_sender.send(result).ok();  // the connection may have been closed
+                continue;
+            }
+            Task::Shutdown => break,
+        };
+        let authenticated = {
+            let mut c = conn.lock().unwrap();
+            c.transition_to_writing();
+            c.session.user.is_some() || users.is_none()
+        };
+        // killed or timed out while waiting for the statements before it
+        let mut admitted = match query_id.map(|query_id| (query_id, processes.dequeue(query_id))) {
+            Some((query_id, Ok(killed))) => Some((query_id, killed)),
+            Some((_, Err(error))) => {
+                let mut process = Process::new(conn.clone(), limit, stats.clone());
+                if is_copy_from_stdin(&sql) {
+                    run_copy_in(&conn, max_copy_frame_bytes, &manager, &mut process,
+                        |result_handler| result_handler.handle_error(vec![error]));
+                } else {
+                    process.handle_error(vec![error]);
+                }
+                continue;
+            }
+            None => None,
+        };
+        if !authenticated {
+            authenticate(&sql, users.as_ref().unwrap(), Process::new(conn, limit, stats.clone()));
+        } else if sql == "shutdown" {
+            shutdown_requested.store(true, Ordering::SeqCst);
+            Process::new(conn, limit, stats.clone()).handle_non_query_finished();
+        } else if sql == "show status" {
+            // the server counters before the ones of the storage
+            let out = format!("{}\n{}", stats.render(), manager.borrow().show_status());
+            let mut c = conn.lock().unwrap();
+            c.write_buffer(to_cstring(out).as_bytes());
+            c.change_to_finished_in_loop();
+        } else if let Ok(out) = process_table_command(&sql, &manager) {
+            let mut c = conn.lock().unwrap();
+            c.write_buffer(to_cstring(out).as_bytes());
+            c.change_to_finished_in_loop();
+        } else {
+            log_debug!("processing {:?}", sql);
+            // taken out of the connection, which is locked by the process when writing
+            let mut session = {
+                let mut c = conn.lock().unwrap();
+                let conn_id = c.token.0;
+                replace(&mut c.session, Session::new(conn_id))
+            };
+            match parse_batch_command(&sql) {
+                Some(ref batch) if !batch.statements.is_empty() => {
+                    // each statement of the batch is listed by itself
+                    if let Some((query_id, _)) = admitted.take() {
+                        processes.finish(query_id);
+                    }
+                    run_batch(batch, &mut session, &conn, limit, &stats, &processes, &mut query_logs,
+                        &manager);
+                }
+                Some(..) => Process::new(conn.clone(), limit, stats.clone()).handle_non_query_finished(),
+                None => {
+                    let mut process = Process::new(conn.clone(), limit, stats.clone());
+                    let conn_id = session.conn_id;
+                    let copy_in = is_copy_from_stdin(&sql);
+                    let run = || {
+                        let mut run_statement = |result_handler : &mut ResultHandler| {
+                            run_logged(&mut query_logs, conn_id, &sql, result_handler, &manager, |handler| {
+                                session_handler(&sql, &mut session, handler, &manager);
+                            });
+                        };
+                        if copy_in {
+                            run_copy_in(&conn, max_copy_frame_bytes, &manager, &mut process, run_statement);
+                        } else {
+                            run_statement(&mut process as &mut ResultHandler);
+                        }
+                    };
+                    match admitted.take() {
+                        Some((query_id, killed)) => run_killable(&processes, query_id, killed, &manager, run),
+                        None => run_tracked(&processes, conn_id, &sql, &manager, run),
+                    }
+                }
+            }
+            conn.lock().unwrap().session = session;
+        }
+        if let Some((query_id, _)) = admitted {
+            processes.finish(query_id);
+        }
+    }
+    // close ignores or panics on a failed write, the tables failed here are logged first
+    for error in manager.borrow_mut().flush_all() {
+        log_error!("flush on shutdown: {}", error);
+    }
+    manager.borrow_mut().close();
+}
+
+// a result for each statement, as if they were sent one by one
+fn run_batch(batch : &Batch, session : &mut Session, conn : &ConnRef, limit : StreamLimit,
+        stats : &ServerStatsRef, processes : &ProcessList, query_logs : &mut QueryLogs,
+        manager : &TableManagerRef) {
+    let mut failed = None;  // the first failed statement, counted from 1
+    for (i, sql) in batch.statements.iter().enumerate() {
+        let mut process = Process::new(conn.clone(), limit, stats.clone());
+        process.more_results = i + 1 < batch.statements.len();
+        if let (Some(failed), true) = (failed, batch.stop_on_error) {
+            process.handle_error(vec![ResultError::new(ResultErrorType::BatchStatementSkipped,
+                format!("skipped since statement {} of the batch failed", failed))]);
+            continue;
+        }
+        let conn_id = session.conn_id;
+        run_tracked(processes, conn_id, sql, manager, || {
+            run_logged(query_logs, conn_id, sql, &mut process, manager, |result_handler| {
+                session_handler(sql, session, result_handler, manager);
+            });
+        });
+        if process.failed && failed.is_none() {
+            failed = Some(i + 1);
+        }
+    }
+}
+
+fn authenticate(line : &String, users : &UserTable, mut process : Process) {
+    let error_msg = match parse_auth_command(line) {
+        Some((ref user, ref password)) if users.verify(user, password) => {
+            process.conn.lock().unwrap().session.user = Some(user.clone());
+            return process.handle_non_query_finished();
+        }
+        // not telling whether the user exists
+        Some(..) => "invalid user or password",
+        None => "authentication required, send `auth user password` first",
+    };
+    process.conn.lock().unwrap().close_after_write = true;
+    process.handle_error(vec![ResultError::new(ResultErrorType::AuthFailed, error_msg.to_string())]);
+}
+
+#[derive(Debug)]
+struct Process {
+    columns : Vec<ColumnInfo>,
+    attr_desc : Vec<AttrType>,
+    conn : ConnRef,
+    header_sended : bool,
+    limit : StreamLimit,
+    batch : Vec<u8>,  // the rows copied out of the pages
+    batch_rows : u32,
+    stats : ServerStatsRef,
+    more_results : bool,  // followed by the results of the batch, so the connection is not finished
+    failed : bool,
+}
+
+impl Process {
+    pub fn new(conn : ConnRef, limit : StreamLimit, stats : ServerStatsRef) -> Process {
+        Process{
+            columns : Vec::new(),
+            attr_desc : Vec::new(),
+            conn : conn,
+            header_sended : false,
+            limit : limit,
+            batch : Vec::new(),
+            batch_rows : 0,
+            stats : stats,
+            more_results : false,
+            failed : false,
+        }
+    }
+    fn send_batch(&mut self) {
+        if self.batch_rows == 0 {
+            return;
+        }
+        let count_bytes : [u8; 4] = unsafe{ transmute(self.batch_rows.to_le()) };
+        let mut c = self.conn.lock().unwrap();
+        c.write_buffer(&[ROW_BATCH_TAG]);
+        c.write_buffer(&count_bytes);
+        c.write_buffer(&self.batch);
+        self.batch.clear();
+        self.batch_rows = 0;
+        // backpressure, the plan waits for a slow client instead of buffering all the rows
+        while c.write_buf.remaining() > self.limit.max_buffered && !c.disconnected {
+            let drained = c.drained.clone();
+            c = drained.wait(c).unwrap();
+        }
+    }
+    fn send_header(&mut self) {
+        assert!(!self.header_sended);
+        let json_header = encode(&self.columns).unwrap();
+        let json_len = json_header.len() as u32;
+        let cstring = to_cstring(json_header);
+        let len_bytes : [u8; 4] = unsafe{ transmute(json_len.to_le()) };
+        {
+            let mut c = self.conn.lock().unwrap();
+            c.write_buffer(&len_bytes);
+            c.write_buffer(cstring.as_bytes());
+        }
+        self.header_sended = true;
+    }
+}
+
+impl ResultHandler for Process {
+    fn handle_non_query_finished(&mut self) {
+        let non_query_header_tag : [u8; 4] = [0, 0, 0, 0];
+        let mut c = self.conn.lock().unwrap();
+        c.write_buffer(&non_query_header_tag);
+        c.finish_result(self.more_results);
+    }
+    fn handle_complete(&mut self, summary : Summary) {
+        // after the end of the rows, or an empty column list for the other statements
+        if !self.header_sended {
+            self.send_header();
+        }
+        let count_bytes : [u8; 4] = unsafe{ transmute((summary.row_num as u32).to_le()) };
+        let mut c = self.conn.lock().unwrap();
+        c.write_buffer(&[summary.kind.code()]);
+        c.write_buffer(&count_bytes);
+        c.finish_result(self.more_results);
+    }
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        // only the code of the first error is sent, the message has all of them
+        self.failed = true;
+        let code_bytes : [u8; 4] = unsafe{ transmute(errors[0].code().to_le()) };
+        let cstring = to_cstring(render_errors(&errors));
+        // the rows before the error are still sent
+        self.send_batch();
+        let mut c = self.conn.lock().unwrap();
+        if self.header_sended {
+            // failed in the middle of the rows
+            c.write_buffer(&[ERROR_TAG]);
+        } else {
+            let error_header_tag : [u8; 4] = [0, 0, 0, 0];
+            c.write_buffer(&error_header_tag);
+        }
+        c.write_buffer(&code_bytes);
+        c.write_buffer(cstring.as_bytes());
+        c.finish_result(self.more_results);
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        if !self.header_sended {
+            self.send_header();
+        }
+        match row {
+            Some(row) => {
+                assert_eq!(self.attr_desc.len(), row.len());
+                for (attr, value) in self.attr_desc.iter().zip(row.iter()) {
+                    encode_value(attr, value, &mut self.batch);
+                }
+                self.batch_rows += 1;
+                if self.batch.len() >= self.limit.batch_bytes {
+                    self.send_batch();
+                }
+            }
+            None => {
+                self.send_batch();
+                self.conn.lock().unwrap().write_buffer(&[END_TAG]);
+            }
+        }
+    }
+    fn handle_warning(&mut self, msg : String) {
+        // the warnings follow the header, such as the one of a fetch with no rows left
+        if !self.header_sended {
+            self.send_header();
+        }
+        self.send_batch();
+        self.stats.warning_sent();
+        let cstring = to_cstring(msg);
+        let len_bytes : [u8; 4] = unsafe{ transmute((cstring.as_bytes().len() as u32).to_le()) };
+        let mut c = self.conn.lock().unwrap();
+        c.write_buffer(&[WARNING_TAG]);
+        c.write_buffer(&len_bytes);
+        c.write_buffer(cstring.as_bytes());
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.columns = columns.to_vec();
+        self.attr_desc = columns.iter().map(|c| c.attr_type.clone()).collect();
+    }
+}
+
+// the same bytes as in the page: 4 bytes for int and float, char padded with 0 to its length,
+// and null as zero bytes
+fn encode_value(attr : &AttrType, value : &TupleValue, buf : &mut Vec<u8>) {
+    let bytes : [u8; 4] = match (attr, value) {
+        (_, &TupleValue::Int(n)) => unsafe{ transmute(n.to_le()) },
+        (_, &TupleValue::Float(f)) => {
+            let bits : u32 = unsafe{ transmute(f) };
+            unsafe{ transmute(bits.to_le()) }
+        }
+        (&AttrType::Char{len}, &TupleValue::Char(ref s)) => {
+            let bytes = &s.as_bytes()[..min(s.len(), len)];
+            buf.extend_from_slice(bytes);
+            buf.extend(repeat(0).take(len - bytes.len()));
+            return;
+        }
+        (&AttrType::Char{len}, _) => return buf.extend(repeat(0).take(len)),
+        (_, _) => [0; 4],
+    };
+    buf.extend_from_slice(&bytes);
+}
+
+pub fn run_server(config : Config) {
+    init_log(&config);
+    catch_stop_signal();
+    let server = launch_server(config);
+    println!("listening on {}", server.addr);
+    if let Some(tls_addr) = server.tls_addr {
+        println!("listening on {} for tls clients", tls_addr);
+    }
+    if let Some(mysql_addr) = server.mysql_addr {
+        println!("listening on {} for mysql clients", mysql_addr);
+    }
+    if let Some(http_addr) = server.http_addr {
+        println!("listening on {} for http requests", http_addr);
+    }
+    is_match!(server.join_handle.join(), Ok(..));
+}
+
+pub fn start_server(config : Config) -> (SocketAddr, JoinHandle<()>) {
+    let server = launch_server(config);
+    (server.addr, server.join_handle)
+}
+
+#[derive(Debug)]
+pub struct RunningServer {
+    pub addr : SocketAddr,
+    pub tls_addr : Option<SocketAddr>,  // also `addr` when plaintext_enabled is false
+    pub mysql_addr : Option<SocketAddr>,
+    pub http_addr : Option<SocketAddr>,
+    pub join_handle : JoinHandle<()>,  // of the native protocol, which stops the worker
+}
+
+struct TlsListener {
+    listener : Option<TcpListener>,
+    context : Option<TlsContext>,
+    plaintext : bool,
+}
+
+// With tls_enabled the tls clients connect to tls_port,
+// or to `port` when plaintext_enabled is false, and no plaintext listener is opened.
+fn bind_tls_listener(config : &Config, host : &str) -> TlsListener {
+    let plaintext = config.get_bool("plaintext_enabled");
+    if !config.get_bool("tls_enabled") {
+        assert!(plaintext, "plaintext_enabled = false needs tls_enabled");
+        return TlsListener{ listener : None, context : None, plaintext : true };
+    }
+    let cert_file = config.get_str("tls_cert_file");
+    let key_file = config.get_str("tls_key_file");
+    let context = match load_tls_context(&cert_file, &key_file) {
+        Ok(context) => context,
+        Err(e) => panic!("can't load the tls certificate: {}", e),
+    };
+    let listener = if plaintext {
+        let addr = format!("{}:{}", host, config.get_usize("tls_port")).parse().unwrap();
+        Some(TcpListener::bind(&addr).unwrap())
+    } else {
+        None
+    };
+    TlsListener{ listener : listener, context : Some(context), plaintext : plaintext }
+}
+
+pub fn launch_server(config : Config) -> RunningServer {
+    // port 0 picks a free port, the address bound is returned
+    let host = config.get_str("host");
+    let port = config.get_usize("port");
+    let addr = format!("{}:{}", host, port).parse().unwrap();
+    let listener = TcpListener::bind(&addr).unwrap();
+    let local_addr = listener.local_addr().unwrap();
+    let tls = bind_tls_listener(&config, &host);
+    let tls_addr = match tls.listener {
+        Some(ref tls_listener) => Some(tls_listener.local_addr().unwrap()),
+        None if !tls.plaintext => Some(local_addr),
+        None => None,
+    };
+    let q = BlockingQueueRef::new(BlockingQueue::new(64));
+    // shared by the protocols, which queue their statements for the worker
+    let processes = ProcessList::from_config(&config);
+    let mysql_addr = start_mysql_listener(&config, q.clone(), processes.clone());
+    let http_addr = start_http_listener(&config, q.clone(), processes.clone());
+    // the same protocol and authentication as tcp, for the local clients
+    let unix_path = config.get_str("unix_socket_path");
+    let unix_listener = if unix_path.is_empty() {
+        None
+    } else {
+        match bind_unix_listener(&unix_path) {
+            Ok(unix_listener) => Some((unix_listener, unix_path)),
+            Err(e) => panic!("can't listen on unix socket {}: {}", unix_path, e),
+        }
+    };
+    let server = spawn(move || {
+        let mut event_loop = EventLoop::new().unwrap();
+        event_loop.register(&listener, SERVER, EventSet::readable(),
+                            PollOpt::level()).unwrap();
+        if let Some((ref unix_listener, _)) = unix_listener {
+            event_loop.register(unix_listener, UNIX_SERVER, EventSet::readable(),
+                                PollOpt::level()).unwrap();
+        }
+        if let Some(ref tls_listener) = tls.listener {
+            event_loop.register(tls_listener, TLS_SERVER, EventSet::readable(),
+                                PollOpt::level()).unwrap();
+        }
+        let mut sqlserver = SqlServer::new(listener, unix_listener, tls, config, q, processes);
+        event_loop.timeout_ms((), SHUTDOWN_CHECK_INTERVAL_MS).unwrap();
+        event_loop.run(&mut sqlserver).unwrap();
+    });
+    RunningServer{
+        addr : local_addr,
+        tls_addr : tls_addr,
+        mysql_addr : mysql_addr,
+        http_addr : http_addr,
+        join_handle : server,
+    }
+}
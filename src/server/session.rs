@@ -0,0 +1,228 @@
+use ::parser::lexer::{Token, TokenLine, TokenType, split_statements};
+use ::parser::variable::VariableCommand;
+use ::store::table::{TableManagerRef, AttrType};
+use ::store::tuple::TupleValue;
+use super::handler::{ResultHandler, PreparedStatements, ColumnInfo, Summary, StatementKind};
+use super::handler::{sql_handler, process_prepare_command, process_show_buffer, process_show_stats};
+use super::handler::{process_explain_analyze, process_flush, process_grant_command};
+use super::result_error::{ResultError, ResultErrorType, from_compile_errors};
+use super::format_result::OutputFormat;
+use super::cursor::{Cursors, process_cursor_command};
+
+
+// The state of a connection carried across its statements, used by the worker only.
+// There are no transactions in the storage yet, every statement commits when it finishes.
+#[derive(Debug)]
+pub struct Session {
+    pub conn_id : usize,
+    pub user : Option<String>,  // set by authentication, None without users_file
+    pub prepared : PreparedStatements,
+    pub cursors : Cursors,
+    pub autocommit : bool,
+    pub output_format : OutputFormat,  // only the local client formats the results itself
+    pub query_timeout_ms : Option<usize>,  // None for query_timeout_ms of the config
+    pub max_result_rows : Option<usize>,  // None for max_result_rows of the config
+    pub null_string : Option<String>,  // None for the default of output_format
+}
+
+#[derive(Debug, Copy, Clone)]
+enum VariableType {
+    Bool,
+    Int{ min : i64, max : i64 },
+    Name(&'static [&'static str]),
+    Text{ max_len : usize },
+}
+
+// the variables of `set`, in the order of `show variables`
+const VARIABLES : [(&'static str, VariableType); 5] = [
+    ("autocommit", VariableType::Bool),
+    // the rows of a select after it are dropped with a warning, 0 for no limit
+    ("max_result_rows", VariableType::Int{ min : 0, max : 2147483647 }),
+    // how the local client writes null in table, csv and tsv, json always has null
+    ("null_string", VariableType::Text{ max_len : 16 }),
+    ("output_format", VariableType::Name(&["table", "csv", "tsv", "json"])),
+    // in milliseconds, 0 for no timeout
+    ("statement_timeout", VariableType::Int{ min : 0, max : 86400000 }),
+];
+
+#[derive(Debug)]
+enum VariableValue {
+    Bool(bool),
+    Int(i64),
+    Name(String),
+    Text(String),
+}
+
+fn parse_variable_value(name : &str, var_type : VariableType, token : &Token)
+        -> Result<VariableValue, String> {
+    let value = token.value.to_lowercase();
+    match var_type {
+        VariableType::Bool => match value.as_ref() {
+            "true" | "on" | "1" => Ok(VariableValue::Bool(true)),
+            "false" | "off" | "0" => Ok(VariableValue::Bool(false)),
+            _ => Err(format!("expect true or false for {}, but got {}", name, token.value)),
+        },
+        VariableType::Int{min, max} => match value.parse::<i64>() {
+            Ok(n) if token.token_type == TokenType::IntegerLiteral && min <= n && n <= max =>
+                Ok(VariableValue::Int(n)),
+            _ => Err(format!("expect an integer from {} to {} for {}, but got {}",
+                min, max, name, token.value)),
+        },
+        VariableType::Name(names) if names.contains(&value.as_ref()) => Ok(VariableValue::Name(value)),
+        VariableType::Name(names) =>
+            Err(format!("expect one of {} for {}, but got {}", names.join(", "), name, token.value)),
+        VariableType::Text{max_len} if token.value.len() <= max_len =>
+            Ok(VariableValue::Text(token.value.clone())),
+        VariableType::Text{max_len} =>
+            Err(format!("expect at most {} chars for {}, but got {}", max_len, name, token.value)),
+    }
+}
+
+impl Session {
+    pub fn new(conn_id : usize) -> Session {
+        Session{
+            conn_id : conn_id,
+            user : None,
+            prepared : PreparedStatements::new(),
+            cursors : Cursors::new(),
+            autocommit : true,
+            output_format : OutputFormat::Table,
+            query_timeout_ms : None,
+            max_result_rows : None,
+            null_string : None,
+        }
+    }
+
+    // when the client disconnects
+    pub fn close(&mut self) {
+        self.prepared.clear();
+        self.cursors.clear();
+    }
+
+    pub fn set_variable(&mut self, name : &str, token : &Token) -> Result<(), ResultError> {
+        let var_type = match VARIABLES.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, var_type)) => var_type,
+            None => return Err(ResultError::new(ResultErrorType::UnknownVariable,
+                format!("unknown variable {}", name))),
+        };
+        let invalid = |msg : String| ResultError::new(ResultErrorType::InvalidVariableValue, msg);
+        match (name, try!(parse_variable_value(name, var_type, token).map_err(&invalid))) {
+            ("autocommit", VariableValue::Bool(true)) => self.autocommit = true,
+            ("autocommit", VariableValue::Bool(false)) => return Err(invalid(
+                "autocommit can't be false, there are no transactions yet".to_string())),
+            ("max_result_rows", VariableValue::Int(n)) => self.max_result_rows = Some(n as usize),
+            ("null_string", VariableValue::Text(ref s)) => self.null_string = Some(s.clone()),
+            ("output_format", VariableValue::Name(ref format)) =>
+                self.output_format = OutputFormat::from_name(format).unwrap(),
+            ("statement_timeout", VariableValue::Int(ms)) => self.query_timeout_ms = Some(ms as usize),
+            other => panic!("invalid variable {:?}", other),
+        }
+        Ok(())
+    }
+
+    // the current values, statement_timeout and max_result_rows fall back to the config
+    pub fn show_variables(&self, default_timeout_ms : usize, default_max_rows : usize)
+            -> Vec<(String, String)> {
+        VARIABLES.iter().map(|&(name, _)| {
+            let value = match name {
+                "autocommit" => self.autocommit.to_string(),
+                "max_result_rows" => self.max_result_rows.unwrap_or(default_max_rows).to_string(),
+                "null_string" => self.null_string.clone()
+                    .unwrap_or(self.output_format.default_null_string().to_string()),
+                "output_format" => self.output_format.name().to_string(),
+                "statement_timeout" => self.query_timeout_ms.unwrap_or(default_timeout_ms).to_string(),
+                _ => panic!("invalid variable {}", name),
+            };
+            (name.to_string(), value)
+        }).collect()
+    }
+}
+
+pub fn process_variable_command(input : &String, session : &mut Session, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) -> Result<(), ()> {
+    let line = TokenLine::parse(input);
+    let command = match VariableCommand::parse(&line.tokens) {
+        Some(command) => command,
+        None => return Err(()),
+    };
+    if line.errors.len() > 0 {
+        result_handler.handle_error(from_compile_errors(&line.errors));
+        return Ok(());
+    }
+    match command {
+        Err(ref err_list) => result_handler.handle_error(from_compile_errors(err_list)),
+        Ok(VariableCommand::Set{name, value}) => match session.set_variable(&name, &value) {
+            Ok(()) => result_handler.handle_non_query_finished(),
+            Err(err) => result_handler.handle_error(vec![err]),
+        },
+        Ok(VariableCommand::ShowVariables) => {
+            // a result of two columns, as a select
+            let variables = {
+                let manager = manager.borrow();
+                session.show_variables(manager.get_query_timeout_ms(), manager.get_config_max_result_rows())
+            };
+            let columns : Vec<ColumnInfo> = ["name", "value"].iter().map(|name| ColumnInfo{
+                name : name.to_string(),
+                attr_type : AttrType::Char{ len : 32 },
+                nullable : false,
+            }).collect();
+            result_handler.handle_metadata(&columns);
+            let row_num = variables.len();
+            for (name, value) in variables.into_iter() {
+                result_handler.handle_row(Some(vec![TupleValue::Char(name), TupleValue::Char(value)]));
+            }
+            result_handler.handle_row(None);
+            result_handler.handle_complete(Summary::new(StatementKind::Select, row_num));
+        }
+    }
+    Ok(())
+}
+
+// run a statement of the session, with its prepared statements and settings
+pub fn session_handler(input : &String, session : &mut Session, result_handler : &mut ResultHandler,
+        manager : &TableManagerRef) {
+    manager.borrow_mut().set_session_timeout(session.query_timeout_ms);
+    manager.borrow_mut().set_session_max_result_rows(session.max_result_rows);
+    manager.borrow_mut().set_session_user(session.user.clone());
+    let handled = process_variable_command(input, session, result_handler, manager).is_ok()
+        || process_cursor_command(input, &mut session.cursors, result_handler, manager).is_ok()
+        || process_prepare_command(input, &mut session.prepared, result_handler, manager).is_ok()
+        || process_show_buffer(input, result_handler, manager).is_ok()
+        || process_show_stats(input, result_handler, manager).is_ok()
+        || process_explain_analyze(input, result_handler, manager).is_ok()
+        || process_flush(input, result_handler, manager).is_ok()
+        || process_grant_command(input, result_handler, manager).is_ok();
+    if !handled {
+        sql_handler(input, result_handler, manager);
+    }
+    manager.borrow_mut().set_session_timeout(None);
+    manager.borrow_mut().set_session_max_result_rows(None);
+    manager.borrow_mut().set_session_user(None);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Batch {
+    pub stop_on_error : bool,  // the statements after a failed one are skipped
+    pub statements : Vec<String>,
+}
+
+// `batch [stop_on_error] statement; statement; ...`, the statements run in order in the session
+pub fn parse_batch_command(input : &str) -> Option<Batch> {
+    let input = input.trim();
+    if input != "batch" && !input.starts_with("batch ") {
+        return None;
+    }
+    let mut rest = input["batch".len()..].trim_left();
+    let stop_on_error = rest == "stop_on_error" || rest.starts_with("stop_on_error ");
+    if stop_on_error {
+        rest = &rest["stop_on_error".len()..];
+    }
+    Some(Batch{ stop_on_error : stop_on_error, statements : split_statements(rest) })
+}
+
+// `copy t from stdin ...`, the frames of its data follow the line even if the statement fails
+pub fn is_copy_from_stdin(input : &str) -> bool {
+    let tokens = TokenLine::parse(input).tokens;
+    tokens.len() >= 4 && tokens[0].token_type == TokenType::Copy && tokens[2].token_type == TokenType::From
+        && tokens[3].token_type == TokenType::Identifier && tokens[3].value.to_lowercase() == "stdin"
+}
@@ -0,0 +1,231 @@
+use std::io;
+use std::io::{Read, Write};
+use std::fs::{metadata, remove_file, set_permissions, Permissions};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixStream as BlockingUnixStream;
+use mio::{Evented, Selector, Token, EventSet, PollOpt, TryAccept};
+use mio::tcp::TcpStream;
+use mio::unix::{UnixListener, UnixStream};
+#[cfg(feature = "tls")]
+use std::mem::replace;
+#[cfg(feature = "tls")]
+use openssl::ssl::{SslAcceptor, SslMethod, SslFiletype, SslStream, MidHandshakeSslStream, HandshakeError};
+
+
+// The native protocol is the same over tcp, tls and unix sockets,
+// the connections only see the stream through Read, Write and Evented.
+#[derive(Debug)]
+pub enum Socket {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+// the certificate and the key of the server
+#[cfg(feature = "tls")]
+pub type TlsContext = SslAcceptor;
+
+// built without the tls feature, so there is never one
+#[cfg(not(feature = "tls"))]
+#[derive(Debug)]
+pub enum TlsContext {}
+
+// The handshake goes on in the reads and writes of the connection, as the socket is non-blocking.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+pub enum TlsStream {
+    Handshaking(MidHandshakeSslStream<TcpStream>),
+    Ready(SslStream<TcpStream>),
+    Failed(MidHandshakeSslStream<TcpStream>),  // kept until the connection is closed and deregistered
+    Taken,  // while the handshake is being continued
+}
+
+#[cfg(feature = "tls")]
+impl TlsStream {
+    fn from_handshake(result : Result<SslStream<TcpStream>, HandshakeError<TcpStream>>) -> TlsStream {
+        match result {
+            Ok(stream) => TlsStream::Ready(stream),
+            Err(HandshakeError::WouldBlock(stream)) => TlsStream::Handshaking(stream),
+            Err(HandshakeError::Failure(stream)) => TlsStream::Failed(stream),
+            Err(HandshakeError::SetupFailure(..)) => TlsStream::Taken,
+        }
+    }
+    fn get_ref(&self) -> &TcpStream {
+        match self {
+            &TlsStream::Handshaking(ref stream) | &TlsStream::Failed(ref stream) => stream.get_ref(),
+            &TlsStream::Ready(ref stream) => stream.get_ref(),
+            &TlsStream::Taken => panic!("the tls stream is taken"),
+        }
+    }
+    // the stream once the handshake is done, or WouldBlock until the client sends more
+    fn get_ready(&mut self) -> io::Result<&mut SslStream<TcpStream>> {
+        if let &mut TlsStream::Handshaking(..) = self {
+            *self = match replace(self, TlsStream::Taken) {
+                TlsStream::Handshaking(stream) => TlsStream::from_handshake(stream.handshake()),
+                _ => unreachable!(),
+            };
+        }
+        match self {
+            &mut TlsStream::Ready(ref mut stream) => Ok(stream),
+            &mut TlsStream::Handshaking(..) => Err(io::Error::new(io::ErrorKind::WouldBlock, "tls handshake")),
+            // handled as a connection closed by the client
+            &mut TlsStream::Failed(ref stream) => Err(io::Error::new(io::ErrorKind::ConnectionAborted,
+                format!("tls handshake failed: {}", stream.error()))),
+            &mut TlsStream::Taken => Err(io::Error::new(io::ErrorKind::ConnectionAborted,
+                "tls handshake failed")),
+        }
+    }
+}
+
+impl From<TcpStream> for Socket {
+    fn from(stream : TcpStream) -> Socket {
+        Socket::Tcp(stream)
+    }
+}
+
+impl From<UnixStream> for Socket {
+    fn from(stream : UnixStream) -> Socket {
+        Socket::Unix(stream)
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf : &mut [u8]) -> io::Result<usize> {
+        match self {
+            &mut Socket::Tcp(ref mut stream) => stream.read(buf),
+            &mut Socket::Unix(ref mut stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            &mut Socket::Tls(ref mut stream) => from_tls_result(try!(stream.get_ready()).read(buf)),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf : &[u8]) -> io::Result<usize> {
+        match self {
+            &mut Socket::Tcp(ref mut stream) => stream.write(buf),
+            &mut Socket::Unix(ref mut stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            &mut Socket::Tls(ref mut stream) => from_tls_result(try!(stream.get_ready()).write(buf)),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            &mut Socket::Tcp(ref mut stream) => stream.flush(),
+            &mut Socket::Unix(ref mut stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            &mut Socket::Tls(ref mut stream) => match stream {
+                &mut TlsStream::Ready(ref mut stream) => stream.flush(),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl Evented for Socket {
+    fn register(&self, selector : &mut Selector, token : Token, interest : EventSet, opts : PollOpt) -> io::Result<()> {
+        match self {
+            &Socket::Tcp(ref stream) => stream.register(selector, token, interest, opts),
+            &Socket::Unix(ref stream) => stream.register(selector, token, interest, opts),
+            #[cfg(feature = "tls")]
+            &Socket::Tls(ref stream) => stream.get_ref().register(selector, token, interest, opts),
+        }
+    }
+    fn reregister(&self, selector : &mut Selector, token : Token, interest : EventSet, opts : PollOpt) -> io::Result<()> {
+        match self {
+            &Socket::Tcp(ref stream) => stream.reregister(selector, token, interest, opts),
+            &Socket::Unix(ref stream) => stream.reregister(selector, token, interest, opts),
+            #[cfg(feature = "tls")]
+            &Socket::Tls(ref stream) => stream.get_ref().reregister(selector, token, interest, opts),
+        }
+    }
+    fn deregister(&self, selector : &mut Selector) -> io::Result<()> {
+        match self {
+            &Socket::Tcp(ref stream) => stream.deregister(selector),
+            &Socket::Unix(ref stream) => stream.deregister(selector),
+            #[cfg(feature = "tls")]
+            &Socket::Tls(ref stream) => stream.get_ref().deregister(selector),
+        }
+    }
+}
+
+pub fn accept_socket<L : TryAccept>(listener : &L) -> io::Result<Option<Socket>>
+        where L::Output : Into<Socket> {
+    listener.accept().map(|stream| stream.map(Into::into))
+}
+
+// The listeners are level triggered on the tcp socket, so a record larger than the read buffer
+// leaves the rest of its data in the ssl buffer until the client sends more;
+// the statements of the clients are far smaller than the buffer.
+#[cfg(feature = "tls")]
+fn from_tls_result(result : io::Result<usize>) -> io::Result<usize> {
+    match result {
+        Ok(n) => Ok(n),
+        Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Err(io::Error::new(io::ErrorKind::WouldBlock,
+            "tls wants more data")),
+        // handled as a connection closed by the client
+        Err(err) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, format!("{}", err))),
+    }
+}
+
+#[cfg(feature = "tls")]
+pub fn load_tls_context(cert_file : &str, key_file : &str) -> Result<TlsContext, String> {
+    let mut builder = try!(SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
+        .map_err(|e| format!("{}", e)));
+    try!(builder.set_certificate_file(cert_file, SslFiletype::PEM)
+        .map_err(|e| format!("invalid certificate {}: {}", cert_file, e)));
+    try!(builder.set_private_key_file(key_file, SslFiletype::PEM)
+        .map_err(|e| format!("invalid private key {}: {}", key_file, e)));
+    try!(builder.check_private_key()
+        .map_err(|e| format!("the key does not match the certificate: {}", e)));
+    Ok(builder.build())
+}
+
+#[cfg(not(feature = "tls"))]
+pub fn load_tls_context(_cert_file : &str, _key_file : &str) -> Result<TlsContext, String> {
+    Err("the server is built without the tls feature".to_string())
+}
+
+// before the first statement of the connection
+#[cfg(feature = "tls")]
+pub fn start_tls(socket : Socket, context : &TlsContext) -> io::Result<Socket> {
+    match socket {
+        Socket::Tcp(stream) => match context.accept(stream) {
+            Err(HandshakeError::SetupFailure(err)) => Err(io::Error::new(io::ErrorKind::ConnectionAborted,
+                format!("{}", err))),
+            // a failure of the first step is returned by the first read
+            result => Ok(Socket::Tls(TlsStream::from_handshake(result))),
+        },
+        other => Ok(other),
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+pub fn start_tls(_socket : Socket, context : &TlsContext) -> io::Result<Socket> {
+    match *context {}
+}
+
+// A socket file left by a server that was killed is removed,
+// but not a socket still accepting connections or a file that is not a socket.
+pub fn bind_unix_listener(path : &str) -> io::Result<UnixListener> {
+    if let Ok(meta) = metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is not a socket", path)));
+        }
+        if BlockingUnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is used by another server", path)));
+        }
+        try!(remove_file(path));
+    }
+    let listener = try!(UnixListener::bind(path));
+    // only the user running the server
+    try!(set_permissions(path, Permissions::from_mode(0o600)));
+    Ok(listener)
+}
+
+pub fn remove_unix_socket(path : &str) {
+    if let Err(e) = remove_file(path) {
+        log_warn!("failed to remove the unix socket {}; err={:?}", path, e);
+    }
+}
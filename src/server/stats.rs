@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+
+pub type ServerStatsRef = Arc<ServerStats>;
+
+// updated by the event loop and the worker
+#[derive(Debug)]
+pub struct ServerStats {
+    start : Instant,
+    connections : AtomicUsize,  // open now
+    rejected_connections : AtomicUsize,
+    warnings : AtomicUsize,  // the results truncated by max_result_rows
+}
+
+impl ServerStats {
+    pub fn new() -> ServerStats {
+        ServerStats{
+            start : Instant::now(),
+            connections : AtomicUsize::new(0),
+            rejected_connections : AtomicUsize::new(0),
+            warnings : AtomicUsize::new(0),
+        }
+    }
+    pub fn make_ref() -> ServerStatsRef {
+        Arc::new(ServerStats::new())
+    }
+    pub fn connection_opened(&self) {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+    }
+    pub fn connection_closed(&self) {
+        self.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::SeqCst);
+    }
+    pub fn warning_sent(&self) {
+        self.warnings.fetch_add(1, Ordering::SeqCst);
+    }
+    // a `name value` line for each counter
+    pub fn render(&self) -> String {
+        let counters = vec![
+            ("connections", &self.connections),
+            ("rejected_connections", &self.rejected_connections),
+            ("warnings", &self.warnings),
+        ];
+        let mut lines : Vec<String> = counters.iter()
+            .map(|&(name, counter)| format!("{} {}", name, counter.load(Ordering::SeqCst)))
+            .collect();
+        lines.push(format!("uptime_s {}", self.start.elapsed().as_secs()));
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,224 @@
+use std::io;
+use std::fs::{OpenOptions, File, read_dir, rename, remove_file};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::BTreeMap;
+use rustc_serialize::json::{encode, decode};
+use ::utils::checksum::{crc32, crc32_update};
+use ::utils::file::{path_join, ensure_dir_exist};
+use ::utils::libwrapper::get_page_size;
+use super::table::Table;
+use super::file::{get_page_offset, read_file_header};
+
+
+pub const MANIFEST_NAME : &'static str = "backup_manifest.json";
+pub const BACKUP_VERSION : u32 = 1;  // increase it when the file format changes
+
+#[derive(Debug, Clone, PartialEq, Eq, RustcDecodable, RustcEncodable)]
+pub struct BackupFile {
+    pub name : String,  // relative to the backup directory
+    pub size : u64,
+    pub checksum : u32,
+}
+
+// Written after all the other files, a backup without a valid manifest is partial.
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct BackupManifest {
+    pub version : u32,
+    pub page_size : usize,  // of the os, the table files saved without their page size are read with it
+    pub files : Vec<BackupFile>,
+}
+
+impl BackupManifest {
+    pub fn new(files : Vec<BackupFile>) -> BackupManifest {
+        BackupManifest{
+            version : BACKUP_VERSION,
+            page_size : get_page_size(),
+            files : files,
+        }
+    }
+    pub fn get_file(&self, name : &str) -> Option<&BackupFile> {
+        self.files.iter().filter(|f| f.name == name).next()
+    }
+}
+
+pub fn prepare_backup_dir(dir : &String) -> Result<(), String> {
+    // refuse to mix the snapshot with existing files
+    if Path::new(dir).exists() {
+        let mut entries = match read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => return Err(format!("can't open {:?}: {}", dir, err)),
+        };
+        if entries.next().is_some() {
+            return Err(format!("{:?} is not empty", dir));
+        }
+    }
+    ensure_dir_exist(dir);
+    Ok(())
+}
+
+pub fn write_backup_file(dir : &String, name : &str, data : &[u8]) -> io::Result<BackupFile> {
+    let path = path_join(dir, &name.to_string());
+    let mut file = try!(OpenOptions::new().write(true).create_new(true).open(&path));
+    try!(file.write_all(data));
+    try!(file.sync_data());
+    Ok(BackupFile{
+        name : name.to_string(),
+        size : data.len() as u64,
+        checksum : crc32(data),
+    })
+}
+
+pub fn copy_backup_file(src : &String, dir : &String, name : &str) -> io::Result<BackupFile> {
+    let path = path_join(dir, &name.to_string());
+    let dst_file = try!(OpenOptions::new().write(true).create_new(true).open(&path));
+    let (size, checksum) = try!(copy_file(src, dst_file));
+    Ok(BackupFile{
+        name : name.to_string(),
+        size : size,
+        checksum : checksum,
+    })
+}
+
+// Copy a file of the backup next to dst and return the path of the copy, for the caller to rename it
+// over dst. The copy is checked against the manifest again, the backup may be changed after it's verified.
+pub fn stage_backup_file(dir : &String, name : &str, manifest : &BackupManifest, dst : &String)
+        -> Result<String, String> {
+    let (size, checksum) = match manifest.get_file(name) {
+        Some(backup_file) => (backup_file.size, backup_file.checksum),
+        None => return Err(format!("{}: not found in the manifest", name)),
+    };
+    let src = path_join(dir, &name.to_string());
+    let staged_path = format!("{}.restore", dst);
+    let result = OpenOptions::new().write(true).create(true).truncate(true).open(&staged_path)
+        .and_then(|staged_file| copy_file(&src, staged_file));
+    let err = match result {
+        Ok(copied) if copied == (size, checksum) => return Ok(staged_path),
+        Ok(..) => format!("{}: changed after it's verified", name),
+        Err(err) => format!("{}: can't copy: {}", name, err),
+    };
+    remove_file(&staged_path).ok();
+    Err(err)
+}
+
+fn copy_file(src : &String, mut dst_file : File) -> io::Result<(u64, u32)> {
+    // return the size and checksum of the copied data
+    let mut src_file = try!(File::open(src));
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0;
+    let mut checksum = 0;
+    loop {
+        let len = match src_file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        try!(dst_file.write_all(&buf[..len]));
+        checksum = crc32_update(checksum, &buf[..len]);
+        size += len as u64;
+    }
+    try!(dst_file.sync_data());
+    Ok((size, checksum))
+}
+
+pub fn write_manifest(dir : &String, manifest : &BackupManifest) -> io::Result<()> {
+    // rename is atomic, so the manifest is either missing or complete
+    let json_str = encode(manifest).unwrap();
+    let tmp_path = path_join(dir, &format!("{}.tmp", MANIFEST_NAME));
+    {
+        let mut file = try!(OpenOptions::new().write(true).create(true).truncate(true).open(&tmp_path));
+        try!(file.write_all(json_str.as_bytes()));
+        try!(file.sync_data());
+    }
+    rename(&tmp_path, path_join(dir, &MANIFEST_NAME.to_string()))
+}
+
+pub fn read_manifest(dir : &String) -> Result<BackupManifest, String> {
+    let path = Path::new(dir).join(MANIFEST_NAME);
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => return Err(format!("can't open manifest of {:?}: {}", dir, err)),
+    };
+    let mut json_str = String::new();
+    if let Err(err) = file.read_to_string(&mut json_str) {
+        return Err(format!("can't read manifest of {:?}: {}", dir, err));
+    }
+    match decode(&json_str) {
+        Ok(manifest) => Ok(manifest),
+        Err(err) => Err(format!("invalid manifest of {:?}: {:?}", dir, err)),
+    }
+}
+
+pub fn verify_backup(dir : &String) -> Result<BackupManifest, String> {
+    // check all the files and report every mismatch, one line for each file
+    let manifest = try!(read_manifest(dir));
+    let mut errors = Vec::new();
+    for backup_file in manifest.files.iter() {
+        let path = Path::new(dir).join(&backup_file.name);
+        let mut data = Vec::new();
+        if let Err(err) = File::open(&path).and_then(|mut f| f.read_to_end(&mut data)) {
+            errors.push(format!("{}: can't read: {}", backup_file.name, err));
+        } else if data.len() as u64 != backup_file.size {
+            errors.push(format!("{}: expected {} bytes, found {}",
+                backup_file.name, backup_file.size, data.len()));
+        } else if crc32(&data) != backup_file.checksum {
+            errors.push(format!("{}: checksum mismatch", backup_file.name));
+        }
+    }
+    if errors.is_empty() {
+        Ok(manifest)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+pub fn check_manifest_format(manifest : &BackupManifest) -> Result<(), String> {
+    if manifest.version != BACKUP_VERSION {
+        return Err(format!("{}: unsupported backup version {}, expected {}",
+            MANIFEST_NAME, manifest.version, BACKUP_VERSION));
+    }
+    if manifest.page_size != get_page_size() {
+        return Err(format!("{}: page size {} does not match {}",
+            MANIFEST_NAME, manifest.page_size, get_page_size()));
+    }
+    Ok(())
+}
+
+pub fn read_backup_catalog(dir : &String) -> Result<BTreeMap<String, Table>, String> {
+    let path = Path::new(dir).join("table_meta.json");
+    let mut json_str = String::new();
+    if let Err(err) = File::open(&path).and_then(|mut f| f.read_to_string(&mut json_str)) {
+        return Err(format!("table_meta.json: can't read: {}", err));
+    }
+    match decode(&json_str) {
+        Ok(tables) => Ok(tables),
+        Err(err) => Err(format!("table_meta.json: invalid catalog: {:?}", err)),
+    }
+}
+
+pub fn check_table_file(dir : &String, table : &str, manifest : &BackupManifest) -> Result<(), String> {
+    // the checksum is verified already, check whether the header matches the file
+    let name = format!("{}.table", table);
+    let size = match manifest.get_file(&name) {
+        Some(backup_file) => backup_file.size,
+        None => return Err(format!("{}: not found in the manifest", name)),
+    };
+    let path = Path::new(dir).join(&name);
+    let header = match File::open(&path) {
+        Ok(file) => read_file_header(&file),
+        Err(err) => Err(err.to_string()),
+    };
+    let (page_sum, first_free_page, page_size) = match header {
+        Ok(header) => (header.page_sum, header.first_free_page, header.page_size),
+        Err(err) => return Err(format!("{}: {}", name, err)),
+    };
+    if first_free_page > page_sum {
+        return Err(format!("{}: invalid file header", name));
+    }
+    // the last page may be compressed and shorter than a page
+    if page_sum > 0 && size <= get_page_offset(page_sum - 1, page_size) {
+        return Err(format!("{}: expected {} pages, the file is truncated", name, page_sum));
+    }
+    Ok(())
+}
@@ -1,127 +1,201 @@
-use std::ptr::null_mut;
-use std::hash::{Hash, Hasher};
-use std::option::Option;
-use std::rc::Rc;
-use std::cell::RefCell;
-use libc::{c_void, free};
-use super::lru::{CacheValue, LruCache};
-use ::utils::libwrapper::alloc_page;
-
-
-pub type DataPtr = *mut c_void;
-
-
-#[derive(Debug, Eq, PartialEq)]
-pub struct PageKey {
-    pub fd : i32,
-    pub page_index : u32,
-}
-
-impl Hash for PageKey {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        let mut n = self.fd as u64;
-        n = n.rotate_left(32);
-        n = n + (self.page_index as u64);
-        n.hash(state);
-    }
-}
-
-
-pub type PageRef = Rc<RefCell<Page>>;
-
-#[derive(Debug)]
-pub struct Page {
-    pub fd : i32,
-    pub page_index : u32,
-    pub data : DataPtr,
-    pub dirty : bool,
-    pub pinned : bool,
-}
-
-impl CacheValue for PageRef {
-    type KeyType = PageKey;
-    fn is_pinned(&self) -> bool {
-        self.borrow().pinned
-    }
-}
-
-impl Page {
-    pub fn new(fd : i32, page_index : u32) -> Page {
-        Page{
-            fd : fd,
-            page_index : page_index,
-            data : null_mut(),
-            dirty : false,
-            pinned : false,
-        }
-    }
-    pub fn alloc(&mut self) {
-        assert!(self.data.is_null());
-        self.data = alloc_page();
-    }
-    pub fn mark_dirty(&mut self) {
-        self.dirty = true;
-    }
-}
-
-impl Drop for Page {
-    fn drop(&mut self) {
-        if self.data.is_null() {
-            return;
-        }
-        unsafe{ free(self.data) };
-    }
-}
-
-
-#[derive(Debug)]
-pub struct PagePool {
-    // should be protected by mutex
-    cache: LruCache<PageRef>,
-    unpinned : usize,
-}
-
-impl PagePool {
-    pub fn new(capacity : usize) -> PagePool {
-        PagePool{
-            cache : LruCache::new(capacity),
-            unpinned : capacity,
-        }
-    }
-    pub fn get_capacity(&self) -> usize {
-        self.cache.capacity
-    }
-    pub fn get_page(&mut self, fd : i32, page_index : u32) -> Option<PageRef> {
-        let key = PageKey{ fd : fd, page_index : page_index };
-        self.cache.get(&key)
-    }
-    pub fn prepare_page(&mut self) -> Option<PageRef> {
-        self.cache.prepare_page()
-    }
-    pub fn remove_tail(&mut self) {
-        self.cache.remove_tail();
-    }
-    pub fn put_page(&mut self, fd : i32, page_index : u32, ptr : DataPtr) {
-        let key = PageKey{ fd : fd, page_index : page_index };
-        let mut new_page = Page::new(fd, page_index);
-        new_page.data = ptr;
-        if ptr.is_null() {
-            new_page.alloc();
-        }
-        self.cache.put(&key, Rc::new(RefCell::new(new_page)));
-    }
-    pub fn pin_page(&mut self, fd : i32, page_index : u32) {
-        assert!(self.unpinned > 0);
-        self.unpinned -= 1;
-        let page = self.get_page(fd, page_index).unwrap();
-        page.borrow_mut().pinned = true;
-    }
-    pub fn unpin_page(&mut self, fd : i32, page_index : u32) {
-        assert!(self.unpinned < self.cache.capacity);
-        self.unpinned += 1;
-        let page = self.get_page(fd, page_index).unwrap();
-        assert!(page.borrow().pinned);
-        page.borrow_mut().pinned = false;
-    }
-    pub fn get_unpinned_num(&self) -> usize { self.unpinned }
-}
+use std::ptr::null_mut;
+use std::option::Option;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use libc::{c_void, free};
+use super::lru::{CacheValue, LruCache};
+use ::utils::libwrapper::alloc_page;
+
+
+pub type DataPtr = *mut c_void;
+
+
+// Assigned to each opened TableFile and never reused in the process,
+// while the fd of a closed file is soon given to the next one opened.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FileId(pub u64);
+
+static NEXT_FILE_ID : AtomicUsize = AtomicUsize::new(1);
+
+impl FileId {
+    pub fn next() -> FileId {
+        FileId(NEXT_FILE_ID.fetch_add(1, Ordering::SeqCst) as u64)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub struct PageKey {
+    pub file_id : FileId,
+    pub page_index : u32,
+}
+
+
+pub type PageRef = Rc<RefCell<Page>>;
+
+#[derive(Debug)]
+pub struct Page {
+    pub file_id : FileId,
+    pub page_index : u32,
+    pub data : DataPtr,
+    pub mapped : bool,  // data points into the mapping of the file, not owned by the page
+    pub dirty : bool,
+    pub pin_count : usize,  // the page can't be evicted until each pin_page has its unpin_page
+    pub last_access : u64,  // the access ordinal of the pool, larger for the more recent ones
+}
+
+impl CacheValue for PageRef {
+    type KeyType = PageKey;
+    fn is_pinned(&self) -> bool {
+        self.borrow().is_pinned()
+    }
+}
+
+impl Page {
+    pub fn new(file_id : FileId, page_index : u32) -> Page {
+        Page{
+            file_id : file_id,
+            page_index : page_index,
+            data : null_mut(),
+            mapped : false,
+            dirty : false,
+            pin_count : 0,
+            last_access : 0,
+        }
+    }
+    pub fn is_pinned(&self) -> bool {
+        self.pin_count > 0
+    }
+    pub fn alloc(&mut self, size : usize) {
+        assert!(self.data.is_null());
+        self.data = alloc_page(size);
+    }
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+}
+
+impl Drop for Page {
+    fn drop(&mut self) {
+        if self.data.is_null() || self.mapped {
+            return;
+        }
+        unsafe{ free(self.data) };
+    }
+}
+
+
+// A cached page as seen by `show buffer`, copied so that the pool is not borrowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSnapshot {
+    pub file_id : FileId,
+    pub page_index : u32,
+    pub pin_count : usize,
+    pub dirty : bool,
+    pub last_access : u64,
+}
+
+#[derive(Debug)]
+pub struct PagePool {
+    // should be protected by mutex
+    cache: LruCache<PageRef>,
+    unpinned : usize,
+    access_num : u64,  // of get_page and put_page, also through pin_page and unpin_page
+}
+
+impl PagePool {
+    pub fn new(capacity : usize) -> PagePool {
+        PagePool{
+            cache : LruCache::new(capacity),
+            unpinned : capacity,
+            access_num : 0,
+        }
+    }
+    pub fn get_capacity(&self) -> usize {
+        self.cache.capacity
+    }
+    // the pages cached, below the capacity until the first eviction
+    pub fn get_load(&self) -> usize {
+        self.cache.get_load()
+    }
+    pub fn get_page(&mut self, file_id : FileId, page_index : u32) -> Option<PageRef> {
+        let key = PageKey{ file_id : file_id, page_index : page_index };
+        let page = self.cache.get(&key);
+        if let Some(ref page) = page {
+            self.access_num += 1;
+            page.borrow_mut().last_access = self.access_num;
+        }
+        page
+    }
+    pub fn prepare_page(&mut self) -> Option<PageRef> {
+        self.cache.prepare_page()
+    }
+    pub fn remove_tail(&mut self) {
+        self.cache.remove_tail();
+    }
+    pub fn remove_page(&mut self, file_id : FileId, page_index : u32) -> Option<PageRef> {
+        let key = PageKey{ file_id : file_id, page_index : page_index };
+        let page = self.cache.remove(&key);
+        if let Some(ref page) = page {
+            assert!(!page.borrow().is_pinned());
+        }
+        page
+    }
+    pub fn put_page(&mut self, file_id : FileId, page_index : u32, ptr : DataPtr, page_size : usize) {
+        // a new page of page_size is allocated if ptr is null
+        let key = PageKey{ file_id : file_id, page_index : page_index };
+        let mut new_page = Page::new(file_id, page_index);
+        new_page.data = ptr;
+        if ptr.is_null() {
+            new_page.alloc(page_size);
+        }
+        self.access_num += 1;
+        new_page.last_access = self.access_num;
+        self.cache.put(&key, Rc::new(RefCell::new(new_page)));
+    }
+    pub fn put_mapped_page(&mut self, file_id : FileId, page_index : u32, ptr : DataPtr) {
+        // the bytes stay in the mapping after the page is evicted
+        assert!(!ptr.is_null());
+        let key = PageKey{ file_id : file_id, page_index : page_index };
+        let mut new_page = Page::new(file_id, page_index);
+        new_page.data = ptr;
+        new_page.mapped = true;
+        self.access_num += 1;
+        new_page.last_access = self.access_num;
+        self.cache.put(&key, Rc::new(RefCell::new(new_page)));
+    }
+    pub fn pin_page(&mut self, file_id : FileId, page_index : u32) {
+        // a page may be pinned by more than one user, such as a scan and the delete of its tuple
+        let page = self.get_page(file_id, page_index).unwrap();
+        if !page.borrow().is_pinned() {
+            assert!(self.unpinned > 0);
+            self.unpinned -= 1;
+        }
+        page.borrow_mut().pin_count += 1;
+    }
+    pub fn unpin_page(&mut self, file_id : FileId, page_index : u32) {
+        let page = self.get_page(file_id, page_index).unwrap();
+        assert!(page.borrow().is_pinned(), "unpin page {} of file {} which is not pinned", page_index, file_id.0);
+        page.borrow_mut().pin_count -= 1;
+        if !page.borrow().is_pinned() {
+            assert!(self.unpinned < self.cache.capacity);
+            self.unpinned += 1;
+        }
+    }
+    pub fn get_unpinned_num(&self) -> usize { self.unpinned }
+    // the cached pages from the most recently accessed, the pool is not touched
+    pub fn snapshot(&self) -> Vec<PageSnapshot> {
+        let mut pages : Vec<PageSnapshot> = self.cache.values().iter().map(|page| {
+            let page = page.borrow();
+            PageSnapshot{
+                file_id : page.file_id,
+                page_index : page.page_index,
+                pin_count : page.pin_count,
+                dirty : page.dirty,
+                last_access : page.last_access,
+            }
+        }).collect();
+        pages.sort_by(|a, b| b.last_access.cmp(&a.last_access));
+        pages
+    }
+}
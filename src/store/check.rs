@@ -0,0 +1,222 @@
+use std::fmt;
+use std::fs::{File, read_dir};
+use std::path::Path;
+use std::mem::size_of;
+use ::utils::file::read_at_most;
+use ::utils::compress::decompress;
+use super::table::{Table, check_catalog};
+use super::buffer::DataPtr;
+use super::page_buf::PageBuf;
+use super::backup::read_backup_catalog;
+use super::ddl_journal::{DdlOp, JOURNAL_NAME, read_intent};
+use super::file::{BitMap, FileHeader, COMPRESSED_PAGE_TAG, COMPRESSED_HEADER_SIZE, PAGE_CHECKSUM_OFFSET};
+use super::file::{get_page_offset, get_slot_sum, read_file_header, get_page_header_size, page_checksum};
+
+
+// a problem of the catalog or a table file, page_index is None for the whole file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckProblem {
+    pub file : String,
+    pub page_index : Option<usize>,
+    pub msg : String,
+}
+
+impl fmt::Display for CheckProblem {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self.page_index {
+            Some(page_index) => write!(f, "{} page {}: {}", self.file, page_index, self.msg),
+            None => write!(f, "{}: {}", self.file, self.msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckReport {
+    pub table_num : usize,
+    pub page_num : usize,
+    pub row_num : usize,  // the slots in use of the pages checked
+    pub problems : Vec<CheckProblem>,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for problem in self.problems.iter() {
+            try!(writeln!(f, "{}", problem));
+        }
+        write!(f, "checked {} tables, {} pages, {} rows: {} problems",
+            self.table_num, self.page_num, self.row_num, self.problems.len())
+    }
+}
+
+// Checks the catalog and the table files saved by a closed server against the schema,
+// every problem is reported and nothing is modified.
+pub fn check_data_dir(table_meta_dir : &String, table_file_dir : &String) -> CheckReport {
+    let mut report = CheckReport{
+        table_num : 0,
+        page_num : 0,
+        row_num : 0,
+        problems : Vec::new(),
+    };
+    let tables = match read_backup_catalog(table_meta_dir) {
+        Ok(tables) => tables,
+        Err(msg) => {
+            report.problems.push(CheckProblem{
+                file : "table_meta.json".to_string(),
+                page_index : None,
+                msg : msg.trim_left_matches("table_meta.json: ").to_string(),
+            });
+            return report;
+        }
+    };
+    // the files of the table may not match the catalog until the server starts again
+    let unfinished = match read_intent(table_meta_dir) {
+        Ok(Some(intent)) => {
+            let op = match intent.op {
+                DdlOp::Create => "create",
+                DdlOp::Drop => "drop",
+                DdlOp::Alter => "alter",
+            };
+            Some(format!("{} table {} is not finished, it's recovered when the server starts",
+                op, intent.table))
+        }
+        Ok(None) => None,
+        Err(msg) => Some(msg.trim_left_matches(&format!("{}: ", JOURNAL_NAME)).to_string()),
+    };
+    if let Some(msg) = unfinished {
+        report.problems.push(CheckProblem{
+            file : JOURNAL_NAME.to_string(),
+            page_index : None,
+            msg : msg,
+        });
+    }
+    for msg in check_catalog(&tables).into_iter() {
+        report.problems.push(CheckProblem{
+            file : "table_meta.json".to_string(),
+            page_index : None,
+            msg : msg,
+        });
+    }
+    for table in tables.values() {
+        check_table_file(table, table_file_dir, &mut report);
+        report.table_num += 1;
+    }
+    // the files left by a table dropped outside the catalog
+    if let Ok(entries) = read_dir(table_file_dir) {
+        // read_dir has no order
+        let mut names : Vec<String> = entries.filter_map(|e| e.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned()).collect();
+        names.sort();
+        for name in names.into_iter() {
+            if name.ends_with(".table") && !tables.contains_key(name.trim_right_matches(".table")) {
+                report.problems.push(CheckProblem{
+                    file : name,
+                    page_index : None,
+                    msg : "not in the catalog".to_string(),
+                });
+            }
+        }
+    }
+    report
+}
+
+fn check_table_file(table : &Table, dir : &String, report : &mut CheckReport) {
+    let name = format!("{}.table", table.name);
+    let file_problem = |msg : String| CheckProblem{ file : name.clone(), page_index : None, msg : msg };
+    let file = match File::open(Path::new(dir).join(&name)) {
+        Ok(file) => file,
+        Err(err) => return report.problems.push(file_problem(format!("can't open: {}", err))),
+    };
+    let header = match read_file_header(&file) {
+        Ok(header) => header,
+        Err(err) => return report.problems.push(file_problem(err)),
+    };
+    let FileHeader{page_sum, first_free_page, page_size, checksum, ..} = header;
+    let tuple_len = table.gen_tuple_desc().tuple_len;
+    if header.tuple_len != 0 && header.tuple_len != tuple_len {
+        return report.problems.push(file_problem(
+            format!("saved with tuples of {} bytes, while the schema has {}", header.tuple_len, tuple_len)));
+    }
+    let header_size = get_page_header_size(checksum);
+    if page_size < header_size + tuple_len + 1 {
+        return report.problems.push(file_problem(
+            format!("page size {} can't hold a tuple of {} bytes", page_size, tuple_len)));
+    }
+    if first_free_page > page_sum {
+        report.problems.push(file_problem(
+            format!("first_free_page {} is over page_sum {}", first_free_page, page_sum)));
+    }
+    let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let slot_sum = get_slot_sum(tuple_len, page_size, checksum);
+    let mut data = vec![0u8; page_size];
+    for page_index in 0..page_sum {
+        let page_problem = |msg : String| CheckProblem{
+            file : name.clone(), page_index : Some(page_index), msg : msg };
+        let offset = get_page_offset(page_index, page_size);
+        if offset >= file_size {
+            report.problems.push(file_problem(format!("expected {} pages, the file is truncated", page_sum)));
+            break;
+        }
+        report.page_num += 1;
+        for b in data.iter_mut() {
+            *b = 0;
+        }
+        let read_len = read_at_most(&file, &mut data, offset);
+        let page = unsafe{ PageBuf::new(data.as_mut_ptr() as DataPtr, page_size) };
+        // the same as TableFile::read_page_from_file
+        if read_len >= COMPRESSED_HEADER_SIZE && page.get_u32(0) == COMPRESSED_PAGE_TAG {
+            let payload_len = page.get_u32(size_of::<u32>()) as usize;
+            if COMPRESSED_HEADER_SIZE + payload_len > read_len {
+                report.problems.push(page_problem(format!("compressed payload of {} bytes is truncated",
+                    payload_len)));
+                continue;
+            }
+            let payload = data[COMPRESSED_HEADER_SIZE..COMPRESSED_HEADER_SIZE + payload_len].to_vec();
+            if !decompress(&payload, &mut data) {
+                report.problems.push(page_problem("can't decompress".to_string()));
+                continue;
+            }
+        } else if read_len < page_size {
+            report.problems.push(page_problem(format!("only {} bytes of the page are saved", read_len)));
+            continue;
+        }
+        let page = unsafe{ PageBuf::new(data.as_mut_ptr() as DataPtr, page_size) };
+        // the rest is not worth checking if any byte has changed
+        if checksum {
+            let (saved, computed) = (page.get_u32(PAGE_CHECKSUM_OFFSET), page_checksum(&data));
+            if saved != computed {
+                report.problems.push(page_problem(format!("checksum {:08x} does not match {:08x} of the data",
+                    saved, computed)));
+                continue;
+            }
+        }
+        let page_slot_sum = page.get_u32(0) as usize;
+        if page_slot_sum != slot_sum {
+            report.problems.push(page_problem(format!("slot_sum {} does not match {} of the schema",
+                page_slot_sum, slot_sum)));
+            continue;
+        }
+        let first_free_slot = page.get_u32(size_of::<u32>()) as usize;
+        let bitmap = BitMap{
+            buf : page.sub(header_size, (slot_sum + 7) / 8),
+            slot_sum : slot_sum,
+        };
+        if first_free_slot > slot_sum {
+            report.problems.push(page_problem(format!("first_free_slot {} is over slot_sum {}",
+                first_free_slot, slot_sum)));
+        } else if first_free_slot < slot_sum && bitmap.is_inuse(first_free_slot) {
+            report.problems.push(page_problem(format!("first_free_slot {} is in use in the bitmap",
+                first_free_slot)));
+        }
+        // the scans assume the bits after the last slot are 0
+        if slot_sum % 8 != 0 && bitmap.buf.get_u8(slot_sum / 8) >> (slot_sum % 8) != 0 {
+            report.problems.push(page_problem("bits after the last slot are set in the bitmap".to_string()));
+        }
+        report.row_num += bitmap.get_inuse_num();
+    }
+}
@@ -0,0 +1,170 @@
+use std::io;
+use std::fs::{OpenOptions, File, rename, remove_file};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rustc_serialize::json::{encode, decode};
+use super::table::Table;
+
+
+pub const JOURNAL_NAME : &'static str = "ddl_journal.json";
+pub const CATALOG_NAME : &'static str = "table_meta.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, RustcDecodable, RustcEncodable)]
+pub enum DdlOp {
+    Create,
+    Drop,
+    Alter,
+}
+
+// A create or drop runs in DDL_STEPS steps:
+// (1) the intent is written to the journal in table_meta_dir
+// (2) the table file is created or removed
+// (3) the catalog is written to a temp file and renamed over table_meta.json
+// (4) the journal is removed
+// The rename of the catalog is the commit point, a journal left by a crash is finished by recover_ddl.
+// An alter has its shadow file synced before (1), and renames it over the table file in (2).
+// Its intent has the new schema, so it's committed by (1).
+#[derive(Debug, Clone, PartialEq, Eq, RustcDecodable, RustcEncodable)]
+pub struct DdlIntent {
+    pub op : DdlOp,
+    pub table : String,
+    pub new_table : Option<String>,  // the json of the table after an alter, None for create and drop
+}
+
+pub const DDL_STEPS : usize = 4;
+
+// the file an alter copies the tuples into, see TableManager::rewrite_table
+pub fn get_shadow_name(table : &String) -> String {
+    format!("{}.rewrite", table)
+}
+
+// the file is either the old one or complete, even if the process is killed in the middle
+pub fn write_file_atomic(path : &String, data : &[u8]) -> io::Result<()> {
+    // different for each call, the managers sharing a directory in the tests may write at the same time
+    static TMP_FILE_NUM : AtomicUsize = AtomicUsize::new(0);
+    let tmp_path = format!("{}.{}.tmp", path, TMP_FILE_NUM.fetch_add(1, Ordering::SeqCst));
+    {
+        let mut file = try!(OpenOptions::new().write(true).create(true).truncate(true).open(&tmp_path));
+        try!(file.write_all(data));
+        try!(file.sync_data());
+    }
+    rename(&tmp_path, path)
+}
+
+pub fn write_catalog(dir : &String, tables : &BTreeMap<String, Table>) -> io::Result<()> {
+    let path = Path::new(dir).join(CATALOG_NAME).to_str().unwrap().to_string();
+    write_file_atomic(&path, encode(tables).unwrap().as_bytes())
+}
+
+// empty if it's missing or empty, like a server started for the first time
+pub fn read_catalog(dir : &String) -> Result<BTreeMap<String, Table>, String> {
+    let mut json_str = String::new();
+    match File::open(Path::new(dir).join(CATALOG_NAME)) {
+        Ok(mut file) => {
+            if let Err(err) = file.read_to_string(&mut json_str) {
+                return Err(format!("{}: can't read: {}", CATALOG_NAME, err));
+            }
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+        Err(err) => return Err(format!("{}: can't read: {}", CATALOG_NAME, err)),
+    }
+    if json_str.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    decode(&json_str).map_err(|err| format!("{}: invalid catalog: {:?}", CATALOG_NAME, err))
+}
+
+pub fn write_intent(dir : &String, intent : &DdlIntent) -> io::Result<()> {
+    let path = Path::new(dir).join(JOURNAL_NAME).to_str().unwrap().to_string();
+    write_file_atomic(&path, encode(intent).unwrap().as_bytes())
+}
+
+pub fn read_intent(dir : &String) -> Result<Option<DdlIntent>, String> {
+    let mut json_str = String::new();
+    match File::open(Path::new(dir).join(JOURNAL_NAME)) {
+        Ok(mut file) => {
+            if let Err(err) = file.read_to_string(&mut json_str) {
+                return Err(format!("{}: can't read: {}", JOURNAL_NAME, err));
+            }
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("{}: can't read: {}", JOURNAL_NAME, err)),
+    }
+    // the journal is renamed into place, so it's never partial
+    match decode(&json_str) {
+        Ok(intent) => Ok(Some(intent)),
+        Err(err) => Err(format!("{}: invalid intent: {:?}", JOURNAL_NAME, err)),
+    }
+}
+
+pub fn clear_intent(dir : &String) -> io::Result<()> {
+    match remove_file(Path::new(dir).join(JOURNAL_NAME)) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// Finishes the ddl left in the journal by a crash, before the catalog is read.
+// A create is rolled back unless the catalog has the table, a drop is always rolled forward
+// since its file may be removed already, and so is an alter since its old file may be replaced.
+// Return what is done, None without any journal.
+pub fn recover_ddl(table_meta_dir : &String, table_file_dir : &String) -> Result<Option<String>, String> {
+    let intent = match try!(read_intent(table_meta_dir)) {
+        Some(intent) => intent,
+        None => return Ok(None),
+    };
+    let mut tables = try!(read_catalog(table_meta_dir));
+    let file_path = Path::new(table_file_dir).join(format!("{}.table", intent.table));
+    let committed = tables.contains_key(&intent.table);
+    let done = match (intent.op, committed) {
+        (DdlOp::Create, true) => format!("create table {} is rolled forward", intent.table),
+        (DdlOp::Create, false) => {
+            try!(remove_if_exist(&file_path));
+            format!("create table {} is rolled back", intent.table)
+        }
+        (DdlOp::Drop, _) => {
+            try!(remove_if_exist(&file_path));
+            if committed {
+                tables.remove(&intent.table);
+                if let Err(err) = write_catalog(table_meta_dir, &tables) {
+                    return Err(format!("{}: can't write: {}", CATALOG_NAME, err));
+                }
+            }
+            format!("drop table {} is rolled forward", intent.table)
+        }
+        (DdlOp::Alter, _) => {
+            let new_table : Table = match intent.new_table {
+                Some(ref json) => try!(decode(json)
+                    .map_err(|err| format!("{}: invalid schema of the alter: {:?}", JOURNAL_NAME, err))),
+                None => return Err(format!("{}: the alter has no schema", JOURNAL_NAME)),
+            };
+            // missing if it's renamed already
+            let shadow_name = format!("{}.table", get_shadow_name(&intent.table));
+            let shadow_path = Path::new(table_file_dir).join(shadow_name);
+            if shadow_path.exists() {
+                if let Err(err) = rename(&shadow_path, &file_path) {
+                    return Err(format!("{}: can't rename: {}", shadow_path.display(), err));
+                }
+            }
+            tables.insert(intent.table.clone(), new_table);
+            if let Err(err) = write_catalog(table_meta_dir, &tables) {
+                return Err(format!("{}: can't write: {}", CATALOG_NAME, err));
+            }
+            format!("alter table {} is rolled forward", intent.table)
+        }
+    };
+    if let Err(err) = clear_intent(table_meta_dir) {
+        return Err(format!("{}: can't remove: {}", JOURNAL_NAME, err));
+    }
+    Ok(Some(done))
+}
+
+fn remove_if_exist(path : &Path) -> Result<(), String> {
+    match remove_file(path) {
+        Ok(..) => Ok(()),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(format!("{}: can't remove: {}", path.display(), err)),
+    }
+}
@@ -0,0 +1,164 @@
+use std::io;
+use std::fs::{OpenOptions, File};
+use std::sync::{Arc, Mutex};
+use std::mem::transmute;
+use ::utils::checksum::crc32;
+use ::utils::file::{write_all_at, read_at_most};
+use super::writer::FileWriterRef;
+
+
+pub type DoubleWriteRef = Arc<Mutex<DoubleWriteBuffer>>;
+
+// a page slot to be written to its final location in a table file
+#[derive(Debug)]
+pub struct PageWrite {
+    pub file : Arc<File>,
+    pub path : String,
+    pub offset : u64,
+    pub data : Vec<u8>,
+    pub writer : FileWriterRef,
+}
+
+// Pages are first written to the buffer file and synced, then written to the table files.
+// If a crash leaves a page half written, the copy in the buffer file is still complete.
+// Record format, little-endian: [path_len u32][path][offset u64][data_len u32][data][crc32 of all before u32]
+#[derive(Debug)]
+pub struct DoubleWriteBuffer {
+    file : File,
+    path : String,
+}
+
+impl DoubleWriteBuffer {
+    pub fn open(path : &String) -> DoubleWriteBuffer {
+        let file = OpenOptions::new().read(true).write(true).create(true).open(path).unwrap();
+        DoubleWriteBuffer{
+            file : file,
+            path : path.clone(),
+        }
+    }
+    pub fn make_ref(path : &String) -> DoubleWriteRef {
+        Arc::new(Mutex::new(DoubleWriteBuffer::open(path)))
+    }
+    pub fn write(&mut self, pages : &Vec<PageWrite>) -> io::Result<()> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+        try!(self.write_buffer(pages));
+        for page in pages.iter() {
+            try!(page.writer.write_at(&page.file, &page.data, page.offset));
+        }
+        for page in pages.iter() {
+            try!(page.file.sync_data());
+        }
+        self.clear()
+    }
+    pub fn write_buffer(&mut self, pages : &Vec<PageWrite>) -> io::Result<()> {
+        let mut buf = Vec::new();
+        for page in pages.iter() {
+            let begin = buf.len();
+            push_u32(&mut buf, page.path.len() as u32);
+            buf.extend_from_slice(page.path.as_bytes());
+            let offset : [u8; 8] = unsafe{ transmute(page.offset.to_le()) };
+            buf.extend_from_slice(&offset);
+            push_u32(&mut buf, page.data.len() as u32);
+            buf.extend_from_slice(&page.data);
+            let checksum = crc32(&buf[begin..]);
+            push_u32(&mut buf, checksum);
+        }
+        try!(self.file.set_len(0));
+        try!(write_all_at(&self.file, &buf, 0));
+        self.file.sync_data()
+    }
+    pub fn clear(&mut self) -> io::Result<()> {
+        try!(self.file.set_len(0));
+        self.file.sync_data()
+    }
+    pub fn read_records(&self) -> Vec<(String, u64, Vec<u8>)> {
+        // stop at the first incomplete or corrupted record,
+        // which means the crash happened before the buffer is synced
+        let mut buf = Vec::new();
+        let len = match self.file.metadata() {
+            Ok(m) => m.len() as usize,
+            Err(..) => return vec![],
+        };
+        buf.resize(len, 0);
+        let len = read_at_most(&self.file, &mut buf, 0);
+        buf.truncate(len);
+        let mut records = Vec::new();
+        let mut pos = 0;
+        loop {
+            let begin = pos;
+            let path_len = match read_u32(&buf, &mut pos) { Some(n) => n as usize, None => break };
+            if pos + path_len > buf.len() { break; }
+            let path = String::from_utf8_lossy(&buf[pos..pos + path_len]).into_owned();
+            pos += path_len;
+            if pos + 8 > buf.len() { break; }
+            let mut offset = [0u8; 8];
+            offset.copy_from_slice(&buf[pos..pos + 8]);
+            let offset = u64::from_le(unsafe{ transmute(offset) });
+            pos += 8;
+            let data_len = match read_u32(&buf, &mut pos) { Some(n) => n as usize, None => break };
+            if pos + data_len > buf.len() { break; }
+            let data = buf[pos..pos + data_len].to_vec();
+            pos += data_len;
+            let end = pos;
+            match read_u32(&buf, &mut pos) {
+                Some(checksum) if checksum == crc32(&buf[begin..end]) => (),
+                _ => break,
+            }
+            records.push((path, offset, data));
+        }
+        records
+    }
+    pub fn recover(&mut self) -> usize {
+        // return the number of pages restored
+        let mut restored = 0;
+        for (path, offset, data) in self.read_records() {
+            let file = match OpenOptions::new().read(true).write(true).open(&path) {
+                Ok(file) => file,
+                Err(..) => continue,  // table dropped
+            };
+            let mut current = vec![0u8; data.len()];
+            let len = read_at_most(&file, &mut current, offset);
+            if len == data.len() && current == data {
+                continue;
+            }
+            check_ok!(write_all_at(&file, &data, offset));
+            check_ok!(file.sync_data());
+            restored += 1;
+        }
+        check_ok!(self.clear());
+        restored
+    }
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+}
+
+fn push_u32(buf : &mut Vec<u8>, n : u32) {
+    let bytes : [u8; 4] = unsafe{ transmute(n.to_le()) };
+    buf.extend_from_slice(&bytes);
+}
+
+fn read_u32(buf : &Vec<u8>, pos : &mut usize) -> Option<u32> {
+    if *pos + 4 > buf.len() {
+        return None;
+    }
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&buf[*pos..*pos + 4]);
+    *pos += 4;
+    Some(u32::from_le(unsafe{ transmute(bytes) }))
+}
+
+// the pages are written in order, it stops at the first error
+pub fn try_write_pages(pages : &Vec<PageWrite>, double_write : &Option<DoubleWriteRef>) -> io::Result<()> {
+    match double_write {
+        &Some(ref buffer) => buffer.lock().unwrap().write(pages),
+        &None => {
+            for page in pages.iter() {
+                try!(page.writer.write_at(&page.file, &page.data, page.offset));
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,611 +1,1754 @@
-use std::collections::HashMap;
-use std::mem::size_of;
-use std::ptr::{write, read, write_bytes, null_mut};
-use std::fs::{OpenOptions, File};
-use std::os::unix::io::AsRawFd;
-use std::slice::{from_raw_parts, from_raw_parts_mut};
-use std::io::{Read, Write, Seek, SeekFrom};
-use std::rc::Rc;
-use std::cell::RefCell;
-use ::utils::libwrapper::get_page_size;
-use ::utils::pointer::{read_string, write_string, pointer_offset};
-use ::utils::config::Config;
-use ::utils::file::{path_join, ensure_dir_exist, assert_file_exist};
-use ::parser::common::{ValueList, ValueType};
-use super::buffer::{DataPtr, PageRef, PagePool};
-use super::table::{TableRef, AttrType, IndexMap};
-use super::tuple::{TupleDesc, TupleValue, TupleData};
-
-
-#[derive(Debug)]
-pub struct PageHeader {
-    pub slot_sum : usize,
-    pub first_free_slot : usize,
-    pub data : DataPtr,
-}
-
-impl PageHeader {
-    pub fn save_to_page_data(&mut self) {
-        unsafe{
-            write::<u32>(self.data as *mut u32, self.slot_sum as u32);
-            let next_data_ptr = pointer_offset(self.data, size_of::<u32>());
-            write::<u32>(next_data_ptr as *mut u32, self.first_free_slot as u32);
-        }
-    }
-
-    pub fn init_from_page_data(&mut self) {
-        unsafe{
-            let slot_sum = read::<u32>(self.data as *const u32) as usize;
-            assert_eq!(slot_sum, self.slot_sum);
-            let next_data_ptr = pointer_offset(self.data, size_of::<u32>());
-            self.first_free_slot = read::<u32>(next_data_ptr as *const u32) as usize;
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct BitMap {
-    pub data : DataPtr,
-    pub slot_sum : usize,
-}
-
-impl BitMap {
-    pub fn next_tuple_index(&self, from : usize) -> usize {
-        if from >= self.slot_sum {
-            return self.slot_sum;
-        }
-        let mut count = from / 8;
-        let mut bit_count = from % 8;
-        while count < (self.slot_sum + 7) / 8 {
-            let n = unsafe{ read::<u8>((self.data as *const u8).offset(count as isize)) };
-            let mut mask = 1 << bit_count;
-            if n < mask { count += 1; bit_count = 0; continue; }
-            loop {
-                // assume the bits whose index > slot_sum is 0
-                if mask & n > 0 {
-                    return count * 8 + bit_count;
-                }
-                mask *= 2;
-                bit_count += 1;
-            }
-        }
-        self.slot_sum
-    }
-    pub fn get_first_free_slot(&self) -> usize {
-        let mut count = 0;
-        while count < (self.slot_sum + 7) / 8 {
-            let n = unsafe{ read::<u8>((self.data as *const u8).offset(count as isize)) };
-            if n == 255 { count += 1; continue; }
-            let mut mask : u8 = 1;
-            let mut bit_count = 0;
-            loop {
-                // assume the bits whose index > slot_sum is 0
-                if (255 - mask) | n < 255 {
-                    return count * 8 + bit_count;
-                }
-                mask *= 2;
-                bit_count += 1;
-            }
-        }
-        self.slot_sum
-    }
-    pub fn get_byte_size(&self) -> usize {
-        (self.slot_sum + 7) / 8
-    }
-    pub fn clean(&mut self) {
-        unsafe{
-            write_bytes(self.data, 0, self.get_byte_size());
-        }
-    }
-    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
-        assert!(index < self.slot_sum);
-        let byte_offset = index / 8;
-        let bit_offset = index % 8;
-        let n = 1 << bit_offset;
-        unsafe{
-            let p = pointer_offset(self.data, byte_offset);
-            let a = read::<u8>(p as *const u8);
-            let b = if inuse {
-                a | n
-            } else {
-                a & (255 - n)
-            };
-            write::<u8>(p as *mut u8, b);
-        }
-    }
-    pub fn is_inuse(&self, index : usize) -> bool {
-        assert!(index < self.slot_sum);
-        let byte_offset = index / 8;
-        let bit_offset = index % 8;
-        let n = 1 << bit_offset;
-        unsafe{
-            let p = pointer_offset(self.data, byte_offset);
-            let a = read::<u8>(p as *const u8);
-            (a & n) > 0
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct FilePage {
-    pub header : PageHeader,
-    pub bitmap : BitMap,
-    pub tuple_data : DataPtr,
-    pub mem_page : PageRef,
-    pub tuple_len : usize,
-}
-
-impl FilePage {
-    pub fn new(mem_page : PageRef, tuple_len : usize) -> FilePage {
-        let data = mem_page.borrow_mut().data;
-        let header_size = 2 * size_of::<u32>();  // PageHeader
-        let slot_sum = get_slot_sum(tuple_len);
-        let bitmap_data = unsafe{ data.offset(header_size as isize) };
-        let bitmap_size = (slot_sum + 7) / 8;
-        let tuple_data = unsafe{ bitmap_data.offset(bitmap_size as isize) };
-        FilePage{
-            header : PageHeader{
-                slot_sum : slot_sum,
-                first_free_slot : 0,
-                data : data,
-            },
-            bitmap : BitMap{
-                data : bitmap_data,
-                slot_sum : slot_sum,
-            },
-            tuple_data : tuple_data,
-            mem_page : mem_page,
-            tuple_len : tuple_len,
-        }
-    }
-    pub fn init_empty_page(&mut self) {
-        self.header.save_to_page_data();
-        self.bitmap.clean();
-    }
-    pub fn init_from_page_data(&mut self) {
-        self.header.init_from_page_data();
-    }
-    pub fn save_to_page(&mut self) {
-        self.header.save_to_page_data();
-    }
-    pub fn is_inuse(&self, index : usize) -> bool {
-        self.bitmap.is_inuse(index)
-    }
-    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
-        self.bitmap.set_inuse(index, inuse);
-    }
-    pub fn insert(&mut self, value_list : &ValueList, tuple_desc : &TupleDesc) {
-        assert!(!self.is_inuse(self.header.first_free_slot));
-        assert_eq!(value_list.len(), tuple_desc.attr_desc.len());
-        assert!(self.header.first_free_slot < self.bitmap.slot_sum);
-
-        let first_free_slot = self.header.first_free_slot;
-        self.set_inuse(first_free_slot, true);
-        self.header.first_free_slot = self.bitmap.get_first_free_slot();
-        self.save_to_page();
-
-        let mut p = unsafe{
-            self.tuple_data.offset(
-                (tuple_desc.tuple_len * first_free_slot) as isize
-                )
-        };
-        for (v, d) in value_list.iter().zip(&tuple_desc.attr_desc) {
-            match (v.value_type, d) {
-                (ValueType::Integer, &AttrType::Int) => {
-                    let n : i32 = v.value.parse::<i32>().unwrap();
-                    unsafe{ write::<i32>(p as *mut i32, n) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::Float, &AttrType::Float) | (ValueType::Integer, &AttrType::Float) => {
-                    let n : f32 = v.value.parse::<f32>().unwrap();
-                    unsafe{ write::<f32>(p as *mut f32, n) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::String, &AttrType::Char{len}) => {
-                    let aligned_len = (len + 3) / 4 * 4;
-                    unsafe{ write_string(p, &v.value, len) };
-                    p = pointer_offset(p, aligned_len);
-                }
-                (ValueType::Null, &AttrType::Int) | (ValueType::Null, &AttrType::Float) => {
-                    unsafe{ write_bytes(p, 0, 4) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::Null, &AttrType::Char{len}) => {
-                    let aligned_len = (len + 3) / 4 * 4;
-                    unsafe{ write_bytes(p, 0, aligned_len) };
-                    p = pointer_offset(p, aligned_len);
-                }
-                _ => panic!("invalid value, expected {:?}, found {:?}", d, v),
-            }
-        }
-    }
-    pub fn get_tuple_value(&self, tuple_index : usize,
-            attr_position : usize,
-            tuple_desc : &TupleDesc) -> TupleValue {
-        assert!(self.is_inuse(tuple_index));
-        let mut p = pointer_offset(self.tuple_data, tuple_index * tuple_desc.tuple_len);
-        p = Self::attr_offset(p, tuple_desc, attr_position);
-        unsafe{
-            match tuple_desc.attr_desc[attr_position] {
-                AttrType::Int => TupleValue::Int(read::<i32>(p as *const i32)),
-                AttrType::Float => TupleValue::Float(read::<f32>(p as *const f32)),
-                AttrType::Char{len} => TupleValue::Char(read_string(p, len)),
-            }
-        }
-    }
-    pub fn get_tuple_data(&self, tuple_index : usize, tuple_desc : &TupleDesc) -> Option<TupleData> {
-        if tuple_index >= self.bitmap.slot_sum {
-            return None;
-        }
-        assert!(self.is_inuse(tuple_index));
-        let mut tuple_data = Vec::new();
-        let data = pointer_offset(self.tuple_data, tuple_index * tuple_desc.tuple_len);
-        let mut p = data;
-        for i in 1..tuple_desc.attr_desc.len() + 1 {
-            tuple_data.push(p);
-            p = Self::attr_offset(data, tuple_desc, i);
-        }
-        Some(tuple_data)
-    }
-    pub fn attr_offset(p : DataPtr, tuple_desc : &TupleDesc, attr_position : usize) -> DataPtr {
-        let mut offset = 0;
-        for (attr_type, _) in tuple_desc.attr_desc.iter().zip(0..attr_position) {
-            match attr_type {
-                &AttrType::Int | &AttrType::Float => offset += 4,
-                &AttrType::Char{len} => offset += (len + 3) / 4 * 4,
-            }
-        }
-        pointer_offset(p, offset)
-    }
-    pub fn is_full(&self) -> bool {
-        self.header.first_free_slot == self.bitmap.slot_sum
-    }
-    pub fn is_in_page(&self, ptr : DataPtr) -> bool {
-        let page_start = self.mem_page.borrow().data;
-        let page_end = pointer_offset(page_start, get_page_size());
-        page_start <= ptr && ptr < page_end
-    }
-    pub fn delete(&mut self, ptr : DataPtr) {
-        let d = ptr as usize - self.tuple_data as usize;
-        let index = d / self.tuple_len;
-        assert!(self.is_inuse(index));
-        self.set_inuse(index, false);
-    }
-}
-
-
-pub type TableFileRef = Rc<RefCell<TableFile>>;
-
-#[derive(Debug)]
-pub struct TableFile {
-    pub saved_name : String,
-    pub file : File,
-    pub loaded_pages : HashMap<usize, FilePage>,
-    pub page_sum : usize,  // including pages not loaded in memory
-    pub table : TableRef,
-    pub first_free_page : usize,
-    pub tuple_desc : TupleDesc,  // for FilePage
-}
-
-impl TableFile {
-    pub fn new(mut name : String, table : TableRef, dir : &String) -> TableFile {
-        name.push_str(".table");
-        name = path_join(dir, &name);
-        let file = OpenOptions::new().read(true).write(true).create(true).open(&name).unwrap();
-        let tuple_desc = table.borrow().gen_tuple_desc();
-        TableFile{
-            saved_name : name,
-            file : file,
-            loaded_pages : HashMap::new(),
-            page_sum : 0,
-            table : table,
-            first_free_page : 0,
-            tuple_desc : tuple_desc,
-        }
-    }
-    pub fn init_from_file(&mut self) {
-        is_match!(self.file.seek(SeekFrom::Start(0)), Ok(..));
-        let mut header = [0 as u32, 0 as u32];
-        is_match!(self.file.read_exact(unsafe{
-            from_raw_parts_mut::<u8>((&mut header).as_ptr() as *mut u8, 8)
-        }), Ok(..));
-        self.page_sum = header[0] as usize;
-        self.first_free_page = header[1] as usize;
-    }
-    pub fn read_page_from_file(&mut self, data : DataPtr, page_index : usize) {
-        assert!(page_index < self.page_sum);
-        let page_size = get_page_size();
-        let offset = page_size * (page_index + 1);
-        is_match!(self.file.seek(SeekFrom::Start(offset as u64)), Ok(..));
-        is_match!(self.file.read_exact(unsafe{
-            from_raw_parts_mut::<u8>(data as *mut u8, page_size)
-        }), Ok(..));
-    }
-    pub fn get_page_slot_sum(&self) -> usize {
-        get_slot_sum(self.tuple_desc.tuple_len)
-    }
-    pub fn save_to_file(&mut self) {
-        // the first page only save header for alignment
-        is_match!(self.file.seek(SeekFrom::Start(0)), Ok(..));
-        let header = [self.page_sum as u32, self.first_free_page as u32];
-        is_match!(self.file.write_all(unsafe{
-            from_raw_parts::<u8>((&header).as_ptr() as *const u8, 8)
-        }), Ok(..));
-        let index_list : Vec<_> = self.loaded_pages.iter().map(|(i, _)| *i).collect();
-        for i in index_list.iter() {
-            self.save_page(*i);
-        }
-    }
-    pub fn save_page(&mut self, page_index : usize) {
-        // the first page only save header for alignment
-        let page_size = get_page_size();
-        let offset = page_size * (page_index + 1);
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        is_match!(self.file.seek(SeekFrom::Start(offset as u64)), Ok(..));
-        is_match!(self.file.write_all(unsafe{
-            from_raw_parts::<u8>(page.mem_page.borrow().data as *const u8, page_size)
-        }), Ok(..));
-    }
-    pub fn delete(&mut self, ptr : DataPtr) {
-        for (_, page) in &mut self.loaded_pages {
-            if page.is_in_page(ptr) {
-                page.delete(ptr);
-                return;
-            }
-        }
-    }
-    pub fn insert(&mut self, value_list : &ValueList) {
-        // must call add_page first if need_new_page() is true
-        let first_free_page = self.first_free_page;
-        self.insert_in_page(first_free_page, value_list)
-    }
-    pub fn insert_in_page(&mut self, page_index : usize, value_list : &ValueList) {
-        // for test
-        assert!(page_index < self.page_sum);
-        let file_page = self.loaded_pages.get_mut(&page_index).unwrap();
-        assert!(!file_page.is_full());
-        file_page.insert(value_list, &self.tuple_desc)
-    }
-    pub fn get_tuple_value(&self, position : usize, attr_position : usize) -> TupleValue {
-        // only for test
-        let page_index = position / self.get_page_slot_sum();
-        let tuple_index = position % self.get_page_slot_sum();
-        assert!(self.loaded_pages.get(&page_index).is_some());
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        page.get_tuple_value(tuple_index, attr_position, &self.tuple_desc)
-    }
-    pub fn get_tuple_data(&self, position : usize) -> Option<TupleData> {
-        let page_index = position / self.get_page_slot_sum();
-        let tuple_index = position % self.get_page_slot_sum();
-        assert!(self.loaded_pages.get(&page_index).is_some());
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        page.get_tuple_data(tuple_index, &self.tuple_desc)
-    }
-    pub fn next_tuple_index(&self, page_index : usize, tuple_index : usize) -> Option<usize> {
-        assert!(self.loaded_pages.get(&page_index).is_some());
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        let next = page.bitmap.next_tuple_index(tuple_index);
-        if next == self.get_page_slot_sum() {
-            None
-        } else {
-            Some(next)
-        }
-    }
-    pub fn add_page(&mut self, mem_page : PageRef) {
-        let file_page = FilePage::new(mem_page, self.tuple_desc.tuple_len);
-        let index = file_page.mem_page.borrow().page_index as usize;
-        self.loaded_pages.insert(index, file_page);
-    }
-    pub fn get_fd(&self) -> i32 {
-        self.file.as_raw_fd()
-    }
-    pub fn is_inuse(&self, page_index : usize, tuple_index : usize) -> bool {
-        assert!(self.loaded_pages.get(&page_index).is_some());
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        page.is_inuse(tuple_index)
-    }
-    pub fn gen_index_map(&self) -> IndexMap {
-        self.table.borrow().gen_index_map()
-    }
-}
-
-
-#[derive(Debug)]
-pub struct TableFileManager {
-    files : HashMap<String, TableFileRef>,  // key is table name
-    pub page_pool : PagePool,
-    table_file_dir : String,
-}
-
-impl TableFileManager {
-    pub fn new(config : &Config) -> TableFileManager {
-        let table_file_dir = config.get_str("table_file_dir");
-        ensure_dir_exist(&table_file_dir);
-        TableFileManager{
-            files : HashMap::new(),
-            page_pool : PagePool::new(config.get_int("max_memory_pool_page_num") as usize),
-            table_file_dir : table_file_dir,
-        }
-    }
-    pub fn init_from_file(&mut self, tables : Vec<TableRef>) {
-        for table in &tables {
-            let table_name = table.borrow().name.clone();
-            let mut file_name = table_name.clone();
-            file_name.push_str(".table");
-            let full_path = path_join(&self.table_file_dir, &file_name);
-            assert_file_exist(&full_path);
-            self.create_file(table_name.clone(), table.clone());
-            self.files.get_mut(&table_name).unwrap().borrow_mut().init_from_file();
-        }
-    }
-    pub fn save_all(&mut self) {
-        for (_, f)  in self.files.iter() {
-            f.borrow_mut().save_to_file();
-        }
-    }
-    pub fn delete(&mut self, table : &String, ptr : DataPtr) {
-        let file = self.get_file(table);
-        file.borrow_mut().delete(ptr);
-    }
-    pub fn insert(&mut self, table : &String, value_list : &ValueList) {
-        let file = self.get_file(table);
-        let is_new_page = self.need_new_page(&file);  // fight the borrow checker, RefCell
-        if is_new_page {
-            let new_page_index = file.borrow().page_sum;
-            self.ensure_page_loaded(&file, new_page_index);
-            file.borrow_mut().loaded_pages.get_mut(&new_page_index).unwrap().init_empty_page();
-        } else {
-            let first_free_page = file.borrow().first_free_page;
-            self.ensure_page_loaded(&file, first_free_page);
-        }
-        file.borrow_mut().insert(value_list);
-    }
-    pub fn insert_in_page(&mut self, table : &String, page_index : usize, value_list : &ValueList) {
-        // for test
-        self.prepare_page(table, page_index);
-        let file = self.get_file(table);
-        file.borrow_mut().insert_in_page(page_index, value_list);
-    }
-    pub fn prepare_page(&mut self, table : &String, page_index : usize) {
-        // for test, will init empty page
-        let file = self.get_file(&table);
-        let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
-        if !page_exist {
-            self.ensure_page_loaded(&file, page_index);
-            file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_empty_page();
-        }
-    }
-    pub fn need_new_page(&mut self, file : &TableFileRef) -> bool {
-        let page_sum = file.borrow().page_sum;
-        let mut first_free_page;
-        loop {
-            first_free_page = file.borrow().first_free_page;
-            assert!(first_free_page <= page_sum);
-            if first_free_page == page_sum { break; }
-            self.ensure_page_loaded(&file, first_free_page);
-            let is_full = file.borrow().loaded_pages.get(&first_free_page).unwrap().is_full();  // fight borrow checker
-            if is_full {
-                file.borrow_mut().first_free_page += 1;
-            } else {
-                return false;
-            }
-        }
-        true
-    }
-    pub fn get_file(&mut self, table : &String) -> TableFileRef {
-        self.files.get_mut(table).unwrap().clone()
-    }
-    pub fn get_tuple_value(&mut self, table : &String,
-            position : usize,
-            attr_position : usize) -> TupleValue{
-        // only for test
-        let file = self.files.get(table).unwrap().clone();
-        let page_index = {
-            let f = file.borrow_mut();
-            position / f.tuple_desc.tuple_len
-        };
-        self.prepare_page(table, page_index);
-        // declare v only to fight lifetime checker
-        let v = file.borrow().get_tuple_value(position, attr_position);
-        v
-    }
-    pub fn get_tuple_data(&mut self, table : &String, position : usize) -> Option<TupleData> {
-        let file = self.files.get(table).unwrap().clone();
-        let page_index = {
-            let f = file.borrow_mut();
-            position / f.get_page_slot_sum()
-        };
-        self.ensure_page_loaded(&file, page_index);
-        // declare v only to fight lifetime checker
-        let v = file.borrow().get_tuple_data(position);
-        v
-    }
-    pub fn get_next_tuple_data(&mut self, table : &String, from : usize) -> Option<(TupleData, usize)> {
-        match self.get_next_position(table, from) {
-            Some(position) => Some((self.get_tuple_data(table, position).unwrap(), position)),
-            None => None,
-        }
-    }
-    pub fn get_next_position(&mut self, table : &String, from : usize) -> Option<usize> {
-        let file = self.get_file(table);
-        let page_sum = file.borrow().page_sum;
-        let slot_sum = file.borrow().get_page_slot_sum();
-        let mut page_index = from / slot_sum;
-        let mut tuple_index = from % slot_sum;
-        while page_index < page_sum {
-            let next = file.borrow().next_tuple_index(page_index, tuple_index);
-            match next {
-                Some(i) => return Some(page_index * slot_sum + i),
-                None => {
-                   page_index += 1;
-                   tuple_index = 0;
-                }
-            }
-        }
-        None
-    }
-    pub fn ensure_page_loaded(&mut self, file : &TableFileRef, page_index : usize) {
-        let page_sum = file.borrow().page_sum;
-        assert!(page_index < page_sum || page_index == page_sum);  // old page or new page
-        let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
-        if !page_exist {
-            let fd = file.borrow().get_fd();
-            let mut ptr = null_mut();
-            if let Some(page) = self.page_pool.prepare_page() {
-                // save tail page
-                let old_page_index = page.borrow().page_index;
-                ptr = page.borrow().data;
-                let old_fd = page.borrow().fd;
-                let old_file = self.get_file_by_fd(old_fd);
-                old_file.borrow_mut().save_page(old_page_index as usize);
-                page.borrow_mut().data = null_mut();
-                old_file.borrow_mut().loaded_pages.remove(&(old_page_index as usize));
-                self.page_pool.remove_tail();
-            }
-            self.page_pool.put_page(fd, page_index as u32, ptr);
-            {
-                let page = self.page_pool.get_page(fd, page_index as u32).unwrap();
-                ptr = page.borrow().data.clone();
-            }
-            if page_index < page_sum {
-                file.borrow_mut().read_page_from_file(ptr, page_index);
-                file.borrow_mut().add_page(self.page_pool.get_page(fd, page_index as u32).unwrap());
-                file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_from_page_data();
-            } else {
-                file.borrow_mut().page_sum += 1;
-                file.borrow_mut().add_page(self.page_pool.get_page(fd, page_index as u32).unwrap());
-            }
-        }
-    }
-    pub fn get_file_by_fd(&self, fd : i32) -> TableFileRef {
-        for (_, file) in self.files.iter() {
-            if file.borrow().get_fd() == fd {
-                return file.clone();
-            }
-        }
-        panic!("invalid fd");
-    }
-    pub fn create_file(&mut self, name : String, table : TableRef) {
-        let file = TableFile::new(name.clone(), table, &self.table_file_dir);
-        self.files.insert(name, Rc::new(RefCell::new(file)));
-    }
-    pub fn pin_page(&mut self, fd : i32, page_index : u32) {
-        self.page_pool.pin_page(fd, page_index);
-    }
-    pub fn unpin_page(&mut self, fd : i32, page_index : u32) {
-        self.page_pool.unpin_page(fd, page_index);
-    }
-    pub fn get_unpinned_num(&self) -> usize {
-        self.page_pool.get_unpinned_num()
-    }
-    pub fn get_file_fd(&self, name : &String) -> i32 {
-        self.files.get(name).unwrap().borrow().get_fd()
-    }
-}
-
-fn get_slot_sum(tuple_len : usize) -> usize {
-    let header_size = 2 * size_of::<u32>();  // PageHeader
-    let page_size = get_page_size();
-    // (n + 8 - 1) / 8 + tuple_len * n <= page_size - header_size
-    (8 * (page_size - header_size) - 7) / (8 * tuple_len + 1)
-}
+use std::collections::{HashMap, HashSet};
+use std::cmp::min;
+use std::mem::size_of;
+use std::ptr::null_mut;
+use std::fs::{OpenOptions, File, remove_file, remove_dir, metadata, read_dir};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::slice::from_raw_parts_mut;
+use std::io;
+use std::ops::Range;
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::utils::libwrapper::{get_page_size, fadvise_willneed};
+use ::utils::config::Config;
+use ::utils::compress::{compress, decompress};
+use ::utils::file::{path_join, ensure_dir_exist, read_at_most, remove_files_with_suffix};
+use ::utils::pointer::{read_u32_le, write_u32_le, pointer_offset};
+use ::utils::checksum::{crc32, crc32_update};
+use ::parser::common::{ValueList, Literal};
+use super::buffer::{DataPtr, PageRef, PagePool, PageSnapshot, FileId};
+use super::page_buf::PageBuf;
+use super::table::{TableRef, AttrType, IndexMap};
+use super::tuple::{TupleDesc, TupleValue, TupleData, gen_tuple_value};
+use super::flusher::{PageFlusher, FlushPage};
+use super::double_write::{DoubleWriteBuffer, DoubleWriteRef, PageWrite, try_write_pages};
+use super::writer::{FileWriterRef, os_file_writer};
+use super::undo::{UndoRecord, UndoOp};
+use super::mmap::MappedFile;
+
+
+#[derive(Debug)]
+pub struct PageHeader {
+    pub slot_sum : usize,
+    pub first_free_slot : usize,
+    pub buf : PageBuf,  // [slot_sum u32, first_free_slot u32]
+}
+
+// In the files of PAGE_FORMAT_CHECKSUM the header is followed by [checksum u32],
+// the crc32 of the rest of the page. It's set in the copy written to the file and verified when loaded.
+pub const PAGE_CHECKSUM_OFFSET : usize = 2 * 4;
+
+pub fn get_page_header_size(checksum : bool) -> usize {
+    if checksum { 3 * 4 } else { 2 * 4 }
+}
+
+pub fn page_checksum(data : &[u8]) -> u32 {
+    let crc = crc32(&data[..PAGE_CHECKSUM_OFFSET]);
+    crc32_update(crc, &data[PAGE_CHECKSUM_OFFSET + 4..])
+}
+
+impl PageHeader {
+    pub fn save_to_page_data(&mut self) {
+        self.buf.put_u32(0, self.slot_sum as u32);
+        self.buf.put_u32(size_of::<u32>(), self.first_free_slot as u32);
+    }
+
+    pub fn init_from_page_data(&mut self) {
+        let slot_sum = self.buf.get_u32(0) as usize;
+        assert_eq!(slot_sum, self.slot_sum);
+        self.first_free_slot = self.buf.get_u32(size_of::<u32>()) as usize;
+    }
+}
+
+#[derive(Debug)]
+pub struct BitMap {
+    pub buf : PageBuf,
+    pub slot_sum : usize,
+}
+
+impl BitMap {
+    pub fn next_tuple_index(&self, from : usize) -> usize {
+        if from >= self.slot_sum {
+            return self.slot_sum;
+        }
+        let mut count = from / 8;
+        let mut bit_count = from % 8;
+        while count < (self.slot_sum + 7) / 8 {
+            let n = self.buf.get_u8(count);
+            let mut mask = 1 << bit_count;
+            if n < mask { count += 1; bit_count = 0; continue; }
+            loop {
+                // assume the bits whose index > slot_sum is 0
+                if mask & n > 0 {
+                    return count * 8 + bit_count;
+                }
+                mask *= 2;
+                bit_count += 1;
+            }
+        }
+        self.slot_sum
+    }
+    pub fn get_first_free_slot(&self) -> usize {
+        let mut count = 0;
+        while count < (self.slot_sum + 7) / 8 {
+            let n = self.buf.get_u8(count);
+            if n == 255 { count += 1; continue; }
+            let mut mask : u8 = 1;
+            let mut bit_count = 0;
+            loop {
+                // assume the bits whose index > slot_sum is 0
+                if (255 - mask) | n < 255 {
+                    return count * 8 + bit_count;
+                }
+                mask *= 2;
+                bit_count += 1;
+            }
+        }
+        self.slot_sum
+    }
+    pub fn get_byte_size(&self) -> usize {
+        (self.slot_sum + 7) / 8
+    }
+    pub fn get_inuse_num(&self) -> usize {
+        let mut count = 0;
+        for i in 0..self.get_byte_size() {
+            count += self.buf.get_u8(i).count_ones() as usize;
+        }
+        count
+    }
+    pub fn clean(&mut self) {
+        let byte_size = self.get_byte_size();
+        self.buf.fill(0, byte_size, 0);
+    }
+    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
+        assert!(index < self.slot_sum);
+        let byte_offset = index / 8;
+        let bit_offset = index % 8;
+        let n = 1 << bit_offset;
+        let a = self.buf.get_u8(byte_offset);
+        let b = if inuse {
+            a | n
+        } else {
+            a & (255 - n)
+        };
+        self.buf.put_u8(byte_offset, b);
+    }
+    pub fn is_inuse(&self, index : usize) -> bool {
+        assert!(index < self.slot_sum);
+        let byte_offset = index / 8;
+        let bit_offset = index % 8;
+        let n = 1 << bit_offset;
+        (self.buf.get_u8(byte_offset) & n) > 0
+    }
+}
+
+#[derive(Debug)]
+pub struct FilePage {
+    pub header : PageHeader,
+    pub bitmap : BitMap,
+    pub tuple_buf : PageBuf,  // slot_sum tuples of tuple_len
+    pub page_buf : PageBuf,  // the whole page
+    pub mem_page : PageRef,
+    pub tuple_len : usize,
+    pub checksum : bool,
+}
+
+impl FilePage {
+    pub fn new(mem_page : PageRef, tuple_len : usize, page_size : usize, checksum : bool) -> FilePage {
+        let page_buf = unsafe{ PageBuf::new(mem_page.borrow().data, page_size) };
+        let header_size = get_page_header_size(checksum);
+        let slot_sum = get_slot_sum(tuple_len, page_size, checksum);
+        let bitmap_size = (slot_sum + 7) / 8;
+        FilePage{
+            header : PageHeader{
+                slot_sum : slot_sum,
+                first_free_slot : 0,
+                buf : page_buf.sub(0, 2 * size_of::<u32>()),
+            },
+            bitmap : BitMap{
+                buf : page_buf.sub(header_size, bitmap_size),
+                slot_sum : slot_sum,
+            },
+            tuple_buf : page_buf.sub(header_size + bitmap_size, tuple_len * slot_sum),
+            page_buf : page_buf,
+            mem_page : mem_page,
+            tuple_len : tuple_len,
+            checksum : checksum,
+        }
+    }
+    pub fn init_empty_page(&mut self) {
+        self.header.save_to_page_data();
+        self.bitmap.clean();
+    }
+    pub fn init_from_page_data(&mut self) -> Result<(), String> {
+        // checked before the header, whose slot_sum may be the corrupted part
+        if self.checksum {
+            let saved = self.page_buf.get_u32(PAGE_CHECKSUM_OFFSET);
+            let computed = page_checksum(self.page_buf.as_slice());
+            if saved != computed {
+                return Err(format!("checksum {:08x} does not match {:08x} of the data", saved, computed));
+            }
+        }
+        self.header.init_from_page_data();
+        Ok(())
+    }
+    pub fn save_to_page(&mut self) {
+        self.header.save_to_page_data();
+    }
+    // for a page written from where it is, such as a mapped one
+    pub fn save_checksum(&mut self) {
+        if self.checksum {
+            let crc = page_checksum(self.page_buf.as_slice());
+            self.page_buf.put_u32(PAGE_CHECKSUM_OFFSET, crc);
+        }
+    }
+    pub fn is_inuse(&self, index : usize) -> bool {
+        self.bitmap.is_inuse(index)
+    }
+    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
+        self.bitmap.set_inuse(index, inuse);
+    }
+    pub fn insert(&mut self, value_list : &ValueList, tuple_desc : &TupleDesc) -> usize {
+        // return the tuple index
+        assert!(!self.is_inuse(self.header.first_free_slot));
+        assert_eq!(value_list.len(), tuple_desc.attr_desc.len());
+        assert!(self.header.first_free_slot < self.bitmap.slot_sum);
+
+        let first_free_slot = self.header.first_free_slot;
+        self.set_inuse(first_free_slot, true);
+        self.header.first_free_slot = self.bitmap.get_first_free_slot();
+        self.save_to_page();
+
+        let mut offset = tuple_desc.tuple_len * first_free_slot;
+        for (v, d) in value_list.iter().zip(&tuple_desc.attr_desc) {
+            match (v.literal(), d) {
+                (&Literal::Int(n), &AttrType::Int) => {
+                    self.tuple_buf.put_i32(offset, n);
+                    offset += 4;
+                }
+                (&Literal::Int(n), &AttrType::Float) => {
+                    self.tuple_buf.put_f32(offset, n as f32);
+                    offset += 4;
+                }
+                (&Literal::Float(f), &AttrType::Float) => {
+                    self.tuple_buf.put_f32(offset, f);
+                    offset += 4;
+                }
+                (&Literal::Str(ref s), &AttrType::Char{len}) => {
+                    let aligned_len = (len + 3) / 4 * 4;
+                    self.tuple_buf.put_string(offset, s, len);
+                    offset += aligned_len;
+                }
+                (&Literal::Null, &AttrType::Int) | (&Literal::Null, &AttrType::Float) => {
+                    self.tuple_buf.fill(offset, 4, 0);
+                    offset += 4;
+                }
+                (&Literal::Null, &AttrType::Char{len}) => {
+                    let aligned_len = (len + 3) / 4 * 4;
+                    self.tuple_buf.fill(offset, aligned_len, 0);
+                    offset += aligned_len;
+                }
+                _ => panic!("invalid value, expected {:?}, found {:?}", d, v),
+            }
+        }
+        first_free_slot
+    }
+    pub fn get_tuple_value(&self, tuple_index : usize,
+            attr_position : usize,
+            tuple_desc : &TupleDesc) -> TupleValue {
+        assert!(self.is_inuse(tuple_index));
+        let offset = tuple_index * tuple_desc.tuple_len + Self::attr_offset(tuple_desc, attr_position);
+        match tuple_desc.attr_desc[attr_position] {
+            AttrType::Int => TupleValue::Int(self.tuple_buf.get_i32(offset)),
+            AttrType::Float => TupleValue::Float(self.tuple_buf.get_f32(offset)),
+            AttrType::Char{len} => TupleValue::Char(self.tuple_buf.get_string(offset, len)),
+        }
+    }
+    pub fn get_tuple_data(&self, tuple_index : usize, tuple_desc : &TupleDesc) -> Option<TupleData> {
+        if tuple_index >= self.bitmap.slot_sum {
+            return None;
+        }
+        assert!(self.is_inuse(tuple_index));
+        let tuple = self.tuple_buf.sub(tuple_index * tuple_desc.tuple_len, tuple_desc.tuple_len);
+        let mut tuple_data = Vec::new();
+        for (i, attr_type) in tuple_desc.attr_desc.iter().enumerate() {
+            let attr_len = match attr_type {
+                &AttrType::Int | &AttrType::Float => 4,
+                &AttrType::Char{len} => len,
+            };
+            tuple_data.push(tuple.ptr_at(Self::attr_offset(tuple_desc, i), attr_len));
+        }
+        Some(tuple_data)
+    }
+    pub fn attr_offset(tuple_desc : &TupleDesc, attr_position : usize) -> usize {
+        // from the beginning of the tuple
+        let mut offset = 0;
+        for (attr_type, _) in tuple_desc.attr_desc.iter().zip(0..attr_position) {
+            match attr_type {
+                &AttrType::Int | &AttrType::Float => offset += 4,
+                &AttrType::Char{len} => offset += (len + 3) / 4 * 4,
+            }
+        }
+        offset
+    }
+    pub fn is_full(&self) -> bool {
+        self.header.first_free_slot == self.bitmap.slot_sum
+    }
+    pub fn is_full_for_insert(&self, fill_factor : usize) -> bool {
+        // the slots over fill_factor are left for later use, they are still valid slots
+        self.is_full() || self.bitmap.get_inuse_num() * 100 >= self.bitmap.slot_sum * fill_factor
+    }
+    pub fn delete(&mut self, index : usize) -> bool {
+        // return false if the slot is not in use
+        if !self.is_inuse(index) {
+            return false;
+        }
+        self.set_inuse(index, false);
+        if index < self.header.first_free_slot {
+            self.header.first_free_slot = index;
+            self.save_to_page();
+        }
+        true
+    }
+    pub fn get_slot_bytes(&self, index : usize) -> Vec<u8> {
+        self.tuple_buf.sub(index * self.tuple_len, self.tuple_len).as_slice().to_vec()
+    }
+    pub fn undo(&mut self, op : UndoOp, index : usize, before : &[u8]) {
+        match op {
+            UndoOp::Insert => {
+                // the inserted slot was the first free one
+                assert!(self.is_inuse(index));
+                self.set_inuse(index, false);
+                self.header.first_free_slot = index;
+                self.save_to_page();
+            }
+            UndoOp::Update => {
+                assert!(self.is_inuse(index));
+                self.tuple_buf.put_bytes(index * self.tuple_len, before);
+            }
+            UndoOp::Delete => {
+                assert!(!self.is_inuse(index));
+                self.set_inuse(index, true);
+                self.tuple_buf.put_bytes(index * self.tuple_len, before);
+                // the slot may have become the first free one by the delete
+                if index == self.header.first_free_slot {
+                    self.header.first_free_slot = self.bitmap.get_first_free_slot();
+                    self.save_to_page();
+                }
+            }
+        }
+    }
+}
+
+
+pub type TableFileRef = Rc<RefCell<TableFile>>;
+
+// A compressed page slot is [COMPRESSED_PAGE_TAG, payload_len, payload],
+// a raw page begins with slot_sum which can never be COMPRESSED_PAGE_TAG.
+// The rest of a compressed page slot is unused.
+pub const COMPRESSED_PAGE_TAG : u32 = 0xffffffff;
+pub const COMPRESSED_HEADER_SIZE : usize = 2 * 4;  // [u32, u32]
+
+// the headers in the file are little-endian u32s like the numbers in the pages
+fn read_u32(data : &[u8], index : usize) -> u32 {
+    assert!((index + 1) * 4 <= data.len());
+    unsafe{ read_u32_le(pointer_offset(data.as_ptr() as DataPtr, index * 4)) }
+}
+
+pub fn encode_u32_list(list : &[u32]) -> Vec<u8> {
+    let mut data = vec![0u8; list.len() * 4];
+    for (i, n) in list.iter().enumerate() {
+        unsafe{ write_u32_le(pointer_offset(data.as_mut_ptr() as DataPtr, i * 4), *n) };
+    }
+    data
+}
+
+pub fn get_page_offset(page_index : usize, page_size : usize) -> u64 {
+    // the first page only save header for alignment
+    (page_size * (page_index + 1)) as u64
+}
+
+// [page_sum, first_free_page, page_size, byte_order, page_format, magic, version, tuple_len]
+// at the beginning of the first page.
+// byte_order is FILE_BYTE_ORDER.
+// page_format is PAGE_FORMAT_CHECKSUM, or 0 in the files whose pages have no checksum.
+// magic is FILE_MAGIC. The files saved before it was recorded have only [page_sum, first_free_page]
+// followed by zeros, they are read as version 0 and get the current header when saved again.
+pub const FILE_HEADER_SIZE : usize = 8 * 4;  // [u32; 8]
+pub const FILE_BYTE_ORDER : u32 = 0x01020304;  // [4, 3, 2, 1] in the file
+pub const PAGE_FORMAT_CHECKSUM : u32 = 1;
+pub const FILE_MAGIC : u32 = 0x54534c42;  // "BLST" in the file
+pub const FILE_FORMAT_VERSION : u32 = 1;  // a file of a later version is refused
+pub const LEGACY_FILE_HEADER_SIZE : usize = 2 * 4;  // [page_sum, first_free_page] of version 0
+
+// The pages below first_free_page with slots freed by deletes follow the header as
+// [free_page_num, page_index ...], it's all zeros in the files saved before they were recorded.
+pub const MAX_FREE_PAGES : usize = 64;
+
+// also limited by the space left in the first page after the header
+pub fn get_free_page_capacity(page_size : usize) -> usize {
+    min(MAX_FREE_PAGES, page_size.saturating_sub(FILE_HEADER_SIZE + 4) / 4)
+}
+
+pub fn read_free_pages(file : &File, page_size : usize) -> Vec<usize> {
+    let capacity = get_free_page_capacity(page_size);
+    let mut data = vec![0u8; (capacity + 1) * 4];
+    let read_len = read_at_most(file, &mut data, FILE_HEADER_SIZE as u64);
+    if read_len < 4 {
+        return Vec::new();
+    }
+    let num = min(read_u32(&data, 0) as usize, min(capacity, read_len / 4 - 1));
+    (0..num).map(|i| read_u32(&data, i + 1) as usize).collect()
+}
+
+// the pages tried by the inserts, kept by the undo of a delete which may have changed them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreePages {
+    pub first_free_page : usize,
+    pub pages : Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub page_sum : usize,
+    pub first_free_page : usize,
+    pub page_size : usize,
+    pub checksum : bool,
+    pub tuple_len : usize,  // of the schema the file was created with, 0 if not recorded
+}
+
+pub fn read_file_header(file : &File) -> Result<FileHeader, String> {
+    // fails if the file is shorter than the header, not a table file, of another byte order or version
+    let mut header = [0u8; FILE_HEADER_SIZE];
+    let read_len = read_at_most(file, &mut header, 0);
+    if read_len < LEGACY_FILE_HEADER_SIZE {
+        return Err("file header is missing".to_string());
+    }
+    if (2..8).all(|index| read_u32(&header, index) == 0) {
+        // version 0, saved in pages of the os page size without checksums
+        return Ok(FileHeader{
+            page_sum : read_u32(&header, 0) as usize,
+            first_free_page : read_u32(&header, 1) as usize,
+            page_size : get_page_size(),
+            checksum : false,
+            tuple_len : 0,
+        });
+    }
+    if read_len < FILE_HEADER_SIZE {
+        return Err("file header is missing".to_string());
+    }
+    match read_u32(&header, 5) {
+        FILE_MAGIC => (),
+        magic => return Err(format!("not a table file, the magic is {:08x}", magic)),
+    }
+    if read_u32(&header, 3) != FILE_BYTE_ORDER {
+        return Err("file header is of another byte order".to_string());
+    }
+    match read_u32(&header, 6) {
+        0 => return Err("format version 0 is unknown".to_string()),
+        version if version > FILE_FORMAT_VERSION => return Err(
+            format!("format version {} is newer than the supported {}", version, FILE_FORMAT_VERSION)),
+        _ => (),
+    }
+    Ok(FileHeader{
+        page_sum : read_u32(&header, 0) as usize,
+        first_free_page : read_u32(&header, 1) as usize,
+        page_size : read_u32(&header, 2) as usize,
+        checksum : read_u32(&header, 4) == PAGE_FORMAT_CHECKSUM,
+        tuple_len : read_u32(&header, 7) as usize,
+    })
+}
+
+pub fn encode_page(data : &[u8], compressed : bool, checksum : bool) -> Vec<u8> {
+    // return the bytes to be saved in the page slot of the file, the page in memory is not changed
+    let mut page = data.to_vec();
+    if checksum {
+        let crc = page_checksum(&page);
+        unsafe{ write_u32_le(pointer_offset(page.as_mut_ptr() as DataPtr, PAGE_CHECKSUM_OFFSET), crc) };
+    }
+    if compressed {
+        let payload = compress(&page);
+        // fall back to raw page when compression does not help
+        if COMPRESSED_HEADER_SIZE + payload.len() < page.len() {
+            let mut slot = encode_u32_list(&[COMPRESSED_PAGE_TAG, payload.len() as u32]);
+            slot.extend_from_slice(&payload);
+            return slot;
+        }
+    }
+    page
+}
+
+#[derive(Debug)]
+pub struct TableFile {
+    pub id : FileId,  // the key of its pages in the page pool and the flusher
+    pub saved_name : String,
+    pub file : File,
+    pub shared_file : Arc<File>,  // for the flusher thread
+    pub loaded_pages : HashMap<usize, FilePage>,
+    pub page_sum : usize,  // including pages not loaded in memory
+    pub table : TableRef,
+    pub first_free_page : usize,
+    pub free_pages : Vec<usize>,  // below first_free_page with free slots, the last one is tried first
+    pub tuple_desc : TupleDesc,  // for FilePage
+    pub page_size : usize,  // recorded in the file header, may differ from the one of the manager
+    pub mapped : Option<MappedFile>,  // with the mmap backend
+    pub checksum : bool,  // the pages have checksums, false in the files saved before they were added
+    pub writer : FileWriterRef,
+    pub read_only : Option<String>,  // the write which failed, until a flush of the whole file succeeds
+}
+
+impl TableFile {
+    pub fn new(mut name : String, table : TableRef, dir : &String, page_size : usize) -> TableFile {
+        name.push_str(".table");
+        name = path_join(dir, &name);
+        let file = OpenOptions::new().read(true).write(true).create(true).open(&name).unwrap();
+        let shared_file = Arc::new(file.try_clone().unwrap());
+        let tuple_desc = table.borrow().gen_tuple_desc();
+        TableFile{
+            id : FileId::next(),
+            saved_name : name,
+            file : file,
+            shared_file : shared_file,
+            loaded_pages : HashMap::new(),
+            page_sum : 0,
+            table : table,
+            first_free_page : 0,
+            free_pages : Vec::new(),
+            tuple_desc : tuple_desc,
+            page_size : page_size,
+            mapped : None,
+            checksum : true,
+            writer : os_file_writer(),
+            read_only : None,
+        }
+    }
+    // the pages are mapped instead of read into the page pool afterwards
+    pub fn enable_mmap(&mut self) {
+        assert!(self.loaded_pages.is_empty());
+        self.mapped = Some(MappedFile::new(&self.file));
+    }
+    pub fn is_mapped(&self) -> bool {
+        self.mapped.is_some()
+    }
+    // the address of the page in the mapping, the file is extended to cover it
+    pub fn map_page(&mut self, page_index : usize) -> DataPtr {
+        let offset = get_page_offset(page_index, self.page_size) as usize;
+        self.mapped.as_mut().unwrap().map_range(&self.file, offset, self.page_size)
+    }
+    pub fn init_from_file(&mut self) -> Result<(), String> {
+        // only the header is read, the pages are loaded when they are used
+        let header = match read_file_header(&self.file) {
+            Ok(header) => header,
+            Err(err) => return Err(format!("{}: {}", self.saved_name, err)),
+        };
+        let FileHeader{page_sum, first_free_page, page_size, ..} = header;
+        let tuple_len = self.tuple_desc.tuple_len;
+        if header.tuple_len != 0 && header.tuple_len != tuple_len {
+            return Err(format!("{}: saved with tuples of {} bytes, while the schema has {}",
+                self.saved_name, header.tuple_len, tuple_len));
+        }
+        if page_size < get_page_header_size(header.checksum) + tuple_len + 1 {
+            return Err(format!("{}: page size {} can't hold a tuple of {} bytes",
+                self.saved_name, page_size, tuple_len));
+        }
+        if first_free_page > page_sum {
+            return Err(format!("{}: first_free_page {} is over page_sum {}",
+                self.saved_name, first_free_page, page_sum));
+        }
+        let file_size = match self.file.metadata() {
+            Ok(m) => m.len(),
+            Err(err) => return Err(format!("{}: {}", self.saved_name, err)),
+        };
+        // the last page may be compressed and shorter than a page
+        if page_sum > 0 && file_size <= get_page_offset(page_sum - 1, page_size) {
+            return Err(format!("{}: expected {} pages, the file is truncated to {} bytes",
+                self.saved_name, page_sum, file_size));
+        }
+        self.page_sum = page_sum;
+        self.first_free_page = first_free_page;
+        // the ones from first_free_page are left by an older version, they are tried by inserts anyway
+        self.free_pages = read_free_pages(&self.file, page_size).into_iter()
+            .filter(|page_index| *page_index < first_free_page).collect();
+        self.page_size = page_size;
+        self.checksum = header.checksum;
+        Ok(())
+    }
+    pub fn read_page_from_file(&mut self, data : DataPtr, page_index : usize) -> Result<(), String> {
+        assert!(page_index < self.page_sum);
+        let page = unsafe{ from_raw_parts_mut::<u8>(data as *mut u8, self.page_size) };
+        // a compressed page may be the last thing in the file and shorter than page_size
+        let read_len = if self.is_mapped() {
+            self.page_size  // in place already, extended with zeros
+        } else {
+            read_at_most(&self.file, page, get_page_offset(page_index, self.page_size))
+        };
+        // pages are saved raw or compressed depending on which is smaller,
+        // so check the tag even if the table is not compressed now
+        if read_len >= COMPRESSED_HEADER_SIZE && read_u32(page, 0) == COMPRESSED_PAGE_TAG {
+            let payload_len = read_u32(page, 1) as usize;
+            if COMPRESSED_HEADER_SIZE + payload_len > read_len {
+                return Err(format!("compressed payload of {} bytes is truncated", payload_len));
+            }
+            let payload = page[COMPRESSED_HEADER_SIZE..COMPRESSED_HEADER_SIZE + payload_len].to_vec();
+            if !decompress(&payload, page) {
+                return Err("can't decompress".to_string());
+            }
+        }
+        Ok(())
+    }
+    pub fn get_page_slot_sum(&self) -> usize {
+        get_slot_sum(self.tuple_desc.tuple_len, self.page_size, self.checksum)
+    }
+    // The pages failed are left dirty, and the table is read-only until a flush succeeds
+    // since the file may have some of them written.
+    pub fn save_to_file(&mut self, double_write : &Option<DoubleWriteRef>) -> io::Result<usize> {
+        // the first page only save header for alignment
+        let header = self.gen_header();
+        let result = self.writer.write_at(&self.file, &header, 0);
+        if let Err(err) = result {
+            return Err(self.write_failed("the header", err));
+        }
+        if let Some(ref mapped) = self.mapped {
+            // the kernel writes the pages back, only wait for it
+            let dirty_num = self.get_dirty_pages().len();
+            for page in self.loaded_pages.values_mut() {
+                if page.mem_page.borrow().dirty {
+                    page.save_checksum();
+                }
+                page.mem_page.borrow_mut().dirty = false;
+            }
+            mapped.sync();
+            if dirty_num > 0 {
+                log_debug!("sync {} dirty page(s) of {}", dirty_num, self.saved_name);
+            }
+            return Ok(dirty_num);
+        }
+        let dirty = self.get_dirty_pages();
+        let page_list : Vec<PageWrite> = dirty.iter().filter_map(|i| self.gen_page_write(*i)).collect();
+        if let Err(err) = try_write_pages(&page_list, double_write) {
+            self.mark_dirty_again(&dirty);
+            return Err(self.write_failed("the dirty pages", err));
+        }
+        if !page_list.is_empty() {
+            log_debug!("flush {} dirty page(s) of {}", page_list.len(), self.saved_name);
+        }
+        Ok(page_list.len())
+    }
+    // Like save_to_file, but on the disk when it returns. It stops at the first error
+    // and leaves the pages which are not written dirty, so a later flush can retry them.
+    // The table is writable again once it succeeds.
+    pub fn flush(&mut self, double_write : &Option<DoubleWriteRef>) -> io::Result<usize> {
+        match self.write_and_sync(double_write) {
+            Ok(page_num) => {
+                self.read_only = None;
+                Ok(page_num)
+            }
+            Err(err) => Err(self.write_failed("the file", err)),
+        }
+    }
+    fn write_and_sync(&mut self, double_write : &Option<DoubleWriteRef>) -> io::Result<usize> {
+        let header = self.gen_header();
+        try!(self.writer.write_at(&self.file, &header, 0));
+        let dirty = self.get_dirty_pages();
+        if let Some(ref mapped) = self.mapped {
+            for i in dirty.iter() {
+                self.loaded_pages.get_mut(i).unwrap().save_checksum();
+            }
+            try!(mapped.try_sync());
+            for i in dirty.iter() {
+                self.loaded_pages.get(i).unwrap().mem_page.borrow_mut().dirty = false;
+            }
+        } else {
+            let page_list : Vec<PageWrite> = dirty.iter().filter_map(|i| self.gen_page_write(*i)).collect();
+            if let Err(err) = try_write_pages(&page_list, double_write) {
+                self.mark_dirty_again(&dirty);
+                return Err(err);
+            }
+        }
+        try!(self.file.sync_data());
+        Ok(dirty.len())
+    }
+    fn mark_dirty_again(&self, page_list : &Vec<usize>) {
+        for i in page_list.iter() {
+            self.loaded_pages.get(i).unwrap().mem_page.borrow_mut().dirty = true;
+        }
+    }
+    // the file is in an unknown state, the error is returned
+    fn write_failed(&mut self, what : &str, err : io::Error) -> io::Error {
+        log_error!("writing {} of {} failed, the table is read-only: {}", what, self.saved_name, err);
+        self.read_only = Some(format!("writing {} of {} failed: {}", what, self.saved_name, err));
+        err
+    }
+    fn gen_header(&self) -> Vec<u8> {
+        let page_format = if self.checksum { PAGE_FORMAT_CHECKSUM } else { 0 };
+        let mut header = encode_u32_list(&[self.page_sum as u32, self.first_free_page as u32,
+            self.page_size as u32, FILE_BYTE_ORDER, page_format, FILE_MAGIC, FILE_FORMAT_VERSION,
+            self.tuple_desc.tuple_len as u32]);
+        let mut free_pages = vec![self.free_pages.len() as u32];
+        free_pages.extend(self.free_pages.iter().map(|page_index| *page_index as u32));
+        header.extend(encode_u32_list(&free_pages));
+        header
+    }
+    // a slot of the page is freed, the pages from first_free_page are tried by inserts anyway
+    pub fn add_free_page(&mut self, page_index : usize) {
+        if page_index >= self.first_free_page || self.free_pages.contains(&page_index) {
+            return;
+        }
+        if self.free_pages.len() < get_free_page_capacity(self.page_size) {
+            self.free_pages.push(page_index);
+        } else {
+            // too many to record, the inserts go through the pages from it instead
+            self.first_free_page = page_index;
+            self.free_pages.retain(|i| *i < page_index);
+        }
+    }
+    // the loaded pages at the end of the file which are empty and not pinned, from the last one
+    pub fn get_empty_tail_pages(&self) -> Vec<usize> {
+        let mut page_list = Vec::new();
+        for page_index in (0..self.page_sum).rev() {
+            match self.loaded_pages.get(&page_index) {
+                Some(page) if page.bitmap.get_inuse_num() == 0 && !page.mem_page.borrow().is_pinned() =>
+                    page_list.push(page_index),
+                _ => break,
+            }
+        }
+        page_list
+    }
+    // The pages of get_empty_tail_pages are dropped and the file is truncated to the rest,
+    // return the pages dropped. They should be removed from the flusher before and the page pool after.
+    pub fn shrink_to_fit(&mut self) -> io::Result<Vec<usize>> {
+        let dropped = self.get_empty_tail_pages();
+        if dropped.is_empty() {
+            return Ok(dropped);
+        }
+        let saved = (self.page_sum, self.first_free_page, self.free_pages.clone());
+        let page_sum = self.page_sum - dropped.len();
+        self.page_sum = page_sum;
+        self.first_free_page = min(self.first_free_page, page_sum);
+        self.free_pages.retain(|page_index| *page_index < page_sum);
+        // the header first, the pages left after it by a crash are never read
+        let header = self.gen_header();
+        let result = self.writer.write_at(&self.file, &header, 0);
+        if let Err(err) = result {
+            let (page_sum, first_free_page, free_pages) = saved;
+            self.page_sum = page_sum;
+            self.first_free_page = first_free_page;
+            self.free_pages = free_pages;
+            return Err(self.write_failed("the header", err));
+        }
+        for page_index in dropped.iter() {
+            self.loaded_pages.remove(page_index);
+        }
+        let len = get_page_offset(page_sum, self.page_size);
+        let file = &self.file;
+        let truncated = file.metadata().and_then(|m| if m.len() > len { file.set_len(len) } else { Ok(()) });
+        if let Err(err) = truncated {
+            log_warn!("{} is not truncated to {} pages: {}", self.saved_name, page_sum, err);
+        }
+        log_debug!("drop {} empty page(s) at the end of {}", dropped.len(), self.saved_name);
+        Ok(dropped)
+    }
+    // the loaded pages changed since they were last written, in the order of the file
+    pub fn get_dirty_pages(&self) -> Vec<usize> {
+        let mut dirty : Vec<usize> = self.loaded_pages.iter()
+            .filter(|&(_, page)| page.mem_page.borrow().dirty).map(|(i, _)| *i).collect();
+        dirty.sort();
+        dirty
+    }
+    pub fn save_page(&mut self, page_index : usize, double_write : &Option<DoubleWriteRef>)
+            -> io::Result<bool> {
+        // return false if the page is clean, a page failed is left dirty
+        if self.is_mapped() {
+            // nothing to write, it's synced with the others at the next save_to_file
+            let page = self.loaded_pages.get_mut(&page_index).unwrap();
+            if page.mem_page.borrow().dirty {
+                page.save_checksum();
+            }
+            page.mem_page.borrow_mut().dirty = false;
+            return Ok(false);
+        }
+        let page = match self.gen_page_write(page_index) {
+            Some(page) => page,
+            None => return Ok(false),
+        };
+        match try_write_pages(&vec![page], double_write) {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                self.mark_dirty_again(&vec![page_index]);
+                Err(self.write_failed(&format!("page {}", page_index), err))
+            }
+        }
+    }
+    fn gen_page_write(&self, page_index : usize) -> Option<PageWrite> {
+        // the page is marked clean
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        if !page.mem_page.borrow().dirty {
+            return None;
+        }
+        let data = page.page_buf.as_slice();
+        page.mem_page.borrow_mut().dirty = false;
+        Some(PageWrite{
+            file : self.shared_file.clone(),
+            path : self.saved_name.clone(),
+            offset : get_page_offset(page_index, self.page_size),
+            data : encode_page(data, self.table.borrow().is_compressed(), self.checksum),
+            writer : self.writer.clone(),
+        })
+    }
+    pub fn gen_flush_page(&self, page_index : usize) -> FlushPage {
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        let data = page.mem_page.borrow().data;
+        FlushPage{
+            file : self.shared_file.clone(),
+            path : self.saved_name.clone(),
+            offset : get_page_offset(page_index, self.page_size),
+            data : data as usize,
+            page_size : self.page_size,
+            compressed : self.table.borrow().is_compressed(),
+            checksum : self.checksum,
+            writer : self.writer.clone(),
+        }
+    }
+    pub fn delete(&mut self, page_index : usize, tuple_index : usize) -> bool {
+        assert!(self.loaded_pages.get(&page_index).is_some());
+        self.loaded_pages.get_mut(&page_index).unwrap().delete(tuple_index)
+    }
+    pub fn insert(&mut self, value_list : &ValueList) -> usize {
+        // TableFileManager::insert loads the first free page, it may have been evicted
+        let first_free_page = self.first_free_page;
+        self.insert_in_page(first_free_page, value_list)
+    }
+    pub fn insert_in_page(&mut self, page_index : usize, value_list : &ValueList) -> usize {
+        // return the position of the new tuple
+        assert!(page_index < self.page_sum);
+        let tuple_index = {
+            let file_page = self.loaded_pages.get_mut(&page_index).unwrap();
+            assert!(!file_page.is_full());
+            file_page.insert(value_list, &self.tuple_desc)
+        };
+        self.gen_position(page_index, tuple_index)
+    }
+    pub fn split_position(&self, position : usize) -> (usize, usize) {
+        // position is page_index * slot_sum + tuple_index
+        let slot_sum = self.get_page_slot_sum();
+        (position / slot_sum, position % slot_sum)
+    }
+    pub fn gen_position(&self, page_index : usize, tuple_index : usize) -> usize {
+        page_index * self.get_page_slot_sum() + tuple_index
+    }
+    pub fn get_tuple_value(&self, position : usize, attr_position : usize) -> TupleValue {
+        // only for test
+        let (page_index, tuple_index) = self.split_position(position);
+        assert!(self.loaded_pages.get(&page_index).is_some());
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        page.get_tuple_value(tuple_index, attr_position, &self.tuple_desc)
+    }
+    pub fn get_tuple_data(&self, position : usize) -> Option<TupleData> {
+        let (page_index, tuple_index) = self.split_position(position);
+        assert!(self.loaded_pages.get(&page_index).is_some());
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        page.get_tuple_data(tuple_index, &self.tuple_desc)
+    }
+    pub fn next_tuple_index(&self, page_index : usize, tuple_index : usize) -> Option<usize> {
+        assert!(self.loaded_pages.get(&page_index).is_some());
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        let next = page.bitmap.next_tuple_index(tuple_index);
+        if next == self.get_page_slot_sum() {
+            None
+        } else {
+            Some(next)
+        }
+    }
+    pub fn add_page(&mut self, page_index : usize, mem_page : PageRef) {
+        // page_index is the page number in this table file, not the one kept by the pool
+        assert!(page_index < self.page_sum);
+        assert!(self.loaded_pages.get(&page_index).is_none());
+        if let Some(ref mapped) = self.mapped {
+            assert!(mapped.contains(mem_page.borrow().data, self.page_size));
+        }
+        let file_page = FilePage::new(mem_page, self.tuple_desc.tuple_len, self.page_size, self.checksum);
+        self.loaded_pages.insert(page_index, file_page);
+    }
+    pub fn get_id(&self) -> FileId {
+        self.id
+    }
+    pub fn is_inuse(&self, page_index : usize, tuple_index : usize) -> bool {
+        assert!(self.loaded_pages.get(&page_index).is_some());
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        page.is_inuse(tuple_index)
+    }
+    pub fn gen_index_map(&self) -> IndexMap {
+        self.table.borrow().gen_index_map()
+    }
+}
+
+
+#[derive(Debug)]
+pub struct TableFileManager {
+    // declared first so that the flusher thread is stopped before the pages are freed
+    flusher : Option<PageFlusher>,
+    files : HashMap<String, TableFileRef>,  // key is table name
+    pub page_pool : PagePool,
+    table_file_dir : String,
+    foreground_flush_num : usize,
+    double_write : Option<DoubleWriteRef>,
+    recovered_page_num : usize,  // restored from double write buffer
+    default_fill_factor : usize,
+    page_size : usize,  // of the files created, the existing ones keep their own
+    scan_prefetch : ScanPrefetch,
+    scan_prefetch_pages : usize,
+    storage_backend : StorageBackend,
+    stats : StorageStats,
+    writer : FileWriterRef,  // of all the files
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreErrorType {
+    InvalidValuesNum,
+    Corrupted,  // a page in the file doesn't match its checksum, it's not kept in the pool
+    EvictFailed,  // none of the unpinned pages in the pool can be written to make room for a page
+    ReadOnly,  // see check_writable
+    TupleNotFound,  // the slot to delete is out of the file or not in use
+}
+
+// a tuple which can't be read or changed, nothing is changed then
+#[derive(Debug, Clone)]
+pub struct StoreError {
+    pub error_type : StoreErrorType,
+    pub error_msg : String,
+}
+
+// where the bytes of the pages are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Buffered,  // read into and written from the page pool
+    Mmap,  // in the mapping of the file, see MappedFile
+}
+
+impl StorageBackend {
+    pub fn from_name(name : &str) -> Option<StorageBackend> {
+        match name {
+            "buffered" => Some(StorageBackend::Buffered),
+            "mmap" => Some(StorageBackend::Mmap),
+            _ => None,
+        }
+    }
+}
+
+// how the pages ahead of a file scan are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanPrefetch {
+    None,
+    Pool,  // into the free frames of the page pool
+    Fadvise,  // into the cache of the os
+}
+
+impl ScanPrefetch {
+    pub fn from_name(name : &str) -> Option<ScanPrefetch> {
+        match name {
+            "none" => Some(ScanPrefetch::None),
+            "pool" => Some(ScanPrefetch::Pool),
+            "fadvise" => Some(ScanPrefetch::Fadvise),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlushStats {
+    pub background : usize,  // pages written by the flusher thread
+    pub foreground : usize,  // pages written at eviction or checkpoint
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StorageStats {
+    pub rows_read : usize,
+    pub rows_written : usize,  // inserted, updated or deleted
+    pub page_hits : usize,  // found in the page pool
+    pub page_misses : usize,
+    pub page_evictions : usize,
+    pub page_prefetches : usize,  // loaded ahead of a scan, not counted as misses
+}
+
+impl TableFileManager {
+    pub fn new(config : &Config) -> TableFileManager {
+        let table_file_dir = config.get_path("table_file_dir");
+        ensure_dir_exist(&table_file_dir);
+        let double_write = if config.get_bool("double_write") {
+            let path = path_join(&table_file_dir, &"double_write.buf".to_string());
+            Some(DoubleWriteBuffer::make_ref(&path))
+        } else {
+            None
+        };
+        // 0 to disable the flusher thread
+        let flush_interval = config.get_usize("flush_interval_ms");
+        TableFileManager{
+            flusher : if flush_interval > 0 {
+                Some(PageFlusher::new(Duration::from_millis(flush_interval as u64), double_write.clone()))
+            } else {
+                None
+            },
+            files : HashMap::new(),
+            page_pool : PagePool::new(config.get_usize("max_memory_pool_page_num")),
+            table_file_dir : table_file_dir,
+            foreground_flush_num : 0,
+            double_write : double_write,
+            recovered_page_num : 0,
+            default_fill_factor : config.get_usize("default_fill_factor"),
+            page_size : match config.get_usize("page_size") {
+                0 => get_page_size(),
+                page_size => page_size,
+            },
+            scan_prefetch : ScanPrefetch::from_name(&config.get_str("scan_prefetch")).unwrap(),
+            scan_prefetch_pages : config.get_usize("scan_prefetch_pages"),
+            storage_backend : StorageBackend::from_name(&config.get_str("storage_backend")).unwrap(),
+            stats : StorageStats{
+                rows_read : 0,
+                rows_written : 0,
+                page_hits : 0,
+                page_misses : 0,
+                page_evictions : 0,
+                page_prefetches : 0,
+            },
+            writer : os_file_writer(),
+        }
+    }
+    pub fn init_from_file(&mut self, tables : Vec<TableRef>) -> Result<(), String> {
+        // every table file is checked and all the problems are returned
+        // repair the pages torn by a crash before reading any of them
+        if let Some(ref double_write) = self.double_write {
+            self.recovered_page_num = double_write.lock().unwrap().recover();
+        }
+        let mut errors = Vec::new();
+        for table in &tables {
+            let table_name = table.borrow().name.clone();
+            let mut file_name = table_name.clone();
+            file_name.push_str(".table");
+            let full_path = path_join(&self.table_file_dir, &file_name);
+            // TableFile::new would create it
+            match metadata(&full_path) {
+                Ok(ref m) if m.is_file() => (),
+                Ok(..) => {
+                    errors.push(format!("{}: not a file", full_path));
+                    continue;
+                }
+                Err(err) => {
+                    errors.push(format!("{}: {}", full_path, err));
+                    continue;
+                }
+            }
+            self.create_file(table_name.clone(), table.clone());
+            if let Err(err) = self.files.get_mut(&table_name).unwrap().borrow_mut().init_from_file() {
+                errors.push(err);
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+    }
+    // The files of all the tables of the catalog in table_file_dir, opened for the queries after a restart.
+    // A .table file of no table, such as one left by a table dropped before the ddl journal,
+    // is kept on the disk and returned with the others.
+    pub fn from_files(config : &Config, tables : Vec<TableRef>)
+            -> Result<(TableFileManager, Vec<String>), String> {
+        let names : HashSet<String> = tables.iter().map(|t| t.borrow().name.clone()).collect();
+        let mut manager = TableFileManager::new(config);
+        try!(manager.init_from_file(tables));
+        let entries = match read_dir(&manager.table_file_dir) {
+            Ok(entries) => entries,
+            Err(err) => return Err(format!("{}: {}", manager.table_file_dir, err)),
+        };
+        let mut unknown = Vec::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_file = entry.file_type().map(|t| t.is_file()).unwrap_or(false);
+            let table = file_name.trim_right_matches(".table");
+            if is_file && file_name.ends_with(".table") && !names.contains(table) {
+                let path = path_join(&manager.table_file_dir, &file_name);
+                unknown.push(format!("{}: not in the catalog", path));
+            }
+        }
+        unknown.sort();  // read_dir has no order
+        Ok((manager, unknown))
+    }
+    pub fn save_all(&mut self) {
+        // a table failed is logged and left read-only, the others are still saved
+        self.sync_flushed_pages();
+        for (_, f)  in self.files.iter() {
+            if let Ok(page_num) = f.borrow_mut().save_to_file(&self.double_write) {
+                self.foreground_flush_num += page_num;
+            }
+        }
+    }
+    // The headers and the dirty pages of all the tables on the disk, it goes on after a table fails
+    // and returns the error of each failed table.
+    pub fn flush_all(&mut self) -> Vec<(String, io::Error)> {
+        self.sync_flushed_pages();
+        let mut names : Vec<String> = self.files.keys().cloned().collect();
+        names.sort();
+        let mut errors = Vec::new();
+        for name in names.into_iter() {
+            let file = self.get_file(&name);
+            let result = file.borrow_mut().flush(&self.double_write);  // fight borrow checker
+            match result {
+                Ok(page_num) => self.foreground_flush_num += page_num,
+                Err(err) => errors.push((name, err)),
+            }
+        }
+        errors
+    }
+    // the header and the dirty pages of one table, on the disk when it returns
+    pub fn sync_file(&mut self, table : &String) -> io::Result<()> {
+        let file = self.get_file(table);
+        let page_num = try!(file.borrow_mut().save_to_file(&self.double_write));
+        self.foreground_flush_num += page_num;
+        let result = file.borrow().file.sync_data();  // fight borrow checker
+        result
+    }
+    // Truncates the empty pages at the end of the file of the table, return the number of pages removed.
+    // They are loaded from the last one to be checked, a page pinned by a scan stops it.
+    pub fn shrink_file(&mut self, table : &String) -> io::Result<usize> {
+        let file = self.get_file(table);
+        if file.borrow().is_mapped() {
+            return Ok(0);  // the mapping is not shrunk
+        }
+        let file_id = file.borrow().get_id();
+        let mut removed = 0;
+        loop {
+            let page_sum = file.borrow().page_sum;
+            if page_sum == 0 || !self.can_load_page(&file, page_sum - 1)
+                    || self.try_ensure_page_loaded(&file, page_sum - 1).is_err() {
+                break;
+            }
+            // the flusher holds the pages by their address and writes them by their offset
+            let page_list = file.borrow().get_empty_tail_pages();
+            for page_index in page_list.iter() {
+                self.remove_from_flusher(file_id, *page_index as u32);
+            }
+            let dropped = try!(file.borrow_mut().shrink_to_fit());
+            if dropped.is_empty() {
+                break;
+            }
+            for page_index in dropped.iter() {
+                self.page_pool.remove_page(file_id, *page_index as u32);
+            }
+            removed += dropped.len();
+        }
+        Ok(removed)
+    }
+    // all the files write through it afterwards, for the tests to fail the writes
+    pub fn set_writer(&mut self, writer : FileWriterRef) {
+        for file in self.files.values() {
+            file.borrow_mut().writer = writer.clone();
+        }
+        self.writer = writer;
+    }
+    // A table can't be changed after a write of its file failed, since the file is in an unknown state.
+    // The reads go on, and a flush writing all of it makes it writable again.
+    pub fn check_writable(&self, table : &String) -> Result<(), StoreError> {
+        match self.files.get(table).and_then(|file| file.borrow().read_only.clone()) {
+            Some(reason) => Err(StoreError{
+                error_type : StoreErrorType::ReadOnly,
+                error_msg : format!("table {} is read-only until a flush succeeds, {}", table, reason),
+            }),
+            None => Ok(()),
+        }
+    }
+    pub fn close(&mut self) {
+        // the pages still queued in the flusher are dirty and written by save_all
+        self.flusher = None;
+        self.save_all();
+    }
+    pub fn drop_all(&mut self) {
+        // Remove the files of all the tables, the pages in memory are discarded without saving.
+        // Like close, the manager should not be used after it.
+        self.flusher = None;
+        let names : Vec<String> = self.files.keys().cloned().collect();
+        for name in names.iter() {
+            self.remove_file(name);
+        }
+        self.double_write = None;
+        // also the files of the tables dropped before and the shadow files of rewrite
+        remove_files_with_suffix(&self.table_file_dir, ".table").ok();
+        remove_file(Path::new(&self.table_file_dir).join("double_write.buf")).ok();
+        remove_dir(&self.table_file_dir).ok();  // only when nothing else is left in it
+    }
+    pub fn delete(&mut self, table : &String, position : usize) -> Result<(), StoreError> {
+        // the page may be evicted after the tuple is scanned, so load it again
+        let file = self.get_file(table);
+        let (page_index, tuple_index) = file.borrow().split_position(position);
+        if page_index >= file.borrow().page_sum {
+            return Err(StoreError{
+                error_type : StoreErrorType::TupleNotFound,
+                error_msg : format!("tuple {} of table {} does not exist", position, table),
+            });
+        }
+        try!(self.try_ensure_page_loaded(&file, page_index));
+        let file_id = file.borrow().get_id();
+        self.pin_page(file_id, page_index as u32);  // keep the flusher away from the page
+        let deleted = file.borrow_mut().delete(page_index, tuple_index);
+        if deleted {
+            self.mark_dirty(&file, page_index);
+            file.borrow_mut().add_free_page(page_index);
+            self.stats.rows_written += 1;
+        }
+        self.unpin_page(file_id, page_index as u32);
+        if deleted {
+            Ok(())
+        } else {
+            Err(StoreError{
+                error_type : StoreErrorType::TupleNotFound,
+                error_msg : format!("tuple {} of table {} is not in use", position, table),
+            })
+        }
+    }
+    pub fn get_slot_bytes(&mut self, table : &String, position : usize) -> Result<Vec<u8>, StoreError> {
+        // the before-image of an undo record
+        let file = self.get_file(table);
+        let (page_index, tuple_index) = file.borrow().split_position(position);
+        try!(self.try_ensure_page_loaded(&file, page_index));
+        let bytes = file.borrow().loaded_pages.get(&page_index).unwrap().get_slot_bytes(tuple_index);
+        Ok(bytes)
+    }
+    pub fn undo(&mut self, table : &String, record : &UndoRecord) -> Result<(), StoreError> {
+        // the page may be evicted after it is changed, so load it again
+        let file = self.get_file(table);
+        let (page_index, tuple_index) = file.borrow().split_position(record.position);
+        try!(self.try_ensure_page_loaded(&file, page_index));
+        let file_id = file.borrow().get_id();
+        self.pin_page(file_id, page_index as u32);  // keep the flusher away from the page
+        file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap()
+            .undo(record.op, tuple_index, &record.before);
+        self.mark_dirty(&file, page_index);
+        if record.op == UndoOp::Insert {
+            let first_free_page = file.borrow().first_free_page;
+            file.borrow_mut().first_free_page = min(first_free_page, page_index);
+        }
+        if let Some(ref free_pages) = record.free_pages {
+            let mut file = file.borrow_mut();
+            file.first_free_page = free_pages.first_free_page;
+            file.free_pages = free_pages.pages.clone();
+        }
+        self.unpin_page(file_id, page_index as u32);
+        Ok(())
+    }
+    pub fn get_free_pages(&mut self, table : &String) -> FreePages {
+        let file = self.get_file(table);
+        let file = file.borrow();
+        FreePages{ first_free_page : file.first_free_page, pages : file.free_pages.clone() }
+    }
+    pub fn mark_dirty_by_position(&mut self, table : &String, position : usize) {
+        // for tuple modified in place, the page should still be pinned by the scan
+        let file = self.get_file(table);
+        let (page_index, _) = file.borrow().split_position(position);
+        self.mark_dirty(&file, page_index);
+        self.stats.rows_written += 1;
+    }
+    pub fn mark_dirty(&mut self, file : &TableFileRef, page_index : usize) {
+        // should be called after the page is modified
+        let file = file.borrow();
+        let mem_page = file.loaded_pages.get(&page_index).unwrap().mem_page.clone();
+        mem_page.borrow_mut().mark_dirty();
+        if mem_page.borrow().is_pinned() {
+            return;  // will be added to the flusher when unpinned
+        }
+        if file.is_mapped() {
+            return;  // written back by the kernel
+        }
+        if let Some(ref flusher) = self.flusher {
+            flusher.add_page((file.get_id(), page_index as u32), file.gen_flush_page(page_index));
+        }
+    }
+    fn remove_from_flusher(&mut self, file_id : FileId, page_index : u32) {
+        // after this the flusher will not touch the page
+        let flushed = match self.flusher {
+            Some(ref flusher) => flusher.remove_page((file_id, page_index)),
+            None => false,
+        };
+        if flushed {
+            let file = self.get_file_by_id(file_id);
+            let file = file.borrow();
+            file.loaded_pages.get(&(page_index as usize)).unwrap().mem_page.borrow_mut().dirty = false;
+        }
+    }
+    fn sync_flushed_pages(&mut self) {
+        let flushed = match self.flusher {
+            Some(ref flusher) => flusher.take_flushed(),
+            None => return,
+        };
+        for (file_id, page_index) in flushed {
+            let file = self.get_file_by_id(file_id);
+            let file = file.borrow();
+            if let Some(page) = file.loaded_pages.get(&(page_index as usize)) {
+                page.mem_page.borrow_mut().dirty = false;
+            }
+        }
+    }
+    pub fn get_recovered_page_num(&self) -> usize {
+        self.recovered_page_num
+    }
+    pub fn get_flush_stats(&self) -> FlushStats {
+        FlushStats{
+            background : match self.flusher {
+                Some(ref flusher) => flusher.get_background_num(),
+                None => 0,
+            },
+            foreground : self.foreground_flush_num,
+        }
+    }
+    pub fn get_storage_stats(&self) -> StorageStats {
+        self.stats
+    }
+    // the pages in the pool with the names of their tables, from the most recently accessed
+    pub fn get_buffer_snapshot(&self) -> Vec<(String, PageSnapshot)> {
+        let names : HashMap<FileId, String> = self.files.iter()
+            .map(|(name, file)| (file.borrow().get_id(), name.clone())).collect();
+        self.page_pool.snapshot().into_iter().map(|page| {
+            // a page is removed from the pool with its file, so the name is always found
+            let name = names.get(&page.file_id).cloned().unwrap_or(format!("file {}", page.file_id.0));
+            (name, page)
+        }).collect()
+    }
+    pub fn get_dirty_page_num(&self) -> usize {
+        self.files.values().map(|f| f.borrow().get_dirty_pages().len()).sum()
+    }
+    // a scan leaves none, a change marks only the pages of the tuples it touches
+    pub fn get_dirty_pages(&mut self, table : &String) -> Vec<usize> {
+        let file = self.get_file(table);
+        let dirty = file.borrow().get_dirty_pages();
+        dirty
+    }
+    pub fn insert(&mut self, table : &String, value_list : &ValueList) -> usize {
+        // return the position of the new tuple
+        check_ok!(self.try_insert(table, value_list))
+    }
+    // Fails without inserting if a page can't be loaded, or if the table is read-only,
+    // which may be caused by evicting one of its own pages for the insert.
+    pub fn try_insert(&mut self, table : &String, value_list : &ValueList) -> Result<usize, StoreError> {
+        try!(self.check_writable(table));
+        let file = self.get_file(table);
+        let free_page = self.find_free_page(&file);  // fight the borrow checker, RefCell
+        let page_index = match try!(free_page) {
+            Some(page_index) => {
+                try!(self.try_ensure_page_loaded(&file, page_index));
+                page_index
+            }
+            None => {
+                let new_page_index = file.borrow().page_sum;
+                try!(self.try_ensure_page_loaded(&file, new_page_index));
+                file.borrow_mut().loaded_pages.get_mut(&new_page_index).unwrap().init_empty_page();
+                new_page_index
+            }
+        };
+        try!(self.check_writable(table));
+        let file_id = file.borrow().get_id();
+        self.pin_page(file_id, page_index as u32);  // keep the flusher away from the page
+        let position = file.borrow_mut().insert_in_page(page_index, value_list);
+        self.mark_dirty(&file, page_index);
+        self.unpin_page(file_id, page_index as u32);
+        self.stats.rows_written += 1;
+        Ok(position)
+    }
+    pub fn insert_in_page(&mut self, table : &String, page_index : usize, value_list : &ValueList) {
+        // for test
+        self.prepare_page(table, page_index);
+        let file = self.get_file(table);
+        let file_id = file.borrow().get_id();
+        self.pin_page(file_id, page_index as u32);
+        file.borrow_mut().insert_in_page(page_index, value_list);
+        self.mark_dirty(&file, page_index);
+        self.unpin_page(file_id, page_index as u32);
+    }
+    pub fn prepare_page(&mut self, table : &String, page_index : usize) {
+        // for test, will init empty page
+        let file = self.get_file(&table);
+        let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
+        if !page_exist {
+            self.ensure_page_loaded(&file, page_index);
+            file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_empty_page();
+            self.mark_dirty(&file, page_index);
+        }
+    }
+    // The page for an insert, one of free_pages before the ones from first_free_page,
+    // None if a new page is needed. The pages full for the insert are dropped from both.
+    pub fn find_free_page(&mut self, file : &TableFileRef) -> Result<Option<usize>, StoreError> {
+        let page_sum = file.borrow().page_sum;
+        let fill_factor = file.borrow().table.borrow().fill_factor.unwrap_or(self.default_fill_factor);
+        loop {
+            let page_index = match file.borrow().free_pages.last() {
+                Some(page_index) => *page_index,
+                None => break,
+            };
+            try!(self.try_ensure_page_loaded(&file, page_index));
+            let is_full = file.borrow().loaded_pages.get(&page_index).unwrap()
+                .is_full_for_insert(fill_factor);  // fight borrow checker
+            if is_full {
+                file.borrow_mut().free_pages.pop();
+            } else {
+                return Ok(Some(page_index));
+            }
+        }
+        let mut first_free_page;
+        loop {
+            first_free_page = file.borrow().first_free_page;
+            assert!(first_free_page <= page_sum);
+            if first_free_page == page_sum { break; }
+            try!(self.try_ensure_page_loaded(&file, first_free_page));
+            let is_full = file.borrow().loaded_pages.get(&first_free_page).unwrap()
+                .is_full_for_insert(fill_factor);  // fight borrow checker
+            if is_full {
+                file.borrow_mut().first_free_page += 1;
+            } else {
+                return Ok(Some(first_free_page));
+            }
+        }
+        Ok(None)
+    }
+    pub fn get_file(&mut self, table : &String) -> TableFileRef {
+        self.files.get_mut(table).unwrap().clone()
+    }
+    pub fn get_tuple_value(&mut self, table : &String,
+            position : usize,
+            attr_position : usize) -> TupleValue{
+        // only for test
+        let file = self.files.get(table).unwrap().clone();
+        let (page_index, _) = file.borrow().split_position(position);
+        self.ensure_page_loaded(&file, page_index);
+        // declare v only to fight lifetime checker
+        let v = file.borrow().get_tuple_value(position, attr_position);
+        v
+    }
+    pub fn get_tuple_data(&mut self, table : &String, position : usize)
+            -> Result<Option<TupleData>, StoreError> {
+        let file = self.files.get(table).unwrap().clone();
+        let (page_index, _) = file.borrow().split_position(position);
+        try!(self.try_ensure_page_loaded(&file, page_index));
+        // declare v only to fight lifetime checker
+        let v = file.borrow().get_tuple_data(position);
+        if v.is_some() {
+            self.stats.rows_read += 1;
+        }
+        Ok(v)
+    }
+    pub fn get_page_tuple_values(&mut self, table : &String, page_index : usize)
+            -> Result<Vec<Vec<TupleValue>>, StoreError> {
+        // the values are copied, so the page can be evicted afterwards
+        let file = self.get_file(table);
+        try!(self.try_ensure_page_loaded(&file, page_index));
+        let file = file.borrow();
+        let mut result = Vec::new();
+        let mut tuple_index = 0;
+        while let Some(i) = file.next_tuple_index(page_index, tuple_index) {
+            let tuple_data = file.get_tuple_data(file.gen_position(page_index, i)).unwrap();
+            result.push(gen_tuple_value(&file.tuple_desc.attr_desc, tuple_data));
+            tuple_index = i + 1;
+        }
+        Ok(result)
+    }
+    // every slot in use of the table in the physical order, see TableSlots
+    pub fn scan_slots(&mut self, table : &String) -> TableSlots {
+        let file = self.get_file(table);
+        TableSlots{
+            file_manager : self,
+            file : file,
+            page_index : 0,
+            tuple_index : 0,
+            pinned : None,
+        }
+    }
+    pub fn get_next_tuple_data(&mut self, table : &String, from : usize)
+            -> Result<Option<(TupleData, usize)>, StoreError> {
+        match try!(self.get_next_position(table, from)) {
+            Some(position) => Ok(Some((try!(self.get_tuple_data(table, position)).unwrap(), position))),
+            None => Ok(None),
+        }
+    }
+    pub fn get_next_position(&mut self, table : &String, from : usize) -> Result<Option<usize>, StoreError> {
+        let file = self.get_file(table);
+        let page_sum = file.borrow().page_sum;
+        let (mut page_index, mut tuple_index) = file.borrow().split_position(from);
+        while page_index < page_sum {
+            // nothing is pinned between calls, the page may have been evicted
+            try!(self.try_ensure_page_loaded(&file, page_index));
+            let next = file.borrow().next_tuple_index(page_index, tuple_index);
+            match next {
+                Some(i) => return Ok(Some(file.borrow().gen_position(page_index, i))),
+                None => {
+                   page_index += 1;
+                   tuple_index = 0;
+                }
+            }
+        }
+        Ok(None)
+    }
+    pub fn ensure_page_loaded(&mut self, file : &TableFileRef, page_index : usize) {
+        check_ok!(self.try_ensure_page_loaded(file, page_index));
+    }
+    // fails if the page in the file doesn't match its checksum or no page can be evicted for it
+    pub fn try_ensure_page_loaded(&mut self, file : &TableFileRef, page_index : usize)
+            -> Result<(), StoreError> {
+        let page_sum = file.borrow().page_sum;
+        assert!(page_index < page_sum || page_index == page_sum);  // old page or new page
+        let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
+        if page_exist {
+            self.stats.page_hits += 1;
+        } else {
+            self.stats.page_misses += 1;
+            try!(self.load_page(file, page_index));
+        }
+        Ok(())
+    }
+    // False if all the pages in the pool are pinned. They are only unpinned by the statement holding them
+    // on this thread, so waiting for a page to be evicted would never end.
+    pub fn can_load_page(&self, file : &TableFileRef, page_index : usize) -> bool {
+        file.borrow().loaded_pages.get(&page_index).is_some() || self.page_pool.get_unpinned_num() > 0
+    }
+    // the number of pages a file scan reads ahead, 0 if disabled
+    pub fn get_scan_prefetch_pages(&self) -> usize {
+        match self.scan_prefetch {
+            ScanPrefetch::None => 0,
+            _ => self.scan_prefetch_pages,
+        }
+    }
+    // A hint that the pages in page_range will be read soon, the pages out of the file are skipped.
+    // The pool mode stops at the first page which would evict another one,
+    // so the pinned and the recently used pages always stay.
+    pub fn prefetch(&mut self, table : &String, page_range : Range<usize>) {
+        let file = self.get_file(table);
+        let end = min(page_range.end, file.borrow().page_sum);
+        if page_range.start >= end {
+            return;
+        }
+        match self.scan_prefetch {
+            ScanPrefetch::None => (),
+            ScanPrefetch::Pool => {
+                for page_index in page_range.start..end {
+                    if file.borrow().loaded_pages.get(&page_index).is_some() {
+                        continue;
+                    }
+                    if self.page_pool.get_load() >= self.page_pool.get_capacity() {
+                        break;
+                    }
+                    // a corrupted page is left to the scan reaching it
+                    if self.load_page(&file, page_index).is_err() {
+                        break;
+                    }
+                    self.stats.page_prefetches += 1;
+                }
+            }
+            ScanPrefetch::Fadvise => {
+                let file = file.borrow();
+                let offset = get_page_offset(page_range.start, file.page_size);
+                fadvise_willneed(&file.file, offset, (end - page_range.start) * file.page_size);
+            }
+        }
+    }
+    fn load_page(&mut self, file : &TableFileRef, page_index : usize) -> Result<(), StoreError> {
+        let page_sum = file.borrow().page_sum;
+        let file_id = file.borrow().get_id();
+        let page_size = file.borrow().page_size;
+        let mapped = file.borrow().is_mapped();
+        let mut ptr = null_mut();
+        // a page which can't be written is kept and moved to the head, so the next one is tried
+        let mut tries = 0;
+        while let Some(page) = self.page_pool.prepare_page() {
+            // save tail page
+            let old_page_index = page.borrow().page_index;
+            let old_file_id = page.borrow().file_id;
+            self.remove_from_flusher(old_file_id, old_page_index);
+            let old_file = self.get_file_by_id(old_file_id);
+            let result = old_file.borrow_mut().save_page(old_page_index as usize, &self.double_write);
+            let dirty = match result {
+                Ok(dirty) => dirty,
+                Err(err) => {
+                    self.page_pool.get_page(old_file_id, old_page_index);
+                    tries += 1;
+                    if tries < self.page_pool.get_unpinned_num() {
+                        continue;
+                    }
+                    return Err(StoreError{
+                        error_type : StoreErrorType::EvictFailed,
+                        error_msg : format!("page {} of {} can't be loaded, no page can be evicted for it: {}",
+                            page_index, file.borrow().saved_name, err),
+                    });
+                }
+            };
+            self.stats.page_evictions += 1;
+            if dirty {
+                self.foreground_flush_num += 1;
+            }
+            log_debug!("evict page {} of {}{} for page {} of {}", old_page_index,
+                old_file.borrow().saved_name, if dirty { " after writing it" } else { "" },
+                page_index, file.borrow().saved_name);
+            // the memory is freed with the old page if it's of another size,
+            // and a page in a mapping stays there
+            if old_file.borrow().page_size == page_size && !page.borrow().mapped && !mapped {
+                ptr = page.borrow().data;
+                page.borrow_mut().data = null_mut();
+            }
+            old_file.borrow_mut().loaded_pages.remove(&(old_page_index as usize));
+            self.page_pool.remove_tail();
+            break;
+        }
+        if mapped {
+            ptr = file.borrow_mut().map_page(page_index);
+            self.page_pool.put_mapped_page(file_id, page_index as u32, ptr);
+        } else {
+            self.page_pool.put_page(file_id, page_index as u32, ptr, page_size);
+        }
+        {
+            let page = self.page_pool.get_page(file_id, page_index as u32).unwrap();
+            ptr = page.borrow().data.clone();
+        }
+        if page_index < page_sum {
+            let read_result = file.borrow_mut().read_page_from_file(ptr, page_index);
+            file.borrow_mut().add_page(page_index, self.page_pool.get_page(file_id, page_index as u32).unwrap());
+            let result = match read_result {
+                Ok(()) => file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_from_page_data(),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                file.borrow_mut().loaded_pages.remove(&page_index);
+                self.page_pool.remove_page(file_id, page_index as u32);
+                let name = file.borrow().saved_name.clone();
+                return Err(StoreError{
+                    error_type : StoreErrorType::Corrupted,
+                    error_msg : format!("page {} of {} is corrupted: {}", page_index, name, err),
+                });
+            }
+        } else {
+            file.borrow_mut().page_sum += 1;
+            file.borrow_mut().add_page(page_index, self.page_pool.get_page(file_id, page_index as u32).unwrap());
+        }
+        Ok(())
+    }
+    pub fn get_file_by_id(&self, file_id : FileId) -> TableFileRef {
+        for (_, file) in self.files.iter() {
+            if file.borrow().get_id() == file_id {
+                return file.clone();
+            }
+        }
+        panic!("invalid file id {:?}", file_id);
+    }
+    pub fn remove_file(&mut self, table : &String) {
+        // the pages in memory are discarded without saving
+        let file = match self.files.get(table) {
+            Some(file) => file.clone(),
+            None => return,
+        };
+        let file_id = file.borrow().get_id();
+        let index_list : Vec<usize> = file.borrow().loaded_pages.keys().cloned().collect();
+        for page_index in index_list {
+            self.remove_from_flusher(file_id, page_index as u32);
+            self.page_pool.remove_page(file_id, page_index as u32);
+        }
+        self.files.remove(table);
+    }
+    // Like remove_file, and the file is removed from table_file_dir, a table created
+    // with the same name afterwards starts from an empty file.
+    pub fn drop_file(&mut self, table : &String) -> io::Result<()> {
+        self.remove_file(table);
+        match remove_file(self.get_file_path(table)) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+    pub fn get_file_path(&self, table : &String) -> String {
+        path_join(&self.table_file_dir, &format!("{}.table", table))
+    }
+    pub fn create_file(&mut self, name : String, table : TableRef) {
+        let mut file = TableFile::new(name.clone(), table, &self.table_file_dir, self.page_size);
+        file.writer = self.writer.clone();
+        // a compressed page has to be decoded into a buffer, so those tables are never mapped
+        if self.storage_backend == StorageBackend::Mmap && !file.table.borrow().is_compressed() {
+            file.enable_mmap();
+        }
+        self.files.insert(name, Rc::new(RefCell::new(file)));
+    }
+    pub fn pin_page(&mut self, file_id : FileId, page_index : u32) {
+        self.remove_from_flusher(file_id, page_index);
+        self.page_pool.pin_page(file_id, page_index);
+    }
+    pub fn unpin_page(&mut self, file_id : FileId, page_index : u32) {
+        self.page_pool.unpin_page(file_id, page_index);
+        let page = self.page_pool.get_page(file_id, page_index).unwrap();
+        if self.flusher.is_some() && page.borrow().dirty {
+            let file = self.get_file_by_id(file_id);
+            self.mark_dirty(&file, page_index as usize);
+        }
+    }
+    pub fn get_unpinned_num(&self) -> usize {
+        self.page_pool.get_unpinned_num()
+    }
+    pub fn get_file_id(&self, name : &String) -> FileId {
+        self.files.get(name).unwrap().borrow().get_id()
+    }
+}
+
+// Yields (page_index, slot, values) for each slot in use, for the tools walking the pages of a table
+// such as backup and the checkers. The values are copied out, and only the page being read is pinned,
+// until the next page or until the iterator is dropped. A page which can't be loaded ends it with an error.
+pub struct TableSlots<'a> {
+    file_manager : &'a mut TableFileManager,
+    file : TableFileRef,
+    page_index : usize,
+    tuple_index : usize,  // the next one to look at in page_index
+    pinned : Option<usize>,
+}
+
+impl<'a> TableSlots<'a> {
+    fn unpin(&mut self) {
+        if let Some(page_index) = self.pinned.take() {
+            let file_id = self.file.borrow().get_id();
+            self.file_manager.unpin_page(file_id, page_index as u32);
+        }
+    }
+}
+
+impl<'a> Iterator for TableSlots<'a> {
+    type Item = Result<(usize, usize, Vec<TupleValue>), StoreError>;
+    fn next(&mut self) -> Option<Result<(usize, usize, Vec<TupleValue>), StoreError>> {
+        loop {
+            if self.page_index >= self.file.borrow().page_sum {
+                self.unpin();
+                return None;
+            }
+            if self.pinned != Some(self.page_index) {
+                self.unpin();
+                let file_id = self.file.borrow().get_id();
+                let result = self.file_manager.try_ensure_page_loaded(&self.file, self.page_index);
+                if let Err(err) = result {
+                    self.page_index = self.file.borrow().page_sum;
+                    return Some(Err(err));
+                }
+                self.file_manager.pin_page(file_id, self.page_index as u32);
+                self.pinned = Some(self.page_index);
+            }
+            let file = self.file.borrow();
+            match file.next_tuple_index(self.page_index, self.tuple_index) {
+                Some(i) => {
+                    let tuple_data = file.get_tuple_data(file.gen_position(self.page_index, i)).unwrap();
+                    let values = gen_tuple_value(&file.tuple_desc.attr_desc, tuple_data);
+                    self.tuple_index = i + 1;
+                    return Some(Ok((self.page_index, i, values)));
+                }
+                None => {
+                    self.page_index += 1;
+                    self.tuple_index = 0;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Drop for TableSlots<'a> {
+    fn drop(&mut self) {
+        self.unpin();
+    }
+}
+
+pub fn get_slot_sum(tuple_len : usize, page_size : usize, checksum : bool) -> usize {
+    let header_size = get_page_header_size(checksum);
+    // (n + 8 - 1) / 8 + tuple_len * n <= page_size - header_size
+    (8 * (page_size - header_size) - 7) / (8 * tuple_len + 1)
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread::{JoinHandle, spawn};
+use std::time::Duration;
+use std::slice::from_raw_parts;
+use super::file::encode_page;
+use super::buffer::FileId;
+use super::double_write::{DoubleWriteRef, PageWrite, try_write_pages};
+use super::writer::FileWriterRef;
+
+
+pub type FlushKey = (FileId, u32);  // (file_id, page_index)
+
+#[derive(Debug)]
+pub struct FlushPage {
+    pub file : Arc<File>,
+    pub path : String,
+    pub offset : u64,
+    pub data : usize,  // DataPtr of the page, usize to be sent to the flusher thread
+    pub page_size : usize,
+    pub compressed : bool,
+    pub checksum : bool,
+    pub writer : FileWriterRef,
+}
+
+#[derive(Debug)]
+struct FlushState {
+    pages : HashMap<FlushKey, FlushPage>,  // dirty and unpinned pages waiting for flush
+    flushed : HashSet<FlushKey>,  // written in background but the page is still marked dirty
+    writing : HashMap<FlushKey, bool>,  // being written, false if registered again after encoded
+    background_num : usize,
+    stopped : bool,
+}
+
+#[derive(Debug)]
+struct FlushShared {
+    state : Mutex<FlushState>,
+    cond : Condvar,
+    written : Condvar,  // notified after each round of writing
+}
+
+// The flusher thread never touches the page pool, the foreground registers
+// a page after modifying it and removes it before pinning, evicting or saving it.
+// The pages are encoded under the lock and written without it. Removing a page
+// waits for the round writing it, so a page removed from the flusher is never being
+// written, and a page registered again during writing is not marked as flushed.
+#[derive(Debug)]
+pub struct PageFlusher {
+    shared : Arc<FlushShared>,
+    worker : Option<JoinHandle<()>>,
+}
+
+impl PageFlusher {
+    pub fn new(interval : Duration, double_write : Option<DoubleWriteRef>) -> PageFlusher {
+        let shared = Arc::new(FlushShared{
+            state : Mutex::new(FlushState{
+                pages : HashMap::new(),
+                flushed : HashSet::new(),
+                writing : HashMap::new(),
+                background_num : 0,
+                stopped : false,
+            }),
+            cond : Condvar::new(),
+            written : Condvar::new(),
+        });
+        let shared_clone = shared.clone();
+        let worker = spawn(move || {
+            flush_loop(shared_clone, interval, double_write);
+        });
+        PageFlusher{
+            shared : shared,
+            worker : Some(worker),
+        }
+    }
+    pub fn add_page(&self, key : FlushKey, page : FlushPage) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.flushed.remove(&key);
+        if let Some(unchanged) = state.writing.get_mut(&key) {
+            *unchanged = false;
+        }
+        state.pages.insert(key, page);
+    }
+    // return true if the page is written in background and not modified after that
+    pub fn remove_page(&self, key : FlushKey) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        state.pages.remove(&key);
+        while state.writing.contains_key(&key) {
+            state = self.shared.written.wait(state).unwrap();
+        }
+        state.flushed.remove(&key)
+    }
+    pub fn take_flushed(&self) -> Vec<FlushKey> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.flushed.drain().collect()
+    }
+    pub fn get_background_num(&self) -> usize {
+        self.shared.state.lock().unwrap().background_num
+    }
+}
+
+impl Drop for PageFlusher {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.cond.notify_one();
+        if let Some(worker) = self.worker.take() {
+            is_match!(worker.join(), Ok(..));
+        }
+    }
+}
+
+fn flush_loop(shared : Arc<FlushShared>, interval : Duration, double_write : Option<DoubleWriteRef>) {
+    let mut state = shared.state.lock().unwrap();
+    while !state.stopped {
+        state = shared.cond.wait_timeout(state, interval).unwrap().0;
+        if state.stopped {
+            break;
+        }
+        let pages : Vec<(FlushKey, FlushPage)> = state.pages.drain().collect();
+        let mut page_list = Vec::new();
+        let mut keys = Vec::new();
+        // the data is copied here, the page may be freed after the lock is released
+        for (key, page) in pages {
+            let data = unsafe{ from_raw_parts::<u8>(page.data as *const u8, page.page_size) };
+            page_list.push(PageWrite{
+                file : page.file,
+                path : page.path,
+                offset : page.offset,
+                data : encode_page(data, page.compressed, page.checksum),
+                writer : page.writer,
+            });
+            state.writing.insert(key, true);
+            keys.push(key);
+        }
+        drop(state);
+        let result = try_write_pages(&page_list, &double_write);
+        state = shared.state.lock().unwrap();
+        for key in keys {
+            let unchanged = state.writing.remove(&key).unwrap();
+            if unchanged && result.is_ok() {
+                state.flushed.insert(key);
+            }
+        }
+        shared.written.notify_all();
+        // the pages failed stay dirty, the foreground writes them when they are evicted or saved
+        match result {
+            Ok(()) => state.background_num += page_list.len(),
+            Err(err) => log_warn!("background flush of {} page(s) failed: {}", page_list.len(), err),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,  // the queries and the changes of the rows
+    Exclusive,  // create, drop and alter
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    shared : usize,  // the number of holders
+    exclusive : bool,
+}
+
+impl LockState {
+    fn is_compatible(&self, mode : LockMode) -> bool {
+        match mode {
+            LockMode::Shared => !self.exclusive,
+            LockMode::Exclusive => !self.exclusive && self.shared == 0,
+        }
+    }
+}
+
+pub type TableLocksRef = Arc<TableLocks>;
+
+// The table locks of the statements, by the table name.
+// A statement takes all its locks at once or none, so the statements never wait for each other in a cycle.
+#[derive(Debug)]
+pub struct TableLocks {
+    states : Mutex<HashMap<String, LockState>>,
+    released : Condvar,
+}
+
+impl TableLocks {
+    pub fn make_ref() -> TableLocksRef {
+        Arc::new(TableLocks{
+            states : Mutex::new(HashMap::new()),
+            released : Condvar::new(),
+        })
+    }
+    fn release(&self, requests : &Vec<(String, LockMode)>) {
+        let mut states = lock_unwrap!(self.states.lock());
+        for &(ref table, mode) in requests.iter() {
+            let remove = {
+                let state = states.get_mut(table).unwrap();
+                match mode {
+                    LockMode::Shared => state.shared -= 1,
+                    LockMode::Exclusive => state.exclusive = false,
+                }
+                state.shared == 0 && !state.exclusive
+            };
+            if remove {
+                states.remove(table);
+            }
+        }
+        self.released.notify_all();
+    }
+}
+
+// Waits at most timeout_ms for the conflicting holders, 0 to fail at once.
+// A table requested in both modes is locked exclusively.
+pub fn lock_tables(locks : &TableLocksRef, requests : Vec<(String, LockMode)>, timeout_ms : usize)
+        -> Result<TableLockGuard, String> {
+    let mut merged : Vec<(String, LockMode)> = Vec::new();
+    for (table, mode) in requests.into_iter() {
+        match merged.iter().position(|&(ref t, _)| *t == table) {
+            Some(i) if mode == LockMode::Exclusive => merged[i].1 = mode,
+            Some(..) => (),
+            None => merged.push((table, mode)),
+        }
+    }
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+    let mut states = lock_unwrap!(locks.states.lock());
+    loop {
+        let conflict = merged.iter()
+            .find(|&&(ref table, mode)| states.get(table).map_or(false, |state| !state.is_compatible(mode)))
+            .map(|&(ref table, _)| table.clone());
+        let table = match conflict {
+            Some(table) => table,
+            None => break,
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(format!("table {} is in use", table));
+        }
+        states = lock_unwrap!(locks.released.wait_timeout(states, deadline - now)).0;
+    }
+    for &(ref table, mode) in merged.iter() {
+        let state = states.entry(table.clone()).or_insert_with(LockState::default);
+        match mode {
+            LockMode::Shared => state.shared += 1,
+            LockMode::Exclusive => state.exclusive = true,
+        }
+    }
+    Ok(TableLockGuard{
+        locks : locks.clone(),
+        requests : merged,
+    })
+}
+
+// the locks are released when it's dropped
+#[derive(Debug)]
+pub struct TableLockGuard {
+    locks : TableLocksRef,
+    requests : Vec<(String, LockMode)>,
+}
+
+impl Drop for TableLockGuard {
+    fn drop(&mut self) {
+        self.locks.release(&self.requests);
+    }
+}
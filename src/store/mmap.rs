@@ -0,0 +1,83 @@
+use std::io;
+use std::fs::File;
+use std::cmp::max;
+use ::utils::libwrapper::{map_file, unmap_file, sync_mapped, get_page_size};
+use super::buffer::DataPtr;
+
+
+// A table file mapped with MAP_SHARED for the mmap storage backend.
+// The pages of the file point into the mapping instead of the buffers of the page pool,
+// so the pool only keeps track of which pages are loaded and pinned, and never frees their bytes.
+//
+// The bytes are not owned by any rust value, the rules for reaching them are:
+// (1) only through the raw pointers of PageBuf and TupleData, the same as the pages of the pool,
+//     so no reference to them lives longer than a call
+// (2) a mapping is never unmapped while the file is open. When the file grows over it,
+//     a larger one is mapped and the old one is kept, since a scan may still hold pointers into it.
+//     Both map the same pages of the os cache, so a write through one is seen through the other.
+// (3) a page is only reached after the file is extended to cover it, or it's SIGBUS
+#[derive(Debug)]
+pub struct MappedFile {
+    mappings : Vec<Mapping>,  // the last one covers the whole file
+    file_len : usize,
+}
+
+#[derive(Debug)]
+struct Mapping {
+    data : *mut u8,
+    len : usize,
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unmap_file(self.data, self.len);
+    }
+}
+
+impl MappedFile {
+    pub fn new(file : &File) -> MappedFile {
+        MappedFile{
+            mappings : Vec::new(),
+            file_len : file.metadata().unwrap().len() as usize,
+        }
+    }
+    // return the address of [offset, offset + len), the file is extended to cover it
+    pub fn map_range(&mut self, file : &File, offset : usize, len : usize) -> DataPtr {
+        let end = offset + len;
+        if end > self.file_len {
+            check_ok!(file.set_len(end as u64));
+            self.file_len = end;
+        }
+        if end > self.get_mapped_len() {
+            // doubled so that a growing file is seldom mapped again
+            let os_page_size = get_page_size();
+            let len = (max(end, 2 * self.get_mapped_len()) + os_page_size - 1) / os_page_size * os_page_size;
+            let data = check_ok!(map_file(file, len));
+            self.mappings.push(Mapping{
+                data : data,
+                len : len,
+            });
+        }
+        unsafe{ self.mappings.last().unwrap().data.offset(offset as isize) as DataPtr }
+    }
+    // write the changed pages of all the mappings back to the file
+    pub fn sync(&self) {
+        check_ok!(self.try_sync());
+    }
+    pub fn try_sync(&self) -> io::Result<()> {
+        for mapping in self.mappings.iter() {
+            try!(sync_mapped(mapping.data, mapping.len));
+        }
+        Ok(())
+    }
+    pub fn get_mapped_len(&self) -> usize {
+        self.mappings.last().map_or(0, |m| m.len)
+    }
+    pub fn get_mapping_num(&self) -> usize {
+        self.mappings.len()
+    }
+    pub fn contains(&self, ptr : DataPtr, len : usize) -> bool {
+        let ptr = ptr as usize;
+        self.mappings.iter().any(|m| ptr >= m.data as usize && ptr + len <= m.data as usize + m.len)
+    }
+}
@@ -1,11 +1,35 @@
-#[allow(dead_code)]
-pub mod table;
-#[allow(dead_code)]
-#[allow(unused_unsafe)]
-pub mod lru;
-#[allow(dead_code)]
-pub mod buffer;
-#[allow(dead_code)]
-pub mod tuple;
-#[allow(dead_code)]
-pub mod file;
+#[allow(dead_code)]
+pub mod table;
+#[allow(dead_code)]
+#[allow(unused_unsafe)]
+pub mod lru;
+#[allow(dead_code)]
+pub mod buffer;
+#[allow(dead_code)]
+pub mod page_buf;
+#[allow(dead_code)]
+pub mod tuple;
+#[allow(dead_code)]
+pub mod file;
+#[allow(dead_code)]
+pub mod flusher;
+#[allow(dead_code)]
+pub mod double_write;
+#[allow(dead_code)]
+pub mod writer;
+#[allow(dead_code)]
+pub mod backup;
+#[allow(dead_code)]
+pub mod rewrite;
+#[allow(dead_code)]
+pub mod undo;
+#[allow(dead_code)]
+pub mod mmap;
+#[allow(dead_code)]
+pub mod stats;
+#[allow(dead_code)]
+pub mod lock;
+#[allow(dead_code)]
+pub mod check;
+#[allow(dead_code)]
+pub mod ddl_journal;
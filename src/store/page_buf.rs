@@ -0,0 +1,97 @@
+use std::ptr::{read_unaligned, write_unaligned, write_bytes, copy_nonoverlapping};
+use std::slice::from_raw_parts;
+use ::utils::pointer::{read_string, write_string, pointer_offset};
+use ::utils::pointer::{read_u32_le, write_u32_le, read_i32_le, write_i32_le, read_f32_le, write_f32_le};
+use super::buffer::DataPtr;
+
+
+// The memory of a page, or a part of it, with bounds-checked access.
+// All the reads and writes of FilePage go through it, so this is the only unsafe code for them.
+// It doesn't own the memory, which is freed with the Page of the pool.
+// The numbers are little-endian as they are in the table files.
+#[derive(Debug, Clone, Copy)]
+pub struct PageBuf {
+    data : DataPtr,
+    len : usize,
+}
+
+impl PageBuf {
+    // data should be valid for len bytes as long as the PageBuf is used
+    pub unsafe fn new(data : DataPtr, len : usize) -> PageBuf {
+        PageBuf{
+            data : data,
+            len : len,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn sub(&self, offset : usize, len : usize) -> PageBuf {
+        // such as the bitmap of a page
+        self.check(offset, len);
+        PageBuf{
+            data : pointer_offset(self.data, offset),
+            len : len,
+        }
+    }
+    pub fn ptr_at(&self, offset : usize, len : usize) -> DataPtr {
+        // for the TupleData of the executors, which read len bytes from it
+        self.check(offset, len);
+        pointer_offset(self.data, offset)
+    }
+    pub fn get_u8(&self, offset : usize) -> u8 {
+        self.check(offset, 1);
+        unsafe{ read_unaligned(pointer_offset(self.data, offset) as *const u8) }
+    }
+    pub fn put_u8(&mut self, offset : usize, n : u8) {
+        self.check(offset, 1);
+        unsafe{ write_unaligned(pointer_offset(self.data, offset) as *mut u8, n) }
+    }
+    pub fn get_u32(&self, offset : usize) -> u32 {
+        self.check(offset, 4);
+        unsafe{ read_u32_le(pointer_offset(self.data, offset)) }
+    }
+    pub fn put_u32(&mut self, offset : usize, n : u32) {
+        self.check(offset, 4);
+        unsafe{ write_u32_le(pointer_offset(self.data, offset), n) }
+    }
+    pub fn get_i32(&self, offset : usize) -> i32 {
+        self.check(offset, 4);
+        unsafe{ read_i32_le(pointer_offset(self.data, offset)) }
+    }
+    pub fn put_i32(&mut self, offset : usize, n : i32) {
+        self.check(offset, 4);
+        unsafe{ write_i32_le(pointer_offset(self.data, offset), n) }
+    }
+    pub fn get_f32(&self, offset : usize) -> f32 {
+        self.check(offset, 4);
+        unsafe{ read_f32_le(pointer_offset(self.data, offset)) }
+    }
+    pub fn put_f32(&mut self, offset : usize, n : f32) {
+        self.check(offset, 4);
+        unsafe{ write_f32_le(pointer_offset(self.data, offset), n) }
+    }
+    pub fn get_string(&self, offset : usize, len : usize) -> String {
+        self.check(offset, len);
+        unsafe{ read_string(pointer_offset(self.data, offset), len) }
+    }
+    pub fn put_string(&mut self, offset : usize, s : &String, len : usize) {
+        self.check(offset, len);
+        unsafe{ write_string(pointer_offset(self.data, offset), s, len) }
+    }
+    pub fn fill(&mut self, offset : usize, len : usize, byte : u8) {
+        self.check(offset, len);
+        unsafe{ write_bytes(pointer_offset(self.data, offset) as *mut u8, byte, len) }
+    }
+    pub fn put_bytes(&mut self, offset : usize, bytes : &[u8]) {
+        self.check(offset, bytes.len());
+        unsafe{ copy_nonoverlapping(bytes.as_ptr(), pointer_offset(self.data, offset) as *mut u8, bytes.len()) }
+    }
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe{ from_raw_parts(self.data as *const u8, self.len) }
+    }
+    fn check(&self, offset : usize, len : usize) {
+        assert!(offset.checked_add(len).map_or(false, |end| end <= self.len),
+            "page access of {} bytes at {} is out of {} bytes", len, offset, self.len);
+    }
+}
@@ -0,0 +1,74 @@
+use std::vec::Vec;
+use ::parser::common::{ValueExpr, ValueList, Literal};
+use ::utils::float::parse_float;
+use super::table::{Attr, AttrType};
+use super::tuple::TupleValue;
+
+
+// where an attribute of the new layout comes from
+#[derive(Debug, Clone)]
+pub enum ColumnSource {
+    Old(usize),  // position of the attribute in the old table, converted to the new type
+    Value(TupleValue),  // the same value for all the tuples, Null for a new nullable attribute
+}
+
+// Turns a tuple of the old layout into the values to be inserted into the new one.
+// Every ALTER is described by the sources of the new attributes.
+#[derive(Debug, Clone)]
+pub struct RowTransform {
+    pub sources : Vec<ColumnSource>,  // one for each attribute of the new table
+}
+
+impl RowTransform {
+    pub fn apply(&self, row : &Vec<TupleValue>, attr_list : &Vec<Attr>) -> Result<ValueList, String> {
+        assert_eq!(self.sources.len(), attr_list.len());
+        let mut value_list = Vec::new();
+        for (source, attr) in self.sources.iter().zip(attr_list.iter()) {
+            let value = match source {
+                &ColumnSource::Old(i) => &row[i],
+                &ColumnSource::Value(ref v) => v,
+            };
+            value_list.push(try!(convert_value(value, attr)));
+        }
+        Ok(value_list)
+    }
+}
+
+pub fn convert_value(value : &TupleValue, attr : &Attr) -> Result<ValueExpr, String> {
+    let invalid = |type_name : &str| Err(format!(
+        "can't convert {:?} to {} for attribute {}", value.to_string(), type_name, attr.name));
+    let literal = match (value, &attr.attr_type) {
+        (&TupleValue::Null, _) => {
+            if !attr.nullable {
+                return Err(format!("attribute {} is not nullable", attr.name));
+            }
+            Literal::Null
+        }
+        (&TupleValue::Int(n), &AttrType::Int) => Literal::Int(n),
+        (&TupleValue::Int(n), &AttrType::Float) => Literal::Float(n as f32),
+        (&TupleValue::Float(f), &AttrType::Int) => {
+            // only keep the value unchanged
+            if f.fract() != 0.0 || f < i32::min_value() as f32 || f >= i32::max_value() as f32 {
+                return invalid("int");
+            }
+            Literal::Int(f as i32)
+        }
+        (&TupleValue::Float(f), &AttrType::Float) => Literal::Float(f),
+        (&TupleValue::Char(ref s), &AttrType::Int) => match s.trim().parse::<i32>() {
+            Ok(n) => Literal::Int(n),
+            Err(..) => return invalid("int"),
+        },
+        (&TupleValue::Char(ref s), &AttrType::Float) => match parse_float(s) {
+            Some(f) => Literal::Float(f),
+            None => return invalid("float"),
+        },
+        (v, &AttrType::Char{len}) => {
+            let s = v.to_string();
+            if s.len() > len {
+                return Err(format!("{:?} is too long for attribute {} char({})", s, attr.name, len));
+            }
+            Literal::Str(s)
+        }
+    };
+    Ok(ValueExpr::from_literal(literal))
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::cmp::Ordering;
+use ::utils::float::format_float;
+use super::table::{Attr, AttrType};
+use super::tuple::TupleValue;
+
+
+// the most buckets of a histogram, a column with fewer rows has a bucket for each row
+pub const HISTOGRAM_BUCKET_NUM : usize = 32;
+
+// Collected by `analyze` in a single scan and saved in the catalog,
+// they are not updated by the later changes until the table is analyzed again.
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct TableStats {
+    pub row_num : usize,
+    pub columns : Vec<ColumnStats>,  // in the order of attr_list
+}
+
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct ColumnStats {
+    pub name : String,
+    pub distinct_num : usize,  // counted by 64 bit hashes, so it may be a little less
+    // Null is stored as zero bytes, so for a nullable column it's the fraction of 0, 0.0 or "",
+    // and always 0 for a column which is not nullable.
+    pub null_fraction : f64,
+    pub min : Option<String>,  // None for an empty table
+    pub max : Option<String>,
+    // The bounds of an equi-depth histogram of an int or float column, empty for the others.
+    // Each of the len - 1 buckets between two adjacent bounds has the same number of rows.
+    pub histogram : Vec<f64>,
+}
+
+impl ColumnStats {
+    // the estimated fraction of the rows less than value, or not greater than it with or_equal
+    pub fn estimate_fraction_below(&self, value : f64, or_equal : bool) -> Option<f64> {
+        let bounds = &self.histogram;
+        if bounds.len() < 2 {
+            return None;
+        }
+        let bucket_num = (bounds.len() - 1) as f64;
+        let last = bounds.len() - 1;
+        // interpolated inside the bucket [lower, upper] containing value,
+        // the repeated bounds of a frequent value are all below it or all above it
+        let lower = if or_equal {
+            match bounds.iter().rposition(|b| *b <= value) {
+                None => return Some(0.0),
+                Some(i) if i == last => return Some(1.0),
+                Some(i) => i,
+            }
+        } else {
+            match bounds.iter().position(|b| *b >= value) {
+                None => return Some(1.0),
+                Some(0) => return Some(0.0),
+                Some(i) => i - 1,
+            }
+        };
+        let width = bounds[lower + 1] - bounds[lower];
+        let inside = if width > 0.0 { (value - bounds[lower]) / width } else { 0.0 };
+        Some((lower as f64 + inside) / bucket_num)
+    }
+    // the estimated fraction of the rows equal to value
+    pub fn estimate_fraction_equal(&self, value : Option<f64>) -> f64 {
+        // a frequent value takes whole buckets, the others are assumed to be uniform
+        let uniform = 1.0 / self.distinct_num.max(1) as f64;
+        let below = |or_equal| value.and_then(|v| self.estimate_fraction_below(v, or_equal));
+        let bucket = match (below(true), below(false)) {
+            (Some(le), Some(lt)) => le - lt,
+            _ => 0.0,
+        };
+        bucket.max(uniform)
+    }
+}
+
+
+// fed with the rows of a table one by one
+#[derive(Debug)]
+pub struct StatsCollector {
+    attr_list : Vec<Attr>,
+    row_num : usize,
+    hashes : Vec<HashSet<u64>>,
+    null_nums : Vec<usize>,
+    numbers : Vec<Vec<f64>>,  // all the values of the int and float columns, sorted at last
+    min_strs : Vec<Option<String>>,
+    max_strs : Vec<Option<String>>,
+}
+
+impl StatsCollector {
+    pub fn new(attr_list : &Vec<Attr>) -> StatsCollector {
+        let n = attr_list.len();
+        StatsCollector{
+            attr_list : attr_list.clone(),
+            row_num : 0,
+            hashes : vec![HashSet::new(); n],
+            null_nums : vec![0; n],
+            numbers : vec![Vec::new(); n],
+            min_strs : vec![None; n],
+            max_strs : vec![None; n],
+        }
+    }
+    pub fn add_row(&mut self, row : &Vec<TupleValue>) {
+        assert_eq!(row.len(), self.attr_list.len());
+        self.row_num += 1;
+        for (i, value) in row.iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            value.to_string().hash(&mut hasher);
+            self.hashes[i].insert(hasher.finish());
+            let is_zero = match value {
+                &TupleValue::Int(n) => {
+                    self.numbers[i].push(n as f64);
+                    n == 0
+                }
+                &TupleValue::Float(f) => {
+                    self.numbers[i].push(f as f64);
+                    f == 0.0
+                }
+                &TupleValue::Char(ref s) => {
+                    if self.min_strs[i].as_ref().map_or(true, |m| s < m) {
+                        self.min_strs[i] = Some(s.clone());
+                    }
+                    if self.max_strs[i].as_ref().map_or(true, |m| s > m) {
+                        self.max_strs[i] = Some(s.clone());
+                    }
+                    s.is_empty()
+                }
+                &TupleValue::Null => true,
+            };
+            if is_zero && self.attr_list[i].nullable {
+                self.null_nums[i] += 1;
+            }
+        }
+    }
+    pub fn finish(mut self) -> TableStats {
+        let mut columns = Vec::new();
+        for (i, attr) in self.attr_list.iter().enumerate() {
+            let numbers = &mut self.numbers[i];
+            numbers.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            let (min, max) = match attr.attr_type {
+                AttrType::Int => (numbers.first().map(|n| (*n as i32).to_string()),
+                    numbers.last().map(|n| (*n as i32).to_string())),
+                AttrType::Float => (numbers.first().map(|n| format_float(*n as f32)),
+                    numbers.last().map(|n| format_float(*n as f32))),
+                AttrType::Char{..} => (self.min_strs[i].take(), self.max_strs[i].take()),
+            };
+            columns.push(ColumnStats{
+                name : attr.name.clone(),
+                distinct_num : self.hashes[i].len(),
+                null_fraction : if self.row_num == 0 { 0.0 }
+                    else { self.null_nums[i] as f64 / self.row_num as f64 },
+                min : min,
+                max : max,
+                histogram : gen_histogram(numbers, HISTOGRAM_BUCKET_NUM),
+            });
+        }
+        TableStats{
+            row_num : self.row_num,
+            columns : columns,
+        }
+    }
+}
+
+// the bounds of bucket_num buckets holding the same number of the sorted values
+pub fn gen_histogram(sorted : &Vec<f64>, bucket_num : usize) -> Vec<f64> {
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+    let bucket_num = bucket_num.min(sorted.len()).max(1);
+    let last = sorted.len() - 1;
+    (0..bucket_num + 1).map(|i| sorted[i * last / bucket_num]).collect()
+}
@@ -1,289 +1,974 @@
-use std::vec::Vec;
-use std::collections::{BTreeMap, HashMap};
-use std::option::Option;
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::fs::OpenOptions;
-use std::io::{Read, Write};
-use rustc_serialize::{Encodable, Decodable, Encoder, Decoder};
-use rustc_serialize::json::{encode, decode};
-use ::parser::common::ValueList;
-use ::utils::config::Config;
-use ::utils::file::{path_join, ensure_dir_exist};
-use ::store::tuple::TupleValue;
-use super::tuple::TupleDesc;
-use super::file::TableFileManager;
-
-
-macro_rules! unwrap {
-    ($result:expr) => ({
-        match $result {
-            Err(err) => panic!("unexpected error: {:?}", err),
-            Ok(data) => data,
-        }
-    })
-}
-
-#[derive(Debug, Copy, Clone)]
-pub enum AttrType {
-    Int,
-    Float,
-    Char{ len : usize },
-}
-
-#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
-pub struct Attr {
-    pub name : String,
-    pub attr_type : AttrType,
-    pub primary : bool,
-    pub nullable : bool,
-}
-
-
-pub type TableRef = Rc<RefCell<Table>>;
-pub type IndexMap = HashMap<(String, String), usize>;
-
-#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
-pub struct Table {
-    pub name : String,
-    pub attr_list : Vec<Attr>,
-}
-
-impl Table {
-    pub fn gen_tuple_desc(&self) -> TupleDesc {
-        TupleDesc::new(&self.attr_list)
-    }
-    pub fn gen_index_map(&self) -> IndexMap {
-        let mut index_map = IndexMap::new();
-        for (i, attr) in self.attr_list.iter().enumerate() {
-            index_map.insert((self.name.clone(), attr.name.clone()), i);
-        }
-        index_map
-    }
-    pub fn get_primary_key_attr(&self) -> Attr {
-        self.attr_list.iter().filter(|a| a.primary).next().unwrap().clone()
-    }
-    pub fn get_primary_key_index(&self) -> usize {
-        let mut index = 0;
-        for attr in self.attr_list.iter() {
-            if attr.primary {
-                return index;
-            }
-            index += 1;
-        }
-        index
-    }
-    pub fn get_attr_name_list(&self) -> Vec<String> {
-        self.attr_list.iter().map(|a| a.name.clone()).collect()
-    }
-    pub fn desc(&self) -> String {
-        let mut result = format!("table: {}\n", self.name);
-        for attr in self.attr_list.iter() {
-            result.push_str(&format!("{} {:?} {} {}\n", attr.name, attr.attr_type,
-                if attr.nullable {"null"}else{"not null"}, if attr.primary {"primary"}else{""}))
-        }
-        result
-    }
-}
-
-
-pub struct TableSet {
-    pub tables : HashMap<String, Table>,
-}
-
-impl TableSet {
-    pub fn new() -> TableSet {
-        TableSet{
-            tables : HashMap::new(),
-        }
-    }
-    pub fn exist(&self, name : &str) -> bool {
-        match self.tables.get(name) {
-            Some(..) => true,
-            None => false,
-        }
-    }
-    pub fn get_attr(&self, table : &Option<String>, attr : &str) -> Option<Attr> {
-        let mut table_list = Vec::new();
-        for (name, t) in self.tables.iter() {
-            if let Some(attr) = t.attr_list.iter().filter(|a| a.name == attr).next() {
-                if let &Some(ref table_name) = table {
-                    if *table_name == name.to_string() {
-                        table_list.push(attr.clone());
-                    }
-                } else {
-                    table_list.push(attr.clone());
-                }
-            }
-        }
-        match table_list.len() {
-            1 => table_list.pop(),
-            _ => None,  // not found or multiple attribute found
-        }
-    }
-    pub fn complete_table_name(&self, table : &mut Option<String>, attr : &mut String) {
-        // should called after get_attr to confirm only one result exist
-        if table.is_some() { return; }
-        for (name, t) in self.tables.iter() {
-            if let Some(..) = t.attr_list.iter().filter(|a| a.name == *attr).next() {
-                *table = Some(name.clone());
-                return;
-            }
-        }
-        panic!("attribute not exist");
-    }
-    pub fn gen_attr_list(&self, table : &String) -> Vec<Attr> {
-        // table should exist
-        self.tables.get(table).unwrap().attr_list.clone()
-    }
-    pub fn add_table(&mut self, table : Table) {
-        self.tables.insert(table.name.clone(), table);
-    }
-}
-
-
-pub type TableManagerRef = Rc<RefCell<TableManager>>;
-
-#[derive(Debug)]
-pub struct TableManager {
-    tables : BTreeMap<String, TableRef>,
-    pub file_manager : TableFileManager,
-    table_meta_dir : String,
-}
-
-impl TableManager {
-    pub fn make_ref(config : &Config) -> TableManagerRef {
-        Rc::new(RefCell::new(TableManager::new(config)))
-    }
-    pub fn new(config : &Config) -> TableManager {
-        let table_meta_dir = config.get_str("table_meta_dir");
-        ensure_dir_exist(&table_meta_dir);
-        TableManager{
-            tables : BTreeMap::new(),
-            file_manager : TableFileManager::new(config),
-            table_meta_dir : table_meta_dir,
-        }
-    }
-    pub fn save_to_file(&mut self) {
-        self.file_manager.save_all();
-        let full_path = path_join(&self.table_meta_dir, &"table_meta.json".to_string());
-        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(
-            &full_path).unwrap();
-        let json_str = self.to_json();
-        is_match!(file.write_all(json_str.as_bytes()), Ok(..));
-    }
-    pub fn from_json_file(config : &Config) -> TableManager {
-        let table_meta_dir = config.get_str("table_meta_dir");
-        ensure_dir_exist(&table_meta_dir);
-        let full_path = path_join(&table_meta_dir, &"table_meta.json".to_string());
-        let mut file = OpenOptions::new().read(true).create(true).open(
-            &full_path).unwrap();
-        let mut json_str = String::new();
-        assert!(file.read_to_string(&mut json_str).is_ok());
-        if json_str.len() == 0 { return TableManager::new(config) }
-        Self::from_json(config, &json_str, true)
-    }
-    pub fn from_json(config : &Config, json : &String, init_file : bool) -> TableManager {
-        // setting init_file to false only for tests
-        let mut tables = BTreeMap::new();
-        let mut table_list = Vec::new();
-        let tree : BTreeMap<String, Table> = unwrap!(decode(json));
-        for (name, table) in tree.iter() {
-            let t = Rc::new(RefCell::new(table.clone()));
-            tables.insert(name.clone(), t.clone());
-            table_list.push(t);
-        }
-        let mut manager = Self::new(config);
-        manager.tables = tables;
-        if init_file {
-            manager.file_manager.init_from_file(table_list);
-        }
-        manager
-    }
-    pub fn to_json(&self) -> String {
-        let mut tree : BTreeMap<String, Table> = BTreeMap::new();
-        for (name, table) in self.tables.iter() {
-            tree.insert(name.clone(), table.borrow().clone());
-        }
-        unwrap!(encode(&tree))
-    }
-    pub fn add_table(&mut self, table : Table) {
-        // add new table and create empty file
-        let name = table.name.clone();
-        assert!(!self.tables.get(&name).is_some());
-        let table_ref = Rc::new(RefCell::new(table));
-        self.file_manager.create_file(name.clone(), table_ref.clone());
-        self.tables.insert(name, table_ref);
-    }
-    pub fn remove_table(&mut self, table : &String) {
-        self.tables.remove(table);
-    }
-    pub fn get_table(&self, name : &str) -> Option<TableRef> {
-        match self.tables.get(name) {
-            Some(ref mut table) => Some(table.clone()),
-            None => None,
-        }
-    }
-    pub fn gen_table_set(&self, used_table : &Vec<String>) -> TableSet {
-        let mut tables = HashMap::new();
-        for name in used_table.iter() {
-            if let Some(t) = self.tables.get(name) {
-                tables.insert(name.clone(), t.borrow().clone());
-            }
-        }
-        TableSet{ tables : tables }
-    }
-    pub fn get_tuple_value(&mut self, table : &String,
-            position : usize,
-            attr_position : usize) -> TupleValue{
-        self.file_manager.get_tuple_value(table, position, attr_position)
-    }
-    pub fn insert(&mut self, table : &String, value_list : &ValueList) {
-        self.file_manager.insert(table, value_list);
-    }
-    pub fn show_tables(&self) -> String {
-        let mut result = String::new();
-        for (_, t) in self.tables.iter() {
-            result.push_str(&t.borrow().desc());
-            result.push('\n');
-        }
-        result
-    }
-}
-
-impl Encodable for AttrType {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        let mut t = BTreeMap::new();
-        match self {
-            &AttrType::Int => t.insert("type".to_string(), "Int".to_string()),
-            &AttrType::Float => t.insert("type".to_string(), "Float".to_string()),
-            &AttrType::Char{len} => {
-                t.insert("type".to_string(), "Char".to_string());
-                t.insert("len".to_string(), len.to_string())
-            }
-        };
-        t.encode(s)
-    }
-}
-
-impl Decodable for AttrType {
-    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
-        let t : BTreeMap<String, String> = try!(BTreeMap::decode(d));
-        let res = match t.get("type") {
-            None => panic!("can't find key 'type' in AttrType json data"),
-            Some(ref s) => match &s[..] {
-                "Int" => AttrType::Int,
-                "Float" => AttrType::Float,
-                "Char" => {
-                    let len = match t.get("len") {
-                        None => panic!("can't find key 'len' for Char in AttrType json data"),
-                        Some(len) => len.parse::<usize>().unwrap(),
-                    };
-                    AttrType::Char{ len : len }
-                }
-                _ => panic!("unexpected type {}", s),
-            }
-        };
-        Ok(res)
-    }
-}
+use std::vec::Vec;
+use std::fmt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::option::Option;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::cell::RefCell;
+use std::fs::{OpenOptions, rename, remove_file, remove_dir, remove_dir_all};
+use std::path::Path;
+use std::io::Read;
+use rustc_serialize::{Encodable, Decodable, Encoder, Decoder};
+use rustc_serialize::json::{encode, decode};
+use ::parser::common::ValueList;
+use ::parser::lexer::KEYWORDS;
+use ::parser::attribute::AttributeExpr;
+use ::parser::create_drop::parse_generated_expr;
+use ::utils::config::Config;
+use ::utils::file::{path_join, ensure_dir_exist, create_temp_dir};
+use ::utils::guard::QueryGuard;
+use ::store::tuple::TupleValue;
+use super::tuple::TupleDesc;
+use super::file::{TableFileManager, StoreError, StoreErrorType};
+use super::rewrite::RowTransform;
+use super::stats::{TableStats, StatsCollector};
+use super::ddl_journal::{DdlIntent, DdlOp, DDL_STEPS, CATALOG_NAME, write_file_atomic, write_intent,
+    clear_intent, recover_ddl, get_shadow_name};
+use super::lock::{TableLocksRef, TableLocks, TableLockGuard, LockMode, lock_tables};
+use super::backup::{BackupManifest, prepare_backup_dir, write_backup_file, copy_backup_file, write_manifest};
+use super::backup::{verify_backup, check_manifest_format, read_backup_catalog, check_table_file, stage_backup_file};
+
+
+macro_rules! unwrap {
+    ($result:expr) => ({
+        match $result {
+            Err(err) => panic!("unexpected error: {:?}", err),
+            Ok(data) => data,
+        }
+    })
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum AttrType {
+    Int,
+    Float,
+    Char{ len : usize },
+}
+
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct Attr {
+    pub name : String,
+    pub attr_type : AttrType,
+    pub primary : bool,
+    pub nullable : bool,
+    pub generated : Option<String>,  // the expression of a generated attribute, None for the older catalogs
+}
+
+
+// the privileges on a table of `grant`, all of them for `grant all`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, RustcDecodable, RustcEncodable)]
+pub enum Privilege {
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Privilege {
+    pub fn all() -> Vec<Privilege> {
+        vec![Privilege::Select, Privilege::Insert, Privilege::Update, Privilege::Delete]
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            &Privilege::Select => "select",
+            &Privilege::Insert => "insert",
+            &Privilege::Update => "update",
+            &Privilege::Delete => "delete",
+        }
+    }
+}
+
+pub type TableRef = Rc<RefCell<Table>>;
+pub type IndexMap = HashMap<(String, String), usize>;
+
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct Table {
+    pub name : String,
+    pub attr_list : Vec<Attr>,
+    pub compression : Option<bool>,  // None for catalogs saved before this field is added
+    pub fill_factor : Option<usize>,  // percentage of slots used by inserts, None for the default in config
+    pub stats : Option<TableStats>,  // of the last analyze, None before it and for the older catalogs
+    pub grants : Option<BTreeMap<String, Vec<Privilege>>>,  // by user, None for the older catalogs
+}
+
+impl Table {
+    pub fn gen_tuple_desc(&self) -> TupleDesc {
+        TupleDesc::new(&self.attr_list)
+    }
+    pub fn is_compressed(&self) -> bool {
+        self.compression == Some(true)
+    }
+    pub fn gen_index_map(&self) -> IndexMap {
+        let mut index_map = IndexMap::new();
+        for (i, attr) in self.attr_list.iter().enumerate() {
+            index_map.insert((self.name.clone(), attr.name.clone()), i);
+        }
+        index_map
+    }
+    pub fn get_primary_key_attr(&self) -> Attr {
+        self.attr_list.iter().filter(|a| a.primary).next().unwrap().clone()
+    }
+    pub fn get_primary_key_index(&self) -> usize {
+        let mut index = 0;
+        for attr in self.attr_list.iter() {
+            if attr.primary {
+                return index;
+            }
+            index += 1;
+        }
+        index
+    }
+    pub fn get_attr_name_list(&self) -> Vec<String> {
+        self.attr_list.iter().map(|a| a.name.clone()).collect()
+    }
+    pub fn is_granted(&self, user : &str, privilege : Privilege) -> bool {
+        match self.grants.as_ref().and_then(|grants| grants.get(user)) {
+            Some(privileges) => privileges.contains(&privilege),
+            None => false,
+        }
+    }
+    pub fn desc(&self) -> String {
+        let mut result = format!("table: {}{}\n", self.name,
+            if self.is_compressed() {" (compressed)"} else {""});
+        for attr in self.attr_list.iter() {
+            let generated = attr.generated.as_ref()
+                .map_or(String::new(), |expr| format!(" generated as ({})", expr));
+            result.push_str(&format!("{} {:?} {} {}{}\n", attr.name, attr.attr_type,
+                if attr.nullable {"null"}else{"not null"}, if attr.primary {"primary"}else{""}, generated))
+        }
+        result
+    }
+    // The references of a schema not made by create table, such as a hand-edited catalog,
+    // which would only panic when the table is used.
+    pub fn check_references(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for attr in self.attr_list.iter() {
+            let sql = match attr.generated {
+                Some(ref sql) => sql,
+                None => continue,
+            };
+            if attr.primary || is_match!(attr.attr_type, AttrType::Char{..}) {
+                problems.push(format!("table {}: attribute {} can't be generated", self.name, attr.name));
+            }
+            let expr = match parse_generated_expr(sql) {
+                Ok(expr) => expr,
+                Err(errs) => {
+                    problems.push(format!("table {}: invalid expression of generated attribute {}: {}",
+                        self.name, attr.name, errs[0].error_msg));
+                    continue;
+                }
+            };
+            for attr_expr in expr.get_attr_list() {
+                let used = match attr_expr {
+                    &AttributeExpr::TableAttr{ref table, ref attr}
+                        if table.as_ref().map_or(true, |t| *t == self.name) => Some(attr),
+                    _ => None,
+                };
+                // a generated attribute using another one could form a cycle
+                let valid = used.and_then(|used| self.attr_list.iter().filter(|a| a.name == *used).next())
+                    .map_or(false, |a| a.generated.is_none() && !is_match!(a.attr_type, AttrType::Char{..}));
+                if !valid {
+                    problems.push(format!("table {}: generated attribute {} uses {}, \
+                        which is not a number attribute of the table", self.name, attr.name, attr_expr));
+                }
+            }
+        }
+        problems
+    }
+}
+
+// every problem of the tables, and a table saved under another name
+pub fn check_catalog(tables : &BTreeMap<String, Table>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (name, table) in tables.iter() {
+        if *name != table.name {
+            problems.push(format!("table {} is saved as {}", table.name, name));
+        }
+        problems.extend(table.check_references());
+    }
+    problems
+}
+
+
+// The names a client can complete, taken at the schema version, which changes with every ddl,
+// so a client only takes it again when the version has changed.
+#[derive(Debug, Clone, PartialEq, RustcEncodable)]
+pub struct Namespace {
+    pub version : usize,
+    pub tables : Vec<NamespaceTable>,  // by name
+    pub keywords : Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, RustcEncodable)]
+pub struct NamespaceTable {
+    pub name : String,
+    pub columns : Vec<String>,  // in the order of the declaration
+}
+
+impl Namespace {
+    pub fn to_json(&self) -> String {
+        encode(self).unwrap()
+    }
+}
+
+
+// by name, so that the tables are searched and listed in the same order every time
+pub struct TableSet {
+    pub tables : BTreeMap<String, Table>,
+}
+
+impl TableSet {
+    pub fn new() -> TableSet {
+        TableSet{
+            tables : BTreeMap::new(),
+        }
+    }
+    pub fn exist(&self, name : &str) -> bool {
+        match self.tables.get(name) {
+            Some(..) => true,
+            None => false,
+        }
+    }
+    pub fn get_attr(&self, table : &Option<String>, attr : &str) -> Option<Attr> {
+        let mut table_list = Vec::new();
+        for (name, t) in self.tables.iter() {
+            if let Some(attr) = t.attr_list.iter().filter(|a| a.name == attr).next() {
+                if let &Some(ref table_name) = table {
+                    if *table_name == name.to_string() {
+                        table_list.push(attr.clone());
+                    }
+                } else {
+                    table_list.push(attr.clone());
+                }
+            }
+        }
+        match table_list.len() {
+            1 => table_list.pop(),
+            _ => None,  // not found or multiple attribute found
+        }
+    }
+    pub fn complete_table_name(&self, table : &mut Option<String>, attr : &mut String) {
+        // should called after get_attr to confirm only one result exist
+        if table.is_some() { return; }
+        for (name, t) in self.tables.iter() {
+            if let Some(..) = t.attr_list.iter().filter(|a| a.name == *attr).next() {
+                *table = Some(name.clone());
+                return;
+            }
+        }
+        panic!("attribute not exist");
+    }
+    pub fn gen_attr_list(&self, table : &String) -> Vec<Attr> {
+        // table should exist
+        self.tables.get(table).unwrap().attr_list.clone()
+    }
+    pub fn add_table(&mut self, table : Table) {
+        self.tables.insert(table.name.clone(), table);
+    }
+    pub fn gen_scope(&self, table : &String) -> TableSet {
+        // only the table in the from list, so the other tables don't make its attributes ambiguous
+        let mut scope = TableSet::new();
+        scope.add_table(self.tables.get(table).unwrap().clone());
+        scope
+    }
+}
+
+
+pub type TableManagerRef = Rc<RefCell<TableManager>>;
+
+// the data of `copy ... from stdin`, streamed by the client of the running statement
+pub struct CopyInput(pub Box<Read>);
+
+impl fmt::Debug for CopyInput {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CopyInput")
+    }
+}
+
+#[derive(Debug)]
+pub struct TableManager {
+    tables : BTreeMap<String, TableRef>,  // by name, whatever the order they are created in
+    pub file_manager : TableFileManager,
+    table_meta_dir : String,
+    schema_version : usize,  // changed by every ddl, the prepared statements check it
+    query_timeout_ms : usize,
+    session_timeout_ms : Option<usize>,  // of the session running statements, over query_timeout_ms
+    query_guard : QueryGuard,  // of the running statement
+    kill_flag : Option<Arc<AtomicBool>>,  // of the running statement in the process list
+    table_locks : TableLocksRef,
+    table_lock_timeout_ms : usize,
+    max_result_rows : usize,  // 0 for no limit
+    session_max_result_rows : Option<usize>,  // of the session running statements, over max_result_rows
+    slow_query_ms : usize,  // 0 to disable the slow query log
+    slow_plan : Option<String>,  // the explain of the last statement slower than slow_query_ms
+    query_num : usize,  // statements run since started
+    temp_dir : Option<String>,  // removed with all the tables when the manager is dropped
+    copy_input : Option<CopyInput>,  // set by the server for a copy from stdin, None for the others
+    admin_user : String,  // bypasses the privileges, which are not checked when it's empty
+    session_user : Option<String>,  // of the session running statements, None for no authentication
+}
+
+impl TableManager {
+    pub fn make_ref(config : &Config) -> TableManagerRef {
+        Rc::new(RefCell::new(TableManager::new(config)))
+    }
+    pub fn new(config : &Config) -> TableManager {
+        TableManager::with_file_manager(config, TableFileManager::new(config))
+    }
+    fn with_file_manager(config : &Config, file_manager : TableFileManager) -> TableManager {
+        let table_meta_dir = config.get_path("table_meta_dir");
+        ensure_dir_exist(&table_meta_dir);
+        TableManager{
+            tables : BTreeMap::new(),
+            file_manager : file_manager,
+            table_meta_dir : table_meta_dir,
+            schema_version : 0,
+            query_timeout_ms : config.get_usize("query_timeout_ms"),
+            session_timeout_ms : None,
+            query_guard : QueryGuard::unlimited(),
+            kill_flag : None,
+            table_locks : TableLocks::make_ref(),
+            table_lock_timeout_ms : config.get_usize("table_lock_timeout_ms"),
+            max_result_rows : config.get_usize("max_result_rows"),
+            session_max_result_rows : None,
+            slow_query_ms : config.get_usize("slow_query_ms"),
+            slow_plan : None,
+            query_num : 0,
+            temp_dir : None,
+            copy_input : None,
+            admin_user : config.get_str("admin_user"),
+            session_user : None,
+        }
+    }
+    // the meta and the files of the tables all in dir instead of the dirs of the config
+    pub fn in_dir(config : &Config, dir : &str) -> TableManager {
+        let mut config = config.clone();
+        check_ok!(config.set_override("in_dir", "table_meta_dir", dir));
+        check_ok!(config.set_override("in_dir", "table_file_dir", dir));
+        TableManager::new(&config)
+    }
+    // in a new temp dir, which is removed by drop_all or when the manager is dropped
+    pub fn in_temp_dir(config : &Config, prefix : &str) -> TableManager {
+        let dir = create_temp_dir(prefix);
+        let mut manager = TableManager::in_dir(config, &dir);
+        manager.temp_dir = Some(dir);
+        manager
+    }
+    pub fn start_query(&mut self) -> QueryGuard {
+        // the plans generated after it are limited by the new guard
+        let guard = QueryGuard::new(self.session_timeout_ms.unwrap_or(self.query_timeout_ms));
+        self.query_guard = match self.kill_flag {
+            Some(ref killed) => guard.with_kill_flag(killed.clone()),
+            None => guard,
+        };
+        self.slow_plan = None;
+        self.query_num += 1;
+        self.query_guard.clone()
+    }
+    pub fn finish_query(&mut self) {
+        self.query_guard = QueryGuard::unlimited();
+    }
+    pub fn get_query_timeout_ms(&self) -> usize {
+        self.query_timeout_ms
+    }
+    pub fn set_session_timeout(&mut self, timeout_ms : Option<usize>) {
+        self.session_timeout_ms = timeout_ms;
+    }
+    pub fn set_kill_flag(&mut self, killed : Option<Arc<AtomicBool>>) {
+        self.kill_flag = killed;
+    }
+    // shared with the holders outside the manager
+    pub fn get_table_locks(&self) -> TableLocksRef {
+        self.table_locks.clone()
+    }
+    // held by a statement until its plan is closed, waiting at most timeout_ms
+    pub fn lock_tables(&self, requests : Vec<(String, LockMode)>, timeout_ms : usize)
+            -> Result<TableLockGuard, String> {
+        lock_tables(&self.table_locks, requests, timeout_ms)
+    }
+    pub fn get_table_lock_timeout_ms(&self) -> usize {
+        self.table_lock_timeout_ms
+    }
+    pub fn get_query_guard(&self) -> QueryGuard {
+        self.query_guard.clone()
+    }
+    pub fn get_max_result_rows(&self) -> usize {
+        self.session_max_result_rows.unwrap_or(self.max_result_rows)
+    }
+    pub fn get_config_max_result_rows(&self) -> usize {
+        self.max_result_rows
+    }
+    pub fn set_session_max_result_rows(&mut self, max_rows : Option<usize>) {
+        self.session_max_result_rows = max_rows;
+    }
+    pub fn get_slow_query_ms(&self) -> usize {
+        self.slow_query_ms
+    }
+    pub fn set_slow_plan(&mut self, explain : String) {
+        self.slow_plan = Some(explain);
+    }
+    pub fn take_slow_plan(&mut self) -> Option<String> {
+        self.slow_plan.take()
+    }
+    pub fn set_copy_input(&mut self, input : Option<CopyInput>) {
+        self.copy_input = input;
+    }
+    // taken by the plan reading it, so it's read by one statement only
+    pub fn take_copy_input(&mut self) -> Option<CopyInput> {
+        self.copy_input.take()
+    }
+    pub fn set_session_user(&mut self, user : Option<String>) {
+        self.session_user = user;
+    }
+    // also without the privilege checks, which need both admin_user and an authenticated user
+    pub fn is_admin(&self) -> bool {
+        match self.session_user {
+            Some(ref user) => self.admin_user.is_empty() || *user == self.admin_user,
+            None => true,
+        }
+    }
+    pub fn has_privilege(&self, table : &str, privilege : Privilege) -> bool {
+        if self.is_admin() {
+            return true;
+        }
+        let user = self.session_user.as_ref().unwrap();
+        match self.tables.get(table) {
+            Some(table) => table.borrow().is_granted(user, privilege),
+            None => false,
+        }
+    }
+    // the grants are saved in the catalog right away
+    pub fn grant(&mut self, table : &str, user : &str, privileges : &[Privilege]) {
+        {
+            let mut table = self.tables.get(table).unwrap().borrow_mut();
+            if table.grants.is_none() {
+                table.grants = Some(BTreeMap::new());
+            }
+            let granted = table.grants.as_mut().unwrap().entry(user.to_string()).or_insert(Vec::new());
+            for privilege in privileges.iter() {
+                if !granted.contains(privilege) {
+                    granted.push(*privilege);
+                }
+            }
+        }
+        self.save_catalog();
+    }
+    // the privileges not granted are ignored
+    pub fn revoke(&mut self, table : &str, user : &str, privileges : &[Privilege]) {
+        {
+            let mut table = self.tables.get(table).unwrap().borrow_mut();
+            if let Some(ref mut grants) = table.grants {
+                let revoked_all = match grants.get_mut(user) {
+                    Some(granted) => {
+                        granted.retain(|privilege| !privileges.contains(privilege));
+                        granted.is_empty()
+                    }
+                    None => false,
+                };
+                if revoked_all {
+                    grants.remove(user);
+                }
+            }
+        }
+        self.save_catalog();
+    }
+    pub fn get_schema_version(&self) -> usize {
+        self.schema_version
+    }
+    pub fn namespace_snapshot(&self) -> Namespace {
+        Namespace{
+            version : self.schema_version,
+            tables : self.tables.values().map(|t| {
+                let t = t.borrow();
+                NamespaceTable{ name : t.name.clone(), columns : t.get_attr_name_list() }
+            }).collect(),
+            keywords : KEYWORDS.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+    pub fn save_to_file(&mut self) {
+        self.file_manager.save_all();
+        self.save_catalog();
+    }
+    // for `flush` and shutting down, a message for each table or file failed instead of panicking
+    pub fn flush_all(&mut self) -> Vec<String> {
+        let mut errors : Vec<String> = self.file_manager.flush_all().into_iter()
+            .map(|(table, err)| format!("table {}: {}", table, err)).collect();
+        let full_path = path_join(&self.table_meta_dir, &CATALOG_NAME.to_string());
+        if let Err(err) = write_file_atomic(&full_path, self.to_json().as_bytes()) {
+            errors.push(format!("{}: {}", CATALOG_NAME, err));
+        }
+        errors
+    }
+    fn save_catalog(&self) {
+        // renamed over the old one, a crash never leaves a partial catalog
+        let full_path = path_join(&self.table_meta_dir, &CATALOG_NAME.to_string());
+        check_ok!(write_file_atomic(&full_path, self.to_json().as_bytes()));
+    }
+    pub fn create_table(&mut self, table : Table) {
+        self.create_table_steps(table, DDL_STEPS);
+    }
+    pub fn drop_table(&mut self, name : &String) {
+        self.drop_table_steps(name, DDL_STEPS);
+    }
+    // Only the first `steps` of the create are run for the tests, the files are left
+    // as a crash after that step would leave them. See DdlIntent for the steps.
+    pub fn create_table_steps(&mut self, table : Table, steps : usize) {
+        let name = table.name.clone();
+        check_ok!(write_intent(&self.table_meta_dir, &DdlIntent{
+            op : DdlOp::Create,
+            table : name.clone(),
+            new_table : None,
+        }));
+        if steps < 2 {
+            return;
+        }
+        // left by a table dropped before the journal
+        remove_file(self.file_manager.get_file_path(&name)).ok();
+        self.add_table(table);
+        // with the header, so it can be opened after a crash
+        check_ok!(self.file_manager.sync_file(&name));
+        if steps < 3 {
+            return;
+        }
+        self.save_catalog();
+        if steps < DDL_STEPS {
+            return;
+        }
+        check_ok!(clear_intent(&self.table_meta_dir));
+    }
+    // the same as create_table_steps for a drop
+    pub fn drop_table_steps(&mut self, name : &String, steps : usize) {
+        check_ok!(write_intent(&self.table_meta_dir, &DdlIntent{
+            op : DdlOp::Drop,
+            table : name.clone(),
+            new_table : None,
+        }));
+        if steps < 2 {
+            return;
+        }
+        // the pages in memory are discarded
+        if let Err(err) = self.file_manager.drop_file(name) {
+            // reported at the next start like the file of a table dropped before the journal
+            log_warn!("can't remove the file of table {}: {}", name, err);
+        }
+        self.remove_table(name);
+        if steps < 3 {
+            return;
+        }
+        self.save_catalog();
+        if steps < DDL_STEPS {
+            return;
+        }
+        check_ok!(clear_intent(&self.table_meta_dir));
+    }
+    pub fn close(&mut self) {
+        // for shutting down, the manager should not be used after it
+        self.file_manager.close();
+        self.save_to_file();
+    }
+    pub fn drop_all(&mut self) {
+        // remove all the tables with their files instead of saving them, like close it is the last call
+        self.file_manager.drop_all();
+        self.tables.clear();
+        self.schema_version += 1;
+        remove_file(Path::new(&self.table_meta_dir).join("table_meta.json")).ok();
+        match self.temp_dir.take() {
+            Some(dir) => { remove_dir_all(&dir).ok(); }
+            None => { remove_dir(&self.table_meta_dir).ok(); }  // only when nothing else is left in it
+        }
+    }
+    pub fn rewrite_table(&mut self, new_table : Table, transform : &RowTransform) -> Result<usize, String> {
+        self.rewrite_table_steps(new_table, transform, DDL_STEPS)
+    }
+    // Copy the tuples into a shadow file of the new layout and return the number copied.
+    // The old file and catalog are not touched until all the tuples are copied,
+    // then only the first `steps` of the switch are run like create_table_steps.
+    // Primary keys are checked by scanning, so there is no index to rebuild.
+    pub fn rewrite_table_steps(&mut self, new_table : Table, transform : &RowTransform, steps : usize)
+            -> Result<usize, String> {
+        let name = new_table.name.clone();
+        if !self.tables.contains_key(&name) {
+            return Err(format!("table {} not exist", name));
+        }
+        self.save_to_file();
+        // the pages failed to be saved would be lost with the old file
+        if let Err(err) = self.file_manager.check_writable(&name) {
+            return Err(err.error_msg);
+        }
+        let page_sum = self.file_manager.get_file(&name).borrow().page_sum;
+        let shadow_name = get_shadow_name(&name);
+        let shadow_path = self.file_manager.get_file_path(&shadow_name);
+        remove_file(&shadow_path).ok();  // left by a crash
+        let mut shadow_table = new_table.clone();
+        shadow_table.name = shadow_name.clone();
+        self.file_manager.create_file(shadow_name.clone(), Rc::new(RefCell::new(shadow_table)));
+
+        let primary_index = new_table.get_primary_key_index();
+        let mut primary_keys = HashSet::new();
+        let mut row_num = 0;
+        let mut error = None;
+        'copy : for page_index in 0..page_sum {
+            let rows = match self.file_manager.get_page_tuple_values(&name, page_index) {
+                Ok(rows) => rows,
+                Err(err) => {
+                    error = Some(err.error_msg);
+                    break 'copy;
+                }
+            };
+            for row in rows {
+                let value_list = match transform.apply(&row, &new_table.attr_list) {
+                    Ok(value_list) => value_list,
+                    Err(err) => {
+                        error = Some(err);
+                        break 'copy;
+                    }
+                };
+                if !primary_keys.insert(value_list[primary_index].value.clone()) {
+                    error = Some(format!("primary key {} is duplicated after conversion",
+                        value_list[primary_index].value));
+                    break 'copy;
+                }
+                if let Err(err) = self.file_manager.try_insert(&shadow_name, &value_list) {
+                    error = Some(format!("can't copy the tuples into {}: {}", shadow_path, err.error_msg));
+                    break 'copy;
+                }
+                row_num += 1;
+            }
+        }
+        if error.is_none() {
+            if let Err(err) = self.file_manager.sync_file(&shadow_name) {
+                error = Some(format!("can't write {}: {}", shadow_path, err));
+            }
+        }
+        self.file_manager.remove_file(&shadow_name);
+        if let Some(err) = error {
+            remove_file(&shadow_path).ok();
+            return Err(err);
+        }
+
+        // The tuples are all in the shadow file, switch to it. After the intent is written
+        // a crash is rolled forward by recover_ddl with the schema in it.
+        let intent = DdlIntent{
+            op : DdlOp::Alter,
+            table : name.clone(),
+            new_table : Some(encode(&new_table).unwrap()),
+        };
+        if let Err(err) = write_intent(&self.table_meta_dir, &intent) {
+            remove_file(&shadow_path).ok();
+            return Err(format!("can't write the ddl journal: {}", err));
+        }
+        if steps < 2 {
+            return Ok(row_num);
+        }
+        let path = self.file_manager.get_file_path(&name);
+        if let Err(err) = rename(&shadow_path, &path) {
+            // the intent goes first, it would finish the alter without the shadow file
+            check_ok!(clear_intent(&self.table_meta_dir));
+            remove_file(&shadow_path).ok();
+            return Err(format!("can't replace {} with {}: {}", path, shadow_path, err));
+        }
+        // the old file has been saved and is only read by the copy, nothing is lost
+        self.file_manager.remove_file(&name);
+        let table_ref = Rc::new(RefCell::new(new_table));
+        self.file_manager.create_file(name.clone(), table_ref.clone());
+        let init_result = self.file_manager.get_file(&name).borrow_mut().init_from_file();
+        if let Err(err) = init_result {
+            // the journal is left, the catalog is updated when the server starts again
+            self.file_manager.remove_file(&name);
+            return Err(format!("can't open the rewritten {}: {}", path, err));
+        }
+        self.tables.insert(name, table_ref);
+        self.schema_version += 1;
+        if steps < 3 {
+            return Ok(row_num);
+        }
+        self.save_catalog();
+        if steps < DDL_STEPS {
+            return Ok(row_num);
+        }
+        check_ok!(clear_intent(&self.table_meta_dir));
+        Ok(row_num)
+    }
+    pub fn analyze(&mut self, name : &String) -> Result<TableStats, StoreError> {
+        // a single scan over the pages, the stats are saved in the catalog right away
+        let table = self.get_table(name).unwrap();
+        let mut collector = StatsCollector::new(&table.borrow().attr_list);
+        for slot in self.file_manager.scan_slots(name) {
+            let (_, _, row) = try!(slot);
+            collector.add_row(&row);
+        }
+        let stats = collector.finish();
+        table.borrow_mut().stats = Some(stats.clone());
+        self.save_to_file();
+        Ok(stats)
+    }
+    pub fn backup(&mut self, dir : &String) -> Result<BackupManifest, String> {
+        // Statements are executed one by one in the worker thread,
+        // so no table can be modified until the snapshot is taken.
+        // The backup directory can be used as both table_meta_dir and table_file_dir.
+        try!(prepare_backup_dir(dir));
+        self.save_to_file();
+        let mut files = Vec::new();
+        let json_str = self.to_json();
+        match write_backup_file(dir, "table_meta.json", json_str.as_bytes()) {
+            Ok(backup_file) => files.push(backup_file),
+            Err(err) => return Err(format!("can't write table_meta.json: {}", err)),
+        }
+        let table_names : Vec<String> = self.tables.keys().cloned().collect();
+        for name in table_names.iter() {
+            let src = self.file_manager.get_file(name).borrow().saved_name.clone();
+            let file_name = format!("{}.table", name);
+            match copy_backup_file(&src, dir, &file_name) {
+                Ok(backup_file) => files.push(backup_file),
+                Err(err) => return Err(format!("can't copy {}: {}", file_name, err)),
+            }
+        }
+        let manifest = BackupManifest::new(files);
+        match write_manifest(dir, &manifest) {
+            Ok(..) => Ok(manifest),
+            Err(err) => Err(format!("can't write manifest: {}", err)),
+        }
+    }
+    pub fn restore(&mut self, dir : &String, force : bool) -> Result<Vec<String>, String> {
+        // return the restored tables, nothing is changed if any check or copy fails
+        let manifest = try!(verify_backup(dir));
+        try!(check_manifest_format(&manifest));
+        let tables = try!(read_backup_catalog(dir));
+        let mut errors = check_catalog(&tables);
+        for name in tables.keys() {
+            if let Err(err) = check_table_file(dir, name, &manifest) {
+                errors.push(err);
+            }
+            if !force && self.tables.contains_key(name) {
+                errors.push(format!("table {} already exist, use `with force` to replace it", name));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        // all the files are copied before any table is replaced
+        let mut staged = Vec::new();
+        for name in tables.keys() {
+            let dst = self.file_manager.get_file_path(name);
+            match stage_backup_file(dir, &format!("{}.table", name), &manifest, &dst) {
+                Ok(staged_path) => staged.push((staged_path, dst)),
+                Err(err) => {
+                    for &(ref staged_path, _) in staged.iter() {
+                        remove_file(staged_path).ok();
+                    }
+                    return Err(format!("can't restore {}", err));
+                }
+            }
+        }
+        let mut restored = Vec::new();
+        for ((name, table), (staged_path, dst)) in tables.into_iter().zip(staged.into_iter()) {
+            if let Err(err) = rename(&staged_path, &dst) {
+                remove_file(&staged_path).ok();
+                errors.push(format!("can't restore {}.table: {}", name, err));
+                continue;
+            }
+            // a dropped table may still have its file registered
+            self.tables.remove(&name);
+            self.file_manager.remove_file(&name);
+            let table_ref = Rc::new(RefCell::new(table));
+            self.file_manager.create_file(name.clone(), table_ref.clone());
+            let init_result = self.file_manager.get_file(&name).borrow_mut().init_from_file();
+            if let Err(err) = init_result {
+                self.file_manager.remove_file(&name);
+                errors.push(format!("can't restore {}.table: {}", name, err));
+                continue;
+            }
+            self.tables.insert(name.clone(), table_ref);
+            restored.push(name);
+        }
+        // the catalog is saved once for all the tables replaced
+        self.schema_version += 1;
+        self.save_to_file();
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        Ok(restored)
+    }
+    pub fn from_json_file(config : &Config) -> TableManager {
+        let table_meta_dir = config.get_path("table_meta_dir");
+        ensure_dir_exist(&table_meta_dir);
+        // a create or drop interrupted by a crash is finished before the catalog is read
+        match recover_ddl(&table_meta_dir, &config.get_path("table_file_dir")) {
+            Ok(Some(done)) => log_info!("{}", done),
+            Ok(None) => (),
+            Err(err) => panic!("can't recover the ddl journal: {}", err),
+        }
+        let full_path = path_join(&table_meta_dir, &CATALOG_NAME.to_string());
+        let mut file = OpenOptions::new().read(true).write(true).create(true).open(
+            &full_path).unwrap();
+        let mut json_str = String::new();
+        assert!(file.read_to_string(&mut json_str).is_ok());
+        if json_str.len() == 0 { return TableManager::new(config) }
+        Self::from_json(config, &json_str, true)
+    }
+    pub fn from_json(config : &Config, json : &String, init_file : bool) -> TableManager {
+        // setting init_file to false only for tests
+        let mut tables = BTreeMap::new();
+        let mut table_list = Vec::new();
+        let tree : BTreeMap<String, Table> = unwrap!(decode(json));
+        let problems = check_catalog(&tree);
+        if !problems.is_empty() {
+            panic!("invalid catalog:\n{}", problems.join("\n"));
+        }
+        for (name, table) in tree.iter() {
+            let t = Rc::new(RefCell::new(table.clone()));
+            tables.insert(name.clone(), t.clone());
+            table_list.push(t);
+        }
+        let mut manager = if init_file {
+            // the server can't run with a part of the tables, while a file of no table is only reported
+            match TableFileManager::from_files(config, table_list) {
+                Ok((file_manager, unknown)) => {
+                    for msg in unknown.iter() {
+                        log_warn!("{}", msg);
+                    }
+                    Self::with_file_manager(config, file_manager)
+                }
+                Err(err) => panic!("can't open the table files:\n{}", err),
+            }
+        } else {
+            Self::new(config)
+        };
+        manager.tables = tables;
+        manager
+    }
+    pub fn to_json(&self) -> String {
+        let mut tree : BTreeMap<String, Table> = BTreeMap::new();
+        for (name, table) in self.tables.iter() {
+            tree.insert(name.clone(), table.borrow().clone());
+        }
+        unwrap!(encode(&tree))
+    }
+    pub fn add_table(&mut self, table : Table) {
+        // add new table and create empty file
+        let name = table.name.clone();
+        assert!(!self.tables.get(&name).is_some());
+        let table_ref = Rc::new(RefCell::new(table));
+        self.file_manager.create_file(name.clone(), table_ref.clone());
+        self.tables.insert(name, table_ref);
+        self.schema_version += 1;
+    }
+    pub fn remove_table(&mut self, table : &String) {
+        self.tables.remove(table);
+        self.schema_version += 1;
+    }
+    pub fn get_table_names(&self) -> Vec<String> {
+        self.tables.keys().cloned().collect()
+    }
+    pub fn get_table(&self, name : &str) -> Option<TableRef> {
+        match self.tables.get(name) {
+            Some(ref mut table) => Some(table.clone()),
+            None => None,
+        }
+    }
+    pub fn gen_table_set(&self, used_table : &Vec<String>) -> TableSet {
+        let mut tables = BTreeMap::new();
+        for name in used_table.iter() {
+            if let Some(t) = self.tables.get(name) {
+                tables.insert(name.clone(), t.borrow().clone());
+            }
+        }
+        TableSet{ tables : tables }
+    }
+    pub fn get_tuple_value(&mut self, table : &String,
+            position : usize,
+            attr_position : usize) -> TupleValue{
+        self.file_manager.get_tuple_value(table, position, attr_position)
+    }
+    pub fn insert(&mut self, table : &String, value_list : &ValueList) -> Result<usize, StoreError> {
+        // checked before any page is touched, only the insert statements are checked by sem_check
+        let attr_num = match self.tables.get(table) {
+            Some(t) => t.borrow().attr_list.len(),
+            None => return Err(StoreError{
+                error_type : StoreErrorType::InvalidValuesNum,
+                error_msg : format!("table {} not exist", table),
+            }),
+        };
+        if value_list.len() != attr_num {
+            return Err(StoreError{
+                error_type : StoreErrorType::InvalidValuesNum,
+                error_msg : format!("invalid insert values number of table {}, expected {}, found {}",
+                    table, attr_num, value_list.len()),
+            });
+        }
+        self.file_manager.try_insert(table, value_list)
+    }
+    // a `name value` line for each counter
+    pub fn show_status(&self) -> String {
+        let storage = self.file_manager.get_storage_stats();
+        let flush = self.file_manager.get_flush_stats();
+        let counters = vec![
+            ("queries", self.query_num),
+            ("rows_read", storage.rows_read),
+            ("rows_written", storage.rows_written),
+            ("page_hits", storage.page_hits),
+            ("page_misses", storage.page_misses),
+            ("page_evictions", storage.page_evictions),
+            ("page_prefetches", storage.page_prefetches),
+            ("dirty_pages", self.file_manager.get_dirty_page_num()),
+            ("background_flushed_pages", flush.background),
+            ("foreground_flushed_pages", flush.foreground),
+        ];
+        let lines : Vec<String> = counters.iter().map(|&(name, n)| format!("{} {}", name, n)).collect();
+        lines.join("\n")
+    }
+    pub fn show_tables(&self) -> String {
+        let mut result = String::new();
+        for (_, t) in self.tables.iter() {
+            result.push_str(&t.borrow().desc());
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl Drop for TableManager {
+    fn drop(&mut self) {
+        if self.temp_dir.is_some() {
+            self.drop_all();
+        }
+    }
+}
+
+impl Encodable for AttrType {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let mut t = BTreeMap::new();
+        match self {
+            &AttrType::Int => t.insert("type".to_string(), "Int".to_string()),
+            &AttrType::Float => t.insert("type".to_string(), "Float".to_string()),
+            &AttrType::Char{len} => {
+                t.insert("type".to_string(), "Char".to_string());
+                t.insert("len".to_string(), len.to_string())
+            }
+        };
+        t.encode(s)
+    }
+}
+
+impl Decodable for AttrType {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        let t : BTreeMap<String, String> = try!(BTreeMap::decode(d));
+        let res = match t.get("type") {
+            None => panic!("can't find key 'type' in AttrType json data"),
+            Some(ref s) => match &s[..] {
+                "Int" => AttrType::Int,
+                "Float" => AttrType::Float,
+                "Char" => {
+                    let len = match t.get("len") {
+                        None => panic!("can't find key 'len' for Char in AttrType json data"),
+                        Some(len) => len.parse::<usize>().unwrap(),
+                    };
+                    AttrType::Char{ len : len }
+                }
+                _ => panic!("unexpected type {}", s),
+            }
+        };
+        Ok(res)
+    }
+}
@@ -1,57 +1,71 @@
-use std::vec::Vec;
-use std::ptr::read;
-use utils::pointer::read_string;
-use super::buffer::DataPtr;
-use super::table::{AttrType, Attr};
-
-
-#[derive(Debug, Clone)]
-pub enum TupleValue {
-    Int(i32),
-    Float(f32),
-    Char(String),
-}
-
-#[derive(Debug, Clone)]
-pub struct TupleDesc {
-    pub attr_desc : Vec<AttrType>,
-    pub tuple_len : usize,
-}
-
-impl TupleDesc {
-    pub fn new(attr_list : &Vec<Attr>) -> TupleDesc {
-        let attr_desc = attr_list.iter().map(|attr| attr.attr_type.clone()).collect();
-        let tuple_len = tuple_len(attr_list);
-        TupleDesc{
-            attr_desc : attr_desc,
-            tuple_len : tuple_len,
-        }
-    }
-}
-
-pub type TupleData = Vec<DataPtr>;
-
-pub fn tuple_len(attr_list : &Vec<Attr>) -> usize {
-    let mut l = 0;
-    for attr in attr_list {
-        l += match attr.attr_type {
-            AttrType::Int | AttrType::Float => 4,
-            AttrType::Char{len} => (len + 3) / 4 * 4,  // align to 4 bytes
-        }
-    }
-    l
-}
-
-pub fn gen_tuple_value(attr_desc : &Vec<AttrType>, tuple_data : TupleData) -> Vec<TupleValue> {
-    let mut value_list = Vec::new();
-    assert_eq!(attr_desc.len(), tuple_data.len());
-    for (attr, p) in attr_desc.iter().zip(tuple_data.iter()) {
-        let value = match attr {
-            &AttrType::Int => TupleValue::Int(unsafe{read::<i32>(*p as *const i32)}),
-            &AttrType::Float => TupleValue::Float(unsafe{read::<f32>(*p as *const f32)}),
-            &AttrType::Char{len} => TupleValue::Char(unsafe{read_string(*p, len)}),
-        };
-        value_list.push(value);
-    }
-    value_list
-}
+use std::vec::Vec;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use utils::pointer::{read_string, read_i32_le, read_f32_le};
+use utils::float::format_float;
+use super::buffer::DataPtr;
+use super::table::{AttrType, Attr};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TupleValue {
+    Int(i32),
+    Float(f32),
+    Char(String),
+    Null,  // null is stored as zero bytes, so it's never read back from page
+}
+
+impl Display for TupleValue {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &TupleValue::Int(n) => write!(f, "{}", n),
+            &TupleValue::Float(n) => write!(f, "{}", format_float(n)),
+            &TupleValue::Char(ref s) => write!(f, "{}", s),
+            &TupleValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TupleDesc {
+    pub attr_desc : Vec<AttrType>,
+    pub tuple_len : usize,
+}
+
+impl TupleDesc {
+    pub fn new(attr_list : &Vec<Attr>) -> TupleDesc {
+        let attr_desc = attr_list.iter().map(|attr| attr.attr_type.clone()).collect();
+        let tuple_len = tuple_len(attr_list);
+        TupleDesc{
+            attr_desc : attr_desc,
+            tuple_len : tuple_len,
+        }
+    }
+}
+
+pub type TupleData = Vec<DataPtr>;
+
+pub fn tuple_len(attr_list : &Vec<Attr>) -> usize {
+    let mut l = 0;
+    for attr in attr_list {
+        l += match attr.attr_type {
+            AttrType::Int | AttrType::Float => 4,
+            AttrType::Char{len} => (len + 3) / 4 * 4,  // align to 4 bytes
+        }
+    }
+    l
+}
+
+pub fn gen_tuple_value(attr_desc : &Vec<AttrType>, tuple_data : TupleData) -> Vec<TupleValue> {
+    let mut value_list = Vec::new();
+    assert_eq!(attr_desc.len(), tuple_data.len());
+    for (attr, p) in attr_desc.iter().zip(tuple_data.iter()) {
+        let value = match attr {
+            &AttrType::Int => TupleValue::Int(unsafe{read_i32_le(*p)}),
+            &AttrType::Float => TupleValue::Float(unsafe{read_f32_le(*p)}),
+            &AttrType::Char{len} => TupleValue::Char(unsafe{read_string(*p, len)}),
+        };
+        value_list.push(value);
+    }
+    value_list
+}
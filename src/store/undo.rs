@@ -0,0 +1,83 @@
+use super::file::{TableFileManager, StoreError, FreePages};
+
+
+// The change of a tuple slot, enough to put the slot back. It only has the position and the raw bytes,
+// so the same record can be written to a log file once there is one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoOp {
+    Insert,  // free the slot again
+    Update,  // write back the before-image
+    Delete,  // mark the slot in use and write back the before-image
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoRecord {
+    pub op : UndoOp,
+    pub position : usize,  // page_index * slot_sum + tuple_index
+    pub before : Vec<u8>,  // the whole tuple slot before the change, empty for insert
+    pub free_pages : Option<FreePages>,  // of the file before a delete, which may add its page
+}
+
+// The changes of a running statement on a table, undone in the reverse order if the statement fails.
+#[derive(Debug)]
+pub struct UndoLog {
+    table : String,
+    records : Vec<UndoRecord>,
+}
+
+impl UndoLog {
+    pub fn new(table : &String) -> UndoLog {
+        UndoLog{
+            table : table.clone(),
+            records : Vec::new(),
+        }
+    }
+    pub fn push(&mut self, op : UndoOp, position : usize, before : Vec<u8>) {
+        self.records.push(UndoRecord{
+            op : op,
+            position : position,
+            before : before,
+            free_pages : None,
+        });
+    }
+    pub fn push_delete(&mut self, position : usize, before : Vec<u8>, free_pages : FreePages) {
+        self.records.push(UndoRecord{
+            op : UndoOp::Delete,
+            position : position,
+            before : before,
+            free_pages : Some(free_pages),
+        });
+    }
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    pub fn get_records(&self) -> &Vec<UndoRecord> {
+        &self.records
+    }
+    // the statement succeeded
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+    // Return the number of the records undone. The ones whose page can't be loaded are skipped,
+    // the others are still undone and the first error is returned.
+    pub fn rollback(&mut self, file_manager : &mut TableFileManager) -> Result<usize, StoreError> {
+        let undone = self.records.len();
+        let mut error = None;
+        while let Some(record) = self.records.pop() {
+            if let Err(err) = file_manager.undo(&self.table, &record) {
+                log_error!("can't undo the change of tuple {} of {}: {}",
+                    record.position, self.table, err.error_msg);
+                if error.is_none() {
+                    error = Some(err);
+                }
+            }
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+        if undone > 0 {
+            log_debug!("undo {} change(s) of {}", undone, self.table);
+        }
+        Ok(undone)
+    }
+}
@@ -0,0 +1,27 @@
+use std::io;
+use std::fs::File;
+use std::fmt::Debug;
+use std::sync::Arc;
+use ::utils::file::write_all_at;
+
+
+// The pages and the headers of the table files are written through it, shared with the flusher thread.
+// The tests replace it to fail the writes like a full disk.
+pub trait FileWriter : Debug + Send + Sync {
+    fn write_at(&self, file : &File, data : &[u8], offset : u64) -> io::Result<()>;
+}
+
+pub type FileWriterRef = Arc<FileWriter>;
+
+#[derive(Debug)]
+pub struct OsFileWriter;
+
+impl FileWriter for OsFileWriter {
+    fn write_at(&self, file : &File, data : &[u8], offset : u64) -> io::Result<()> {
+        write_all_at(file, data, offset)
+    }
+}
+
+pub fn os_file_writer() -> FileWriterRef {
+    Arc::new(OsFileWriter)
+}
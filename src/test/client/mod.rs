@@ -0,0 +1,3 @@
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_connection;
@@ -0,0 +1,292 @@
+use std::net::{TcpListener, SocketAddr};
+use std::io::{Read, Write, BufRead, BufReader};
+use std::fs::{File, remove_dir_all, create_dir_all};
+use std::time::Duration;
+use std::thread::spawn;
+#[cfg(feature = "tls")]
+use std::iter::repeat;
+use std::cmp::min;
+use ::client::{connect, Client, Credentials, Response, RowStream, ClientError};
+#[cfg(feature = "tls")]
+use ::client::{connect_tls, TlsVerify};
+use ::server::server::{start_server, launch_server};
+use ::server::auth::gen_user_entry;
+use ::server::handler::{Summary, StatementKind};
+use ::store::tuple::TupleValue;
+use ::utils::config::Config;
+
+
+#[cfg(feature = "tls")]
+const TLS_FINGERPRINT : &'static str =
+    "99:24:F0:1C:E4:F3:70:AB:5B:04:D0:24:F9:C1:D7:C7:2C:B2:B9:0E:20:75:34:5F:71:0F:22:29:F0:47:1E:49";
+
+fn test_config(name : &str, users_file : &str, extra : &str) -> Config {
+    remove_dir_all(format!("test_file/{}/", name)).ok();
+    Config::new(&format!(r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        result_batch_bytes = 64
+        users_file = "{}"
+        table_meta_dir = "test_file/{}/table_meta/"
+        table_file_dir = "test_file/{}/table_file/"
+        {}"#, users_file, name, name, extra))
+}
+
+fn start_test_server(name : &str, users_file : &str) -> SocketAddr {
+    let (addr, _) = start_server(test_config(name, users_file, ""));
+    addr
+}
+
+// the self-signed certificate of localhost in src/test/tls/
+#[cfg(feature = "tls")]
+fn tls_test_config(name : &str, plaintext : bool) -> Config {
+    test_config(name, "", &format!(r#"
+        tls_enabled = true
+        tls_cert_file = "src/test/tls/cert.pem"
+        tls_key_file = "src/test/tls/key.pem"
+        tls_port = 0
+        plaintext_enabled = {}"#, plaintext))
+}
+
+fn connect_test(addr : &SocketAddr, credentials : Option<Credentials>) -> Client {
+    let mut client = check_ok!(connect(addr, credentials));
+    check_ok!(client.set_timeout(Some(Duration::from_secs(10))));
+    client
+}
+
+fn affected(client : &mut Client, sql : &str) -> Summary {
+    match check_ok!(client.execute(sql)) {
+        Response::Affected(summary) => summary,
+        response => panic!("unexpected response {:?}", response),
+    }
+}
+
+fn query<'a>(client : &'a mut Client, sql : &str) -> RowStream<'a> {
+    match check_ok!(client.execute(sql)) {
+        Response::Rows(rows) => rows,
+        response => panic!("unexpected response {:?}", response),
+    }
+}
+
+#[test]
+fn test_client() {
+    let addr = start_test_server("test_client", "");
+    let mut client = connect_test(&addr, None);
+    assert_pattern!(check_ok!(client.execute("create table client_msg(id int not null primary, \
+        score float, content char(8))")), Response::Done);
+    for i in 0..40 {
+        let sql = format!("insert client_msg values({}, {}.5, \"c{}\")", i, i, i);
+        assert_eq!(affected(&mut client, &sql), Summary::new(StatementKind::Insert, 1));
+    }
+
+    {
+        let mut rows = query(&mut client, "select * from client_msg");
+        assert_eq!(rows.get_columns()[2].name, "content");
+        let mut ids = Vec::new();
+        while let Some(row) = check_ok!(rows.next_row()) {
+            let id = check_ok!(row.get_int("id"));
+            assert_eq!(check_ok!(row.get_float(1)), id as f32 + 0.5);
+            assert_eq!(check_ok!(row.get_string("content")), format!("c{}", id));
+            assert_pattern!(row.get_int("content"), Err(ClientError::TypeMismatch{..}));
+            assert_pattern!(row.get_int("not_exist"), Err(ClientError::ColumnNotExist(..)));
+            assert_pattern!(row.get_int(3), Err(ClientError::ColumnNotExist(..)));
+            ids.push(id);
+        }
+        assert_eq!(ids, (0..40).collect::<Vec<i32>>());
+        assert_eq!(rows.get_summary(), Some(Summary::new(StatementKind::Select, 40)));
+    }
+
+    // the rest of the rows are skipped when the stream is dropped
+    {
+        let mut rows = query(&mut client, "select id from client_msg");
+        let row = check_ok!(rows.next().unwrap());
+        assert_eq!(row.values(), &[TupleValue::Int(0)]);
+    }
+    assert_eq!(affected(&mut client, "delete from client_msg where id >= 10"),
+        Summary::new(StatementKind::Delete, 30));
+
+    match client.execute("select * from not_exist") {
+        Err(ClientError::Server{code, message}) => {
+            assert_eq!(code, 1200);  // SemTableNotExist
+            assert!(message.contains("not_exist"));
+        }
+        _ => panic!("the select should fail"),
+    }
+    assert!(!client.is_broken());
+    let rows = query(&mut client, "select id from client_msg");
+    assert_eq!(rows.count(), 10);
+}
+
+#[test]
+fn test_client_auth() {
+    // out of the dir of the server, which is removed when it starts
+    remove_dir_all("test_file/test_client_auth_users/").ok();
+    check_ok!(create_dir_all("test_file/test_client_auth_users/"));
+    let mut users_file = check_ok!(File::create("test_file/test_client_auth_users/users"));
+    check_ok!(writeln!(users_file, "{}", gen_user_entry("admin", "secret")));
+    drop(users_file);
+    let addr = start_test_server("test_client_auth", "test_file/test_client_auth_users/users");
+
+    let credentials = Credentials{ user : "admin".to_string(), password : "wrong".to_string() };
+    match connect(&addr, Some(credentials)) {
+        Err(ClientError::Server{code, ..}) => assert_eq!(code, 2103),  // AuthFailed
+        _ => panic!("the auth should fail"),
+    }
+    let credentials = Credentials{ user : "admin".to_string(), password : "secret".to_string() };
+    let mut client = connect_test(&addr, Some(credentials));
+    assert_pattern!(check_ok!(client.execute("create table client_auth(id int not null primary)")),
+        Response::Done);
+}
+
+#[test]
+fn test_client_broken_stream() {
+    // a server closing the connection in the middle of the rows, then answering a new connection
+    let listener = check_ok!(TcpListener::bind("127.0.0.1:0"));
+    let addr = check_ok!(listener.local_addr());
+    spawn(move || {
+        for (i, stream) in listener.incoming().take(2).enumerate() {
+            let mut stream = check_ok!(stream);
+            let mut line = String::new();
+            check_ok!(BufReader::new(check_ok!(stream.try_clone())).read_line(&mut line));
+            if i == 0 {
+                let header = r#"[{"name":"id","attr_type":{"type":"Int"},"nullable":false}]"#;
+                check_ok!(stream.write_all(&[header.len() as u8, 0, 0, 0]));
+                check_ok!(stream.write_all(header.as_bytes()));
+                check_ok!(stream.write_all(b"\x03\x02\0\0\0\x01\0\0\0"));  // one of the two rows
+            } else {
+                check_ok!(stream.write_all(b"\0\0\0\0\r\n"));
+            }
+        }
+    });
+
+    let mut client = connect_test(&addr, None);
+    {
+        let mut rows = query(&mut client, "select id from t");
+        assert_eq!(check_ok!(check_ok!(rows.next_row()).unwrap().get_int(0)), 1);
+        assert_pattern!(rows.next_row(), Err(ClientError::Io(..)));
+        assert_pattern!(rows.next(), None);
+    }
+    assert!(client.is_broken());
+    assert_pattern!(client.execute("select id from t"), Err(ClientError::ConnectionBroken));
+    check_ok!(client.reconnect());
+    assert_pattern!(check_ok!(client.execute("drop table t")), Response::Done);
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn test_client_tls() {
+    let server = launch_server(tls_test_config("test_client_tls", true));
+    let tls_addr = server.tls_addr.unwrap();
+    assert!(tls_addr != server.addr);
+
+    let verify = TlsVerify::Fingerprint(TLS_FINGERPRINT.to_string());
+    let mut client = check_ok!(connect_tls(&tls_addr, None, verify));
+    assert_pattern!(check_ok!(client.execute("create table client_tls(id int not null primary)")),
+        Response::Done);
+    assert_eq!(affected(&mut client, "insert client_tls values(1)"), Summary::new(StatementKind::Insert, 1));
+    let verify = TlsVerify::CaFile("src/test/tls/cert.pem".to_string());
+    let mut client = check_ok!(connect_tls(&tls_addr, None, verify));
+    assert_eq!(query(&mut client, "select * from client_tls").count(), 1);
+
+    let verify = TlsVerify::Fingerprint(repeat('0').take(64).collect());
+    assert_pattern!(connect_tls(&tls_addr, None, verify), Err(ClientError::Tls(..)));
+    // the plaintext listener is still open
+    let mut client = connect_test(&server.addr, None);
+    assert_eq!(query(&mut client, "select * from client_tls").count(), 1);
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn test_client_tls_only() {
+    let server = launch_server(tls_test_config("test_client_tls_only", false));
+    assert_eq!(server.tls_addr, Some(server.addr));
+
+    let mut client = connect_test(&server.addr, None);
+    assert!(client.execute("create table client_tls_only(id int not null primary)").is_err());
+    assert!(client.is_broken());
+    let verify = TlsVerify::Fingerprint(TLS_FINGERPRINT.to_string());
+    let mut client = check_ok!(connect_tls(&server.addr, None, verify));
+    assert_pattern!(check_ok!(client.execute("create table client_tls_only(id int not null primary)")),
+        Response::Done);
+}
+
+#[test]
+fn test_client_cursor() {
+    let addr = start_test_server("test_client_cursor", "");
+    let mut client = connect_test(&addr, None);
+    check_ok!(client.execute("create table client_cursor(id int not null primary)"));
+    for i in 0..3 {
+        affected(&mut client, &format!("insert client_cursor values({})", i));
+    }
+    assert_pattern!(check_ok!(client.execute("declare c cursor for select id from client_cursor")),
+        Response::Done);
+    let mut pages = Vec::new();
+    for _ in 0..3 {
+        let mut rows = query(&mut client, "fetch 2 from c");
+        let ids : Vec<i32> = rows.by_ref().map(|row| check_ok!(check_ok!(row).get_int(0))).collect();
+        pages.push((ids, rows.get_warnings().to_vec()));
+    }
+    assert_eq!(pages[0], (vec![0, 1], vec![]));
+    assert_eq!(pages[1], (vec![2], vec!["cursor c has no more rows".to_string()]));
+    assert_eq!(pages[2], (vec![], vec!["cursor c has no more rows".to_string()]));
+    assert_pattern!(check_ok!(client.execute("close c")), Response::Done);
+    assert_pattern!(client.execute("fetch 2 from c"), Err(ClientError::Server{code : 2107, ..}));
+}
+
+// reads at most chunk bytes at a time, so the data is sent in frames of it
+struct ChunkReader<'a> {
+    data : &'a [u8],
+    chunk : usize,
+}
+
+impl<'a> Read for ChunkReader<'a> {
+    fn read(&mut self, buf : &mut [u8]) -> ::std::io::Result<usize> {
+        let len = min(buf.len(), self.chunk);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+fn count_rows(client : &mut Client, table : &str) -> usize {
+    query(client, &format!("select id from {}", table)).count()
+}
+
+#[test]
+fn test_client_copy_in() {
+    let (addr, _) = start_server(test_config("test_client_copy_in", "", "max_copy_frame_bytes = 16384"));
+    let mut client = connect_test(&addr, None);
+    check_ok!(client.execute("create table client_copy(id int not null primary, \
+        score float, content char(128))"));
+
+    // the lines are split across the frames, and more than the server reads ahead,
+    // they are long as each insert scans the table for the primary key
+    let data : String = (0..500).map(|i| format!("{},{}.5,{:c>126}\n", i, i, i)).collect();
+    let mut reader = ChunkReader{ data : data.as_bytes(), chunk : 1000 };
+    let summary = check_ok!(client.copy_in("copy client_copy from stdin", &mut reader));
+    assert_eq!(summary, Summary::new(StatementKind::Insert, 500));
+    assert_eq!(count_rows(&mut client, "client_copy"), 500);
+
+    // the first failed line ends the copy, the lines before it are kept
+    let data : String = (500..600).map(|i| {
+        if i == 550 { "x,1.5,bad\n".to_string() } else { format!("{},{}.5,c{}\n", i, i, i) }
+    }).collect();
+    match client.copy_in("copy client_copy from stdin", &mut data.as_bytes()) {
+        Err(ClientError::Server{code : 2005, ref message}) => assert!(message.contains("line 51, column 1")),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(count_rows(&mut client, "client_copy"), 550);
+
+    // a statement failed before reading the data still drops all of it
+    let result = client.copy_in("copy client_copy_missing from stdin", &mut data.as_bytes());
+    assert_pattern!(result, Err(ClientError::Server{..}));
+    assert_eq!(count_rows(&mut client, "client_copy"), 550);
+
+    // a frame over max_copy_frame_bytes fails the copy before any line of it
+    let data : String = (3000..5000).map(|i| format!("{},1.5,c\n", i)).collect();
+    match client.copy_in("copy client_copy from stdin", &mut data.as_bytes()) {
+        Err(ClientError::Server{code : 2018, ref message}) =>
+            assert!(message.contains("over max_copy_frame_bytes 16384")),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(count_rows(&mut client, "client_copy"), 550);
+}
@@ -1,15 +1,30 @@
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_create_drop;
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_change;
-#[allow(dead_code)]
-#[allow(unused_imports)]
-pub mod test_query;
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_evaluate;
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_gen_plan;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_create_drop;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_change;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_copy;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+pub mod test_query;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_evaluate;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_gen_plan;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_backup;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_alter;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_bench;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_empty;
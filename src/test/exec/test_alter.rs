@@ -0,0 +1,147 @@
+use std::fs::{File, remove_dir_all, metadata};
+use std::io::Read;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ::exec::error::ExecErrorType;
+use ::exec::query::FileScan;
+use ::store::tuple::gen_tuple_value;
+use ::store::table::{TableManager, TableManagerRef, AttrType};
+use ::utils::config::Config;
+use ::test::utils::FullDiskWriter;
+
+
+fn gen_manager(dir : &str) -> TableManagerRef {
+    remove_dir_all(dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "{}"
+        table_file_dir = "{}""#, dir, dir));
+    Rc::new(RefCell::new(TableManager::new(&config)))
+}
+
+fn scan_rows(table_name : &str, manager : &TableManagerRef) -> Vec<String> {
+    let table_name = table_name.to_string();
+    let tuple_desc = manager.borrow().get_table(&table_name).unwrap().borrow().gen_tuple_desc();
+    let mut scan = FileScan::new(&table_name, manager);
+    let mut result = Vec::new();
+    scan.open();
+    while let Some(tuple_data) = scan.get_next() {
+        let row : Vec<String> = gen_tuple_value(&tuple_desc.attr_desc, tuple_data)
+            .iter().map(|v| v.to_string()).collect();
+        result.push(row.join(","));
+    }
+    result
+}
+
+fn read_file(path : &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+fn run_sql(sql : &str, manager : &TableManagerRef) -> Option<ExecErrorType> {
+    let mut plan = gen_plan_helper!(sql, manager);
+    plan.open();
+    while let Some(..) = plan.get_next() {}
+    plan.get_error().map(|err| err.error_type)
+}
+
+fn insert_rows(manager : &TableManagerRef) {
+    assert_pattern!(run_sql("create table alter_msg(id int not null primary, \
+        content char(8), score float) with fill_factor 80", manager), None);
+    for i in 0..300 {
+        let sql = format!("insert alter_msg values({}, \"{}\", {}.5)", i, i * 3, i);
+        assert_pattern!(run_sql(&sql, manager), None);
+    }
+}
+
+#[test]
+fn test_alter_rewrite() {
+    let manager = gen_manager("test_file/alter/rewrite");
+    insert_rows(&manager);
+    let rows = scan_rows("alter_msg", &manager);
+    assert_eq!(rows[7], "7,21,7.5");
+
+    assert_pattern!(run_sql("alter table alter_msg add comment char(20) null", &manager), None);
+    let expected : Vec<String> = rows.iter().map(|r| format!("{},", r)).collect();
+    assert_eq!(scan_rows("alter_msg", &manager), expected);
+    assert_pattern!(run_sql("insert alter_msg values(1000, \"1\", 1, \"a longer comment\")", &manager), None);
+
+    // the layout shrinks from 28 bytes to 8 bytes
+    assert_pattern!(run_sql("alter table alter_msg drop comment", &manager), None);
+    assert_pattern!(run_sql("alter table alter_msg modify content int null", &manager), None);
+    assert_pattern!(run_sql("alter table alter_msg drop score", &manager), None);
+    let table = manager.borrow().get_table(&"alter_msg".to_string()).unwrap();
+    assert_eq!(table.borrow().gen_tuple_desc().tuple_len, 8);
+    assert_pattern!(table.borrow().attr_list[1].attr_type, AttrType::Int);
+    assert_eq!(table.borrow().fill_factor, Some(80));
+    let rows = scan_rows("alter_msg", &manager);
+    assert_eq!(rows.len(), 301);
+    assert_eq!(rows[7], "7,21");
+    assert_pattern!(run_sql("select * from alter_msg where content = 21", &manager), None);
+
+    assert_pattern!(run_sql("alter table alter_msg modify id float not null primary", &manager), None);
+    assert_eq!(scan_rows("alter_msg", &manager)[7], "7,21");
+    assert!(metadata("test_file/alter/rewrite/alter_msg.rewrite.table").is_err());
+}
+
+#[test]
+fn test_alter_failure_keeps_table() {
+    let manager = gen_manager("test_file/alter/failure");
+    insert_rows(&manager);
+    assert_pattern!(run_sql("update alter_msg set content = \"bad\" where id = 150", &manager), None);
+    manager.borrow_mut().save_to_file();
+    let rows = scan_rows("alter_msg", &manager);
+    let file_data = read_file("test_file/alter/failure/alter_msg.table");
+    let catalog = read_file("test_file/alter/failure/table_meta.json");
+
+    // fails in the middle of the table
+    let mut plan = gen_plan_helper!("alter table alter_msg modify content int", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::AlterRewriteFailed);
+    assert_eq!(err.error_msg, "can't convert \"bad\" to int for attribute content");
+    // longer than the new char len
+    let mut plan = gen_plan_helper!("alter table alter_msg modify content char(2)", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_msg,
+        "\"102\" is too long for attribute content char(2)");
+    // 0.5 can't become an int
+    assert_eq!(run_sql("alter table alter_msg modify score int", &manager),
+        Some(ExecErrorType::AlterRewriteFailed));
+
+    assert!(metadata("test_file/alter/failure/alter_msg.rewrite.table").is_err());
+    assert_eq!(scan_rows("alter_msg", &manager), rows);
+    manager.borrow_mut().save_to_file();
+    assert_eq!(read_file("test_file/alter/failure/alter_msg.table"), file_data);
+    assert_eq!(read_file("test_file/alter/failure/table_meta.json"), catalog);
+    assert_pattern!(run_sql("insert alter_msg values(1000, \"new\", 1)", &manager), None);
+    assert_eq!(scan_rows("alter_msg", &manager).len(), 301);
+}
+
+#[test]
+fn test_alter_write_failure() {
+    let manager = gen_manager("test_file/alter/write_failure");
+    insert_rows(&manager);
+    manager.borrow_mut().save_to_file();
+    let rows = scan_rows("alter_msg", &manager);
+    let catalog = read_file("test_file/alter/write_failure/table_meta.json");
+
+    // the shadow file is larger than the old one
+    let file_len = metadata("test_file/alter/write_failure/alter_msg.table").unwrap().len() as usize;
+    let writer = Arc::new(FullDiskWriter{ limit : AtomicUsize::new(file_len) });
+    manager.borrow_mut().file_manager.set_writer(writer.clone());
+    assert_eq!(run_sql("alter table alter_msg add comment char(20) null", &manager),
+        Some(ExecErrorType::AlterRewriteFailed));
+    assert!(metadata("test_file/alter/write_failure/alter_msg.rewrite.table").is_err());
+    assert_eq!(read_file("test_file/alter/write_failure/table_meta.json"), catalog);
+    assert_eq!(scan_rows("alter_msg", &manager), rows);
+
+    writer.limit.store(usize::max_value(), Ordering::SeqCst);
+    assert_pattern!(run_sql("alter table alter_msg add comment char(20) null", &manager), None);
+    assert_eq!(scan_rows("alter_msg", &manager).len(), 300);
+}
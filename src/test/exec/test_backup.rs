@@ -0,0 +1,182 @@
+use std::fs::{OpenOptions, File, remove_dir_all, remove_file, create_dir, remove_dir, metadata};
+use std::io::{Read, Write, Seek, SeekFrom};
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::exec::error::ExecErrorType;
+use ::exec::query::FileScan;
+use ::store::tuple::{TupleValue, gen_tuple_value};
+use ::store::table::{TableManager, TableManagerRef};
+use ::store::backup::{verify_backup, MANIFEST_NAME};
+use ::store::file::get_page_offset;
+use ::utils::libwrapper::get_page_size;
+use ::utils::config::Config;
+use super::test_query::{gen_test_table, insert_data};
+
+
+fn gen_config(dir : &str) -> Config {
+    Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "{}"
+        table_file_dir = "{}""#, dir, dir))
+}
+
+fn scan_id(table_name : &String, manager : &TableManagerRef) -> Vec<(i32, f32)> {
+    let tuple_desc = manager.borrow().get_table(table_name).unwrap().borrow().gen_tuple_desc();
+    let mut scan = FileScan::new(table_name, manager);
+    let mut result = Vec::new();
+    scan.open();
+    while let Some(tuple_data) = scan.get_next() {
+        let row = gen_tuple_value(&tuple_desc.attr_desc, tuple_data);
+        result.push((extract!(row[0], TupleValue::Int(n), n), extract!(row[1], TupleValue::Float(f), f)));
+    }
+    result.sort_by_key(|&(id, _)| id);
+    result
+}
+
+fn scan_rows(table_name : &str, manager : &TableManagerRef) -> Vec<String> {
+    let table_name = table_name.to_string();
+    let tuple_desc = manager.borrow().get_table(&table_name).unwrap().borrow().gen_tuple_desc();
+    let mut scan = FileScan::new(&table_name, manager);
+    let mut result = Vec::new();
+    scan.open();
+    while let Some(tuple_data) = scan.get_next() {
+        result.push(format!("{:?}", gen_tuple_value(&tuple_desc.attr_desc, tuple_data)));
+    }
+    result
+}
+
+fn read_file(path : &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+fn run_sql(sql : &str, manager : &TableManagerRef) -> Option<ExecErrorType> {
+    let mut plan = gen_plan_helper!(sql, manager);
+    plan.open();
+    while let Some(..) = plan.get_next() {}
+    plan.get_error().map(|err| err.error_type)
+}
+
+#[test]
+fn test_backup_snapshot() {
+    let table_name = "test_backup_message".to_string();
+    remove_dir_all("test_file/backup").ok();
+    let manager = Rc::new(RefCell::new(TableManager::new(&gen_config("test_file/backup/origin"))));
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    insert_data(&table_name, &manager);
+
+    assert_pattern!(run_sql("backup to 'test_file/backup/snapshot'", &manager), None);
+    let manifest = verify_backup(&"test_file/backup/snapshot".to_string()).unwrap();
+    let names : Vec<String> = manifest.files.iter().map(|f| f.name.clone()).collect();
+    assert_eq!(names, vec!["table_meta.json", "test_backup_message.table"]);
+
+    // changes after the backup are not in the snapshot
+    assert_pattern!(run_sql("insert test_backup_message values(11, 1.5, \"new\")", &manager), None);
+    assert_pattern!(run_sql("delete from test_backup_message where id = 233", &manager), None);
+    assert_pattern!(run_sql("update test_backup_message set score = 2.5 where id = 777", &manager), None);
+    manager.borrow_mut().save_to_file();
+    assert_eq!(run_sql("backup to 'test_file/backup/snapshot'", &manager),
+        Some(ExecErrorType::BackupFailed));
+
+    let snapshot = Rc::new(RefCell::new(
+        TableManager::from_json_file(&gen_config("test_file/backup/snapshot"))));
+    assert_eq!(scan_id(&table_name, &snapshot), vec![(1, 123.0), (233, 666.666), (777, 12345.777)]);
+    assert_eq!(scan_id(&table_name, &manager), vec![(1, 123.0), (11, 1.5), (777, 2.5)]);
+    assert!(verify_backup(&"test_file/backup/snapshot".to_string()).is_ok());
+
+    // a damaged or partial backup is detected
+    let mut file = OpenOptions::new().append(true)
+        .open("test_file/backup/snapshot/test_backup_message.table").unwrap();
+    file.write_all(b"x").unwrap();
+    assert!(verify_backup(&"test_file/backup/snapshot".to_string()).is_err());
+    remove_file(format!("test_file/backup/snapshot/{}", MANIFEST_NAME)).unwrap();
+    assert!(verify_backup(&"test_file/backup/snapshot".to_string()).is_err());
+}
+
+#[test]
+fn test_restore_round_trip() {
+    remove_dir_all("test_file/restore").ok();
+    let manager = Rc::new(RefCell::new(TableManager::new(&gen_config("test_file/restore/origin"))));
+    assert_pattern!(run_sql("create table restore_plain(id int not null primary, \
+        content char(32))", &manager), None);
+    assert_pattern!(run_sql("create table restore_compressed(id int not null primary, \
+        score float) with compression", &manager), None);
+    for i in 0..300 {
+        let sql = format!("insert restore_plain values({}, \"row {}\")", i, i * 7);
+        assert_pattern!(run_sql(&sql, &manager), None);
+        let sql = format!("insert restore_compressed values({}, {})", i, i % 5);
+        assert_pattern!(run_sql(&sql, &manager), None);
+    }
+    let plain_rows = scan_rows("restore_plain", &manager);
+    let compressed_rows = scan_rows("restore_compressed", &manager);
+    assert_eq!(plain_rows.len(), 300);
+    assert_pattern!(run_sql("backup to 'test_file/restore/snapshot'", &manager), None);
+
+    // refuse to replace the existing tables
+    let err = {
+        let mut plan = gen_plan_helper!("restore from 'test_file/restore/snapshot'", &manager);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        plan.get_error().unwrap()
+    };
+    assert_eq!(err.error_type, ExecErrorType::RestoreFailed);
+    assert_eq!(err.error_msg.lines().collect::<Vec<&str>>(), vec![
+        "table restore_compressed already exist, use `with force` to replace it",
+        "table restore_plain already exist, use `with force` to replace it"]);
+
+    assert_pattern!(run_sql("drop table restore_plain", &manager), None);
+    assert_pattern!(run_sql("drop table restore_compressed", &manager), None);
+    assert_pattern!(run_sql("restore from 'test_file/restore/snapshot'", &manager), None);
+    assert_eq!(scan_rows("restore_plain", &manager), plain_rows);
+    assert_eq!(scan_rows("restore_compressed", &manager), compressed_rows);
+    for name in vec!["restore_plain.table", "restore_compressed.table"] {
+        assert_eq!(read_file(&format!("test_file/restore/origin/{}", name)),
+            read_file(&format!("test_file/restore/snapshot/{}", name)));
+    }
+
+    // replace the modified table
+    assert_pattern!(run_sql("delete from restore_plain where id < 100", &manager), None);
+    assert_pattern!(run_sql("insert restore_plain values(1000, \"new\")", &manager), None);
+    assert_pattern!(run_sql("restore from 'test_file/restore/snapshot' with force", &manager), None);
+    assert_eq!(scan_rows("restore_plain", &manager), plain_rows);
+    let reopened = Rc::new(RefCell::new(
+        TableManager::from_json_file(&gen_config("test_file/restore/origin"))));
+    assert_eq!(scan_rows("restore_plain", &reopened), plain_rows);
+
+    // a file which can't be copied leaves all the tables as they are
+    assert_pattern!(run_sql("delete from restore_compressed where id < 100", &manager), None);
+    create_dir("test_file/restore/origin/restore_plain.table.restore").unwrap();
+    let err = {
+        let mut plan = gen_plan_helper!("restore from 'test_file/restore/snapshot' with force", &manager);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        plan.get_error().unwrap()
+    };
+    assert_eq!(err.error_type, ExecErrorType::RestoreFailed);
+    assert!(err.error_msg.starts_with("can't restore restore_plain.table: can't copy"), "{}", err.error_msg);
+    assert_eq!(scan_rows("restore_compressed", &manager).len(), 200);
+    assert!(metadata("test_file/restore/origin/restore_compressed.table.restore").is_err());
+    remove_dir("test_file/restore/origin/restore_plain.table.restore").unwrap();
+    assert_pattern!(run_sql("restore from 'test_file/restore/snapshot' with force", &manager), None);
+    assert_eq!(scan_rows("restore_compressed", &manager), compressed_rows);
+
+    // every damaged file is reported and nothing is restored
+    let mut file = OpenOptions::new().write(true)
+        .open("test_file/restore/snapshot/restore_plain.table").unwrap();
+    file.seek(SeekFrom::Start(get_page_offset(0, get_page_size()) + 100)).unwrap();
+    file.write_all(b"x").unwrap();
+    let mut file = OpenOptions::new().append(true)
+        .open("test_file/restore/snapshot/table_meta.json").unwrap();
+    file.write_all(b" ").unwrap();
+    assert_pattern!(run_sql("delete from restore_plain where id < 100", &manager), None);
+    let mut plan = gen_plan_helper!("restore from 'test_file/restore/snapshot' with force", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::RestoreFailed);
+    assert_eq!(err.error_msg.lines().collect::<Vec<&str>>().len(), 2);
+    assert!(err.error_msg.starts_with("table_meta.json: expected"));
+    assert!(err.error_msg.contains("restore_plain.table: checksum mismatch"));
+    assert_eq!(scan_rows("restore_plain", &manager).len(), 200);
+}
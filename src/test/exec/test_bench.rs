@@ -0,0 +1,54 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::store::table::{TableManager, TableManagerRef};
+use ::utils::config::Config;
+use ::utils::bench::{DataGenerator, time_plan};
+use super::test_query::gen_test_table;
+
+
+// Run with `cargo test bench_ -- --ignored --nocapture` to compare the numbers before and after a change.
+const BENCH_ROW_NUM : usize = 200000;
+
+fn gen_bench_manager(table_name : &str) -> TableManagerRef {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 64
+        flush_interval_ms = 0"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, table_name)));
+    let mut table = gen_test_table(&table_name.to_string());
+    table.fill_factor = Some(100);
+    manager.borrow_mut().add_table(table.clone());
+    DataGenerator::new(&table, 233).load_into(&manager, BENCH_ROW_NUM);
+    manager
+}
+
+#[test]
+#[ignore]
+fn bench_full_scan() {
+    let manager = gen_bench_manager("bench_full_scan");
+    let mut plan = gen_plan_helper!("select * from bench_full_scan", &manager);
+    let timing = time_plan(&mut plan);
+    assert_eq!(timing.rows, BENCH_ROW_NUM);
+    println!("full scan: {}", timing);
+}
+
+#[test]
+#[ignore]
+fn bench_filtered_scan() {
+    let manager = gen_bench_manager("bench_filtered_scan");
+    let mut plan = gen_plan_helper!(
+        "select id, content from bench_filtered_scan where score < 10 and id % 2 = 0", &manager);
+    let timing = time_plan(&mut plan);
+    assert!(timing.rows > 0 && timing.rows < BENCH_ROW_NUM / 2);
+    println!("filtered scan: {}", timing);
+}
+
+#[test]
+#[ignore]
+fn bench_primary_key_lookup() {
+    // without an index it is a full scan returning one row
+    let manager = gen_bench_manager("bench_primary_key_lookup");
+    let mut plan = gen_plan_helper!("select * from bench_primary_key_lookup where id = 100000", &manager);
+    let timing = time_plan(&mut plan);
+    assert_eq!(timing.rows, 1);
+    println!("primary key lookup: {}", timing);
+}
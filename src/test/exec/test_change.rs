@@ -1,220 +1,468 @@
-use std::ptr::read;
-use std::collections::HashMap;
-use ::exec::change::{Insert, Delete, Update};
-use ::exec::query::{FileScan, Filter};
-use ::exec::error::ExecErrorType;
-use ::store::tuple::TupleValue;
-use ::store::table::{TableManager, Table, Attr, AttrType};
-use ::utils::config::Config;
-use ::utils::pointer::read_string;
-use ::parser::condition::ConditionExpr;
-use super::test_query::{gen_test_manager, gen_test_table};
-
-
-#[test]
-fn test_insert() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::make_ref(&config);
-    let table_name = "test_insert_message".to_string();
-    manager.borrow_mut().add_table(gen_test_table(&table_name));
-    assert_pattern!(manager.borrow().get_table(&table_name), Some(..));
-
-    let file = manager.borrow_mut().file_manager.get_file(&table_name);
-    assert_eq!(file.borrow().loaded_pages.len(), 0);
-
-    let mut plan = gen_plan_helper!(
-        "insert test_insert_message values(233, 2.3333, \"i am doyoubi\")", &manager);
-    plan.open();
-    assert_pattern!(plan.get_next(), None);
-    assert_pattern!(plan.get_error(), None);
-
-    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 0), TupleValue::Int(233));
-    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 1), TupleValue::Float(2.3333));
-    assert_eq!(extract!(
-        manager.borrow_mut().get_tuple_value(&table_name, 0, 2), TupleValue::Char(s), s), "i am doyoubi");
-}
-
-#[test]
-fn test_duplicate_primary_key() {
-    let table_name = "test_change_message".to_string();
-    let manager = gen_test_manager(&table_name);
-
-    let mut scan = FileScan::new(&table_name, &manager);
-    scan.open();
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), None);
-
-    let mut plan = gen_plan_helper!(
-        "insert test_change_message values(233, 2.3333, \"i am doyoubi\")", &manager);
-    plan.open();
-    assert_pattern!(plan.get_next(), None);
-    let err = plan.get_error().unwrap();
-    assert_eq!(err.error_type, ExecErrorType::PrimaryKeyExist);
-
-    let mut scan = FileScan::new(&table_name, &manager);
-    scan.open();
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), None);
-}
-
-#[test]
-fn test_insert_without_creating_new_page() {
-    let table_name = "test_change_message".to_string();
-    let manager = gen_test_manager(&table_name);
-
-    let mut scan = FileScan::new(&table_name, &manager);
-    scan.open();
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), None);
-
-    let mut plan = gen_plan_helper!(
-        "insert test_change_message values(1234, 2.3333, \"i am doyoubi\")", &manager);
-    plan.open();
-    assert_pattern!(plan.get_next(), None);
-    assert_pattern!(plan.get_error(), None);
-
-    let mut scan = FileScan::new(&table_name, &manager);
-    scan.open();
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), Some(..));
-    let tuple_data = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-    assert_pattern!(scan.get_next(), Some(..));
-    assert_pattern!(scan.get_next(), None);
-
-    assert_eq!(unsafe{read::<i32>(tuple_data[0] as *const i32)}, 1234);
-    assert_eq!(unsafe{read::<f32>(tuple_data[1] as *const f32)}, 2.3333);
-    assert_eq!(unsafe{read_string(tuple_data[2], 16)}, "i am doyoubi");
-}
-
-#[test]
-fn test_delete() {
-    {
-        // delete all
-        let table_name = "test_change_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), None);
-        let mut delete = Delete::new(&table_name,
-            FileScan::new(&table_name, &manager), &manager);
-        delete.open();
-        assert_pattern!(delete.get_next(), Some(..));
-        assert_pattern!(delete.get_next(), Some(..));
-        assert_pattern!(delete.get_next(), Some(..));
-        assert_pattern!(delete.get_next(), None);
-        let mut new_scan = FileScan::new(&table_name, &manager);
-        new_scan.open();
-        assert_pattern!(new_scan.get_next(), None);
-    }
-    {
-        // delete with where clause
-        let table_name = "test_change_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), None);
-
-        let table = gen_test_table(&table_name);
-        let mut data_souce = FileScan::new(&table_name, &manager);
-        let cond = Box::new(gen_parse_result!(ConditionExpr::parse,
-            "test_change_message.id = 777"));
-        data_souce = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), data_souce);
-        let mut delete = Delete::new(&table_name, data_souce, &manager);
-        delete.open();
-        let deleted_tuple = extract!(delete.get_next(), Some(tuple_data), tuple_data);
-        assert_eq!(unsafe{ read::<i32>(deleted_tuple[0] as *const i32) }, 777);
-        assert_pattern!(delete.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 1);
-    }
-}
-
-#[test]
-fn test_update() {
-    {
-        // update all
-        let table_name = "test_change_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let table = gen_test_table(&table_name);
-        let mut set_values = HashMap::new();
-        set_values.insert(1, TupleValue::Float(233.666));
-        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
-            FileScan::new(&table_name, &manager), &manager);
-        update.open();
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), None);
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 233.666);
-    }
-    {
-        // update with where clause
-        let table_name = "test_change_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let table = gen_test_table(&table_name);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 12345.777);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
-
-        let mut set_values = HashMap::new();
-        set_values.insert(1, TupleValue::Float(233.666));
-        let mut data_souce = FileScan::new(&table_name, &manager);
-        let cond = Box::new(gen_parse_result!(ConditionExpr::parse,
-            "test_change_message.id = 777"));
-        data_souce = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), data_souce);
-        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
-            data_souce, &manager);
-        update.open();
-        let updated_tuple = extract!(update.get_next(), Some(tuple_data), tuple_data);
-        assert_eq!(unsafe{ read::<i32>(updated_tuple[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<f32>(updated_tuple[1] as *const f32) }, 233.666);
-        assert_pattern!(update.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
-    }
-}
-
+use std::ptr::read;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ::exec::change::{Insert, Delete, Update};
+use ::exec::query::{FileScan, Filter};
+use ::exec::error::ExecErrorType;
+use ::store::tuple::TupleValue;
+use ::store::table::{TableManager, TableManagerRef, Table, Attr, AttrType};
+use ::utils::config::Config;
+use ::utils::pointer::read_string;
+use ::parser::condition::ConditionExpr;
+use ::parser::common::{ValueExpr, Literal};
+use ::parser::InsertStatement;
+use ::test::utils::FullDiskWriter;
+use super::test_query::{gen_test_manager, gen_test_manager_with, gen_test_table};
+
+
+#[test]
+fn test_insert() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let table_name = "test_insert_message".to_string();
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    assert_pattern!(manager.borrow().get_table(&table_name), Some(..));
+
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert_eq!(file.borrow().loaded_pages.len(), 0);
+
+    let mut plan = gen_plan_helper!(
+        "insert test_insert_message values(233, 2.3333, \"i am doyoubi\")", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_pattern!(plan.get_error(), None);
+    assert_eq!(plan.get_affected_num(), 1);
+
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 0), TupleValue::Int(233));
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 1), TupleValue::Float(2.3333));
+    assert_eq!(extract!(
+        manager.borrow_mut().get_tuple_value(&table_name, 0, 2), TupleValue::Char(s), s), "i am doyoubi");
+}
+
+#[test]
+fn test_insert_values_num() {
+    // not checked by sem_check, the storage rejects it before any page is touched
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let table_name = "test_insert_values_num".to_string();
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, &table_name)));
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    let value_lists = vec![
+        vec![ValueExpr::from_literal(Literal::Int(1)), ValueExpr::from_literal(Literal::Float(1.0))],
+        vec![
+            ValueExpr::from_literal(Literal::Int(1)),
+            ValueExpr::from_literal(Literal::Float(1.0)),
+            ValueExpr::from_literal(Literal::Str("one".to_string())),
+            ValueExpr::from_literal(Literal::Int(2)),
+        ],
+    ];
+    for value_list in value_lists.into_iter() {
+        let found = value_list.len();
+        let stmt = InsertStatement{ table : table_name.clone(), value_list : value_list };
+        let mut plan = Insert::new(stmt, &manager);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        let err = extract!(plan.get_error(), Some(err), err);
+        assert_eq!(err.error_type, ExecErrorType::InvalidInsertValuesNum);
+        assert_eq!(err.error_msg, format!(
+            "invalid insert values number of table test_insert_values_num, expected 3, found {}", found));
+        assert_eq!(plan.get_affected_num(), 0);
+    }
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert_eq!(file.borrow().page_sum, 0);
+}
+
+#[test]
+fn test_duplicate_primary_key() {
+    let table_name = "test_change_message".to_string();
+    let manager = gen_test_manager(&table_name);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), None);
+
+    let mut plan = gen_plan_helper!(
+        "insert test_change_message values(233, 2.3333, \"i am doyoubi\")", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::PrimaryKeyExist);
+    assert_eq!(plan.get_affected_num(), 0);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), None);
+}
+
+#[test]
+fn test_insert_without_creating_new_page() {
+    let table_name = "test_change_message".to_string();
+    let manager = gen_test_manager(&table_name);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), None);
+
+    let mut plan = gen_plan_helper!(
+        "insert test_change_message values(1234, 2.3333, \"i am doyoubi\")", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_pattern!(plan.get_error(), None);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), Some(..));
+    let tuple_data = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+    assert_pattern!(scan.get_next(), Some(..));
+    assert_pattern!(scan.get_next(), None);
+
+    assert_eq!(unsafe{read::<i32>(tuple_data[0] as *const i32)}, 1234);
+    assert_eq!(unsafe{read::<f32>(tuple_data[1] as *const f32)}, 2.3333);
+    assert_eq!(unsafe{read_string(tuple_data[2], 16)}, "i am doyoubi");
+}
+
+#[test]
+fn test_delete() {
+    {
+        // delete all
+        let table_name = "test_change_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), None);
+        let mut delete = Delete::new(&table_name,
+            FileScan::new(&table_name, &manager), &manager);
+        delete.open();
+        assert_pattern!(delete.get_next(), Some(..));
+        assert_pattern!(delete.get_next(), Some(..));
+        assert_pattern!(delete.get_next(), Some(..));
+        assert_pattern!(delete.get_next(), None);
+        assert_eq!(delete.get_affected_num(), 3);
+        let mut new_scan = FileScan::new(&table_name, &manager);
+        new_scan.open();
+        assert_pattern!(new_scan.get_next(), None);
+    }
+    {
+        // delete with where clause
+        let table_name = "test_change_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), None);
+
+        let table = gen_test_table(&table_name);
+        let mut data_souce = FileScan::new(&table_name, &manager);
+        let cond = Box::new(gen_checked_result!(ConditionExpr::parse,
+            "test_change_message.id = 777"));
+        data_souce = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), data_souce);
+        let mut delete = Delete::new(&table_name, data_souce, &manager);
+        delete.open();
+        let deleted_tuple = extract!(delete.get_next(), Some(tuple_data), tuple_data);
+        assert_eq!(unsafe{ read::<i32>(deleted_tuple[0] as *const i32) }, 777);
+        assert_pattern!(delete.get_next(), None);
+        assert_eq!(delete.get_affected_num(), 1);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
+        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 1);
+    }
+}
+
+#[test]
+fn test_delete_small_page() {
+    // 10 slots in a page, so the rows are in more pages than the pool holds
+    let table_name = "test_change_small_page_message".to_string();
+    let manager = gen_test_manager_with(&table_name, "page_size = 256");
+    for id in 1000..1040 {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(id)),
+            ValueExpr::from_literal(Literal::Float(1.5)),
+            ValueExpr::from_literal(Literal::Str("row".to_string())),
+        ];
+        check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+    }
+    assert!(manager.borrow_mut().file_manager.get_file(&table_name).borrow().page_sum > 4);
+
+    let mut delete = gen_plan_helper!(
+        "delete from test_change_small_page_message where test_change_small_page_message.id < 1020", &manager);
+    delete.open();
+    while let Some(..) = delete.get_next() {}
+    assert_pattern!(delete.get_error(), None);
+    assert_eq!(delete.get_affected_num(), 23);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    let mut ids = Vec::new();
+    while let Some(tuple_data) = scan.get_next() {
+        ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+    }
+    assert_eq!(ids, (1020..1040).collect::<Vec<i32>>());
+}
+
+#[test]
+fn test_update() {
+    {
+        // update all
+        let table_name = "test_change_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let table = gen_test_table(&table_name);
+        let mut set_values = HashMap::new();
+        set_values.insert(1, TupleValue::Float(233.666));
+        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
+            FileScan::new(&table_name, &manager), &manager);
+        update.open();
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), None);
+        assert_eq!(update.get_affected_num(), 3);
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 233.666);
+        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
+        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 233.666);
+    }
+    {
+        // update with where clause
+        let table_name = "test_change_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let table = gen_test_table(&table_name);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
+        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 12345.777);
+        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
+
+        let mut set_values = HashMap::new();
+        set_values.insert(1, TupleValue::Float(233.666));
+        let mut data_souce = FileScan::new(&table_name, &manager);
+        let cond = Box::new(gen_checked_result!(ConditionExpr::parse,
+            "test_change_message.id = 777"));
+        data_souce = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), data_souce);
+        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
+            data_souce, &manager);
+        update.open();
+        let updated_tuple = extract!(update.get_next(), Some(tuple_data), tuple_data);
+        assert_eq!(unsafe{ read::<i32>(updated_tuple[0] as *const i32) }, 777);
+        assert_eq!(unsafe{ read::<f32>(updated_tuple[1] as *const f32) }, 233.666);
+        assert_pattern!(update.get_next(), None);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
+        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
+        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
+    }
+    {
+        // set null, which is stored as zero
+        let table_name = "test_change_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut update = gen_plan_helper!(
+            "update test_change_message set score = null where id = 777", &manager);
+        update.open();
+        let updated_tuple = extract!(update.get_next(), Some(tuple_data), tuple_data);
+        assert_eq!(unsafe{ read::<f32>(updated_tuple[1] as *const f32) }, 0.0);
+        assert_pattern!(update.get_next(), None);
+    }
+}
+
+
+// 40 more rows in pages of 10 slots, more pages than the pool holds
+fn gen_rollback_test_manager(table_name : &String) -> TableManagerRef {
+    let manager = gen_test_manager_with(table_name, "page_size = 256");
+    for id in 1000..1040 {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(id)),
+            ValueExpr::from_literal(Literal::Float(1.5)),
+            ValueExpr::from_literal(Literal::Str(format!("row {}", id))),
+        ];
+        check_ok!(manager.borrow_mut().insert(table_name, &value_list));
+    }
+    manager
+}
+
+fn read_table_file(table_name : &String, manager : &TableManagerRef) -> Vec<u8> {
+    manager.borrow_mut().save_to_file();
+    let path = manager.borrow().file_manager.get_file_path(table_name);
+    let mut data = Vec::new();
+    check_ok!(check_ok!(File::open(&path)).read_to_end(&mut data));
+    data
+}
+
+#[test]
+fn test_update_rollback() {
+    let table_name = "test_change_update_rollback".to_string();
+    let manager = gen_rollback_test_manager(&table_name);
+    let saved = read_table_file(&table_name, &manager);
+
+    let guard = manager.borrow_mut().start_query();
+    let mut update = gen_plan_helper!(
+        "update test_change_update_rollback set score = 0.5, content = \"changed\"", &manager);
+    update.open();
+    for _ in 0..25 {
+        assert_pattern!(update.get_next(), Some(..));
+    }
+    guard.cancel();
+    assert_pattern!(update.get_next(), None);
+    assert_eq!(update.get_error().unwrap().error_type, ExecErrorType::QueryCancelled);
+    assert_eq!(update.get_affected_num(), 0);
+    manager.borrow_mut().finish_query();
+    assert!(read_table_file(&table_name, &manager) == saved);
+
+    // the undo records of a finished statement are dropped
+    let mut update = gen_plan_helper!(
+        "update test_change_update_rollback set score = 0.5 where test_change_update_rollback.id >= 1000", &manager);
+    update.open();
+    while let Some(..) = update.get_next() {}
+    assert_pattern!(update.get_error(), None);
+    assert_eq!(update.get_affected_num(), 40);
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 3, 1), TupleValue::Float(0.5));
+}
+
+#[test]
+fn test_delete_rollback() {
+    let table_name = "test_change_delete_rollback".to_string();
+    let manager = gen_rollback_test_manager(&table_name);
+    let saved = read_table_file(&table_name, &manager);
+
+    let guard = manager.borrow_mut().start_query();
+    let mut delete = gen_plan_helper!(
+        "delete from test_change_delete_rollback where test_change_delete_rollback.id >= 1000", &manager);
+    delete.open();
+    for _ in 0..30 {
+        assert_pattern!(delete.get_next(), Some(..));
+    }
+    guard.cancel();
+    assert_pattern!(delete.get_next(), None);
+    assert_eq!(delete.get_error().unwrap().error_type, ExecErrorType::QueryCancelled);
+    assert_eq!(delete.get_affected_num(), 0);
+    manager.borrow_mut().finish_query();
+    assert!(read_table_file(&table_name, &manager) == saved);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    let mut row_num = 0;
+    while let Some(..) = scan.get_next() {
+        row_num += 1;
+    }
+    assert_eq!(row_num, 43);
+}
+
+#[test]
+fn test_delete_read_only() {
+    let table_name = "test_change_delete_read_only".to_string();
+    let manager = gen_test_manager_with(&table_name, "page_size = 256");
+    let writer = Arc::new(FullDiskWriter{ limit : AtomicUsize::new(256) });
+    manager.borrow_mut().file_manager.set_writer(writer.clone());
+    assert_eq!(manager.borrow_mut().flush_all().len(), 1);
+
+    let mut delete = gen_plan_helper!(
+        "delete from test_change_delete_read_only where test_change_delete_read_only.id >= 0", &manager);
+    delete.open();
+    assert_pattern!(delete.get_next(), None);
+    assert_eq!(delete.get_error().unwrap().error_type, ExecErrorType::TableReadOnly);
+    assert_eq!(delete.get_affected_num(), 0);
+    let mut insert = gen_plan_helper!(
+        "insert test_change_delete_read_only values(9, 1.5, \"full\")", &manager);
+    insert.open();
+    assert_pattern!(insert.get_next(), None);
+    assert_eq!(insert.get_error().unwrap().error_type, ExecErrorType::TableReadOnly);
+
+    // the reads go on
+    let count_rows = || {
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let mut row_num = 0;
+        while let Some(..) = scan.get_next() {
+            row_num += 1;
+        }
+        assert_pattern!(scan.get_error(), None);
+        row_num
+    };
+    assert_eq!(count_rows(), 3);
+
+    writer.limit.store(usize::max_value(), Ordering::SeqCst);
+    assert!(manager.borrow_mut().flush_all().is_empty());
+    let mut delete = gen_plan_helper!(
+        "delete from test_change_delete_read_only where test_change_delete_read_only.id >= 0", &manager);
+    delete.open();
+    while let Some(..) = delete.get_next() {}
+    assert_pattern!(delete.get_error(), None);
+    assert_eq!(delete.get_affected_num(), 3);
+    assert_eq!(count_rows(), 0);
+}
+
+#[test]
+fn test_generated() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let table_name = "test_generated".to_string();
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, &table_name)));
+    let sql_list = [
+        "create table test_generated(id int not null primary, price float, num int, \
+            total float generated as (price * num), half int generated as (-num / 2))",
+        "insert test_generated values(1, 2.5, 4)",
+        "insert test_generated values(2, 1.5, null)",
+    ];
+    for sql in sql_list.iter() {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        plan.open();
+        while plan.get_next().is_some() {}
+        assert_pattern!(plan.get_error(), None);
+    }
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 3), TupleValue::Float(10.0));
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 4), TupleValue::Int(-2));
+    // null is counted as 0
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 1, 3), TupleValue::Float(0.0));
+
+    let mut plan = gen_plan_helper!(
+        "update test_generated set num = 7 where test_generated.id = 1", &manager);
+    plan.open();
+    while plan.get_next().is_some() {}
+    assert_pattern!(plan.get_error(), None);
+    assert_eq!(plan.get_affected_num(), 1);
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 3), TupleValue::Float(17.5));
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 0, 4), TupleValue::Int(-3));
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 1, 3), TupleValue::Float(0.0));
+}
@@ -0,0 +1,304 @@
+use std::fs::{File, remove_file};
+use std::io::{Read, Write, Cursor};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use ::exec::error::ExecErrorType;
+use ::exec::query::FileScan;
+use ::store::tuple::{TupleValue, gen_tuple_value};
+use ::store::table::{TableManagerRef, CopyInput};
+use ::utils::file::ensure_dir_exist;
+use super::test_query::{gen_test_manager, gen_test_table};
+
+
+fn write_csv(path : &str, content : &str) {
+    ensure_dir_exist(&"test_file/copy".to_string());
+    let mut file = File::create(path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
+fn scan_all(table_name : &String, manager : &TableManagerRef) -> Vec<Vec<TupleValue>> {
+    let tuple_desc = manager.borrow().get_table(table_name).unwrap().borrow().gen_tuple_desc();
+    let mut scan = FileScan::new(table_name, manager);
+    let mut result = Vec::new();
+    scan.open();
+    while let Some(tuple_data) = scan.get_next() {
+        result.push(gen_tuple_value(&tuple_desc.attr_desc, tuple_data));
+    }
+    result
+}
+
+fn find_row(rows : &Vec<Vec<TupleValue>>, id : i32) -> Option<Vec<TupleValue>> {
+    rows.iter().filter(|r| match r[0] {
+        TupleValue::Int(n) => n == id,
+        _ => false,
+    }).next().cloned()
+}
+
+#[test]
+fn test_copy_from_with_header() {
+    let table_name = "test_copy_header_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    write_csv("test_file/copy/test_copy_header.csv",
+        "content;id;score\n\
+         first;11;1.5\n\
+         bad id;x;12.0\n\
+         \"multi\nline\";12;\n\
+         \"quoted;\"\"content\"\"\";13;3.5\n\
+         \n\
+         too many;14;4.5;extra\n\
+         it is a very long content;15;5.5\n\
+         duplicate;233;6.5\n\
+         ;16;7.5\n");
+
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_header_message from 'test_file/copy/test_copy_header.csv' \
+         with header delimiter ';' empty_as_null max_errors 10", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::CopyLinesRejected);
+    let lines : Vec<&str> = err.error_msg.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "3 row(s) inserted, 5 line(s) rejected");
+    assert!(lines[1].starts_with("line 3, column 2: invalid int value"));
+    assert_eq!(lines[2], "line 8: expected 3 fields, found 4");
+    assert!(lines[3].starts_with("line 9, column 1: invalid char len"));
+    assert_eq!(lines[4], "line 10: primary key 233 already exist");
+    assert!(lines[5].starts_with("line 11, column 1: attribute content is not nullable"));
+
+    let rows = scan_all(&table_name, &manager);
+    assert_eq!(rows.len(), 3 + 3);
+    let row = find_row(&rows, 11).unwrap();
+    assert_pattern!(&row[1], &TupleValue::Float(1.5));
+    assert_eq!(extract!(row[2], TupleValue::Char(ref s), s.clone()), "first");
+    let row = find_row(&rows, 12).unwrap();
+    assert_pattern!(&row[1], &TupleValue::Float(0.0));  // null is stored as zero
+    assert_eq!(extract!(row[2], TupleValue::Char(ref s), s.clone()), "multi\nline");
+    let row = find_row(&rows, 13).unwrap();
+    assert_eq!(extract!(row[2], TupleValue::Char(ref s), s.clone()), "quoted;\"content\"");
+    assert_pattern!(find_row(&rows, 15), None);
+    assert_pattern!(find_row(&rows, 16), None);
+}
+
+#[test]
+fn test_copy_from_too_many_errors() {
+    let table_name = "test_copy_abort_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    write_csv("test_file/copy/test_copy_abort.csv",
+        "11,1.5,first\n\
+         12,,second\n\
+         13,3.5,third\n");
+
+    // empty field is not null without empty_as_null
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_abort_message from 'test_file/copy/test_copy_abort.csv'", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::CopyTooManyErrors);
+    assert!(err.error_msg.contains("line 2, column 2: invalid float value"));
+
+    let rows = scan_all(&table_name, &manager);
+    assert_eq!(rows.len(), 3 + 1);
+    assert_pattern!(find_row(&rows, 11), Some(..));
+    assert_pattern!(find_row(&rows, 13), None);
+}
+
+#[test]
+fn test_copy_from_invalid_header() {
+    let table_name = "test_copy_invalid_header_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    write_csv("test_file/copy/test_copy_invalid_header.csv", "id,name\n11,first\n");
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_invalid_header_message \
+         from 'test_file/copy/test_copy_invalid_header.csv' with header", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::CopyInvalidHeader);
+    assert_eq!(err.error_msg, "line 1, column 2: unknown attribute `name`");
+
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_invalid_header_message from 'test_file/copy/not_exist.csv'", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_type, ExecErrorType::CopyOpenFileFailed);
+    assert_eq!(scan_all(&table_name, &manager).len(), 3);
+}
+
+#[test]
+fn test_copy_from_crlf() {
+    let table_name = "test_copy_crlf_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    // only the '\r' ending a record is dropped, the ones in a quoted field are kept
+    write_csv("test_file/copy/test_copy_crlf.csv",
+        "11,1.5,\"multi\r\nline\"\r\n\
+         12,2.5,plain\r\n\
+         13,3.5,\"cr\r\"\r\n\
+         14,4.5,\"quoted\"x\r\n\
+         15,5.5,after\r\n");
+
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_crlf_message from 'test_file/copy/test_copy_crlf.csv' with max_errors 10", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::CopyLinesRejected);
+    assert_eq!(err.error_msg.lines().collect::<Vec<&str>>(), vec![
+        "3 row(s) inserted, 1 line(s) rejected",
+        "line 5: unexpected 'x' after the closing quote of column 3"]);
+
+    let rows = scan_all(&table_name, &manager);
+    assert_eq!(rows.len(), 3 + 3);
+    let content = |id| extract!(find_row(&rows, id).unwrap()[2], TupleValue::Char(ref s), s.clone());
+    assert_eq!(content(11), "multi\r\nline");
+    assert_eq!(content(12), "plain");
+    assert_eq!(content(13), "cr\r");
+    assert_pattern!(find_row(&rows, 14), None);
+    assert_pattern!(find_row(&rows, 15), None);
+}
+
+#[test]
+fn test_copy_from_stdin() {
+    let table_name = "test_copy_stdin_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let data = b"id,score,content\n11,1.5,first\nx,2.5,second\n12,3.5,third\n".to_vec();
+    manager.borrow_mut().set_copy_input(Some(CopyInput(Box::new(Cursor::new(data)))));
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_stdin_message from stdin with header max_errors 1", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::CopyLinesRejected);
+    assert!(err.error_msg.starts_with("2 row(s) inserted, 1 line(s) rejected\nline 3, column 1"));
+    assert_eq!(plan.get_affected_num(), 2);
+    assert_eq!(scan_all(&table_name, &manager).len(), 3 + 2);
+
+    // the input is taken by the plan reading it
+    let mut plan = gen_plan_helper!("copy test_copy_stdin_message from stdin", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_type, ExecErrorType::CopyStdinFailed);
+}
+
+fn sort_by_id(mut rows : Vec<Vec<TupleValue>>) -> Vec<String> {
+    rows.sort_by_key(|r| extract!(r[0], TupleValue::Int(n), n));
+    rows.iter().map(|r| format!("{:?}", r)).collect()
+}
+
+#[test]
+fn test_copy_to_round_trip() {
+    let table_name = "test_copy_to_message".to_string();
+    let import_table_name = "test_copy_to_import_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let out = "test_file/copy/test_copy_to.csv";
+    ensure_dir_exist(&"test_file/copy".to_string());
+    remove_file(out).ok();
+    for sql in vec![
+            "insert test_copy_to_message values(11, 1.25, \"a,b;c\")",
+            "insert test_copy_to_message values(12, 0.5, \"say \\\"hi\\\"\")",
+            "insert test_copy_to_message values(13, 3, \"line\\nbreak\\r\")",
+            "insert test_copy_to_message values(14, 100000, \"\")",
+            "insert test_copy_to_message values(15, 0.1, \" spaced \")"] {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        plan.open();
+        plan.get_next();
+        assert_pattern!(plan.get_error(), None);
+    }
+
+    let mut plan = gen_plan_helper!(
+        "copy (select * from test_copy_to_message) to 'test_file/copy/test_copy_to.csv' \
+         with header delimiter ';'", &manager);
+    plan.open();
+    let mut row_num = 0;
+    while let Some(..) = plan.get_next() {
+        row_num += 1;
+    }
+    assert_pattern!(plan.get_error(), None);
+    assert_eq!(row_num, 3 + 5);
+
+    manager.borrow_mut().add_table(gen_test_table(&import_table_name));
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_to_import_message from 'test_file/copy/test_copy_to.csv' \
+         with header delimiter ';'", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_pattern!(plan.get_error(), None);
+    assert_eq!(sort_by_id(scan_all(&import_table_name, &manager)),
+        sort_by_id(scan_all(&table_name, &manager)));
+
+    // refuse to overwrite without the option
+    let mut plan = gen_plan_helper!(
+        "copy (select id from test_copy_to_message where id > 10 and id < 13) \
+         to 'test_file/copy/test_copy_to.csv'", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_type, ExecErrorType::CopyFileExist);
+
+    let mut plan = gen_plan_helper!(
+        "copy (select id from test_copy_to_message where id > 10 and id < 13) \
+         to 'test_file/copy/test_copy_to.csv' with header overwrite", &manager);
+    plan.open();
+    while let Some(..) = plan.get_next() {}
+    assert_pattern!(plan.get_error(), None);
+    let mut content = String::new();
+    File::open(out).unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content, "id\n11\n12\n");
+
+    // the file written before the query is killed is removed
+    let killed = Arc::new(AtomicBool::new(false));
+    manager.borrow_mut().set_kill_flag(Some(killed.clone()));
+    manager.borrow_mut().start_query();
+    let mut plan = gen_plan_helper!(
+        "copy (select * from test_copy_to_message) to 'test_file/copy/test_copy_to.csv' \
+         with overwrite", &manager);
+    plan.open();
+    assert!(plan.get_next().is_some());
+    killed.store(true, Ordering::SeqCst);
+    while let Some(..) = plan.get_next() {}
+    assert_eq!(plan.get_error().unwrap().error_msg, "query killed");
+    assert!(!Path::new(out).exists());
+    manager.borrow_mut().set_kill_flag(None);
+}
+
+#[test]
+fn test_copy_null_as() {
+    let table_name = "test_copy_null_as_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    write_csv("test_file/copy/test_copy_null_as.csv",
+        "11,NULL,first\n\
+         12,2.5,NULL\n\
+         13,3.5,\"NULL\"\n\
+         14,,\n");
+
+    // only the unquoted NULL is null, and the empty fields are not
+    let mut plan = gen_plan_helper!(
+        "copy test_copy_null_as_message from 'test_file/copy/test_copy_null_as.csv' \
+         with null_as 'NULL' max_errors 10", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    let lines : Vec<&str> = err.error_msg.lines().collect();
+    assert_eq!(lines[0], "2 row(s) inserted, 2 line(s) rejected");
+    assert!(lines[1].starts_with("line 2, column 3: attribute content is not nullable"));
+    assert!(lines[2].starts_with("line 4, column 2: invalid float value"));
+    let rows = scan_all(&table_name, &manager);
+    assert_pattern!(&find_row(&rows, 11).unwrap()[1], &TupleValue::Float(0.0));  // null is stored as zero
+    assert_eq!(extract!(find_row(&rows, 13).unwrap()[2], TupleValue::Char(ref s), s.clone()), "NULL");
+
+    // the string equal to null_as is quoted
+    let out = "test_file/copy/test_copy_null_as_out.csv";
+    remove_file(out).ok();
+    let mut plan = gen_plan_helper!(
+        "copy (select id, content from test_copy_null_as_message where id > 10 and id < 20) \
+         to 'test_file/copy/test_copy_null_as_out.csv' with null_as 'NULL'", &manager);
+    plan.open();
+    while let Some(..) = plan.get_next() {}
+    assert_pattern!(plan.get_error(), None);
+    let mut content = String::new();
+    File::open(out).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines : Vec<&str> = content.lines().collect();
+    lines.sort();
+    assert_eq!(lines, vec!["11,first", "13,\"NULL\""]);
+}
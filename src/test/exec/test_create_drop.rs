@@ -1,47 +1,96 @@
-use ::parser::common::Statement;
-use ::store::table::{TableManager, Table, Attr, AttrType};
-use ::utils::config::Config;
-use ::exec::gen_plan::gen_plan;
-
-
-#[test]
-fn test_create_table() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::make_ref(&config);
-    assert_pattern!(manager.borrow().get_table("msg"), None);
-    let mut plan = gen_plan_helper!(
-        "create table msg(id int not null primary, content char(233))", &manager);
-    plan.open();
-    assert_pattern!(plan.get_next(), None);
-    let table = extract!(manager.borrow().get_table("msg"), Some(tab), tab);
-    let tab = table.borrow();
-    assert_eq!(tab.name, "msg");
-    assert_eq!(tab.attr_list.len(), 2);
-}
-
-#[test]
-fn test_drop_table() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::make_ref(&config);
-    let table = Table{
-        name : "msg".to_string(),
-        attr_list : vec![Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            }],
-    };
-    manager.borrow_mut().add_table(table);
-    let mut plan = gen_plan_helper!("drop table msg", &manager);
-    assert_pattern!(manager.borrow().get_table("msg"), Some(..));
-    plan.open();
-    assert_pattern!(plan.get_next(), None);
-    assert_pattern!(manager.borrow().get_table("msg"), None);
-}
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::metadata;
+use ::parser::common::Statement;
+use ::store::table::{TableManager, Table, Attr, AttrType};
+use ::exec::query::FileScan;
+use ::utils::config::Config;
+use ::exec::gen_plan::gen_plan;
+
+
+#[test]
+fn test_create_table() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    assert_pattern!(manager.borrow().get_table("msg"), None);
+    let mut plan = gen_plan_helper!(
+        "create table msg(id int not null primary, content char(233))", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let table = extract!(manager.borrow().get_table("msg"), Some(tab), tab);
+    let tab = table.borrow();
+    assert_eq!(tab.name, "msg");
+    assert_eq!(tab.attr_list.len(), 2);
+    assert_pattern!(tab.fill_factor, None);
+
+    let mut plan = gen_plan_helper!(
+        "create table sparse_msg(id int not null primary) with fill_factor 70", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let table = extract!(manager.borrow().get_table("sparse_msg"), Some(tab), tab);
+    assert_pattern!(table.borrow().fill_factor, Some(70));
+}
+
+#[test]
+fn test_drop_table() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let table = Table{
+        name : "msg".to_string(),
+        attr_list : vec![Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            }],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    };
+    manager.borrow_mut().add_table(table);
+    let mut plan = gen_plan_helper!("drop table msg", &manager);
+    assert_pattern!(manager.borrow().get_table("msg"), Some(..));
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    assert_pattern!(manager.borrow().get_table("msg"), None);
+}
+
+#[test]
+fn test_recreate_dropped_table() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_recreate_dropped_table")));
+    let run = |sql : &str| {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        plan.open();
+        while let Some(..) = plan.get_next() {}
+        assert_pattern!(plan.get_error(), None);
+    };
+    run("create table recreated(id int not null primary, content char(16))");
+    run("insert recreated values(1, \"one\")");
+    run("insert recreated values(2, \"two\")");
+    let name = "recreated".to_string();
+    let path = manager.borrow().file_manager.get_file_path(&name);
+    assert!(manager.borrow().file_manager.page_pool.get_load() > 0);
+
+    run("drop table recreated");
+    assert!(metadata(&path).is_err());
+    assert_eq!(manager.borrow().file_manager.page_pool.get_load(), 0);
+
+    run("create table recreated(id int not null primary, content char(16))");
+    let file = manager.borrow_mut().file_manager.get_file(&name);
+    assert_eq!(file.borrow().page_sum, 0);
+    let mut scan = FileScan::new(&name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), None);
+    assert_pattern!(scan.get_error(), None);
+    run("insert recreated values(1, \"again\")");
+}
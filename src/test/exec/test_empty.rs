@@ -0,0 +1,152 @@
+use std::fs::{File, remove_dir_all, remove_file};
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::exec::error::ExecErrorType;
+use ::store::table::{TableManager, TableManagerRef};
+use ::utils::config::Config;
+use ::utils::file::ensure_dir_exist;
+use super::test_query::gen_test_table;
+
+
+// a table created a moment ago, without any page
+fn gen_empty_manager(table_name : &str) -> TableManagerRef {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, table_name)));
+    manager.borrow_mut().add_table(gen_test_table(&table_name.to_string()));
+    manager
+}
+
+// the number of get_next returning a tuple, and the error
+fn run_sql(sql : &str, manager : &TableManagerRef) -> (usize, Option<ExecErrorType>) {
+    let mut plan = gen_plan_helper!(sql, manager);
+    plan.open();
+    let mut row_num = 0;
+    while let Some(..) = plan.get_next() {
+        row_num += 1;
+    }
+    (row_num, plan.get_error().map(|err| err.error_type))
+}
+
+fn get_page_sum(table_name : &str, manager : &TableManagerRef) -> usize {
+    manager.borrow_mut().file_manager.get_file(&table_name.to_string()).borrow().page_sum
+}
+
+fn read_file(path : &str) -> String {
+    let mut content = String::new();
+    File::open(path).unwrap().read_to_string(&mut content).unwrap();
+    content
+}
+
+#[test]
+fn test_empty_query() {
+    let manager = gen_empty_manager("empty_query");
+    for sql in vec![
+            "select * from empty_query",
+            "select content from empty_query",
+            "select id, score from empty_query where score > 1.0 and content = \"a\"",
+            "select * from empty_query where score is not null"].iter() {
+        assert_eq!(run_sql(sql, &manager), (0, None));
+    }
+    // nothing is loaded for them
+    assert_eq!(get_page_sum("empty_query", &manager), 0);
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+
+    let table_name = "empty_query".to_string();
+    let mut manager = manager.borrow_mut();
+    let file_manager = &mut manager.file_manager;
+    assert_pattern!(file_manager.get_next_position(&table_name, 0), Ok(None));
+    assert_pattern!(file_manager.get_next_tuple_data(&table_name, 0), Ok(None));
+    assert_eq!(file_manager.scan_slots(&table_name).count(), 0);
+}
+
+#[test]
+fn test_empty_change() {
+    let manager = gen_empty_manager("empty_change");
+    for sql in vec![
+            "update empty_change set score = 1.5",
+            "update empty_change set content = \"x\" where id = 1",
+            "delete from empty_change where id > 0",
+            "delete from empty_change"].iter() {
+        assert_eq!(run_sql(sql, &manager), (0, None));
+    }
+    assert_eq!(get_page_sum("empty_change", &manager), 0);
+
+    // the first page is created by the insert and found right away
+    assert_eq!(run_sql("insert empty_change values(1, 1.5, \"one\")", &manager), (0, None));
+    assert_eq!(get_page_sum("empty_change", &manager), 1);
+    assert_eq!(run_sql("select * from empty_change", &manager), (1, None));
+    assert_eq!(run_sql("insert empty_change values(1, 2.5, \"two\")", &manager),
+        (0, Some(ExecErrorType::PrimaryKeyExist)));
+    assert_eq!(run_sql("delete from empty_change", &manager), (1, None));
+    // empty again with a page
+    assert_eq!(run_sql("select * from empty_change", &manager), (0, None));
+    assert_eq!(run_sql("update empty_change set score = 1.5", &manager), (0, None));
+}
+
+#[test]
+fn test_empty_analyze() {
+    let manager = gen_empty_manager("empty_analyze");
+    assert_eq!(run_sql("analyze empty_analyze", &manager), (0, None));
+    let table = manager.borrow().get_table("empty_analyze").unwrap();
+    let stats = table.borrow().stats.clone().unwrap();
+    assert_eq!(stats.row_num, 0);
+    assert_eq!(stats.columns.len(), 3);
+    for column in stats.columns.iter() {
+        assert_eq!(column.distinct_num, 0);
+        assert_eq!(column.null_fraction, 0.0);
+        assert!(column.min.is_none());
+        assert!(column.max.is_none());
+        assert!(column.histogram.is_empty());
+    }
+    // the estimates of the plans come from these stats
+    assert_eq!(run_sql("select * from empty_analyze where id < 10 or score = 1.0", &manager), (0, None));
+}
+
+#[test]
+fn test_empty_copy() {
+    let manager = gen_empty_manager("empty_copy");
+    ensure_dir_exist(&"test_file/copy".to_string());
+    remove_file("test_file/copy/test_empty_header.csv").ok();
+    remove_file("test_file/copy/test_empty.csv").ok();
+    assert_eq!(run_sql("copy (select * from empty_copy) to 'test_file/copy/test_empty_header.csv' \
+        with header", &manager), (0, None));
+    assert_eq!(read_file("test_file/copy/test_empty_header.csv"), "id,score,content\n");
+    assert_eq!(run_sql("copy (select id from empty_copy where id > 1) to 'test_file/copy/test_empty.csv'",
+        &manager), (0, None));
+    assert_eq!(read_file("test_file/copy/test_empty.csv"), "");
+
+    // a file without any line, with or without the header expected
+    assert_eq!(run_sql("copy empty_copy from 'test_file/copy/test_empty.csv'", &manager), (0, None));
+    assert_eq!(run_sql("copy empty_copy from 'test_file/copy/test_empty.csv' with header", &manager),
+        (0, None));
+    assert_eq!(run_sql("copy empty_copy from 'test_file/copy/test_empty_header.csv' with header", &manager),
+        (0, None));
+    assert_eq!(get_page_sum("empty_copy", &manager), 0);
+    let mut file = File::create("test_file/copy/test_empty.csv").unwrap();
+    file.write_all(b"\n\n").unwrap();
+    assert_eq!(run_sql("copy empty_copy from 'test_file/copy/test_empty.csv'", &manager), (0, None));
+    assert_eq!(get_page_sum("empty_copy", &manager), 0);
+}
+
+#[test]
+fn test_empty_ddl() {
+    let manager = gen_empty_manager("empty_ddl");
+    assert_eq!(run_sql("alter table empty_ddl add comment char(8) null", &manager), (0, None));
+    assert_eq!(run_sql("alter table empty_ddl modify score int null", &manager), (0, None));
+    assert_eq!(run_sql("alter table empty_ddl drop content", &manager), (0, None));
+    assert_eq!(get_page_sum("empty_ddl", &manager), 0);
+    assert_eq!(run_sql("select * from empty_ddl", &manager), (0, None));
+    assert_eq!(run_sql("insert empty_ddl values(1, 2, \"c\")", &manager), (0, None));
+    assert_eq!(run_sql("select comment from empty_ddl where score = 2", &manager), (1, None));
+    assert_eq!(run_sql("delete from empty_ddl", &manager), (1, None));
+
+    remove_dir_all("test_file/empty/backup").ok();
+    assert_eq!(run_sql("backup to 'test_file/empty/backup'", &manager), (0, None));
+    assert_eq!(run_sql("drop table empty_ddl", &manager), (0, None));
+    assert_eq!(run_sql("restore from 'test_file/empty/backup'", &manager), (0, None));
+    assert_eq!(run_sql("select * from empty_ddl", &manager), (0, None));
+    assert_eq!(run_sql("drop table empty_ddl", &manager), (0, None));
+    assert!(manager.borrow().get_table("empty_ddl").is_none());
+}
@@ -1,129 +1,323 @@
-use std::ptr::read;
-use ::exec::query::FileScan;
-use ::exec::gen_plan::{
-    gen_update_plan,
-};
-use ::utils::pointer::read_string;
-use super::test_query::gen_test_manager;
-
-
-#[test]
-fn test_gen_update_plan() {
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut update = gen_plan_helper!(
-            "update test_gen_plan_message set score = 86.86, content = \"updated\"", &manager);
-        update.open();
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read_string(t1[2], 16) }, "updated");
-        assert_eq!(unsafe{ read_string(t2[2], 16) }, "updated");
-        assert_eq!(unsafe{ read_string(t3[2], 16) }, "updated");
-    }
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut update = gen_plan_helper!(
-            "update test_gen_plan_message set score = 86.86 where id = 777",
-            &manager);
-        update.open();
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        assert_pattern!(scan.get_next(), Some(..));
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), Some(..));
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
-    }
-}
-
-#[test]
-fn test_gen_delete_plan() {
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut update = gen_plan_helper!(
-            "delete from test_gen_plan_message", &manager);
-        update.open();
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        assert_pattern!(scan.get_next(), None);
-    }
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut update = gen_plan_helper!(
-            "delete from test_gen_plan_message where id = 233", &manager);
-        update.open();
-        assert_pattern!(update.get_next(), Some(..));
-        assert_pattern!(update.get_next(), None);
-
-        let mut scan = FileScan::new(&table_name, &manager);
-        scan.open();
-        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
-    }
-}
-
-#[test]
-fn test_query() {
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut query = gen_plan_helper!(
-            "select * from test_gen_plan_message", &manager);
-        query.open();
-        let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
-    }
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut query = gen_plan_helper!(
-            "select * from test_gen_plan_message where id = 777", &manager);
-        query.open();
-        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-    }
-    {
-        let table_name = "test_gen_plan_message".to_string();
-        let manager = gen_test_manager(&table_name);
-        let mut query = gen_plan_helper!(
-            "select score, content from test_gen_plan_message where id = 777", &manager);
-        query.open();
-        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t2[0] as *const f32) }, 12345.777);
-        assert_eq!(unsafe{ read_string(t2[1], 16) }, "dyb");
-    }
-}
+use std::ptr::read;
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::exec::query::FileScan;
+use ::exec::gen_plan::{
+    gen_update_plan,
+    gen_select_proj_info,
+    gen_column_names,
+    estimate_row_num,
+    check_plan_pages,
+};
+use ::parser::select::SelectStatement;
+use ::store::table::{TableManager, TableManagerRef};
+use ::exec::error::ExecErrorType;
+use ::utils::pointer::read_string;
+use ::utils::config::Config;
+use ::utils::bench::{DataGenerator, time_plan};
+use super::test_query::{gen_test_manager, gen_test_table};
+
+
+#[test]
+fn test_gen_update_plan() {
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut update = gen_plan_helper!(
+            "update test_gen_plan_message set score = 86.86, content = \"updated\"", &manager);
+        update.open();
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), None);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 86.86);
+        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
+        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 86.86);
+        assert_eq!(unsafe{ read_string(t1[2], 16) }, "updated");
+        assert_eq!(unsafe{ read_string(t2[2], 16) }, "updated");
+        assert_eq!(unsafe{ read_string(t3[2], 16) }, "updated");
+    }
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut update = gen_plan_helper!(
+            "update test_gen_plan_message set score = 86.86 where id = 777",
+            &manager);
+        update.open();
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), None);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        assert_pattern!(scan.get_next(), Some(..));
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), Some(..));
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
+    }
+}
+
+#[test]
+fn test_gen_delete_plan() {
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut update = gen_plan_helper!(
+            "delete from test_gen_plan_message", &manager);
+        update.open();
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), None);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        assert_pattern!(scan.get_next(), None);
+    }
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut update = gen_plan_helper!(
+            "delete from test_gen_plan_message where id = 233", &manager);
+        update.open();
+        assert_pattern!(update.get_next(), Some(..));
+        assert_pattern!(update.get_next(), None);
+
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(scan.get_next(), None);
+        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+    }
+}
+
+#[test]
+fn test_query() {
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut query = gen_plan_helper!(
+            "select * from test_gen_plan_message", &manager);
+        query.open();
+        let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(query.get_next(), None);
+        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
+        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+    }
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777", &manager);
+        query.open();
+        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(query.get_next(), None);
+        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+    }
+    {
+        let table_name = "test_gen_plan_message".to_string();
+        let manager = gen_test_manager(&table_name);
+        let mut query = gen_plan_helper!(
+            "select score, content from test_gen_plan_message where id = 777", &manager);
+        query.open();
+        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(query.get_next(), None);
+        assert_eq!(unsafe{ read::<f32>(t2[0] as *const f32) }, 12345.777);
+        assert_eq!(unsafe{ read_string(t2[1], 16) }, "dyb");
+    }
+}
+
+#[test]
+fn test_star_expansion() {
+    let table_name = "test_gen_plan_message".to_string();
+    let table = Rc::new(RefCell::new(gen_test_table(&table_name)));
+    let all_attr = vec![
+        (table_name.clone(), "id".to_string()),
+        (table_name.clone(), "score".to_string()),
+        (table_name.clone(), "content".to_string()),
+    ];
+    for sql in vec!["select * from test_gen_plan_message",
+                    "select test_gen_plan_message.* from test_gen_plan_message"] {
+        let select = gen_parse_result!(SelectStatement::parse, sql);
+        let (attr_index, proj_attr_list) = gen_select_proj_info(&select, &table);
+        assert_eq!(attr_index, vec![0, 1, 2]);
+        assert_eq!(proj_attr_list, all_attr);
+        assert_eq!(gen_column_names(&proj_attr_list), vec!["id", "score", "content"]);
+    }
+    let mut query = gen_plan_helper!(
+        "select test_gen_plan_message.* from test_gen_plan_message where id = 777", &gen_test_manager(&table_name));
+    query.open();
+    let t = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    assert_pattern!(query.get_next(), None);
+    assert_eq!(t.len(), 3);
+    assert_eq!(unsafe{ read_string(t[2], 16) }, "dyb");
+
+    // the columns of a join
+    let proj_attr_list = vec![
+        ("author".to_string(), "id".to_string()),
+        ("author".to_string(), "name".to_string()),
+        ("book".to_string(), "id".to_string()),
+    ];
+    assert_eq!(gen_column_names(&proj_attr_list), vec!["author.id", "author.name", "book.id"]);
+    assert!(gen_column_names(&Vec::new()).is_empty());
+}
+
+// the estimate of the where condition by the stats, and the number of the rows actually selected
+fn estimate_and_count(sql : &str, manager : &TableManagerRef) -> (Option<usize>, usize) {
+    let select = gen_parse_result!(SelectStatement::parse, sql);
+    let table = manager.borrow().get_table("test_estimate").unwrap();
+    let mut cond = select.where_condition.clone().unwrap();
+    assert_pattern!(cond.check_literals(), Ok(()));
+    let estimate = estimate_row_num(&cond, &table.borrow());
+    let mut plan = gen_plan_helper!(sql, manager);
+    (estimate, time_plan(&mut plan).rows)
+}
+
+#[test]
+fn test_estimate_row_num() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 8
+        flush_interval_ms = 0"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_estimate")));
+    let table = gen_test_table(&"test_estimate".to_string());
+    manager.borrow_mut().add_table(table.clone());
+    // score in [0, 1000) is skewed towards 0, about an eighth of the rows are below 1 and a third below 10
+    let row_num = 2000;
+    DataGenerator::new(&table, 233).load_into(&manager, row_num);
+    assert_eq!(estimate_and_count("select * from test_estimate where score < 10", &manager).0, None);
+
+    let mut analyze = gen_plan_helper!("analyze test_estimate", &manager);
+    analyze.open();
+    assert_pattern!(analyze.get_next(), None);
+    analyze.close();
+    {
+        let table = manager.borrow().get_table("test_estimate").unwrap();
+        let table = table.borrow();
+        let stats = table.stats.as_ref().unwrap();
+        assert_eq!(stats.row_num, row_num);
+        let id = &stats.columns[0];
+        assert_eq!(id.distinct_num, row_num);
+        assert_eq!(id.null_fraction, 0.0);
+        assert_eq!(id.min, Some("1".to_string()));
+        assert_eq!(id.max, Some(row_num.to_string()));
+        assert_eq!(id.histogram.len(), 33);
+        assert!(stats.columns[2].histogram.is_empty());
+        assert!(manager.borrow().to_json().contains("\"histogram\""));
+    }
+
+    // within 5% of all the rows, where a guess of a third is far from the skewed counts
+    for sql in vec![
+            "select * from test_estimate where score < 1",
+            "select * from test_estimate where score < 10",
+            "select * from test_estimate where score >= 100",
+            "select * from test_estimate where 1.5 > score",
+            "select * from test_estimate where score <= 500.5 and score > 2",
+            "select * from test_estimate where id <= 300",
+            "select * from test_estimate where score < 10 and id > 1000"] {
+        let (estimate, count) = estimate_and_count(sql, &manager);
+        let estimate = estimate.unwrap();
+        let error = (estimate as f64 - count as f64).abs();
+        assert!(error <= row_num as f64 * 0.05, "{}: estimated {}, found {}", sql, estimate, count);
+    }
+    let (_, count) = estimate_and_count("select * from test_estimate where score < 1", &manager);
+    assert!((count as f64 - row_num as f64 / 3.0).abs() > row_num as f64 * 0.05);
+
+    let plan = gen_plan_helper!("select * from test_estimate where id <= 300", &manager);
+    assert!(plan.explain().contains("estimated rows: "));
+}
+
+#[test]
+fn test_check_plan_pages() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        flush_interval_ms = 0"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_check_plan_pages")));
+    for table in ["plan_pages", "plan_pages_pinned1", "plan_pages_pinned2"].iter() {
+        let table = gen_test_table(&table.to_string());
+        manager.borrow_mut().add_table(table.clone());
+        DataGenerator::new(&table, 233).load_into(&manager, 1);
+    }
+    let plans = vec![
+        ("select * from plan_pages where id > 0", 1),
+        ("select id from plan_pages", 1),
+        ("insert plan_pages values(2, 1.0, \"a\")", 1),
+        ("update plan_pages set score = 1.0 where id = 1", 1),
+        ("delete from plan_pages", 1),
+        ("create table plan_pages_new(id int not null primary)", 0),
+    ];
+    for &(sql, pages) in plans.iter() {
+        let plan = gen_plan_helper!(sql, &manager);
+        assert_eq!(plan.get_min_pages(), pages);
+        check_ok!(check_plan_pages(&plan, &manager));
+    }
+
+    // the whole pool is pinned outside the statement
+    let pinned : Vec<_> = ["plan_pages_pinned1", "plan_pages_pinned2"].iter()
+        .map(|t| manager.borrow().file_manager.get_file_id(&t.to_string())).collect();
+    for file_id in pinned.iter() {
+        manager.borrow_mut().file_manager.pin_page(*file_id, 0);
+    }
+    let plan = gen_plan_helper!("select * from plan_pages", &manager);
+    let err = check_plan_pages(&plan, &manager).unwrap_err();
+    assert_eq!(err.error_type, ExecErrorType::PagePoolExhausted);
+    assert_eq!(err.error_msg, "query needs at least 1 buffer pages, pool has 0 of 2 unpinned");
+    let plan = gen_plan_helper!("create table plan_pages_new(id int not null primary)", &manager);
+    check_ok!(check_plan_pages(&plan, &manager));
+    for file_id in pinned.iter() {
+        manager.borrow_mut().file_manager.unpin_page(*file_id, 0);
+    }
+}
+
+#[test]
+fn test_empty_source() {
+    let table_name = "test_empty_source".to_string();
+    let manager = gen_test_manager(&table_name);
+    let accessed_pages = |manager : &TableManagerRef| {
+        let stats = manager.borrow().file_manager.get_storage_stats();
+        stats.page_hits + stats.page_misses
+    };
+    let before = accessed_pages(&manager);
+    for &(sql, describe) in [
+            ("select * from test_empty_source where 1 = 2", "empty: 1 = 2 is always false"),
+            ("select * from test_empty_source where id > 1 and 1 + 1 > 3",
+                "empty: test_empty_source.id > 1 and 1 + 1 > 3 is always false"),
+            // not takes the rest of the condition
+            ("select * from test_empty_source where not 1 = 1 or \"a\" = \"b\"",
+                "empty: not (1 = 1 or 'a' = 'b') is always false"),
+            ].iter() {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        assert_eq!(plan.describe(), describe);
+        assert_eq!(plan.get_min_pages(), 0);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        plan.close();
+        assert_pattern!(plan.get_error(), None);
+    }
+    // no page is loaded or even looked up
+    assert_eq!(accessed_pages(&manager), before);
+
+    // not decided without the rows
+    for sql in vec![
+            "select * from test_empty_source where 1 = 1",
+            "select * from test_empty_source where id > 1 or 1 > 2",
+            "select * from test_empty_source where not 1 > 2"] {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        assert_eq!(plan.get_min_pages(), 1);
+        plan.open();
+        assert_pattern!(plan.get_next(), Some(..));
+        plan.close();
+    }
+    assert!(accessed_pages(&manager) > before);
+}
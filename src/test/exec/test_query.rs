@@ -1,196 +1,424 @@
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::ptr::read;
-use ::utils::pointer::read_string;
-use ::store::table::{TableManagerRef, TableManager, Table, Attr, AttrType};
-use ::store::file::TableFileManager;
-use ::parser::common::{ValueExpr, ValueType};
-use ::parser::condition::ConditionExpr;
-use ::utils::config::Config;
-use ::exec::query::{FileScan, Filter, Projection};
-use ::exec::iter::ExecIterRef;
-use ::exec::gen_plan::gen_proj_info;
-
-
-pub fn gen_test_table(table_name : &String) -> Table {
-    Table{
-        name : table_name.clone(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "score".to_string(),
-                attr_type : AttrType::Float,
-                primary : false,
-                nullable : true,
-            },
-            Attr{
-                name : "content".to_string(),
-                attr_type : AttrType::Char{ len : 16 },
-                primary : false,
-                nullable : false,
-            },
-        ],
-    }
-}
-
-pub fn insert_data(table_name : &String, manager : &TableManagerRef) {
-    let mut value_list = vec![
-        ValueExpr{ value : "233".to_string(), value_type : ValueType::Integer },
-        ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
-        ValueExpr{ value : "qweryu".to_string(), value_type : ValueType::String },
-    ];
-    manager.borrow_mut().insert(&table_name, &value_list);
-    value_list[0].value = "777".to_string();
-    value_list[1].value = "12345.777".to_string();
-    value_list[2].value = "dyb".to_string();
-    manager.borrow_mut().insert(&table_name, &value_list);
-
-    value_list[0].value = "1".to_string();
-    value_list[1].value = "123.0".to_string();
-    value_list[2].value = "str".to_string();
-
-    manager.borrow_mut().file_manager.insert_in_page(&table_name, 1, &value_list);
-
-    let file = manager.borrow_mut().file_manager.get_file(&table_name);
-    assert!(file.borrow().is_inuse(0, 0));
-    assert!(file.borrow().is_inuse(0, 1));
-    assert!(!file.borrow().is_inuse(0, 2));
-    assert!(file.borrow().is_inuse(1, 0));
-}
-
-pub fn gen_test_manager(table_name : &String) -> TableManagerRef {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
-    manager.borrow_mut().add_table(gen_test_table(table_name));
-    insert_data(table_name, &manager);
-    manager
-}
-
-macro_rules! assert_int {
-    ($p:expr, $n:expr) => ({
-        let i = unsafe{ read::<i32>($p as *const i32) };
-        assert_eq!(i, $n);
-    })
-}
-
-macro_rules! assert_float {
-    ($p:expr, $n:expr) => ({
-        let i = unsafe{ read::<f32>($p as *const f32) };
-        assert_eq!(i, $n);
-    })
-}
-
-macro_rules! assert_str {
-    ($p:expr, $s:expr) => ({
-        let i = unsafe{ read_string($p, 16) };
-        assert_eq!(i, $s);
-    })
-}
-
-#[test]
-fn test_file_scan() {
-    let table_name = "test_query_message".to_string();
-    let manager = gen_test_manager(&table_name);
-    let mut plan = FileScan::new(&table_name, &manager);
-    plan.open();
-    let mut t = plan.get_next().unwrap();
-    assert_int!(t[0], 233);
-    assert_float!(t[1], 666.666);
-    assert_str!(t[2], "qweryu");
-    t = plan.get_next().unwrap();
-    assert_int!(t[0], 777);
-    assert_float!(t[1], 12345.777);
-    assert_str!(t[2], "dyb");
-    t = plan.get_next().unwrap();
-    assert_int!(t[0], 1);
-    assert_float!(t[1], 123.0);
-    assert_str!(t[2], "str");
-    assert_pattern!(plan.get_next(), None);
-}
-
-fn gen_filter_plan(expr : &str) -> ExecIterRef {
-    let table_name = "test_query_message".to_string();
-    let manager = gen_test_manager(&table_name);
-    let scan = FileScan::new(&table_name, &manager);
-    let table = gen_test_table(&table_name);
-    let cond = Box::new(gen_parse_result!(ConditionExpr::parse, expr));
-    Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), scan)
-}
-
-#[test]
-fn test_filter() {
-    {
-        let mut plan = gen_filter_plan("test_query_message.id = 1");
-        plan.open();
-        let tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 1);
-        assert_float!(tuple_data[1], 123.0);
-        assert_str!(tuple_data[2], "str");
-        assert_pattern!(plan.get_next(), None);
-    }
-    {
-        let mut plan = gen_filter_plan("test_query_message.score < 1000");
-        plan.open();
-        let mut tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 233);
-        assert_float!(tuple_data[1], 666.666);
-        assert_str!(tuple_data[2], "qweryu");
-        tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 1);
-        assert_float!(tuple_data[1], 123.0);
-        assert_str!(tuple_data[2], "str");
-        assert_pattern!(plan.get_next(), None);
-    }
-    {
-        let mut plan = gen_filter_plan("0 < 1000");
-        plan.open();
-        let mut tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 233);
-        assert_float!(tuple_data[1], 666.666);
-        assert_str!(tuple_data[2], "qweryu");
-        tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 777);
-        assert_float!(tuple_data[1], 12345.777);
-        assert_str!(tuple_data[2], "dyb");
-        tuple_data = plan.get_next().unwrap();
-        assert_int!(tuple_data[0], 1);
-        assert_float!(tuple_data[1], 123.0);
-        assert_str!(tuple_data[2], "str");
-        assert_pattern!(plan.get_next(), None);
-    }
-}
-
-#[test]
-fn test_projection() {
-    let table_name = "test_query_message".to_string();
-    let manager = gen_test_manager(&table_name);
-    let scan = FileScan::new(&table_name, &manager);
-    let projs = vec![
-        ("test_query_message".to_string(), "id".to_string()),
-        ("test_query_message".to_string(), "content".to_string()),
-    ];
-    let attr_index = vec![0, 2];
-    let mut plan = Projection::new(attr_index, projs, scan);
-    plan.open();
-    let mut tuple_data = plan.get_next().unwrap();
-    assert_eq!(tuple_data.len(), 2);
-    assert_int!(tuple_data[0], 233);
-    assert_str!(tuple_data[1], "qweryu");
-    tuple_data = plan.get_next().unwrap();
-    assert_eq!(tuple_data.len(), 2);
-    assert_int!(tuple_data[0], 777);
-    assert_str!(tuple_data[1], "dyb");
-    tuple_data = plan.get_next().unwrap();
-    assert_eq!(tuple_data.len(), 2);
-    assert_int!(tuple_data[0], 1);
-    assert_str!(tuple_data[1], "str");
-    assert_pattern!(plan.get_next(), None);
-}
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::thread::{sleep, spawn};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::fs::{OpenOptions, remove_dir_all};
+use ::store::table::{TableManagerRef, TableManager, Table, Attr, AttrType};
+use ::store::file::{TableFileManager, get_page_offset};
+use ::parser::common::{ValueExpr, Literal};
+use ::parser::condition::ConditionExpr;
+use ::utils::config::Config;
+use ::utils::file::{read_at_most, write_all_at};
+use ::exec::query::{FileScan, Filter, Projection};
+use ::exec::iter::{ExecIter, ExecIterRef};
+use ::exec::error::{ExecError, ExecErrorType};
+use ::store::tuple::{TupleData, TupleValue, gen_tuple_value};
+use ::exec::gen_plan::gen_proj_info;
+
+
+pub fn gen_test_table(table_name : &String) -> Table {
+    Table{
+        name : table_name.clone(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "score".to_string(),
+                attr_type : AttrType::Float,
+                primary : false,
+                nullable : true,
+                generated : None,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Char{ len : 16 },
+                primary : false,
+                nullable : false,
+                generated : None,
+            },
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    }
+}
+
+pub fn insert_data(table_name : &String, manager : &TableManagerRef) {
+    let mut value_list = vec![
+        ValueExpr::from_literal(Literal::Int(233)),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+        ValueExpr::from_literal(Literal::Str("qweryu".to_string())),
+    ];
+    check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+    value_list[0] = ValueExpr::from_literal(Literal::Int(777));
+    value_list[1] = ValueExpr::from_literal(Literal::Float(12345.777));
+    value_list[2] = ValueExpr::from_literal(Literal::Str("dyb".to_string()));
+    check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+
+    value_list[0] = ValueExpr::from_literal(Literal::Int(1));
+    value_list[1] = ValueExpr::from_literal(Literal::Float(123.0));
+    value_list[2] = ValueExpr::from_literal(Literal::Str("str".to_string()));
+
+    manager.borrow_mut().file_manager.insert_in_page(&table_name, 1, &value_list);
+
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(file.borrow().is_inuse(0, 0));
+    assert!(file.borrow().is_inuse(0, 1));
+    assert!(!file.borrow().is_inuse(0, 2));
+    assert!(file.borrow().is_inuse(1, 0));
+}
+
+pub fn gen_test_manager(table_name : &String) -> TableManagerRef {
+    gen_test_manager_with(table_name, "")
+}
+
+// extra is appended to the config, such as `page_size = 256`
+pub fn gen_test_manager_with(table_name : &String, extra : &str) -> TableManagerRef {
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        {}"#, extra));
+    // removed when the test drops the manager
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, table_name)));
+    manager.borrow_mut().add_table(gen_test_table(table_name));
+    insert_data(table_name, &manager);
+    manager
+}
+
+// the tuples of gen_test_table
+fn test_row(tuple_data : TupleData) -> Vec<TupleValue> {
+    gen_tuple_value(&vec![AttrType::Int, AttrType::Float, AttrType::Char{ len : 16 }], tuple_data)
+}
+
+fn test_values(id : i32, score : f32, content : &str) -> Vec<TupleValue> {
+    vec![TupleValue::Int(id), TupleValue::Float(score), TupleValue::Char(content.to_string())]
+}
+
+// id and content of gen_test_table
+fn proj_row(tuple_data : TupleData) -> Vec<TupleValue> {
+    gen_tuple_value(&vec![AttrType::Int, AttrType::Char{ len : 16 }], tuple_data)
+}
+
+#[test]
+fn test_file_scan() {
+    let table_name = "test_query_message".to_string();
+    check_file_scan(&table_name, &gen_test_manager(&table_name));
+}
+
+#[test]
+fn test_file_scan_small_page() {
+    // the same on every machine
+    let table_name = "test_query_small_page_message".to_string();
+    let manager = gen_test_manager_with(&table_name, "page_size = 256");
+    assert_eq!(manager.borrow_mut().file_manager.get_file(&table_name).borrow().get_page_slot_sum(), 10);
+    check_file_scan(&table_name, &manager);
+}
+
+fn check_file_scan(table_name : &String, manager : &TableManagerRef) {
+    let mut plan = FileScan::new(table_name, manager);
+    plan.open();
+    let mut t = plan.get_next().unwrap();
+    assert_eq!(test_row(t), test_values(233, 666.666, "qweryu"));
+    t = plan.get_next().unwrap();
+    assert_eq!(test_row(t), test_values(777, 12345.777, "dyb"));
+    t = plan.get_next().unwrap();
+    assert_eq!(test_row(t), test_values(1, 123.0, "str"));
+    assert_pattern!(plan.get_next(), None);
+}
+
+fn gen_filter_plan(expr : &str) -> ExecIterRef {
+    let table_name = "test_query_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let scan = FileScan::new(&table_name, &manager);
+    let table = gen_test_table(&table_name);
+    let cond = Box::new(gen_checked_result!(ConditionExpr::parse, expr));
+    Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), scan)
+}
+
+#[test]
+fn test_filter() {
+    {
+        let mut plan = gen_filter_plan("test_query_message.id = 1");
+        plan.open();
+        let tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(1, 123.0, "str"));
+        assert_pattern!(plan.get_next(), None);
+    }
+    {
+        let mut plan = gen_filter_plan("test_query_message.score < 1000");
+        plan.open();
+        let mut tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(233, 666.666, "qweryu"));
+        tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(1, 123.0, "str"));
+        assert_pattern!(plan.get_next(), None);
+    }
+    {
+        let mut plan = gen_filter_plan("0 < 1000");
+        plan.open();
+        let mut tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(233, 666.666, "qweryu"));
+        tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(777, 12345.777, "dyb"));
+        tuple_data = plan.get_next().unwrap();
+        assert_eq!(test_row(tuple_data), test_values(1, 123.0, "str"));
+        assert_pattern!(plan.get_next(), None);
+    }
+}
+
+#[test]
+fn test_projection() {
+    let table_name = "test_query_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let scan = FileScan::new(&table_name, &manager);
+    let projs = vec![
+        ("test_query_message".to_string(), "id".to_string()),
+        ("test_query_message".to_string(), "content".to_string()),
+    ];
+    let attr_index = vec![0, 2];
+    let mut plan = Projection::new(attr_index, projs, scan);
+    plan.open();
+    let mut tuple_data = plan.get_next().unwrap();
+    assert_eq!(tuple_data.len(), 2);
+    assert_eq!(proj_row(tuple_data), vec![TupleValue::Int(233), TupleValue::Char("qweryu".to_string())]);
+    tuple_data = plan.get_next().unwrap();
+    assert_eq!(tuple_data.len(), 2);
+    assert_eq!(proj_row(tuple_data), vec![TupleValue::Int(777), TupleValue::Char("dyb".to_string())]);
+    tuple_data = plan.get_next().unwrap();
+    assert_eq!(tuple_data.len(), 2);
+    assert_eq!(proj_row(tuple_data), vec![TupleValue::Int(1), TupleValue::Char("str".to_string())]);
+    assert_pattern!(plan.get_next(), None);
+}
+
+#[test]
+fn test_scan_large_table() {
+    scan_large_table("test_scan_large_table", "", None);
+}
+
+#[test]
+fn test_scan_large_table_small_page() {
+    scan_large_table("test_scan_large_table_small_page", "page_size = 256", Some(10));
+}
+
+fn scan_large_table(dir : &str, extra : &str, slot_sum : Option<usize>) {
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 3
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/{}/"
+        {}"#, dir, extra));
+    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+    let table_name = "test_query_large_message".to_string();
+    let mut table = gen_test_table(&table_name);
+    table.fill_factor = Some(100);
+    manager.borrow_mut().add_table(table);
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    if let Some(slot_sum) = slot_sum {
+        assert_eq!(file.borrow().get_page_slot_sum(), slot_sum);
+    }
+    let tuple_sum = file.borrow().get_page_slot_sum() * 10;
+    for i in 0..tuple_sum {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+            ValueExpr::from_literal(Literal::Str("qweryu".to_string())),
+        ];
+        check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+    }
+    assert_eq!(file.borrow().page_sum, 10);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    let mut ids = Vec::new();
+    while let Some(tuple_data) = scan.get_next() {
+        let row = test_row(tuple_data);
+        let id = extract!(row[0], TupleValue::Int(id), id) as usize;
+        assert_eq!(scan.get_position(), Some(id));
+        ids.push(id);
+        assert!(file.borrow().loaded_pages.len() <= 3);
+    }
+    scan.close();
+    assert_eq!(ids, (0..tuple_sum).collect::<Vec<usize>>());
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 3);
+}
+
+#[derive(Debug)]
+struct SlowIter {
+    data_source : ExecIterRef,
+}
+
+impl ExecIter for SlowIter {
+    fn open(&mut self) { self.data_source.open(); }
+    fn close(&mut self) { self.data_source.close(); }
+    fn explain(&self) -> String { "slow".to_string() }
+    fn get_next(&mut self) -> Option<TupleData> {
+        sleep(Duration::from_millis(20));
+        self.data_source.get_next()
+    }
+    fn get_error(&self) -> Option<ExecError> { self.data_source.get_error() }
+    fn get_position(&self) -> Option<usize> { self.data_source.get_position() }
+    fn get_affected_num(&self) -> usize { 0 }
+}
+
+#[test]
+fn test_query_timeout() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_query_timeout/"
+        query_timeout_ms = 10"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+    let table_name = "test_query_timeout".to_string();
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    insert_data(&table_name, &manager);
+
+    manager.borrow_mut().start_query();
+    let mut plan : ExecIterRef = Box::new(SlowIter{ data_source : FileScan::new(&table_name, &manager) });
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::QueryCancelled);
+    assert_eq!(err.error_msg, "query cancelled: exceeded 10 ms");
+    plan.close();
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+
+    // cancelled from the guard of the running query
+    manager.borrow_mut().finish_query();
+    let guard = manager.borrow_mut().get_query_guard();
+    let mut plan = FileScan::new(&table_name, &manager);
+    plan.open();
+    assert!(plan.get_next().is_some());
+    guard.cancel();
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_msg, "query cancelled");
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+
+    // killed from another thread by the flag of the process list
+    let killed = Arc::new(AtomicBool::new(false));
+    manager.borrow_mut().set_kill_flag(Some(killed.clone()));
+    manager.borrow_mut().start_query();
+    let mut plan = FileScan::new(&table_name, &manager);
+    plan.open();
+    assert!(plan.get_next().is_some());
+    let killed_clone = killed.clone();
+    is_match!(spawn(move || killed_clone.store(true, Ordering::SeqCst)).join(), Ok(..));
+    assert_pattern!(plan.get_next(), None);
+    assert_eq!(plan.get_error().unwrap().error_msg, "query killed");
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+    manager.borrow_mut().set_kill_flag(None);
+}
+
+#[test]
+fn test_page_pool_exhausted() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_page_pool_exhausted/""#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+    let value_list = vec![
+        ValueExpr::from_literal(Literal::Int(1)),
+        ValueExpr::from_literal(Literal::Float(1.0)),
+        ValueExpr::from_literal(Literal::Str("a".to_string())),
+    ];
+    let tables : Vec<String> = ["pool_scanned", "pool_pinned1", "pool_pinned2"].iter()
+        .map(|t| t.to_string()).collect();
+    for table in tables.iter() {
+        manager.borrow_mut().add_table(gen_test_table(table));
+        check_ok!(manager.borrow_mut().insert(table, &value_list));
+    }
+    // the other two tables take the whole pool
+    let pinned : Vec<_> = tables[1..].iter().map(|t| manager.borrow().file_manager.get_file_id(t)).collect();
+    for file_id in pinned.iter() {
+        manager.borrow_mut().file_manager.pin_page(*file_id, 0);
+    }
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 0);
+
+    manager.borrow_mut().start_query();
+    let mut plan = FileScan::new(&tables[0], &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = plan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::PagePoolExhausted);
+    assert_eq!(err.error_msg, "page 0 of table pool_scanned can't be loaded, all the 2 pages in the pool are pinned");
+    plan.close();
+    manager.borrow_mut().finish_query();
+
+    for file_id in pinned.iter() {
+        manager.borrow_mut().file_manager.unpin_page(*file_id, 0);
+    }
+    let mut plan = FileScan::new(&tables[0], &manager);
+    plan.open();
+    assert!(plan.get_next().is_some());
+    plan.close();
+    assert!(plan.get_error().is_none());
+}
+
+#[test]
+fn test_checksum_mismatch() {
+    let meta_dir = "test_file/table_meta/test_checksum_mismatch/";
+    let file_dir = "test_file/table_file/test_checksum_mismatch/";
+    remove_dir_all(meta_dir).ok();
+    remove_dir_all(file_dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        table_meta_dir = "{}"
+        table_file_dir = "{}""#, meta_dir, file_dir));
+    let table_name = "checksum_message".to_string();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        let mut table = gen_test_table(&table_name);
+        table.fill_factor = Some(100);
+        manager.borrow_mut().add_table(table);
+        for i in 0..25 {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(i)),
+                ValueExpr::from_literal(Literal::Float(i as f32)),
+                ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+            ];
+            check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+        }
+        manager.borrow_mut().save_to_file();
+    }
+    // a byte in the tuples of the second page
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let path = manager.borrow().file_manager.get_file_path(&table_name);
+    let file = check_ok!(OpenOptions::new().read(true).write(true).open(&path));
+    let offset = get_page_offset(1, 256) + 100;
+    let mut byte = [0u8; 1];
+    assert_eq!(read_at_most(&file, &mut byte, offset), 1);
+    check_ok!(write_all_at(&file, &[byte[0] ^ 0xff], offset));
+
+    let scan_ids = |manager : &TableManagerRef| {
+        let mut plan = FileScan::new(&table_name, manager);
+        plan.open();
+        let mut ids = Vec::new();
+        while let Some(tuple_data) = plan.get_next() {
+            let row = test_row(tuple_data);
+            ids.push(extract!(row[0], TupleValue::Int(id), id));
+        }
+        plan.close();
+        (ids, plan.get_error())
+    };
+    let (ids, err) = scan_ids(&manager);
+    assert_eq!(ids, (0..10).collect::<Vec<i32>>());
+    let err = err.unwrap();
+    assert_eq!(err.error_type, ExecErrorType::ChecksumMismatch);
+    let prefix = format!("page 1 of {} is corrupted: checksum", path);
+    assert!(err.error_msg.starts_with(&prefix), "{}", err.error_msg);
+    let scanned = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(scanned.borrow().loaded_pages.get(&1).is_none());
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+
+    // the page is read again by the next scan
+    check_ok!(write_all_at(&file, &byte, offset));
+    let (ids, err) = scan_ids(&manager);
+    assert_eq!(ids, (0..25).collect::<Vec<i32>>());
+    assert!(err.is_none());
+}
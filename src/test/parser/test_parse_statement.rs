@@ -0,0 +1,239 @@
+use std::result::Result::{Ok, Err};
+use std::iter::repeat;
+use ::parser::common::{parse_statement, Statement, ToSql, MAX_NESTING_DEPTH};
+use ::parser::compile_error::{CompileErrorType, ErrorList};
+
+
+const VALID_STATEMENTS : [&'static str; 15] = [
+    "select a1, tab.a2 from tab where a1 > 1 and not (a2 = \"x\\\"y\" or a3 is not null)",
+    "select * from (select * from tab) group by a having tab.b > -(1 + 2) * 3.5 order by a",
+    "select count(a) from tab where (a + 1) % 2 != ? or b <= 'str'",
+    "update tab set a = 1, b = \"string\" where a >= 1.5",
+    "insert tab values(1, null, 2.5, \"中文\\n\")",
+    "delete from tab where a < 1",
+    "create table dept (id int not null primary, name char(20) null) with fill_factor 70 compression",
+    "drop table dept",
+    "alter table author modify name char(20) not null",
+    "copy author from \"author.csv\" with header delimiter ';' empty_as_null max_errors 3",
+    "copy (select a, b from author where a > 1) to 'out.csv' with header overwrite",
+    "backup to 'backup/2016'",
+    "restore from 'backup/2016' with force",
+    "analyze author",
+    "select 学生.名字 from 学生 where 编号 = 1",
+];
+
+const HOSTILE_CHARS : [char; 16] = [
+    '(', ')', '"', '\'', '\\', '\0', '.', '-', '+', '?', ',', '=', '\n', '9', '光', 'n',
+];
+
+// deterministic, so that a failed input can be reproduced
+struct Mutator {
+    seed : u32,
+}
+
+impl Mutator {
+    fn gen(&mut self, n : usize) -> usize {
+        self.seed = self.seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.seed >> 16) as usize % n
+    }
+    fn mutate(&mut self, statement : &str) -> String {
+        let mut chars : Vec<char> = statement.chars().collect();
+        for _ in 0..(self.gen(4) + 1) {
+            if chars.is_empty() {
+                break;
+            }
+            let i = self.gen(chars.len());
+            match self.gen(5) {
+                0 => { chars.remove(i); }
+                1 => {
+                    let c = chars[i];
+                    chars.insert(i, c);
+                }
+                2 => {
+                    let c = HOSTILE_CHARS[self.gen(HOSTILE_CHARS.len())];
+                    chars.insert(i, c);
+                }
+                3 => {
+                    let j = self.gen(chars.len());
+                    chars.swap(i, j);
+                }
+                _ => chars.truncate(i),
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+fn check_errors(input : &str, errors : &ErrorList) {
+    assert!(!errors.is_empty(), "no error for {:?}", input);
+    let max_column = input.chars().count() as i32 + 1;
+    for err in errors.iter() {
+        assert!(err.token.column >= 1 && err.token.column <= max_column,
+            "invalid column of {:?} for {:?}", err, input);
+    }
+}
+
+fn check_input(input : &str) {
+    if let Err(ref errors) = parse_statement(input) {
+        check_errors(input, errors);
+    }
+}
+
+#[test]
+fn test_valid_statements() {
+    for statement in VALID_STATEMENTS.iter() {
+        assert_pattern!(parse_statement(statement), Ok(..));
+    }
+}
+
+#[test]
+fn test_truncated_statements() {
+    for statement in VALID_STATEMENTS.iter() {
+        for (i, _) in statement.char_indices() {
+            check_input(&statement[..i]);
+        }
+    }
+}
+
+#[test]
+fn test_mutated_statements() {
+    let mut mutator = Mutator{ seed : 233 };
+    for statement in VALID_STATEMENTS.iter() {
+        for _ in 0..500 {
+            check_input(&mutator.mutate(statement));
+        }
+    }
+}
+
+#[test]
+fn test_hostile_inputs() {
+    let inputs = [
+        "", " ", "\0", "\n\n", ";", "?", "(", ")", "\"", "'", "\\", "\"\\", "'abc\\'", "\"\\\0\"",
+        "select", "select *", "select * from", "select * from (", "select * from ((select",
+        "select * from t where", "select * from t where not", "select * from t where a =",
+        "select * from t where (a = 1", "select * from t where a = 1)", "select * from t where ((((",
+        "select * from t where a is not", "select * from t where - - - -", "select 1.",
+        "select * from t where a = 99999999999999999999", "select * from t where a = 1.2.3",
+        "insert", "insert t values(", "insert t values(1,", "insert t values(,)", "insert t values(1 2)",
+        "update t set", "update t set a =", "update t set = 1", "delete from", "delete from t where",
+        "create table", "create table t(", "create table t(a char(", "create table t(a char())",
+        "create table t(a char(99999999999))", "create table t(a int) with fill_factor",
+        "drop", "drop table", "alter table t add", "alter table t modify a char(",
+        "copy", "copy t from", "copy t from 'x' with", "copy t from 'x' with max_errors",
+        "copy (", "copy (select * from t", "copy (select * from t) to", "backup to", "restore from 'x' with",
+        "analyze", "analyze t t",
+        "select \0 from t", "select 光 from 星 where 光 = \"光", "is not not is", "null null null",
+    ];
+    for input in inputs.iter() {
+        check_input(input);
+    }
+}
+
+#[test]
+fn test_nul_in_input() {
+    // a '\0' is a blank outside the string literals instead of the end of the input
+    let stmt = extract!(parse_statement("select a\0from tab"), Ok(stmt), stmt);
+    let stmt = extract!(stmt, Statement::Select(stmt), stmt);
+    assert_eq!(format!("{}", stmt), "select a from tab");
+    let errors = extract!(parse_statement("select a\0 tab"), Err(errors), errors);
+    check_errors("select a\0 tab", &errors);
+}
+
+#[test]
+fn test_integer_out_of_range() {
+    let input = "insert tab values(2147483648)";
+    let errors = extract!(parse_statement(input), Err(errors), errors);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].error_type, CompileErrorType::LexerInvalidInteger);
+    assert_eq!(errors[0].token.column, 19);
+    assert_pattern!(parse_statement("insert tab values(2147483647)"), Ok(..));
+}
+
+fn check_round_trip(input : &str) -> String {
+    let stmt = extract!(parse_statement(input), Ok(stmt), stmt);
+    let sql = stmt.to_sql();
+    let reparsed = match parse_statement(&sql) {
+        Ok(reparsed) => reparsed,
+        Err(errors) => panic!("{:?} of {:?} can't be parsed: {:?}", sql, input, errors),
+    };
+    assert_eq!(format!("{:?}", reparsed), format!("{:?}", stmt), "{:?} of {:?}", sql, input);
+    assert_eq!(reparsed.to_sql(), sql);
+    sql
+}
+
+#[test]
+fn test_round_trip() {
+    let cases = [
+        ("select a1, tab.a2 from tab where a1 > 1 and not (a2 = \"x\\\"y\" or a3 is not null)",
+            "select a1, tab.a2 from tab where a1 > 1 and not (a2 = 'x\"y' or a3 is not null)"),
+        ("select * from (select * from tab) group by a having tab.b > -(1 + 2) * 3.5 order by a",
+            "select * from (select * from tab) group by a having tab.b > -((1 + 2) * 3.5) order by a"),
+        ("select count(a) from tab where (a + 1) % 2 != ? or b <= 'str'",
+            "select count(a) from tab where (a + 1) % 2 != ? or b <= 'str'"),
+        ("select t.* from t where ((a = 1) and (b = 2)) or (((c * 2) + 1) = 3)",
+            "select t.* from t where a = 1 and b = 2 or c * 2 + 1 = 3"),
+        ("select * from t where a = 1 and (b = 2 or c = 3) and (d = 4 and e = 5)",
+            "select * from t where a = 1 and (b = 2 or c = 3) and (d = 4 and e = 5)"),
+        ("select * from t where a - (b - c) = (a - b) - c / (d * e)",
+            "select * from t where a - (b - c) = a - b - c / (d * e)"),
+        ("select * from t where (not a = 1) and b = 2 or not not c = 3",
+            "select * from t where (not a = 1) and b = 2 or not (not c = 3)"),
+        ("select * from t where a = 1 and (not b = 2) and c = 3",
+            "select * from t where a = 1 and (not b = 2) and c = 3"),
+        ("select * from t where a * (-b) + c = - - 1",
+            "select * from t where a * (-b) + c = -(-1)"),
+        ("update tab set a = 1, b = \"it's\\\\\\n\" where a >= 1.5",
+            "update tab set a = 1, b = 'it\\'s\\\\\\n' where a >= 1.5"),
+        ("insert tab values(1, null, 2.5, \"中文\\t\", ?)", "insert tab values(1, null, 2.5, '中文\\t', ?)"),
+        ("delete from tab", "delete from tab"),
+        ("create table dept (id int not null primary, name char(20) null) with fill_factor 70 compression",
+            "create table dept (id int not null primary, name char(20)) with compression fill_factor 70"),
+        ("create table item (id int not null primary, total float GENERATED as ((price) * num))",
+            "create table item (id int not null primary, total float generated as (price * num))"),
+        ("drop table dept", "drop table dept"),
+        ("alter table author add age int not null", "alter table author add age int not null"),
+        ("alter table author drop age", "alter table author drop age"),
+        ("copy author from \"author.csv\" with header delimiter ';' empty_as_null max_errors 3",
+            "copy author from 'author.csv' with header delimiter ';' empty_as_null max_errors 3"),
+        ("copy author from Stdin with max_errors 3", "copy author from stdin with max_errors 3"),
+        ("copy (select a, b from author where a > 1) to 'out.csv' with delimiter ',' null_as 'NULL' overwrite",
+            "copy (select a, b from author where a > 1) to 'out.csv' with null_as 'NULL' overwrite"),
+        ("WITH high AS (select * from t where score > 100), low as (select id from t) select * from high",
+            "with high as (select * from t where score > 100), low as (select id from t) select * from high"),
+        ("backup to 'backup/2016'", "backup to 'backup/2016'"),
+        ("restore from 'backup/2016' with force", "restore from 'backup/2016' with force"),
+        ("analyze author", "analyze author"),
+        ("select 学生.名字 from 学生 where 编号 = 1", "select 学生.名字 from 学生 where 编号 = 1"),
+    ];
+    for &(input, expected) in cases.iter() {
+        assert_eq!(check_round_trip(input), expected);
+    }
+}
+
+fn nested(prefix : &str, unit_head : &str, inner : &str, unit_tail : &str, num : usize) -> String {
+    let head : String = repeat(unit_head).take(num).collect();
+    let tail : String = repeat(unit_tail).take(num).collect();
+    format!("select * from tab where {}{}{}{}", prefix, head, inner, tail)
+}
+
+#[test]
+fn test_nesting_depth() {
+    // (prefix, the head and tail of a level, inner, the depth of a level)
+    let cases = [
+        ("", "(", "a = 1", ")", 1),
+        ("", "not ", "a = 1", "", 1),
+        ("a = ", "-", "1", "", 1),
+        ("a = ", "-(", "1", ")", 2),
+        ("a = ", "(1 + (", "1", "))", 2),
+    ];
+    for &(prefix, head, inner, tail, level_depth) in cases.iter() {
+        let input = nested(prefix, head, inner, tail, MAX_NESTING_DEPTH / level_depth);
+        assert_pattern!(parse_statement(&input), Ok(..));
+
+        let input = nested(prefix, head, inner, tail, 10000);
+        let errors = extract!(parse_statement(&input), Err(errors), errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, CompileErrorType::ParserTooDeepNesting);
+        check_errors(&input, &errors);
+    }
+}
@@ -1,318 +1,643 @@
-use ::store::table::{TableSet, Table, Attr, AttrType};
-use ::parser::condition::ConditionExpr;
-use ::parser::compile_error::CompileErrorType;
-use ::parser::select::SelectStatement;
-use ::parser::update::UpdateStatement;
-use ::parser::insert::InsertStatement;
-use ::parser::delete::DeleteStatement;
-use ::parser::create_drop::{CreateStatement, DropStatement};
-use ::parser::sem_check::{
-    check_drop,
-    check_create,
-    check_condition,
-    check_insert,
-    check_update,
-    check_select,
-};
-
-
-macro_rules! assert_ok {
-    ($check_result:expr) => (assert_pattern!($check_result, Ok(..)))
-}
-
-macro_rules! assert_err {
-    ($check_result:expr, $expected_type:expr) => ({
-        let error_list = extract!($check_result, Err(error_list), error_list);
-        let error_type = error_list.first().unwrap().error_type;
-        assert_eq!(error_type, $expected_type);
-    })
-}
-
-fn add_table(table_set : &mut TableSet) {
-    let t1 = Table{
-        name : "author".to_string(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "name".to_string(),
-                attr_type : AttrType::Char{ len : 10 },
-                primary : false,
-                nullable : false,
-            }
-        ],
-    };
-    let t2 = Table{
-        name : "book".to_string(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "author_id".to_string(),
-                attr_type : AttrType::Int,
-                primary : false,
-                nullable : true,
-            },
-            Attr{
-                name : "name".to_string(),
-                attr_type : AttrType::Char{ len : 10},
-                primary : false,
-                nullable : true,
-            }
-        ]
-    };
-    table_set.add_table(t1);
-    table_set.add_table(t2);
-}
-
-#[test]
-fn test_check_drop() {
-    let drop_stmt = gen_parse_result!(DropStatement::parse, "drop table author");
-    let mut table_set = TableSet::new();
-    assert_err!(check_drop(&drop_stmt, &table_set), CompileErrorType::SemTableNotExist);
-    add_table(&mut table_set);
-    assert_ok!(check_drop(&drop_stmt, &table_set));
-}
-
-#[test]
-fn test_check_create() {
-    {// table exist
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary)");
-        let mut table_set = TableSet::new();
-        assert_ok!(check_create(&create_stmt, &table_set));
-        add_table(&mut table_set);
-        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemTableExist);
-    }
-    {// unique primary
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary)");
-        let table_set = TableSet::new();
-        assert_ok!(check_create(&create_stmt, &table_set));
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary, num int not null primary)");
-        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemMultiplePrimary);
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int)");
-        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemNoPrimary);
-    }
-    {// primary not null
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary)");
-        let table_set = TableSet::new();
-        assert_ok!(check_create(&create_stmt, &table_set));
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int primary)");
-        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemNullablePrimary);
-    }
-    {// unique attribute
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary)");
-        let table_set = TableSet::new();
-        assert_ok!(check_create(&create_stmt, &table_set));
-        let create_stmt = gen_parse_result!(CreateStatement::parse,
-            "create table author(id int not null primary, id char(10))");
-        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
-    }
-}
-
-#[test]
-fn test_check_condition() {
-    // arithmatic type correctness already guranteed by grammar
-    {// comparasion type check
-        let mut table_set = TableSet::new();
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 < 0 or 1 = 2");
-        assert_ok!(check_condition(&mut condition, &table_set, &None));
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 = null");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 < \"i am string\"");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
-        
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "\"aaa\" = \"bbb\"");
-        assert_ok!(check_condition(&mut condition, &table_set, &None));
-
-        add_table(&mut table_set);
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is not null");
-        assert_ok!(check_condition(&mut condition, &table_set, &None));
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is 1");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "2 is null");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
-    }
-    {// attirbute check
-        let mut table_set = TableSet::new();
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "a is null");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidAttribute);
-
-        add_table(&mut table_set);
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is null");
-        assert_ok!(check_condition(&mut condition, &table_set, &None));
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.author_id is null");
-        assert_ok!(check_condition(&mut condition, &table_set, &None));
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.id > 1");
-        assert_ok!(check_condition(&mut condition, &table_set,
-            &Some((Some("book".to_string()), "id".to_string()))));
-        assert_err!(check_condition(&mut condition, &table_set,
-            &Some((Some("book".to_string()), "author_id".to_string()))),
-            CompileErrorType::SemShouldUseGroupByAttribute);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "sum(book.id) > 1");
-        assert_ok!(check_condition(&mut condition, &table_set,
-            &Some((Some("book".to_string()), "id".to_string()))));
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "invalid_func(book.id) > 1");
-        assert_err!(check_condition(&mut condition, &table_set,
-            &Some((Some("book".to_string()), "id".to_string()))),
-            CompileErrorType::SemInvalidAggreFuncName);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "sum(book.id) > 1");
-        assert_err!(check_condition(&mut condition, &table_set, &None),
-            CompileErrorType::SemInvalidAggregateFunctionUse);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.name > 0");
-        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "author.name is null");
-        assert_err!(check_condition(&mut condition, &table_set, &None),
-            CompileErrorType::SemAttributeNotNullable);
-
-        let mut condition = gen_parse_result!(ConditionExpr::parse, "id is null");
-        assert_err!(check_condition(&mut condition, &table_set, &None),
-            CompileErrorType::SemInvalidAttribute);
-    }
-}
-
-#[test]
-fn test_check_insert() {
-    let mut table_set = TableSet::new();
-    add_table(&mut table_set);
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2, \"book name\")");
-    assert_ok!(check_insert(&mut insert, &table_set));
-
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2, \"book name\", 3)");
-    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertValuesNum);
-
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2.0, \"book name\")");
-    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertValueType);
-
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert author values(1, \"doyoubi\")");
-    assert_ok!(check_insert(&mut insert, &table_set));
-    let mut insert = gen_parse_result!(InsertStatement::parse,
-        "insert author values(1, \"it is difficult to come up with a long name\")");
-    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertCharLen);
-
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert author values(1, null)");
-    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemAttributeNotNullable);
-
-    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, null, \"book name\")");
-    assert_ok!(check_insert(&mut insert, &table_set));
-}
-
-#[test]
-fn test_check_update() {
-    let mut table_set = TableSet::new();
-    add_table(&mut table_set);
-
-    let mut update = gen_parse_result!(UpdateStatement::parse,
-        "update book set author_id = 2, name = \"doyoubi\" where book.id = 1");
-    assert_ok!(check_update(&mut update, &table_set));
-
-    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set id = 1");
-    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemChangePrimaryAttr);
-
-    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set invalid_attr = 1");
-    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemInvalidAttribute);
-
-    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set author_id = 2.2");
-    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemInvalidInsertValueType);
-
-    let mut update = gen_parse_result!(UpdateStatement::parse, "update author set name = null");
-    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemAttributeNotNullable);
-}
-
-#[test]
-fn test_check_select() {
-    let mut table_set = TableSet::new();
-    add_table(&mut table_set);
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select book.id, book.name from book where book.id > 1");
-    assert_ok!(check_select(&mut select, &table_set));
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select author_id, min(book.author_id) from book where book.id > 1 \
-        group by author_id having min(author_id) > 2 and author_id > 3");
-    assert_ok!(check_select(&mut select, &table_set));
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select book.author_id, min(book.author_id) from book where book.id > 1 \
-        group by author_id having min(book.id) > 2 and book.id > 3");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select book.id, min(book.id) from book where book.id > 1 \
-        group by author_id having min(author_id) > 2 and author_id > 3");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book where num = 1");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book where num = 1");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book group by book.name");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemSelectAllWithGroupBy);
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select num from book");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by book.id");
-    assert_ok!(check_select(&mut select, &table_set));
-
-    let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by num");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select book.name from book group by book.name order by book.id");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select book.id from book group by book.name order by book.name");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
-}
-
-#[test]
-fn test_select_table_not_exist() {
-    let table_set = TableSet::new();
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select msg.id from msg");
-    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemTableNotExist);
-}
-
-#[test]
-fn test_complete_table_in_attribute() {
-    let mut table_set = TableSet::new();
-    add_table(&mut table_set);
-
-    let mut select = gen_parse_result!(SelectStatement::parse,
-        "select author_id, min(author_id) from book where book.id > 1 \
-        group by author_id having min(author_id) > 2 and author_id > 3");
-    assert_ok!(check_select(&mut select, &table_set));
-    assert_eq!(format!("{}", select), "select (book.author_id), min(book.author_id) from book \
-        where ((book.id) > Integer(1)) group by (book.author_id) \
-        having ((min(book.author_id) > Integer(2)) and ((book.author_id) > Integer(3)))");
-}
+use ::store::table::{TableSet, Table, Attr, AttrType};
+use ::parser::condition::ConditionExpr;
+use ::parser::compile_error::CompileErrorType;
+use ::parser::common::{Literal, ToSql};
+use ::parser::select::SelectStatement;
+use ::parser::update::UpdateStatement;
+use ::parser::insert::InsertStatement;
+use ::parser::delete::DeleteStatement;
+use ::parser::create_drop::{CreateStatement, DropStatement};
+use ::parser::alter::AlterStatement;
+use ::parser::copy::CopyFromStatement;
+use ::parser::analyze::AnalyzeStatement;
+use ::parser::sem_check::{
+    check_drop,
+    check_create,
+    check_condition,
+    check_insert,
+    check_update,
+    check_select,
+    check_copy_from,
+    check_alter,
+    check_analyze,
+};
+
+
+macro_rules! assert_ok {
+    ($check_result:expr) => (assert_pattern!($check_result, Ok(..)))
+}
+
+macro_rules! assert_errors {
+    ($check_result:expr, $expected_types:expr) => ({
+        let error_list = extract!($check_result, Err(error_list), error_list);
+        let error_types : Vec<CompileErrorType> = error_list.iter().map(|e| e.error_type).collect();
+        assert_eq!(error_types, $expected_types);
+    })
+}
+
+macro_rules! assert_err {
+    ($check_result:expr, $expected_type:expr) => ({
+        let error_list = extract!($check_result, Err(error_list), error_list);
+        let error_type = error_list.first().unwrap().error_type;
+        assert_eq!(error_type, $expected_type);
+    })
+}
+
+fn add_table(table_set : &mut TableSet) {
+    let t1 = Table{
+        name : "author".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "name".to_string(),
+                attr_type : AttrType::Char{ len : 10 },
+                primary : false,
+                nullable : false,
+                generated : None,
+            }
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    };
+    let t2 = Table{
+        name : "book".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "author_id".to_string(),
+                attr_type : AttrType::Int,
+                primary : false,
+                nullable : true,
+                generated : None,
+            },
+            Attr{
+                name : "name".to_string(),
+                attr_type : AttrType::Char{ len : 10},
+                primary : false,
+                nullable : true,
+                generated : None,
+            }
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    };
+    table_set.add_table(t1);
+    table_set.add_table(t2);
+}
+
+#[test]
+fn test_check_drop() {
+    let drop_stmt = gen_parse_result!(DropStatement::parse, "drop table author");
+    let mut table_set = TableSet::new();
+    assert_err!(check_drop(&drop_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_drop(&drop_stmt, &table_set));
+}
+
+#[test]
+fn test_check_analyze() {
+    let analyze_stmt = gen_parse_result!(AnalyzeStatement::parse, "analyze author");
+    let mut table_set = TableSet::new();
+    assert_err!(check_analyze(&analyze_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_analyze(&analyze_stmt, &table_set));
+}
+
+#[test]
+fn test_check_create() {
+    {// table exist
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary)");
+        let mut table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        add_table(&mut table_set);
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemTableExist);
+    }
+    {// unique primary
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary)");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary, num int not null primary)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemMultiplePrimary);
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemNoPrimary);
+    }
+    {// primary not null
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary)");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int primary)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemNullablePrimary);
+    }
+    {// unique attribute
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary)");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary, id char(10))");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
+    }
+    {// fill factor
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with fill_factor 70");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with fill_factor 5");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidFillFactor);
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with fill_factor 101");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidFillFactor);
+    }
+}
+
+#[test]
+fn test_check_condition() {
+    // arithmatic type correctness already guranteed by grammar
+    {// comparasion type check
+        let mut table_set = TableSet::new();
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 < 0 or 1 = 2");
+        assert_ok!(check_condition(&mut condition, &table_set, &None));
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 = null");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "1 < \"i am string\"");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
+        
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "\"aaa\" = \"bbb\"");
+        assert_ok!(check_condition(&mut condition, &table_set, &None));
+
+        add_table(&mut table_set);
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is not null");
+        assert_ok!(check_condition(&mut condition, &table_set, &None));
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is 1");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "2 is null");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
+    }
+    {// attirbute check
+        let mut table_set = TableSet::new();
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "a is null");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidAttribute);
+
+        add_table(&mut table_set);
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "author_id is null");
+        assert_ok!(check_condition(&mut condition, &table_set, &None));
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.author_id is null");
+        assert_ok!(check_condition(&mut condition, &table_set, &None));
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.id > 1");
+        assert_ok!(check_condition(&mut condition, &table_set,
+            &Some((Some("book".to_string()), "id".to_string()))));
+        assert_err!(check_condition(&mut condition, &table_set,
+            &Some((Some("book".to_string()), "author_id".to_string()))),
+            CompileErrorType::SemShouldUseGroupByAttribute);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "sum(book.id) > 1");
+        assert_ok!(check_condition(&mut condition, &table_set,
+            &Some((Some("book".to_string()), "id".to_string()))));
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "invalid_func(book.id) > 1");
+        assert_err!(check_condition(&mut condition, &table_set,
+            &Some((Some("book".to_string()), "id".to_string()))),
+            CompileErrorType::SemInvalidAggreFuncName);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "sum(book.id) > 1");
+        assert_err!(check_condition(&mut condition, &table_set, &None),
+            CompileErrorType::SemInvalidAggregateFunctionUse);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "book.name > 0");
+        assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidValueType);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "author.name is null");
+        assert_err!(check_condition(&mut condition, &table_set, &None),
+            CompileErrorType::SemAttributeNotNullable);
+
+        let mut condition = gen_parse_result!(ConditionExpr::parse, "id is null");
+        assert_err!(check_condition(&mut condition, &table_set, &None),
+            CompileErrorType::SemInvalidAttribute);
+    }
+}
+
+#[test]
+fn test_check_insert() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2, \"book name\")");
+    assert_ok!(check_insert(&mut insert, &table_set));
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2, \"book name\", 3)");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertValuesNum);
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2)");
+    let errs = extract!(check_insert(&mut insert, &table_set), Err(errs), errs);
+    assert_eq!(errs[0].error_msg, "invalid insert values number, expected 3, found 2");
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, 2.0, \"book name\")");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertValueType);
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert author values(1, \"doyoubi\")");
+    assert_ok!(check_insert(&mut insert, &table_set));
+    let mut insert = gen_parse_result!(InsertStatement::parse,
+        "insert author values(1, \"it is difficult to come up with a long name\")");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertCharLen);
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert author values(1, null)");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemAttributeNotNullable);
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, null, \"book name\")");
+    assert_ok!(check_insert(&mut insert, &table_set));
+}
+
+#[test]
+fn test_check_literal() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, null, \"book name\")");
+    assert!(insert.value_list.iter().all(|v| v.literal.is_none()));
+    assert_ok!(check_insert(&mut insert, &table_set));
+    let literals : Vec<Literal> = insert.value_list.iter().map(|v| v.literal().clone()).collect();
+    assert_eq!(literals, vec![Literal::Int(1), Literal::Null, Literal::Str("book name".to_string())]);
+
+    let mut condition = gen_parse_result!(ConditionExpr::parse, "book.id + 1.5 > 2 and book.name = \"a\"");
+    assert_ok!(check_condition(&mut condition, &table_set, &None));
+    let display = format!("{}", condition);
+    check_ok!(condition.check_literals());
+    assert_eq!(format!("{}", condition), display);
+
+    // out of the range of f32
+    let huge = "1000000000000000000000000000000000000000.0";
+    let mut condition = gen_parse_result!(ConditionExpr::parse, &format!("book.id < {}", huge));
+    assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidLiteral);
+    let mut condition = gen_parse_result!(ConditionExpr::parse, &format!("-(1 + {}) < book.id", huge));
+    assert_err!(check_condition(&mut condition, &table_set, &None), CompileErrorType::SemInvalidLiteral);
+    let mut update = gen_parse_result!(UpdateStatement::parse,
+        &format!("update book set author_id = 1 where book.id = {}", huge));
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemInvalidLiteral);
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        &format!("select * from book where book.id = {}", huge));
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidLiteral);
+}
+
+#[test]
+fn test_check_update() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse,
+        "update book set author_id = 2, name = \"doyoubi\" where book.id = 1");
+    assert_ok!(check_update(&mut update, &table_set));
+
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set id = 1");
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemChangePrimaryAttr);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set invalid_attr = 1");
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set author_id = 2.2");
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemInvalidInsertValueType);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update author set name = null");
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemAttributeNotNullable);
+}
+
+#[test]
+fn test_check_select() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.id, book.name from book where book.id > 1");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select author_id, min(book.author_id) from book where book.id > 1 \
+        group by author_id having min(author_id) > 2 and author_id > 3");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.author_id, min(book.author_id) from book where book.id > 1 \
+        group by author_id having min(book.id) > 2 and book.id > 3");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.id, min(book.id) from book where book.id > 1 \
+        group by author_id having min(author_id) > 2 and author_id > 3");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book where num = 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book where num = 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book group by book.name");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemSelectAllWithGroupBy);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select book.* from book where book.id = 1");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select author.* from book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select book.* from book group by book.name");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemSelectAllWithGroupBy);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select num from book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by book.id");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by num");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.name from book group by book.name order by book.id");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.id from book group by book.name order by book.name");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+}
+
+#[test]
+fn test_check_with() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select id, name from book where id > 1) select name from b where id < 10");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert!(select.with_list.is_empty());
+    assert_eq!(select.to_sql(), "select book.name from book where book.id > 1 and book.id < 10");
+
+    // a is used twice
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with a as (select * from book where id > 1), b as (select id from a where a.id < 5), \
+        c as (select name from a) select * from b");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(select.to_sql(), "select book.id from book where book.id > 1 and book.id < 5");
+
+    // the with table hides the table after it
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with author as (select * from author where id = 1), book as (select author_id from book) \
+        select author.* from author");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(select.to_sql(), "select * from author where author.id = 1");
+
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select id from book) select name from b");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select * from book) select book.name from b");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select * from book) select * from b where num = 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select * from book), b as (select * from author) select * from b");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemTableExist);
+
+    // b can't be used by itself
+    let mut select = gen_parse_result!(SelectStatement::parse_with,
+        "with b as (select * from b) select * from b");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemTableNotExist);
+}
+
+#[test]
+fn test_check_all_errors() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse,
+        "update book set invalid_attr = 1, author_id = 2.2 where book.name > 0");
+    assert_errors!(check_update(&mut update, &table_set), vec![
+        CompileErrorType::SemInvalidAttribute,
+        CompileErrorType::SemInvalidInsertValueType,
+        CompileErrorType::SemInvalidValueType,
+    ]);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select num, book.id from book where book.name > 1 and author_id = 1 order by other");
+    assert_errors!(check_select(&mut select, &table_set), vec![
+        CompileErrorType::SemInvalidAttribute,
+        CompileErrorType::SemInvalidValueType,
+        CompileErrorType::SemInvalidAttribute,
+    ]);
+
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert author values(null, 2)");
+    assert_errors!(check_insert(&mut insert, &table_set), vec![
+        CompileErrorType::SemAttributeNotNullable,
+        CompileErrorType::SemInvalidInsertValueType,
+    ]);
+
+    let create_stmt = gen_parse_result!(CreateStatement::parse,
+        "create table author(id int primary, id char(10)) with fill_factor 5");
+    assert_errors!(check_create(&create_stmt, &table_set), vec![
+        CompileErrorType::SemTableExist,
+        CompileErrorType::SemDuplicateAttr,
+        CompileErrorType::SemNullablePrimary,
+        CompileErrorType::SemInvalidFillFactor,
+    ]);
+}
+
+#[test]
+fn test_no_cascading_errors() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    // no type error for an unknown attribute
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book where num > 1");
+    assert_errors!(check_select(&mut select, &table_set), vec![CompileErrorType::SemInvalidAttribute]);
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update book set num = \"a\"");
+    assert_errors!(check_update(&mut update, &table_set), vec![CompileErrorType::SemInvalidAttribute]);
+
+    // nothing else is checked without the table
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update msg set a = 1 where b > \"c\"");
+    assert_errors!(check_update(&mut update, &table_set), vec![CompileErrorType::SemTableNotExist]);
+
+    // nor the attributes depending on an unknown group by attribute
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.id, max(book.id) from book where num > 1 group by other having max(book.id) > 1");
+    assert_errors!(check_select(&mut select, &table_set), vec![
+        CompileErrorType::SemInvalidAttribute,
+        CompileErrorType::SemInvalidAttribute,
+    ]);
+}
+
+#[test]
+fn test_select_table_not_exist() {
+    let table_set = TableSet::new();
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select msg.id from msg");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemTableNotExist);
+}
+
+#[test]
+fn test_complete_table_in_attribute() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select author_id, min(author_id) from book where book.id > 1 \
+        group by author_id having min(author_id) > 2 and author_id > 3");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(format!("{}", select), "select (book.author_id), min(book.author_id) from book \
+        where ((book.id) > Integer(1)) group by (book.author_id) \
+        having ((min(book.author_id) > Integer(2)) and ((book.author_id) > Integer(3)))");
+}
+
+#[test]
+fn test_check_copy_from() {
+    let mut table_set = TableSet::new();
+    let copy = gen_parse_result!(CopyFromStatement::parse, "copy author from 'data/author.csv'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_copy_from(&copy, &table_set));
+
+    let copy = gen_parse_result!(CopyFromStatement::parse, "copy author from '../author.csv'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemInvalidCopyPath);
+    let copy = gen_parse_result!(CopyFromStatement::parse, "copy author from 'data/../../author.csv'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemInvalidCopyPath);
+    let copy = gen_parse_result!(CopyFromStatement::parse, "copy author from '/etc/passwd'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemInvalidCopyPath);
+    let copy = gen_parse_result!(CopyFromStatement::parse, "copy author from stdin");
+    assert_ok!(check_copy_from(&copy, &table_set));
+
+    let copy = gen_parse_result!(CopyFromStatement::parse,
+        "copy author from 'author.csv' with delimiter ';'");
+    assert_ok!(check_copy_from(&copy, &table_set));
+    let copy = gen_parse_result!(CopyFromStatement::parse,
+        "copy author from 'author.csv' with delimiter '光'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemInvalidCopyDelimiter);
+    let copy = gen_parse_result!(CopyFromStatement::parse,
+        "copy author from 'author.csv' with delimiter '\"'");
+    assert_err!(check_copy_from(&copy, &table_set), CompileErrorType::SemInvalidCopyDelimiter);
+}
+
+#[test]
+fn test_check_alter() {
+    let mut table_set = TableSet::new();
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author add age int");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_alter(&alter, &table_set));
+
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author add name int");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemDuplicateAttr);
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author add age int not null");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemAttributeNotNullable);
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author drop id");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemChangePrimaryAttr);
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author drop age");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemInvalidAttribute);
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author drop name");
+    assert_ok!(check_alter(&alter, &table_set));
+
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author modify name char(20) null");
+    assert_ok!(check_alter(&alter, &table_set));
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author modify id float not null primary");
+    assert_ok!(check_alter(&alter, &table_set));
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author modify id float not null");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemChangePrimaryAttr);
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table author modify name int primary");
+    assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemChangePrimaryAttr);
+}
+
+#[test]
+fn test_check_generated() {
+    let table_set = TableSet::new();
+    let create_stmt = gen_parse_result!(CreateStatement::parse,
+        "create table item(id int not null primary, price float, num int, \
+            total float generated as (price * num))");
+    assert_ok!(check_create(&create_stmt, &table_set));
+    let create_stmt = gen_parse_result!(CreateStatement::parse,
+        "create table item(id int not null primary, num int, twice int generated as (item.num * 2))");
+    assert_ok!(check_create(&create_stmt, &table_set));
+    let invalid_list = [
+        "create table item(id int not null primary generated as (1))",
+        "create table item(id int not null primary, name char(10) generated as (1))",
+        "create table item(id int not null primary, name char(10), n int generated as (name + 1))",
+        "create table item(id int not null primary, n int generated as (price))",
+        "create table item(id int not null primary, n int generated as (book.id))",
+        "create table item(id int not null primary, n int generated as (sum(id)))",
+        "create table item(id int not null primary, a int generated as (id), b int generated as (a))",
+    ];
+    for sql in invalid_list.iter() {
+        let create_stmt = gen_parse_result!(CreateStatement::parse, sql);
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidGeneratedAttr);
+    }
+
+    let mut table_set = TableSet::new();
+    table_set.add_table(Table{
+        name : "item".to_string(),
+        attr_list : vec![
+            Attr{ name : "id".to_string(), attr_type : AttrType::Int, primary : true, nullable : false,
+                generated : None },
+            Attr{ name : "num".to_string(), attr_type : AttrType::Int, primary : false, nullable : true,
+                generated : None },
+            Attr{ name : "note".to_string(), attr_type : AttrType::Int, primary : false, nullable : true,
+                generated : None },
+            Attr{ name : "twice".to_string(), attr_type : AttrType::Int, primary : false, nullable : true,
+                generated : Some("num * 2".to_string()) },
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    });
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert item values(1, 2, 3)");
+    assert_ok!(check_insert(&mut insert, &table_set));
+    let mut insert = gen_parse_result!(InsertStatement::parse, "insert item values(1, 2, 3, 4)");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertValuesNum);
+
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update item set num = 3");
+    assert_ok!(check_update(&mut update, &table_set));
+    let mut update = gen_parse_result!(UpdateStatement::parse, "update item set twice = 3");
+    assert_err!(check_update(&mut update, &table_set), CompileErrorType::SemChangeGeneratedAttr);
+
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table item drop note");
+    assert_ok!(check_alter(&alter, &table_set));
+    let alter = gen_parse_result!(AlterStatement::parse, "alter table item drop twice");
+    assert_ok!(check_alter(&alter, &table_set));
+    let changes = [
+        "alter table item drop num",
+        "alter table item modify num float",
+        "alter table item modify twice float",
+        "alter table item modify note int generated as (num + 1)",
+        "alter table item add half float generated as (num / 2)",
+    ];
+    for sql in changes.iter() {
+        let alter = gen_parse_result!(AlterStatement::parse, sql);
+        assert_err!(check_alter(&alter, &table_set), CompileErrorType::SemChangeGeneratedAttr);
+    }
+}
@@ -1,225 +1,363 @@
-use ::parser::common::exp_list_to_string;
-use ::parser::select::{SelectExpr, Relation, GroupbyHaving, SelectStatement, RelationList};
-use ::parser::attribute::AttributeExpr;
-use ::parser::update::{AssignExpr, UpdateStatement};
-use ::parser::insert::InsertStatement;
-use ::parser::delete::DeleteStatement;
-use ::parser::create_drop::{DropStatement, AttributeDeclaration, CreateStatement, AttrType};
-use super::super::utils::{test_by_display_str, test_by_list_to_str};
-
-#[test]
-fn test_parse_select_expr() {
-    test_by_display_str(
-        "select *", 2,
-        SelectExpr::parse,
-        "select *"
-    );
-    test_by_display_str(
-        "select attribute_name", 2,
-        SelectExpr::parse,
-        "select attribute_name"
-    );
-    test_by_display_str(
-        "select a1, a2, a3", 6,
-        SelectExpr::parse,
-        "select a1, a2, a3"
-    );
-}
-
-#[test]
-fn test_parse_relation() {
-    test_by_list_to_str(
-        "from table_name", 2,
-        Relation::parse,
-        "table_name"
-    );
-    test_by_list_to_str(
-        "from tb1, tb2, tb3", 6,
-        Relation::parse,
-        "tb1, tb2, tb3"
-    );
-    test_by_list_to_str(
-        "from (select * from tab)", 7,
-        Relation::parse,
-        "(select * from tab)"
-    );
-    test_by_list_to_str(
-        "from tab, (select * from tab)", 9,
-        Relation::parse,
-        "tab, (select * from tab)"
-    );
-}
-
-#[test]
-fn test_parse_group_by_having() {
-    test_by_display_str(
-        "group by attribute", 3,
-        GroupbyHaving::parse,
-        "group by attribute"
-    );
-    test_by_display_str(
-        "group by tab.attribute having dept.employee > 1", 11,
-        GroupbyHaving::parse,
-        "group by (tab.attribute) having ((dept.employee) > Integer(1))"
-    );
-}
-
-#[test]
-fn test_parse_select_statement() {
-    test_by_display_str(
-        "select sum(employee) from table_name where tab.money > 0\
-        group by huang.guangxing having dept.number > 1 order by doyoubi",
-        27,
-        SelectStatement::parse,
-        "select sum(employee) from table_name where ((tab.money) > Integer(0)) \
-        group by (huang.guangxing) having ((dept.number) > Integer(1)) order by doyoubi"
-    );
-    test_by_display_str(
-        "select tab.attr from huang group by doyoubi", 9,
-        SelectStatement::parse,
-        "select (tab.attr) from huang group by doyoubi"
-    );
-    test_by_display_str(
-        "select attr from huang where doyoubi is not null", 8,
-        SelectStatement::parse,
-        "select attr from huang where (doyoubi is not Null(null))"
-    );
-    test_by_display_str(
-        "select attr from huang order by doyoubi", 7,
-        SelectStatement::parse,
-        "select attr from huang order by doyoubi"
-    );
-    test_by_display_str(
-        "select attr from huang group by attr order by doyoubi", 10,
-        SelectStatement::parse,
-        "select attr from huang group by attr order by doyoubi"
-    );
-}
-
-#[test]
-fn test_parse_assign() {
-    test_by_list_to_str(
-        "abc = 1", 3,
-        AssignExpr::parse,
-        "(abc = Integer(1))"
-    );
-}
-
-#[test]
-fn test_parse_assign_list() {
-    test_by_list_to_str(
-        "a = 1, b = 2", 7,
-        AssignExpr::parse,
-        "(a = Integer(1)), (b = Integer(2))"
-    );
-}
-
-#[test]
-fn test_udpate_statement_parse() {
-    test_by_display_str(
-        "update tab set a = 1", 6,
-        UpdateStatement::parse,
-        "update tab set (a = Integer(1))"
-    );
-    test_by_display_str(
-        "update tab set a = 1, b = \"string\" where a > 1", 14,
-        UpdateStatement::parse,
-        "update tab set (a = Integer(1)), (b = String(string)) where (a > Integer(1))"
-    );
-}
-
-#[test]
-fn test_insert_statement_parse() {
-    test_by_display_str(
-        "insert tab values(1)", 6,
-        InsertStatement::parse,
-        "insert tab values(Integer(1))"
-    );
-    test_by_display_str(
-        "insert tab values(1, null)", 8,
-        InsertStatement::parse,
-        "insert tab values(Integer(1), Null(null))"
-    );
-}
-
-#[test]
-fn test_delete_statement_parse() {
-    test_by_display_str(
-        "delete from tab", 3,
-        DeleteStatement::parse,
-        "delete from tab"
-    );
-    test_by_display_str(
-        "delete from tab where a > 1", 7,
-        DeleteStatement::parse,
-        "delete from tab where (a > Integer(1))"
-    );
-}
-
-#[test]
-fn test_drop_statement_parse() {
-    test_by_display_str(
-        "drop table dept", 3,
-        DropStatement::parse,
-        "drop table dept"
-    );
-}
-
-#[test]
-fn test_attribute_declaration_parse() {
-    test_by_display_str(
-        "name char(1) not null", 7,
-        AttributeDeclaration::parse_decl,
-        "(name Char(1) not null)"
-    );
-    test_by_display_str(
-        "name char(1) null", 6,
-        AttributeDeclaration::parse_decl,
-        "(name Char(1) null)"
-    );
-    test_by_display_str(
-        "name char(1) primary", 6,
-        AttributeDeclaration::parse_decl,
-        "(name Char(1) null primary)"
-    );
-}
-
-#[test]
-fn test_attr_type_parse() {
-    test_by_display_str(
-        "int", 1,
-        AttrType::parse,
-        "Int"
-    );
-    test_by_display_str(
-        "float", 1,
-        AttrType::parse,
-        "Float"
-    );
-    test_by_display_str(
-        "char(10)", 4,
-        AttrType::parse,
-        "Char(10)"
-    );
-}
-
-#[test]
-fn test_attr_decl_list() {
-    test_by_list_to_str(
-        "title char(233) not null, content char(666)", 13,
-        AttributeDeclaration::parse_list,
-        "(title Char(233) not null), (content Char(666) null)"
-    );
-}
-
-#[test]
-fn test_create_statement_parse() {
-    test_by_display_str(
-        "create table dept (\
-            id int primary,\
-            name char(666) not null\
-        )", 16,
-        CreateStatement::parse,
-        "create table dept ((id Int null primary), (name Char(666) not null))"
-    )
-}
+use ::parser::common::exp_list_to_string;
+use ::parser::select::{SelectExpr, Relation, GroupbyHaving, SelectStatement, RelationList};
+use ::parser::attribute::AttributeExpr;
+use ::parser::update::{AssignExpr, UpdateStatement};
+use ::parser::insert::InsertStatement;
+use ::parser::delete::DeleteStatement;
+use ::parser::create_drop::{DropStatement, AttributeDeclaration, CreateStatement, AttrType};
+use ::parser::copy::{CopyFromStatement, CopyToStatement};
+use ::parser::alter::AlterStatement;
+use ::parser::backup::{BackupStatement, RestoreStatement};
+use ::parser::compile_error::CompileErrorType;
+use ::parser::analyze::AnalyzeStatement;
+use super::super::utils::{test_by_display_str, test_by_list_to_str};
+
+#[test]
+fn test_parse_select_expr() {
+    test_by_display_str(
+        "select *", 2,
+        SelectExpr::parse,
+        "select *"
+    );
+    test_by_display_str(
+        "select tab.*", 4,
+        SelectExpr::parse,
+        "select tab.*"
+    );
+    test_by_display_str(
+        "select tab.attribute_name", 4,
+        SelectExpr::parse,
+        "select (tab.attribute_name)"
+    );
+    test_by_display_str(
+        "select attribute_name", 2,
+        SelectExpr::parse,
+        "select attribute_name"
+    );
+    test_by_display_str(
+        "select a1, a2, a3", 6,
+        SelectExpr::parse,
+        "select a1, a2, a3"
+    );
+}
+
+#[test]
+fn test_parse_relation() {
+    test_by_list_to_str(
+        "from table_name", 2,
+        Relation::parse,
+        "table_name"
+    );
+    test_by_list_to_str(
+        "from tb1, tb2, tb3", 6,
+        Relation::parse,
+        "tb1, tb2, tb3"
+    );
+    test_by_list_to_str(
+        "from (select * from tab)", 7,
+        Relation::parse,
+        "(select * from tab)"
+    );
+    test_by_list_to_str(
+        "from tab, (select * from tab)", 9,
+        Relation::parse,
+        "tab, (select * from tab)"
+    );
+}
+
+#[test]
+fn test_parse_group_by_having() {
+    test_by_display_str(
+        "group by attribute", 3,
+        GroupbyHaving::parse,
+        "group by attribute"
+    );
+    test_by_display_str(
+        "group by tab.attribute having dept.employee > 1", 11,
+        GroupbyHaving::parse,
+        "group by (tab.attribute) having ((dept.employee) > Integer(1))"
+    );
+}
+
+#[test]
+fn test_parse_select_statement() {
+    test_by_display_str(
+        "select sum(employee) from table_name where tab.money > 0\
+        group by huang.guangxing having dept.number > 1 order by doyoubi",
+        27,
+        SelectStatement::parse,
+        "select sum(employee) from table_name where ((tab.money) > Integer(0)) \
+        group by (huang.guangxing) having ((dept.number) > Integer(1)) order by doyoubi"
+    );
+    test_by_display_str(
+        "select tab.attr from huang group by doyoubi", 9,
+        SelectStatement::parse,
+        "select (tab.attr) from huang group by doyoubi"
+    );
+    test_by_display_str(
+        "select attr from huang where doyoubi is not null", 8,
+        SelectStatement::parse,
+        "select attr from huang where (doyoubi is not Null(null))"
+    );
+    test_by_display_str(
+        "select attr from huang order by doyoubi", 7,
+        SelectStatement::parse,
+        "select attr from huang order by doyoubi"
+    );
+    test_by_display_str(
+        "select attr from huang group by attr order by doyoubi", 10,
+        SelectStatement::parse,
+        "select attr from huang group by attr order by doyoubi"
+    );
+}
+
+#[test]
+fn test_parse_assign() {
+    test_by_list_to_str(
+        "abc = 1", 3,
+        AssignExpr::parse,
+        "(abc = Integer(1))"
+    );
+}
+
+#[test]
+fn test_parse_assign_list() {
+    test_by_list_to_str(
+        "a = 1, b = 2", 7,
+        AssignExpr::parse,
+        "(a = Integer(1)), (b = Integer(2))"
+    );
+}
+
+#[test]
+fn test_udpate_statement_parse() {
+    test_by_display_str(
+        "update tab set a = 1", 6,
+        UpdateStatement::parse,
+        "update tab set (a = Integer(1))"
+    );
+    test_by_display_str(
+        "update tab set a = 1, b = \"string\" where a > 1", 14,
+        UpdateStatement::parse,
+        "update tab set (a = Integer(1)), (b = String(string)) where (a > Integer(1))"
+    );
+}
+
+#[test]
+fn test_insert_statement_parse() {
+    test_by_display_str(
+        "insert tab values(1)", 6,
+        InsertStatement::parse,
+        "insert tab values(Integer(1))"
+    );
+    test_by_display_str(
+        "insert tab values(1, null)", 8,
+        InsertStatement::parse,
+        "insert tab values(Integer(1), Null(null))"
+    );
+}
+
+#[test]
+fn test_delete_statement_parse() {
+    test_by_display_str(
+        "delete from tab", 3,
+        DeleteStatement::parse,
+        "delete from tab"
+    );
+    test_by_display_str(
+        "delete from tab where a > 1", 7,
+        DeleteStatement::parse,
+        "delete from tab where (a > Integer(1))"
+    );
+}
+
+#[test]
+fn test_drop_statement_parse() {
+    test_by_display_str(
+        "drop table dept", 3,
+        DropStatement::parse,
+        "drop table dept"
+    );
+}
+
+#[test]
+fn test_attribute_declaration_parse() {
+    test_by_display_str(
+        "name char(1) not null", 7,
+        AttributeDeclaration::parse_decl,
+        "(name Char(1) not null)"
+    );
+    test_by_display_str(
+        "name char(1) null", 6,
+        AttributeDeclaration::parse_decl,
+        "(name Char(1) null)"
+    );
+    test_by_display_str(
+        "name char(1) primary", 6,
+        AttributeDeclaration::parse_decl,
+        "(name Char(1) null primary)"
+    );
+}
+
+#[test]
+fn test_attr_type_parse() {
+    test_by_display_str(
+        "int", 1,
+        AttrType::parse,
+        "Int"
+    );
+    test_by_display_str(
+        "float", 1,
+        AttrType::parse,
+        "Float"
+    );
+    test_by_display_str(
+        "char(10)", 4,
+        AttrType::parse,
+        "Char(10)"
+    );
+}
+
+#[test]
+fn test_attr_decl_list() {
+    test_by_list_to_str(
+        "title char(233) not null, content char(666)", 13,
+        AttributeDeclaration::parse_list,
+        "(title Char(233) not null), (content Char(666) null)"
+    );
+}
+
+#[test]
+fn test_create_statement_parse() {
+    test_by_display_str(
+        "create table dept (\
+            id int primary,\
+            name char(666) not null\
+        )", 16,
+        CreateStatement::parse,
+        "create table dept ((id Int null primary), (name Char(666) not null))"
+    );
+    test_by_display_str(
+        "create table dept (id int primary) with compression", 10,
+        CreateStatement::parse,
+        "create table dept ((id Int null primary)) with compression"
+    );
+    test_by_display_str(
+        "create table dept (id int primary) with fill_factor 70 compression", 12,
+        CreateStatement::parse,
+        "create table dept ((id Int null primary)) with compression fill_factor 70"
+    );
+    test_by_display_str(
+        "create table item (id int primary, twice int generated as (num * 2))", 18,
+        CreateStatement::parse,
+        "create table item ((id Int null primary), (twice Int null generated as (num * Integer(2))))"
+    );
+}
+
+#[test]
+fn test_copy_from_statement_parse() {
+    test_by_display_str(
+        "copy author from 'data/author.csv'", 4,
+        CopyFromStatement::parse,
+        "copy author from \"data/author.csv\" with delimiter \",\""
+    );
+    test_by_display_str(
+        "copy author from \"author.csv\" with header delimiter ';' empty_as_null max_errors 3", 11,
+        CopyFromStatement::parse,
+        "copy author from \"author.csv\" with header delimiter \";\" empty_as_null max_errors 3"
+    );
+    test_by_display_str(
+        "copy author from STDIN with header", 6,
+        CopyFromStatement::parse,
+        "copy author from stdin with header delimiter \",\""
+    );
+    let tokens = gen_token!("copy author from 'author.csv' with headers");
+    assert_pattern!(CopyFromStatement::parse(&mut tokens.iter()), Err(..));
+    let tokens = gen_token!("copy author from 'author.csv' with max_errors");
+    assert_pattern!(CopyFromStatement::parse(&mut tokens.iter()), Err(..));
+    // the delimiter is a single character
+    let tokens = gen_token!("copy author from 'author.csv' with delimiter ''");
+    let errs = extract!(CopyFromStatement::parse(&mut tokens.iter()), Err(errs), errs);
+    assert_eq!(errs[0].error_type, CompileErrorType::ParserInvalidCopyOption);
+    let tokens = gen_token!("copy author from 'author.csv' with delimiter ';;'");
+    let errs = extract!(CopyFromStatement::parse(&mut tokens.iter()), Err(errs), errs);
+    assert_eq!(errs[0].error_type, CompileErrorType::ParserInvalidCopyOption);
+}
+
+#[test]
+fn test_copy_to_statement_parse() {
+    test_by_display_str(
+        "copy (select a, b from author where a > 1) to 'out.csv' with header overwrite", 18,
+        CopyToStatement::parse,
+        "copy (select a, b from author where (a > Integer(1))) to \"out.csv\" with header delimiter \",\" overwrite"
+    );
+    let tokens = gen_token!("copy (select * from author) into 'out.csv'");
+    assert_pattern!(CopyToStatement::parse(&mut tokens.iter()), Err(..));
+    let tokens = gen_token!("copy (select * from author) to 'out.csv' with max_errors 1");
+    assert_pattern!(CopyToStatement::parse(&mut tokens.iter()), Err(..));
+}
+
+#[test]
+fn test_backup_statement_parse() {
+    test_by_display_str(
+        "backup to 'backup/2016'", 3,
+        BackupStatement::parse,
+        "backup to \"backup/2016\""
+    );
+    let tokens = gen_token!("backup 'backup/2016'");
+    assert_pattern!(BackupStatement::parse(&mut tokens.iter()), Err(..));
+    let tokens = gen_token!("backup to 'backup/2016' with overwrite");
+    assert_pattern!(BackupStatement::parse(&mut tokens.iter()), Err(..));
+}
+
+#[test]
+fn test_restore_statement_parse() {
+    test_by_display_str(
+        "restore from 'backup/2016'", 3,
+        RestoreStatement::parse,
+        "restore from \"backup/2016\""
+    );
+    test_by_display_str(
+        "restore from 'backup/2016' with force", 5,
+        RestoreStatement::parse,
+        "restore from \"backup/2016\" with force"
+    );
+    let tokens = gen_token!("restore from 'backup/2016' with overwrite");
+    assert_pattern!(RestoreStatement::parse(&mut tokens.iter()), Err(..));
+}
+
+#[test]
+fn test_analyze_statement_parse() {
+    test_by_display_str(
+        "analyze author", 2,
+        AnalyzeStatement::parse,
+        "analyze author"
+    );
+    let tokens = gen_token!("analyze table author");
+    assert_pattern!(AnalyzeStatement::parse(&mut tokens.iter()), Err(..));
+    let tokens = gen_token!("analyze");
+    assert_pattern!(AnalyzeStatement::parse(&mut tokens.iter()), Err(..));
+}
+
+#[test]
+fn test_alter_statement_parse() {
+    test_by_display_str(
+        "alter table author add age int", 6,
+        AlterStatement::parse,
+        "alter table author add (age Int null)"
+    );
+    test_by_display_str(
+        "alter table author drop age", 5,
+        AlterStatement::parse,
+        "alter table author drop age"
+    );
+    test_by_display_str(
+        "alter table author modify name char(20) not null", 11,
+        AlterStatement::parse,
+        "alter table author modify (name Char(20) not null)"
+    );
+    let tokens = gen_token!("alter table author rename age");
+    assert_pattern!(AlterStatement::parse(&mut tokens.iter()), Err(..));
+    let tokens = gen_token!("alter table author add age");
+    assert_pattern!(AlterStatement::parse(&mut tokens.iter()), Err(..));
+}
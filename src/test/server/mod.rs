@@ -1,6 +1,27 @@
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_handler;
-#[allow(dead_code)]
-#[allow(unused_imports)]
-mod test_buf;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_handler;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_buf;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_json_result;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_server;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_format_result;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_query_log;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_mysql;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_http;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_database;
@@ -0,0 +1,154 @@
+use std::fs::{remove_dir_all, metadata};
+use ::{Database, Row, TupleValue, AttrType, Summary, StatementKind};
+
+
+const CONFIG : &'static str = r#"
+    max_memory_pool_page_num = 4
+    table_meta_dir = "test_file/test_database/table_meta/"
+    table_file_dir = "test_file/test_database/table_file/""#;
+
+#[test]
+fn test_database() {
+    remove_dir_all("test_file/test_database/").ok();
+    let mut db = check_ok!(Database::open(CONFIG));
+    let result = check_ok!(db.execute("create table database_msg(id int not null primary, content char(8))"));
+    assert!(result.columns.is_empty());
+    assert_eq!(result.summary, None);
+    for sql in vec!["insert database_msg values(1, \"one\")", "insert database_msg values(2, \"two\")"] {
+        let result = check_ok!(db.execute(sql));
+        assert_eq!(result.summary, Some(Summary::new(StatementKind::Insert, 1)));
+    }
+
+    let result = check_ok!(db.execute("select * from database_msg"));
+    assert_eq!(result.columns.len(), 2);
+    assert_eq!(result.columns[1].name, "content");
+    assert_pattern!(result.columns[1].attr_type, AttrType::Char{ len : 8 });
+    assert_eq!(result.summary, Some(Summary::new(StatementKind::Select, 2)));
+    let rows = check_ok!(db.query("select id from database_msg"));
+    let ids : Vec<TupleValue> = rows.map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![TupleValue::Int(1), TupleValue::Int(2)]);
+
+    let result = check_ok!(db.execute("update database_msg set content = \"uno\" where id = 1"));
+    assert_eq!(result.summary, Some(Summary::new(StatementKind::Update, 1)));
+    let err = db.execute("insert database_msg values(1, \"again\")").unwrap_err();
+    assert_eq!(err.code(), 2000);  // PrimaryKeyExist
+    let err = db.query("select * from not_exist").unwrap_err();
+    assert!(err.to_string().contains("not_exist"));
+    db.close();
+
+    // saved when closed
+    let mut db = check_ok!(Database::open(CONFIG));
+    let rows : Vec<Row> = check_ok!(db.query("select * from database_msg where id = 1")).collect();
+    assert_eq!(rows, vec![vec![TupleValue::Int(1), TupleValue::Char("uno".to_string())]]);
+    let result = check_ok!(db.execute("delete from database_msg where id > 0"));
+    assert_eq!(result.summary, Some(Summary::new(StatementKind::Delete, 2)));
+    check_ok!(db.execute("drop table database_msg"));
+    drop(db);
+
+    let mut db = check_ok!(Database::open(CONFIG));
+    assert!(db.execute("select * from database_msg").is_err());
+}
+
+#[test]
+fn test_database_drop_all() {
+    let config = r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_database_drop_all/table_meta/"
+        table_file_dir = "test_file/test_database_drop_all/table_file/""#;
+    remove_dir_all("test_file/test_database_drop_all/").ok();
+    let mut db = check_ok!(Database::open(config));
+    check_ok!(db.execute("create table drop_all_msg(id int not null primary, content char(8))"));
+    check_ok!(db.execute("insert drop_all_msg values(1, \"one\")"));
+    db.drop_all();
+    // nothing else was in the dirs
+    assert!(metadata("test_file/test_database_drop_all/table_meta/").is_err());
+    assert!(metadata("test_file/test_database_drop_all/table_file/").is_err());
+
+    let mut db = check_ok!(Database::open(config));
+    assert!(db.execute("select * from drop_all_msg").is_err());
+}
+
+#[test]
+fn test_database_max_result_rows() {
+    let config = r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_database_max_result_rows/table_meta/"
+        table_file_dir = "test_file/test_database_max_result_rows/table_file/""#;
+    remove_dir_all("test_file/test_database_max_result_rows/").ok();
+    let mut db = check_ok!(Database::open(config));
+    check_ok!(db.execute("create table max_rows_msg(id int not null primary)"));
+    for i in 0..3 {
+        check_ok!(db.execute(&format!("insert max_rows_msg values({})", i)));
+    }
+
+    // exactly at the limit
+    check_ok!(db.execute("set max_result_rows = 3"));
+    let result = check_ok!(db.execute("select * from max_rows_msg"));
+    assert_eq!(result.rows.len(), 3);
+    assert!(!result.truncated);
+    assert!(result.warnings.is_empty());
+    // one over the limit
+    check_ok!(db.execute("set max_result_rows = 2"));
+    let result = check_ok!(db.execute("select * from max_rows_msg"));
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.summary, Some(Summary::new(StatementKind::Select, 2)));
+    assert!(result.truncated);
+    assert_eq!(result.warnings, vec!["result truncated to max_result_rows 2".to_string()]);
+    // the other statements are not limited
+    let result = check_ok!(db.execute("delete from max_rows_msg where id > 0"));
+    assert_eq!(result.summary, Some(Summary::new(StatementKind::Delete, 2)));
+
+    check_ok!(db.execute("insert max_rows_msg values(1)"));
+    check_ok!(db.execute("insert max_rows_msg values(2)"));
+    check_ok!(db.execute("set max_result_rows = 0"));
+    let result = check_ok!(db.execute("select * from max_rows_msg"));
+    assert_eq!(result.rows.len(), 3);
+    assert!(!result.truncated);
+    db.drop_all();
+}
+
+#[test]
+fn test_database_with() {
+    let config = r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_database_with/table_meta/"
+        table_file_dir = "test_file/test_database_with/table_file/""#;
+    remove_dir_all("test_file/test_database_with/").ok();
+    let mut db = check_ok!(Database::open(config));
+    check_ok!(db.execute("create table with_score(id int not null primary, score int, name char(8))"));
+    for &(id, score, name) in [(1, 50, "a"), (2, 150, "b"), (3, 200, "c"), (4, 120, "d")].iter() {
+        check_ok!(db.execute(&format!("insert with_score values({}, {}, '{}')", id, score, name)));
+    }
+
+    let rows : Vec<Row> = check_ok!(db.query(
+        "with high as (select * from with_score where score > 100) select id from high where id < 4")).collect();
+    assert_eq!(rows, vec![vec![TupleValue::Int(2)], vec![TupleValue::Int(3)]]);
+
+    // high is used by both of the with tables after it
+    let result = check_ok!(db.execute("with high as (select id, name from with_score where score > 100), \
+        top as (select * from high where id > 2), low as (select id from high where id < 3) \
+        select name from top"));
+    assert_eq!(result.columns.len(), 1);
+    assert_eq!(result.columns[0].name, "name");
+    assert_eq!(result.rows, vec![vec![TupleValue::Char("c".to_string())], vec![TupleValue::Char("d".to_string())]]);
+
+    // the with table hides the table of the same name
+    let rows : Vec<Row> = check_ok!(db.query(
+        "with with_score as (select id from with_score where score < 100) select * from with_score")).collect();
+    assert_eq!(rows, vec![vec![TupleValue::Int(1)]]);
+    let err = db.query("with high as (select id from with_score) select score from high").unwrap_err();
+    assert!(err.to_string().contains("score not exist in with table high"));
+    db.drop_all();
+}
+
+#[test]
+fn test_database_invalid_config() {
+    let config = r#"
+        max_memory_pool_page_num = "four"
+        table_meta_dir = "test_file/test_database_invalid_config/table_meta/"
+        table_file_dir = "test_file/test_database_invalid_config/table_file/""#;
+    match Database::open(config) {
+        Err(err) => assert!(err.contains("max_memory_pool_page_num")),
+        Ok(..) => panic!("opened with an invalid config"),
+    }
+}
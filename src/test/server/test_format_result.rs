@@ -0,0 +1,119 @@
+use ::server::format_result::{FormatResult, OutputFormat};
+use ::server::handler::{ResultHandler, ColumnInfo, Summary, StatementKind, sql_handler};
+use ::store::table::{TableManager, AttrType};
+use ::store::tuple::TupleValue;
+use ::utils::config::Config;
+
+
+fn gen_columns() -> Vec<ColumnInfo> {
+    vec![
+        ColumnInfo{ name : "id".to_string(), attr_type : AttrType::Int, nullable : false },
+        ColumnInfo{ name : "content".to_string(), attr_type : AttrType::Char{ len : 16 }, nullable : true },
+    ]
+}
+
+fn gen_rows() -> Vec<Vec<TupleValue>> {
+    vec![
+        vec![TupleValue::Int(1), TupleValue::Char("a,b".to_string())],
+        vec![TupleValue::Int(2), TupleValue::Char("say \"hi\"".to_string())],
+        vec![TupleValue::Int(3), TupleValue::Char("line\nbreak\tand tab".to_string())],
+    ]
+}
+
+fn format_rows(format : OutputFormat, header : bool) -> String {
+    let mut result = FormatResult::new(Vec::new(), format, header);
+    result.handle_metadata(&gen_columns());
+    for row in gen_rows() {
+        result.write_row(row);
+    }
+    result.finish_rows();
+    String::from_utf8(result.get_writer().clone()).unwrap()
+}
+
+#[test]
+fn test_output_format_from_name() {
+    assert_eq!(OutputFormat::from_name("CSV"), Some(OutputFormat::Csv));
+    assert_eq!(OutputFormat::from_name("tsv"), Some(OutputFormat::Tsv));
+    assert_eq!(OutputFormat::from_name("json"), Some(OutputFormat::Json));
+    assert_eq!(OutputFormat::from_name("table"), Some(OutputFormat::Table));
+    assert_eq!(OutputFormat::from_name("xml"), None);
+}
+
+#[test]
+fn test_format_csv() {
+    assert_eq!(format_rows(OutputFormat::Csv, true),
+        "id,content\n1,\"a,b\"\n2,\"say \"\"hi\"\"\"\n3,\"line\nbreak\tand tab\"\n");
+    assert_eq!(format_rows(OutputFormat::Csv, false),
+        "1,\"a,b\"\n2,\"say \"\"hi\"\"\"\n3,\"line\nbreak\tand tab\"\n");
+}
+
+#[test]
+fn test_format_tsv() {
+    assert_eq!(format_rows(OutputFormat::Tsv, true),
+        "id\tcontent\n1\ta,b\n2\t\"say \"\"hi\"\"\"\n3\t\"line\nbreak\tand tab\"\n");
+}
+
+#[test]
+fn test_format_json() {
+    assert_eq!(format_rows(OutputFormat::Json, true),
+        "[{\"id\":1,\"content\":\"a,b\"},{\"id\":2,\"content\":\"say \\\"hi\\\"\"},\
+        {\"id\":3,\"content\":\"line\\nbreak\\tand tab\"}]\n");
+}
+
+#[test]
+fn test_format_table() {
+    assert_eq!(format_rows(OutputFormat::Table, true),
+        "id Int, content Char { len: 16 }\n[Int(1), Char(\"a,b\")]\n[Int(2), Char(\"say \\\"hi\\\"\")]\n\
+        [Int(3), Char(\"line\\nbreak\\tand tab\")]\nend\n");
+}
+
+#[test]
+fn test_format_null() {
+    let format_null = |format : OutputFormat, null_string : Option<&str>| {
+        let mut result = FormatResult::new(Vec::new(), format, false);
+        result.set_null_string(null_string.map(|s| s.to_string()));
+        result.handle_metadata(&gen_columns());
+        result.write_row(vec![TupleValue::Int(1), TupleValue::Null]);
+        result.write_row(vec![TupleValue::Int(2), TupleValue::Char("".to_string())]);
+        result.write_row(vec![TupleValue::Int(3), TupleValue::Char("NULL".to_string())]);
+        result.finish_rows();
+        String::from_utf8(result.get_writer().clone()).unwrap()
+    };
+    assert_eq!(format_null(OutputFormat::Table, None),
+        "[Int(1), NULL]\n[Int(2), Char(\"\")]\n[Int(3), Char(\"NULL\")]\nend\n");
+    // an empty string is quoted when null is an empty field
+    assert_eq!(format_null(OutputFormat::Csv, None), "1,\n2,\"\"\n3,NULL\n");
+    assert_eq!(format_null(OutputFormat::Tsv, Some("NULL")), "1\tNULL\n2\t\n3\t\"NULL\"\n");
+    assert_eq!(format_null(OutputFormat::Json, Some("NULL")),
+        "[{\"id\":1,\"content\":null},{\"id\":2,\"content\":\"\"},{\"id\":3,\"content\":\"NULL\"}]\n");
+}
+
+#[test]
+fn test_format_summary() {
+    let mut result = FormatResult::new(Vec::new(), OutputFormat::Table, true);
+    result.handle_complete(Summary::new(StatementKind::Update, 3));
+    result.handle_complete(Summary::new(StatementKind::Select, 1));
+    assert_eq!(String::from_utf8(result.get_writer().clone()).unwrap(),
+        "Query OK, 3 rows affected\n1 row in set\n");
+    // only the rows in csv
+    let mut result = FormatResult::new(Vec::new(), OutputFormat::Csv, true);
+    result.handle_complete(Summary::new(StatementKind::Insert, 1));
+    assert!(result.get_writer().is_empty());
+}
+
+#[test]
+fn test_format_query_result() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_format_query_result/"
+        table_file_dir = "test_file/table_file/test_format_query_result/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut result = FormatResult::new(Vec::new(), OutputFormat::Csv, true);
+    sql_handler(&"create table format_result(id int not null primary, content char(8))".to_string(),
+        &mut result, &manager);
+    sql_handler(&"insert format_result values(1, 'a,b')".to_string(), &mut result, &manager);
+    // nothing but the rows and the errors are written
+    assert!(result.get_writer().is_empty());
+    sql_handler(&"select * from format_result".to_string(), &mut result, &manager);
+    assert_eq!(String::from_utf8(result.get_writer().clone()).unwrap(), "id,content\n1,\"a,b\"\n");
+}
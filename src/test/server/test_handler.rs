@@ -1,45 +1,1124 @@
-use ::server::handler::{sql_handler, ResultHandler};
-use ::store::tuple::TupleData;
-use ::store::table::{TableManager, AttrType};
-use ::utils::config::Config;
-
-
-#[derive(Debug)]
-struct MockHandler {
-    pub helper_data : String,
-}
-
-impl MockHandler {
-    pub fn new() -> MockHandler {
-        // Box::new(MockHandler{ helper_data : String::new() })
-        MockHandler{ helper_data : String::new() }
-    }
-}
-
-impl ResultHandler for MockHandler {
-    fn handle_error(&mut self, err_msg : String) {
-        self.helper_data = err_msg
-    }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
-        match tuple_data {
-            Some(..) => self.helper_data.push('1'),
-            None => self.helper_data.push('0'),
-        }
-    }
-    fn set_tuple_info(&mut self, _attr_desc : Vec<AttrType>, _attr_index : Vec<usize>) {}
-    fn handle_non_query_finished(&mut self) {}
-}
-
-
-#[test]
-fn test_handler() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::make_ref(&config);
-    let mut handler = MockHandler::new();
-    let sql = "create table msg(id int not null primary)".to_string();
-    sql_handler(&sql, &mut handler, &manager);
-    assert_eq!(handler.helper_data, "");
-}
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::remove_dir_all;
+use std::collections::HashMap;
+use std::thread::{sleep, spawn, JoinHandle};
+use std::sync::mpsc::{channel, Sender};
+use std::time::{Duration, Instant};
+use ::server::handler::{sql_handler, ResultHandler, ColumnInfo, Summary, StatementKind, process_table_command};
+use ::server::handler::{PreparedStatements, process_prepare_command};
+use ::server::result_error::{ResultError, ResultErrorType, render_errors};
+use ::server::session::{Session, Batch, session_handler, parse_batch_command, is_copy_from_stdin};
+use ::server::format_result::OutputFormat;
+use ::server::processlist::{ProcessList, QueryState, run_tracked, is_process_command,
+    process_processlist_command};
+use ::parser::compile_error::CompileErrorType;
+use ::exec::error::ExecErrorType;
+use ::store::tuple::TupleValue;
+use ::store::table::{TableManager, TableManagerRef, AttrType, Privilege};
+use ::store::lock::{LockMode, lock_tables};
+use ::utils::config::Config;
+use ::utils::log::capture_logs;
+use ::utils::bench::DataGenerator;
+
+
+#[derive(Debug)]
+struct MockHandler {
+    pub helper_data : String,
+    pub columns : Option<Vec<ColumnInfo>>,
+    pub errors : Vec<ResultError>,
+    pub rows : Vec<Vec<TupleValue>>,
+    pub summary : Option<Summary>,
+}
+
+impl MockHandler {
+    pub fn new() -> MockHandler {
+        // Box::new(MockHandler{ helper_data : String::new() })
+        MockHandler{
+            helper_data : String::new(),
+            columns : None,
+            errors : Vec::new(),
+            rows : Vec::new(),
+            summary : None,
+        }
+    }
+}
+
+impl ResultHandler for MockHandler {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        self.helper_data = render_errors(&errors);
+        self.errors = errors;
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        assert!(self.columns.is_some());
+        match row {
+            Some(row) => {
+                assert_eq!(row.len(), self.columns.as_ref().unwrap().len());
+                self.helper_data.push('1');
+                self.rows.push(row);
+            }
+            None => self.helper_data.push('0'),
+        }
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.columns = Some(columns.to_vec());
+    }
+    fn handle_non_query_finished(&mut self) {}
+    fn handle_complete(&mut self, summary : Summary) {
+        if summary.kind != StatementKind::Select {
+            self.helper_data = summary.row_num.to_string();
+        }
+        self.summary = Some(summary);
+    }
+    fn handle_warning(&mut self, _msg : String) {
+        self.helper_data.push('w');
+    }
+}
+
+
+#[test]
+fn test_handler() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut handler = MockHandler::new();
+    let sql = "create table msg(id int not null primary)".to_string();
+    sql_handler(&sql, &mut handler, &manager);
+    assert_eq!(handler.helper_data, "");
+}
+
+#[test]
+fn test_handler_copy_row_count() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_copy_row_count/"
+        table_file_dir = "test_file/table_file/test_handler_copy_row_count/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut handler = MockHandler::new();
+    sql_handler(&"create table handler_copy(id int not null primary)".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "");
+    for sql in vec!["insert handler_copy values(1)", "insert handler_copy values(2)"] {
+        sql_handler(&sql.to_string(), &mut handler, &manager);
+        assert_eq!(handler.helper_data, "1");
+    }
+    let sql = "copy (select * from handler_copy) to 'test_file/handler_copy.csv' with overwrite";
+    let mut handler = MockHandler::new();
+    sql_handler(&sql.to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "2");
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::CopyTo, 2)));
+    // the rows go to the file, not the handler
+    assert_eq!(handler.columns.unwrap().len(), 0);
+}
+
+#[test]
+fn test_handler_log() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_log/"
+        table_file_dir = "test_file/table_file/test_handler_log/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    sql_handler(&"create table handler_log(id int not null primary)".to_string(), &mut MockHandler::new(), &manager);
+    let mut handler = MockHandler::new();
+    let sql = "select * from handler_log where id = 1".to_string();
+    let logs = capture_logs(|| sql_handler(&sql, &mut handler, &manager));
+    assert_eq!(handler.helper_data, "0");
+    assert_eq!(logs[0], "debug blastoise::server::handler: statement begin: select * from handler_log where id = 1");
+    assert!(logs[1].starts_with("debug blastoise::exec::gen_plan: plan: "));
+    assert!(logs[2].starts_with("debug blastoise::server::handler: statement end after "));
+    assert!(logs[2].ends_with(" us: select * from handler_log where id = 1"));
+    assert_eq!(logs.len(), 3);
+
+    // nothing is captured outside
+    sql_handler(&sql, &mut handler, &manager);
+    assert!(capture_logs(|| ()).is_empty());
+}
+
+#[test]
+fn test_handler_metadata() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_metadata/"
+        table_file_dir = "test_file/table_file/test_handler_metadata/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    for sql in vec!["create table handler_meta(id int not null primary, content char(8), score float)",
+                    "insert handler_meta values(1, \"a\", 1.5)",
+                    "insert handler_meta values(2, \"b\", 2.5)"] {
+        sql_handler(&sql.to_string(), &mut MockHandler::new(), &manager);
+    }
+
+    // follow the projection instead of the table
+    let mut handler = MockHandler::new();
+    sql_handler(&"select score, id from handler_meta".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "110");
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 2)));
+    assert_eq!(handler.rows, vec![
+        vec![TupleValue::Float(1.5), TupleValue::Int(1)],
+        vec![TupleValue::Float(2.5), TupleValue::Int(2)],
+    ]);
+    let columns = handler.columns.unwrap();
+    assert_eq!(columns.len(), 2);
+    assert_eq!(columns[0].name, "score");
+    assert_pattern!(columns[0].attr_type, AttrType::Float);
+    assert!(columns[0].nullable);
+    assert_eq!(columns[1].name, "id");
+    assert_pattern!(columns[1].attr_type, AttrType::Int);
+    assert!(!columns[1].nullable);
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_meta where id = 3".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "0");
+    let columns = handler.columns.unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_pattern!(columns[1].attr_type, AttrType::Char{ len : 8 });
+    // the file is never read
+    let mut handler = MockHandler::new();
+    sql_handler(&"select content, id from handler_meta where 1 > 2".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "0");
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 0)));
+    let names : Vec<String> = handler.columns.unwrap().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["content", "id"]);
+
+    // no column for dml, only the affected rows
+    let mut handler = MockHandler::new();
+    sql_handler(&"update handler_meta set score = 0.5 where id > 0".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "2");
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Update, 2)));
+    assert_eq!(handler.columns.unwrap().len(), 0);
+    let mut handler = MockHandler::new();
+    sql_handler(&"delete from handler_meta where id = 1".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "1");
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Delete, 1)));
+    assert_eq!(handler.columns.unwrap().len(), 0);
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"drop table handler_meta".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "");
+    assert_pattern!(handler.columns, None);
+    assert_eq!(handler.summary, None);
+}
+
+#[test]
+fn test_handler_unicode() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_unicode/"
+        table_file_dir = "test_file/table_file/test_handler_unicode/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    for sql in vec!["create table 学生(编号 int not null primary, 名字 char(6))",
+                    "insert 学生 values(1, \"张三\")"] {
+        let mut handler = MockHandler::new();
+        sql_handler(&sql.to_string(), &mut handler, &manager);
+        assert!(handler.errors.is_empty());
+    }
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"select 名字, 编号 from 学生 where 学生.名字 = \"张三\"".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "10");
+    assert_eq!(handler.rows, vec![vec![TupleValue::Char("张三".to_string()), TupleValue::Int(1)]]);
+    assert_eq!(handler.columns.unwrap()[0].name, "名字");
+
+    // the column counts chars, not the 3 bytes of each chinese char
+    let mut handler = MockHandler::new();
+    sql_handler(&"insert 学生 values(2, 1.)".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::LexerInvalidFloat));
+    assert_eq!(handler.errors[0].column, Some(21));
+    // char(6) holds 6 bytes, which is 2 chinese chars
+    let mut handler = MockHandler::new();
+    sql_handler(&"insert 学生 values(2, \"张三李\")".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors[0].error_type,
+        ResultErrorType::Compile(CompileErrorType::SemInvalidInsertCharLen));
+}
+
+#[test]
+fn test_handler_error() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_error/"
+        table_file_dir = "test_file/table_file/test_handler_error/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    sql_handler(&"create table handler_error(id int not null primary)".to_string(), &mut MockHandler::new(), &manager);
+    sql_handler(&"insert handler_error values(1)".to_string(), &mut MockHandler::new(), &manager);
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from not_exist".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 1);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemTableNotExist));
+    assert_eq!(handler.errors[0].code(), 1200);
+    assert_pattern!(handler.errors[0].column, None);
+    assert_eq!(handler.helper_data, "SemTableNotExist: table `not_exist` not exist");
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_error where id = = 1".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 4);
+    assert_eq!(handler.errors[0].code(), 1105);
+    assert_eq!(handler.errors[0].column, Some(29));
+    assert_eq!(handler.errors[0].token, Some("where".to_string()));
+    assert_eq!(handler.errors[1].code(), 1101);
+    assert_eq!(handler.errors[1].column, Some(40));
+    let lines : Vec<&str> = handler.helper_data.split('\n').collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "ParserCanNotParseLeftToken column 29 `where`: Can not parse the left tokens : where");
+
+    let mut handler = MockHandler::new();
+    sql_handler(&"insert handler_error values(1)".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 1);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Exec(ExecErrorType::PrimaryKeyExist));
+    assert_eq!(handler.errors[0].code(), 2000);
+    assert_pattern!(handler.errors[0].column, None);
+    assert_eq!(handler.helper_data, format!("PrimaryKeyExist: {}", handler.errors[0].error_msg));
+
+    // all the semantic errors are reported at once
+    let mut handler = MockHandler::new();
+    sql_handler(&"update handler_error set num = 1, id = 2 where id > \"a\"".to_string(), &mut handler, &manager);
+    let codes : Vec<u32> = handler.errors.iter().map(|e| e.code()).collect();
+    assert_eq!(codes, vec![1208, 1215, 1206]);
+    let lines : Vec<&str> = handler.helper_data.split('\n').collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "SemInvalidAttribute: num not exist or multiple found");
+
+    // the literal is rejected before any row is deleted
+    let mut handler = MockHandler::new();
+    sql_handler(&"delete from handler_error where id < 1000000000000000000000000000000000000000.0".to_string(),
+        &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 1);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemInvalidLiteral));
+    assert_eq!(handler.errors[0].code(), 1221);
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_error".to_string(), &mut handler, &manager);
+    assert_eq!(handler.rows, vec![vec![TupleValue::Int(1)]]);
+}
+
+#[test]
+fn test_handler_prepare() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_prepare/"
+        table_file_dir = "test_file/table_file/test_handler_prepare/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut prepared = PreparedStatements::new();
+    sql_handler(&"create table handler_prepare(id int not null primary, content char(8))".to_string(),
+        &mut MockHandler::new(), &manager);
+
+    let mut handler = MockHandler::new();
+    let sql = "prepare ins as insert handler_prepare values(?, ?)".to_string();
+    assert_pattern!(process_prepare_command(&sql, &mut prepared, &mut handler, &manager), Ok(()));
+    assert_eq!(handler.errors.len(), 0);
+    for i in 0..100 {
+        let mut handler = MockHandler::new();
+        let sql = format!("execute ins ({}, \"c{}\")", i, i);
+        assert_pattern!(process_prepare_command(&sql, &mut prepared, &mut handler, &manager), Ok(()));
+        assert_eq!(handler.helper_data, "1");
+    }
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_prepare".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, format!("{}0", (0..100).map(|_| "1").collect::<String>()));
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_prepare where content = \"c42\"".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "10");
+    assert_eq!(handler.rows, vec![vec![TupleValue::Int(42), TupleValue::Char("c42".to_string())]]);
+
+    // numbers become arithmetic operants in conditions
+    let sql = "prepare sel as select * from handler_prepare where id < ? and content != ?".to_string();
+    process_prepare_command(&sql, &mut prepared, &mut MockHandler::new(), &manager).unwrap();
+    let mut handler = MockHandler::new();
+    process_prepare_command(&"execute sel (3, \"c1\")".to_string(), &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.helper_data, "110");
+    assert_eq!(handler.columns.unwrap().len(), 2);
+
+    // prepared again after the ddl
+    sql_handler(&"alter table handler_prepare add score float".to_string(), &mut MockHandler::new(), &manager);
+    let mut handler = MockHandler::new();
+    process_prepare_command(&"execute sel (3, \"c1\")".to_string(), &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.helper_data, "110");
+    assert_eq!(handler.columns.unwrap().len(), 3);
+
+    // the parameters are counted before the statement is checked again
+    let mut handler = MockHandler::new();
+    process_prepare_command(&"execute ins (100)".to_string(), &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemInvalidParameterNum));
+    // which fails as the insert has no value for score now
+    let mut handler = MockHandler::new();
+    let sql = "execute ins (100, \"c100\")".to_string();
+    process_prepare_command(&sql, &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.errors[0].error_type,
+        ResultErrorType::Compile(CompileErrorType::SemInvalidInsertValuesNum));
+    let sql = "prepare ins_score as insert handler_prepare values(?, ?, ?)".to_string();
+    process_prepare_command(&sql, &mut prepared, &mut MockHandler::new(), &manager).unwrap();
+    let mut handler = MockHandler::new();
+    let sql = "execute ins_score (100, 1, 1.5)".to_string();
+    process_prepare_command(&sql, &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemInvalidInsertValueType));
+
+    let mut handler = MockHandler::new();
+    process_prepare_command(&"prepare ins as delete from handler_prepare".to_string(),
+        &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.errors[0].code(), 2102);
+    process_prepare_command(&"deallocate ins".to_string(), &mut prepared, &mut MockHandler::new(), &manager).unwrap();
+    let mut handler = MockHandler::new();
+    process_prepare_command(&"execute ins (101, \"a\")".to_string(), &mut prepared, &mut handler, &manager).unwrap();
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::PreparedStatementNotExist);
+    assert_eq!(handler.errors[0].code(), 2101);
+
+    // only prepared statements can have placeholders
+    assert_pattern!(process_prepare_command(&"select * from handler_prepare".to_string(),
+        &mut prepared, &mut MockHandler::new(), &manager), Err(()));
+    let mut handler = MockHandler::new();
+    sql_handler(&"delete from handler_prepare where id = ?".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors[0].code(), 1107);
+    assert_eq!(handler.errors[0].column, Some(40));
+}
+
+#[test]
+fn test_handler_max_result_rows() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        max_result_rows = 2
+        table_meta_dir = "test_file/table_meta/test_handler_max_result_rows/"
+        table_file_dir = "test_file/table_file/test_handler_max_result_rows/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut handler = MockHandler::new();
+    sql_handler(&"create table max_rows(id int not null primary)".to_string(), &mut handler, &manager);
+    for i in 0..3 {
+        sql_handler(&format!("insert max_rows values({})", i), &mut handler, &manager);
+        assert_eq!(handler.helper_data, "1");
+    }
+    handler.helper_data.clear();
+    sql_handler(&"select * from max_rows".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "11w0");
+    handler.helper_data.clear();
+    sql_handler(&"select * from max_rows where id > 0".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "110");
+}
+
+#[test]
+fn test_handler_close() {
+    remove_dir_all("test_file/test_handler_close/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        flush_interval_ms = 10
+        table_meta_dir = "test_file/test_handler_close/table_meta/"
+        table_file_dir = "test_file/test_handler_close/table_file/""#.to_string());
+    {
+        let manager = TableManager::make_ref(&config);
+        let mut handler = MockHandler::new();
+        sql_handler(&"create table handler_close(id int not null primary)".to_string(), &mut handler, &manager);
+        for i in 0..3 {
+            sql_handler(&format!("insert handler_close values({})", i), &mut handler, &manager);
+            assert_eq!(handler.helper_data, "1");
+        }
+        manager.borrow_mut().close();
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let mut handler = MockHandler::new();
+    sql_handler(&"select * from handler_close".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "1110");
+}
+
+fn get_status(manager : &TableManagerRef) -> HashMap<String, usize> {
+    let out = check_ok!(process_table_command(&"show status".to_string(), manager));
+    out.lines().map(|line| {
+        let fields : Vec<&str> = line.split(' ').collect();
+        assert_eq!(fields.len(), 2);
+        (fields[0].to_string(), fields[1].parse::<usize>().unwrap())
+    }).collect()
+}
+
+#[test]
+fn test_handler_show_status() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_show_status/"
+        table_file_dir = "test_file/table_file/test_handler_show_status/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut handler = MockHandler::new();
+    sql_handler(&"create table show_status(id int not null primary)".to_string(), &mut handler, &manager);
+    let before = get_status(&manager);
+    assert_eq!(before["queries"], 1);
+    for i in 0..3 {
+        sql_handler(&format!("insert show_status values({})", i), &mut handler, &manager);
+    }
+    let inserted = get_status(&manager);
+    assert_eq!(inserted["queries"], 4);
+    assert_eq!(inserted["rows_written"] - before["rows_written"], 3);
+    assert_eq!(inserted["dirty_pages"], 0);  // saved after each statement
+
+    handler.helper_data.clear();
+    sql_handler(&"select * from show_status".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "1110");
+    let selected = get_status(&manager);
+    assert_eq!(selected["queries"], 5);
+    assert_eq!(selected["rows_read"] - inserted["rows_read"], 3);
+    assert_eq!(selected["rows_written"], inserted["rows_written"]);
+    assert!(selected["page_hits"] + selected["page_misses"] > inserted["page_hits"] + inserted["page_misses"]);
+
+    sql_handler(&"delete from show_status where id = 1".to_string(), &mut handler, &manager);
+    let deleted = get_status(&manager);
+    assert_eq!(deleted["rows_written"] - selected["rows_written"], 1);
+}
+
+// slower than the timeout of a session on each row
+struct SlowHandler {
+    inner : MockHandler,
+}
+
+impl ResultHandler for SlowHandler {
+    fn handle_error(&mut self, errors : Vec<ResultError>) {
+        self.inner.handle_error(errors);
+    }
+    fn handle_row(&mut self, row : Option<Vec<TupleValue>>) {
+        sleep(Duration::from_millis(5));
+        self.inner.handle_row(row);
+    }
+    fn handle_metadata(&mut self, columns : &[ColumnInfo]) {
+        self.inner.handle_metadata(columns);
+    }
+    fn handle_non_query_finished(&mut self) {}
+    fn handle_complete(&mut self, summary : Summary) {
+        self.inner.handle_complete(summary);
+    }
+    fn handle_warning(&mut self, msg : String) {
+        self.inner.handle_warning(msg);
+    }
+}
+
+#[test]
+fn test_handler_session() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_handler_session/"
+        table_file_dir = "test_file/table_file/test_handler_session/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut first = Session::new(1);
+    let mut second = Session::new(2);
+    first.query_timeout_ms = Some(1);
+    session_handler(&"create table handler_session(id int not null primary)".to_string(),
+        &mut first, &mut MockHandler::new(), &manager);
+    for i in 0..5 {
+        session_handler(&format!("insert handler_session values({})", i),
+            &mut second, &mut MockHandler::new(), &manager);
+    }
+
+    // the same name in both sessions
+    let mut handler = MockHandler::new();
+    session_handler(&"prepare sel as select * from handler_session where id < ?".to_string(),
+        &mut first, &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 0);
+    let mut handler = MockHandler::new();
+    session_handler(&"execute sel (2)".to_string(), &mut second, &mut handler, &manager);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::PreparedStatementNotExist);
+    session_handler(&"prepare sel as select * from handler_session where id > ?".to_string(),
+        &mut second, &mut MockHandler::new(), &manager);
+    let mut handler = MockHandler::new();
+    session_handler(&"execute sel (2)".to_string(), &mut second, &mut handler, &manager);
+    assert_eq!(handler.helper_data, "110");
+    session_handler(&"deallocate sel".to_string(), &mut second, &mut MockHandler::new(), &manager);
+    let mut handler = MockHandler::new();
+    session_handler(&"execute sel (4)".to_string(), &mut first, &mut handler, &manager);
+    assert_eq!(handler.helper_data, "11110");
+
+    // only the first session has a timeout
+    let mut handler = SlowHandler{ inner : MockHandler::new() };
+    session_handler(&"select * from handler_session".to_string(), &mut first, &mut handler, &manager);
+    assert_eq!(handler.inner.errors[0].error_type, ResultErrorType::Exec(ExecErrorType::QueryCancelled));
+    let mut handler = SlowHandler{ inner : MockHandler::new() };
+    session_handler(&"select * from handler_session".to_string(), &mut second, &mut handler, &manager);
+    assert_eq!(handler.inner.helper_data, "111110");
+
+    // the prepared statements are freed when the client disconnects
+    first.close();
+    let mut handler = MockHandler::new();
+    session_handler(&"execute sel (4)".to_string(), &mut first, &mut handler, &manager);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::PreparedStatementNotExist);
+}
+
+#[test]
+fn test_session_variables() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        query_timeout_ms = 100
+        table_meta_dir = "test_file/table_meta/test_session_variables/"
+        table_file_dir = "test_file/table_file/test_session_variables/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut session = Session::new(1);
+    let show = |session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&"show variables".to_string(), session, &mut handler, &manager);
+        assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 5)));
+        handler.rows.iter().map(|row| format!("{}={}", row[0], row[1])).collect::<Vec<String>>()
+    };
+    assert_eq!(show(&mut session),
+        vec!["autocommit=true", "max_result_rows=0",
+            "null_string=NULL", "output_format=table", "statement_timeout=100"]);
+
+    for sql in vec!["set output_format = 'csv'", "set Statement_Timeout = 5000", "set autocommit = on"] {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), &mut session, &mut handler, &manager);
+        assert_eq!(handler.errors.len(), 0);
+    }
+    assert_eq!(session.output_format, OutputFormat::Csv);
+    assert_eq!(session.query_timeout_ms, Some(5000));
+    assert_eq!(show(&mut session),
+        vec!["autocommit=true", "max_result_rows=0",
+            "null_string=", "output_format=csv", "statement_timeout=5000"]);
+    session_handler(&"set null_string = '-'".to_string(), &mut session, &mut MockHandler::new(), &manager);
+    assert_eq!(session.null_string, Some("-".to_string()));
+
+    let errors = vec![
+        ("set not_exist = 1", ResultErrorType::UnknownVariable),
+        ("set output_format = xml", ResultErrorType::InvalidVariableValue),
+        ("set statement_timeout = 86400001", ResultErrorType::InvalidVariableValue),
+        ("set statement_timeout = 1.5", ResultErrorType::InvalidVariableValue),
+        ("set autocommit = false", ResultErrorType::InvalidVariableValue),
+        ("set null_string = 'longer than sixteen'", ResultErrorType::InvalidVariableValue),
+        ("set autocommit", ResultErrorType::Compile(CompileErrorType::ParserNoMoreToken)),
+        ("set autocommit = on off", ResultErrorType::Compile(CompileErrorType::ParserCanNotParseLeftToken)),
+    ];
+    for (sql, error_type) in errors.into_iter() {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), &mut session, &mut handler, &manager);
+        assert_eq!(handler.errors[0].error_type, error_type);
+    }
+    // unchanged by the failed ones
+    assert_eq!(show(&mut session),
+        vec!["autocommit=true", "max_result_rows=0",
+            "null_string=-", "output_format=csv", "statement_timeout=5000"]);
+}
+
+#[test]
+fn test_flush() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 8"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_flush")));
+    let mut session = Session::new(1);
+    let mut handler = MockHandler::new();
+    session_handler(&"create table flush(id int not null primary)".to_string(), &mut session, &mut handler,
+        &manager);
+    // not saved like an insert statement
+    let table = manager.borrow().get_table("flush").unwrap().borrow().clone();
+    DataGenerator::new(&table, 233).load_into(&manager, 10);
+    assert!(manager.borrow().file_manager.get_dirty_page_num() > 0);
+
+    let mut handler = MockHandler::new();
+    session_handler(&"flush".to_string(), &mut session, &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 0);
+    assert_eq!(handler.summary, None);
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 0);
+}
+
+#[test]
+fn test_show_buffer() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        flush_interval_ms = 0"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_show_buffer")));
+    let mut session = Session::new(1);
+    let run = |sql : &str, session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), session, &mut handler, &manager);
+        handler
+    };
+    run("create table show_buffer(id int not null primary, content char(40))", &mut session);
+    for i in 0..20 {
+        run(&format!("insert show_buffer values({}, \"row\")", i), &mut session);
+    }
+    let page_sum = manager.borrow_mut().file_manager.get_file(&"show_buffer".to_string()).borrow().page_sum;
+    assert!(page_sum > 2);
+
+    let handler = run("show buffer", &mut session);
+    assert_eq!(handler.errors.len(), 0);
+    let names : Vec<String> = handler.columns.unwrap().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["table", "page_index", "pin_count", "dirty", "last_access"]);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 2)));
+    // the primary key check scans all the pages before the last one is inserted into
+    assert_eq!(handler.rows[0][0], TupleValue::Char("show_buffer".to_string()));
+    assert_eq!(handler.rows[0][1], TupleValue::Int(page_sum as i32 - 1));
+    assert_eq!(handler.rows[1][1], TupleValue::Int(page_sum as i32 - 2));
+    for row in handler.rows.iter() {
+        // saved after the insert and unpinned after the scan
+        assert_eq!(row[2], TupleValue::Int(0));
+        assert_eq!(row[3], TupleValue::Char("false".to_string()));
+    }
+}
+
+#[test]
+fn test_show_stats() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        flush_interval_ms = 0"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_show_stats")));
+    let mut session = Session::new(1);
+    let run = |sql : &str, session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), session, &mut handler, &manager);
+        handler
+    };
+    run("create table show_stats(id int not null primary, score float null, name char(8))", &mut session);
+    for i in 0..10 {
+        run(&format!("insert show_stats values({}, {}.0, \"n{}\")", i, i % 4, i % 3), &mut session);
+    }
+
+    let handler = run("show stats for show_stats", &mut session);
+    assert_eq!(handler.errors.len(), 0);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 0)));
+    assert_eq!(run("show stats for not_exist", &mut session).errors.len(), 1);
+
+    assert_eq!(run("analyze show_stats", &mut session).errors.len(), 0);
+    let handler = run("show stats for show_stats", &mut session);
+    assert_eq!(handler.errors.len(), 0);
+    let names : Vec<String> = handler.columns.unwrap().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["column", "row_num", "distinct_num", "null_fraction", "min", "max", "histogram"]);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 3)));
+    assert_eq!(handler.rows[0][..6].to_vec(), vec![TupleValue::Char("id".to_string()), TupleValue::Int(10),
+        TupleValue::Int(10), TupleValue::Float(0.0), TupleValue::Char("0".to_string()),
+        TupleValue::Char("9".to_string())]);
+    // 0 of the nullable score can't be told from null
+    assert_eq!(handler.rows[1][2], TupleValue::Int(4));
+    assert_eq!(handler.rows[1][3], TupleValue::Float(0.3));
+    assert_eq!(handler.rows[1][6], TupleValue::Char("0 0 0 0 1 1 1 2 2 3 3".to_string()));
+    assert_eq!(handler.rows[2][4], TupleValue::Char("n0".to_string()));
+    assert_eq!(handler.rows[2][6], TupleValue::Char(String::new()));
+}
+
+#[test]
+fn test_show_namespace() {
+    let config = Config::new(&"flush_interval_ms = 0".to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_show_namespace")));
+    let mut handler = MockHandler::new();
+    let version = |manager : &TableManagerRef| {
+        check_ok!(process_table_command(&"show namespace version".to_string(), manager))
+    };
+    let empty = manager.borrow().namespace_snapshot();
+    assert!(empty.tables.is_empty());
+    assert!(empty.keywords.contains(&"select".to_string()));
+    assert_eq!(version(&manager), format!("{}\r\n", empty.version));
+
+    sql_handler(&"create table namespace_b(id int not null primary, name char(8))".to_string(),
+        &mut handler, &manager);
+    sql_handler(&"create table namespace_a(score float not null primary)".to_string(), &mut handler, &manager);
+    assert!(handler.errors.is_empty());
+    let created = manager.borrow().namespace_snapshot();
+    assert_eq!(created.version, empty.version + 2);
+    assert_eq!(version(&manager), format!("{}\r\n", created.version));
+    let tables : Vec<(String, Vec<String>)> = created.tables.iter()
+        .map(|t| (t.name.clone(), t.columns.clone())).collect();
+    assert_eq!(tables, vec![
+        ("namespace_a".to_string(), vec!["score".to_string()]),
+        ("namespace_b".to_string(), vec!["id".to_string(), "name".to_string()])]);
+    let out = check_ok!(process_table_command(&"show namespace".to_string(), &manager));
+    assert_eq!(out, format!("{}\r\n", created.to_json()));
+
+    // the rows don't change the namespace
+    sql_handler(&"insert namespace_b values(1, \"x\")".to_string(), &mut handler, &manager);
+    assert_eq!(manager.borrow().namespace_snapshot(), created);
+
+    sql_handler(&"drop table namespace_a".to_string(), &mut handler, &manager);
+    assert!(handler.errors.is_empty());
+    let dropped = manager.borrow().namespace_snapshot();
+    assert_eq!(dropped.version, created.version + 1);
+    assert_eq!(dropped.tables.len(), 1);
+    assert_eq!(dropped.tables[0].name, "namespace_b");
+}
+
+#[test]
+fn test_explain_analyze() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_explain_analyze")));
+    let mut session = Session::new(1);
+    let run = |sql : &str, session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), session, &mut handler, &manager);
+        handler
+    };
+    run("create table explain_analyze(id int not null primary, score int null)", &mut session);
+    for i in 0..100 {
+        run(&format!("insert explain_analyze values({}, {})", i, i % 10), &mut session);
+    }
+
+    let handler = run("explain analyze select id from explain_analyze where score < 3", &mut session);
+    assert_eq!(handler.errors.len(), 0);
+    let names : Vec<String> = handler.columns.unwrap().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["operator", "calls", "rows", "time_us"]);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 3)));
+    let operators : Vec<TupleValue> = handler.rows.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(operators, vec![
+        TupleValue::Char("projection: explain_analyze.id".to_string()),
+        TupleValue::Char("  filter: explain_analyze.score < 3".to_string()),
+        TupleValue::Char("    file scan: explain_analyze".to_string()),
+    ]);
+    // the last call of each returns no row
+    let counts : Vec<(TupleValue, TupleValue)> = handler.rows.iter().map(|row| (row[1].clone(), row[2].clone()))
+        .collect();
+    assert_eq!(counts, vec![
+        (TupleValue::Int(31), TupleValue::Int(30)),
+        (TupleValue::Int(31), TupleValue::Int(30)),
+        (TupleValue::Int(101), TupleValue::Int(100)),
+    ]);
+
+    let sql = "explain analyze select id from explain_analyze where score < 3 and 1 = 2";
+    let handler = run(sql, &mut session);
+    let operators : Vec<TupleValue> = handler.rows.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(operators, vec![
+        TupleValue::Char("projection: explain_analyze.id".to_string()),
+        TupleValue::Char("  empty: explain_analyze.score < 3 and 1 = 2 is always false".to_string()),
+    ]);
+
+    // the statement is run, its result is not returned
+    let handler = run("explain analyze delete from explain_analyze where id = 1", &mut session);
+    assert_eq!(handler.rows.len(), 3);
+    assert_eq!(handler.rows[0][0], TupleValue::Char("delete: explain_analyze".to_string()));
+    assert_eq!(run("select * from explain_analyze", &mut session).rows.len(), 99);
+    let handler = run("explain analyze insert explain_analyze values(2, 2)", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Exec(ExecErrorType::PrimaryKeyExist));
+    let handler = run("explain analyze select * from not_exist", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemTableNotExist));
+}
+
+#[test]
+fn test_kill_query() {
+    let processes = ProcessList::make_ref();
+    let sql = "select * from kill_query".to_string();
+    let (processes_clone, sql_clone) = (processes.clone(), sql.clone());
+    // a long query on its own storage, like the worker of the server
+    let victim = spawn(move || {
+        let config = Config::new(&r#"
+            max_memory_pool_page_num = 2"#.to_string());
+        let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_kill_query")));
+        sql_handler(&"create table kill_query(id int not null primary)".to_string(),
+            &mut MockHandler::new(), &manager);
+        for i in 0..200 {
+            sql_handler(&format!("insert kill_query values({})", i), &mut MockHandler::new(), &manager);
+        }
+        let mut handler = SlowHandler{ inner : MockHandler::new() };
+        run_tracked(&processes_clone, 7, &sql_clone, &manager, || {
+            sql_handler(&sql_clone, &mut handler, &manager);
+        });
+        let unpinned = manager.borrow().file_manager.get_unpinned_num();
+        (handler.inner.errors, handler.inner.rows.len(), unpinned)
+    });
+
+    let mut running = processes.get_snapshot();
+    while running.is_empty() {
+        sleep(Duration::from_millis(1));
+        running = processes.get_snapshot();
+    }
+    assert_eq!(running.len(), 1);
+    assert_eq!((running[0].conn_id, &running[0].statement), (7, &sql));
+    let query_id = running[0].query_id;
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&"show processlist".to_string(), &processes, &mut handler), Ok(..));
+    let names : Vec<String> = handler.columns.unwrap().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["conn_id", "query_id", "statement", "state", "elapsed_ms"]);
+    assert_eq!(handler.rows[0][..4].to_vec(), vec![TupleValue::Int(7), TupleValue::Int(query_id as i32),
+        TupleValue::Char(sql.clone()), TupleValue::Char("executing".to_string())]);
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&format!("kill {}", query_id), &processes, &mut handler), Ok(..));
+    assert_eq!(handler.errors.len(), 0);
+
+    let (errors, row_num, unpinned) = victim.join().unwrap();
+    assert_eq!(errors[0].error_type, ResultErrorType::Exec(ExecErrorType::QueryCancelled));
+    assert_eq!(errors[0].error_msg, "query killed");
+    assert!(row_num < 200);
+    assert_eq!(unpinned, 2);
+
+    // nothing is running after it
+    assert_eq!(processes.get_snapshot().len(), 0);
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&"show processlist".to_string(), &processes, &mut handler), Ok(..));
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 0)));
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&format!("kill {}", query_id), &processes, &mut handler), Ok(..));
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::QueryNotExist);
+    assert!(!is_process_command(&"kill query".to_string()));
+}
+
+#[test]
+fn test_queued_query() {
+    let processes = ProcessList::with_limits(2, 50);
+    let (first, second) = ("select * from a".to_string(), "select * from b".to_string());
+    let first_id = processes.enqueue(1, &first).unwrap();
+    let second_id = processes.enqueue(2, &second).unwrap();
+    let error = processes.enqueue(3, &"select * from c".to_string()).unwrap_err();
+    assert_eq!(error.error_type, ResultErrorType::QueueFull);
+    // the worker takes them in order
+    let queued = processes.get_snapshot();
+    assert_eq!(queued.iter().map(|q| (q.conn_id, q.query_id, q.state)).collect::<Vec<_>>(),
+        vec![(1, first_id, QueryState::Queued), (2, second_id, QueryState::Queued)]);
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&"show processlist".to_string(), &processes, &mut handler), Ok(..));
+    assert_eq!(handler.rows[1][2..4].to_vec(), vec![TupleValue::Char(second),
+        TupleValue::Char("queued".to_string())]);
+
+    // an executing statement is not counted
+    assert!(processes.dequeue(first_id).is_ok());
+    assert_eq!(processes.get_snapshot()[0].state, QueryState::Executing);
+    let third_id = processes.enqueue(3, &"select * from c".to_string()).unwrap();
+    processes.finish(first_id);
+
+    // killed before the worker takes it
+    let mut handler = MockHandler::new();
+    is_match!(process_processlist_command(&format!("kill {}", second_id), &processes, &mut handler), Ok(..));
+    assert_eq!(handler.errors.len(), 0);
+    let error = processes.dequeue(second_id).unwrap_err();
+    assert_eq!(error.error_type, ResultErrorType::Exec(ExecErrorType::QueryCancelled));
+
+    sleep(Duration::from_millis(100));
+    let error = processes.dequeue(third_id).unwrap_err();
+    assert_eq!(error.error_type, ResultErrorType::QueueTimeout);
+    assert_eq!(processes.get_snapshot().len(), 0);
+
+    // no limit by default
+    let processes = ProcessList::new();
+    for i in 0..100 {
+        processes.enqueue(i, &first).unwrap();
+    }
+}
+
+// a scan on another thread holds the shared lock of the table until it's told to finish
+fn hold_table_lock(manager : &TableManagerRef, table : &str) -> (Sender<()>, JoinHandle<()>) {
+    let locks = manager.borrow().get_table_locks();
+    let table = table.to_string();
+    let (locked_sender, locked_receiver) = channel();
+    let (finish_sender, finish_receiver) = channel();
+    let scan = spawn(move || {
+        let _lock = lock_tables(&locks, vec![(table, LockMode::Shared)], 0).unwrap();
+        locked_sender.send(()).unwrap();
+        finish_receiver.recv().ok();
+    });
+    locked_receiver.recv().unwrap();
+    (finish_sender, scan)
+}
+
+#[test]
+fn test_table_lock() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_table_lock")));
+    let run = |sql : &str| {
+        let mut handler = MockHandler::new();
+        sql_handler(&sql.to_string(), &mut handler, &manager);
+        handler
+    };
+    run("create table table_lock(id int not null primary)");
+    run("insert table_lock values(1)");
+
+    let (finish_sender, scan) = hold_table_lock(&manager, "table_lock");
+    let handler = run("drop table table_lock");
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Exec(ExecErrorType::TableInUse));
+    assert_eq!(handler.errors[0].error_msg, "table table_lock is in use");
+    assert_eq!(run("alter table table_lock add score int null").errors.len(), 1);
+    // the other queries share the lock
+    assert_eq!(run("select * from table_lock").helper_data, "10");
+    assert_eq!(run("insert table_lock values(2)").errors.len(), 0);
+    finish_sender.send(()).unwrap();
+    is_match!(scan.join(), Ok(..));
+
+    assert_eq!(run("drop table table_lock").errors.len(), 0);
+    assert!(manager.borrow().get_table("table_lock").is_none());
+}
+
+#[test]
+fn test_table_lock_wait() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_lock_timeout_ms = 10000"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_table_lock_wait")));
+    sql_handler(&"create table table_lock_wait(id int not null primary)".to_string(),
+        &mut MockHandler::new(), &manager);
+
+    // the drop waits for the scan to finish
+    let (finish_sender, scan) = hold_table_lock(&manager, "table_lock_wait");
+    let finisher = spawn(move || {
+        sleep(Duration::from_millis(50));
+        finish_sender.send(()).unwrap();
+    });
+    let mut handler = MockHandler::new();
+    sql_handler(&"drop table table_lock_wait".to_string(), &mut handler, &manager);
+    assert_eq!(handler.errors.len(), 0);
+    assert!(manager.borrow().get_table("table_lock_wait").is_none());
+    is_match!(finisher.join(), Ok(..));
+    is_match!(scan.join(), Ok(..));
+}
+
+#[test]
+fn test_table_lock_statement_timeout() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_lock_timeout_ms = 10000"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_table_lock_statement_timeout")));
+    let mut session = Session::new(1);
+    let mut run = |sql : &str| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), &mut session, &mut handler, &manager);
+        handler
+    };
+    run("create table lock_statement_timeout(id int not null primary)");
+    run("set statement_timeout = 100");
+
+    // the wait ends at the timeout of the statement before table_lock_timeout_ms
+    let (finish_sender, scan) = hold_table_lock(&manager, "lock_statement_timeout");
+    let start = Instant::now();
+    let handler = run("drop table lock_statement_timeout");
+    let elapsed = start.elapsed();
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Exec(ExecErrorType::StatementTimeout));
+    assert_eq!(handler.errors[0].error_msg,
+        "table lock_statement_timeout is in use, statement timeout of 100 ms exceeded");
+    assert!(elapsed >= Duration::from_millis(90));
+    assert!(elapsed < Duration::from_millis(5000));
+    finish_sender.send(()).unwrap();
+    is_match!(scan.join(), Ok(..));
+    assert_eq!(run("drop table lock_statement_timeout").errors.len(), 0);
+}
+
+#[test]
+fn test_session_cursor() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_session_cursor/"
+        table_file_dir = "test_file/table_file/test_session_cursor/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut session = Session::new(1);
+    let run = |sql : &str, session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), session, &mut handler, &manager);
+        handler
+    };
+    run("create table session_cursor(id int not null primary)", &mut session);
+    for i in 0..3 {
+        run(&format!("insert session_cursor values({})", i), &mut session);
+    }
+
+    assert_eq!(run("declare c cursor for select * from session_cursor", &mut session).errors.len(), 0);
+    // the rows are copied out when the cursor is declared
+    run("insert session_cursor values(3)", &mut session);
+    let handler = run("fetch 2 from c", &mut session);
+    assert_eq!(handler.rows, vec![vec![TupleValue::Int(0)], vec![TupleValue::Int(1)]]);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 2)));
+    assert_eq!(handler.helper_data, "110");
+    let handler = run("fetch 2 from c", &mut session);
+    assert_eq!(handler.rows, vec![vec![TupleValue::Int(2)]]);
+    assert_eq!(handler.helper_data, "1w0");  // the end of the cursor
+    let handler = run("fetch 2 from c", &mut session);
+    assert_eq!(handler.summary, Some(Summary::new(StatementKind::Select, 0)));
+    assert_eq!(handler.helper_data, "w0");
+
+    let handler = run("declare c cursor for select * from session_cursor", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::CursorExist);
+    let handler = run("declare d cursor for delete from session_cursor", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::CursorNotSelect);
+    let handler = run("declare d cursor for select * from not_exist", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemTableNotExist));
+    let handler = run("fetch 0 from c", &mut session);
+    assert_eq!(handler.errors[0].error_type,
+        ResultErrorType::Compile(CompileErrorType::ParserUnExpectedTokenType));
+    assert_eq!(run("close c", &mut session).errors.len(), 0);
+    let handler = run("fetch 1 from c", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::CursorNotExist);
+
+    // dropped when the client disconnects
+    run("declare d cursor for select * from session_cursor", &mut session);
+    session.close();
+    let handler = run("close d", &mut session);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::CursorNotExist);
+}
+
+#[test]
+fn test_grant() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        admin_user = "admin""#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_grant")));
+    let session_of = |user : &str| {
+        let mut session = Session::new(1);
+        session.user = Some(user.to_string());
+        session
+    };
+    let (mut admin, mut alice, mut bob) = (session_of("admin"), session_of("alice"), session_of("bob"));
+    let run = |sql : &str, session : &mut Session| {
+        let mut handler = MockHandler::new();
+        session_handler(&sql.to_string(), session, &mut handler, &manager);
+        handler
+    };
+    let denied = |handler : MockHandler, msg : &str| {
+        assert_eq!(handler.errors.len(), 1);
+        assert_eq!(handler.errors[0].error_type, ResultErrorType::PermissionDenied);
+        assert_eq!(handler.errors[0].error_msg, msg);
+    };
+    run("create table grant_book(id int not null primary)", &mut admin);
+    run("create table grant_author(id int not null primary)", &mut admin);
+    for i in 0..3 {
+        run(&format!("insert grant_book values({})", i), &mut admin);
+    }
+    assert_eq!(run("grant select on grant_book to alice", &mut admin).errors.len(), 0);
+    assert_eq!(run("GRANT all ON grant_author TO bob", &mut admin).errors.len(), 0);
+    assert_eq!(run("grant insert, update on grant_book to bob", &mut admin).errors.len(), 0);
+
+    // the same statements as two users
+    let select = "select * from grant_book";
+    assert_eq!(run(select, &mut alice).rows.len(), 3);
+    denied(run(select, &mut bob), "permission denied for table grant_book");
+    // the tables of a with table and a sub query are checked as well
+    let with_select = "with b as (select * from grant_book) select * from b";
+    assert_eq!(run(with_select, &mut alice).rows.len(), 3);
+    denied(run(with_select, &mut bob), "permission denied for table grant_book");
+    // granted for one table only
+    denied(run("select * from grant_author", &mut alice), "permission denied for table grant_author");
+    assert_eq!(run("show stats for grant_book", &mut alice).errors.len(), 0);
+    denied(run("show stats for grant_book", &mut bob), "permission denied for table grant_book");
+    let insert = "insert grant_book values(3)";
+    denied(run(insert, &mut alice), "permission denied for table grant_book");
+    assert_eq!(run(insert, &mut bob).summary, Some(Summary::new(StatementKind::Insert, 1)));
+    denied(run("delete from grant_book", &mut bob), "permission denied for table grant_book");
+    assert_eq!(run("select * from grant_author", &mut bob).errors.len(), 0);
+
+    // ddl and the grants are only for the admin
+    denied(run("drop table grant_author", &mut bob), "permission denied for table grant_author");
+    denied(run("grant select on grant_author to bob", &mut alice), "permission denied for grant");
+    let handler = run("grant select on not_exist to alice", &mut admin);
+    assert_eq!(handler.errors[0].error_type, ResultErrorType::Compile(CompileErrorType::SemTableNotExist));
+    let handler = run("grant drop on grant_book to alice", &mut admin);
+    assert_eq!(handler.errors[0].error_type,
+        ResultErrorType::Compile(CompileErrorType::ParserUnExpectedTokenType));
+
+    // a prepared statement is checked on every execute
+    assert_eq!(run("prepare sel as select * from grant_book where id < ?", &mut alice).errors.len(), 0);
+    assert_eq!(run("execute sel (2)", &mut alice).rows.len(), 2);
+    assert_eq!(run("revoke select on grant_book from alice", &mut admin).errors.len(), 0);
+    denied(run("execute sel (2)", &mut alice), "permission denied for table grant_book");
+    denied(run(select, &mut alice), "permission denied for table grant_book");
+    let book = manager.borrow().get_table("grant_book").unwrap();
+    assert!(!book.borrow().is_granted("alice", Privilege::Select));
+    assert!(book.borrow().is_granted("bob", Privilege::Update));
+
+    // without a user such as for an embedded database
+    assert_eq!(run(select, &mut Session::new(2)).rows.len(), 4);
+}
+
+#[test]
+fn test_parse_batch_command() {
+    assert_eq!(parse_batch_command("batch select * from t; delete from t"), Some(Batch{
+        stop_on_error : false,
+        statements : vec!["select * from t".to_string(), "delete from t".to_string()],
+    }));
+    assert_eq!(parse_batch_command("batch stop_on_error delete from t;"), Some(Batch{
+        stop_on_error : true,
+        statements : vec!["delete from t".to_string()],
+    }));
+    assert_eq!(parse_batch_command("batch"), Some(Batch{ stop_on_error : false, statements : vec![] }));
+    assert_eq!(parse_batch_command("batches"), None);
+    assert_eq!(parse_batch_command("select * from batch"), None);
+}
+
+#[test]
+fn test_is_copy_from_stdin() {
+    assert!(is_copy_from_stdin("copy t from stdin"));
+    assert!(is_copy_from_stdin("COPY t FROM Stdin with header"));
+    // followed by the data even if the statement is invalid
+    assert!(is_copy_from_stdin("copy t from stdin with headers"));
+    assert!(!is_copy_from_stdin("copy t from 'stdin'"));
+    assert!(!is_copy_from_stdin("copy (select * from stdin) to 'out.csv'"));
+    assert!(!is_copy_from_stdin("select * from stdin"));
+}
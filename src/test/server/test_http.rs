@@ -0,0 +1,137 @@
+use std::net::{TcpStream, SocketAddr};
+use std::io::{Read, Write, Cursor};
+use std::fs::{File, remove_dir_all, create_dir_all};
+use std::time::Duration;
+use ::server::server::launch_server;
+use ::server::auth::gen_user_entry;
+use ::server::http::{read_request, parse_basic_auth, ChunkedWriter};
+use ::utils::base64::encode_base64;
+use ::utils::config::Config;
+
+
+// the status, the head and the body with the chunks joined
+fn http_request(addr : &SocketAddr, method : &str, path : &str, auth : Option<&str>, body : &str)
+        -> (u16, String, String) {
+    let mut stream = check_ok!(TcpStream::connect(addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    let auth_header = match auth {
+        Some(user_password) => format!("Authorization: Basic {}\r\n", encode_base64(user_password.as_bytes())),
+        None => String::new(),
+    };
+    let request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+        method, path, auth_header, body.len(), body);
+    check_ok!(stream.write_all(request.as_bytes()));
+    let mut response = String::new();
+    check_ok!(stream.read_to_string(&mut response));
+    let split = response.find("\r\n\r\n").unwrap();
+    let (head, mut body) = (response[..split].to_string(), response[split + 4..].to_string());
+    let status = head[9..12].parse().unwrap();
+    if head.contains("Transfer-Encoding: chunked") {
+        body = decode_chunked(&body);
+    }
+    (status, head, body)
+}
+
+fn decode_chunked(mut data : &str) -> String {
+    let mut body = String::new();
+    loop {
+        let line_end = data.find("\r\n").unwrap();
+        let len = usize::from_str_radix(&data[..line_end], 16).unwrap();
+        if len == 0 {
+            assert_eq!(&data[line_end..], "\r\n\r\n");
+            return body;
+        }
+        let chunk_start = line_end + 2;
+        body.push_str(&data[chunk_start..chunk_start + len]);
+        assert_eq!(&data[chunk_start + len..chunk_start + len + 2], "\r\n");
+        data = &data[chunk_start + len + 2..];
+    }
+}
+
+#[test]
+fn test_read_request() {
+    let mut input = Cursor::new(b"POST /query HTTP/1.1\r\nContent-Length: 4\r\nX-Test :  a b \r\n\r\nshow".to_vec());
+    let request = check_ok!(read_request(&mut input));
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/query");
+    assert_eq!(request.get_header("x-test"), Some("a b"));
+    assert_eq!(request.body, b"show");
+
+    let mut input = Cursor::new(b"POST /query\r\n\r\n".to_vec());
+    assert_eq!(read_request(&mut input).unwrap_err().0, 400);
+    let mut input = Cursor::new(b"POST /query HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n".to_vec());
+    assert_eq!(read_request(&mut input).unwrap_err().0, 413);
+
+    assert_eq!(parse_basic_auth("Basic YWRtaW46c2VjcmV0IHBhc3M="),
+        Some(("admin".to_string(), "secret pass".to_string())));
+    assert_eq!(parse_basic_auth("Bearer YWRtaW46c2VjcmV0IHBhc3M="), None);
+}
+
+#[test]
+fn test_chunked_writer() {
+    let mut writer = ChunkedWriter::new(Vec::new());
+    check_ok!(writer.write_all(b"abc"));
+    check_ok!(writer.flush());
+    check_ok!(writer.write_all(&vec![b'x'; 9000]));
+    let output = String::from_utf8(check_ok!(writer.finish())).unwrap();
+    assert!(output.starts_with("3\r\nabc\r\n2328\r\n"));
+    let mut expected = b"abc".to_vec();
+    expected.extend(vec![b'x'; 9000]);
+    assert_eq!(decode_chunked(&output).into_bytes(), expected);
+}
+
+#[test]
+fn test_http_query() {
+    remove_dir_all("test_file/test_http_query/").ok();
+    check_ok!(create_dir_all("test_file/test_http_query/"));
+    let mut users_file = check_ok!(File::create("test_file/test_http_query/users"));
+    check_ok!(writeln!(users_file, "{}", gen_user_entry("admin", "secret pass")));
+    drop(users_file);
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        http_enabled = true
+        http_port = 0
+        users_file = "test_file/test_http_query/users"
+        table_meta_dir = "test_file/test_http_query/table_meta/"
+        table_file_dir = "test_file/test_http_query/table_file/""#.to_string());
+    let server = launch_server(config);
+    let addr = server.http_addr.unwrap();
+    let auth = Some("admin:secret pass");
+
+    let (status, head, _) = http_request(&addr, "POST", "/query", Some("admin:wrong"), "show tables");
+    assert_eq!(status, 401);
+    assert!(head.contains("WWW-Authenticate: Basic"));
+    assert_eq!(http_request(&addr, "POST", "/query", None, "show tables").0, 401);
+    assert_eq!(http_request(&addr, "GET", "/query", auth, "").0, 405);
+    assert_eq!(http_request(&addr, "POST", "/other", auth, "").0, 404);
+
+    let (status, _, body) = http_request(&addr, "POST", "/query", auth,
+        "create table test_http(id int not null primary, name char(8))");
+    assert_eq!((status, body.as_str()), (200, "{}"));
+    for i in 0..1000 {
+        let (status, _, body) = http_request(&addr, "POST", "/query", auth,
+            &format!("insert test_http values({}, \"n{}\");", i, i));
+        assert_eq!((status, body.as_str()), (200, "{\"row_count\":1}"));
+    }
+    let (status, head, body) = http_request(&addr, "POST", "/query", auth,
+        "select * from test_http where id < 2");
+    assert_eq!(status, 200);
+    assert!(head.contains("Transfer-Encoding: chunked"));
+    assert_eq!(body, "{\"columns\":[{\"name\":\"id\",\"type\":\"Int\"},\
+        {\"name\":\"name\",\"type\":\"Char\",\"len\":8}],\"rows\":[[0,\"n0\"],[1,\"n1\"]]}");
+    // more than one chunk
+    let (status, _, body) = http_request(&addr, "POST", "/query", auth, "select * from test_http");
+    assert_eq!(status, 200);
+    assert!(body.len() > 8192);
+    assert!(body.ends_with("[999,\"n999\"]]}"));
+
+    let (status, _, body) = http_request(&addr, "POST", "/query", auth, "select * from not_exist");
+    assert_eq!(status, 400);
+    assert!(body.starts_with("{\"errors\":[{\"code\":1200,\"type\":\"SemTableNotExist\""));
+    let (status, _, body) = http_request(&addr, "POST", "/query", auth,
+        "insert test_http values(1, \"again\")");
+    assert_eq!(status, 500);
+    assert!(body.starts_with("{\"errors\":[{\"code\":2000,"));
+}
@@ -0,0 +1,60 @@
+use ::server::json_result::{JsonLayout, JsonResultWriter, ColumnList, result_to_json, tuple_value_to_json};
+use ::store::table::AttrType;
+use ::store::tuple::TupleValue;
+
+
+fn gen_columns() -> ColumnList {
+    vec![
+        ("id".to_string(), AttrType::Int),
+        ("score".to_string(), AttrType::Float),
+        ("content".to_string(), AttrType::Char{ len : 16 }),
+    ]
+}
+
+fn gen_rows() -> Vec<Vec<TupleValue>> {
+    vec![
+        vec![TupleValue::Int(1), TupleValue::Float(0.1), TupleValue::Char("say \"hi\"".to_string())],
+        vec![TupleValue::Int(-2), TupleValue::Null, TupleValue::Char("a\\b\n\t\u{1}".to_string())],
+    ]
+}
+
+#[test]
+fn test_tuple_value_to_json() {
+    assert_eq!(tuple_value_to_json(&TupleValue::Int(-233)), "-233");
+    assert_eq!(tuple_value_to_json(&TupleValue::Float(2.5)), "2.5");
+    assert_eq!(tuple_value_to_json(&TupleValue::Float(0.1)), TupleValue::Float(0.1).to_string());
+    assert_eq!(tuple_value_to_json(&TupleValue::Float(1.0 / 0.0)), "null");
+    assert_eq!(tuple_value_to_json(&TupleValue::Null), "null");
+    assert_eq!(tuple_value_to_json(&TupleValue::Char("\"\\/\r".to_string())), "\"\\\"\\\\/\\r\"");
+}
+
+#[test]
+fn test_result_to_json() {
+    assert_eq!(result_to_json(&gen_columns(), &gen_rows(), JsonLayout::Objects),
+        "[{\"id\":1,\"score\":0.1,\"content\":\"say \\\"hi\\\"\"},\
+         {\"id\":-2,\"score\":null,\"content\":\"a\\\\b\\n\\t\\u0001\"}]");
+    assert_eq!(result_to_json(&gen_columns(), &gen_rows(), JsonLayout::Envelope),
+        "{\"columns\":[{\"name\":\"id\",\"type\":\"Int\"},\
+         {\"name\":\"score\",\"type\":\"Float\"},\
+         {\"name\":\"content\",\"type\":\"Char\",\"len\":16}],\
+         \"rows\":[[1,0.1,\"say \\\"hi\\\"\"],[-2,null,\"a\\\\b\\n\\t\\u0001\"]]}");
+    assert_eq!(result_to_json(&gen_columns(), &vec![], JsonLayout::Objects), "[]");
+    assert_eq!(result_to_json(&gen_columns(), &vec![], JsonLayout::Envelope),
+        "{\"columns\":[{\"name\":\"id\",\"type\":\"Int\"},\
+         {\"name\":\"score\",\"type\":\"Float\"},\
+         {\"name\":\"content\",\"type\":\"Char\",\"len\":16}],\"rows\":[]}");
+}
+
+#[test]
+fn test_json_result_writer() {
+    let rows = gen_rows();
+    for layout in vec![JsonLayout::Objects, JsonLayout::Envelope] {
+        let mut writer = JsonResultWriter::new(Vec::new(), gen_columns(), layout).unwrap();
+        for row in rows.iter() {
+            writer.write_row(row).unwrap();
+        }
+        assert_eq!(writer.get_row_num(), 2);
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(output, result_to_json(&gen_columns(), &rows, layout));
+    }
+}
@@ -0,0 +1,133 @@
+use std::net::TcpStream;
+use std::io::Write;
+use std::fs::{File, remove_dir_all, create_dir_all};
+use std::time::Duration;
+use ::server::server::launch_server;
+use ::server::auth::gen_user_entry;
+use ::server::mysql::{PacketStream, COM_QUERY, COM_PING, COM_QUIT, TYPE_LONG, TYPE_STRING,
+    read_u16, lenenc_str, parse_handshake_response};
+use ::utils::config::Config;
+
+
+const CLIENT_CAPABILITIES : u32 = 0x200 | 0x8000 | 0x80000;  // protocol 41, secure connection, plugin auth
+
+fn gen_handshake_response(user : &str, password : &str) -> Vec<u8> {
+    let mut p = Vec::new();
+    for i in 0..4 {
+        p.push((CLIENT_CAPABILITIES >> (i * 8)) as u8);
+    }
+    p.extend_from_slice(&[0, 0, 0, 1, 33]);  // max packet size and charset
+    p.extend_from_slice(&[0u8; 23]);
+    p.extend_from_slice(user.as_bytes());
+    p.push(0);
+    p.push(password.len() as u8 + 1);
+    p.extend_from_slice(password.as_bytes());
+    p.push(0);
+    p.extend_from_slice(b"mysql_clear_password\0");
+    p
+}
+
+fn connect(addr : &::std::net::SocketAddr, user : &str, password : &str) -> (PacketStream<TcpStream>, Vec<u8>) {
+    let stream = check_ok!(TcpStream::connect(addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    let mut packets = PacketStream::new(stream);
+    let handshake = check_ok!(packets.read_packet());
+    assert_eq!(handshake[0], 10);
+    assert!(handshake.ends_with(b"mysql_clear_password\0"));
+    check_ok!(packets.write_packet(&gen_handshake_response(user, password)));
+    let reply = check_ok!(packets.read_packet());
+    (packets, reply)
+}
+
+fn send_command(packets : &mut PacketStream<TcpStream>, command : u8, arg : &str) -> Vec<u8> {
+    let mut payload = vec![command];
+    payload.extend_from_slice(arg.as_bytes());
+    packets.reset_seq();
+    check_ok!(packets.write_packet(&payload));
+    check_ok!(packets.read_packet())
+}
+
+fn err_code(packet : &Vec<u8>) -> u16 {
+    assert_eq!(packet[0], 0xff);
+    read_u16(packet, 1)
+}
+
+#[test]
+fn test_parse_handshake_response() {
+    let (user, password) = parse_handshake_response(&gen_handshake_response("admin", "pass")).unwrap();
+    assert_eq!(user, "admin");
+    assert_eq!(password, b"pass\0");
+    assert!(parse_handshake_response(b"\0\x02").is_none());
+    let mut truncated = gen_handshake_response("admin", "pass");
+    truncated.truncate(40);
+    assert!(parse_handshake_response(&truncated).is_none());
+}
+
+#[test]
+fn test_mysql_protocol() {
+    remove_dir_all("test_file/test_mysql_protocol/").ok();
+    check_ok!(create_dir_all("test_file/test_mysql_protocol/"));
+    let mut users_file = check_ok!(File::create("test_file/test_mysql_protocol/users"));
+    check_ok!(writeln!(users_file, "{}", gen_user_entry("admin", "secret")));
+    drop(users_file);
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        mysql_enabled = true
+        mysql_port = 0
+        users_file = "test_file/test_mysql_protocol/users"
+        table_meta_dir = "test_file/test_mysql_protocol/table_meta/"
+        table_file_dir = "test_file/test_mysql_protocol/table_file/""#.to_string());
+    let server = launch_server(config);
+    let addr = server.mysql_addr.unwrap();
+
+    let (_, reply) = connect(&addr, "admin", "wrong");
+    assert_eq!(err_code(&reply), 1045);
+
+    let (mut packets, reply) = connect(&addr, "admin", "secret");
+    assert_eq!(reply[0], 0);
+    assert_eq!(send_command(&mut packets, COM_PING, "")[0], 0);
+
+    let ok = send_command(&mut packets, COM_QUERY,
+        "create table test_mysql(id int not null primary, name char(8));");
+    assert_eq!(ok[0], 0);
+    for sql in vec!["insert test_mysql values(1, \"one\")", "insert test_mysql values(2, null)"] {
+        let ok = send_command(&mut packets, COM_QUERY, sql);
+        assert_eq!(&ok[..2], &[0, 1]);  // affected rows
+    }
+
+    let column_num = send_command(&mut packets, COM_QUERY, "select * from test_mysql");
+    assert_eq!(column_num, vec![2]);
+    let id_column = check_ok!(packets.read_packet());
+    assert!(id_column.starts_with(&lenenc_str(b"def")));
+    assert_eq!(id_column[id_column.len() - 6], TYPE_LONG);
+    let name_column = check_ok!(packets.read_packet());
+    assert_eq!(name_column[name_column.len() - 6], TYPE_STRING);
+    assert_eq!(check_ok!(packets.read_packet())[0], 0xfe);
+    let mut rows = Vec::new();
+    loop {
+        let packet = check_ok!(packets.read_packet());
+        if packet[0] == 0xfe && packet.len() < 9 {
+            break;
+        }
+        rows.push(packet);
+    }
+    let mut first = lenenc_str(b"1");
+    first.extend(lenenc_str(b"one"));
+    let mut second = lenenc_str(b"2");
+    second.extend(lenenc_str(b""));  // null is stored as zero, so it's read back as an empty string
+    assert_eq!(rows, vec![first, second]);
+
+    let err = send_command(&mut packets, COM_QUERY, "select * from not_exist");
+    assert_eq!(err_code(&err), 1146);
+    assert_eq!(&err[3..9], b"#42S02");
+    let err = send_command(&mut packets, COM_QUERY, "select from");
+    assert_eq!(err_code(&err), 1064);
+    let err = send_command(&mut packets, COM_QUERY,
+        "insert test_mysql values(1, \"again\")");
+    assert_eq!(err_code(&err), 1062);
+
+    packets.reset_seq();
+    check_ok!(packets.write_packet(&[COM_QUIT]));
+}
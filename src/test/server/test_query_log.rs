@@ -0,0 +1,149 @@
+use std::fs::{File, remove_dir_all, create_dir_all, metadata};
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+use std::thread::sleep;
+use rustc_serialize::json::decode;
+use ::server::query_log::{QueryLog, QueryLogs, QueryLogEntry, run_logged};
+use ::server::format_result::{FormatResult, OutputFormat};
+use ::server::handler::{sql_handler, record_slow_plan, ResultHandler};
+use ::store::table::TableManager;
+use ::store::tuple::TupleData;
+use ::exec::iter::{ExecIter, ExecIterRef};
+use ::exec::error::ExecError;
+use ::utils::config::Config;
+
+
+fn read_entries(path : &str) -> Vec<QueryLogEntry> {
+    let file = check_ok!(File::open(path));
+    BufReader::new(file).lines()
+        .map(|line| check_ok!(decode::<QueryLogEntry>(&check_ok!(line))))
+        .collect()
+}
+
+#[test]
+fn test_query_log() {
+    remove_dir_all("test_file/test_query_log/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        query_log_path = "test_file/test_query_log/query.log"
+        query_log_max_statement_len = 20
+        table_meta_dir = "test_file/test_query_log/table_meta/"
+        table_file_dir = "test_file/test_query_log/table_file/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut query_logs = QueryLogs::from_config(&config);
+    assert!(query_logs.query_log.is_some());
+    assert!(query_logs.slow_log.is_none());
+    let statements = vec![
+        "create table query_log(id int not null primary)",
+        "insert query_log values(1)",
+        "insert query_log values(2)",
+        "select * from query_log",
+        "select * from not_exist",
+    ];
+    for sql in statements.iter() {
+        let sql = sql.to_string();
+        let mut result = FormatResult::new(Vec::new(), OutputFormat::Csv, false);
+        run_logged(&mut query_logs, 7, &sql, &mut result, &manager, |result_handler| {
+            sql_handler(&sql, result_handler, &manager);
+        });
+    }
+    query_logs.query_log.unwrap().flush();
+
+    let entries = read_entries("test_file/test_query_log/query.log");
+    assert_eq!(entries.len(), 5);
+    assert!(entries.iter().all(|e| e.conn_id == 7 && e.timestamp_ms > 0));
+    assert_eq!(entries[0].statement, "create table query_l");
+    assert_eq!(entries[1].statement, "insert query_log val");
+    let rows : Vec<usize> = entries.iter().map(|e| e.rows).collect();
+    assert_eq!(rows, vec![0, 1, 1, 2, 0]);
+    let error_codes : Vec<Option<u32>> = entries.iter().map(|e| e.error_code).collect();
+    assert_eq!(&error_codes[..4], &[None, None, None, None]);
+    assert!(error_codes[4].is_some());
+}
+
+#[test]
+fn test_query_log_rotation() {
+    remove_dir_all("test_file/test_query_log_rotation/").ok();
+    check_ok!(create_dir_all("test_file/test_query_log_rotation/"));
+    let path = "test_file/test_query_log_rotation/query.log";
+    let mut query_log = QueryLog::new(path.to_string(), 1024, 300, Duration::from_millis(0));
+    let entry = QueryLogEntry{
+        timestamp_ms : 1,
+        conn_id : 1,
+        statement : "select * from t".to_string(),
+        rows : 0,
+        duration_us : 10,
+        error_code : None,
+        plan : None,
+    };
+    for _ in 0..10 {
+        query_log.append(&entry);
+    }
+    query_log.flush();
+    assert!(check_ok!(metadata(path)).len() <= 300);
+    assert!(check_ok!(metadata(format!("{}.1", path))).len() <= 300);
+    let entries = read_entries(path);
+    assert!(entries.len() > 0);
+    assert_eq!(entries[0].statement, "select * from t");
+}
+
+#[derive(Debug)]
+struct SlowIter {
+    remaining : usize,
+}
+
+impl ExecIter for SlowIter {
+    fn open(&mut self) {}
+    fn close(&mut self) {}
+    fn get_next(&mut self) -> Option<TupleData> {
+        sleep(Duration::from_millis(60));
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(Vec::new())
+    }
+    fn explain(&self) -> String { "slow iter for test".to_string() }
+    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_position(&self) -> Option<usize> { None }
+    fn get_affected_num(&self) -> usize { 0 }
+}
+
+#[test]
+fn test_slow_query_log() {
+    remove_dir_all("test_file/test_slow_query_log/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        slow_query_ms = 100
+        slow_query_log_path = "test_file/test_slow_query_log/slow.log"
+        table_meta_dir = "test_file/test_slow_query_log/table_meta/"
+        table_file_dir = "test_file/test_slow_query_log/table_file/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut query_logs = QueryLogs::from_config(&config);
+    assert!(query_logs.query_log.is_none());
+    for sql in vec!["create table slow_query(id int not null primary)", "insert slow_query values(1)"] {
+        let sql = sql.to_string();
+        let mut result = FormatResult::new(Vec::new(), OutputFormat::Csv, false);
+        run_logged(&mut query_logs, 1, &sql, &mut result, &manager, |result_handler| {
+            sql_handler(&sql, result_handler, &manager);
+        });
+    }
+    let mut result = FormatResult::new(Vec::new(), OutputFormat::Csv, false);
+    run_logged(&mut query_logs, 1, &"slow statement".to_string(), &mut result, &manager, |result_handler| {
+        let guard = manager.borrow_mut().start_query();
+        let mut plan : ExecIterRef = Box::new(SlowIter{ remaining : 2 });
+        plan.open();
+        while let Some(..) = plan.get_next() {}
+        plan.close();
+        record_slow_plan(&guard, &plan, &manager);
+        manager.borrow_mut().finish_query();
+        result_handler.handle_non_query_finished();
+    });
+    query_logs.slow_log.unwrap().flush();
+
+    let entries = read_entries("test_file/test_slow_query_log/slow.log");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].statement, "slow statement");
+    assert!(entries[0].duration_us >= 100000);
+    assert_eq!(entries[0].plan, Some("slow iter for test".to_string()));
+}
@@ -0,0 +1,366 @@
+use std::net::TcpStream;
+use std::io::{Read, Write};
+use std::fs::{File, remove_dir_all, create_dir_all, metadata};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixStream, UnixListener};
+use std::time::Duration;
+use std::thread::sleep;
+use ::server::server::start_server;
+use ::server::auth::gen_user_entry;
+use ::utils::config::Config;
+
+
+fn read_response<S : Read>(stream : &mut S, len : usize) -> Vec<u8> {
+    let mut buf = vec![0u8; len];
+    check_ok!(stream.read_exact(&mut buf));
+    buf
+}
+
+// an error result or a non-query result, until the first \r\n
+fn read_until_crlf<S : Read>(stream : &mut S) -> Vec<u8> {
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n") {
+        response.extend(read_response(stream, 1));
+    }
+    response
+}
+
+fn send_sql<S : Write>(stream : &mut S, sql : &str) {
+    check_ok!(stream.write_all(sql.as_bytes()));
+}
+
+#[test]
+fn test_server() {
+    remove_dir_all("test_file/test_server/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        table_meta_dir = "test_file/test_server/table_meta/"
+        table_file_dir = "test_file/test_server/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    let non_query_result = b"\0\0\0\0\r\n";
+
+    send_sql(&mut stream, "create table test_server_message(id int not null primary, content char(4))\n");
+    assert_eq!(read_response(&mut stream, 6), non_query_result);
+    // two statements sent at once are handled one by one
+    send_sql(&mut stream, "insert test_server_message values(1, \"ab\")\r\n\
+        insert test_server_message values(2573, \"cd\")\n");
+    let insert_result = b"\x02\0\0\0[]\x01\x01\0\0\0\r\n";  // no column, insert and one row affected
+    assert_eq!(read_response(&mut stream, 13), insert_result);
+    assert_eq!(read_response(&mut stream, 13), insert_result);
+
+    send_sql(&mut stream, "select * from test_server_message\n");
+    let header = concat!(r#"[{"name":"id","attr_type":{"type":"Int"},"nullable":false},"#,
+        r#"{"name":"content","attr_type":{"len":"4","type":"Char"},"nullable":true}]"#);
+    let mut expected = vec![header.len() as u8, 0, 0, 0];
+    expected.extend_from_slice(header.as_bytes());
+    expected.extend_from_slice(b"\x03\x02\0\0\0");  // a batch of two rows
+    expected.extend_from_slice(b"\x01\0\0\0ab\0\0");
+    expected.extend_from_slice(b"\r\n\0\0cd\0\0");  // 2573 is \r\n in bytes
+    expected.extend_from_slice(b"\0\0\x02\0\0\0\r\n");  // the end, then select and two rows
+    assert_eq!(read_response(&mut stream, expected.len()), expected);
+
+    send_sql(&mut stream, "select * from not_exist\n");
+    let response = read_response(&mut stream, 8);
+    assert_eq!(response, b"\0\0\0\0\xb0\x04\0\0");  // code of SemTableNotExist
+    let mut error_msg = Vec::new();
+    while !error_msg.ends_with(b"\r\n") {
+        error_msg.extend(read_response(&mut stream, 1));
+    }
+    assert!(String::from_utf8(error_msg).unwrap().contains("not_exist"));
+
+    // closed before reading the result, the server keeps serving the others
+    let mut closed_stream = check_ok!(TcpStream::connect(&addr));
+    send_sql(&mut closed_stream, "select * from test_server_message\n");
+    drop(closed_stream);
+
+    let mut new_stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(new_stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut new_stream, "select * from test_server_message\n");
+    assert_eq!(read_response(&mut new_stream, expected.len()), expected);
+    send_sql(&mut stream, "select * from test_server_message\n");
+    assert_eq!(read_response(&mut stream, expected.len()), expected);
+}
+
+#[test]
+fn test_server_max_connections() {
+    remove_dir_all("test_file/test_server_max_connections/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        max_connections = 2
+        table_meta_dir = "test_file/test_server_max_connections/table_meta/"
+        table_file_dir = "test_file/test_server_max_connections/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let mut streams = Vec::new();
+    for i in 0..2 {
+        let mut stream = check_ok!(TcpStream::connect(&addr));
+        check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+        // make sure the connection is accepted
+        send_sql(&mut stream, &format!("create table max_connections_{}(id int not null primary)\n", i));
+        assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+        streams.push(stream);
+    }
+
+    let mut rejected = check_ok!(TcpStream::connect(&addr));
+    check_ok!(rejected.set_read_timeout(Some(Duration::from_secs(10))));
+    let mut response = Vec::new();
+    check_ok!(rejected.read_to_end(&mut response));
+    assert_eq!(response, b"\0\0\0\0\x34\x08\0\0too many connections\r\n");
+    send_sql(&mut streams[0], "show status\n");
+    let mut stats = Vec::new();
+    while !stats.ends_with(b"\r\n") {
+        stats.extend(read_response(&mut streams[0], 1));
+    }
+    let stats = String::from_utf8(stats).unwrap();
+    assert!(stats.contains("connections 2\n"));
+    assert!(stats.contains("rejected_connections 1\n"));
+
+    // a closed connection leaves room for a new one
+    drop(streams.pop());
+    let mut response = Vec::new();
+    for _ in 0..100 {
+        let mut stream = check_ok!(TcpStream::connect(&addr));
+        check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+        send_sql(&mut stream, "create table max_connections_2(id int not null primary)\n");
+        response = vec![0u8; 6];
+        if stream.read_exact(&mut response).is_ok() && response == b"\0\0\0\0\r\n" {
+            break;
+        }
+        sleep(Duration::from_millis(10));  // the server may not have noticed the close yet
+    }
+    assert_eq!(response, b"\0\0\0\0\r\n");
+}
+
+#[test]
+fn test_server_row_batch() {
+    remove_dir_all("test_file/test_server_row_batch/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        result_batch_bytes = 64
+        max_write_buffer_bytes = 256
+        table_meta_dir = "test_file/test_server_row_batch/table_meta/"
+        table_file_dir = "test_file/test_server_row_batch/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "create table test_server_row_batch(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    for i in 0..500 {
+        send_sql(&mut stream, &format!("insert test_server_row_batch values({})\n", i));
+        assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+    }
+
+    send_sql(&mut stream, "select * from test_server_row_batch\n");
+    // the server waits for the client instead of buffering all the rows
+    sleep(Duration::from_millis(100));
+    let header = r#"[{"name":"id","attr_type":{"type":"Int"},"nullable":false}]"#;
+    let response = read_response(&mut stream, 4 + header.len());
+    assert_eq!(&response[4..], header.as_bytes());
+    let mut ids = Vec::new();
+    let mut batch_num = 0;
+    loop {
+        let tag = read_response(&mut stream, 1)[0];
+        if tag == 0 {
+            break;
+        }
+        assert_eq!(tag, 3);
+        let count = read_response(&mut stream, 4);
+        let count = count[0] as usize + ((count[1] as usize) << 8);
+        assert!(count > 0 && count * 4 <= 64);
+        for _ in 0..count {
+            let id = read_response(&mut stream, 4);
+            ids.push(id[0] as usize + ((id[1] as usize) << 8));
+        }
+        batch_num += 1;
+    }
+    assert_eq!(read_response(&mut stream, 7), b"\0\xf4\x01\0\0\r\n");  // 500 rows selected
+    assert_eq!(ids, (0..500).collect::<Vec<usize>>());
+    assert!(batch_num >= 500 * 4 / 64);
+}
+
+#[test]
+fn test_server_auth() {
+    remove_dir_all("test_file/test_server_auth/").ok();
+    check_ok!(create_dir_all("test_file/test_server_auth/"));
+    let mut users_file = check_ok!(File::create("test_file/test_server_auth/users"));
+    check_ok!(writeln!(users_file, "# name:salt:hash"));
+    check_ok!(writeln!(users_file, "{}", gen_user_entry("admin", "secret pass")));
+    drop(users_file);
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        users_file = "test_file/test_server_auth/users"
+        table_meta_dir = "test_file/test_server_auth/table_meta/"
+        table_file_dir = "test_file/test_server_auth/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let failed_result = b"\0\0\0\0\x37\x08\0\0";  // code of AuthFailed
+
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "auth admin secret pass\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    send_sql(&mut stream, "create table test_server_auth(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+
+    // the connection is closed after the error
+    for line in vec!["auth admin secret\n", "auth nobody secret pass\n",
+                     "create table test_server_auth2(id int not null primary)\n"] {
+        let mut stream = check_ok!(TcpStream::connect(&addr));
+        check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+        send_sql(&mut stream, line);
+        let mut response = Vec::new();
+        check_ok!(stream.read_to_end(&mut response));
+        assert_eq!(&response[..8], failed_result);
+        assert!(response.ends_with(b"\r\n"));
+    }
+    // the rejected create never ran
+    send_sql(&mut stream, "create table test_server_auth2(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+}
+
+#[test]
+fn test_server_shutdown() {
+    remove_dir_all("test_file/test_server_shutdown/").ok();
+    let config_str = r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        flush_interval_ms = 10
+        table_meta_dir = "test_file/test_server_shutdown/table_meta/"
+        table_file_dir = "test_file/test_server_shutdown/table_file/""#.to_string();
+    let (addr, server) = start_server(Config::new(&config_str));
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "create table server_shutdown(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    send_sql(&mut stream, "insert server_shutdown values(1)\n");
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+    send_sql(&mut stream, "shutdown\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    is_match!(server.join(), Ok(..));
+
+    // the table and the row are kept
+    let (addr, _) = start_server(Config::new(&config_str));
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "insert server_shutdown values(1)\n");
+    assert_eq!(read_response(&mut stream, 4), b"\0\0\0\0");  // duplicated primary key
+}
+
+#[test]
+fn test_server_unix_socket() {
+    remove_dir_all("test_file/test_server_unix_socket/").ok();
+    check_ok!(create_dir_all("test_file/test_server_unix_socket/"));
+    let path = "test_file/test_server_unix_socket/blastoise.sock";
+    let mut users_file = check_ok!(File::create("test_file/test_server_unix_socket/users"));
+    check_ok!(writeln!(users_file, "{}", gen_user_entry("admin", "secret")));
+    drop(users_file);
+    // left by a server that was killed
+    drop(check_ok!(UnixListener::bind(path)));
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        unix_socket_path = "test_file/test_server_unix_socket/blastoise.sock"
+        users_file = "test_file/test_server_unix_socket/users"
+        table_meta_dir = "test_file/test_server_unix_socket/table_meta/"
+        table_file_dir = "test_file/test_server_unix_socket/table_file/""#.to_string());
+    let (_, server) = start_server(config);
+    assert_eq!(check_ok!(metadata(path)).permissions().mode() & 0o777, 0o600);
+
+    let mut stream = check_ok!(UnixStream::connect(path));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "create table server_unix_socket(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 8), b"\0\0\0\0\x37\x08\0\0");  // AuthFailed
+
+    let mut stream = check_ok!(UnixStream::connect(path));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+    send_sql(&mut stream, "auth admin secret\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    send_sql(&mut stream, "create table server_unix_socket(id int not null primary)\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    send_sql(&mut stream, "insert server_unix_socket values(1)\n");
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+    send_sql(&mut stream, "shutdown\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    is_match!(server.join(), Ok(..));
+    assert!(metadata(path).is_err());
+}
+
+#[test]
+fn test_server_batch() {
+    remove_dir_all("test_file/test_server_batch/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        table_meta_dir = "test_file/test_server_batch/table_meta/"
+        table_file_dir = "test_file/test_server_batch/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+
+    // a result for each statement, the failed one does not stop the others
+    send_sql(&mut stream, "batch create table server_batch(id int not null primary, s char(4)); \
+        insert server_batch values(1, \"a;b\"); insert server_batch values(1, \"c\"); \
+        insert server_batch values(2, \"d\")\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+    let error = read_until_crlf(&mut stream);
+    assert_eq!(&error[..8], b"\0\0\0\0\xd0\x07\0\0");  // PrimaryKeyExist
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+
+    send_sql(&mut stream, "batch stop_on_error insert server_batch values(3, \"e\"); \
+        insert server_batch values(2, \"f\"); insert server_batch values(4, \"g\");\n");
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x01\x01\0\0\0\r\n");
+    let error = read_until_crlf(&mut stream);
+    assert_eq!(&error[..8], b"\0\0\0\0\xd0\x07\0\0");
+    let skipped = read_until_crlf(&mut stream);
+    assert_eq!(&skipped[..8], b"\0\0\0\0\x38\x08\0\0");  // BatchStatementSkipped
+    assert!(String::from_utf8_lossy(&skipped).contains("statement 2 of the batch failed"));
+
+    // the connection goes on after the batch
+    send_sql(&mut stream, "delete from server_batch where id > 0\n");
+    assert_eq!(read_response(&mut stream, 13), b"\x02\0\0\0[]\x03\x03\0\0\0\r\n");
+    send_sql(&mut stream, "batch\n");
+    assert_eq!(read_response(&mut stream, 6), b"\0\0\0\0\r\n");
+}
+
+#[test]
+fn test_server_processlist() {
+    remove_dir_all("test_file/test_server_processlist/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        host = "127.0.0.1"
+        port = 0
+        table_meta_dir = "test_file/test_server_processlist/table_meta/"
+        table_file_dir = "test_file/test_server_processlist/table_file/""#.to_string());
+    let (addr, _) = start_server(config);
+    let mut stream = check_ok!(TcpStream::connect(&addr));
+    check_ok!(stream.set_read_timeout(Some(Duration::from_secs(10))));
+
+    // nothing else is running, and the command itself is not in the list
+    send_sql(&mut stream, "show processlist\n");
+    let header = concat!(r#"[{"name":"conn_id","attr_type":{"type":"Int"},"nullable":false},"#,
+        r#"{"name":"query_id","attr_type":{"type":"Int"},"nullable":false},"#,
+        r#"{"name":"statement","attr_type":{"len":"256","type":"Char"},"nullable":false},"#,
+        r#"{"name":"state","attr_type":{"len":"9","type":"Char"},"nullable":false},"#,
+        r#"{"name":"elapsed_ms","attr_type":{"type":"Int"},"nullable":false}]"#);
+    let mut expected = vec![header.len() as u8, (header.len() >> 8) as u8, 0, 0];
+    expected.extend_from_slice(header.as_bytes());
+    expected.extend_from_slice(b"\0\0\0\0\0\0\r\n");  // the end, then select and no row
+    assert_eq!(read_response(&mut stream, expected.len()), expected);
+
+    send_sql(&mut stream, "kill 12345\n");
+    let error = read_until_crlf(&mut stream);
+    assert_eq!(&error[..8], b"\0\0\0\0\x3e\x08\0\0");  // QueryNotExist
+    assert!(String::from_utf8_lossy(&error).contains("query 12345 is not running"));
+}
@@ -1,12 +1,21 @@
-#[allow(dead_code)]
-#[allow(unused_imports)]  // lint bug
-mod test_lru;
-#[allow(dead_code)]
-#[allow(unused_imports)]  // lint bug
-pub mod test_buffer;
-#[allow(dead_code)]
-#[allow(unused_imports)]  // lint bug
-mod test_table;
-#[allow(dead_code)]
-#[allow(unused_imports)]  // lint bug
-mod test_file;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_lru;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+pub mod test_buffer;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_table;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_file;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_lock;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_check;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_ddl_journal;
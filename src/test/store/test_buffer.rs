@@ -1,48 +1,134 @@
-use std::boxed::Box;
-use std::option::Option::{Some, None};
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::ptr::{write, read, null_mut};
-use ::store::buffer::{Page, PagePool, DataPtr};
-
-
-#[test]
-fn test_page_pool() {
-    let mut pool = PagePool::new(1);
-    let (mut fd, mut page_index) = (11, 12);
-    assert_pattern!(pool.get_page(fd, page_index), None);
-    let ptr;
-    pool.put_page(fd, page_index, null_mut());
-    {
-        let page1 = pool.get_page(fd, page_index).unwrap();
-        let mut p1 = page1.borrow_mut();
-        assert_eq!(p1.fd, fd);
-        assert_eq!(p1.page_index, page_index);
-        assert!(!p1.data.is_null());
-        ptr = p1.data;
-        unsafe{write::<i32>(p1.data as *mut i32, 666);}
-        p1.data = null_mut();
-    }
-    pool.remove_tail();
-    pool.put_page(21, 22, ptr);
-    fd = 21;
-    page_index = 22;
-    {
-        let page2 = pool.get_page(fd, page_index).unwrap();
-        let p2 = page2.borrow();
-        assert_eq!(p2.fd, fd);
-        assert_eq!(p2.page_index, page_index);
-        assert!(!p2.data.is_null());
-        assert_eq!(p2.data, ptr);
-        let n = unsafe{read::<i32>(p2.data as *const i32)};
-        assert_eq!(n, 666);
-    }
-}
-
-#[test]
-#[should_panic]
-fn test_crash() {
-    let mut pool = PagePool::new(1);
-    pool.put_page(11, 12, null_mut());
-    pool.put_page(11, 13, null_mut());
-}
+use std::boxed::Box;
+use std::option::Option::{Some, None};
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::ptr::{write, read, null_mut};
+use ::store::buffer::{Page, PagePool, PageSnapshot, DataPtr, FileId};
+
+
+#[test]
+fn test_page_pool() {
+    let mut pool = PagePool::new(1);
+    let (mut file_id, mut page_index) = (FileId(11), 12);
+    assert_pattern!(pool.get_page(file_id, page_index), None);
+    let ptr;
+    pool.put_page(file_id, page_index, null_mut(), 64);
+    {
+        let page1 = pool.get_page(file_id, page_index).unwrap();
+        let mut p1 = page1.borrow_mut();
+        assert_eq!(p1.file_id, file_id);
+        assert_eq!(p1.page_index, page_index);
+        assert!(!p1.data.is_null());
+        ptr = p1.data;
+        unsafe{write::<i32>(p1.data as *mut i32, 666);}
+        p1.data = null_mut();
+    }
+    pool.remove_tail();
+    pool.put_page(FileId(21), 22, ptr, 64);
+    file_id = FileId(21);
+    page_index = 22;
+    {
+        let page2 = pool.get_page(file_id, page_index).unwrap();
+        let p2 = page2.borrow();
+        assert_eq!(p2.file_id, file_id);
+        assert_eq!(p2.page_index, page_index);
+        assert!(!p2.data.is_null());
+        assert_eq!(p2.data, ptr);
+        let n = unsafe{read::<i32>(p2.data as *const i32)};
+        assert_eq!(n, 666);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_crash() {
+    let mut pool = PagePool::new(1);
+    pool.put_page(FileId(11), 12, null_mut(), 64);
+    pool.put_page(FileId(11), 13, null_mut(), 64);
+}
+
+#[test]
+fn test_page_pool_snapshot() {
+    let mut pool = PagePool::new(3);
+    assert!(pool.snapshot().is_empty());
+    let file_id = FileId(31);
+    pool.put_page(file_id, 0, null_mut(), 64);  // 1
+    pool.put_page(file_id, 1, null_mut(), 64);  // 2
+    pool.put_page(file_id, 2, null_mut(), 64);  // 3
+    pool.get_page(file_id, 0);  // 4
+    pool.pin_page(file_id, 1);  // 5
+    pool.get_page(file_id, 0).unwrap().borrow_mut().mark_dirty();  // 6
+    assert_pattern!(pool.get_page(file_id, 3), None);  // a miss is not an access
+
+    let snapshot = |page_index, pin_count, dirty, last_access| PageSnapshot{
+        file_id : file_id,
+        page_index : page_index,
+        pin_count : pin_count,
+        dirty : dirty,
+        last_access : last_access,
+    };
+    assert_eq!(pool.snapshot(), vec![
+        snapshot(0, 0, true, 6),
+        snapshot(1, 1, false, 5),
+        snapshot(2, 0, false, 3),
+    ]);
+
+    // the snapshot doesn't change the order of eviction
+    pool.snapshot();
+    let tail = pool.prepare_page().unwrap();
+    assert_eq!(tail.borrow().page_index, 2);
+    pool.remove_tail();
+    pool.unpin_page(file_id, 1);  // 7
+    assert_eq!(pool.snapshot(), vec![
+        snapshot(1, 0, false, 7),
+        snapshot(0, 0, true, 6),
+    ]);
+}
+
+#[test]
+fn test_pin_count() {
+    // a scan and a probe of the same page, each unpins only its own pin
+    let mut pool = PagePool::new(2);
+    let file_id = FileId(41);
+    pool.put_page(file_id, 0, null_mut(), 64);
+    pool.put_page(file_id, 1, null_mut(), 64);
+    let pin_count = |pool : &mut PagePool, page_index| {
+        let page = pool.get_page(file_id, page_index).unwrap();
+        let pin_count = page.borrow().pin_count;
+        pin_count
+    };
+
+    pool.pin_page(file_id, 0);  // scan
+    assert_eq!(pool.get_unpinned_num(), 1);
+    pool.pin_page(file_id, 0);  // probe
+    assert_eq!(pool.get_unpinned_num(), 1);
+    assert_eq!(pin_count(&mut pool, 0), 2);
+    pool.unpin_page(file_id, 0);  // scan
+    assert_eq!(pin_count(&mut pool, 0), 1);
+    assert_eq!(pool.get_unpinned_num(), 1);
+
+    // page 0 becomes the tail, but page 1 is evicted while the probe holds page 0
+    pool.get_page(file_id, 1);
+    let tail = pool.prepare_page().unwrap();
+    assert_eq!(tail.borrow().page_index, 1);
+    pool.remove_tail();
+    pool.put_page(file_id, 2, null_mut(), 64);
+
+    pool.pin_page(file_id, 2);  // scan
+    assert_eq!(pool.get_unpinned_num(), 0);
+    pool.unpin_page(file_id, 0);  // probe
+    assert_eq!(pin_count(&mut pool, 0), 0);
+    assert_eq!(pool.get_unpinned_num(), 1);
+    let tail = pool.prepare_page().unwrap();
+    assert_eq!(tail.borrow().page_index, 0);
+}
+
+#[test]
+#[should_panic]
+fn test_unpin_below_zero() {
+    let mut pool = PagePool::new(2);
+    pool.put_page(FileId(51), 0, null_mut(), 64);
+    pool.pin_page(FileId(51), 0);
+    pool.unpin_page(FileId(51), 0);
+    pool.unpin_page(FileId(51), 0);
+}
@@ -0,0 +1,89 @@
+use std::fs::{OpenOptions, File, remove_dir_all};
+use std::path::Path;
+use ::store::table::TableManager;
+use ::store::file::{get_page_offset, read_file_header, encode_u32_list, page_checksum};
+use ::store::check::{CheckProblem, check_data_dir};
+use ::parser::common::{ValueExpr, Literal};
+use ::utils::config::Config;
+use ::utils::file::{create_temp_dir, write_all_at, read_at_most};
+use ::test::exec::test_query;
+
+
+#[test]
+fn test_check_data_dir() {
+    let dir = create_temp_dir("test_check_data_dir");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2"#.to_string());
+    let table_name = "check_msg".to_string();
+    {
+        let mut manager = TableManager::in_dir(&config, &dir);
+        manager.add_table(test_query::gen_test_table(&table_name));
+        manager.add_table(test_query::gen_test_table(&"check_empty".to_string()));
+        let mut value_list = vec![
+            ValueExpr::from_literal(Literal::Int(1)),
+            ValueExpr::from_literal(Literal::Float(1.0)),
+            ValueExpr::from_literal(Literal::Str("one".to_string())),
+        ];
+        check_ok!(manager.insert(&table_name, &value_list));
+        value_list[0] = ValueExpr::from_literal(Literal::Int(2));
+        check_ok!(manager.insert(&table_name, &value_list));
+        // a second page with only the first slot in use
+        value_list[0] = ValueExpr::from_literal(Literal::Int(3));
+        manager.file_manager.insert_in_page(&table_name, 1, &value_list);
+        manager.save_to_file();
+    }
+    let report = check_data_dir(&dir, &dir);
+    assert!(report.is_ok(), "{}", report);
+    assert_eq!((report.table_num, report.page_num, report.row_num), (2, 2, 3));
+
+    // the second slot of the second page is marked in use, while first_free_slot still points to it
+    let path = Path::new(&dir).join("check_msg.table");
+    let file = check_ok!(OpenOptions::new().read(true).write(true).open(&path));
+    let page_size = read_file_header(&file).unwrap().page_size;
+    let page_offset = get_page_offset(1, page_size);
+    let bitmap_offset = page_offset + 3 * 4;
+    let mut bitmap = [0u8; 1];
+    read_at_most(&file, &mut bitmap, bitmap_offset);
+    assert_eq!(bitmap[0], 1);
+    check_ok!(write_all_at(&file, &[3], bitmap_offset));
+    // with the checksum updated, so that the bitmap itself is checked
+    let mut page = vec![0u8; page_size];
+    read_at_most(&file, &mut page, page_offset);
+    check_ok!(write_all_at(&file, &encode_u32_list(&[page_checksum(&page)]), page_offset + 2 * 4));
+    // and a byte of the first page is changed
+    let page_offset = get_page_offset(0, page_size);
+    read_at_most(&file, &mut page, page_offset);
+    let saved = page_checksum(&page);
+    page[100] ^= 0xff;
+    check_ok!(write_all_at(&file, &page[100..101], page_offset + 100));
+    // and a file left by a table not in the catalog
+    check_ok!(File::create(Path::new(&dir).join("dropped.table")));
+
+    let report = check_data_dir(&dir, &dir);
+    assert_eq!(report.problems, vec![
+        CheckProblem{
+            file : "check_msg.table".to_string(),
+            page_index : Some(0),
+            msg : format!("checksum {:08x} does not match {:08x} of the data", saved, page_checksum(&page)),
+        },
+        CheckProblem{
+            file : "check_msg.table".to_string(),
+            page_index : Some(1),
+            msg : "first_free_slot 1 is in use in the bitmap".to_string(),
+        },
+        CheckProblem{
+            file : "dropped.table".to_string(),
+            page_index : None,
+            msg : "not in the catalog".to_string(),
+        },
+    ]);
+    assert_eq!(report.problems[1].to_string(), "check_msg.table page 1: first_free_slot 1 is in use in the bitmap");
+    // nothing is repaired
+    read_at_most(&file, &mut bitmap, bitmap_offset);
+    assert_eq!(bitmap[0], 3);
+
+    let report = check_data_dir(&"test_file/check_not_exist/".to_string(), &dir);
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].file, "table_meta.json");
+    remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,195 @@
+use std::fs::{remove_dir_all, read_dir};
+use std::path::Path;
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::store::table::{TableManager, Attr, AttrType};
+use ::store::rewrite::{RowTransform, ColumnSource};
+use ::store::tuple::TupleValue;
+use ::store::ddl_journal::{DdlIntent, DdlOp, DDL_STEPS, JOURNAL_NAME, read_intent, read_catalog, recover_ddl};
+use ::store::check::check_data_dir;
+use ::utils::config::Config;
+use ::test::exec::test_query::{gen_test_table, insert_data};
+
+
+fn gen_config(dir : &str) -> Config {
+    Config::new(&format!(r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "{}"
+        table_file_dir = "{}""#, dir, dir))
+}
+
+fn table_file_exist(dir : &str, table : &str) -> bool {
+    Path::new(dir).join(format!("{}.table", table)).exists()
+}
+
+// the temp files of the catalog and the journal are renamed away
+fn get_file_names(dir : &str) -> Vec<String> {
+    let mut names : Vec<String> = read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_create_table_crash() {
+    for steps in 1..DDL_STEPS + 1 {
+        let dir = format!("test_file/ddl_journal/create_{}", steps);
+        remove_dir_all(&dir).ok();
+        {
+            let mut manager = TableManager::new(&gen_config(&dir));
+            manager.create_table(gen_test_table(&"kept".to_string()));
+            manager.create_table_steps(gen_test_table(&"created".to_string()), steps);
+            // dropped without saving, like a crash
+        }
+        let intent = read_intent(&dir).unwrap();
+        if steps < DDL_STEPS {
+            assert_eq!(intent, Some(DdlIntent{
+                op : DdlOp::Create,
+                table : "created".to_string(),
+                new_table : None,
+            }));
+            let report = check_data_dir(&dir, &dir);
+            assert_eq!(report.problems[0].file, JOURNAL_NAME);
+        } else {
+            assert_eq!(intent, None);
+        }
+        assert_eq!(table_file_exist(&dir, "created"), steps >= 2);
+
+        // the catalog is the commit point
+        let done = recover_ddl(&dir, &dir).unwrap();
+        let committed = steps >= 3;
+        match steps {
+            1 | 2 => assert_eq!(done, Some("create table created is rolled back".to_string())),
+            3 => assert_eq!(done, Some("create table created is rolled forward".to_string())),
+            _ => assert_eq!(done, None),
+        }
+        assert_eq!(read_intent(&dir).unwrap(), None);
+        assert_eq!(recover_ddl(&dir, &dir).unwrap(), None);
+        assert_eq!(table_file_exist(&dir, "created"), committed);
+        assert!(check_data_dir(&dir, &dir).is_ok());
+
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config(&dir))));
+        assert_eq!(manager.borrow().get_table_names().len(), if committed { 2 } else { 1 });
+        if committed {
+            insert_data(&"created".to_string(), &manager);
+            manager.borrow_mut().close();
+        }
+        assert_eq!(get_file_names(&dir), if committed {
+            vec!["created.table", "kept.table", "table_meta.json"]
+        } else {
+            vec!["kept.table", "table_meta.json"]
+        });
+    }
+}
+
+#[test]
+fn test_drop_table_crash() {
+    for steps in 1..DDL_STEPS + 1 {
+        let dir = format!("test_file/ddl_journal/drop_{}", steps);
+        remove_dir_all(&dir).ok();
+        {
+            let manager = Rc::new(RefCell::new(TableManager::new(&gen_config(&dir))));
+            for name in vec!["kept", "dropped"].iter() {
+                manager.borrow_mut().create_table(gen_test_table(&name.to_string()));
+                insert_data(&name.to_string(), &manager);
+            }
+            manager.borrow_mut().save_to_file();
+            manager.borrow_mut().drop_table_steps(&"dropped".to_string(), steps);
+        }
+        assert_eq!(table_file_exist(&dir, "dropped"), steps < 2);
+        assert_eq!(read_catalog(&dir).unwrap().contains_key("dropped"), steps < 3);
+
+        // the file may be removed already, so it's always rolled forward
+        let done = recover_ddl(&dir, &dir).unwrap();
+        if steps < DDL_STEPS {
+            assert_eq!(done, Some("drop table dropped is rolled forward".to_string()));
+        } else {
+            assert_eq!(done, None);
+        }
+        assert!(!table_file_exist(&dir, "dropped"));
+        assert!(!read_catalog(&dir).unwrap().contains_key("dropped"));
+        assert!(check_data_dir(&dir, &dir).is_ok());
+
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config(&dir))));
+        assert_eq!(manager.borrow().get_table_names(), vec!["kept"]);
+        // the rows saved before the drop
+        assert_eq!(manager.borrow_mut().file_manager.scan_slots(&"kept".to_string()).count(), 3);
+        assert_eq!(get_file_names(&dir), vec!["kept.table", "table_meta.json"]);
+    }
+}
+
+#[test]
+fn test_alter_table_crash() {
+    for steps in 1..DDL_STEPS + 1 {
+        let dir = format!("test_file/ddl_journal/alter_{}", steps);
+        remove_dir_all(&dir).ok();
+        {
+            let manager = Rc::new(RefCell::new(TableManager::new(&gen_config(&dir))));
+            manager.borrow_mut().create_table(gen_test_table(&"altered".to_string()));
+            insert_data(&"altered".to_string(), &manager);
+            let mut new_table = gen_test_table(&"altered".to_string());
+            new_table.attr_list.push(Attr{
+                name : "comment".to_string(),
+                attr_type : AttrType::Char{ len : 8 },
+                primary : false,
+                nullable : true,
+                generated : None,
+            });
+            let transform = RowTransform{
+                sources : vec![ColumnSource::Old(0), ColumnSource::Old(1), ColumnSource::Old(2),
+                    ColumnSource::Value(TupleValue::Null)],
+            };
+            assert_eq!(manager.borrow_mut().rewrite_table_steps(new_table, &transform, steps), Ok(3));
+        }
+        let intent = read_intent(&dir).unwrap();
+        assert_eq!(intent.map(|intent| intent.op), if steps < DDL_STEPS { Some(DdlOp::Alter) } else { None });
+        assert_eq!(table_file_exist(&dir, "altered.rewrite"), steps < 2);
+        let attr_num = read_catalog(&dir).unwrap().get("altered").unwrap().attr_list.len();
+        assert_eq!(attr_num, if steps < 3 { 3 } else { 4 });
+
+        // the old file may be replaced already, so it's always rolled forward
+        let done = recover_ddl(&dir, &dir).unwrap();
+        if steps < DDL_STEPS {
+            assert_eq!(done, Some("alter table altered is rolled forward".to_string()));
+        } else {
+            assert_eq!(done, None);
+        }
+        assert_eq!(read_catalog(&dir).unwrap().get("altered").unwrap().attr_list.len(), 4);
+        assert!(check_data_dir(&dir, &dir).is_ok());
+
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config(&dir))));
+        let rows : Vec<Vec<TupleValue>> = manager.borrow_mut().file_manager.scan_slots(&"altered".to_string())
+            .map(|slot| check_ok!(slot).2).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == 4));
+        assert_eq!(get_file_names(&dir), vec!["altered.table", "table_meta.json"]);
+    }
+}
+
+#[test]
+fn test_recover_on_open() {
+    // from_json_file finishes the journal itself
+    let dir = "test_file/ddl_journal/open";
+    remove_dir_all(dir).ok();
+    {
+        let mut manager = TableManager::new(&gen_config(dir));
+        manager.create_table(gen_test_table(&"kept".to_string()));
+        manager.create_table_steps(gen_test_table(&"created".to_string()), 2);
+    }
+    let manager = TableManager::from_json_file(&gen_config(dir));
+    assert_eq!(manager.get_table_names(), vec!["kept"]);
+    assert!(!table_file_exist(dir, "created"));
+    assert_eq!(read_intent(&dir.to_string()).unwrap(), None);
+
+    // a missing catalog is empty
+    let dir = "test_file/ddl_journal/empty";
+    remove_dir_all(dir).ok();
+    {
+        let mut manager = TableManager::new(&gen_config(dir));
+        manager.create_table_steps(gen_test_table(&"created".to_string()), 2);
+    }
+    assert_eq!(read_catalog(&dir.to_string()).unwrap().len(), 0);
+    assert_eq!(recover_ddl(&dir.to_string(), &dir.to_string()).unwrap(),
+        Some("create table created is rolled back".to_string()));
+    assert_eq!(get_file_names(dir).len(), 0);
+}
@@ -1,320 +1,1774 @@
-use std::ptr::{read, write};
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::sync::{Arc, RwLock};
-use libc::malloc;
-use ::utils::pointer::{read_string, write_string, pointer_offset};
-use ::utils::config::Config;
-use ::store::file::{TableFile, FilePage, BitMap, PageHeader, TableFileManager};
-use ::store::buffer::{DataPtr, Page};
-use ::store::table::{Table, Attr, AttrType, TableManager};
-use ::parser::common::{ValueExpr, ValueType};
-use ::store::tuple::TupleValue;
-use ::test::exec::test_query;
-use ::test::exec::test_query::gen_test_manager;
-
-
-#[test]
-fn test_page_header() {
-    {
-        let data;
-        unsafe{
-            data = malloc(8);
-            write::<u32>(data as *mut u32, 233);
-            write::<u32>((data as *mut u32).offset(1), 666);
-        }
-        let mut header = PageHeader{
-            slot_sum : 233,
-            first_free_slot : 0,
-            data : data,
-        };
-        header.init_from_page_data();
-        assert_eq!(header.slot_sum, 233);
-        assert_eq!(header.first_free_slot, 666);
-    }
-    {
-        let data;
-        unsafe{
-            data = malloc(8);
-            write::<u32>(data as *mut u32, 111);
-            write::<u32>((data as *mut u32).offset(1), 222);
-        }
-        let mut header = PageHeader{
-            slot_sum : 233,
-            first_free_slot : 666,
-            data : data,
-        };
-        header.save_to_page_data();
-        assert_eq!(unsafe{read::<u32>(data as *const u32)}, 233);
-        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(1))}, 666);
-    }
-}
-
-#[test]
-fn test_bitmap()
-{
-    let data = unsafe{ malloc(3) };
-    let mut bitmap = BitMap{
-        data : data,
-        slot_sum : 24,
-    };
-    bitmap.clean();
-    for i in 0..24 {
-        assert!(!bitmap.is_inuse(i));
-    }
-    bitmap.set_inuse(8, true);
-    assert!(!bitmap.is_inuse(7));
-    assert!(bitmap.is_inuse(8));
-    assert!(!bitmap.is_inuse(9));
-    assert_eq!(bitmap.next_tuple_index(0), 8);
-    bitmap.set_inuse(8 + 7, true);
-    assert!(!bitmap.is_inuse(8 + 6));
-    assert!(bitmap.is_inuse(8 + 7));
-    assert!(!bitmap.is_inuse(8 + 8));
-    assert_eq!(bitmap.next_tuple_index(0), 8);
-    assert_eq!(bitmap.next_tuple_index(8), 8);
-    assert_eq!(bitmap.next_tuple_index(8 + 1), 8 + 7);
-    bitmap.set_inuse(8 + 3, true);
-    assert!(!bitmap.is_inuse(8 + 2));
-    assert!(bitmap.is_inuse(8 + 3));
-    assert!(!bitmap.is_inuse(8 + 4));
-    unsafe{
-        assert_eq!(read::<u8>(data as *const u8), 0);
-        assert_eq!(read::<u8>((data as *const u8).offset(1)), 137);
-        assert_eq!(read::<u8>((data as *const u8).offset(2)), 0);
-    }
-    assert_eq!(bitmap.get_first_free_slot(), 0);
-    bitmap.set_inuse(0, true);
-    assert_eq!(bitmap.get_first_free_slot(), 1);
-    for i in 1..8 {
-        bitmap.set_inuse(i, true);
-    }
-    assert_eq!(bitmap.next_tuple_index(8), 8);
-    assert_eq!(bitmap.get_first_free_slot(), 8 + 1);
-    bitmap.set_inuse(3, false);
-    assert_eq!(bitmap.get_first_free_slot(), 3);
-    assert_eq!(bitmap.next_tuple_index(50), bitmap.slot_sum);
-}
-
-fn gen_test_table() -> Table {
-    Table{
-        name : "test_file_message".to_string(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "content".to_string(),
-                attr_type : AttrType::Char{ len : 6 },
-                primary : false,
-                nullable : false,
-            },
-            Attr{
-                name : "score".to_string(),
-                attr_type : AttrType::Float,
-                primary : false,
-                nullable : true,
-            },
-        ],
-    }
-}
-
-#[test]
-fn test_file_page_insert() {
-    let table = gen_test_table();
-    let tuple_desc = table.gen_tuple_desc();
-    assert_eq!(tuple_desc.tuple_len, 16);
-    let mut mem_page = Page::new(1, 2);
-    mem_page.alloc();
-    let page = Rc::new(RefCell::new(mem_page));
-    let mut file_page = FilePage::new(page, tuple_desc.tuple_len);
-    file_page.init_empty_page();
-    let mut value_list = vec![
-        ValueExpr{ value : "233".to_string(), value_type : ValueType::Integer },
-        ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
-        ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
-    ];
-    assert_eq!(file_page.header.first_free_slot, 0);
-    assert_eq!(file_page.is_inuse(0), false);
-    file_page.insert(&value_list, &tuple_desc);
-    assert_eq!(file_page.header.first_free_slot, 1);
-    assert_eq!(file_page.is_inuse(0), true);
-    value_list[0].value = "777".to_string();
-    value_list[1].value = "dyb".to_string();
-    value_list[2].value = "12345.777".to_string();
-    file_page.insert(&value_list, &tuple_desc);
-    assert_eq!(file_page.header.first_free_slot, 2);
-    assert_eq!(file_page.is_inuse(1), true);
-
-    file_page.save_to_page();
-    let mut p = file_page.mem_page.borrow().data;
-    assert_eq!(unsafe{ read(p as *const u32) }, 253);  // slot_sum
-    p = pointer_offset(p, 4);
-    assert_eq!(unsafe{ read(p as *const u32) }, 2);  // first_free_slot
-    p = pointer_offset(p, 4);
-    assert_eq!(unsafe{ read(p as *const u8) }, 3);  // bitmap
-    assert_eq!(unsafe{ read(pointer_offset(p, 4) as *const u8) }, 0);  // bitmap
-    p = pointer_offset(p, (253 + 7) / 8);
-    // first tuple
-    assert_eq!(unsafe{ read(p as *const u32) }, 233);  // tuple data: id
-    p = pointer_offset(p, 4);
-    assert_eq!(unsafe{ read_string(p, 6) }, "abcdef");  // tuple data: content
-    p = pointer_offset(p, 8);
-    assert_eq!(unsafe{ read(p as *const f32) }, 666.666);  // tuple data: score
-    p = pointer_offset(p, 4);
-    // second tuple
-    assert_eq!(unsafe{ read(p as *const u32) }, 777);  // tuple data: id
-    p = pointer_offset(p, 4);
-    assert_eq!(unsafe{ read_string(p, 6) }, "dyb");  // tuple data: content
-    p = pointer_offset(p, 8);
-    assert_eq!(unsafe{ read(p as *const f32) }, 12345.777);  // tuple data: score
-}
-
-#[test]
-fn test_file_insert() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let mut manager = TableFileManager::new(&config);
-    let table = Rc::new(RefCell::new(gen_test_table()));
-    let table_name = "test_file_message".to_string();
-    manager.create_file(table_name.clone(), table);
-    let mut value_list = vec![
-        ValueExpr{ value : "233".to_string(), value_type : ValueType::Integer },
-        ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
-        ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
-    ];
-    manager.insert(&table_name, &value_list);
-    assert_pattern!(manager.get_tuple_value(&table_name, 0, 0), TupleValue::Int(233));
-    assert_pattern!(manager.get_tuple_value(&table_name, 0, 2), TupleValue::Float(666.666));
-    assert_eq!(extract!(
-        manager.get_tuple_value(&table_name, 0, 1), TupleValue::Char(s), s), "abcdef");
-
-    value_list[0].value = "777".to_string();
-    value_list[1].value = "dyb".to_string();
-    value_list[2].value = "12345.777".to_string();
-    manager.insert(&table_name, &value_list);
-    assert_pattern!(manager.get_tuple_value(&table_name, 1, 0), TupleValue::Int(777));
-    assert_pattern!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Float(12345.777));
-    assert_eq!(extract!(
-        manager.get_tuple_value(&table_name, 1, 1), TupleValue::Char(s), s), "dyb");
-}
-
-#[test]
-fn test_get_tuple_data() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 2
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let mut manager = TableFileManager::new(&config);
-    let table = Rc::new(RefCell::new(gen_test_table()));
-    let table_name = "test_file_message".to_string();
-    manager.create_file(table_name.clone(), table);
-    let value_list = vec![
-        ValueExpr{ value : "233".to_string(), value_type : ValueType::Integer },
-        ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
-        ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
-    ];
-    manager.insert(&table_name, &value_list);
-    let tuple_data = manager.get_tuple_data(&table_name, 0).unwrap();
-    let p1 = tuple_data[0];
-    let p2 = tuple_data[1];
-    let p3 = tuple_data[2];
-    assert_eq!(unsafe{ read::<i32>(p1 as *const i32) }, 233);
-    assert_eq!(unsafe{ read_string(p2, 6) }, "abcdef");
-    assert_eq!(unsafe{ read::<f32>(p3 as *const f32) }, 666.666);
-}
-
-#[test]
-fn test_file_persistence() {
-    {
-        let config = Config::new(&r#"
-            max_memory_pool_page_num = 2
-            table_meta_dir = "test_file/table_meta/test_file_persistence/"
-            table_file_dir = "test_file/table_file""#.to_string());
-        let table_name = "test_file_persistence_message".to_string();
-        {
-            let manager = Rc::new(RefCell::new(TableManager::new(&config)));
-            manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
-            test_query::insert_data(&table_name, &manager);
-            manager.borrow_mut().save_to_file();
-        }
-        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
-        let file = manager.borrow_mut().file_manager.get_file(&table_name);
-        assert_eq!(file.borrow().page_sum, 2);
-        assert_eq!(file.borrow().first_free_page, 0);
-        let mut query = gen_plan_helper!(
-            "select * from test_file_persistence_message", &manager);
-        query.open();
-        let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-        assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
-    }
-    {
-        // test page switch
-        let config = Config::new(&r#"
-            max_memory_pool_page_num = 2
-            table_meta_dir = "test_file/table_meta/test_persistence_with_page_switch/"
-            table_file_dir = "test_file/table_file/test_persistence_with_page_switch/""#.to_string());
-        let table_name = "test_file_persistence_message".to_string();
-        let add_table_name = "test_file_message".to_string();
-        {
-            let manager = Rc::new(RefCell::new(TableManager::new(&config)));
-            manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
-            test_query::insert_data(&table_name, &manager);
-
-            assert_eq!(manager.borrow().file_manager.page_pool.get_capacity(), 2);
-            manager.borrow_mut().add_table(gen_test_table());
-
-            let mut insert = gen_plan_helper!(
-                "insert test_file_message values(766, \"test\", 2.3333)",
-                &manager);
-            insert.open();
-            assert_pattern!(insert.get_next(), None);
-            assert_pattern!(insert.get_error(), None);
-
-            let mut scan = gen_plan_helper!(
-                "select * from test_file_message", &manager);
-            scan.open();
-            assert_pattern!(scan.get_next(), Some(..));
-            assert_pattern!(insert.get_next(), None);
-
-            manager.borrow_mut().save_to_file();
-        }
-        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
-        {
-            let file = manager.borrow_mut().file_manager.get_file(&table_name);
-            assert_eq!(file.borrow().page_sum, 2);
-            assert_eq!(file.borrow().first_free_page, 0);
-            let mut query = gen_plan_helper!(
-                "select * from test_file_persistence_message", &manager);
-            query.open();
-            let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-            let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-            let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-            assert_pattern!(query.get_next(), None);
-            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-            assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-            assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
-        }
-        {
-            let file = manager.borrow_mut().file_manager.get_file(&add_table_name);
-            assert_eq!(file.borrow().page_sum, 1);
-            assert_eq!(file.borrow().first_free_page, 0);
-            let mut query = gen_plan_helper!(
-                "select * from test_file_message", &manager);
-            query.open();
-            let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
-            assert_pattern!(query.get_next(), None);
-            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 766);
-        }
-    }
-}
+use std::ptr::{read, write, null_mut};
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fs::{File, OpenOptions, remove_file, remove_dir_all, read_dir, metadata};
+use std::path::Path;
+use std::io::{Read, Write};
+use std::slice::from_raw_parts;
+use std::thread::sleep;
+use std::time::Duration;
+use libc::malloc;
+use ::utils::pointer::{read_string, write_string, pointer_offset};
+use ::utils::config::Config;
+use ::utils::libwrapper::get_page_size;
+use ::utils::file::{read_at_most, write_all_at, ensure_dir_exist, create_temp_dir};
+use ::utils::log::capture_logs;
+use ::store::file::{TableFile, FilePage, BitMap, PageHeader, TableFileManager, COMPRESSED_PAGE_TAG};
+use ::store::file::{TableFileRef, FileHeader, get_page_offset, read_file_header, encode_page};
+use ::store::file::{encode_u32_list, page_checksum, read_free_pages, StoreErrorType};
+use ::store::double_write::{DoubleWriteBuffer, PageWrite};
+use ::store::writer::os_file_writer;
+use ::store::buffer::{DataPtr, Page, PagePool, FileId};
+use ::store::page_buf::PageBuf;
+use ::store::undo::{UndoLog, UndoOp, UndoRecord};
+use ::store::table::{Table, TableRef, Attr, AttrType, TableManager, TableManagerRef};
+use ::parser::common::{ValueExpr, Literal};
+use ::store::tuple::{TupleValue, gen_tuple_value};
+use ::test::exec::test_query;
+use ::test::exec::test_query::gen_test_manager;
+use ::test::utils::FullDiskWriter;
+
+
+#[test]
+fn test_page_header() {
+    {
+        let data;
+        unsafe{
+            data = malloc(8);
+            write::<u32>(data as *mut u32, 233);
+            write::<u32>((data as *mut u32).offset(1), 666);
+        }
+        let mut header = PageHeader{
+            slot_sum : 233,
+            first_free_slot : 0,
+            buf : unsafe{ PageBuf::new(data, 8) },
+        };
+        header.init_from_page_data();
+        assert_eq!(header.slot_sum, 233);
+        assert_eq!(header.first_free_slot, 666);
+    }
+    {
+        let data;
+        unsafe{
+            data = malloc(8);
+            write::<u32>(data as *mut u32, 111);
+            write::<u32>((data as *mut u32).offset(1), 222);
+        }
+        let mut header = PageHeader{
+            slot_sum : 233,
+            first_free_slot : 666,
+            buf : unsafe{ PageBuf::new(data, 8) },
+        };
+        header.save_to_page_data();
+        assert_eq!(unsafe{read::<u32>(data as *const u32)}, 233);
+        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(1))}, 666);
+    }
+}
+
+#[test]
+fn test_bitmap()
+{
+    let data = unsafe{ malloc(3) };
+    let mut bitmap = BitMap{
+        buf : unsafe{ PageBuf::new(data, 3) },
+        slot_sum : 24,
+    };
+    bitmap.clean();
+    for i in 0..24 {
+        assert!(!bitmap.is_inuse(i));
+    }
+    bitmap.set_inuse(8, true);
+    assert!(!bitmap.is_inuse(7));
+    assert!(bitmap.is_inuse(8));
+    assert!(!bitmap.is_inuse(9));
+    assert_eq!(bitmap.next_tuple_index(0), 8);
+    bitmap.set_inuse(8 + 7, true);
+    assert!(!bitmap.is_inuse(8 + 6));
+    assert!(bitmap.is_inuse(8 + 7));
+    assert!(!bitmap.is_inuse(8 + 8));
+    assert_eq!(bitmap.next_tuple_index(0), 8);
+    assert_eq!(bitmap.next_tuple_index(8), 8);
+    assert_eq!(bitmap.next_tuple_index(8 + 1), 8 + 7);
+    bitmap.set_inuse(8 + 3, true);
+    assert!(!bitmap.is_inuse(8 + 2));
+    assert!(bitmap.is_inuse(8 + 3));
+    assert!(!bitmap.is_inuse(8 + 4));
+    unsafe{
+        assert_eq!(read::<u8>(data as *const u8), 0);
+        assert_eq!(read::<u8>((data as *const u8).offset(1)), 137);
+        assert_eq!(read::<u8>((data as *const u8).offset(2)), 0);
+    }
+    assert_eq!(bitmap.get_first_free_slot(), 0);
+    bitmap.set_inuse(0, true);
+    assert_eq!(bitmap.get_first_free_slot(), 1);
+    for i in 1..8 {
+        bitmap.set_inuse(i, true);
+    }
+    assert_eq!(bitmap.next_tuple_index(8), 8);
+    assert_eq!(bitmap.get_first_free_slot(), 8 + 1);
+    bitmap.set_inuse(3, false);
+    assert_eq!(bitmap.get_first_free_slot(), 3);
+    assert_eq!(bitmap.next_tuple_index(50), bitmap.slot_sum);
+}
+
+#[test]
+fn test_page_buf() {
+    let data = unsafe{ malloc(12) };
+    let mut buf = unsafe{ PageBuf::new(data, 12) };
+    buf.put_u32(0, 233);
+    buf.put_i32(4, -666);
+    buf.put_f32(8, 1.5);
+    assert_eq!(unsafe{ read::<u32>(data as *const u32) }, 233);
+    assert_eq!(buf.get_i32(4), -666);
+    assert_eq!(buf.get_f32(8), 1.5);
+    let mut sub = buf.sub(4, 8);
+    assert_eq!(sub.get_i32(0), -666);
+    sub.put_string(4, &"ab".to_string(), 4);
+    assert_eq!(buf.get_string(8, 4), "ab");
+    sub.fill(0, 8, 0);
+    assert_eq!(buf.get_u32(0), 233);
+    assert_eq!(&buf.as_slice()[4..], &[0u8; 8][..]);
+}
+
+#[test]
+fn test_page_little_endian() {
+    let mut data = [0u8; 32];
+    let mut page = unsafe{ PageBuf::new(data.as_mut_ptr() as DataPtr, 32) };
+    page.put_u32(0, 233);
+    page.put_i32(4, -666);
+    page.put_f32(8, 1.5);
+    assert_eq!(&page.as_slice()[..12], &[233, 0, 0, 0, 0x66, 0xfd, 0xff, 0xff, 0, 0, 0xc0, 0x3f]);
+    let mut header = PageHeader{
+        slot_sum : 0x0102,
+        first_free_slot : 0x030405,
+        buf : page.sub(16, 8),
+    };
+    header.save_to_page_data();
+    assert_eq!(&data[16..24], &[2, 1, 0, 0, 5, 4, 3, 0]);
+
+    // a compressed page slot begins with the tag and the payload length
+    let slot = encode_page(&[0u8; 256], true, false);
+    assert_eq!(&slot[..8], &[0xff, 0xff, 0xff, 0xff, (slot.len() - 8) as u8, 0, 0, 0]);
+
+    // the checksum is set in the copy and covers the rest of the page
+    let data = [7u8; 256];
+    let slot = encode_page(&data, false, true);
+    assert_eq!(&slot[..8], &data[..8]);
+    assert_eq!(&slot[8..12], &encode_u32_list(&[page_checksum(&data)])[..]);
+    assert_eq!(&slot[12..], &data[12..]);
+    assert_eq!(page_checksum(&slot), page_checksum(&data));
+}
+
+#[test]
+#[should_panic(expected = "page access of 4 bytes at 9 is out of 12 bytes")]
+fn test_page_buf_out_of_bounds() {
+    let buf = unsafe{ PageBuf::new(malloc(12), 12) };
+    buf.get_u32(9);
+}
+
+#[test]
+#[should_panic(expected = "page access of 1 bytes at 3 is out of 3 bytes")]
+fn test_bitmap_out_of_bounds() {
+    // a slot_sum larger than the bytes of the bitmap
+    let bitmap = BitMap{
+        buf : unsafe{ PageBuf::new(malloc(3), 3) },
+        slot_sum : 32,
+    };
+    bitmap.is_inuse(30);
+}
+
+#[test]
+#[should_panic(expected = "is out of")]
+fn test_sub_page_buf_out_of_bounds() {
+    let buf = unsafe{ PageBuf::new(malloc(12), 12) };
+    buf.sub(8, 2).fill(0, 4, 0);
+}
+
+fn gen_test_table() -> Table {
+    Table{
+        name : "test_file_message".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Char{ len : 6 },
+                primary : false,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "score".to_string(),
+                attr_type : AttrType::Float,
+                primary : false,
+                nullable : true,
+                generated : None,
+            },
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    }
+}
+
+#[test]
+fn test_file_page_insert() {
+    let table = gen_test_table();
+    let tuple_desc = table.gen_tuple_desc();
+    assert_eq!(tuple_desc.tuple_len, 16);
+    // 4096 bytes whatever the os uses, slot_sum below depends on it
+    let mut mem_page = Page::new(FileId(1), 2);
+    mem_page.alloc(4096);
+    let page = Rc::new(RefCell::new(mem_page));
+    let mut file_page = FilePage::new(page, tuple_desc.tuple_len, 4096, true);
+    file_page.init_empty_page();
+    let mut value_list = vec![
+        ValueExpr::from_literal(Literal::Int(233)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    assert_eq!(file_page.header.first_free_slot, 0);
+    assert_eq!(file_page.is_inuse(0), false);
+    file_page.insert(&value_list, &tuple_desc);
+    assert_eq!(file_page.header.first_free_slot, 1);
+    assert_eq!(file_page.is_inuse(0), true);
+    value_list[0] = ValueExpr::from_literal(Literal::Int(777));
+    value_list[1] = ValueExpr::from_literal(Literal::Str("dyb".to_string()));
+    value_list[2] = ValueExpr::from_literal(Literal::Float(12345.777));
+    file_page.insert(&value_list, &tuple_desc);
+    assert_eq!(file_page.header.first_free_slot, 2);
+    assert_eq!(file_page.is_inuse(1), true);
+
+    file_page.save_to_page();
+    let mut p = file_page.mem_page.borrow().data;
+    assert_eq!(unsafe{ read(p as *const u32) }, 253);  // slot_sum
+    p = pointer_offset(p, 4);
+    assert_eq!(unsafe{ read(p as *const u32) }, 2);  // first_free_slot
+    p = pointer_offset(p, 4);  // checksum, set when the page is saved
+    p = pointer_offset(p, 4);
+    assert_eq!(unsafe{ read(p as *const u8) }, 3);  // bitmap
+    assert_eq!(unsafe{ read(pointer_offset(p, 4) as *const u8) }, 0);  // bitmap
+    p = pointer_offset(p, (253 + 7) / 8);
+    // first tuple
+    assert_eq!(unsafe{ read(p as *const u32) }, 233);  // tuple data: id
+    p = pointer_offset(p, 4);
+    assert_eq!(unsafe{ read_string(p, 6) }, "abcdef");  // tuple data: content
+    p = pointer_offset(p, 8);
+    assert_eq!(unsafe{ read(p as *const f32) }, 666.666);  // tuple data: score
+    p = pointer_offset(p, 4);
+    // second tuple
+    assert_eq!(unsafe{ read(p as *const u32) }, 777);  // tuple data: id
+    p = pointer_offset(p, 4);
+    assert_eq!(unsafe{ read_string(p, 6) }, "dyb");  // tuple data: content
+    p = pointer_offset(p, 8);
+    assert_eq!(unsafe{ read(p as *const f32) }, 12345.777);  // tuple data: score
+}
+
+#[test]
+fn test_add_page_index() {
+    let dir = "test_file/table_file/test_add_page_index/".to_string();
+    ensure_dir_exist(&dir);
+    let table = Rc::new(RefCell::new(gen_test_table()));
+    let mut file = TableFile::new("test_file_add_page_message".to_string(), table, &dir, 256);
+    file.page_sum = 3;
+    // the page index kept by the pool differs from the one in the table file
+    let mut mem_page = Page::new(file.get_id(), 7);
+    mem_page.alloc(256);
+    file.add_page(2, Rc::new(RefCell::new(mem_page)));
+    assert!(file.loaded_pages.get(&2).is_some());
+    assert!(file.loaded_pages.get(&7).is_none());
+    file.loaded_pages.get_mut(&2).unwrap().init_empty_page();
+    let value_list = vec![
+        ValueExpr::from_literal(Literal::Int(233)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    file.insert_in_page(2, &value_list);
+    let position = file.gen_position(2, 0);
+    assert!(file.get_tuple_data(position).is_some());
+    assert_pattern!(file.get_tuple_value(position, 0), TupleValue::Int(233));
+}
+
+#[test]
+fn test_file_id_not_reused() {
+    let dir = "test_file/table_file/test_file_id_not_reused/".to_string();
+    ensure_dir_exist(&dir);
+    let table = Rc::new(RefCell::new(gen_test_table()));
+    let mut pool = PagePool::new(2);
+    let file = TableFile::new("test_file_id_message".to_string(), table.clone(), &dir, 256);
+    let old_id = file.get_id();
+    pool.put_page(old_id, 0, null_mut(), 256);
+    // the reopened file usually gets the same fd
+    drop(file);
+    let file = TableFile::new("test_file_id_message".to_string(), table.clone(), &dir, 256);
+    assert!(file.get_id() != old_id);
+    assert_pattern!(pool.get_page(file.get_id(), 0), None);
+    assert!(pool.get_page(old_id, 0).is_some());
+    let other = TableFile::new("test_file_id_other".to_string(), table, &dir, 256);
+    assert!(other.get_id() != file.get_id());
+}
+
+#[test]
+fn test_file_insert() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table = Rc::new(RefCell::new(gen_test_table()));
+    let table_name = "test_file_message".to_string();
+    manager.create_file(table_name.clone(), table);
+    let mut value_list = vec![
+        ValueExpr::from_literal(Literal::Int(233)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    manager.insert(&table_name, &value_list);
+    assert_pattern!(manager.get_tuple_value(&table_name, 0, 0), TupleValue::Int(233));
+    assert_pattern!(manager.get_tuple_value(&table_name, 0, 2), TupleValue::Float(666.666));
+    assert_eq!(extract!(
+        manager.get_tuple_value(&table_name, 0, 1), TupleValue::Char(s), s), "abcdef");
+
+    value_list[0] = ValueExpr::from_literal(Literal::Int(777));
+    value_list[1] = ValueExpr::from_literal(Literal::Str("dyb".to_string()));
+    value_list[2] = ValueExpr::from_literal(Literal::Float(12345.777));
+    manager.insert(&table_name, &value_list);
+    assert_pattern!(manager.get_tuple_value(&table_name, 1, 0), TupleValue::Int(777));
+    assert_pattern!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Float(12345.777));
+    assert_eq!(extract!(
+        manager.get_tuple_value(&table_name, 1, 1), TupleValue::Char(s), s), "dyb");
+}
+
+#[test]
+fn test_undo_records() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table = Rc::new(RefCell::new(gen_test_table()));
+    let table_name = "test_file_message".to_string();
+    manager.create_file(table_name.clone(), table);
+    let gen_row = |id : i32| vec![
+        ValueExpr::from_literal(Literal::Int(id)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    assert_eq!(manager.insert(&table_name, &gen_row(1)), 0);
+    assert_eq!(manager.insert(&table_name, &gen_row(2)), 1);
+    let before = check_ok!(manager.get_slot_bytes(&table_name, 0));
+    let free_pages = manager.get_free_pages(&table_name);
+    check_ok!(manager.delete(&table_name, 0));
+
+    // the insert takes the slot freed by the delete, the first free one of the page header again
+    let mut undo_log = UndoLog::new(&table_name);
+    undo_log.push_delete(0, before, free_pages.clone());
+    let position = manager.insert(&table_name, &gen_row(3));
+    assert_eq!(position, 0);
+    undo_log.push(UndoOp::Insert, position, Vec::new());
+    assert_pattern!(undo_log.rollback(&mut manager), Ok(2));
+    assert_eq!(undo_log.len(), 0);
+    assert_eq!(manager.get_free_pages(&table_name), free_pages);
+
+    assert_pattern!(manager.get_tuple_value(&table_name, 0, 0), TupleValue::Int(1));
+    let file = manager.get_file(&table_name);
+    assert!(file.borrow().is_inuse(0, 0));
+    assert!(file.borrow().is_inuse(0, 1));
+    assert!(!file.borrow().is_inuse(0, 2));
+    assert_eq!(file.borrow().loaded_pages.get(&0).unwrap().header.first_free_slot, 2);
+    assert_eq!(manager.insert(&table_name, &gen_row(4)), 2);
+}
+
+#[test]
+fn test_get_tuple_data() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table = Rc::new(RefCell::new(gen_test_table()));
+    let table_name = "test_file_message".to_string();
+    manager.create_file(table_name.clone(), table);
+    let value_list = vec![
+        ValueExpr::from_literal(Literal::Int(233)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    manager.insert(&table_name, &value_list);
+    let tuple_data = check_ok!(manager.get_tuple_data(&table_name, 0)).unwrap();
+    let p1 = tuple_data[0];
+    let p2 = tuple_data[1];
+    let p3 = tuple_data[2];
+    assert_eq!(unsafe{ read::<i32>(p1 as *const i32) }, 233);
+    assert_eq!(unsafe{ read_string(p2, 6) }, "abcdef");
+    assert_eq!(unsafe{ read::<f32>(p3 as *const f32) }, 666.666);
+}
+
+#[test]
+fn test_drop_temp_manager() {
+    let table_name = "test_drop_temp_manager".to_string();
+    let manager = gen_test_manager(&table_name);
+    manager.borrow_mut().save_to_file();
+    let path = manager.borrow().file_manager.get_file_path(&table_name);
+    let dir = Path::new(&path).parent().unwrap().to_path_buf();
+    assert!(metadata(&path).is_ok());
+    assert!(metadata(dir.join("table_meta.json")).is_ok());
+    drop(manager);
+    assert!(metadata(&dir).is_err());
+}
+
+#[test]
+fn test_drop_all() {
+    let dir = create_temp_dir("test_drop_all");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        double_write = true"#.to_string());
+    let table_name = "test_drop_all".to_string();
+    let mut manager = TableManager::in_dir(&config, &dir);
+    manager.add_table(test_query::gen_test_table(&table_name));
+    manager.save_to_file();
+    // left by a table dropped before
+    check_ok!(File::create(Path::new(&dir).join("dropped.table")));
+    check_ok!(File::create(Path::new(&dir).join("not_a_table.txt")));
+    manager.drop_all();
+    assert!(manager.get_table(&table_name).is_none());
+    let left : Vec<String> = check_ok!(read_dir(&dir))
+        .map(|entry| check_ok!(entry).file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(left, vec!["not_a_table.txt".to_string()]);
+    remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_file_persistence() {
+    {
+        let config = Config::new(&r#"
+            max_memory_pool_page_num = 2
+            table_meta_dir = "test_file/table_meta/test_file_persistence/"
+            table_file_dir = "test_file/table_file""#.to_string());
+        let table_name = "test_file_persistence_message".to_string();
+        {
+            let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+            manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+            test_query::insert_data(&table_name, &manager);
+            manager.borrow_mut().save_to_file();
+        }
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        assert_eq!(file.borrow().page_sum, 2);
+        assert_eq!(file.borrow().first_free_page, 0);
+        let mut query = gen_plan_helper!(
+            "select * from test_file_persistence_message", &manager);
+        query.open();
+        let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(query.get_next(), None);
+        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
+        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+    }
+    {
+        // test page switch
+        let config = Config::new(&r#"
+            max_memory_pool_page_num = 2
+            table_meta_dir = "test_file/table_meta/test_persistence_with_page_switch/"
+            table_file_dir = "test_file/table_file/test_persistence_with_page_switch/""#.to_string());
+        let table_name = "test_file_persistence_message".to_string();
+        let add_table_name = "test_file_message".to_string();
+        {
+            let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+            manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+            test_query::insert_data(&table_name, &manager);
+
+            assert_eq!(manager.borrow().file_manager.page_pool.get_capacity(), 2);
+            manager.borrow_mut().add_table(gen_test_table());
+
+            let mut insert = gen_plan_helper!(
+                "insert test_file_message values(766, \"test\", 2.3333)",
+                &manager);
+            insert.open();
+            assert_pattern!(insert.get_next(), None);
+            assert_pattern!(insert.get_error(), None);
+
+            let mut scan = gen_plan_helper!(
+                "select * from test_file_message", &manager);
+            scan.open();
+            assert_pattern!(scan.get_next(), Some(..));
+            assert_pattern!(insert.get_next(), None);
+
+            manager.borrow_mut().save_to_file();
+        }
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        {
+            let file = manager.borrow_mut().file_manager.get_file(&table_name);
+            assert_eq!(file.borrow().page_sum, 2);
+            assert_eq!(file.borrow().first_free_page, 0);
+            let mut query = gen_plan_helper!(
+                "select * from test_file_persistence_message", &manager);
+            query.open();
+            let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+            let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+            let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+            assert_pattern!(query.get_next(), None);
+            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
+            assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+            assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+        }
+        {
+            let file = manager.borrow_mut().file_manager.get_file(&add_table_name);
+            assert_eq!(file.borrow().page_sum, 1);
+            assert_eq!(file.borrow().first_free_page, 0);
+            let mut query = gen_plan_helper!(
+                "select * from test_file_message", &manager);
+            query.open();
+            let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+            assert_pattern!(query.get_next(), None);
+            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 766);
+        }
+    }
+}
+
+#[test]
+fn test_page_size_in_header() {
+    let table_name = "test_file_page_size_message".to_string();
+    let path = "test_file/table_file/test_page_size/test_file_page_size_message.table";
+    let gen_config = |extra : &str| Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_page_size/"
+        table_file_dir = "test_file/table_file/test_page_size/"
+        {}"#, extra));
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&gen_config("page_size = 256"))));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file();
+    }
+    assert_eq!(read_file_header(&File::open(path).unwrap()), Ok(FileHeader{
+        page_sum : 2, first_free_page : 0, page_size : 256, checksum : true, tuple_len : 24 }));
+    let mut header = [0u8; 32];
+    assert_eq!(read_at_most(&File::open(path).unwrap(), &mut header, 0), 32);
+    assert_eq!(header, [2, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 4, 3, 2, 1, 1, 0, 0, 0,
+        b'B', b'L', b'S', b'T', 1, 0, 0, 0, 24, 0, 0, 0]);
+
+    // the file keeps its own page size after page_size is changed
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config(""))));
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert_eq!(file.borrow().page_size, 256);
+    assert_eq!(file.borrow().get_page_slot_sum(), 10);
+    let mut query = gen_plan_helper!("select * from test_file_page_size_message", &manager);
+    query.open();
+    let mut ids = Vec::new();
+    while let Some(tuple_data) = query.get_next() {
+        ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+    }
+    assert_eq!(ids, vec![233, 777, 1]);
+
+    // saved before the magic number was recorded, read as version 0
+    let legacy = FileHeader{
+        page_sum : 1, first_free_page : 0, page_size : get_page_size(), checksum : false, tuple_len : 0 };
+    let header = [1u32, 0, 0, 0, 0, 0, 0, 0];
+    let mut old = OpenOptions::new().write(true).create(true).truncate(true)
+        .open("test_file/table_file/test_page_size/old.table").unwrap();
+    old.write_all(unsafe{ from_raw_parts::<u8>(header.as_ptr() as *const u8, 32) }).unwrap();
+    let old = File::open("test_file/table_file/test_page_size/old.table").unwrap();
+    assert_eq!(read_file_header(&old), Ok(legacy));
+    // with only the two numbers when it has no pages
+    let mut old = OpenOptions::new().read(true).write(true).truncate(true)
+        .open("test_file/table_file/test_page_size/old.table").unwrap();
+    old.write_all(&[0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!(read_file_header(&old), Ok(FileHeader{ page_sum : 0, ..legacy }));
+    // but a magic number with format version 0 is unknown
+    let old = OpenOptions::new().read(true).write(true)
+        .open("test_file/table_file/test_page_size/old.table").unwrap();
+    write_all_at(&old, &[4, 3, 2, 1, 0, 0, 0, 0, b'B', b'L', b'S', b'T', 0, 0, 0, 0, 24, 0, 0, 0], 12).unwrap();
+    assert_eq!(read_file_header(&old), Err("format version 0 is unknown".to_string()));
+
+    // saved by a host of the other byte order
+    let header = [0u8, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 2, 3, 4, 1, 0, 0, 0,
+        b'B', b'L', b'S', b'T', 1, 0, 0, 0, 24, 0, 0, 0];
+    let mut swapped = OpenOptions::new().write(true).create(true).truncate(true)
+        .open("test_file/table_file/test_page_size/swapped.table").unwrap();
+    swapped.write_all(&header).unwrap();
+    let swapped = File::open("test_file/table_file/test_page_size/swapped.table").unwrap();
+    assert_eq!(read_file_header(&swapped), Err("file header is of another byte order".to_string()));
+}
+
+#[test]
+fn test_file_without_checksum() {
+    // saved before the pages had checksums, the page header has no room for one
+    let dir = "test_file/table_file/test_file_without_checksum/";
+    remove_dir_all(dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        table_meta_dir = "test_file/table_meta/test_file_without_checksum/"
+        table_file_dir = "{}""#, dir));
+    let table_name = "test_file_no_checksum_message".to_string();
+    let path = format!("{}{}.table", dir, table_name);
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        manager.borrow_mut().file_manager.get_file(&table_name).borrow_mut().checksum = false;
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file();
+    }
+    assert_eq!(read_file_header(&File::open(&path).unwrap()), Ok(FileHeader{
+        page_sum : 2, first_free_page : 0, page_size : 256, checksum : false, tuple_len : 24 }));
+
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(!file.borrow().checksum);
+    let mut query = gen_plan_helper!("select * from test_file_no_checksum_message", &manager);
+    query.open();
+    let mut ids = Vec::new();
+    while let Some(tuple_data) = query.get_next() {
+        ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+    }
+    query.close();
+    assert_eq!(ids, vec![233, 777, 1]);
+    // and it stays so when saved again
+    manager.borrow_mut().save_to_file();
+    assert_eq!(read_file_header(&File::open(&path).unwrap()), Ok(FileHeader{
+        page_sum : 2, first_free_page : 0, page_size : 256, checksum : false, tuple_len : 24 }));
+}
+
+#[test]
+fn test_legacy_file_header() {
+    // saved with only [page_sum, first_free_page], in pages of the os page size without checksums
+    let dir = "test_file/table_file/test_legacy_file_header/";
+    remove_dir_all(dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_legacy_file_header/"
+        table_file_dir = "{}""#, dir));
+    let table_name = "test_file_legacy_message".to_string();
+    let path = format!("{}{}.table", dir, table_name);
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        manager.borrow_mut().file_manager.get_file(&table_name).borrow_mut().checksum = false;
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file();
+    }
+    let file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+    write_all_at(&file, &[0u8; 24], 8).unwrap();
+    assert_eq!(read_file_header(&file), Ok(FileHeader{
+        page_sum : 2, first_free_page : 0, page_size : get_page_size(), checksum : false, tuple_len : 0 }));
+
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let mut query = gen_plan_helper!("select * from test_file_legacy_message", &manager);
+    query.open();
+    let mut ids = Vec::new();
+    while let Some(tuple_data) = query.get_next() {
+        ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+    }
+    query.close();
+    assert_eq!(ids, vec![233, 777, 1]);
+    // upgraded to the current header when saved again
+    manager.borrow_mut().save_to_file();
+    assert_eq!(read_file_header(&file), Ok(FileHeader{
+        page_sum : 2, first_free_page : 0, page_size : get_page_size(), checksum : false, tuple_len : 24 }));
+}
+
+#[test]
+fn test_init_from_invalid_file() {
+    let dir = "test_file/table_file/test_init_from_invalid_file/";
+    remove_dir_all(dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "{}""#, dir));
+    let table_names = vec!["test_file_init_ok", "test_file_init_truncated", "test_file_init_header",
+        "test_file_init_magic", "test_file_init_tuple_len", "test_file_init_missing"];
+    let tables : Vec<TableRef> = table_names.iter().map(|name| {
+        let mut table = gen_test_table();
+        table.name = name.to_string();
+        table.fill_factor = Some(100);
+        Rc::new(RefCell::new(table))
+    }).collect();
+    let slot_sum = {
+        let mut manager = TableFileManager::new(&config);
+        for table in tables[..5].iter() {
+            manager.create_file(table.borrow().name.clone(), table.clone());
+        }
+        let slot_sum = manager.get_file(&table_names[0].to_string()).borrow().get_page_slot_sum();
+        for name in table_names[..5].iter() {
+            for i in 0..(slot_sum * 2) {
+                let value_list = vec![
+                    ValueExpr::from_literal(Literal::Int(i as i32)),
+                    ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+                    ValueExpr::from_literal(Literal::Float(666.666)),
+                ];
+                manager.insert(&name.to_string(), &value_list);
+            }
+        }
+        manager.save_all();
+        slot_sum
+    };
+    let path = |name : &str| format!("{}{}.table", dir, name);
+    // the second page is lost
+    let truncated = OpenOptions::new().write(true).open(path("test_file_init_truncated")).unwrap();
+    truncated.set_len(get_page_offset(1, 256)).unwrap();
+    // of no byte order
+    let header = OpenOptions::new().write(true).open(path("test_file_init_header")).unwrap();
+    write_all_at(&header, &[0xff, 0xff, 0xff, 0xff], 12).unwrap();
+    // not a table file
+    let magic = OpenOptions::new().write(true).open(path("test_file_init_magic")).unwrap();
+    write_all_at(&magic, &[0x7f, b'E', b'L', b'F'], 20).unwrap();
+    // a column is added to the catalog without rewriting the file
+    tables[4].borrow_mut().attr_list.push(Attr{
+        name : "extra".to_string(),
+        attr_type : AttrType::Int,
+        primary : false,
+        nullable : true,
+        generated : None,
+    });
+
+    let mut manager = TableFileManager::new(&config);
+    let result = manager.init_from_file(tables);
+    let err = extract!(result, Err(err), err);
+    assert_eq!(err.lines().collect::<Vec<&str>>(), vec![
+        format!("{}: expected 2 pages, the file is truncated to 512 bytes", path("test_file_init_truncated")),
+        format!("{}: file header is of another byte order", path("test_file_init_header")),
+        format!("{}: not a table file, the magic is 464c457f", path("test_file_init_magic")),
+        format!("{}: saved with tuples of 16 bytes, while the schema has 20",
+            path("test_file_init_tuple_len")),
+        format!("{}: No such file or directory (os error 2)", path("test_file_init_missing")),
+    ]);
+    // not created by the check
+    assert!(!Path::new(&path("test_file_init_missing")).exists());
+
+    // the valid file is opened with its pages loaded when they are used
+    let name = table_names[0].to_string();
+    let file = manager.get_file(&name);
+    assert_eq!(file.borrow().page_sum, 2);
+    assert!(file.borrow().loaded_pages.is_empty());
+    let position = file.borrow().gen_position(1, slot_sum - 1);
+    let id = manager.get_tuple_value(&name, position, 0);
+    assert_eq!(extract!(id, TupleValue::Int(id), id), (slot_sum * 2 - 1) as i32);
+}
+
+#[test]
+fn test_from_files() {
+    let dir = "test_file/table_file/test_from_files/";
+    remove_dir_all(dir).ok();
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "{}"
+        table_file_dir = "{}""#, dir, dir));
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        for name in ["test_from_files_kept", "test_from_files_dropped"].iter() {
+            manager.borrow_mut().add_table(test_query::gen_test_table(&name.to_string()));
+            test_query::insert_data(&name.to_string(), &manager);
+        }
+        manager.borrow_mut().remove_table(&"test_from_files_dropped".to_string());
+        manager.borrow_mut().save_to_file();
+    }
+    // not a table file
+    File::create(format!("{}test_from_files.txt", dir)).unwrap();
+
+    let kept = Rc::new(RefCell::new(test_query::gen_test_table(&"test_from_files_kept".to_string())));
+    let opened = TableFileManager::from_files(&config, vec![kept]);
+    let (mut file_manager, unknown) = extract!(opened, Ok(opened), opened);
+    assert_eq!(unknown, vec![format!("{}test_from_files_dropped.table: not in the catalog", dir)]);
+    let file = file_manager.get_file(&"test_from_files_kept".to_string());
+    assert!(file.borrow().page_sum > 0);
+    assert_eq!(file.borrow().tuple_desc.tuple_len, test_query::gen_test_table(&"t".to_string())
+        .gen_tuple_desc().tuple_len);
+    assert_eq!(file_manager.scan_slots(&"test_from_files_kept".to_string()).count(), 3);
+    // still on the disk
+    assert!(Path::new(&format!("{}test_from_files_dropped.table", dir)).exists());
+
+    // the restarted server only logs it
+    let mut manager = None;
+    let logs = capture_logs(|| manager = Some(TableManager::from_json_file(&config)));
+    let warnings : Vec<String> = logs.into_iter().filter(|line| line.starts_with("warn")).collect();
+    assert_eq!(warnings, vec![format!("warn blastoise::store::table: {}test_from_files_dropped.table: \
+        not in the catalog", dir)]);
+    let manager = Rc::new(RefCell::new(manager.unwrap()));
+    let mut query = gen_plan_helper!("select * from test_from_files_kept where id = 777", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    assert_pattern!(query.get_next(), None);
+}
+
+#[test]
+fn test_compressed_page() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_compressed_page/"
+        table_file_dir = "test_file/table_file/test_compressed_page/""#.to_string());
+    let table_name = "test_file_compressed_message".to_string();
+    let mut expected = Vec::new();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        let mut table = gen_test_table();
+        table.name = table_name.clone();
+        table.attr_list[1].attr_type = AttrType::Char{ len : 8 };  // no padding in tuple
+        table.compression = Some(true);
+        manager.borrow_mut().add_table(table);
+        let slot_sum = manager.borrow_mut().file_manager.get_file(&table_name).borrow().get_page_slot_sum();
+        // fill the first page with random data which can not be compressed,
+        // and put a few tuples in the second page
+        let mut seed : u32 = 233;
+        for i in 0..(slot_sum + 2) {
+            let mut content = String::new();
+            for _ in 0..8 {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                content.push((b'a' + (seed >> 16) as u8 % 26) as char);
+            }
+            let id = if i < slot_sum { (seed & 0x7fffffff) as i32 } else { i as i32 };
+            let score = (seed % 100000) as f32 / 7.0;
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(id)),
+                ValueExpr::from_literal(Literal::Str(content.clone())),
+                ValueExpr::from_literal(Literal::Float(score)),
+            ];
+            check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+            expected.push((id, content, score));
+        }
+        manager.borrow_mut().save_to_file();
+    }
+    {
+        let mut data = Vec::new();
+        File::open("test_file/table_file/test_compressed_page/test_file_compressed_message.table")
+            .unwrap().read_to_end(&mut data).unwrap();
+        let page_size = get_page_size();
+        let tag_of = |page_index : usize| unsafe{
+            read::<u32>(data[page_size * (page_index + 1)..].as_ptr() as *const u32)
+        };
+        assert!(tag_of(0) != COMPRESSED_PAGE_TAG);
+        assert_eq!(tag_of(1), COMPRESSED_PAGE_TAG);
+        assert!(data.len() < page_size * 3);
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    assert!(manager.borrow().get_table(&table_name).unwrap().borrow().is_compressed());
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert_eq!(file.borrow().page_sum, 2);
+    let attr_desc = file.borrow().tuple_desc.attr_desc.clone();
+    for (i, &(id, ref content, score)) in expected.iter().enumerate() {
+        let tuple_data = check_ok!(manager.borrow_mut().file_manager.get_tuple_data(&table_name, i)).unwrap();
+        let row = gen_tuple_value(&attr_desc, tuple_data);
+        assert_eq!(extract!(row[0], TupleValue::Int(n), n), id);
+        assert_eq!(extract!(row[1], TupleValue::Char(ref s), s.clone()), *content);
+        assert_eq!(extract!(row[2], TupleValue::Float(f), f), score);
+    }
+}
+
+#[test]
+fn test_truncated_compressed_page() {
+    remove_dir_all("test_file/table_meta/test_truncated_compressed_page/").ok();
+    remove_dir_all("test_file/table_file/test_truncated_compressed_page/").ok();
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        table_meta_dir = "test_file/table_meta/test_truncated_compressed_page/"
+        table_file_dir = "test_file/table_file/test_truncated_compressed_page/""#.to_string());
+    let table_name = "test_file_truncated_message".to_string();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        let mut table = gen_test_table();
+        table.name = table_name.clone();
+        table.compression = Some(true);
+        manager.borrow_mut().add_table(table);
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(233)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+        manager.borrow_mut().save_to_file();
+    }
+    // the payload length is over the end of the file
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let path = manager.borrow().file_manager.get_file_path(&table_name);
+    let file = check_ok!(OpenOptions::new().read(true).write(true).open(&path));
+    let offset = get_page_offset(0, 256);
+    let mut tag = [0u8; 4];
+    assert_eq!(read_at_most(&file, &mut tag, offset), 4);
+    assert_eq!(unsafe{ read::<u32>(tag.as_ptr() as *const u32) }, COMPRESSED_PAGE_TAG);
+    check_ok!(write_all_at(&file, &[0xff; 4], offset + 4));
+
+    let mut manager = manager.borrow_mut();
+    let err = manager.file_manager.get_tuple_data(&table_name, 0).unwrap_err();
+    assert_eq!(err.error_type, StoreErrorType::Corrupted);
+    assert!(err.error_msg.contains("truncated"), "{}", err.error_msg);
+    assert_pattern!(manager.file_manager.get_slot_bytes(&table_name, 0), Err(..));
+    assert_pattern!(manager.file_manager.scan_slots(&table_name).next(), Some(Err(..)));
+    let file = manager.file_manager.get_file(&table_name);
+    assert!(file.borrow().loaded_pages.get(&0).is_none());
+    assert_eq!(manager.file_manager.get_unpinned_num(), 2);
+}
+
+#[test]
+fn test_background_flush() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        flush_interval_ms = 10
+        table_meta_dir = "test_file/table_meta/test_background_flush/"
+        table_file_dir = "test_file/table_file/test_background_flush/""#.to_string());
+    let table_name = "test_file_flush_message".to_string();
+    remove_file("test_file/table_file/test_background_flush/test_file_flush_message.table").ok();
+    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    test_query::insert_data(&table_name, &manager);
+    let mut update = gen_plan_helper!(
+        "update test_file_flush_message set content = \"flushed\" where id = 777", &manager);
+    update.open();
+    while let Some(..) = update.get_next() {}
+
+    // wait for the flusher, no checkpoint here
+    let page_size = get_page_size();
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    let mut flushed = false;
+    for _ in 0..200 {
+        sleep(Duration::from_millis(10));
+        let mut data = Vec::new();
+        File::open("test_file/table_file/test_background_flush/test_file_flush_message.table")
+            .unwrap().read_to_end(&mut data).unwrap();
+        flushed = (0..2).all(|page_index| {
+            let mem_page = file.borrow().loaded_pages.get(&page_index).unwrap().mem_page.clone();
+            let page_data = unsafe{
+                from_raw_parts::<u8>(mem_page.borrow().data as *const u8, page_size)
+            };
+            // the file has the checksum stamped in, which the page in memory doesn't
+            let saved = encode_page(page_data, false, file.borrow().checksum);
+            let offset = page_size * (page_index + 1);
+            data.len() >= offset + page_size && data[offset..offset + page_size] == saved[..]
+        });
+        if flushed { break; }
+    }
+    assert!(flushed);
+    let stats = manager.borrow().file_manager.get_flush_stats();
+    assert!(stats.background >= 2);
+    assert_eq!(stats.foreground, 0);
+
+    // pages written in background are clean
+    manager.borrow_mut().save_to_file();
+    assert_eq!(manager.borrow().file_manager.get_flush_stats().foreground, 0);
+}
+
+#[test]
+fn test_double_write_recovery() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        double_write = true
+        table_meta_dir = "test_file/table_meta/test_double_write/"
+        table_file_dir = "test_file/table_file/test_double_write/""#.to_string());
+    let table_name = "test_file_double_write_message".to_string();
+    let table_path = "test_file/table_file/test_double_write/test_file_double_write_message.table";
+    let buffer_path = "test_file/table_file/test_double_write/double_write.buf".to_string();
+    remove_file(table_path).ok();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file();
+    }
+    assert_eq!(metadata(&buffer_path).unwrap().len(), 0);
+
+    // crash after the buffer is synced but before the final copy is completely written
+    let page_size = get_page_size();
+    let file = Arc::new(OpenOptions::new().read(true).write(true).open(table_path).unwrap());
+    let mut good_page = vec![0u8; page_size];
+    assert_eq!(read_at_most(&file, &mut good_page, get_page_offset(0, page_size)), page_size);
+    {
+        let mut buffer = DoubleWriteBuffer::open(&buffer_path);
+        buffer.write_buffer(&vec![PageWrite{
+            file : file.clone(),
+            path : table_path.to_string(),
+            offset : get_page_offset(0, page_size),
+            data : good_page.clone(),
+            writer : os_file_writer(),
+        }]).unwrap();
+    }
+    write_all_at(&file, &vec![0xab; page_size / 2], get_page_offset(0, page_size)).unwrap();
+    // a torn record at the end of buffer is ignored
+    OpenOptions::new().append(true).open(&buffer_path).unwrap().write_all(&[7, 0, 0]).unwrap();
+
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    assert_eq!(manager.borrow().file_manager.get_recovered_page_num(), 1);
+    assert_eq!(metadata(&buffer_path).unwrap().len(), 0);
+    let mut restored_page = vec![0u8; page_size];
+    read_at_most(&file, &mut restored_page, get_page_offset(0, page_size));
+    assert!(restored_page == good_page);
+
+    let mut query = gen_plan_helper!("select * from test_file_double_write_message", &manager);
+    query.open();
+    let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    assert_pattern!(query.get_next(), None);
+    assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
+    assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+    assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+}
+
+#[test]
+fn test_fill_factor() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        default_fill_factor = 70
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_fill_factor/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut full_table = gen_test_table();
+    full_table.name = "test_file_full_message".to_string();
+    full_table.fill_factor = Some(100);
+    let mut sparse_table = gen_test_table();
+    sparse_table.name = "test_file_sparse_message".to_string();  // use default_fill_factor
+    for table in vec![full_table, sparse_table] {
+        let table_name = table.name.clone();
+        manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+        let file = manager.get_file(&table_name);
+        let slot_sum = file.borrow().get_page_slot_sum();
+        assert_eq!(slot_sum, 253);
+        for i in 0..slot_sum {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(i as i32)),
+                ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+                ValueExpr::from_literal(Literal::Float(666.666)),
+            ];
+            manager.insert(&table_name, &value_list);
+        }
+    }
+    let full_file = manager.get_file(&"test_file_full_message".to_string());
+    assert_eq!(full_file.borrow().page_sum, 1);
+    let sparse_file = manager.get_file(&"test_file_sparse_message".to_string());
+    assert_eq!(sparse_file.borrow().page_sum, 2);
+    // 253 * 0.7 = 177.1
+    assert_eq!(sparse_file.borrow().loaded_pages.get(&0).unwrap().bitmap.get_inuse_num(), 178);
+    assert_eq!(sparse_file.borrow().loaded_pages.get(&1).unwrap().bitmap.get_inuse_num(), 253 - 178);
+}
+
+#[test]
+fn test_get_tuple_value_multi_page() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_get_tuple_value_multi_page/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_multi_page_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let slot_sum = manager.get_file(&table_name).borrow().get_page_slot_sum();
+    // three pages, more than the memory pool can hold
+    for i in 0..(slot_sum * 3) {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list);
+    }
+    let file = manager.get_file(&table_name);
+    assert_eq!(file.borrow().page_sum, 3);
+    for &(page_index, tuple_index) in [(0, 0), (2, 7), (1, slot_sum - 1), (0, 5), (2, slot_sum - 1)].iter() {
+        let position = file.borrow().gen_position(page_index, tuple_index);
+        assert_eq!(file.borrow().split_position(position), (page_index, tuple_index));
+        let expected = (page_index * slot_sum + tuple_index) as i32;
+        let id = manager.get_tuple_value(&table_name, position, 0);
+        assert_eq!(extract!(id, TupleValue::Int(id), id), expected);
+        let content = manager.get_tuple_value(&table_name, position, 1);
+        assert_eq!(extract!(content, TupleValue::Char(ref s), s), "abcdef");
+    }
+}
+
+#[test]
+fn test_evicted_page_on_disk() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_evicted_page_on_disk/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_evicted_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let slot_sum = manager.get_file(&table_name).borrow().get_page_slot_sum();
+    for i in 0..(slot_sum * 3) {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list);
+    }
+    let file = manager.get_file(&table_name);
+    assert!(file.borrow().loaded_pages.get(&0).is_none());
+
+    // the whole page is in the file before save_to_file
+    let page_size = file.borrow().page_size;
+    let mut data = vec![0u8; page_size];
+    let saved = File::open("test_file/table_file/test_evicted_page_on_disk/test_file_evicted_message.table")
+        .unwrap();
+    assert_eq!(read_at_most(&saved, &mut data, get_page_offset(0, page_size)), page_size);
+    let page = unsafe{ PageBuf::new(data.as_mut_ptr() as DataPtr, page_size) };
+    assert_eq!(page.get_u32(0) as usize, slot_sum);
+    assert_eq!(page.get_u32(4) as usize, slot_sum);  // full
+
+    // and read back when the page is loaded again
+    for &tuple_index in [0, 5, slot_sum - 1].iter() {
+        let position = file.borrow().gen_position(0, tuple_index);
+        let id = manager.get_tuple_value(&table_name, position, 0);
+        assert_eq!(extract!(id, TupleValue::Int(id), id), tuple_index as i32);
+        let score = manager.get_tuple_value(&table_name, position, 2);
+        assert_eq!(extract!(score, TupleValue::Float(f), f), 666.666);
+    }
+    assert!(file.borrow().loaded_pages.get(&0).is_some());
+}
+
+#[test]
+fn test_delete_evicted_page() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_delete_evicted_page/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_delete_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let slot_sum = manager.get_file(&table_name).borrow().get_page_slot_sum();
+    for i in 0..(slot_sum * 3) {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list);
+    }
+    let file = manager.get_file(&table_name);
+    assert!(file.borrow().loaded_pages.get(&0).is_none());  // evicted by the later pages
+    let position = file.borrow().gen_position(0, 3);
+    assert_pattern!(manager.delete(&table_name, position), Ok(()));
+    assert!(!file.borrow().is_inuse(0, 3));
+    assert!(file.borrow().is_inuse(0, 4));
+    assert_eq!(manager.get_unpinned_num(), 2);
+    // deleted already
+    assert_pattern!(manager.delete(&table_name, position), Err(..));
+    let position = file.borrow().gen_position(3, 0);
+    assert_pattern!(manager.delete(&table_name, position), Err(..));
+
+    // the deletion is saved when the page is evicted again
+    let logs = capture_logs(|| {
+        for &page_index in [1, 2, 0].iter() {
+            let position = file.borrow().gen_position(page_index, 4);
+            manager.get_tuple_value(&table_name, position, 0);
+        }
+    });
+    assert!(!file.borrow().is_inuse(0, 3));
+    assert!(logs.iter().any(|l| l.starts_with("debug blastoise::store::file: evict page 0 of ")
+        && l.contains("test_file_delete_message.table after writing it")));
+}
+
+#[test]
+fn test_reuse_free_pages() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_reuse_free_pages/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_reuse_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let file = manager.get_file(&table_name);
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let insert = |manager : &mut TableFileManager, i : usize| {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list)
+    };
+    for i in 0..(slot_sum * 2) {
+        insert(&mut manager, i);
+    }
+    assert_eq!(file.borrow().page_sum, 2);
+    assert_eq!(file.borrow().first_free_page, 1);
+
+    for i in 0..slot_sum {
+        let position = file.borrow().gen_position(0, i);
+        assert_pattern!(manager.delete(&table_name, position), Ok(()));
+    }
+    assert_eq!(file.borrow().free_pages, vec![0]);
+    // the emptied page is filled again before the file grows
+    assert_eq!(insert(&mut manager, 0), file.borrow().gen_position(0, 0));
+    for i in 1..slot_sum {
+        insert(&mut manager, i);
+    }
+    assert_eq!(file.borrow().page_sum, 2);
+    assert_eq!(file.borrow().loaded_pages.get(&0).unwrap().bitmap.get_inuse_num(), slot_sum);
+    insert(&mut manager, slot_sum * 2);
+    assert_eq!(file.borrow().page_sum, 3);
+    assert!(file.borrow().free_pages.is_empty());
+
+    // saved after the header
+    let position = file.borrow().gen_position(1, 7);
+    assert_pattern!(manager.delete(&table_name, position), Ok(()));
+    file.borrow_mut().save_to_file(&None);
+    let page_size = file.borrow().page_size;
+    assert_eq!(read_free_pages(&file.borrow().file, page_size), vec![1]);
+    assert_eq!(insert(&mut manager, 7), position);
+}
+
+#[test]
+fn test_evict_write_failed() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_evict_write_failed/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let writer = Arc::new(FullDiskWriter{ limit : AtomicUsize::new(usize::max_value()) });
+    manager.set_writer(writer.clone());
+    let mut table = gen_test_table();
+    table.name = "test_file_full_disk_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let file = manager.get_file(&table_name);
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let gen_row = |i : usize| vec![
+        ValueExpr::from_literal(Literal::Int(i as i32)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    for i in 0..(slot_sum * 2) {
+        manager.insert(&table_name, &gen_row(i));
+    }
+
+    // both pages in the pool are dirty and can't be written for the new one
+    let page_size = file.borrow().page_size;
+    writer.limit.store(page_size, Ordering::SeqCst);
+    let err = extract!(manager.try_insert(&table_name, &gen_row(slot_sum * 2)), Err(err), err);
+    assert_eq!(err.error_type, StoreErrorType::EvictFailed);
+    assert!(err.error_msg.contains("os error"));
+    assert_eq!(file.borrow().page_sum, 2);
+    assert_eq!(file.borrow().get_dirty_pages(), vec![0, 1]);
+    assert!(file.borrow().read_only.is_some());
+    // still readable
+    let position = file.borrow().gen_position(1, 5);
+    assert_eq!(manager.get_tuple_value(&table_name, position, 0), TupleValue::Int((slot_sum + 5) as i32));
+    let err = extract!(manager.try_insert(&table_name, &gen_row(slot_sum * 2)), Err(err), err);
+    assert_eq!(err.error_type, StoreErrorType::ReadOnly);
+    assert_pattern!(manager.check_writable(&table_name), Err(..));
+    assert_eq!(manager.flush_all().len(), 1);
+    assert!(file.borrow().read_only.is_some());
+
+    // writable again after a flush succeeds
+    writer.limit.store(usize::max_value(), Ordering::SeqCst);
+    assert!(manager.flush_all().is_empty());
+    assert!(file.borrow().read_only.is_none());
+    assert_pattern!(manager.try_insert(&table_name, &gen_row(slot_sum * 2)), Ok(..));
+    assert_eq!(file.borrow().page_sum, 3);
+    let mut data = vec![0u8; page_size];
+    read_at_most(&file.borrow().file, &mut data, get_page_offset(1, page_size));
+    assert_eq!(&data[8..12], &encode_u32_list(&[page_checksum(&data)])[..]);
+}
+
+#[test]
+fn test_shrink_file() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_shrink_file/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_shrink_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let file = manager.get_file(&table_name);
+    let file_id = file.borrow().get_id();
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let gen_row = |i : usize| vec![
+        ValueExpr::from_literal(Literal::Int(i as i32)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    for i in 0..(slot_sum * 4) {
+        manager.insert(&table_name, &gen_row(i));
+    }
+    manager.save_all();
+    let path = manager.get_file_path(&table_name);
+    let page_size = file.borrow().page_size;
+    assert_eq!(metadata(&path).unwrap().len(), get_page_offset(4, page_size));
+    assert_eq!(manager.shrink_file(&table_name).unwrap(), 0);
+
+    // the last page with rows deleted gets the next insert
+    for page_index in [2, 3, 1].iter() {
+        for i in 0..slot_sum {
+            let position = file.borrow().gen_position(*page_index, i);
+            assert_pattern!(manager.delete(&table_name, position), Ok(()));
+        }
+    }
+    let position = file.borrow().gen_position(1, 0);
+    manager.insert(&table_name, &gen_row(0));
+    assert_eq!(manager.shrink_file(&table_name).unwrap(), 2);
+    assert_eq!(file.borrow().page_sum, 2);
+    assert_eq!(metadata(&path).unwrap().len(), get_page_offset(2, page_size));
+    assert_eq!(read_file_header(&file.borrow().file).unwrap().page_sum, 2);
+    assert!(file.borrow().free_pages.iter().all(|page_index| *page_index < 2));
+    assert!(file.borrow().first_free_page <= 2);
+    assert!(file.borrow().loaded_pages.get(&2).is_none() && file.borrow().loaded_pages.get(&3).is_none());
+    assert!(manager.page_pool.get_page(file_id, 3).is_none());
+    assert_eq!(manager.shrink_file(&table_name).unwrap(), 0);
+
+    // the inserts go on from the pages left
+    assert_eq!(manager.insert(&table_name, &gen_row(1)), file.borrow().gen_position(1, 1));
+    assert!(file.borrow().is_inuse(1, 0));
+    for i in 2..(slot_sum + 1) {
+        manager.insert(&table_name, &gen_row(i));
+    }
+    assert_eq!(file.borrow().page_sum, 3);
+    manager.save_all();
+    assert_eq!(metadata(&path).unwrap().len(), get_page_offset(3, page_size));
+    assert_eq!(manager.get_tuple_value(&table_name, position, 0), TupleValue::Int(0));
+}
+
+#[test]
+fn test_interleaved_insert() {
+    // the first free page of one table is evicted by the inserts of the other one
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_interleaved_insert/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table_names = vec!["test_file_interleaved_a".to_string(), "test_file_interleaved_b".to_string()];
+    for table_name in table_names.iter() {
+        let mut table = gen_test_table();
+        table.name = table_name.clone();
+        table.fill_factor = Some(100);
+        manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    }
+    let slot_sum = manager.get_file(&table_names[0]).borrow().get_page_slot_sum();
+    for i in 0..(slot_sum * 2 + 10) {
+        for (j, table_name) in table_names.iter().enumerate() {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int((i * 2 + j) as i32)),
+                ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+                ValueExpr::from_literal(Literal::Float(666.666)),
+            ];
+            manager.insert(table_name, &value_list);
+        }
+    }
+    for (j, table_name) in table_names.iter().enumerate() {
+        let file = manager.get_file(table_name);
+        assert_eq!(file.borrow().page_sum, 3);
+        for &i in [0, slot_sum - 1, slot_sum, slot_sum * 2 + 9].iter() {
+            let v = manager.get_tuple_value(table_name, i, 0);
+            assert_eq!(extract!(v, TupleValue::Int(n), n), (i * 2 + j) as i32);
+        }
+    }
+}
+
+#[test]
+fn test_get_next_position() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_get_next_position/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_next_position_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let slot_sum = manager.get_file(&table_name).borrow().get_page_slot_sum();
+    let tuple_sum = slot_sum * 5;
+    for i in 0..tuple_sum {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list);
+    }
+    let mut ids = Vec::new();
+    let mut from = 0;
+    while let Some((tuple_data, position)) = check_ok!(manager.get_next_tuple_data(&table_name, from)) {
+        ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) } as usize);
+        from = position + 1;
+    }
+    assert_eq!(ids, (0..tuple_sum).collect::<Vec<usize>>());
+}
+
+#[test]
+fn test_scan_slots() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 3
+        page_size = 256"#.to_string());
+    let table_name = "scan_slots_message".to_string();
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_scan_slots")));
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    let mut positions = Vec::new();
+    for i in 0..40 {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i)),
+            ValueExpr::from_literal(Literal::Float(i as f32)),
+            ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+        ];
+        positions.push(check_ok!(manager.borrow_mut().insert(&table_name, &value_list)));
+    }
+    // deleted slots in every page, and a page with all its slots deleted
+    let slot_sum = manager.borrow_mut().file_manager.get_file(&table_name).borrow().get_page_slot_sum();
+    let mut kept = Vec::new();
+    for (i, position) in positions.iter().enumerate() {
+        if i % 3 == 0 || *position / slot_sum == 1 {
+            check_ok!(manager.borrow_mut().file_manager.delete(&table_name, *position));
+        } else {
+            kept.push(TupleValue::Int(i as i32));
+        }
+    }
+    assert!(manager.borrow_mut().file_manager.get_file(&table_name).borrow().page_sum > 3);
+
+    let slots : Vec<(usize, usize, Vec<TupleValue>)> =
+        check_ok!(manager.borrow_mut().file_manager.scan_slots(&table_name).collect::<Result<Vec<_>, _>>());
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 3);
+    let mut scanned = Vec::new();
+    {
+        let mut query = gen_plan_helper!("select * from scan_slots_message", &manager);
+        query.open();
+        while let Some(tuple_data) = query.get_next() {
+            let (page_index, slot) = manager.borrow_mut().file_manager.get_file(&table_name).borrow()
+                .split_position(query.get_position().unwrap());
+            let attr_desc = vec![AttrType::Int, AttrType::Float, AttrType::Char{ len : 16 }];
+            scanned.push((page_index, slot, gen_tuple_value(&attr_desc, tuple_data)));
+        }
+        query.close();
+    }
+    assert_eq!(slots, scanned);
+    assert_eq!(slots.iter().map(|&(_, _, ref row)| row[0].clone()).collect::<Vec<TupleValue>>(), kept);
+
+    // the page being read is unpinned when the iterator is dropped in the middle
+    {
+        let mut manager = manager.borrow_mut();
+        let mut iter = manager.file_manager.scan_slots(&table_name);
+        let (page_index, slot, _) = check_ok!(iter.next().unwrap());
+        assert_eq!((page_index, slot), (0, 1));
+    }
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 3);
+}
+
+#[test]
+fn test_scan_prefetch() {
+    let table_name = "test_file_prefetch_message".to_string();
+    let gen_config = |extra : &str| Config::new(&format!(r#"
+        max_memory_pool_page_num = 8
+        page_size = 256
+        scan_prefetch_pages = 3
+        table_meta_dir = "test_file/table_meta/test_prefetch/"
+        table_file_dir = "test_file/table_file/test_prefetch/"
+        {}"#, extra));
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&gen_config(""))));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        for i in 0..60 {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(i)),
+                ValueExpr::from_literal(Literal::Float(i as f32)),
+                ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+            ];
+            check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+        }
+        manager.borrow_mut().save_to_file();
+    }
+    // every scan starts with a cold pool
+    let scan = |mode : &str| {
+        let config = gen_config(&format!("scan_prefetch = \"{}\"", mode));
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        let mut query = gen_plan_helper!("select * from test_file_prefetch_message", &manager);
+        query.open();
+        let mut ids = Vec::new();
+        while let Some(tuple_data) = query.get_next() {
+            ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+        }
+        query.close();
+        (manager, ids)
+    };
+    let (none_manager, none_ids) = scan("none");
+    let (pool_manager, pool_ids) = scan("pool");
+    let (fadvise_manager, fadvise_ids) = scan("fadvise");
+    assert_eq!(none_ids, (0..60).collect::<Vec<i32>>());
+    assert_eq!(pool_ids, none_ids);
+    assert_eq!(fadvise_ids, none_ids);
+
+    let page_sum = none_manager.borrow_mut().file_manager.get_file(&table_name).borrow().page_sum;
+    assert_eq!(page_sum, 6);
+    let none_stats = none_manager.borrow().file_manager.get_storage_stats();
+    assert_eq!((none_stats.page_misses, none_stats.page_prefetches), (6, 0));
+    // only the first page is missed, the others are hits when the scan reaches them
+    let pool_stats = pool_manager.borrow().file_manager.get_storage_stats();
+    assert_eq!((pool_stats.page_misses, pool_stats.page_prefetches), (1, 5));
+    assert_eq!(pool_stats.page_hits, none_stats.page_hits + 5);
+    assert_eq!(pool_stats.page_evictions, 0);
+    let fadvise_stats = fadvise_manager.borrow().file_manager.get_storage_stats();
+    assert_eq!((fadvise_stats.page_misses, fadvise_stats.page_prefetches), (6, 0));
+
+    // the pages read ahead are the same as the ones read on demand
+    let none_file = none_manager.borrow_mut().file_manager.get_file(&table_name);
+    let pool_file = pool_manager.borrow_mut().file_manager.get_file(&table_name);
+    for page_index in 0..page_sum {
+        let none_file = none_file.borrow();
+        let pool_file = pool_file.borrow();
+        assert_eq!(pool_file.loaded_pages.get(&page_index).unwrap().page_buf.as_slice(),
+            none_file.loaded_pages.get(&page_index).unwrap().page_buf.as_slice());
+    }
+}
+
+#[test]
+fn test_prefetch_never_evicts() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 3
+        scan_prefetch = "pool"
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_prefetch_never_evicts/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_prefetch_evict_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let slot_sum = manager.get_file(&table_name).borrow().get_page_slot_sum();
+    for i in 0..(slot_sum * 5) {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i as i32)),
+            ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+            ValueExpr::from_literal(Literal::Float(666.666)),
+        ];
+        manager.insert(&table_name, &value_list);
+    }
+    let file = manager.get_file(&table_name);
+    let loaded = |file : &TableFileRef| {
+        let mut pages : Vec<usize> = file.borrow().loaded_pages.keys().cloned().collect();
+        pages.sort();
+        pages
+    };
+    assert_eq!(loaded(&file), vec![2, 3, 4]);
+    // the pool is full, nothing is read ahead
+    let before = manager.get_storage_stats();
+    manager.prefetch(&table_name, 0..2);
+    assert_eq!(loaded(&file), vec![2, 3, 4]);
+    assert_eq!(manager.get_storage_stats().page_evictions, before.page_evictions);
+    assert_eq!(manager.get_storage_stats().page_prefetches, 0);
+    // the pages out of the file are skipped
+    manager.prefetch(&table_name, 5..8);
+    assert_eq!(file.borrow().page_sum, 5);
+}
+
+#[test]
+fn test_mmap_backend() {
+    // the files written through the mappings are read by the page pool and the other way round
+    let table_name = "test_file_mmap_message".to_string();
+    let gen_config = |backend : &str| Config::new(&format!(r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        storage_backend = "{}"
+        table_meta_dir = "test_file/table_meta/test_mmap/"
+        table_file_dir = "test_file/table_file/test_mmap/""#, backend));
+    let scan = |manager : &TableManagerRef| {
+        let mut query = gen_plan_helper!("select * from test_file_mmap_message", manager);
+        query.open();
+        let mut ids = Vec::new();
+        while let Some(tuple_data) = query.get_next() {
+            ids.push(unsafe{ read::<i32>(tuple_data[0] as *const i32) });
+        }
+        query.close();
+        ids
+    };
+    let mut expected : Vec<i32> = (0..50).filter(|&i| i != 0 && i != 25).collect();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&gen_config("mmap"))));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        for i in 0..50 {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(i)),
+                ValueExpr::from_literal(Literal::Float(i as f32)),
+                ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+            ];
+            check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+        }
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        assert!(file.borrow().is_mapped());
+        assert_eq!(file.borrow().page_sum, 5);
+        // the pages evicted before are found in the mapping again
+        assert_pattern!(manager.borrow_mut().file_manager.delete(&table_name, 0), Ok(()));
+        assert_pattern!(manager.borrow_mut().file_manager.delete(&table_name, 25), Ok(()));
+        assert_eq!(scan(&manager), expected);
+        manager.borrow_mut().save_to_file();
+        assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 0);
+    }
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config("buffered"))));
+        assert!(!manager.borrow_mut().file_manager.get_file(&table_name).borrow().is_mapped());
+        assert_eq!(scan(&manager), expected);
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(50)),
+            ValueExpr::from_literal(Literal::Float(50.0)),
+            ValueExpr::from_literal(Literal::Str("row50".to_string())),
+        ];
+        check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+        manager.borrow_mut().save_to_file();
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&gen_config("mmap"))));
+    // in the slot freed by 25, the page of the last delete is tried first
+    expected.insert(24, 50);
+    assert_eq!(scan(&manager), expected);
+}
+
+#[test]
+fn test_mmap_grow() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        page_size = 256
+        storage_backend = "mmap"
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file/test_mmap_grow/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let mut table = gen_test_table();
+    table.name = "test_file_mmap_grow_message".to_string();
+    table.fill_factor = Some(100);
+    let table_name = table.name.clone();
+    manager.create_file(table_name.clone(), Rc::new(RefCell::new(table)));
+    let gen_value_list = |i : usize| vec![
+        ValueExpr::from_literal(Literal::Int(i as i32)),
+        ValueExpr::from_literal(Literal::Str("abcdef".to_string())),
+        ValueExpr::from_literal(Literal::Float(666.666)),
+    ];
+    manager.insert(&table_name, &gen_value_list(0));
+    let file = manager.get_file(&table_name);
+    let old_data = check_ok!(manager.get_tuple_data(&table_name, 0)).unwrap();
+    assert_eq!(file.borrow().mapped.as_ref().unwrap().get_mapping_num(), 1);
+
+    // over the first mapping, which is kept for old_data
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let page_num = get_page_size() / 256 + 2;
+    for i in 1..(slot_sum * page_num) {
+        manager.insert(&table_name, &gen_value_list(i));
+    }
+    assert!(file.borrow().mapped.as_ref().unwrap().get_mapping_num() > 1);
+    assert!(file.borrow().loaded_pages.get(&0).is_none());
+    assert_eq!(unsafe{ read::<i32>(old_data[0] as *const i32) }, 0);
+
+    // written through the latest mapping, seen through the first one
+    let mut before = check_ok!(manager.get_slot_bytes(&table_name, 0));
+    unsafe{ write::<i32>(before.as_mut_ptr() as *mut i32, 233) };
+    check_ok!(manager.undo(&table_name, &UndoRecord{
+        op : UndoOp::Update,
+        position : 0,
+        before : before,
+        free_pages : None,
+    }));
+    assert_eq!(unsafe{ read::<i32>(old_data[0] as *const i32) }, 233);
+
+    // the file only grows by the pages, not by the mappings
+    manager.save_all();
+    let page_sum = file.borrow().page_sum;
+    assert_eq!(page_sum, page_num);
+    let path = file.borrow().saved_name.clone();
+    assert_eq!(metadata(&path).unwrap().len(), get_page_offset(page_sum, 256));
+}
+
+#[test]
+fn test_dirty_pages() {
+    let table_name = "test_file_dirty_pages".to_string();
+    // all the pages stay in the pool, 10 slots in a page
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 8
+        page_size = 256"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, &table_name)));
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    for i in 0..50 {
+        let value_list = vec![
+            ValueExpr::from_literal(Literal::Int(i)),
+            ValueExpr::from_literal(Literal::Float(i as f32)),
+            ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+        ];
+        check_ok!(manager.borrow_mut().insert(&table_name, &value_list));
+    }
+    let dirty_pages = |manager : &TableManagerRef|
+        manager.borrow_mut().file_manager.get_dirty_pages(&table_name);
+    assert_eq!(dirty_pages(&manager), vec![0, 1, 2, 3, 4]);
+    manager.borrow_mut().save_to_file();
+    assert_eq!(dirty_pages(&manager), Vec::<usize>::new());
+
+    let run = |sql : &str| {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        plan.open();
+        while plan.get_next().is_some() {}
+        plan.close();
+    };
+    run("select * from test_file_dirty_pages where score > 10.0");
+    assert_eq!(dirty_pages(&manager), Vec::<usize>::new());
+    run("update test_file_dirty_pages set score = 0.5 where id = 15 or id = 35");
+    assert_eq!(dirty_pages(&manager), vec![1, 3]);
+    run("delete from test_file_dirty_pages where id = 42");
+    assert_eq!(dirty_pages(&manager), vec![1, 3, 4]);
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 3);
+
+    // only the dirty pages are written
+    let flushed = manager.borrow().file_manager.get_flush_stats().foreground;
+    manager.borrow_mut().save_to_file();
+    assert_eq!(manager.borrow().file_manager.get_flush_stats().foreground - flushed, 3);
+    assert_eq!(dirty_pages(&manager), Vec::<usize>::new());
+}
+
+#[test]
+fn test_flush_all() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 8
+        page_size = 256"#.to_string());
+    let manager = Rc::new(RefCell::new(TableManager::in_temp_dir(&config, "test_flush_all")));
+    let tables = vec![("test_file_flush_all1".to_string(), 2), ("test_file_flush_all2".to_string(), 3)];
+    for &(ref table_name, page_num) in tables.iter() {
+        manager.borrow_mut().add_table(test_query::gen_test_table(table_name));
+        for i in 0..page_num * 10 - 5 {
+            let value_list = vec![
+                ValueExpr::from_literal(Literal::Int(i)),
+                ValueExpr::from_literal(Literal::Float(i as f32)),
+                ValueExpr::from_literal(Literal::Str(format!("row{}", i))),
+            ];
+            check_ok!(manager.borrow_mut().insert(table_name, &value_list));
+        }
+    }
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 5);
+
+    let flushed = manager.borrow().file_manager.get_flush_stats().foreground;
+    assert_eq!(manager.borrow_mut().file_manager.flush_all().len(), 0);
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 0);
+    assert_eq!(manager.borrow().file_manager.get_flush_stats().foreground - flushed, 5);
+    for &(ref table_name, page_num) in tables.iter() {
+        let file = manager.borrow_mut().file_manager.get_file(table_name);
+        let path = file.borrow().saved_name.clone();
+        let first_free_page = file.borrow().first_free_page;
+        assert_eq!(read_file_header(&File::open(&path).unwrap()), Ok(FileHeader{
+            page_sum : page_num as usize,
+            first_free_page : first_free_page,
+            page_size : 256,
+            checksum : true,
+            tuple_len : 24,
+        }));
+        assert_eq!(metadata(&path).unwrap().len(), get_page_offset(page_num as usize, 256));
+    }
+    // nothing left to write
+    assert_eq!(manager.borrow_mut().flush_all().len(), 0);
+    assert_eq!(manager.borrow().file_manager.get_flush_stats().foreground - flushed, 5);
+}
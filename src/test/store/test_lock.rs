@@ -0,0 +1,53 @@
+use std::thread::{spawn, sleep};
+use std::time::{Duration, Instant};
+use ::store::lock::{TableLocks, LockMode, lock_tables};
+
+
+fn requests(tables : &[(&str, LockMode)]) -> Vec<(String, LockMode)> {
+    tables.iter().map(|&(table, mode)| (table.to_string(), mode)).collect()
+}
+
+#[test]
+fn test_lock_modes() {
+    let locks = TableLocks::make_ref();
+    let first = lock_tables(&locks, requests(&[("a", LockMode::Shared)]), 0).unwrap();
+    let both = requests(&[("a", LockMode::Shared), ("b", LockMode::Shared)]);
+    let second = lock_tables(&locks, both, 0).unwrap();
+    assert_eq!(lock_tables(&locks, requests(&[("a", LockMode::Exclusive)]), 0).unwrap_err(),
+        "table a is in use");
+    // none of the tables is locked when one of them is in use
+    let both = requests(&[("c", LockMode::Exclusive), ("a", LockMode::Exclusive)]);
+    assert!(lock_tables(&locks, both, 0).is_err());
+    let c = lock_tables(&locks, requests(&[("c", LockMode::Exclusive)]), 0).unwrap();
+    assert!(lock_tables(&locks, requests(&[("c", LockMode::Shared)]), 0).is_err());
+    drop(c);
+    drop(first);
+    assert!(lock_tables(&locks, requests(&[("a", LockMode::Exclusive)]), 0).is_err());
+    drop(second);
+    // exclusive wins for a table requested in both modes
+    let both = requests(&[("a", LockMode::Shared), ("a", LockMode::Exclusive)]);
+    let a = lock_tables(&locks, both, 0).unwrap();
+    assert!(lock_tables(&locks, requests(&[("a", LockMode::Shared)]), 0).is_err());
+    drop(a);
+    let both = requests(&[("a", LockMode::Exclusive), ("c", LockMode::Exclusive)]);
+    assert!(lock_tables(&locks, both, 0).is_ok());
+}
+
+#[test]
+fn test_lock_wait() {
+    let locks = TableLocks::make_ref();
+    let shared = lock_tables(&locks, requests(&[("t", LockMode::Shared)]), 0).unwrap();
+    let start = Instant::now();
+    assert!(lock_tables(&locks, requests(&[("t", LockMode::Exclusive)]), 50).is_err());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    // granted as soon as the holder releases it
+    let holder = spawn(move || {
+        sleep(Duration::from_millis(50));
+        drop(shared);
+    });
+    let start = Instant::now();
+    assert!(lock_tables(&locks, requests(&[("t", LockMode::Exclusive)]), 10000).is_ok());
+    assert!(start.elapsed() < Duration::from_millis(5000));
+    is_match!(holder.join(), Ok(..));
+}
@@ -1,143 +1,298 @@
-use std::collections::HashMap;
-use rustc_serialize::json::{encode, decode};
-use ::store::table::{Table, Attr, AttrType, TableManager};
-use ::test::utils::remove_blanks;
-use ::utils::config::Config;
-
-
-#[test]
-fn test_attr_type() {
-    {
-        let json = extract!(encode(&AttrType::Int), Ok(s), s);
-        assert_eq!(json, "{\"type\":\"Int\"}");
-        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
-        assert_pattern!(attr, AttrType::Int);
-    }
-    {
-        let json = extract!(encode(&AttrType::Float), Ok(s), s);
-        assert_eq!(json, "{\"type\":\"Float\"}");
-        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
-        assert_pattern!(attr, AttrType::Float);
-    }
-    {
-        let json = extract!(encode(&AttrType::Char{len:233}), Ok(s), s);
-        assert_eq!(json, "{\"len\":\"233\",\"type\":\"Char\"}");
-        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
-        assert_pattern!(attr, AttrType::Char{len:233});
-    }
-}
-
-const JSON_DATA : &'static str = r#"
-    {
-        "author": {
-            "name": "author",
-            "attr_list": [
-                {
-                    "name": "id",
-                    "attr_type": { "type": "Int" },
-                    "primary": true,
-                    "nullable": false
-                },
-                {
-                    "name": "name",
-                    "attr_type": { "len": "10", "type": "Char" },
-                    "primary": false,
-                    "nullable": false
-                }
-            ]
-        },
-        "book": {
-            "name": "book",
-            "attr_list": [
-                {
-                    "name": "id",
-                    "attr_type": { "type": "Int" },
-                    "primary": true,
-                    "nullable": false
-                },
-                {
-                    "name": "author_id",
-                    "attr_type": { "type": "Int" },
-                    "primary": true,
-                    "nullable": true
-                }
-            ]
-        }
-    }
-    "#;
-
-#[test]
-fn test_json_translate() {
-    let t1 = Table{
-        name : "author".to_string(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "name".to_string(),
-                attr_type : AttrType::Char{ len : 10 },
-                primary : false,
-                nullable : false,
-            }
-        ],
-    };
-    let t2 = Table{
-        name : "book".to_string(),
-        attr_list : vec![
-            Attr{
-                name : "id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : false,
-            },
-            Attr{
-                name : "author_id".to_string(),
-                attr_type : AttrType::Int,
-                primary : true,
-                nullable : true,
-            }
-        ]
-    };
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 5
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let mut manager = TableManager::new(&config);
-    manager.add_table(t1);
-    manager.add_table(t2);
-    assert_eq!(manager.to_json(), remove_blanks(JSON_DATA));
-
-    let gen_manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
-    assert_eq!(gen_manager.to_json(), remove_blanks(JSON_DATA));
-}
-
-#[test]
-fn test_get_table() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 5
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
-    let table = extract!(manager.get_table("book"), Some(table), table);
-    let table = table.borrow();
-    assert_eq!(table.name, "book");
-    assert_eq!(table.attr_list.len(), 2);
-}
-
-#[test]
-fn test_gen_table_set() {
-    let config = Config::new(&r#"
-        max_memory_pool_page_num = 5
-        table_meta_dir = "test_file/table_meta/"
-        table_file_dir = "test_file/table_file""#.to_string());
-    let manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
-    let mut used_table = Vec::new();
-    used_table.push("author".to_string());
-    used_table.push("book".to_string());
-    let set = manager.gen_table_set(&used_table);
-    assert_eq!(set.tables.len(), 2);
-}
+use std::collections::{HashMap, BTreeMap};
+use rustc_serialize::json::{encode, decode};
+use ::store::table::{Table, Attr, AttrType, TableManager, Privilege, check_catalog};
+use ::test::utils::remove_blanks;
+use ::utils::config::Config;
+
+
+#[test]
+fn test_attr_type() {
+    {
+        let json = extract!(encode(&AttrType::Int), Ok(s), s);
+        assert_eq!(json, "{\"type\":\"Int\"}");
+        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
+        assert_pattern!(attr, AttrType::Int);
+    }
+    {
+        let json = extract!(encode(&AttrType::Float), Ok(s), s);
+        assert_eq!(json, "{\"type\":\"Float\"}");
+        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
+        assert_pattern!(attr, AttrType::Float);
+    }
+    {
+        let json = extract!(encode(&AttrType::Char{len:233}), Ok(s), s);
+        assert_eq!(json, "{\"len\":\"233\",\"type\":\"Char\"}");
+        let attr = extract!(decode::<AttrType>(&json), Ok(attr), attr);
+        assert_pattern!(attr, AttrType::Char{len:233});
+    }
+}
+
+const JSON_DATA : &'static str = r#"
+    {
+        "author": {
+            "name": "author",
+            "attr_list": [
+                {
+                    "name": "id",
+                    "attr_type": { "type": "Int" },
+                    "primary": true,
+                    "nullable": false,
+                    "generated": null
+                },
+                {
+                    "name": "name",
+                    "attr_type": { "len": "10", "type": "Char" },
+                    "primary": false,
+                    "nullable": false,
+                    "generated": null
+                }
+            ],
+            "compression": null,
+            "fill_factor": null,
+            "stats": null,
+            "grants": null
+        },
+        "book": {
+            "name": "book",
+            "attr_list": [
+                {
+                    "name": "id",
+                    "attr_type": { "type": "Int" },
+                    "primary": true,
+                    "nullable": false,
+                    "generated": null
+                },
+                {
+                    "name": "author_id",
+                    "attr_type": { "type": "Int" },
+                    "primary": true,
+                    "nullable": true,
+                    "generated": null
+                }
+            ],
+            "compression": true,
+            "fill_factor": 70,
+            "stats": null,
+            "grants": { "alice": ["Select", "Insert"] }
+        }
+    }
+    "#;
+
+#[test]
+fn test_json_translate() {
+    let t1 = Table{
+        name : "author".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "name".to_string(),
+                attr_type : AttrType::Char{ len : 10 },
+                primary : false,
+                nullable : false,
+                generated : None,
+            }
+        ],
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    };
+    let t2 = Table{
+        name : "book".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+                generated : None,
+            },
+            Attr{
+                name : "author_id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : true,
+                generated : None,
+            }
+        ],
+        compression : Some(true),
+        fill_factor : Some(70),
+        stats : None,
+        grants : Some(vec![("alice".to_string(), vec![Privilege::Select, Privilege::Insert])]
+            .into_iter().collect()),
+    };
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let mut manager = TableManager::new(&config);
+    manager.add_table(t1);
+    manager.add_table(t2);
+    assert_eq!(manager.to_json(), remove_blanks(JSON_DATA));
+
+    let gen_manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
+    assert_eq!(gen_manager.to_json(), remove_blanks(JSON_DATA));
+    assert!(!gen_manager.get_table("author").unwrap().borrow().is_compressed());
+    assert!(gen_manager.get_table("book").unwrap().borrow().is_compressed());
+
+    let book = gen_manager.get_table("book").unwrap();
+    assert!(book.borrow().is_granted("alice", Privilege::Insert));
+    assert!(!book.borrow().is_granted("alice", Privilege::Delete));
+    assert!(!book.borrow().is_granted("bob", Privilege::Select));
+
+    // catalog saved before compression, generated and grants are added
+    let old_json = remove_blanks(JSON_DATA)
+        .replace(",\"generated\":null", "")
+        .replace(",\"compression\":null,\"fill_factor\":null,\"stats\":null,\"grants\":null", "")
+        .replace(",\"compression\":true,\"fill_factor\":70,\"stats\":null", "")
+        .replace(",\"grants\":{\"alice\":[\"Select\",\"Insert\"]}", "");
+    let old_manager = TableManager::from_json(&config, &old_json, false);
+    assert_pattern!(old_manager.get_table("book").unwrap().borrow().compression, None);
+    assert_pattern!(old_manager.get_table("book").unwrap().borrow().fill_factor, None);
+    assert!(old_manager.get_table("book").unwrap().borrow().stats.is_none());
+    assert!(old_manager.get_table("book").unwrap().borrow().grants.is_none());
+    assert!(old_manager.get_table("book").unwrap().borrow().attr_list[0].generated.is_none());
+}
+
+#[test]
+fn test_get_table() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
+    let table = extract!(manager.get_table("book"), Some(table), table);
+    let table = table.borrow();
+    assert_eq!(table.name, "book");
+    assert_eq!(table.attr_list.len(), 2);
+}
+
+#[test]
+fn test_gen_table_set() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::from_json(&config, &JSON_DATA.to_string(), false);
+    let mut used_table = Vec::new();
+    used_table.push("author".to_string());
+    used_table.push("book".to_string());
+    let set = manager.gen_table_set(&used_table);
+    assert_eq!(set.tables.len(), 2);
+}
+
+fn gen_catalog_table(name : &str, attr_names : &[&str]) -> Table {
+    Table{
+        name : name.to_string(),
+        attr_list : attr_names.iter().enumerate().map(|(i, attr)| Attr{
+            name : attr.to_string(),
+            attr_type : AttrType::Int,
+            primary : i == 0,
+            nullable : i != 0,
+            generated : None,
+        }).collect(),
+        compression : None,
+        fill_factor : None,
+        stats : None,
+        grants : None,
+    }
+}
+
+#[test]
+fn test_catalog_order() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5"#.to_string());
+    // the attributes are not sorted by name
+    let tables = vec![
+        gen_catalog_table("paper", &["id", "title", "author_id"]),
+        gen_catalog_table("author", &["id", "name"]),
+        gen_catalog_table("journal", &["z", "y", "x"]),
+        gen_catalog_table("book", &["id", "author_id"]),
+    ];
+    let orders = [[0, 1, 2, 3], [3, 2, 1, 0], [2, 0, 3, 1], [1, 3, 0, 2]];
+    let mut outputs = Vec::new();
+    for order in orders.iter() {
+        let mut manager = TableManager::in_temp_dir(&config, "test_catalog_order");
+        for &i in order.iter() {
+            manager.add_table(tables[i].clone());
+        }
+        assert_eq!(manager.get_table_names(), vec!["author", "book", "journal", "paper"]);
+        let used_table = order.iter().map(|&i| tables[i].name.clone()).collect();
+        let table_set = manager.gen_table_set(&used_table);
+        let names : Vec<&String> = table_set.tables.keys().collect();
+        assert_eq!(names, vec!["author", "book", "journal", "paper"]);
+        outputs.push((manager.to_json(), manager.show_tables()));
+        manager.drop_all();
+    }
+    for output in outputs.iter() {
+        assert_eq!(*output, outputs[0]);
+    }
+    let (ref json, ref show) = outputs[0];
+    // the attributes stay in the order of the declaration
+    assert_eq!(*show, "table: author\nid Int not null primary\nname Int null \n\n\
+        table: book\nid Int not null primary\nauthor_id Int null \n\n\
+        table: journal\nz Int not null primary\ny Int null \nx Int null \n\n\
+        table: paper\nid Int not null primary\ntitle Int null \nauthor_id Int null \n\n");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::from_json(&config, json, false);
+    assert_eq!(manager.to_json(), *json);
+    assert_eq!(manager.get_table("journal").unwrap().borrow().get_attr_name_list(), vec!["z", "y", "x"]);
+}
+
+fn gen_generated_catalog(expr_list : &[&str]) -> BTreeMap<String, Table> {
+    let mut table = gen_catalog_table("item", &["id", "num"]);
+    table.attr_list.push(Attr{
+        name : "name".to_string(),
+        attr_type : AttrType::Char{ len : 10 },
+        primary : false,
+        nullable : true,
+        generated : None,
+    });
+    for (i, expr) in expr_list.iter().enumerate() {
+        let mut attr = table.attr_list[1].clone();
+        attr.name = format!("g{}", i);
+        attr.generated = Some(expr.to_string());
+        table.attr_list.push(attr);
+    }
+    let mut tables = BTreeMap::new();
+    tables.insert("item".to_string(), table);
+    tables
+}
+
+#[test]
+fn test_check_catalog() {
+    let tables = gen_generated_catalog(&["num * 2", "item.num + 1.5", "-num"]);
+    assert_eq!(check_catalog(&tables), Vec::<String>::new());
+
+    let tables = gen_generated_catalog(&["price", "name + 1", "g0 * 2", "book.num", "sum(num)", "num +"]);
+    let problems = check_catalog(&tables);
+    assert_eq!(problems.len(), 6);
+    assert_eq!(problems[0],
+        "table item: generated attribute g0 uses price, which is not a number attribute of the table");
+    assert_eq!(problems[1],
+        "table item: generated attribute g1 uses name, which is not a number attribute of the table");
+    assert!(problems[5].starts_with("table item: invalid expression of generated attribute g5: "));
+
+    let mut tables = gen_generated_catalog(&[]);
+    let table = tables.remove("item").unwrap();
+    tables.insert("book".to_string(), table);
+    assert_eq!(check_catalog(&tables), vec!["table item is saved as book"]);
+}
+
+#[test]
+#[should_panic(expected = "invalid catalog:\ntable item: generated attribute g0 uses price")]
+fn test_dangling_reference_on_load() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5"#.to_string());
+    let json = encode(&gen_generated_catalog(&["price"])).unwrap();
+    TableManager::from_json(&config, &json, false);
+}
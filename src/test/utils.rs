@@ -1,113 +1,440 @@
-use std::fmt::{Display, Debug};
-use std::option::Option::None;
-use std::result::Result::Ok;
-use std::ptr::{write, read};
-use libc::malloc;
-use ::parser::lexer::TokenIter;
-use ::parser::compile_error::ErrorList;
-use ::parser::common::exp_list_to_string;
-use ::utils::pointer::{write_string, read_string};
-use ::store::buffer::DataPtr;
-
-
-macro_rules! gen_token {
-    ($input_str:expr) => ({
-            let line = ::parser::lexer::TokenLine::parse($input_str);
-            assert!(line.errors.is_empty());
-            line.tokens.clone()
-        });
-}
-
-macro_rules! assert_pattern {
-    ($expression:expr, $pattern:pat) => (
-        match $expression {
-            $pattern => (),
-            other => panic!("assert pattern fail, pattern not matched, found {:?}", other),
-        }
-    )
-}
-
-pub fn test_by_display_str<Res : Display + Debug>(
-        input_str : &str,
-        token_num : usize,
-        parse_func : fn(&mut TokenIter) -> Result<Res, ErrorList>,
-        debug_str : &str) {
-    let tokens = gen_token!(input_str);
-    assert_eq!(tokens.len(), token_num);
-    let mut it = tokens.iter();
-    let res = parse_func(&mut it);
-    let res = extract!(res, Ok(res), res);
-    assert_eq!(format!("{}", res), debug_str);
-    assert_pattern!(it.next(), None);
-}
-
-pub fn test_by_list_to_str<Res : Display + Debug>(
-        input_str : &str,
-        token_num : usize,
-        parse_func : fn(&mut TokenIter) -> Result<Vec<Res>, ErrorList>,
-        debug_str : &str) {
-    let tokens = gen_token!(input_str);
-    assert_eq!(tokens.len(), token_num);
-    let mut it = tokens.iter();
-    let res = parse_func(&mut it);
-    let res = extract!(res, Ok(res), res);
-    assert_eq!(exp_list_to_string(&res), debug_str);
-    assert_pattern!(it.next(), None);
-}
-
-pub fn remove_blanks(s : &str) -> String {
-    let mut result = String::new();
-    for c in s.chars() {
-        match c {
-            '\n' | ' ' | '\t' => continue,
-            _ => result.push(c),
-        };
-    }
-    result
-}
-
-macro_rules! gen_plan_helper {
-    ($input_str:expr, $manager:expr) => ({
-        use ::exec::gen_plan::gen_table_set;
-        use ::parser::common::Statement;
-        use ::parser::sem_check::check_sem;
-        use ::exec::gen_plan::gen_plan;
-
-        let tokens = gen_token!($input_str);
-        let stmt = Statement::parse(&mut tokens.iter());
-        let mut stmt = extract!(stmt, Ok(stmt), stmt);
-        let table_set = gen_table_set(&stmt, &$manager);
-        assert_pattern!(check_sem(&mut stmt, &table_set), Ok(()));
-        gen_plan(stmt, $manager)
-    })
-}
-
-macro_rules! gen_parse_result {
-    ($class:ident :: $parse_func:ident, $input_str:expr) => ({
-        let tokens = gen_token!($input_str);
-        extract!($class::$parse_func(&mut tokens.iter()), Ok(result), result)
-    })
-}
-
-// test code in ::utils
-#[test]
-fn test_raw_str_convert() {
-    unsafe{
-        let p : DataPtr = malloc(3);
-        let s = "ab".to_string();
-        write_string(p, &s, 3);
-        assert_eq!(read::<u8>(p as *const u8), 97);
-        assert_eq!(read::<u8>((p as *const u8).offset(1)), 98);
-        assert_eq!(read_string(p, 2), "ab");
-        assert_eq!(read_string(p, 3), "ab");
-    }
-    unsafe{
-        let p : DataPtr = malloc(3);
-        let s = "abc".to_string();
-        write_string(p, &s, 3);
-        assert_eq!(read::<u8>(p as *const u8), 97);
-        assert_eq!(read::<u8>((p as *const u8).offset(1)), 98);
-        assert_eq!(read::<u8>((p as *const u8).offset(2)), 99);
-        assert_eq!(read_string(p, 3), "abc");
-    }
-}
+use std::fmt::{Display, Debug};
+use std::option::Option::None;
+use std::result::Result::Ok;
+use std::ptr::{write, read};
+use libc::malloc;
+use ::parser::lexer::TokenIter;
+use ::parser::compile_error::ErrorList;
+use ::parser::common::exp_list_to_string;
+use ::utils::pointer::{write_string, read_string, read_string_checked, canonical_str};
+use ::utils::pointer::{read_u32_le, write_u32_le, read_i32_le, write_i32_le, read_f32_le, write_f32_le};
+use ::store::buffer::DataPtr;
+use ::store::writer::FileWriter;
+use ::utils::file::write_all_at;
+use std::io;
+use std::fs::File;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+macro_rules! gen_token {
+    ($input_str:expr) => ({
+            let line = ::parser::lexer::TokenLine::parse($input_str);
+            assert!(line.errors.is_empty());
+            line.tokens.clone()
+        });
+}
+
+macro_rules! assert_pattern {
+    ($expression:expr, $pattern:pat) => (
+        match $expression {
+            $pattern => (),
+            other => panic!("assert pattern fail, pattern not matched, found {:?}", other),
+        }
+    )
+}
+
+pub fn test_by_display_str<Res : Display + Debug>(
+        input_str : &str,
+        token_num : usize,
+        parse_func : fn(&mut TokenIter) -> Result<Res, ErrorList>,
+        debug_str : &str) {
+    let tokens = gen_token!(input_str);
+    assert_eq!(tokens.len(), token_num);
+    let mut it = tokens.iter();
+    let res = parse_func(&mut it);
+    let res = extract!(res, Ok(res), res);
+    assert_eq!(format!("{}", res), debug_str);
+    assert_pattern!(it.next(), None);
+}
+
+pub fn test_by_list_to_str<Res : Display + Debug>(
+        input_str : &str,
+        token_num : usize,
+        parse_func : fn(&mut TokenIter) -> Result<Vec<Res>, ErrorList>,
+        debug_str : &str) {
+    let tokens = gen_token!(input_str);
+    assert_eq!(tokens.len(), token_num);
+    let mut it = tokens.iter();
+    let res = parse_func(&mut it);
+    let res = extract!(res, Ok(res), res);
+    assert_eq!(exp_list_to_string(&res), debug_str);
+    assert_pattern!(it.next(), None);
+}
+
+pub fn remove_blanks(s : &str) -> String {
+    let mut result = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' | ' ' | '\t' => continue,
+            _ => result.push(c),
+        };
+    }
+    result
+}
+
+// The writes reaching limit bytes in any file fail with ENOSPC like on a full disk,
+// the headers at the beginning of the table files still fit with a limit of a page.
+#[derive(Debug)]
+pub struct FullDiskWriter {
+    pub limit : AtomicUsize,
+}
+
+impl FileWriter for FullDiskWriter {
+    fn write_at(&self, file : &File, data : &[u8], offset : u64) -> io::Result<()> {
+        if offset as usize + data.len() > self.limit.load(Ordering::SeqCst) {
+            return Err(io::Error::from_raw_os_error(::libc::ENOSPC));
+        }
+        write_all_at(file, data, offset)
+    }
+}
+
+macro_rules! gen_plan_helper {
+    ($input_str:expr, $manager:expr) => ({
+        use ::exec::gen_plan::gen_table_set;
+        use ::parser::common::Statement;
+        use ::parser::sem_check::check_sem;
+        use ::exec::gen_plan::gen_plan;
+
+        let tokens = gen_token!($input_str);
+        let stmt = Statement::parse(&mut tokens.iter());
+        let mut stmt = extract!(stmt, Ok(stmt), stmt);
+        let table_set = gen_table_set(&stmt, &$manager);
+        assert_pattern!(check_sem(&mut stmt, &table_set), Ok(()));
+        gen_plan(stmt, $manager)
+    })
+}
+
+macro_rules! gen_parse_result {
+    ($class:ident :: $parse_func:ident, $input_str:expr) => ({
+        let tokens = gen_token!($input_str);
+        extract!($class::$parse_func(&mut tokens.iter()), Ok(result), result)
+    })
+}
+
+// with the literals parsed as check_sem does, for the expressions evaluated without a statement
+macro_rules! gen_checked_result {
+    ($class:ident :: $parse_func:ident, $input_str:expr) => ({
+        let mut result = gen_parse_result!($class::$parse_func, $input_str);
+        check_ok!(result.check_literals());
+        result
+    })
+}
+
+// test code in ::utils
+#[test]
+fn test_raw_str_convert() {
+    unsafe{
+        let p : DataPtr = malloc(3);
+        let s = "ab".to_string();
+        write_string(p, &s, 3);
+        assert_eq!(read::<u8>(p as *const u8), 97);
+        assert_eq!(read::<u8>((p as *const u8).offset(1)), 98);
+        assert_eq!(read_string(p, 2), "ab");
+        assert_eq!(read_string(p, 3), "ab");
+    }
+    unsafe{
+        let p : DataPtr = malloc(3);
+        let s = "abc".to_string();
+        write_string(p, &s, 3);
+        assert_eq!(read::<u8>(p as *const u8), 97);
+        assert_eq!(read::<u8>((p as *const u8).offset(1)), 98);
+        assert_eq!(read::<u8>((p as *const u8).offset(2)), 99);
+        assert_eq!(read_string(p, 3), "abc");
+    }
+}
+
+#[test]
+fn test_little_endian() {
+    // the bytes are the same whatever the host is
+    let mut data = [0u8; 12];
+    let p = data.as_mut_ptr() as DataPtr;
+    unsafe{
+        write_u32_le(p, 0x01020304);
+        write_i32_le((p as *mut u8).offset(4) as DataPtr, -2);
+        write_f32_le((p as *mut u8).offset(8) as DataPtr, 1.5);
+    }
+    assert_eq!(data, [4, 3, 2, 1, 0xfe, 0xff, 0xff, 0xff, 0, 0, 0xc0, 0x3f]);
+
+    let data = [0x78u8, 0x56, 0x34, 0x12, 0xff, 0xff, 0xff, 0x7f, 0, 0, 0x80, 0xbf];
+    let p = data.as_ptr() as DataPtr;
+    unsafe{
+        assert_eq!(read_u32_le(p), 0x12345678);
+        assert_eq!(read_i32_le((p as *mut u8).offset(4) as DataPtr), i32::max_value());
+        assert_eq!(read_f32_le((p as *mut u8).offset(8) as DataPtr), -1.0);
+        // not aligned
+        assert_eq!(read_u32_le((p as *mut u8).offset(1) as DataPtr), 0xff123456);
+    }
+}
+
+#[test]
+fn test_string_padding() {
+    unsafe{
+        let p : DataPtr = malloc(4);
+        // empty, all padding
+        write_string(p, &"".to_string(), 4);
+        for i in 0..4 {
+            assert_eq!(read::<u8>((p as *const u8).offset(i)), 0);
+        }
+        assert_eq!(read_string(p, 4), "");
+        // full length, no NUL at the end
+        write_string(p, &"abcd".to_string(), 4);
+        assert_eq!(read_string(p, 4), "abcd");
+        // the part after the NUL is not stored
+        write_string(p, &"ab\0c".to_string(), 4);
+        assert_eq!(read::<u8>((p as *const u8).offset(3)), 0);
+        assert_eq!(read_string(p, 4), "ab");
+        write_string(p, &"\0abc".to_string(), 4);
+        assert_eq!(read_string(p, 4), "");
+    }
+    assert_eq!(canonical_str("ab\0c\0"), "ab");
+    assert_eq!(canonical_str("abc"), "abc");
+}
+
+#[test]
+fn test_write_string_char_boundary() {
+    unsafe{
+        let p : DataPtr = malloc(4);
+        // exactly fits
+        write_string(p, &"aé".to_string(), 3);
+        assert_eq!(read_string(p, 3), "aé");
+        // é would be split after the third byte
+        write_string(p, &"abé".to_string(), 3);
+        assert_eq!(read::<u8>((p as *const u8).offset(2)), 0);
+        assert_eq!(read_string_checked(p, 3), Ok("ab".to_string()));
+        write_string(p, &"日本".to_string(), 4);
+        assert_eq!(read_string_checked(p, 4), Ok("日".to_string()));
+        write_string(p, &"日本".to_string(), 2);
+        assert_eq!(read_string_checked(p, 2), Ok("".to_string()));
+    }
+    unsafe{
+        // the first two bytes of 日, left by a damaged page
+        let p : DataPtr = malloc(4);
+        for (i, b) in [b'a', 0xe6, 0x97, 0].iter().enumerate() {
+            write::<u8>((p as *mut u8).offset(i as isize), *b);
+        }
+        assert_eq!(read_string_checked(p, 4), Err("a\u{fffd}".to_string()));
+        assert_eq!(read_string(p, 4), "a\u{fffd}");
+    }
+}
+
+#[test]
+fn test_compress() {
+    use ::utils::compress::{compress, decompress};
+    let mut src = vec![0u8; 1000];
+    src[10] = 1; src[11] = 2;
+    for i in 500..800 { src[i] = (i % 7) as u8; }
+    for i in 900..1000 { src[i] = 233; }
+    let c = compress(&src);
+    assert!(c.len() < 400);
+    let mut dst = vec![0xffu8; 1000];
+    assert!(decompress(&c, &mut dst));
+    assert_eq!(dst, src);
+
+    // corrupted or truncated input
+    let mut short = vec![0u8; 999];
+    assert!(!decompress(&c, &mut short));
+    assert!(!decompress(&c[..c.len() - 1], &mut dst));
+    assert!(decompress(&compress(&[]), &mut []));
+}
+
+#[test]
+fn test_crc32() {
+    use ::utils::checksum::{crc32, crc32_update};
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xcbf43926);
+}
+
+#[test]
+fn test_sha256() {
+    use ::utils::sha256::{sha256, to_hex};
+    assert_eq!(to_hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(to_hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    let long = vec![b'a'; 1000];
+    assert_eq!(to_hex(&sha256(&long)), "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3");
+}
+
+#[test]
+fn test_base64() {
+    use ::utils::base64::{encode_base64, decode_base64};
+    for &(data, encoded) in [(&b""[..], ""), (&b"f"[..], "Zg=="), (&b"fo"[..], "Zm8="),
+            (&b"foo"[..], "Zm9v"), (&b"admin:secret pass"[..], "YWRtaW46c2VjcmV0IHBhc3M=")].iter() {
+        assert_eq!(encode_base64(data), encoded);
+        assert_eq!(decode_base64(encoded).unwrap(), data.to_vec());
+    }
+    assert!(decode_base64("Z").is_none());
+    assert!(decode_base64("Zm9!").is_none());
+}
+
+#[test]
+fn test_config_precedence() {
+    use std::fs::{File, create_dir_all};
+    use std::io::Write;
+    use ::utils::config::Config;
+    let dir = "test_file/test_config_precedence/";
+    check_ok!(create_dir_all(dir));
+    let mut base = check_ok!(File::create(format!("{}base.toml", dir)));
+    check_ok!(base.write_all(b"port = 1000\nhost = \"127.0.0.1\"\nslow_query_ms = 5\n"));
+    let mut file = check_ok!(File::create(format!("{}db_config.toml", dir)));
+    check_ok!(file.write_all(b"include = \"base.toml\"\nport = 2000\ndouble_write = false\n"));
+
+    // the file replaces what it includes, the command line replaces the file
+    let args : Vec<String> = vec!["--config", "test_file/test_config_precedence/db_config.toml",
+        "--port", "3000", "--table-file-dir", "test_file/test_config_precedence/data"]
+        .iter().map(|s| s.to_string()).collect();
+    let mut config = Config::from_args(&args).unwrap();
+    assert_eq!(config.get_usize("port"), 3000);
+    assert_eq!(config.get_str("host"), "127.0.0.1");
+    assert_eq!(config.get_usize("slow_query_ms"), 5);
+    assert_eq!(config.get_path("table_file_dir"), "test_file/test_config_precedence/data");
+    assert_eq!(config.get_usize("max_connections"), 1024);
+    assert_pattern!(config.get_str_opt("include"), None);
+
+    // then the environment
+    let vars = vec![("BLASTOISE_PORT".to_string(), "4000".to_string()),
+        ("BLASTOISE_DOUBLE_WRITE".to_string(), "true".to_string()),
+        ("PORT".to_string(), "5000".to_string())];
+    config.apply_env(vars.into_iter()).unwrap();
+    assert_eq!(config.get_usize("port"), 4000);
+    assert!(config.get_bool("double_write"));
+
+    let err = config.set_override("command line", "port", "abc").unwrap_err();
+    assert_eq!(format!("{}", err), "command line: key `port`: expect an integer, but got abc");
+    let args : Vec<String> = vec!["--port".to_string()];
+    assert!(Config::from_args(&args).is_err());
+}
+
+#[test]
+fn test_config_validation() {
+    use ::utils::config::Config;
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    assert_eq!(config.get_usize("max_memory_pool_page_num"), 2);
+    assert_eq!(config.get_str("host"), "0.0.0.0");
+    assert_eq!(config.get_usize("default_fill_factor"), 100);
+    assert!(config.get_bool("plaintext_enabled"));
+    assert_pattern!(config.get_str_opt("null_string"), None);
+
+    // every invalid key at once
+    let errors = Config::parse("max_memory_pool_page_num = 0\nport = \"80\"\nplaintext_enabled = \"yes\"\n\
+        page_num = 3\n", "db_config.toml").unwrap_err();
+    let msgs : Vec<String> = errors.iter().map(|e| format!("{}", e)).collect();
+    assert_eq!(msgs, vec![
+        "db_config.toml:1: key `max_memory_pool_page_num`: expect an integer from 1 to 2147483647, but got 0",
+        "db_config.toml:4: key `page_num`: unknown key",
+        "db_config.toml:3: key `plaintext_enabled`: expect true or false",
+        "db_config.toml:2: key `port`: expect an integer",
+    ]);
+
+    let mut config = Config::parse("", "db_config.toml").unwrap();
+    assert!(config.set_override("command line", "default_fill_factor", "5").is_err());
+    assert!(config.set_override("command line", "max_memory_pool_pages", "5").is_err());
+    assert!(config.set_override("command line", "include", "base.toml").is_err());
+    assert!(Config::parse("table_file_dir = \"src/lib.rs/table_file\"", "db_config.toml").is_err());
+}
+
+#[test]
+fn test_config_malformed_file() {
+    use std::fs::{File, create_dir_all};
+    use std::io::Write;
+    use ::utils::config::Config;
+    let dir = "test_file/test_config_malformed_file/";
+    check_ok!(create_dir_all(dir));
+    let path = format!("{}db_config.toml", dir);
+    let mut file = check_ok!(File::create(&path));
+    check_ok!(file.write_all(b"port = 8080\n# the host\nhost = 0.0.0.0\n"));
+    let errors = Config::from_file(&path).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].source, path);
+    assert_eq!(errors[0].line, Some(3));
+    assert_eq!(errors[0].key, Some("host".to_string()));
+    let prefix = "test_file/test_config_malformed_file/db_config.toml:3: key `host`: ";
+    assert!(format!("{}", errors[0]).starts_with(prefix));
+
+    let errors = Config::from_file("test_file/test_config_malformed_file/not_exist.toml").unwrap_err();
+    assert_eq!(errors[0].line, None);
+
+    // a file including itself
+    let mut file = check_ok!(File::create(&path));
+    check_ok!(file.write_all(b"include = \"db_config.toml\"\n"));
+    let errors = Config::from_file(&path).unwrap_err();
+    assert_eq!(errors[0].key, Some("include".to_string()));
+}
+
+#[test]
+fn test_data_generator() {
+    use ::utils::bench::DataGenerator;
+    use ::parser::common::ValueType;
+    use ::test::exec::test_query::gen_test_table;
+    let table = gen_test_table(&"test_data_generator".to_string());
+    let rows = DataGenerator::new(&table, 233).gen_rows(1000);
+    let again = DataGenerator::new(&table, 233).gen_rows(1000);
+    let other = DataGenerator::new(&table, 666).gen_rows(1000);
+    let values = |rows : &Vec<::parser::common::ValueList>| -> Vec<Vec<String>> {
+        rows.iter().map(|row| row.iter().map(|v| v.value.clone()).collect()).collect()
+    };
+    assert_eq!(values(&rows), values(&again));
+    assert!(values(&rows) != values(&other));
+
+    let mut small = 0;
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row[0].value_type, ValueType::Integer);
+        assert_eq!(row[0].value, (i + 1).to_string());
+        assert_eq!(row[1].value_type, ValueType::Float);
+        let score = check_ok!(row[1].value.parse::<f32>());
+        assert!(score >= 0.0 && score < 1000.0);
+        if score < 10.0 {
+            small += 1;
+        }
+        assert_eq!(row[2].value_type, ValueType::String);
+        assert_eq!(row[2].value.len(), 16);
+        assert!(row[2].value.chars().all(|c| c >= 'a' && c <= 'z'));
+    }
+    // a third of the scores are below 10 since ln(11) / ln(1001) is about 0.35
+    assert!(small > 250 && small < 450);
+}
+
+#[test]
+fn test_float_round_trip() {
+    use std::mem::transmute;
+    use ::utils::float::{parse_float, format_float};
+    use ::store::tuple::TupleValue;
+    use ::parser::common::{ValueExpr, Literal};
+    // xorshift, all the bit patterns are equally likely
+    let mut state : u32 = 2463534242;
+    let mut checked = 0;
+    for _ in 0..100000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let f : f32 = unsafe{ transmute(state) };
+        if !f.is_finite() {
+            continue;
+        }
+        let s = format_float(f);
+        assert_eq!(parse_float(&s), Some(f));
+        assert!(!s.contains('e'));
+        checked += 1;
+    }
+    assert!(checked > 99000);
+    for &f in [0.0f32, -0.0, 1.0, 0.1, 2.3333, 12345.777, 666.666, ::std::f32::MAX, ::std::f32::MIN_POSITIVE]
+            .iter() {
+        assert_eq!(parse_float(&format_float(f)), Some(f));
+    }
+
+    // the same text through the literal, the stored value and the output
+    let mut value = ValueExpr::from_literal(Literal::Float(12345.777));
+    assert_eq!(value.value, "12345.777");
+    value.literal = None;
+    check_ok!(value.check_literal());
+    let f = extract!(value.literal(), &Literal::Float(f), f);
+    assert_eq!(TupleValue::Float(f).to_string(), "12345.777");
+    assert_eq!(format_float(1.0), "1");
+    assert_eq!(parse_float(" 1.5 "), Some(1.5));
+    assert_eq!(parse_float("1e39"), None);
+    assert_eq!(parse_float("inf"), None);
+    assert_eq!(parse_float("nan"), None);
+    assert_eq!(parse_float("one"), None);
+}
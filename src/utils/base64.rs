@@ -0,0 +1,45 @@
+// standard base64 with padding, only for the Basic auth of the http listener
+const CHARS : &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode_base64(data : &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CHARS[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn decode_base64(s : &str) -> Option<Vec<u8>> {
+    let s = s.trim_right_matches('=').as_bytes();
+    if s.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::new();
+    let mut n = 0u32;
+    for (i, c) in s.iter().enumerate() {
+        let value = match CHARS.iter().position(|x| x == c) {
+            Some(value) => value as u32,
+            None => return None,
+        };
+        n = n << 6 | value;
+        if i % 4 == 3 {
+            out.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+            n = 0;
+        }
+    }
+    match s.len() % 4 {
+        2 => out.push((n >> 4) as u8),
+        3 => out.extend_from_slice(&[(n >> 10) as u8, (n >> 2) as u8]),
+        _ => (),
+    }
+    Some(out)
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use ::parser::common::{ValueExpr, ValueList, Literal};
+use ::store::table::{Table, Attr, AttrType, TableManagerRef};
+use ::exec::iter::ExecIterRef;
+
+
+// The rows of a table for the benchmarks, the same seed always gives the same rows:
+// the primary key counts from 1, the other ints and the floats are skewed towards the small values
+// like a zipf distribution, and the chars are random letters filling the whole length.
+#[derive(Debug)]
+pub struct DataGenerator {
+    table : Table,
+    state : u64,  // of xorshift64*
+    next_id : i32,
+}
+
+impl DataGenerator {
+    pub fn new(table : &Table, seed : u64) -> DataGenerator {
+        DataGenerator{
+            table : table.clone(),
+            // xorshift never leaves 0
+            state : if seed == 0 { 0x9e3779b97f4a7c15 } else { seed },
+            next_id : 1,
+        }
+    }
+    pub fn gen_row(&mut self) -> ValueList {
+        let attr_list = self.table.attr_list.clone();
+        let row : ValueList = attr_list.iter().map(|attr| self.gen_value(attr)).collect();
+        self.next_id += 1;
+        row
+    }
+    pub fn gen_rows(&mut self, n : usize) -> Vec<ValueList> {
+        (0..n).map(|_| self.gen_row()).collect()
+    }
+    pub fn load_into(&mut self, manager : &TableManagerRef, n : usize) {
+        // written into the table file directly, the primary keys are unique without checking
+        let table = self.table.name.clone();
+        for _ in 0..n {
+            let row = self.gen_row();
+            check_ok!(manager.borrow_mut().insert(&table, &row));
+        }
+    }
+
+    fn gen_value(&mut self, attr : &Attr) -> ValueExpr {
+        let literal = match attr.attr_type {
+            AttrType::Int if attr.primary => Literal::Int(self.next_id),
+            AttrType::Int => Literal::Int(self.gen_skewed(1000.0) as i32),
+            AttrType::Float => Literal::Float((self.gen_skewed(1000.0) * 100.0).floor() as f32 / 100.0),
+            AttrType::Char{len} => {
+                let s : String = (0..len).map(|_| (b'a' + (self.next_u64() % 26) as u8) as char).collect();
+                Literal::Str(s)
+            }
+        };
+        ValueExpr::from_literal(literal)
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+    fn gen_skewed(&mut self, max : f64) -> f64 {
+        // in [0, max) with the density proportional to 1 / (x + 1)
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        (max + 1.0).powf(unit) - 1.0
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlanTiming {
+    pub rows : usize,
+    pub elapsed : Duration,
+}
+
+impl PlanTiming {
+    pub fn rows_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs() as f64 + self.elapsed.subsec_nanos() as f64 / 1e9;
+        if secs > 0.0 { self.rows as f64 / secs } else { 0.0 }
+    }
+}
+
+impl fmt::Display for PlanTiming {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let elapsed_ms = self.elapsed.as_secs() * 1000 + (self.elapsed.subsec_nanos() / 1000000) as u64;
+        write!(f, "{} rows in {} ms, {:.0} rows/s", self.rows, elapsed_ms, self.rows_per_sec())
+    }
+}
+
+// open the plan, take all the rows and close it
+pub fn time_plan(plan : &mut ExecIterRef) -> PlanTiming {
+    let start = Instant::now();
+    plan.open();
+    let mut rows = 0;
+    while plan.get_next().is_some() {
+        rows += 1;
+    }
+    plan.close();
+    PlanTiming{
+        rows : rows,
+        elapsed : start.elapsed(),
+    }
+}
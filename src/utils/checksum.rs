@@ -0,0 +1,16 @@
+// crc32 (IEEE 802.3), the same as zlib
+pub fn crc32(data : &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+pub fn crc32_update(crc : u32, data : &[u8]) -> u32 {
+    let mut crc = !crc;
+    for b in data.iter() {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);  // 0xffffffff if the lowest bit is set
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,70 @@
+use std::vec::Vec;
+
+
+// A simple run length encoding for pages, most of the bytes in a page are
+// zero padding of char attributes and empty slots.
+// control byte c < 128 : followed by c + 1 literal bytes
+// control byte c >= 128 : followed by one byte repeated c - 128 + MIN_RUN times
+const MIN_RUN : usize = 3;
+const MAX_RUN : usize = 127 + MIN_RUN;
+const MAX_LITERAL : usize = 128;
+
+pub fn compress(src : &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut literal_begin = 0;
+    let mut i = 0;
+    while i < src.len() {
+        let mut run = 1;
+        while i + run < src.len() && src[i + run] == src[i] && run < MAX_RUN {
+            run += 1;
+        }
+        if run >= MIN_RUN {
+            push_literal(&mut result, &src[literal_begin..i]);
+            result.push((run - MIN_RUN + 128) as u8);
+            result.push(src[i]);
+            i += run;
+            literal_begin = i;
+        } else {
+            i += run;
+        }
+    }
+    push_literal(&mut result, &src[literal_begin..]);
+    result
+}
+
+fn push_literal(result : &mut Vec<u8>, literal : &[u8]) {
+    for chunk in literal.chunks(MAX_LITERAL) {
+        result.push((chunk.len() - 1) as u8);
+        result.extend_from_slice(chunk);
+    }
+}
+
+// return false if the src is corrupted or does not fill the whole dst
+pub fn decompress(src : &[u8], dst : &mut [u8]) -> bool {
+    let mut i = 0;
+    let mut out = 0;
+    while i < src.len() {
+        let c = src[i] as usize;
+        i += 1;
+        if c < 128 {
+            let len = c + 1;
+            if i + len > src.len() || out + len > dst.len() {
+                return false;
+            }
+            dst[out..out + len].copy_from_slice(&src[i..i + len]);
+            i += len;
+            out += len;
+        } else {
+            let len = c - 128 + MIN_RUN;
+            if i >= src.len() || out + len > dst.len() {
+                return false;
+            }
+            for b in dst[out..out + len].iter_mut() {
+                *b = src[i];
+            }
+            i += 1;
+            out += len;
+        }
+    }
+    out == dst.len()
+}
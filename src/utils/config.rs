@@ -1,38 +1,428 @@
-use std::fs::OpenOptions;
-use std::io::Read;
-use toml::{Value, Table, Parser};
-
-
-#[derive(Debug)]
-pub struct Config {
-    config : Table,
-}
-
-impl Config {
-    pub fn from_cwd_config() -> Config {
-        let cwd = "./db_config.toml".to_string();
-        Self::from_file(&cwd)
-    }
-    pub fn from_file(config_file : &String) -> Config {
-        let mut file = check_ok!(OpenOptions::new().read(true).open(config_file));
-        let mut buf = String::new();
-        check_ok!(file.read_to_string(&mut buf));
-        Self::new(&buf)
-    }
-    pub fn new(config_str : &String) -> Config {
-        let mut parser = Parser::new(&config_str);
-        let config = match parser.parse() {
-            Some(v) => v,
-            None => panic!("db config error: {:?}", parser.errors),
-        };
-        Config{
-            config : config,
-        }
-    }
-    pub fn get_int(&self, path : &str) -> i64 {
-        extract!(self.config.get(path), Some(&Value::Integer(n)), n)
-    }
-    pub fn get_str(&self, path : &str) -> String {
-        extract!(self.config.get(path), Some(&Value::String(ref s)), s.clone())
-    }
-}
+use std::fmt;
+use std::env;
+use std::error::Error;
+use std::fs::{OpenOptions, metadata};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use toml::{Value, Table, Parser};
+
+
+const DEFAULT_CONFIG_FILE : &'static str = "./db_config.toml";
+const ENV_PREFIX : &'static str = "BLASTOISE_";
+const MAX_INT : i64 = ::std::i32::MAX as i64;
+const MAX_PORT : i64 = 65535;
+
+// where a config went wrong: the file or `command line` or the environment variable,
+// the line of the file and the key when they are known
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub source : String,
+    pub line : Option<usize>,  // 1-based
+    pub key : Option<String>,
+    pub msg : String,
+}
+
+pub type ConfigErrorList = Vec<ConfigError>;
+
+impl ConfigError {
+    fn new(source : &str, line : Option<usize>, key : Option<&str>, msg : String) -> ConfigError {
+        ConfigError{
+            source : source.to_string(),
+            line : line,
+            key : key.map(|k| k.to_string()),
+            msg : msg,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}", self.source));
+        if let Some(line) = self.line {
+            try!(write!(f, ":{}", line));
+        }
+        if let Some(ref key) = self.key {
+            try!(write!(f, ": key `{}`", key));
+        }
+        write!(f, ": {}", self.msg)
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        &self.msg
+    }
+}
+
+pub fn render_config_errors(errors : &[ConfigError]) -> String {
+    let lines : Vec<String> = errors.iter().map(|e| format!("{}", e)).collect();
+    lines.join("\n")
+}
+
+#[derive(Debug, Copy, Clone)]
+enum KeyType {
+    Bool{ default : bool },
+    Int{ min : i64, max : i64, default : i64 },
+    Name{ choices : &'static [&'static str], default : &'static str },
+    Text{ default : Option<&'static str>, max_len : usize },  // None for no default
+    Dir{ default : &'static str },  // created when missing, so only checked to be writable
+}
+
+// every key of the config, a value of another type or out of the range is an error
+const KEYS : [(&'static str, KeyType); 43] = [
+    ("max_memory_pool_page_num", KeyType::Int{ min : 1, max : MAX_INT, default : 5 }),
+    ("table_file_dir", KeyType::Dir{ default : "table_file" }),
+    ("table_meta_dir", KeyType::Dir{ default : "./" }),
+    ("host", KeyType::Text{ default : Some("0.0.0.0"), max_len : 256 }),
+    // 0 for any free port
+    ("port", KeyType::Int{ min : 0, max : MAX_PORT, default : 8080 }),
+    ("max_connections", KeyType::Int{ min : 1, max : MAX_INT, default : 1024 }),
+    // the length limit of a unix socket address
+    ("unix_socket_path", KeyType::Text{ default : Some(""), max_len : 107 }),
+    ("tls_enabled", KeyType::Bool{ default : false }),
+    ("tls_cert_file", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    ("tls_key_file", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    ("tls_port", KeyType::Int{ min : 0, max : MAX_PORT, default : 8443 }),
+    ("plaintext_enabled", KeyType::Bool{ default : true }),
+    ("result_batch_bytes", KeyType::Int{ min : 1, max : MAX_INT, default : 8192 }),
+    ("max_write_buffer_bytes", KeyType::Int{ min : 1, max : MAX_INT, default : 65536 }),
+    // a frame of copy from stdin over it fails the copy
+    ("max_copy_frame_bytes", KeyType::Int{ min : 1, max : MAX_INT, default : 1048576 }),
+    ("users_file", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    // the user of users_file granting the privileges, the others only have the ones granted
+    ("admin_user", KeyType::Text{ default : Some(""), max_len : 256 }),
+    ("mysql_enabled", KeyType::Bool{ default : false }),
+    ("mysql_port", KeyType::Int{ min : 0, max : MAX_PORT, default : 3306 }),
+    ("http_enabled", KeyType::Bool{ default : false }),
+    ("http_port", KeyType::Int{ min : 0, max : MAX_PORT, default : 8081 }),
+    ("flush_interval_ms", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    ("double_write", KeyType::Bool{ default : false }),
+    ("default_fill_factor", KeyType::Int{ min : 10, max : 100, default : 100 }),
+    // the page of the table files created, 0 for the page size of the os
+    ("page_size", KeyType::Int{ min : 0, max : 1 << 20, default : 0 }),
+    ("scan_prefetch", KeyType::Name{ choices : &["none", "pool", "fadvise"], default : "none" }),
+    ("scan_prefetch_pages", KeyType::Int{ min : 1, max : MAX_INT, default : 4 }),
+    ("storage_backend", KeyType::Name{ choices : &["buffered", "mmap"], default : "buffered" }),
+    ("query_timeout_ms", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    // the statements waiting for the worker, a statement over it is refused, 0 for no limit
+    ("max_queued_statements", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    // how long a statement may wait for the worker before it's answered with an error, 0 for no limit
+    ("queue_timeout_ms", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    ("max_result_rows", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    // how long a statement waits for the tables used by a conflicting statement, 0 to fail at once
+    ("table_lock_timeout_ms", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    ("query_log_path", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    ("query_log_max_statement_len", KeyType::Int{ min : 0, max : MAX_INT, default : 1024 }),
+    ("query_log_max_bytes", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    ("query_log_flush_interval_ms", KeyType::Int{ min : 1, max : MAX_INT, default : 1000 }),
+    ("slow_query_ms", KeyType::Int{ min : 0, max : MAX_INT, default : 0 }),
+    ("slow_query_log_path", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    ("log_level", KeyType::Name{ choices : &["error", "warn", "info", "debug", "trace"], default : "info" }),
+    // empty for stderr
+    ("log_file", KeyType::Text{ default : Some(""), max_len : 4096 }),
+    // None for the default of the output format
+    ("null_string", KeyType::Text{ default : None, max_len : 16 }),
+    // only in a config file, replaced by the values of the file it names
+    ("include", KeyType::Text{ default : None, max_len : 4096 }),
+];
+
+fn get_key_type(key : &str) -> Option<KeyType> {
+    KEYS.iter().find(|&&(name, _)| name == key).map(|&(_, key_type)| key_type)
+}
+
+// The values are taken in the order, each one replacing the ones before it:
+// (1) the defaults in KEYS, used when a key is missing
+// (2) the files included by `include = "path"`, relative to the including file
+// (3) the config file
+// (4) the command line, `--table-file-dir /data` sets table_file_dir
+// (5) the environment, BLASTOISE_TABLE_FILE_DIR sets table_file_dir
+// Only the validated values are kept, so the getters don't fail.
+#[derive(Debug, Clone)]
+pub struct Config {
+    config : Table,
+}
+
+impl Config {
+    pub fn from_cwd_config() -> Config {
+        match Self::from_file(DEFAULT_CONFIG_FILE) {
+            Ok(config) => config,
+            Err(errors) => panic!("db config error: {}", render_config_errors(&errors)),
+        }
+    }
+    pub fn from_file(config_file : &str) -> Result<Config, ConfigErrorList> {
+        let mut included = Vec::new();
+        let config = Config{ config : try!(load_file(Path::new(config_file), &mut included)) };
+        try!(config.check_dirs());
+        Ok(config)
+    }
+    // `--config path` and the `--name value` pairs, then the environment
+    pub fn from_args(args : &[String]) -> Result<Config, ConfigErrorList> {
+        let (config_file, overrides) = try!(parse_args(args).map_err(|err| vec![err]));
+        let config_file = config_file.unwrap_or(DEFAULT_CONFIG_FILE.to_string());
+        let mut included = Vec::new();
+        let mut config = Config{ config : try!(load_file(Path::new(&config_file), &mut included)) };
+        let mut errors = Vec::new();
+        for &(ref key, ref value) in overrides.iter() {
+            if let Err(err) = config.set_override("command line", key, value) {
+                errors.push(err);
+            }
+        }
+        if let Err(env_errors) = config.apply_env(env::vars()) {
+            errors.extend(env_errors);
+        }
+        if errors.is_empty() {
+            try!(config.check_dirs());
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+    pub fn new(config_str : &String) -> Config {
+        match Self::parse(config_str, "config") {
+            Ok(config) => config,
+            Err(errors) => panic!("db config error: {}", render_config_errors(&errors)),
+        }
+    }
+    pub fn parse(config_str : &str, source : &str) -> Result<Config, ConfigErrorList> {
+        let config = Config{ config : try!(parse_table(config_str, source)) };
+        if config.config.contains_key("include") {
+            let line = find_key_line(config_str, "include");
+            return Err(vec![ConfigError::new(source, line, Some("include"),
+                "can only be used in a config file".to_string())]);
+        }
+        try!(config.check_dirs());
+        Ok(config)
+    }
+    // the variables starting with BLASTOISE_, the others are ignored
+    pub fn apply_env<I : Iterator<Item=(String, String)>>(&mut self, vars : I)
+            -> Result<(), ConfigErrorList> {
+        let mut errors = Vec::new();
+        for (name, value) in vars {
+            if !name.starts_with(ENV_PREFIX) {
+                continue;
+            }
+            let key = name[ENV_PREFIX.len()..].to_lowercase();
+            if let Err(err) = self.set_override(&format!("environment {}", name), &key, &value) {
+                errors.push(err);
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+    pub fn set_override(&mut self, source : &str, key : &str, value : &str) -> Result<(), ConfigError> {
+        let key_type = match get_key_type(key) {
+            Some(..) if key == "include" => None,
+            key_type => key_type,
+        };
+        let key_type = match key_type {
+            Some(key_type) => key_type,
+            None => return Err(ConfigError::new(source, None, Some(key), "unknown key".to_string())),
+        };
+        match parse_value(key_type, value) {
+            Ok(v) => {
+                self.config.insert(key.to_string(), v);
+                Ok(())
+            }
+            Err(msg) => Err(ConfigError::new(source, None, Some(key), msg)),
+        }
+    }
+
+    pub fn get_usize(&self, key : &str) -> usize {
+        match (self.get_key(key), self.config.get(key)) {
+            (KeyType::Int{..}, Some(&Value::Integer(n))) => n as usize,
+            (KeyType::Int{default, ..}, None) => default as usize,
+            _ => panic!("config key {} is not an integer", key),
+        }
+    }
+    pub fn get_bool(&self, key : &str) -> bool {
+        match (self.get_key(key), self.config.get(key)) {
+            (KeyType::Bool{..}, Some(&Value::Boolean(b))) => b,
+            (KeyType::Bool{default}, None) => default,
+            _ => panic!("config key {} is not a boolean", key),
+        }
+    }
+    pub fn get_str(&self, key : &str) -> String {
+        match self.get_str_opt(key) {
+            Some(s) => s,
+            None => panic!("config key {} has no default", key),
+        }
+    }
+    // None when the key is missing and has no default
+    pub fn get_str_opt(&self, key : &str) -> Option<String> {
+        match (self.get_key(key), self.config.get(key)) {
+            (KeyType::Name{..}, Some(&Value::String(ref s))) => Some(s.clone()),
+            (KeyType::Name{default, ..}, None) => Some(default.to_string()),
+            (KeyType::Text{..}, Some(&Value::String(ref s))) => Some(s.clone()),
+            (KeyType::Text{default, ..}, None) => default.map(|s| s.to_string()),
+            _ => panic!("config key {} is not a string", key),
+        }
+    }
+    // the directory checked to be writable
+    pub fn get_path(&self, key : &str) -> String {
+        match (self.get_key(key), self.config.get(key)) {
+            (KeyType::Dir{..}, Some(&Value::String(ref s))) => s.clone(),
+            (KeyType::Dir{default}, None) => default.to_string(),
+            _ => panic!("config key {} is not a path", key),
+        }
+    }
+
+    fn get_key(&self, key : &str) -> KeyType {
+        match get_key_type(key) {
+            Some(key_type) => key_type,
+            None => panic!("unknown config key {}", key),
+        }
+    }
+
+    // with the last value of each key
+    fn check_dirs(&self) -> Result<(), ConfigErrorList> {
+        let mut errors = Vec::new();
+        for &(key, key_type) in KEYS.iter() {
+            if let KeyType::Dir{..} = key_type {
+                let path = self.get_path(key);
+                if let Err(msg) = check_dir_writable(&path) {
+                    errors.push(ConfigError::new("config", None, Some(key), msg));
+                }
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+// the nearest existing one of the directory and its parents should be a writable directory
+fn check_dir_writable(path : &str) -> Result<(), String> {
+    let mut dir = Path::new(path);
+    loop {
+        let existing = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        if let Ok(meta) = metadata(existing) {
+            if !meta.is_dir() {
+                return Err(format!("{} is not a directory", existing.display()));
+            }
+            if meta.permissions().readonly() {
+                return Err(format!("{} is not writable", existing.display()));
+            }
+            return Ok(());
+        }
+        dir = match dir.parent() {
+            Some(parent) => parent,
+            None => return Err(format!("{} can't be created", path)),
+        };
+    }
+}
+
+fn check_value(key_type : KeyType, value : &Value) -> Result<(), String> {
+    match (key_type, value) {
+        (KeyType::Bool{..}, &Value::Boolean(..)) => Ok(()),
+        (KeyType::Bool{..}, _) => Err("expect true or false".to_string()),
+        (KeyType::Int{min, max, ..}, &Value::Integer(n)) if n < min || n > max =>
+            Err(format!("expect an integer from {} to {}, but got {}", min, max, n)),
+        (KeyType::Int{..}, &Value::Integer(..)) => Ok(()),
+        (KeyType::Int{..}, _) => Err("expect an integer".to_string()),
+        (KeyType::Name{choices, ..}, &Value::String(ref s)) if !choices.contains(&&s[..]) =>
+            Err(format!("expect one of {}, but got {}", choices.join(", "), s)),
+        (KeyType::Name{..}, &Value::String(..)) => Ok(()),
+        (KeyType::Text{max_len, ..}, &Value::String(ref s)) if s.len() > max_len =>
+            Err(format!("expect at most {} chars", max_len)),
+        (KeyType::Text{..}, &Value::String(..)) | (KeyType::Dir{..}, &Value::String(..)) => Ok(()),
+        (_, _) => Err("expect a string".to_string()),
+    }
+}
+
+// from the command line or the environment
+fn parse_value(key_type : KeyType, value : &str) -> Result<Value, String> {
+    let parsed = match key_type {
+        KeyType::Bool{..} => match value.parse::<bool>() {
+            Ok(b) => Value::Boolean(b),
+            Err(..) => return Err(format!("expect true or false, but got {}", value)),
+        },
+        KeyType::Int{..} => match value.parse::<i64>() {
+            Ok(n) => Value::Integer(n),
+            Err(..) => return Err(format!("expect an integer, but got {}", value)),
+        },
+        _ => Value::String(value.to_string()),
+    };
+    try!(check_value(key_type, &parsed));
+    Ok(parsed)
+}
+
+// the line where the key is set, 1-based
+fn find_key_line(config_str : &str, key : &str) -> Option<usize> {
+    config_str.lines().position(|l| {
+        let l = l.trim();
+        l.starts_with(key) && l[key.len()..].trim_left().starts_with('=')
+    }).map(|i| i + 1)
+}
+
+// All the unknown and invalid keys, or only the first error of the parser
+// with the key written before `=` on its line.
+fn parse_table(config_str : &str, source : &str) -> Result<Table, ConfigErrorList> {
+    let mut parser = Parser::new(config_str);
+    let table = match parser.parse() {
+        Some(table) => table,
+        None => {
+            let err = parser.errors[0].clone();
+            let (line, _) = parser.to_linecol(err.lo);
+            let key = config_str.lines().nth(line)
+                .and_then(|l| l.find('=').map(|i| l[..i].trim().to_string()))
+                .and_then(|k| if k.is_empty() { None } else { Some(k) });
+            let key = key.as_ref().map(|k| &k[..]);
+            return Err(vec![ConfigError::new(source, Some(line + 1), key, err.desc)]);
+        }
+    };
+    let mut errors = Vec::new();
+    for (key, value) in table.iter() {
+        let line = find_key_line(config_str, key);
+        match get_key_type(key) {
+            Some(key_type) => if let Err(msg) = check_value(key_type, value) {
+                errors.push(ConfigError::new(source, line, Some(&key[..]), msg));
+            },
+            None => errors.push(ConfigError::new(source, line, Some(&key[..]), "unknown key".to_string())),
+        }
+    }
+    if errors.is_empty() { Ok(table) } else { Err(errors) }
+}
+
+// the values of the file replace the ones it includes
+fn load_file(path : &Path, included : &mut Vec<PathBuf>) -> Result<Table, ConfigErrorList> {
+    let source = path.to_string_lossy().into_owned();
+    if included.iter().any(|p| p == path) {
+        return Err(vec![ConfigError::new(&source, None, Some("include"), "included in a cycle".to_string())]);
+    }
+    included.push(path.to_path_buf());
+    let mut buf = String::new();
+    let read_result = OpenOptions::new().read(true).open(path).and_then(|mut f| f.read_to_string(&mut buf));
+    if let Err(err) = read_result {
+        return Err(vec![ConfigError::new(&source, None, None, format!("{}", err))]);
+    }
+    let mut table = try!(parse_table(&buf, &source));
+    let include = match table.remove("include") {
+        Some(Value::String(include)) => include,
+        _ => return Ok(table),
+    };
+    let include_path = path.parent().unwrap_or(Path::new("")).join(include);
+    let mut config = try!(load_file(&include_path, included));
+    config.extend(table.into_iter());
+    Ok(config)
+}
+
+// `--config path` and `--name value`, the dashes of the name are underscores of the key
+fn parse_args(args : &[String]) -> Result<(Option<String>, Vec<(String, String)>), ConfigError> {
+    let mut config_file = None;
+    let mut overrides = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        if !arg.starts_with("--") || arg.len() == 2 {
+            return Err(ConfigError::new("command line", None, None, format!("invalid option {}", arg)));
+        }
+        let key = arg[2..].replace("-", "_");
+        let value = match it.next() {
+            Some(value) => value.clone(),
+            None => return Err(ConfigError::new("command line", None, Some(&key),
+                "missing the value".to_string())),
+        };
+        if key == "config" {
+            config_file = Some(value);
+        } else {
+            overrides.push((key, value));
+        }
+    }
+    Ok((config_file, overrides))
+}
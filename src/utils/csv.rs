@@ -0,0 +1,168 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::vec::Vec;
+use ::store::tuple::TupleValue;
+
+
+#[derive(Debug, Clone)]
+pub struct CsvField {
+    pub value : String,
+    pub quoted : bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvRecord {
+    pub line : usize,  // line number where the record begins, start from 1
+    pub fields : Vec<CsvField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvError {
+    pub line : usize,
+    pub error_msg : String,
+    pub read_failed : bool,  // the reader failed, instead of a malformed record
+}
+
+// read one record at a time, a quoted field can span multiple lines
+pub struct CsvReader<R : BufRead> {
+    reader : R,
+    delimiter : char,
+    line : usize,
+}
+
+impl<R : BufRead> CsvReader<R> {
+    pub fn new(reader : R, delimiter : char) -> CsvReader<R> {
+        CsvReader{
+            reader : reader,
+            delimiter : delimiter,
+            line : 0,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, CsvError> {
+        let mut buf = String::new();
+        match self.reader.read_line(&mut buf) {
+            Ok(0) => Ok(None),
+            Ok(..) => {
+                self.line += 1;
+                if buf.ends_with('\n') { buf.pop(); }
+                Ok(Some(buf))
+            }
+            Err(err) => Err(CsvError{
+                line : self.line + 1,
+                error_msg : format!("read error: {}", err),
+                read_failed : true,
+            }),
+        }
+    }
+
+    pub fn read_record(&mut self) -> Result<Option<CsvRecord>, CsvError> {
+        let mut line = match try!(self.read_line()) {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let begin_line = self.line;
+        let mut fields = Vec::new();
+        let mut value = String::new();
+        let mut quoted = false;
+        let mut in_quote = false;
+        let mut closed = false;  // after the closing quote, only a delimiter or the end of the line may follow
+        loop {
+            let mut it = line.chars().peekable();
+            while let Some(c) = it.next() {
+                if in_quote {
+                    if c != '"' {
+                        value.push(c);
+                    } else if it.peek() == Some(&'"') {
+                        value.push('"');
+                        it.next();
+                    } else {
+                        in_quote = false;
+                        closed = true;
+                    }
+                } else if c == '\r' && it.peek().is_none() {
+                    ()  // the '\r' of a CRLF record terminator, one in a quoted field is kept above
+                } else if c == self.delimiter {
+                    fields.push(CsvField{ value : value.clone(), quoted : quoted });
+                    value.clear();
+                    quoted = false;
+                    closed = false;
+                } else if closed {
+                    return Err(CsvError{
+                        line : self.line,
+                        error_msg : format!("unexpected {:?} after the closing quote of column {}",
+                            c, fields.len() + 1),
+                        read_failed : false,
+                    });
+                } else if c == '"' && value.is_empty() && !quoted {
+                    in_quote = true;
+                    quoted = true;
+                } else {
+                    value.push(c);
+                }
+            }
+            if !in_quote {
+                break;
+            }
+            // quoted field continues on the next line
+            line = match try!(self.read_line()) {
+                Some(line) => line,
+                None => return Err(CsvError{
+                    line : begin_line,
+                    error_msg : "unterminated quoted field".to_string(),
+                    read_failed : false,
+                }),
+            };
+            value.push('\n');
+        }
+        fields.push(CsvField{ value : value, quoted : quoted });
+        Ok(Some(CsvRecord{
+            line : begin_line,
+            fields : fields,
+        }))
+    }
+}
+
+pub fn quote_field(field : &str, delimiter : char) -> String {
+    let need_quote = field.chars().any(|c| c == delimiter || c == '"' || c == '\n' || c == '\r');
+    if !need_quote {
+        return field.to_string();
+    }
+    format!("\"{}\"", field.replace("\"", "\"\""))
+}
+
+pub fn write_record<W : Write>(writer : &mut W, fields : &Vec<String>, delimiter : char) -> io::Result<()> {
+    let fields = fields.iter().map(|f| CsvField{ value : f.clone(), quoted : false }).collect();
+    write_record_fields(writer, &fields, delimiter)
+}
+
+// the quoted fields are always quoted, so that they are not read as null
+pub fn write_record_fields<W : Write>(writer : &mut W, fields : &Vec<CsvField>, delimiter : char)
+        -> io::Result<()> {
+    let mut line = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(delimiter);
+        }
+        if field.quoted {
+            line.push_str(&format!("\"{}\"", field.value.replace("\"", "\"\"")));
+        } else {
+            line.push_str(&quote_field(&field.value, delimiter));
+        }
+    }
+    if fields.len() == 1 && fields[0].value.is_empty() && !fields[0].quoted {
+        line.push_str("\"\"");  // or it will be read as a blank line
+    }
+    line.push('\n');
+    writer.write_all(line.as_bytes())
+}
+
+// Null is written as null_string unquoted, and a string equal to null_string is quoted,
+// so that they can be told apart when the file is read with the same null_string.
+pub fn value_to_field(value : &TupleValue, null_string : &str) -> CsvField {
+    match value {
+        &TupleValue::Null => CsvField{ value : null_string.to_string(), quoted : false },
+        &TupleValue::Char(ref s) => CsvField{ value : s.clone(), quoted : s == null_string },
+        other => CsvField{ value : other.to_string(), quoted : false },
+    }
+}
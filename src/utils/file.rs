@@ -1,38 +1,81 @@
-use std::fs::{metadata, create_dir_all};
-use std::path::Path;
-
-
-pub fn path_join(path : &String, file : &String) -> String {
-    match metadata(path) {
-        Ok(m) => assert!(m.is_dir()),
-        Err(e) => panic!("path_join fail {:?}, {:?}", path, e),
-    }
-    Path::new(path).join(file).to_str().unwrap().to_string()
-}
-
-pub fn ensure_dir_exist(path : &String) {
-    match metadata(path) {
-        Ok(m) => {
-            if !m.is_dir() {
-                panic!("{:?} is not a directory", path);
-            }
-        }
-        Err(..) => {
-            // directory not exist or permission denied
-            check_ok!(create_dir_all(path));
-        }
-    }
-}
-
-pub fn assert_file_exist(path : &String) {
-    match metadata(path) {
-        Ok(m) => {
-            if !m.is_file() {
-                panic!("{:?} is not a file", path);
-            }
-        }
-        Err(err) => {
-            panic!("{:?}", err);
-        }
-    }
-}
+use std::io;
+use std::env;
+use std::process;
+use std::fs::{metadata, create_dir_all, remove_dir_all, remove_file, read_dir, File};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::libwrapper::{read_at, write_at};
+use std::path::Path;
+
+
+pub fn path_join(path : &String, file : &String) -> String {
+    match metadata(path) {
+        Ok(m) => assert!(m.is_dir()),
+        Err(e) => panic!("path_join fail {:?}, {:?}", path, e),
+    }
+    Path::new(path).join(file).to_str().unwrap().to_string()
+}
+
+pub fn ensure_dir_exist(path : &String) {
+    match metadata(path) {
+        Ok(m) => {
+            if !m.is_dir() {
+                panic!("{:?} is not a directory", path);
+            }
+        }
+        Err(..) => {
+            // directory not exist or permission denied
+            check_ok!(create_dir_all(path));
+        }
+    }
+}
+
+// a new empty directory under the temp dir of the os, different for each call in each process
+pub fn create_temp_dir(prefix : &str) -> String {
+    static TEMP_DIR_NUM : AtomicUsize = AtomicUsize::new(0);
+    let num = TEMP_DIR_NUM.fetch_add(1, Ordering::SeqCst);
+    let path = env::temp_dir().join(format!("{}_{}_{}", prefix, process::id(), num));
+    remove_dir_all(&path).ok();  // left by an earlier process with the same pid
+    check_ok!(create_dir_all(&path));
+    path.to_str().unwrap().to_string()
+}
+
+// the files of dir named with the suffix, the others are kept
+pub fn remove_files_with_suffix(dir : &String, suffix : &str) -> io::Result<()> {
+    for entry in try!(read_dir(dir)) {
+        let path = try!(entry).path();
+        let matched = path.file_name().and_then(|name| name.to_str())
+            .map_or(false, |name| name.ends_with(suffix));
+        if matched && path.is_file() {
+            try!(remove_file(&path));
+        }
+    }
+    Ok(())
+}
+
+pub fn write_all_at(file : &File, mut data : &[u8], mut offset : u64) -> io::Result<()> {
+    // positioned write does not change the file offset
+    while !data.is_empty() {
+        match write_at(file, data, offset) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(n) => {
+                data = &data[n..];
+                offset += n as u64;
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+pub fn read_at_most(file : &File, buf : &mut [u8], offset : u64) -> usize {
+    // return the length read, which is shorter than buf at the end of file
+    let mut read_len = 0;
+    while read_len < buf.len() {
+        match read_at(file, &mut buf[read_len..], offset + read_len as u64) {
+            Ok(0) | Err(..) => break,
+            Ok(n) => read_len += n,
+        }
+    }
+    read_len
+}
@@ -0,0 +1,17 @@
+// The only conversions between a float and its text, for the literals, the csv files,
+// the converted chars and the results, so a float always reads back as the same f32.
+
+// None for the text of no f32 and for inf and nan, which can't be stored
+pub fn parse_float(s : &str) -> Option<f32> {
+    match s.trim().parse::<f32>() {
+        Ok(f) if f.is_finite() => Some(f),
+        _ => None,
+    }
+}
+
+// The shortest text which parses back to the same f32, such as 12345.777 rather than
+// the 12345.77734375 of f64. It has no exponent, and no '.' for an integral value.
+pub fn format_float(f : f32) -> String {
+    // Display of f32 is already the shortest round trip
+    format!("{}", f)
+}
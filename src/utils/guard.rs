@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+
+// The time limit and the cancel flag of the running query.
+// The clones share the flag, so the query can be cancelled from another thread.
+#[derive(Debug, Clone)]
+pub struct QueryGuard {
+    start : Instant,
+    timeout_ms : usize,  // 0 for no limit
+    cancelled : Arc<AtomicBool>,
+    killed : Option<Arc<AtomicBool>>,  // set by `kill` of the process list
+}
+
+impl QueryGuard {
+    pub fn new(timeout_ms : usize) -> QueryGuard {
+        QueryGuard{
+            start : Instant::now(),
+            timeout_ms : timeout_ms,
+            cancelled : Arc::new(AtomicBool::new(false)),
+            killed : None,
+        }
+    }
+    pub fn with_kill_flag(mut self, killed : Arc<AtomicBool>) -> QueryGuard {
+        self.killed = Some(killed);
+        self
+    }
+    pub fn unlimited() -> QueryGuard {
+        QueryGuard::new(0)
+    }
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+    pub fn get_timeout_ms(&self) -> usize {
+        self.timeout_ms
+    }
+    // the time left for the blocking waits of the query, None for no limit
+    pub fn get_remaining(&self) -> Option<Duration> {
+        if self.timeout_ms == 0 {
+            return None;
+        }
+        let timeout = Duration::from_millis(self.timeout_ms as u64);
+        Some(timeout.checked_sub(self.start.elapsed()).unwrap_or(Duration::from_millis(0)))
+    }
+    // the operators call it between the tuples and stop with the error message
+    pub fn check(&self) -> Result<(), String> {
+        if self.killed.as_ref().map_or(false, |killed| killed.load(Ordering::SeqCst)) {
+            return Err("query killed".to_string());
+        }
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err("query cancelled".to_string());
+        }
+        if self.timeout_ms > 0 && self.start.elapsed() > Duration::from_millis(self.timeout_ms as u64) {
+            return Err(format!("query cancelled: exceeded {} ms", self.timeout_ms));
+        }
+        Ok(())
+    }
+}
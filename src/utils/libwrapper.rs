@@ -1,13 +1,111 @@
-use libc::{_SC_PAGESIZE, sysconf, memalign, c_void};
-
-
-pub fn alloc_page() -> *mut c_void {
-    unsafe {
-        let size = sysconf(_SC_PAGESIZE) as usize;
-        memalign(size, size)        
-    }
-}
-
-pub fn get_page_size() -> usize {
-    unsafe { sysconf(_SC_PAGESIZE) as usize }
-}
+use std::io;
+use std::fs::File;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use libc::{_SC_PAGESIZE, sysconf, memalign, c_void};
+use libc::{mmap, munmap, msync, PROT_READ, PROT_WRITE, MAP_SHARED, MAP_FAILED, MS_SYNC};
+use libc::{c_int, signal, sighandler_t, SIGINT, SIGTERM};
+#[cfg(target_os = "linux")]
+use libc::off_t;
+
+
+pub fn alloc_page(size : usize) -> *mut c_void {
+    // aligned to the page of the os whatever the size is
+    unsafe {
+        memalign(sysconf(_SC_PAGESIZE) as usize, size)
+    }
+}
+
+pub fn get_page_size() -> usize {
+    unsafe { sysconf(_SC_PAGESIZE) as usize }
+}
+
+// the whole file from offset 0 with MAP_SHARED, len may be over the end of the file
+pub fn map_file(file : &File, len : usize) -> io::Result<*mut u8> {
+    let ptr = unsafe {
+        mmap(null_mut(), len, PROT_READ | PROT_WRITE, MAP_SHARED, file.as_raw_fd(), 0)
+    };
+    if ptr == MAP_FAILED {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ptr as *mut u8)
+    }
+}
+
+pub fn unmap_file(ptr : *mut u8, len : usize) {
+    unsafe {
+        munmap(ptr as *mut c_void, len);
+    }
+}
+
+pub fn sync_mapped(ptr : *mut u8, len : usize) -> io::Result<()> {
+    match unsafe{ msync(ptr as *mut c_void, len, MS_SYNC) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// positioned io, the offset of the file is not used on unix and moved on windows
+#[cfg(unix)]
+pub fn read_at(file : &File, buf : &mut [u8], offset : u64) -> io::Result<usize> {
+    file.read_at(buf, offset)
+}
+
+#[cfg(unix)]
+pub fn write_at(file : &File, data : &[u8], offset : u64) -> io::Result<usize> {
+    file.write_at(data, offset)
+}
+
+#[cfg(windows)]
+pub fn read_at(file : &File, buf : &mut [u8], offset : u64) -> io::Result<usize> {
+    file.seek_read(buf, offset)
+}
+
+#[cfg(windows)]
+pub fn write_at(file : &File, data : &[u8], offset : u64) -> io::Result<usize> {
+    file.seek_write(data, offset)
+}
+
+// not in this version of libc
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn posix_fadvise(fd : c_int, offset : off_t, len : off_t, advice : c_int) -> c_int;
+}
+
+#[cfg(target_os = "linux")]
+const POSIX_FADV_WILLNEED : c_int = 3;
+
+// only a hint for the kernel to read the range ahead, nothing is reported if it's ignored
+#[cfg(target_os = "linux")]
+pub fn fadvise_willneed(file : &File, offset : u64, len : usize) {
+    unsafe {
+        posix_fadvise(file.as_raw_fd(), offset as off_t, len as off_t, POSIX_FADV_WILLNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fadvise_willneed(_file : &File, _offset : u64, _len : usize) {}
+
+static STOP_SIGNALED : AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_stop_signal(_ : c_int) {
+    STOP_SIGNALED.store(true, Ordering::SeqCst);
+}
+
+// SIGINT and SIGTERM only set a flag, checked by stop_signaled
+pub fn catch_stop_signal() {
+    unsafe {
+        signal(SIGINT, handle_stop_signal as sighandler_t);
+        signal(SIGTERM, handle_stop_signal as sighandler_t);
+    }
+}
+
+pub fn stop_signaled() -> bool {
+    STOP_SIGNALED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,125 @@
+use std::io::{Write, stderr};
+use std::fs::{File, OpenOptions};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, AtomicPtr, Ordering};
+use std::cell::RefCell;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::config::Config;
+
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+    Trace = 4,
+}
+
+impl LogLevel {
+    pub fn from_name(name : &str) -> Option<LogLevel> {
+        match name {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            &LogLevel::Error => "error",
+            &LogLevel::Warn => "warn",
+            &LogLevel::Info => "info",
+            &LogLevel::Debug => "debug",
+            &LogLevel::Trace => "trace",
+        }
+    }
+}
+
+// shared by all the threads, info until init_log
+static LOG_LEVEL : AtomicUsize = AtomicUsize::new(LogLevel::Info as usize);
+// null for stderr, the file of an earlier init_log is never freed since a thread may still be writing it
+static LOG_FILE : AtomicPtr<Mutex<File>> = AtomicPtr::new(0 as *mut Mutex<File>);
+
+// the lines logged by the current thread inside capture_logs
+thread_local!(static CAPTURED : RefCell<Option<Vec<String>>> = RefCell::new(None));
+
+// log_level and log_file of the config, an empty log_file for stderr
+pub fn init_log(config : &Config) {
+    let level = LogLevel::from_name(&config.get_str("log_level")).unwrap();
+    let path = config.get_str("log_file");
+    let file = if path.is_empty() {
+        0 as *mut Mutex<File>
+    } else {
+        let file = check_ok!(OpenOptions::new().append(true).create(true).open(&path));
+        Box::into_raw(Box::new(Mutex::new(file)))
+    };
+    LOG_FILE.store(file, Ordering::SeqCst);
+    LOG_LEVEL.store(level as usize, Ordering::SeqCst);
+}
+
+// checked by the macros before the message is formatted
+pub fn log_enabled(level : LogLevel) -> bool {
+    level as usize <= LOG_LEVEL.load(Ordering::Relaxed) || CAPTURED.with(|c| c.borrow().is_some())
+}
+
+pub fn write_log(level : LogLevel, target : &str, msg : String) {
+    let line = format!("{} {}: {}", level.name(), target, msg);
+    let captured = CAPTURED.with(|c| match c.borrow_mut().as_mut() {
+        Some(lines) => {
+            lines.push(line.clone());
+            true
+        }
+        None => false,
+    });
+    if captured {
+        return;
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let line = format!("{}.{:03} {}\n", timestamp.as_secs(), timestamp.subsec_nanos() / 1000000, line);
+    let file = LOG_FILE.load(Ordering::SeqCst);
+    if file.is_null() {
+        stderr().write_all(line.as_bytes()).ok();
+    } else {
+        let file = unsafe{ &*file };
+        lock_unwrap!(file.lock()).write_all(line.as_bytes()).ok();
+    }
+}
+
+// All the levels logged by the current thread while running f are returned instead of written,
+// the other threads are not affected.
+pub fn capture_logs<F : FnOnce()>(f : F) -> Vec<String> {
+    CAPTURED.with(|c| *c.borrow_mut() = Some(Vec::new()));
+    f();
+    CAPTURED.with(|c| c.borrow_mut().take().unwrap())
+}
+
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => ({
+        if ::utils::log::log_enabled($level) {
+            ::utils::log::write_log($level, module_path!(), format!($($arg)*));
+        }
+    })
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => (log_at!(::utils::log::LogLevel::Error, $($arg)*))
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => (log_at!(::utils::log::LogLevel::Warn, $($arg)*))
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => (log_at!(::utils::log::LogLevel::Info, $($arg)*))
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => (log_at!(::utils::log::LogLevel::Debug, $($arg)*))
+}
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => (log_at!(::utils::log::LogLevel::Trace, $($arg)*))
+}
@@ -1,12 +1,30 @@
-#[macro_use]
-pub mod macro_utils;
-#[allow(dead_code)]
-pub mod libwrapper;
-#[allow(dead_code)]
-pub mod pointer;
-#[allow(dead_code)]
-pub mod config;
-#[allow(dead_code)]
-pub mod file;
-#[allow(dead_code)]
-pub mod array;
+#[macro_use]
+pub mod macro_utils;
+#[macro_use]
+#[allow(dead_code)]
+pub mod log;
+#[allow(dead_code)]
+pub mod libwrapper;
+#[allow(dead_code)]
+pub mod pointer;
+#[allow(dead_code)]
+pub mod config;
+#[allow(dead_code)]
+pub mod file;
+#[allow(dead_code)]
+pub mod array;
+#[allow(dead_code)]
+pub mod csv;
+#[allow(dead_code)]
+pub mod compress;
+#[allow(dead_code)]
+pub mod checksum;
+#[allow(dead_code)]
+pub mod guard;
+#[allow(dead_code)]
+pub mod sha256;
+#[allow(dead_code)]
+pub mod base64;
+#[allow(dead_code)]
+pub mod bench;
+pub mod float;
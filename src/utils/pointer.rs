@@ -1,47 +1,95 @@
-use std::ptr::{write, read, write_bytes};
-use std::vec::Vec;
-use std::ffi::CString;
-use ::store::buffer::DataPtr;
-
-
-pub fn char_to_u8(c : char) -> u8 {
-    if (c as u32) > 127 {
-        return 42;  // invalid ascii to '*'
-    }
-    c as u8
-}
-
-pub unsafe fn write_string(ptr : DataPtr, input : &String, len : usize) {
-    assert!(input.len() <= len);
-    write_bytes(ptr, 0, len);
-    for (i, c) in input.chars().enumerate() {
-        let byte = char_to_u8(c);
-        write::<u8>((ptr as *mut u8).offset(i as isize), byte);
-    }
-}
-
-pub unsafe fn read_string(ptr : DataPtr, len : usize) -> String {
-    let mut s = String::new();
-    for i in 0..len {
-        let n = read::<u8>((ptr as *const u8).offset(i as isize));
-        if n == 0 {
-            return s;
-        }
-        s.push(n as char);
-    }
-    s
-}
-
-pub fn pointer_offset(ptr : DataPtr, byte_offset : usize) -> DataPtr {
-    unsafe{
-        (ptr as *mut u8).offset(byte_offset as isize) as DataPtr
-    }
-}
-
-pub fn to_cstring(s : String) -> CString {
-    let mut buf = Vec::new();
-    for c in s.chars() {
-        buf.push(char_to_u8(c));
-    }
-    CString::new(buf).unwrap()
-}
+use std::ptr::{write_bytes, copy_nonoverlapping, read_unaligned, write_unaligned};
+use std::slice::from_raw_parts;
+use std::str::from_utf8;
+use std::ffi::CString;
+use ::store::buffer::DataPtr;
+
+
+// A char(len) value is stored as its utf-8 bytes followed by zero bytes up to len,
+// and read_string stops at the first NUL within len. So a string is stored and compared
+// as its part before the first NUL, which is canonical_str. The empty string is all zero
+// bytes, which is also how null is stored, they can't be told apart without a null bitmap.
+pub fn canonical_str(s : &str) -> &str {
+    match s.find('\0') {
+        Some(i) => &s[..i],
+        None => s,
+    }
+}
+
+pub unsafe fn write_string(ptr : DataPtr, input : &String, len : usize) {
+    // cut after the last char which fits in len
+    write_bytes(ptr, 0, len);
+    let input = canonical_str(input);
+    let bytes = input[..char_boundary_within(input, len)].as_bytes();
+    copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len());
+}
+
+pub fn char_boundary_within(s : &str, len : usize) -> usize {
+    // the length of the longest prefix of s not longer than len and not splitting a char
+    if len >= s.len() {
+        return s.len();
+    }
+    let mut end = len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    end
+}
+
+// Err with the lossy string if the bytes are not valid utf-8,
+// which write_string never leaves, so the page is damaged.
+pub unsafe fn read_string_checked(ptr : DataPtr, len : usize) -> Result<String, String> {
+    let bytes = from_raw_parts(ptr as *const u8, len);
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+    match from_utf8(&bytes[..end]) {
+        Ok(s) => Ok(s.to_string()),
+        Err(..) => Err(String::from_utf8_lossy(&bytes[..end]).into_owned()),
+    }
+}
+
+pub unsafe fn read_string(ptr : DataPtr, len : usize) -> String {
+    match read_string_checked(ptr, len) {
+        Ok(s) => s,
+        Err(s) => {
+            log_warn!("invalid utf-8 in a char({}) value, read as {:?}", len, s);
+            s
+        }
+    }
+}
+
+// The numbers in the table files are little-endian on any host,
+// from_le and to_le do nothing on a little-endian one.
+pub unsafe fn read_u32_le(ptr : DataPtr) -> u32 {
+    u32::from_le(read_unaligned(ptr as *const u32))
+}
+
+pub unsafe fn write_u32_le(ptr : DataPtr, n : u32) {
+    write_unaligned(ptr as *mut u32, n.to_le())
+}
+
+pub unsafe fn read_i32_le(ptr : DataPtr) -> i32 {
+    i32::from_le(read_unaligned(ptr as *const i32))
+}
+
+pub unsafe fn write_i32_le(ptr : DataPtr, n : i32) {
+    write_unaligned(ptr as *mut i32, n.to_le())
+}
+
+pub unsafe fn read_f32_le(ptr : DataPtr) -> f32 {
+    f32::from_bits(read_u32_le(ptr))
+}
+
+pub unsafe fn write_f32_le(ptr : DataPtr, n : f32) {
+    write_u32_le(ptr, n.to_bits())
+}
+
+pub fn pointer_offset(ptr : DataPtr, byte_offset : usize) -> DataPtr {
+    unsafe{
+        (ptr as *mut u8).offset(byte_offset as isize) as DataPtr
+    }
+}
+
+pub fn to_cstring(s : String) -> CString {
+    // utf-8 like the char values in the rows
+    CString::new(s.into_bytes()).unwrap()
+}